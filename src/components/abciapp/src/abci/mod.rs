@@ -10,16 +10,27 @@ pub mod staking;
 
 use {
     crate::api::{
-        query_server::query_api, submission_server::submission_api::SubmissionApi,
+        admin_server::AdminApi,
+        query_server::{query_api, BLOCK_CREATED},
+        submission_server::{access, submission_api::SubmissionApi},
     },
-    config::abci::{global_cfg::CFG, ABCIConfig},
+    config::abci::{global_cfg::CFG, ABCIConfig, RuntimeConfig},
     futures::executor::ThreadPool,
+    globutils::logging,
     lazy_static::lazy_static,
+    ledger::store::{archive, batch::SyncMode, flush_data, LedgerState},
+    nix::sys::signal::{self, SigHandler, Signal},
+    parking_lot::{Condvar, Mutex},
     ruc::*,
     std::{
-        env, fs,
+        env,
+        fs::{self, File},
         net::SocketAddr,
-        sync::{atomic::AtomicBool, Arc},
+        process,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
         thread,
     },
 };
@@ -32,6 +43,92 @@ lazy_static! {
     pub static ref POOL: ThreadPool = pnk!(ThreadPool::new());
     /// if is exiting, we should not do anything.
     pub static ref IS_EXITING: AtomicBool = AtomicBool::new(false);
+    // set by `request_shutdown`
+    static ref SHUTDOWN_REQUESTED: (Mutex<bool>, Condvar) =
+        (Mutex::new(false), Condvar::new());
+}
+
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Reload the `RuntimeConfig` in the file of `--runtime-config`, and put it in force,
+/// by SIGHUP or the `reload_config` route of the admin server.
+pub fn reload_config() -> Result<()> {
+    let cfg = RuntimeConfig::from_file(&CFG.runtime_config).c(d!())?;
+    access::validate(&cfg.submission_access).c(d!())?;
+    if let Some(f) = cfg.log_filter.as_deref() {
+        logging::set_log_filter(f).c(d!())?;
+    }
+    log::info!(target: "abciapp", "Runtime configuration loaded: {:?}", cfg);
+    cfg.apply();
+    Ok(())
+}
+
+extern "C" fn on_sighup(_: i32) {
+    RELOAD_REQUESTED.store(true, Ordering::Release);
+}
+
+// the handler only raises a flag, the reload is done in a thread of its own
+fn watch_sighup() -> Result<()> {
+    unsafe { signal::signal(Signal::SIGHUP, SigHandler::Handler(on_sighup)) }.c(d!())?;
+    thread::spawn(|| loop {
+        if RELOAD_REQUESTED.swap(false, Ordering::AcqRel) {
+            info_omit!(reload_config());
+        }
+        sleep_ms!(500);
+    });
+    Ok(())
+}
+
+/// Ask the node to shut down, by SIGTERM/SIGINT or the `shutdown` route
+/// of the admin server, see `shutdown`.
+pub fn request_shutdown() {
+    IS_EXITING.store(true, Ordering::Release);
+    *SHUTDOWN_REQUESTED.0.lock() = true;
+    SHUTDOWN_REQUESTED.1.notify_all();
+}
+
+/// Wait for `request_shutdown`, then shut down gracefully, the node can exit after it.
+///
+/// New submissions are refused since the request, the block in flight is applied,
+/// the query server catches up with it, then the data of the ledger is flushed
+/// and synced to disk.
+pub fn shutdown() -> Result<()> {
+    {
+        let mut requested = SHUTDOWN_REQUESTED.0.lock();
+        while !*requested {
+            SHUTDOWN_REQUESTED.1.wait(&mut requested);
+        }
+    }
+    log::info!(target: "abciapp", "Shutting down, waiting for the block in flight");
+    while IN_SAFE_ITV.load(Ordering::SeqCst) {
+        sleep_ms!(1);
+    }
+
+    if CFG.enable_query_service {
+        // it is notified of a block at the beginning of the next one
+        *BLOCK_CREATED.0.lock() = true;
+        BLOCK_CREATED.1.notify_one();
+        while *BLOCK_CREATED.0.lock() {
+            sleep_ms!(10);
+        }
+    }
+
+    flush_data();
+    sync_dir(&CFG.ledger_dir).c(d!())
+}
+
+// fsync the files in `dir` and `dir` itself,
+// the ones of the database are flushed by `flush_data`
+fn sync_dir(dir: &str) -> Result<()> {
+    for entry in fs::read_dir(dir).c(d!())? {
+        let entry = entry.c(d!())?;
+        if entry.file_type().c(d!())?.is_file() {
+            File::open(entry.path())
+                .and_then(|f| f.sync_all())
+                .c(d!())?;
+        }
+    }
+    File::open(dir).and_then(|f| f.sync_all()).c(d!())
 }
 
 /// Starting findorad
@@ -41,6 +138,13 @@ pub fn run() -> Result<()> {
         Some(CFG.ledger_dir.as_str())
     };
 
+    if CFG.replay_archive.is_some() || CFG.export_state.is_some() {
+        replay_and_export().c(d!())?;
+    }
+
+    reload_config().c(d!())?;
+    watch_sighup().c(d!())?;
+
     let config = ruc::info!(ABCIConfig::from_file())
         .or_else(|_| ABCIConfig::from_env().c(d!()))?;
 
@@ -57,14 +161,18 @@ pub fn run() -> Result<()> {
 
     let submission_service_hdr = Arc::clone(&app.la);
 
+    thread::spawn(|| pnk!(AdminApi::create(CFG.admin_port)));
+
     if CFG.enable_query_service {
         let query_service_hdr = submission_service_hdr.read().borrowable_ledger_state();
         pnk!(query_api::service::start_query_server(
             Arc::clone(&query_service_hdr),
+            &CFG.query_namespaces,
             &[
                 (&config.abci_host, config.query_port),
                 (&config.abci_host, config.ledger_port)
             ],
+            CFG.api.clone(),
         ))
         .write()
         .update();
@@ -76,6 +184,7 @@ pub fn run() -> Result<()> {
                 submission_service_hdr,
                 &submission_host,
                 submission_port,
+                CFG.api.clone(),
             ));
         });
     }
@@ -102,3 +211,35 @@ pub fn run() -> Result<()> {
 
     Ok(())
 }
+
+/// Replay a block archive into the empty ledger in `CFG.ledger_dir`,
+/// and/or export the state of the ledger, then exit,
+/// with an error code if a divergence is found by the replay.
+fn replay_and_export() -> Result<()> {
+    let mut ledger = LedgerState::load_or_init(&CFG.ledger_dir).c(d!())?;
+    if let Some(mode) = CFG.ledger_sync_mode.as_deref() {
+        ledger
+            .set_sync_mode(mode.parse::<SyncMode>().c(d!())?)
+            .c(d!())?;
+    }
+
+    let mut diverged = false;
+    if let Some(path) = CFG.replay_archive.as_deref() {
+        if 0 != ledger.blocks.len() {
+            return Err(eg!("the ledger to replay into must be empty"));
+        }
+        let report = archive::replay(&mut ledger, path, CFG.replay_until).c(d!())?;
+        println!("{}", serde_json::to_string_pretty(&report).c(d!())?);
+        diverged = report.divergence.is_some();
+        // the process exits without dropping the ledger
+        ledger.sync().c(d!())?;
+    }
+
+    if let Some(path) = CFG.export_state.as_deref() {
+        serde_json::to_vec(&ledger.export())
+            .c(d!())
+            .and_then(|e| fs::write(path, e).c(d!(path.to_owned())))?;
+    }
+
+    process::exit(if diverged { 1 } else { 0 });
+}
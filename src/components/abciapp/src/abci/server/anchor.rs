@@ -0,0 +1,132 @@
+//!
+//! # Checkpoint anchoring
+//!
+//! Publishes the state commitment of the ledger to an external chain
+//! every `CFG.anchor_itv` blocks, through the webhook in `CFG.anchor_webhook`,
+//! e.g. a signer service sending it to an Ethereum contract.
+//!
+//! The webhook receives `AnchorRequest` in JSON, and replies with a receipt in JSON,
+//! such as the hash of the anchoring transaction. Receipts are appended to
+//! `<ledger_dir>/anchor_receipts.jsonl`, an independent timestamping trail for auditors.
+//!
+//! Failures are logged and skipped, each state commitment also commits
+//! all the previous ones, so the next anchor covers the missing ones.
+//!
+
+use {
+    crate::abci::POOL,
+    config::abci::global_cfg::CFG,
+    lazy_static::lazy_static,
+    parking_lot::Mutex,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::{
+        fs::{self, OpenOptions},
+        io::{ErrorKind, Write},
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+const RECEIPTS_FILE: &str = "anchor_receipts.jsonl";
+
+lazy_static! {
+    // one anchor at a time, so receipts are appended in order
+    static ref ANCHOR_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Sent to the webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorRequest {
+    /// Tendermint height of the anchored block
+    pub height: u64,
+    /// State commitment of the ledger at `height`, in hex
+    pub state_commitment: String,
+}
+
+/// A published anchor, recorded locally
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchorReceipt {
+    #[allow(missing_docs)]
+    #[serde(flatten)]
+    pub request: AnchorRequest,
+    /// Returned by the webhook
+    pub receipt: Value,
+    /// Unix timestamp of the receipt, in seconds
+    pub timestamp: u64,
+}
+
+/// Called on each commit, does nothing if anchoring is disabled
+/// or `height` is not a multiple of the interval.
+pub fn anchor(height: u64, state_commitment: &[u8]) {
+    let webhook = match CFG.anchor_webhook.as_ref() {
+        Some(w) if 0 < CFG.anchor_itv && 0 == height % CFG.anchor_itv => w.clone(),
+        _ => return,
+    };
+    let req = AnchorRequest {
+        height,
+        state_commitment: hex::encode(state_commitment),
+    };
+
+    POOL.spawn_ok(async move {
+        let _lock = ANCHOR_LOCK.lock();
+        ruc::info_omit!(publish(&webhook, req).c(d!()));
+    });
+}
+
+fn publish(webhook: &str, req: AnchorRequest) -> Result<()> {
+    let receipt = attohttpc::post(webhook)
+        .json(&req)
+        .c(d!())?
+        .send()
+        .c(d!(webhook))?
+        .error_for_status()
+        .c(d!())?
+        .json::<Value>()
+        .c(d!())?;
+
+    let receipt = AnchorReceipt {
+        request: req,
+        receipt,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .c(d!())?
+            .as_secs(),
+    };
+    let line = serde_json::to_string(&receipt).c(d!())? + "\n";
+
+    let path = receipts_path();
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .c(d!(path.display()))?
+        .write_all(line.as_bytes())
+        .c(d!())
+}
+
+/// Recorded receipts of the anchors in the height range `[start, end]`
+pub fn get_receipts(start: u64, end: u64) -> Result<Vec<AnchorReceipt>> {
+    let content = match fs::read_to_string(receipts_path()) {
+        Ok(c) => c,
+        Err(e) if ErrorKind::NotFound == e.kind() => return Ok(vec![]),
+        Err(e) => return Err(eg!(e)),
+    };
+
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str::<AnchorReceipt>(l).c(d!()))
+        .filter(|r| {
+            r.as_ref()
+                .map(|r| start <= r.request.height && r.request.height <= end)
+                .unwrap_or(true)
+        })
+        .collect()
+}
+
+#[inline(always)]
+pub(super) fn receipts_path() -> PathBuf {
+    Path::new(&CFG.ledger_dir).join(RECEIPTS_FILE)
+}
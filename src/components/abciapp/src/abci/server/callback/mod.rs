@@ -6,10 +6,13 @@ mod utils;
 
 use {
     crate::{
-        abci::{server::ABCISubmissionServer, staking, IN_SAFE_ITV, IS_EXITING, POOL},
+        abci::{
+            server::{anchor, state_sync, ABCISubmissionServer},
+            staking, IN_SAFE_ITV, IS_EXITING, POOL,
+        },
         api::{
             query_server::BLOCK_CREATED,
-            submission_server::{convert_tx, try_tx_catalog, TxCatalog},
+            submission_server::{convert_tx, mempool, try_tx_catalog, TxCatalog},
         },
     },
     abci::{
@@ -18,14 +21,16 @@ use {
         ResponseBeginBlock, ResponseCheckTx, ResponseCommit, ResponseDeliverTx,
         ResponseEndBlock, ResponseInfo, ResponseInitChain, ResponseQuery,
     },
-    config::abci::global_cfg::CFG,
+    config::abci::{global_cfg::CFG, RuntimeConfig},
     fp_storage::hash::{Sha256, StorageHasher},
     lazy_static::lazy_static,
     ledger::{
         converter::is_convert_account,
+        data_model::{TxnCheckCode, TxnEffect},
         staking::KEEP_HIST,
         store::{
             api_cache,
+            archive::{self, ArchivedBlock},
             fbnc::{new_mapx, Mapx},
         },
     },
@@ -120,18 +125,41 @@ pub fn check_tx(s: &mut ABCISubmissionServer, req: &RequestCheckTx) -> ResponseC
 
     match tx_catalog {
         TxCatalog::FindoraTx => {
-            if matches!(req.field_type, CheckTxType::New) {
-                if let Ok(tx) = convert_tx(req.get_tx()) {
+            if let Ok(tx) = convert_tx(req.get_tx()) {
+                if tx.is_expired(td_height as u64 + 1) {
+                    // also on rechecks, to evict expired transactions from the mempool
+                    resp.log = "Transaction expired".to_owned();
+                    resp.code = TxnCheckCode::Expired.code();
+                } else if matches!(req.field_type, CheckTxType::New) {
                     if !tx.valid_in_abci() {
                         resp.log = "Should not appear in ABCI".to_owned();
                         resp.code = 1;
                     } else if TX_HISTORY.read().contains_key(&tx.hash_tm_rawbytes()) {
                         resp.log = "Historical transaction".to_owned();
                         resp.code = 1;
+                    } else if let Some(min_fee) = RuntimeConfig::current()
+                        .mempool_min_fee
+                        // the transactions exempted from fees pay nothing
+                        .filter(|min_fee| 0 < tx.fee() && tx.fee() < *min_fee)
+                    {
+                        resp.log = format!("The fee is lower than {}", min_fee);
+                        resp.code = 1;
+                    } else if let Err(e) = TxnEffect::check_stateless(tx.clone())
+                        .and_then(|_| mempool::claim(&tx, td_height as u64))
+                    {
+                        // stateless validation, e.g. signatures and the balance of amounts,
+                        // and double spends in the mempool,
+                        // checks against the ledger are left to `deliver_tx`
+                        resp.log = e.to_string();
+                        resp.code = e.code.code();
                     }
-                } else {
-                    resp.log = "Invalid format".to_owned();
+                } else if let Err(e) = mempool::claim(&tx, td_height as u64) {
+                    // the claims of the transactions left in the mempool are taken again
+                    resp.log = e.to_string();
+                    resp.code = e.code.code();
                 }
+            } else if matches!(req.field_type, CheckTxType::New) {
+                resp.log = "Invalid format".to_owned();
             }
             resp
         }
@@ -199,6 +227,13 @@ pub fn begin_block(
         .write()
         .get_staking_mut()
         .set_custom_block_height(header.height as u64);
+    la.get_committed_state().write().set_block_time(
+        header
+            .time
+            .as_ref()
+            .map(|t| t.seconds.max(0) as u64)
+            .unwrap_or_default(),
+    );
 
     // then create new block or update simulator
     if la.all_commited() {
@@ -233,6 +268,7 @@ pub fn deliver_tx(
                 POOL.spawn_ok(async move {
                     TX_HISTORY.write().set_value(txhash, Default::default());
                 });
+                mempool::delivered(&tx);
 
                 if tx.valid_in_abci() {
                     // Log print for monitor purpose
@@ -387,13 +423,51 @@ pub fn end_block(
 
     if !la.all_commited() && la.block_txn_count() != 0 {
         pnk!(la.end_block());
+
+        if let Some(path) = CFG.block_archive.as_deref() {
+            let ledger = la.get_committed_state().read();
+            // the staking state which the state commitment of the block is computed from,
+            // only cloned for the archive
+            if let (Some(block), Some(staking)) =
+                (ledger.blocks.last(), ledger.get_last_block_staking())
+            {
+                info_omit!(archive::append(
+                    path,
+                    &ArchivedBlock {
+                        height: td_height as u64,
+                        staking: staking.clone(),
+                        block,
+                    }
+                ));
+            }
+        }
+    }
+
+    la.after_block();
+
+    let mut events = vec![];
+
+    let jailed =
+        staking::jail_validators(la.get_committed_state().write().get_staking_mut());
+    if !jailed.is_empty() {
+        events.push(utils::gen_validator_jailed_event(&jailed));
     }
 
-    if let Ok(Some(vs)) = ruc::info!(staking::get_validators(
+    let vs = ruc::info!(staking::get_validators(
         la.get_committed_state().read().get_staking().deref(),
         begin_block_req.last_commit_info.as_ref()
-    )) {
+    ));
+    if let Ok(Some((vs, change))) = vs {
         resp.set_validator_updates(RepeatedField::from_vec(vs));
+        events.push(utils::gen_validator_set_event(&change));
+        la.get_committed_state()
+            .read()
+            .get_staking()
+            .record_validator_set_change(change);
+    }
+
+    if !events.is_empty() {
+        resp.set_events(RepeatedField::from_vec(events));
     }
 
     staking::system_ops(
@@ -422,6 +496,10 @@ pub fn commit(s: &mut ABCISubmissionServer, req: &RequestCommit) -> ResponseComm
 
     // cache last block for QueryServer
     pnk!(api_cache::update_api_cache(&mut state));
+    api_cache::update_fee_hist(&mut state, Some(mempool::depth() as u64));
+
+    // the spent outputs are claimed again by the rechecks of the mempool
+    mempool::reset();
 
     // snapshot them finally
     let path = format!("{}/{}", &CFG.ledger_dir, &state.get_status().snapshot_file);
@@ -431,6 +509,7 @@ pub fn commit(s: &mut ABCISubmissionServer, req: &RequestCommit) -> ResponseComm
 
     let mut r = ResponseCommit::new();
     let la_hash = state.get_state_commitment().0.as_ref().to_vec();
+    anchor::anchor(td_height as u64, &la_hash);
     let cs_hash = s.account_base_app.write().commit(req).data;
 
     if CFG.checkpoint.disable_evm_block_height < td_height
@@ -442,6 +521,12 @@ pub fn commit(s: &mut ABCISubmissionServer, req: &RequestCommit) -> ResponseComm
     }
 
     IN_SAFE_ITV.store(false, Ordering::Release);
+    drop(state);
+    drop(la);
+
+    // state-sync snapshots of this block, for peers
+    state_sync::snapshot(s, td_height as u64);
+
     r
 }
 
@@ -2,7 +2,15 @@
 
 use {
     abci::{Event, Pair},
-    ledger::data_model::{Operation, Transaction, TxnSID},
+    ledger::{
+        converter::{
+            designated_transfer::DesignatedTransferOperation,
+            payment_stream::PaymentStreamOperation, pull_payment::PullPaymentOperation,
+            session_key::SessionKeyOperation, spending_policy::SpendingPolicyOperation,
+        },
+        data_model::{Operation, Transaction, TxnSID},
+        staking::{TendermintAddr, ValidatorSetChange},
+    },
     protobuf::RepeatedField,
     serde::Serialize,
     std::time::SystemTime,
@@ -81,6 +89,42 @@ pub fn gen_tendermint_attr(tx: &Transaction) -> RepeatedField<Event> {
     RepeatedField::from_vec(res)
 }
 
+/// generate the event of a change of the validator set in `EndBlock`,
+/// e.g. subscribed by `tm.event = 'NewBlock' AND validator_set.height > 0`
+///   - "validator_set.height" => "<height>"
+///   - "validator_set.effective_height" => "<height>"
+///   - "validator_set.updates" => "Json<BTreeMap<addr, power>>"
+pub fn gen_validator_set_event(change: &ValidatorSetChange) -> Event {
+    let mut ev = Event::new();
+    ev.set_field_type("validator_set".to_owned());
+
+    let mut kv = vec![Pair::new(), Pair::new(), Pair::new()];
+    kv[0].set_key("height".as_bytes().to_vec());
+    kv[0].set_value(change.height.to_string().into_bytes());
+    kv[1].set_key("effective_height".as_bytes().to_vec());
+    kv[1].set_value(change.effective_height.to_string().into_bytes());
+    kv[2].set_key("updates".as_bytes().to_vec());
+    kv[2].set_value(serde_json::to_vec(&change.updates).unwrap());
+
+    ev.set_attributes(RepeatedField::from_vec(kv));
+    ev
+}
+
+/// generate the event of validators jailed in `EndBlock`
+/// for self-delegating less than the minimum
+///   - "validator_jailed.validators" => "Json<[addr]>"
+pub fn gen_validator_jailed_event(jailed: &[TendermintAddr]) -> Event {
+    let mut ev = Event::new();
+    ev.set_field_type("validator_jailed".to_owned());
+
+    let mut kv = Pair::new();
+    kv.set_key("validators".as_bytes().to_vec());
+    kv.set_value(serde_json::to_vec(jailed).unwrap());
+
+    ev.set_attributes(RepeatedField::from_vec(vec![kv]));
+    ev
+}
+
 // collect informations of inputs and outputs
 // # return: ([from ...], [to ...])
 fn gen_tendermint_attr_addr(tx: &Transaction) -> (Vec<TagAttr>, Vec<TagAttr>) {
@@ -104,7 +148,7 @@ fn gen_tendermint_attr_addr(tx: &Transaction) -> (Vec<TagAttr>, Vec<TagAttr>) {
                         base.$idx.push(attr);
                     });
                 };
-                // define\issue\AIR\memo
+                // define\issue\AIR\memo\revocation\tracer key\mirror\deposit
                 ($data: expr) => {
                     let mut attr = TagAttr::default();
                     attr.addr = globutils::wallet::public_key_to_bech32(&$data.pubkey);
@@ -126,6 +170,106 @@ fn gen_tendermint_attr_addr(tx: &Transaction) -> (Vec<TagAttr>, Vec<TagAttr>) {
                 Operation::UpdateMemo(d) => {
                     append_attr!(d);
                 }
+                Operation::UpdateRevocation(d) => {
+                    append_attr!(d);
+                }
+                Operation::UpdateTracerKey(d) => {
+                    append_attr!(d);
+                }
+                Operation::UpdateAssetMirror(d) => {
+                    append_attr!(d);
+                }
+                Operation::PrismDeposit(d) => {
+                    append_attr!(d);
+                }
+                Operation::PrismWithdraw(d) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr =
+                        globutils::wallet::public_key_to_bech32(&d.body.receiver);
+                    attr.asset_type = Some(hex::encode(&d.body.asset.val.0[..]));
+                    attr.asset_amount = Some(d.body.amount);
+                    base.1.push(attr);
+                }
+                Operation::PullPayment(PullPaymentOperation::Authorize(d)) => {
+                    append_attr!(d);
+                }
+                Operation::PullPayment(PullPaymentOperation::Pull(d)) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&d.pubkey);
+                    attr.asset_type = Some(hex::encode(&d.body.asset.val.0[..]));
+                    attr.asset_amount = Some(d.body.amount);
+                    base.1.push(attr);
+                }
+                Operation::PullPayment(PullPaymentOperation::Revoke(d)) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&d.pubkey);
+                    attr.asset_type = Some(hex::encode(&d.body.asset.val.0[..]));
+                    attr.asset_amount = Some(d.body.refund);
+                    base.1.push(attr);
+                }
+                Operation::PaymentStream(PaymentStreamOperation::Create(d)) => {
+                    append_attr!(d);
+                }
+                Operation::PaymentStream(PaymentStreamOperation::Claim(d)) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&d.pubkey);
+                    attr.asset_type = Some(hex::encode(&d.body.asset.val.0[..]));
+                    attr.asset_amount = Some(d.body.amount);
+                    base.1.push(attr);
+                }
+                Operation::PaymentStream(PaymentStreamOperation::Cancel(d)) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&d.pubkey);
+                    attr.asset_type = Some(hex::encode(&d.body.asset.val.0[..]));
+                    attr.asset_amount = Some(d.body.refund);
+                    base.1.push(attr);
+                }
+                Operation::DesignatedTransfer(DesignatedTransferOperation::Send(d)) => {
+                    append_attr!(d);
+                }
+                Operation::DesignatedTransfer(
+                    DesignatedTransferOperation::Claim(d)
+                    | DesignatedTransferOperation::Reclaim(d),
+                ) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&d.pubkey);
+                    attr.asset_type = Some(hex::encode(&d.body.asset.val.0[..]));
+                    attr.asset_amount = Some(d.body.amount);
+                    base.1.push(attr);
+                }
+                Operation::SpendingPolicy(SpendingPolicyOperation::Set(d)) => {
+                    append_attr!(d);
+                }
+                Operation::SpendingPolicy(SpendingPolicyOperation::Remove(d)) => {
+                    append_attr!(d);
+                }
+                Operation::SessionKey(SessionKeyOperation::Authorize(d)) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&d.pubkey);
+                    attr.asset_type = Some(hex::encode(&d.body.asset.val.0[..]));
+                    attr.asset_amount = Some(d.body.allowance);
+                    base.1.push(attr);
+                }
+                Operation::SessionKey(SessionKeyOperation::Revoke(d)) => {
+                    append_attr!(d);
+                }
+                Operation::Ibc(op) => {
+                    if let Some(pk) = op.get_signer() {
+                        let mut attr = TagAttr::default();
+                        attr.addr = globutils::wallet::public_key_to_bech32(pk);
+                        base.1.push(attr);
+                    }
+                }
+                Operation::Kv(op) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&op.pubkey);
+                    base.1.push(attr);
+                }
+                Operation::AnchorHash(op) => {
+                    let mut attr = TagAttr::default();
+                    attr.addr = globutils::wallet::public_key_to_bech32(&op.pubkey);
+                    base.1.push(attr);
+                }
                 _ => {}
             }
 
@@ -5,17 +5,20 @@
 use {
     crate::{
         abci::server::callback::TENDERMINT_BLOCK_HEIGHT,
-        api::submission_server::SubmissionServer,
+        api::submission_server::{ordering, SubmissionServer},
     },
     abci::{
-        RequestBeginBlock, RequestCheckTx, RequestCommit, RequestDeliverTx,
-        RequestEndBlock, RequestInfo, RequestInitChain, RequestQuery,
-        ResponseBeginBlock, ResponseCheckTx, ResponseCommit, ResponseDeliverTx,
-        ResponseEndBlock, ResponseInfo, ResponseInitChain, ResponseQuery,
+        RequestApplySnapshotChunk, RequestBeginBlock, RequestCheckTx, RequestCommit,
+        RequestDeliverTx, RequestEndBlock, RequestInfo, RequestInitChain,
+        RequestListSnapshots, RequestLoadSnapshotChunk, RequestOfferSnapshot,
+        RequestQuery, ResponseApplySnapshotChunk, ResponseBeginBlock, ResponseCheckTx,
+        ResponseCommit, ResponseDeliverTx, ResponseEndBlock, ResponseInfo,
+        ResponseInitChain, ResponseListSnapshots, ResponseLoadSnapshotChunk,
+        ResponseOfferSnapshot, ResponseQuery,
     },
     baseapp::BaseApp as AccountBaseAPP,
     config::abci::global_cfg::CFG,
-    ledger::store::LedgerState,
+    ledger::store::{batch::SyncMode, LedgerState},
     parking_lot::RwLock,
     rand_chacha::ChaChaRng,
     rand_core::SeedableRng,
@@ -29,7 +32,9 @@ use {
 
 pub use tx_sender::forward_txn_with_mode;
 
+pub mod anchor;
 pub mod callback;
+pub mod state_sync;
 pub mod tx_sender;
 
 /// findora impl of tendermint abci
@@ -45,10 +50,15 @@ impl ABCISubmissionServer {
         basedir: Option<&str>,
         tendermint_reply: String,
     ) -> Result<ABCISubmissionServer> {
-        let ledger_state = match basedir {
+        let mut ledger_state = match basedir {
             None => LedgerState::tmp_ledger(),
             Some(basedir) => pnk!(LedgerState::load_or_init(basedir)),
         };
+        if let Some(mode) = CFG.ledger_sync_mode.as_deref() {
+            ledger_state
+                .set_sync_mode(mode.parse::<SyncMode>().c(d!())?)
+                .c(d!())?;
+        }
         let tendermint_height = ledger_state.get_staking().cur_height();
         TENDERMINT_BLOCK_HEIGHT.swap(tendermint_height as i64, Ordering::Relaxed);
 
@@ -68,15 +78,23 @@ impl ABCISubmissionServer {
         };
 
         let prng = rand_chacha::ChaChaRng::from_entropy();
+        let mut la = SubmissionServer::new_no_auto_commit(
+            prng,
+            Arc::new(RwLock::new(ledger_state)),
+            TendermintForward { tendermint_reply },
+        )
+        .c(d!())?;
+        la.set_ordering_policy(
+            ordering::ordering_policy(
+                CFG.txn_ordering
+                    .as_deref()
+                    .unwrap_or(ordering::DEFAULT_ORDERING_POLICY),
+            )
+            .c(d!())?,
+        );
+
         Ok(ABCISubmissionServer {
-            la: Arc::new(RwLock::new(
-                SubmissionServer::new_no_auto_commit(
-                    prng,
-                    Arc::new(RwLock::new(ledger_state)),
-                    TendermintForward { tendermint_reply },
-                )
-                .c(d!())?,
-            )),
+            la: Arc::new(RwLock::new(la)),
             account_base_app: Arc::new(RwLock::new(account_base_app)),
         })
     }
@@ -122,4 +140,30 @@ impl abci::Application for ABCISubmissionServer {
     fn commit(&mut self, req: &RequestCommit) -> ResponseCommit {
         callback::commit(self, req)
     }
+
+    #[inline(always)]
+    fn list_snapshots(&mut self, _req: &RequestListSnapshots) -> ResponseListSnapshots {
+        state_sync::list_snapshots()
+    }
+
+    #[inline(always)]
+    fn offer_snapshot(&mut self, req: &RequestOfferSnapshot) -> ResponseOfferSnapshot {
+        state_sync::offer_snapshot(self, req)
+    }
+
+    #[inline(always)]
+    fn load_snapshot_chunk(
+        &mut self,
+        req: &RequestLoadSnapshotChunk,
+    ) -> ResponseLoadSnapshotChunk {
+        state_sync::load_snapshot_chunk(req)
+    }
+
+    #[inline(always)]
+    fn apply_snapshot_chunk(
+        &mut self,
+        req: &RequestApplySnapshotChunk,
+    ) -> ResponseApplySnapshotChunk {
+        state_sync::apply_snapshot_chunk(self, req)
+    }
 }
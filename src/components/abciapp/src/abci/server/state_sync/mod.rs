@@ -0,0 +1,519 @@
+//!
+//! # State sync
+//!
+//! ABCI snapshots of the data of a node, so that new nodes can join
+//! without replaying all the blocks:
+//!
+//! - every `CFG.state_sync_itv` blocks, after `commit`, a background thread takes
+//!   a checkpoint of the data of the block, the way of the checkpoints of RocksDB:
+//!   the stores flushed, the files under `CFG.ledger_dir`, the ledger and the EVM
+//!   stores, are hard-linked to `<ledger_dir>/state_sync/<height>.checkpoint`
+//!   if immutable once written, the sst and blob files, the others, the small
+//!   manifests and options, are copied; the stores are only locked meanwhile,
+//!   the checkpoint is given up if a later block is committed first
+//! - the checkpoint is packed into chunks under `<ledger_dir>/state_sync/<height>`
+//!   by the same thread, then removed, only the latest `KEEP_SNAPSHOTS` are kept;
+//!   no checkpoint is taken while the last one is being packed
+//! - a snapshot is only accepted by an empty node, each chunk is checked against
+//!   the hashes in the metadata of the snapshot, then the stores of the node are
+//!   closed, all of the chunks are unpacked into `CFG.ledger_dir`, and the ledger
+//!   and the EVM state are reloaded
+//!
+//! Tendermint verifies the restored app hash by `Info` at last.
+//!
+
+#[cfg(test)]
+mod test;
+
+use {
+    super::{anchor, callback::TENDERMINT_BLOCK_HEIGHT, ABCISubmissionServer},
+    abci::{
+        RequestApplySnapshotChunk, RequestLoadSnapshotChunk, RequestOfferSnapshot,
+        ResponseApplySnapshotChunk, ResponseApplySnapshotChunk_Result,
+        ResponseListSnapshots, ResponseLoadSnapshotChunk, ResponseOfferSnapshot,
+        ResponseOfferSnapshot_Result, Snapshot,
+    },
+    baseapp::BaseApp as AccountBaseAPP,
+    config::abci::global_cfg::CFG,
+    fp_storage::hash::{Sha256, StorageHasher},
+    lazy_static::lazy_static,
+    ledger::store::{fbnc, LedgerState},
+    parking_lot::Mutex,
+    protobuf::RepeatedField,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        fs::{self, File, OpenOptions},
+        io::{self, BufReader, Read, Write},
+        path::{Component, Path, PathBuf},
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+    },
+};
+
+/// Version of the layout of the chunks
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+const CHUNK_SIZE: usize = 16 * 1024 * 1024;
+const KEEP_SNAPSHOTS: usize = 2;
+const SNAPSHOT_DIR: &str = "state_sync";
+const META_FILE: &str = "meta.json";
+const RESTORE_FILE: &str = "restore.tmp";
+const CHECKPOINT_SUFFIX: &str = ".checkpoint";
+
+lazy_static! {
+    // the snapshot being restored, offered by tendermint
+    static ref RESTORING: Mutex<Option<SnapshotMeta>> = Mutex::new(None);
+}
+
+// a checkpoint is being packed in the background
+static PACKING: AtomicBool = AtomicBool::new(false);
+
+// the metadata of a snapshot, also sent to peers in `Snapshot.metadata`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotMeta {
+    height: u64,
+    chunk_hashes: Vec<[u8; 32]>,
+}
+
+impl SnapshotMeta {
+    // the hash of all chunk hashes
+    fn hash(&self) -> Vec<u8> {
+        Sha256::hash(&self.chunk_hashes.concat()).to_vec()
+    }
+
+    // the chunk at `idx` is the one of this snapshot
+    fn check_chunk(&self, idx: usize, chunk: &[u8]) -> bool {
+        self.chunk_hashes.get(idx).map(|h| &h[..]) == Some(&Sha256::hash(chunk)[..])
+    }
+
+    fn to_snapshot(&self) -> Result<Snapshot> {
+        let mut s = Snapshot::new();
+        s.set_height(self.height);
+        s.set_format(SNAPSHOT_FORMAT);
+        s.set_chunks(self.chunk_hashes.len() as u32);
+        s.set_hash(self.hash());
+        s.set_metadata(serde_json::to_vec(self).c(d!())?);
+        Ok(s)
+    }
+}
+
+/// Take a checkpoint of the block at `height` and pack it in the background,
+/// called after its `commit`; does nothing if state sync is disabled
+/// or `height` is not a multiple of the interval.
+pub fn snapshot(s: &ABCISubmissionServer, height: u64) {
+    if 0 == height || 0 == CFG.state_sync_itv || 0 != height % CFG.state_sync_itv {
+        return;
+    }
+    if PACKING.swap(true, Ordering::AcqRel) {
+        log::warn!(
+            target: "abciapp",
+            "state sync: the last snapshot is still being packed, skip {}",
+            height
+        );
+        return;
+    }
+
+    let s = s.clone();
+    thread::spawn(move || {
+        let root = snapshots_root();
+        if let Ok(Some(cp)) = ruc::info!(checkpoint(&s, height)) {
+            info_omit!(pack(&cp, &root.join(height.to_string()), height));
+            info_omit!(fs::remove_dir_all(&cp).c(d!()));
+        }
+
+        // keep the latest ones
+        let mut heights = snapshot_heights().unwrap_or_default();
+        heights.sort_unstable();
+        for h in heights.iter().rev().skip(KEEP_SNAPSHOTS) {
+            info_omit!(fs::remove_dir_all(root.join(h.to_string())).c(d!()));
+        }
+
+        PACKING.store(false, Ordering::Release);
+    });
+}
+
+// Link the files of the ledger and the EVM stores to `<height>.checkpoint`,
+// no block can be committed meanwhile, `None` if the one at `height` is not
+// the last committed block anymore.
+fn checkpoint(s: &ABCISubmissionServer, height: u64) -> Result<Option<PathBuf>> {
+    let la = s.la.read();
+    let ledger = la.get_committed_state().read();
+    let _evm = s.account_base_app.read();
+    if height != ledger.get_tendermint_height() {
+        log::warn!(
+            target: "abciapp",
+            "state sync: a later block is committed, skip {}",
+            height
+        );
+        return Ok(None);
+    }
+    fbnc::flush_data();
+
+    let root = snapshots_root();
+    fs::create_dir_all(&root).c(d!())?;
+    // the ones left by a crash
+    for e in fs::read_dir(&root).c(d!())?.filter_map(|e| e.ok()) {
+        if e.file_name().to_string_lossy().ends_with(CHECKPOINT_SUFFIX) {
+            fs::remove_dir_all(e.path()).c(d!())?;
+        }
+    }
+
+    let cp = root.join(format!("{}{}", height, CHECKPOINT_SUFFIX));
+    let skip = [root, anchor::receipts_path()];
+    link_files(Path::new(&CFG.ledger_dir), &skip, &cp).c(d!())?;
+
+    Ok(Some(cp))
+}
+
+// Hard-link the files under `src` immutable once written to `dst`,
+// the sst and blob files of RocksDB, copy the others.
+fn link_files(src: &Path, skip: &[PathBuf], dst: &Path) -> Result<()> {
+    for (rel, path) in list_files(src, skip).c(d!())? {
+        let to = dst.join(&rel);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).c(d!())?;
+        }
+        let immutable = path
+            .extension()
+            .map(|e| "sst" == e || "blob" == e)
+            .unwrap_or(false);
+        if immutable {
+            fs::hard_link(&path, &to).c(d!())?;
+        } else {
+            fs::copy(&path, &to).c(d!())?;
+        }
+    }
+    Ok(())
+}
+
+// Pack all files under `src` into chunks under `dir`, the metadata at last.
+fn pack(src: &Path, dir: &Path, height: u64) -> Result<SnapshotMeta> {
+    fs::create_dir_all(dir).c(d!())?;
+
+    let mut w = ChunkWriter {
+        dir: dir.to_owned(),
+        buf: Vec::with_capacity(CHUNK_SIZE),
+        hashes: vec![],
+    };
+    for (rel, path) in list_files(src, &[]).c(d!())? {
+        let rel = rel.as_bytes();
+        let len = fs::metadata(&path).c(d!())?.len();
+        w.write_all(&(rel.len() as u32).to_be_bytes()).c(d!())?;
+        w.write_all(rel).c(d!())?;
+        w.write_all(&len.to_be_bytes()).c(d!())?;
+        let copied =
+            io::copy(&mut File::open(&path).c(d!())?.take(len), &mut w).c(d!())?;
+        if copied != len {
+            return Err(eg!(format!("{}: truncated", path.display())));
+        }
+    }
+    w.flush_chunk().c(d!())?;
+
+    let meta = SnapshotMeta {
+        height,
+        chunk_hashes: w.hashes,
+    };
+    fs::write(dir.join(META_FILE), serde_json::to_vec(&meta).c(d!())?).c(d!())?;
+
+    Ok(meta)
+}
+
+/// Snapshots available to peers
+pub fn list_snapshots() -> ResponseListSnapshots {
+    let snapshots = snapshot_heights()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|h| ruc::info!(read_meta(h)).ok())
+        .filter_map(|m| ruc::info!(m.to_snapshot()).ok())
+        .collect();
+
+    let mut resp = ResponseListSnapshots::new();
+    resp.set_snapshots(RepeatedField::from_vec(snapshots));
+    resp
+}
+
+/// A chunk of a local snapshot, empty if it does not exist
+pub fn load_snapshot_chunk(req: &RequestLoadSnapshotChunk) -> ResponseLoadSnapshotChunk {
+    let mut resp = ResponseLoadSnapshotChunk::new();
+    if SNAPSHOT_FORMAT == req.get_format() {
+        let path = snapshots_root()
+            .join(req.get_height().to_string())
+            .join(chunk_name(req.get_chunk()));
+        if let Ok(chunk) = fs::read(path) {
+            resp.set_chunk(chunk);
+        }
+    }
+    resp
+}
+
+/// Accept a snapshot if this node has no data yet
+pub fn offer_snapshot(
+    s: &mut ABCISubmissionServer,
+    req: &RequestOfferSnapshot,
+) -> ResponseOfferSnapshot {
+    let mut resp = ResponseOfferSnapshot::new();
+    let snapshot = req.get_snapshot();
+
+    let result = if SNAPSHOT_FORMAT != snapshot.get_format() {
+        ResponseOfferSnapshot_Result::REJECT_FORMAT
+    } else if 0 < s
+        .la
+        .read()
+        .get_committed_state()
+        .read()
+        .get_tendermint_height()
+    {
+        ResponseOfferSnapshot_Result::ABORT
+    } else {
+        match serde_json::from_slice::<SnapshotMeta>(snapshot.get_metadata()) {
+            Ok(meta)
+                if meta.height == snapshot.get_height()
+                    && meta.chunk_hashes.len() == snapshot.get_chunks() as usize
+                    && meta.hash() == snapshot.get_hash() =>
+            {
+                match fs::create_dir_all(snapshots_root())
+                    .and_then(|_| File::create(restore_path()))
+                {
+                    Ok(_) => {
+                        *RESTORING.lock() = Some(meta);
+                        ResponseOfferSnapshot_Result::ACCEPT
+                    }
+                    Err(e) => {
+                        log::error!(target: "abciapp", "state sync: {}", e);
+                        ResponseOfferSnapshot_Result::ABORT
+                    }
+                }
+            }
+            _ => ResponseOfferSnapshot_Result::REJECT,
+        }
+    };
+
+    resp.set_result(result);
+    resp
+}
+
+/// Chunks are applied in order, the data is reloaded after the last one
+pub fn apply_snapshot_chunk(
+    s: &mut ABCISubmissionServer,
+    req: &RequestApplySnapshotChunk,
+) -> ResponseApplySnapshotChunk {
+    let mut resp = ResponseApplySnapshotChunk::new();
+
+    let mut restoring = RESTORING.lock();
+    let meta = if let Some(meta) = restoring.as_ref() {
+        meta
+    } else {
+        resp.set_result(ResponseApplySnapshotChunk_Result::ABORT);
+        return resp;
+    };
+
+    let idx = req.get_index() as usize;
+    let is_last = idx + 1 == meta.chunk_hashes.len();
+    if !meta.check_chunk(idx, req.get_chunk()) {
+        resp.set_result(ResponseApplySnapshotChunk_Result::RETRY);
+        resp.set_refetch_chunks(vec![req.get_index()]);
+        resp.set_reject_senders(RepeatedField::from_vec(vec![req
+            .get_sender()
+            .to_owned()]));
+        return resp;
+    }
+
+    let res = OpenOptions::new()
+        .append(true)
+        .open(restore_path())
+        .c(d!())
+        .and_then(|mut f| f.write_all(req.get_chunk()).c(d!()))
+        .and_then(|_| alt!(is_last, restore(s).c(d!()), Ok(())));
+
+    match res {
+        Ok(_) => {
+            if is_last {
+                *restoring = None;
+            }
+            resp.set_result(ResponseApplySnapshotChunk_Result::ACCEPT);
+        }
+        Err(e) => {
+            log::error!(target: "abciapp", "state sync: {}", e);
+            *restoring = None;
+            resp.set_result(ResponseApplySnapshotChunk_Result::ABORT);
+        }
+    }
+
+    resp
+}
+
+// Close the stores, unpack all chunks, then reload the ledger and the EVM state.
+fn restore(s: &mut ABCISubmissionServer) -> Result<()> {
+    let root = Path::new(&CFG.ledger_dir);
+
+    // nothing is kept open under the ledger dir while its files are replaced,
+    // the stores of this empty node are swapped out for ones in a temporary dir
+    let tmp = tempfile::tempdir().c(d!())?.into_path();
+    *s.account_base_app.write() =
+        AccountBaseAPP::new(&tmp, CFG.disable_eth_empty_blocks).c(d!())?;
+    *s.la.read().get_committed_state().write() =
+        LedgerState::new(&tmp.to_string_lossy(), Some("restoring")).c(d!())?;
+
+    unpack(&restore_path(), root).c(d!())?;
+    fs::remove_file(restore_path()).c(d!())?;
+
+    let ledger = LedgerState::load_or_init(&CFG.ledger_dir).c(d!())?;
+    TENDERMINT_BLOCK_HEIGHT
+        .swap(ledger.get_staking().cur_height() as i64, Ordering::Relaxed);
+    *s.la.read().get_committed_state().write() = ledger;
+    *s.account_base_app.write() =
+        AccountBaseAPP::new(root, CFG.disable_eth_empty_blocks).c(d!())?;
+    info_omit!(fs::remove_dir_all(&tmp).c(d!()));
+
+    Ok(())
+}
+
+// Unpack the files of the chunks concatenated in `archive` into `root`.
+fn unpack(archive: &Path, root: &Path) -> Result<()> {
+    let mut r = BufReader::new(File::open(archive).c(d!())?);
+
+    loop {
+        let mut len = [0u8; 4];
+        match r.read_exact(&mut len) {
+            Ok(_) => {}
+            Err(e) if io::ErrorKind::UnexpectedEof == e.kind() => break,
+            Err(e) => return Err(eg!(e)),
+        }
+        let mut rel = vec![0u8; u32::from_be_bytes(len) as usize];
+        r.read_exact(&mut rel).c(d!())?;
+        let rel = String::from_utf8(rel).c(d!())?;
+        // never write outside of the root
+        if Path::new(&rel)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(eg!(format!("invalid path: {}", rel)));
+        }
+
+        let mut len = [0u8; 8];
+        r.read_exact(&mut len).c(d!())?;
+        let len = u64::from_be_bytes(len);
+
+        let path = root.join(&rel);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).c(d!())?;
+        }
+        let copied =
+            io::copy(&mut (&mut r).take(len), &mut File::create(&path).c(d!())?)
+                .c(d!())?;
+        if copied != len {
+            return Err(eg!(format!("{}: truncated", rel)));
+        }
+    }
+
+    Ok(())
+}
+
+// splits the archive into chunks of `CHUNK_SIZE`
+struct ChunkWriter {
+    dir: PathBuf,
+    buf: Vec<u8>,
+    hashes: Vec<[u8; 32]>,
+}
+
+impl ChunkWriter {
+    fn flush_chunk(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        fs::write(
+            self.dir.join(chunk_name(self.hashes.len() as u32)),
+            &self.buf,
+        )?;
+        self.hashes.push(Sha256::hash(&self.buf));
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = data.len().min(CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if CHUNK_SIZE == self.buf.len() {
+            self.flush_chunk()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// (relative path, path) of all files under `root`, except the paths in `skip`,
+// the same data is always listed in the same order
+fn list_files(root: &Path, skip: &[PathBuf]) -> Result<Vec<(String, PathBuf)>> {
+    let mut res = vec![];
+    collect_files(root, root, skip, &mut res).c(d!())?;
+    Ok(res)
+}
+
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    skip: &[PathBuf],
+    res: &mut Vec<(String, PathBuf)>,
+) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .c(d!())?
+        .map(|e| e.map(|e| e.path()).c(d!()))
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if skip.contains(&path) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(root, &path, skip, res).c(d!())?;
+        } else if path.is_file() {
+            let rel = path.strip_prefix(root).c(d!())?;
+            let rel = rel.to_str().c(d!("non-UTF8 path"))?.to_owned();
+            res.push((rel, path));
+        }
+    }
+
+    Ok(())
+}
+
+fn snapshot_heights() -> Result<Vec<u64>> {
+    let root = snapshots_root();
+    if !root.exists() {
+        return Ok(vec![]);
+    }
+    Ok(fs::read_dir(root)
+        .c(d!())?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().join(META_FILE).exists())
+        .filter_map(|e| e.file_name().to_str().and_then(|n| n.parse::<u64>().ok()))
+        .collect())
+}
+
+fn read_meta(height: u64) -> Result<SnapshotMeta> {
+    let path = snapshots_root().join(height.to_string()).join(META_FILE);
+    fs::read(&path)
+        .c(d!())
+        .and_then(|m| serde_json::from_slice(&m).c(d!()))
+}
+
+#[inline(always)]
+fn chunk_name(idx: u32) -> String {
+    format!("chunk-{}", idx)
+}
+
+#[inline(always)]
+fn snapshots_root() -> PathBuf {
+    Path::new(&CFG.ledger_dir).join(SNAPSHOT_DIR)
+}
+
+#[inline(always)]
+fn restore_path() -> PathBuf {
+    snapshots_root().join(RESTORE_FILE)
+}
@@ -0,0 +1,131 @@
+#![allow(missing_docs)]
+
+use {
+    super::*,
+    rand::{random, RngCore},
+};
+
+// the chunks of a snapshot concatenated, as by `apply_snapshot_chunk`
+fn concat_chunks(dir: &Path, meta: &SnapshotMeta, archive: &Path) -> Result<()> {
+    let mut f = File::create(archive).c(d!())?;
+    for i in 0..meta.chunk_hashes.len() {
+        let chunk = fs::read(dir.join(chunk_name(i as u32))).c(d!())?;
+        assert!(meta.check_chunk(i, &chunk));
+        f.write_all(&chunk).c(d!())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn state_sync_pack_unpack() {
+    let src = pnk!(tempfile::tempdir());
+    let dst = pnk!(tempfile::tempdir());
+    let chunks = pnk!(tempfile::tempdir());
+
+    // more than one chunk
+    let mut big = vec![0u8; CHUNK_SIZE + 1024];
+    rand::thread_rng().fill_bytes(&mut big);
+    let files = vec![
+        ("ledger_status".to_owned(), b"{}".to_vec()),
+        ("__bnc__/a/b/data".to_owned(), big),
+        (
+            "chain_state/000001.sst".to_owned(),
+            random::<[u8; 32]>().to_vec(),
+        ),
+        ("empty".to_owned(), vec![]),
+    ];
+    for (rel, data) in files.iter() {
+        let path = src.path().join(rel);
+        pnk!(fs::create_dir_all(path.parent().unwrap()));
+        pnk!(fs::write(path, data));
+    }
+    // skipped by the checkpoints
+    let skip = src.path().join(SNAPSHOT_DIR);
+    pnk!(fs::create_dir_all(&skip));
+    pnk!(fs::write(skip.join("x"), b"x"));
+    assert_eq!(
+        files.len(),
+        pnk!(list_files(src.path(), &[skip.clone()])).len()
+    );
+    pnk!(fs::remove_dir_all(&skip));
+
+    let meta = pnk!(pack(src.path(), chunks.path(), 100));
+    assert_eq!(100, meta.height);
+    assert_eq!(2, meta.chunk_hashes.len());
+    assert!(chunks.path().join(META_FILE).exists());
+    // the same data is always packed in the same way
+    let again = pnk!(tempfile::tempdir());
+    assert_eq!(
+        meta.chunk_hashes,
+        pnk!(pack(src.path(), again.path(), 100)).chunk_hashes
+    );
+
+    let archive = chunks.path().join(RESTORE_FILE);
+    pnk!(concat_chunks(chunks.path(), &meta, &archive));
+    pnk!(unpack(&archive, dst.path()));
+    for (rel, data) in files.iter() {
+        assert_eq!(data, &pnk!(fs::read(dst.path().join(rel))));
+    }
+}
+
+#[test]
+fn state_sync_chunk_hash_mismatch() {
+    let src = pnk!(tempfile::tempdir());
+    let chunks = pnk!(tempfile::tempdir());
+    pnk!(fs::write(src.path().join("data"), random::<[u8; 32]>()));
+
+    let meta = pnk!(pack(src.path(), chunks.path(), 1));
+    let mut chunk = pnk!(fs::read(chunks.path().join(chunk_name(0))));
+    assert!(meta.check_chunk(0, &chunk));
+    assert!(!meta.check_chunk(1, &chunk));
+
+    chunk[0] ^= 1;
+    assert!(!meta.check_chunk(0, &chunk));
+}
+
+#[test]
+fn state_sync_path_traversal() {
+    let dir = pnk!(tempfile::tempdir());
+    let root = dir.path().join("root");
+    pnk!(fs::create_dir_all(&root));
+
+    for rel in ["../escaped", "/tmp/escaped", "a/../../escaped", "./x"].iter() {
+        let archive = dir.path().join("archive");
+        let mut data = vec![];
+        data.extend_from_slice(&(rel.len() as u32).to_be_bytes());
+        data.extend_from_slice(rel.as_bytes());
+        data.extend_from_slice(&1u64.to_be_bytes());
+        data.push(b'x');
+        pnk!(fs::write(&archive, data));
+
+        assert!(unpack(&archive, &root).is_err());
+    }
+    assert!(!dir.path().join("escaped").exists());
+    assert!(pnk!(fs::read_dir(&root)).next().is_none());
+}
+
+#[test]
+fn state_sync_checkpoint_links() {
+    use std::os::unix::fs::MetadataExt;
+
+    let src = pnk!(tempfile::tempdir());
+    let dst = pnk!(tempfile::tempdir());
+    let cp = dst.path().join(format!("1{}", CHECKPOINT_SUFFIX));
+    for rel in ["db/000001.sst", "db/000002.blob", "db/MANIFEST-000003"].iter() {
+        let path = src.path().join(rel);
+        pnk!(fs::create_dir_all(path.parent().unwrap()));
+        pnk!(fs::write(path, rel.as_bytes()));
+    }
+    pnk!(link_files(src.path(), &[], &cp));
+
+    let ino = |root: &Path, rel: &str| pnk!(fs::metadata(root.join(rel))).ino();
+    for rel in ["db/000001.sst", "db/000002.blob"].iter() {
+        assert_eq!(ino(src.path(), rel), ino(&cp, rel));
+    }
+
+    // the mutable ones are copied, later writes do not reach the checkpoint
+    let manifest = "db/MANIFEST-000003";
+    assert_ne!(ino(src.path(), manifest), ino(&cp, manifest));
+    pnk!(fs::write(src.path().join(manifest), b"changed"));
+    assert_eq!(manifest.as_bytes(), &pnk!(fs::read(cp.join(manifest)))[..]);
+}
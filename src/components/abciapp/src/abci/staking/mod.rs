@@ -22,7 +22,8 @@ use {
                 governance::{governance_penalty_tendermint_auto, ByzantineKind},
                 mint_fra::{MintEntry, MintFraOps, MintKind},
             },
-            td_addr_to_string, Staking, VALIDATOR_UPDATE_BLOCK_ITV,
+            td_addr_to_string, Staking, TendermintAddr, ValidatorSetChange,
+            VALIDATOR_UPDATE_BLOCK_ITV,
         },
         store::LedgerState,
     },
@@ -46,7 +47,8 @@ lazy_static! {
     pub static ref TD_NODE_SELF_ADDR: Vec<u8> = pnk!(whoami::get_self_addr());
 }
 
-/// Get the effective validators at current block height.
+/// Get the effective validators at current block height,
+/// along with the change of the validator set they make.
 ///
 /// > #### Tendermint Rules
 /// >
@@ -70,13 +72,14 @@ lazy_static! {
 pub fn get_validators(
     staking: &Staking,
     last_commit_info: Option<&LastCommitInfo>,
-) -> Result<Option<Vec<ValidatorUpdate>>> {
+) -> Result<Option<(Vec<ValidatorUpdate>, ValidatorSetChange)>> {
+    let td_height = TENDERMINT_BLOCK_HEIGHT.load(Ordering::Relaxed);
+
     // Update the validator list every 4 blocks to ensure that
     // the validator list obtained from `LastCommitInfo` is exactly
     // the same as the current block.
     // So we can use it to filter out non-existing entries.
-    if 0 != TENDERMINT_BLOCK_HEIGHT.load(Ordering::Relaxed) % VALIDATOR_UPDATE_BLOCK_ITV
-    {
+    if 0 != td_height % VALIDATOR_UPDATE_BLOCK_ITV {
         return Ok(None);
     }
 
@@ -100,22 +103,24 @@ pub fn get_validators(
         .c(d!())?
         .body
         .values()
-        .filter(|v| {
+        // jailed validators are removed from tendermint
+        .map(|v| (v, alt!(v.jailed, 0, v.td_power)))
+        .filter(|(v, td_power)| {
             if let Some(power) = cur_entries.get(&v.td_addr) {
                 // - new power > 0: change existing entries
                 // - new power = 0: remove existing entries
                 // - the power returned by `LastCommitInfo` is impossible
                 // to be zero in the context of tendermint
-                *power as u64 != v.td_power
+                *power as u64 != *td_power
             } else {
                 // add new validator
                 //
                 // try to remove non-existing entries is not allowed
-                0 < v.td_power
+                0 < *td_power
             }
         })
         // this conversion is safe in the context of tendermint
-        .map(|v| (&v.td_pubkey, v.td_power as i64))
+        .map(|(v, td_power)| (&v.td_addr, &v.td_pubkey, td_power as i64))
         .collect::<Vec<_>>();
 
     if vs.is_empty() {
@@ -123,7 +128,7 @@ pub fn get_validators(
     }
 
     // reverse sort
-    vs.sort_by(|a, b| b.1.cmp(&a.1));
+    vs.sort_by(|a, b| b.2.cmp(&a.2));
 
     let validator_limit =
         if CFG.checkpoint.validators_limit_v2_height > staking.cur_height() {
@@ -134,24 +139,65 @@ pub fn get_validators(
 
     // set the power of every extra validators to zero,
     // then tendermint can remove them from consensus logic.
-    vs.iter_mut().skip(validator_limit).for_each(|(k, power)| {
-        alt!(cur_entries.contains_key(k), *power = 0, *power = -1);
-    });
+    vs.iter_mut()
+        .skip(validator_limit)
+        .for_each(|(_, k, power)| {
+            alt!(cur_entries.contains_key(k), *power = 0, *power = -1);
+        });
+    vs.retain(|(_, _, power)| -1 < *power);
 
-    Ok(Some(
-        vs.iter()
-            .filter(|(_, power)| -1 < *power)
-            .map(|(pubkey, power)| {
-                let mut vu = ValidatorUpdate::new();
-                let mut pk = PubKey::new();
-                pk.set_field_type("ed25519".to_owned());
-                pk.set_data(pubkey.to_vec());
-                vu.set_power(*power);
-                vu.set_pub_key(pk);
-                vu
-            })
-            .collect(),
-    ))
+    let updates = vs
+        .iter()
+        .map(|(addr, _, power)| (td_addr_to_string(addr), *power as u64))
+        .collect::<BTreeMap<_, _>>();
+    let mut validators = cur_entries
+        .iter()
+        .map(|(addr, power)| (td_addr_to_string(addr), *power as u64))
+        .collect::<BTreeMap<_, _>>();
+    for (addr, power) in updates.iter() {
+        if 0 == *power {
+            validators.remove(addr);
+        } else {
+            validators.insert(addr.clone(), *power);
+        }
+    }
+    let change = ValidatorSetChange {
+        height: td_height as u64,
+        effective_height: 2 + td_height as u64,
+        updates,
+        validators,
+    };
+
+    let vs = vs
+        .iter()
+        .map(|(_, pubkey, power)| {
+            let mut vu = ValidatorUpdate::new();
+            let mut pk = PubKey::new();
+            pk.set_field_type("ed25519".to_owned());
+            pk.set_data(pubkey.to_vec());
+            vu.set_power(*power);
+            vu.set_pub_key(pk);
+            vu
+        })
+        .collect();
+
+    Ok(Some((vs, change)))
+}
+
+/// Jail the validators self-delegating less than the minimum,
+/// at the heights where the validator set is updated, see `get_validators`.
+pub fn jail_validators(staking: &mut Staking) -> Vec<TendermintAddr> {
+    if 0 != TENDERMINT_BLOCK_HEIGHT.load(Ordering::Relaxed) % VALIDATOR_UPDATE_BLOCK_ITV
+    {
+        return vec![];
+    }
+
+    staking
+        .validator_jail_under_min_self_delegation()
+        .iter()
+        .filter_map(|id| staking.validator_get_current_one_by_id(id))
+        .map(|v| td_addr_to_string(&v.td_addr))
+        .collect()
 }
 
 /// Call this function in `EndBlock`,
@@ -173,6 +219,7 @@ pub fn system_ops(
         &header.proposer_address,
         last_commit_info.map(get_last_vote_percent)
     ));
+    ruc::info_omit!(la.get_staking_mut().epoch_try_close());
 
     // tendermint primary governances
     evs.iter()
@@ -1,19 +1,26 @@
 #![allow(missing_docs)]
 
 use {
+    super::get_validators,
+    crate::abci::server::callback::TENDERMINT_BLOCK_HEIGHT,
+    abci::{LastCommitInfo, VoteInfo},
     finutils::txn_builder::{TransactionBuilder, TransferOperationBuilder},
     ledger::{
         data_model::{
             Transaction, TransferType, TxnEffect, TxoRef, ASSET_TYPE_FRA,
             BLACK_HOLE_PUBKEY, TX_FEE_MIN,
         },
-        staking::{FF_PK_LIST, FRA_PRE_ISSUE_AMOUNT},
+        staking::{
+            td_pubkey_to_td_addr, Validator, ValidatorData, ValidatorKind, FF_PK_LIST,
+            FRA_PRE_ISSUE_AMOUNT, VALIDATOR_UPDATE_BLOCK_ITV,
+        },
         store::{utils::fra_gen_initial_tx, LedgerState},
     },
     rand::random,
     rand_chacha::ChaChaRng,
     rand_core::SeedableRng,
     ruc::*,
+    std::sync::atomic::Ordering,
     zei::xfr::{
         asset_record::{open_blind_asset_record, AssetRecordType},
         sig::{XfrKeyPair, XfrPublicKey},
@@ -161,3 +168,72 @@ fn gen_transfer_tx(
     tx_builder.add_operation(op);
     Ok(tx_builder.take_transaction())
 }
+
+#[test]
+fn staking_validator_set_change() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut ledger = LedgerState::tmp_ledger();
+    let staking = ledger.get_staking_mut();
+
+    // powers of 10, 20 and 30, the first one is jailed
+    let mut vs = pnk!((0..3)
+        .map(|i| {
+            Validator::new(
+                vec![i as u8 + 1; 32],
+                10 * (i + 1),
+                XfrKeyPair::generate(&mut prng).get_pk(),
+                [1, 10],
+                Default::default(),
+                ValidatorKind::Initiator,
+            )
+        })
+        .collect::<Result<Vec<_>>>());
+    vs[0].jailed = true;
+    let addrs = vs.iter().map(|v| v.td_addr.clone()).collect::<Vec<_>>();
+    staking.validator_set_at_height_force(1, pnk!(ValidatorData::new(1, vs)));
+    staking.set_custom_block_height(1);
+    let addr = |i: u8| td_pubkey_to_td_addr(&[i + 1; 32]);
+    // the powers of the validators in the last block of tendermint
+    let last_commit = |powers: &[(usize, i64)]| {
+        let mut lci = LastCommitInfo::new();
+        for (i, power) in powers.iter() {
+            let mut v = abci::Validator::new();
+            v.set_address(addrs[*i].clone());
+            v.set_power(*power);
+            let mut vote = VoteInfo::new();
+            vote.set_validator(v);
+            lci.mut_votes().push(vote);
+        }
+        lci
+    };
+
+    // the set is only updated every `VALIDATOR_UPDATE_BLOCK_ITV` blocks
+    let height = 10 * VALIDATOR_UPDATE_BLOCK_ITV;
+    TENDERMINT_BLOCK_HEIGHT.store(height + 1, Ordering::Relaxed);
+    assert!(pnk!(get_validators(staking, None)).is_none());
+
+    // nothing in tendermint yet, the jailed one is not added
+    TENDERMINT_BLOCK_HEIGHT.store(height, Ordering::Relaxed);
+    let (updates, change) = pnk!(pnk!(get_validators(staking, None)));
+    assert_eq!(2, updates.len());
+    assert_eq!(height as u64, change.height);
+    assert_eq!(2 + height as u64, change.effective_height);
+    let expected = map! {B addr(1) => 20, addr(2) => 30 };
+    assert_eq!(expected, change.updates);
+    assert_eq!(expected, change.validators);
+
+    // all of them in tendermint, the second one with another power
+    let lci = last_commit(&[(0, 10), (1, 25), (2, 30)]);
+    let (updates, change) = pnk!(pnk!(get_validators(staking, Some(&lci))));
+    assert_eq!(
+        vec![20, 0],
+        updates.iter().map(|u| u.get_power()).collect::<Vec<_>>()
+    );
+    // the jailed one is removed
+    assert_eq!(map! {B addr(0) => 0, addr(1) => 20 }, change.updates);
+    assert_eq!(map! {B addr(1) => 20, addr(2) => 30 }, change.validators);
+
+    // in line with tendermint
+    let lci = last_commit(&[(1, 20), (2, 30)]);
+    assert!(pnk!(get_validators(staking, Some(&lci))).is_none());
+}
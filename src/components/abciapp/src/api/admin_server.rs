@@ -0,0 +1,77 @@
+//!
+//! # Administration of the node
+//!
+//! The routes acting on the node itself, eg. stopping it, on a listener of their
+//! own bound to `ADMIN_HOST`, apart from the public servers: the requests passed
+//! on by a proxy in front of these come from the host too, they can not be told
+//! apart from the ones of the operators.
+//!
+
+use {
+    crate::abci,
+    actix_web::{error, middleware, web, App, HttpServer},
+    finutils::api::NetworkRoute,
+    log::info,
+    ruc::*,
+};
+
+/// The admin server only listens on the loopback interface
+pub const ADMIN_HOST: &str = "127.0.0.1";
+
+/// Shut the node down gracefully, see `abci::shutdown`
+#[allow(clippy::unnecessary_wraps)]
+async fn shutdown() -> actix_web::Result<String> {
+    abci::request_shutdown();
+    Ok("shutting down".into())
+}
+
+/// Reload the runtime configuration, see `abci::reload_config`
+async fn reload_config() -> actix_web::Result<String> {
+    abci::reload_config()
+        .map(|_| "reloaded".into())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))
+}
+
+/// Structures exposed to the outside world
+pub struct AdminApi;
+
+/// Define interface
+#[allow(missing_docs)]
+pub enum AdminRoutes {
+    Shutdown,
+    ReloadConfig,
+}
+
+impl NetworkRoute for AdminRoutes {
+    fn route(&self) -> String {
+        let endpoint = match *self {
+            AdminRoutes::Shutdown => "shutdown",
+            AdminRoutes::ReloadConfig => "reload_config",
+        };
+        "/".to_owned() + endpoint
+    }
+}
+
+impl AdminApi {
+    /// Create the admin api on `ADMIN_HOST:port`
+    pub fn create(port: u16) -> Result<AdminApi> {
+        let _ = actix_rt::System::new("findora admin API");
+
+        HttpServer::new(|| {
+            App::new()
+                .wrap(middleware::Logger::default())
+                .route(&AdminRoutes::Shutdown.route(), web::post().to(shutdown))
+                .route(
+                    &AdminRoutes::ReloadConfig.route(),
+                    web::post().to(reload_config),
+                )
+        })
+        .bind(&format!("{}:{}", ADMIN_HOST, port))
+        .c(d!())?
+        .run();
+
+        info!("Admin server started");
+
+        Ok(AdminApi)
+    }
+}
@@ -0,0 +1,260 @@
+//!
+//! # Common settings of the http servers
+//!
+//! CORS, request body limits and timeouts, see `ApiServerConfig`,
+//! the CORS origins can be reloaded, see `RuntimeConfig`.
+//!
+//! The encoding of the transactions and the blocks, JSON or binary,
+//! see `globutils::wire`.
+//!
+//! The compression of the responses, by gzip or zstd as negotiated by the
+//! `Accept-Encoding` of the clients, for the bodies above a minimal size,
+//! see `ApiServerConfig::compression_min_size_of`.
+//!
+
+use {
+    actix_cors::Cors,
+    actix_web::{
+        dev::{Body, ResponseBody, ServiceRequest, ServiceResponse},
+        error,
+        http::{
+            header::{
+                HeaderValue, ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE,
+                VARY,
+            },
+            StatusCode,
+        },
+        Error, HttpRequest, HttpResponse,
+    },
+    config::abci::{ApiServerConfig, RuntimeConfig},
+    flate2::{write::GzEncoder, Compression},
+    globutils::wire::Encoding,
+    serde::{de::DeserializeOwned, Serialize},
+    std::{future::Future, io::Write, time::Duration},
+};
+
+/// The content codings of the compressed responses
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Coding {
+    #[allow(missing_docs)]
+    Zstd,
+    #[allow(missing_docs)]
+    Gzip,
+}
+
+impl Coding {
+    fn name(self) -> &'static str {
+        match self {
+            Coding::Zstd => "zstd",
+            Coding::Gzip => "gzip",
+        }
+    }
+
+    fn compress(self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Coding::Zstd => zstd::stream::encode_all(body, 0),
+            Coding::Gzip => {
+                let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+                enc.write_all(body).and_then(|_| enc.finish())
+            }
+        }
+    }
+}
+
+/// The coding preferred by the `Accept-Encoding` of `req`, zstd over gzip
+/// at the same quality, none if neither is accepted.
+pub fn accepted_coding(req: &ServiceRequest) -> Option<Coding> {
+    req.headers()
+        .get(ACCEPT_ENCODING)?
+        .to_str()
+        .ok()
+        .and_then(preferred_coding)
+}
+
+// `*` stands for gzip unless it is listed on its own, eg. refused by `gzip;q=0`
+fn preferred_coding(accept: &str) -> Option<Coding> {
+    let (mut zstd, mut gzip, mut any) = (None, None, None);
+    for item in accept.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or_default().trim();
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match coding {
+            c if c.eq_ignore_ascii_case("zstd") => zstd = Some(q),
+            c if c.eq_ignore_ascii_case("gzip") => gzip = Some(q),
+            "*" => any = Some(q),
+            _ => {}
+        }
+    }
+
+    let zstd = zstd.unwrap_or(0.0);
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    if 0.0 < zstd && gzip <= zstd {
+        Some(Coding::Zstd)
+    } else if 0.0 < gzip {
+        Some(Coding::Gzip)
+    } else {
+        None
+    }
+}
+
+/// Compress the response of a request in `coding`,
+/// if its body is complete and of `min_size` bytes at least.
+pub async fn with_compression<F>(
+    coding: Option<Coding>,
+    min_size: Option<usize>,
+    fut: F,
+) -> Result<ServiceResponse, Error>
+where
+    F: Future<Output = Result<ServiceResponse, Error>>,
+{
+    let res = fut.await?;
+    let (coding, min_size) = match (coding, min_size) {
+        (Some(c), Some(s)) => (c, s),
+        _ => return Ok(res),
+    };
+    if res.status() == StatusCode::NO_CONTENT
+        || res.headers().contains_key(CONTENT_ENCODING)
+    {
+        return Ok(res);
+    }
+
+    Ok(res.map_body(|head, body| {
+        let compressed = match body {
+            ResponseBody::Body(Body::Bytes(ref b))
+            | ResponseBody::Other(Body::Bytes(ref b))
+                if b.len() >= min_size =>
+            {
+                coding.compress(b)
+            }
+            _ => return body,
+        };
+        match compressed {
+            Ok(c) => {
+                let headers = head.headers_mut();
+                headers
+                    .insert(CONTENT_ENCODING, HeaderValue::from_static(coding.name()));
+                headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+                ResponseBody::Body(Body::from(c))
+            }
+            Err(_) => body,
+        }
+    }))
+}
+
+/// The CORS policy of `cfg`, or of `RuntimeConfig` if it overrides the origins,
+/// any origin is allowed if none is configured.
+pub fn cors(cfg: &ApiServerConfig) -> Cors {
+    let origins = cfg.cors_origins.clone();
+    Cors::default()
+        .allowed_origin_fn(move |origin, _| {
+            let runtime = RuntimeConfig::current().cors_origins;
+            let origins = runtime.as_ref().unwrap_or(&origins);
+            origins.is_empty()
+                || origins.iter().any(|o| "*" == o || origin == o.as_str())
+        })
+        .allow_any_method()
+        .allow_any_header()
+        .supports_credentials()
+        .max_age(3600)
+}
+
+/// Run the handling of a request with a timeout, if any.
+pub async fn with_timeout<F>(
+    t: Option<Duration>,
+    fut: F,
+) -> Result<ServiceResponse, Error>
+where
+    F: Future<Output = Result<ServiceResponse, Error>>,
+{
+    match t {
+        Some(t) => actix_rt::time::timeout(t, fut)
+            .await
+            .unwrap_or_else(|_| Err(error::ErrorGatewayTimeout("request timeout"))),
+        None => fut.await,
+    }
+}
+
+/// Decode the body of `req` by its `Content-Type`, JSON if it is not set.
+pub fn decode_body<T: DeserializeOwned>(
+    req: &HttpRequest,
+    body: &[u8],
+) -> Result<T, Error> {
+    let ct = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .map(|v| v.to_str().map_err(error::ErrorBadRequest))
+        .transpose()?;
+    Encoding::from_content_type(ct)
+        .map_err(|e| error::ErrorUnsupportedMediaType(e.to_string()))?
+        .decode(body)
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))
+}
+
+/// A response of `value` in the encoding accepted by `req`, JSON by default.
+pub fn encoded<T: Serialize>(
+    req: &HttpRequest,
+    value: &T,
+) -> Result<HttpResponse, Error> {
+    let accept = req.headers().get(ACCEPT).and_then(|v| v.to_str().ok());
+    let encoding = Encoding::from_accept(accept);
+    encoding
+        .encode(value)
+        .map(|body| HttpResponse::Ok().content_type(encoding.mime()).body(body))
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, actix_web::test::TestRequest};
+
+    #[test]
+    fn accepted_coding_header() {
+        let req = |accept: Option<&str>| {
+            let mut req = TestRequest::default();
+            if let Some(a) = accept {
+                req = req.header(ACCEPT_ENCODING, a);
+            }
+            accepted_coding(&req.to_srv_request())
+        };
+        assert_eq!(None, req(None));
+        assert_eq!(Some(Coding::Gzip), req(Some("gzip, deflate")));
+    }
+
+    #[test]
+    fn preferred_coding_q_values() {
+        for (accept, coding) in [
+            ("", None),
+            ("identity", None),
+            ("br, deflate", None),
+            ("gzip", Some(Coding::Gzip)),
+            ("GZip", Some(Coding::Gzip)),
+            ("zstd", Some(Coding::Zstd)),
+            ("gzip, zstd", Some(Coding::Zstd)),
+            // q-values
+            ("zstd;q=0.5, gzip;q=0.8", Some(Coding::Gzip)),
+            ("zstd;q=0.8, gzip;q=0.8", Some(Coding::Zstd)),
+            (" gzip ; q=0.9 , zstd ; q=1 ", Some(Coding::Zstd)),
+            ("zstd;level=1;q=0.1, gzip;q=0.2", Some(Coding::Gzip)),
+            // unparsable, as if not set
+            ("zstd;q=x, gzip;q=0.5", Some(Coding::Zstd)),
+            // refused by q=0
+            ("gzip;q=0", None),
+            ("zstd;q=0, gzip", Some(Coding::Gzip)),
+            ("zstd;q=0.0, gzip;q=0", None),
+            // `*`
+            ("*", Some(Coding::Gzip)),
+            ("*;q=0", None),
+            ("br, *;q=0.5", Some(Coding::Gzip)),
+            ("zstd;q=0.4, *;q=0.5", Some(Coding::Gzip)),
+            ("zstd, *;q=0", Some(Coding::Zstd)),
+            ("gzip;q=0, *", None),
+            ("*, gzip;q=0", None),
+            ("gzip;q=0.2, *", Some(Coding::Gzip)),
+        ] {
+            assert_eq!(coding, preferred_coding(accept), "{}", accept);
+        }
+    }
+}
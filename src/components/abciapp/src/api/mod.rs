@@ -2,6 +2,12 @@
 //! # Services provided by api
 //!
 
+/// Administration of the node, only from its host
+pub mod admin_server;
+
+/// Common settings of the http servers
+pub mod http;
+
 /// Provide query service for ledgerState
 pub mod query_server;
 
@@ -4,28 +4,51 @@
 
 use {
     super::server::QueryServer,
-    actix_web::{error, web},
+    crate::{
+        abci::server::anchor::{self, AnchorReceipt},
+        api::http,
+    },
+    actix_web::{error, web, HttpRequest, HttpResponse},
     config::abci::global_cfg::CFG,
     finutils::api::{
-        DelegationInfo, DelegatorInfo, DelegatorList, NetworkRoute, Validator,
+        BondEntry, DelegationInfo, DelegatorDelegations, DelegatorInfo, DelegatorList,
+        DelegatorRewards, DelegatorUnbondings, NetworkRoute, UnbondingEntry, Validator,
         ValidatorDetail, ValidatorList,
     },
     globutils::HashOf,
     ledger::{
+        converter::{
+            designated_transfer::DesignatedTransfer, ibc::IbcClient,
+            notary::NotarizedHash, payment_stream::PaymentStream,
+            prism::PrismAuthorities, pull_payment::PullAuthorization,
+            session_key::SessionKey, spending_policy::KeyPolicy,
+            vesting::VestingSchedule,
+        },
         data_model::{
-            AssetType, AssetTypeCode, AuthenticatedUtxo, StateCommitmentData, TxnSID,
-            TxoSID, UnAuthenticatedUtxo, Utxo,
+            AssetMetadata, AssetMirror, AssetType, AssetTypeCode, AuthenticatedIbcValue,
+            AuthenticatedKvValue, AuthenticatedNotarizedHash,
+            AuthenticatedRevocationRegistry, AuthenticatedUtxo, Operation,
+            RevocationRegistry, StateCommitmentData, TracerKeyRotation, TxnSID, TxoSID,
+            UnAuthenticatedUtxo, UpdateRevocation, Utxo,
         },
         staking::{
-            DelegationRwdDetail, DelegationState, Staking, TendermintAddr,
-            TendermintAddrRef,
+            epoch::EpochRecord, Delegation, DelegationRwdDetail, DelegationState,
+            Staking, TendermintAddr, TendermintAddrRef, ValidatorSetChange,
+            BLOCK_HEIGHT_MAX,
+        },
+        store::{
+            checksum::{LedgerChecksums, Subsystem},
+            dust_limits,
         },
     },
     parking_lot::RwLock,
     ruc::*,
     serde::{Deserialize, Serialize},
-    std::{collections::BTreeMap, mem, sync::Arc},
-    zei::xfr::{sig::XfrPublicKey, structs::OwnerMemo},
+    std::{collections::BTreeMap, mem, str::FromStr, sync::Arc},
+    zei::xfr::{
+        sig::XfrPublicKey,
+        structs::{OwnerMemo, TracingPolicies, XfrAmount, XfrAssetType},
+    },
 };
 
 /// Ping route to check for liveness of API
@@ -147,17 +170,544 @@ pub async fn query_asset(
     }
 }
 
-/// query tx according to `TxnSID`
+/// The metadata of an asset, for wallets and explorers
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AssetMetadataResp {
+    #[allow(missing_docs)]
+    pub code: String,
+    #[allow(missing_docs)]
+    pub decimals: u8,
+    /// `None` if the memo of the asset is a free text
+    pub metadata: Option<AssetMetadata>,
+}
+
+/// query the metadata in the memo of an asset, see `AssetMetadata`
+pub async fn query_asset_metadata(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<web::Json<AssetMetadataResp>> {
+    let qs = data.read();
+    let code = AssetTypeCode::new_from_base64(&*info)
+        .map_err(|_| error::ErrorBadRequest("Invalid asset definition encoding."))?;
+    let asset = qs.ledger_cloned.get_asset_type(&code).ok_or_else(|| {
+        error::ErrorNotFound("Specified asset definition does not currently exist.")
+    })?;
+    Ok(web::Json(AssetMetadataResp {
+        code: code.to_base64(),
+        decimals: asset.properties.asset_rules.decimals,
+        metadata: AssetMetadata::from_memo(&asset.properties.memo),
+    }))
+}
+
+/// query the tracing policies of an asset,
+/// transfers of the asset must carry the tracer memos of all of them
+pub async fn query_asset_tracing_policies(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<web::Json<TracingPolicies>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    if let Ok(token_code) = AssetTypeCode::new_from_base64(&*info) {
+        if let Some(asset) = ledger.get_asset_type(&token_code) {
+            Ok(web::Json(asset.get_tracing_policies_ref().clone()))
+        } else {
+            Err(actix_web::error::ErrorNotFound(
+                "Specified asset definition does not currently exist.",
+            ))
+        }
+    } else {
+        Err(actix_web::error::ErrorBadRequest(
+            "Invalid asset definition encoding.",
+        ))
+    }
+}
+
+/// query tracer key rotations of the tracing policies of an asset,
+/// so that auditors know which key decrypts the tracer memos of which block range
+pub async fn query_asset_tracer_keys(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<web::Json<Vec<TracerKeyRotation>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    if let Ok(token_code) = AssetTypeCode::new_from_base64(&*info) {
+        Ok(web::Json(
+            ledger
+                .get_status()
+                .get_tracer_key_rotations(&token_code)
+                .to_vec(),
+        ))
+    } else {
+        Err(actix_web::error::ErrorBadRequest(
+            "Invalid asset definition encoding.",
+        ))
+    }
+}
+
+/// The state of the prism bridge, without the processed withdrawals
+#[derive(Serialize, Deserialize)]
+pub struct PrismStateView {
+    /// None before the authorities are set
+    pub authorities: Option<PrismAuthorities>,
+    /// Version of the authorities
+    pub version: u64,
+    /// Amounts deposited and not withdrawn yet
+    #[serde(with = "globutils::serde_pairs")]
+    pub locked: BTreeMap<AssetTypeCode, u64>,
+}
+
+/// query the authorities and the locked assets of the prism bridge
+pub async fn query_prism_state(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> web::Json<PrismStateView> {
+    let qs = data.read();
+    let prism = qs.ledger_cloned.get_status().get_prism_state();
+    web::Json(PrismStateView {
+        authorities: prism.authorities.clone(),
+        version: prism.version,
+        locked: prism.locked.clone(),
+    })
+}
+
+/// query the mirrors of an asset on external chains
+pub async fn query_asset_mirrors(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<web::Json<Vec<AssetMirror>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    if let Ok(token_code) = AssetTypeCode::new_from_base64(&*info) {
+        Ok(web::Json(
+            ledger.get_status().get_asset_mirrors(&token_code).to_vec(),
+        ))
+    } else {
+        Err(actix_web::error::ErrorBadRequest(
+            "Invalid asset definition encoding.",
+        ))
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct MirrorParams {
+    chain_id: u64,
+    // hex, with or without the `0x` prefix
+    contract: String,
+}
+
+/// query the asset mirrored by a contract on an external chain
+pub async fn query_mirrored_asset(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<MirrorParams>,
+) -> actix_web::Result<web::Json<AssetTypeCode>> {
+    let contract = hex::decode(info.contract.trim_start_matches("0x"))
+        .ok()
+        .and_then(|c| <[u8; 20]>::try_from(c.as_slice()).ok())
+        .ok_or_else(|| error::ErrorBadRequest("Invalid contract address."))?;
+
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_status()
+        .get_mirrored_asset(info.chain_id, &contract)
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("No asset mirrored by the contract."))
+}
+
+/// query the receipts of the anchors published by this node,
+/// in the tendermint height range `[start, end]`
+pub async fn query_anchor_receipts(
+    web::Query(info): web::Query<BlockRangeParams>,
+) -> actix_web::Result<web::Json<Vec<AnchorReceipt>>> {
+    anchor::get_receipts(info.start as u64, info.end as u64)
+        .map(web::Json)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))
+}
+
+/// query the light client of a counterparty chain
+pub async fn query_ibc_client(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    client_id: web::Path<String>,
+) -> actix_web::Result<web::Json<IbcClient>> {
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_status()
+        .get_ibc_state()
+        .clients
+        .get(client_id.as_str())
+        .cloned()
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("Unknown client."))
+}
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct IbcKeyParams {
+    // e.g. `commitments/{client_id}/{sequence}`
+    key: String,
+}
+
+/// query a value of the IBC store with its proof, relayed to counterparty chains
+pub async fn query_ibc_proof(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<IbcKeyParams>,
+) -> actix_web::Result<web::Json<AuthenticatedIbcValue>> {
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_ibc_value(&info.key)
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("No value found."))
+}
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct KvKeyParams {
+    key: String,
+}
+
+/// query an entry of the key-value store with its proof
+pub async fn query_kv_value(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<KvKeyParams>,
+) -> actix_web::Result<web::Json<AuthenticatedKvValue>> {
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_kv_value(&info.key)
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("No value found."))
+}
+
+fn parse_anchored_hash(hash: &str) -> actix_web::Result<[u8; 32]> {
+    hex::decode(hash)
+        .ok()
+        .and_then(|h| <[u8; 32]>::try_from(h.as_slice()).ok())
+        .ok_or_else(|| error::ErrorBadRequest("Invalid hash."))
+}
+
+/// query the records of a hash, in hex, by all keys anchoring it
+pub async fn query_anchored_hash(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    hash: web::Path<String>,
+) -> actix_web::Result<web::Json<Vec<NotarizedHash>>> {
+    let hash = parse_anchored_hash(hash.as_str())?;
+    let qs = data.read();
+    let records = qs
+        .ledger_cloned
+        .get_status()
+        .get_notary_state()
+        .get_records(&hash);
+    Ok(web::Json(records))
+}
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct AnchorProofParams {
+    // in hex
+    hash: String,
+    // in bech32 or base64 format
+    owner: String,
+}
+
+/// query a hash anchored by a key with its proof
+pub async fn query_anchored_hash_proof(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<AnchorProofParams>,
+) -> actix_web::Result<web::Json<AuthenticatedNotarizedHash>> {
+    let hash = parse_anchored_hash(&info.hash)?;
+    let owner = globutils::address::parse_address(&info.owner)
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_notarized_hash(&hash, &owner)
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("No record found."))
+}
+
+/// query an authorization of pull payments, with the amounts remaining in it
+pub async fn query_pull_authorization(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    id: web::Path<String>,
+) -> actix_web::Result<web::Json<PullAuthorization>> {
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_status()
+        .get_pull_payment_state()
+        .get(id.as_str())
+        .map(|auth| web::Json(auth.clone()))
+        .map_err(|_| error::ErrorNotFound("Unknown authorization."))
+}
+
+/// The status of a payment stream at the last committed height
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PaymentStreamStatus {
+    #[allow(missing_docs)]
+    pub stream: PaymentStream,
+    /// The last committed height
+    pub height: u64,
+    /// Amount vested and not claimed yet
+    pub claimable: u64,
+    /// Amount not vested yet
+    pub unvested: u64,
+}
+
+/// query a payment stream, with the amounts vested at the last committed height
+pub async fn query_payment_stream(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    id: web::Path<String>,
+) -> actix_web::Result<web::Json<PaymentStreamStatus>> {
+    let qs = data.read();
+    let height = qs.ledger_cloned.get_tendermint_height();
+    qs.ledger_cloned
+        .get_status()
+        .get_payment_stream_state()
+        .get(id.as_str())
+        .map(|stream| {
+            web::Json(PaymentStreamStatus {
+                stream: stream.clone(),
+                height,
+                claimable: stream.claimable(height),
+                unvested: stream.unvested(height),
+            })
+        })
+        .map_err(|_| error::ErrorNotFound("Unknown stream."))
+}
+
+/// The status of a designated transfer at the last committed height
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DesignatedTransferStatus {
+    #[allow(missing_docs)]
+    pub transfer: DesignatedTransfer,
+    /// The last committed height
+    pub height: u64,
+    /// Whether the recipient can claim it in the next block,
+    /// otherwise the sender can reclaim it
+    pub claimable: bool,
+}
+
+/// query a pending designated transfer
+pub async fn query_designated_transfer(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    id: web::Path<String>,
+) -> actix_web::Result<web::Json<DesignatedTransferStatus>> {
+    let qs = data.read();
+    let height = qs.ledger_cloned.get_tendermint_height();
+    qs.ledger_cloned
+        .get_status()
+        .get_designated_transfer_state()
+        .get(id.as_str())
+        .map(|transfer| {
+            web::Json(DesignatedTransferStatus {
+                transfer: transfer.clone(),
+                height,
+                claimable: !transfer.is_expired(height + 1),
+            })
+        })
+        .map_err(|_| error::ErrorNotFound("Unknown designated transfer."))
+}
+
+/// The spending policy of a key at the last committed height
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SpendingPolicyStatus {
+    #[allow(missing_docs)]
+    pub policy: KeyPolicy,
+    /// The last committed height
+    pub height: u64,
+    /// The amounts that can still be sent within the limits in the next block
+    #[serde(with = "globutils::serde_pairs")]
+    pub remaining: BTreeMap<AssetTypeCode, u64>,
+}
+
+/// query the spending policy bound to an address
+pub async fn query_spending_policy(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<web::Json<SpendingPolicyStatus>> {
+    let pk = globutils::address::parse_address(address.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let qs = data.read();
+    let height = qs.ledger_cloned.get_tendermint_height();
+    qs.ledger_cloned
+        .get_status()
+        .get_spending_policy_state()
+        .get(&pk)
+        .map(|kp| {
+            web::Json(SpendingPolicyStatus {
+                policy: kp.clone(),
+                height,
+                remaining: kp
+                    .policy
+                    .limits
+                    .keys()
+                    .filter_map(|code| {
+                        kp.remaining(code, height + 1).map(|r| (*code, r))
+                    })
+                    .collect(),
+            })
+        })
+        .map_err(|_| error::ErrorNotFound("No spending policy."))
+}
+
+/// The session keys of a primary key at the last committed height
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionKeysStatus {
+    /// The last committed height
+    pub height: u64,
+    /// The session keys which can sign in the next block
+    pub sessions: BTreeMap<XfrPublicKey, SessionKey>,
+}
+
+/// query the session keys authorized by an address
+pub async fn query_session_keys(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<web::Json<SessionKeysStatus>> {
+    let pk = globutils::address::parse_address(address.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let qs = data.read();
+    let height = qs.ledger_cloned.get_tendermint_height();
+    let sessions = qs
+        .ledger_cloned
+        .get_status()
+        .get_session_key_state()
+        .get_sessions(&pk)
+        .map(|s| {
+            s.iter()
+                .filter(|(_, k)| !k.is_expired(height + 1))
+                .map(|(pk, k)| (*pk, k.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(web::Json(SessionKeysStatus { height, sessions }))
+}
+
+/// An unspent output with a vesting schedule
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VestingOutput {
+    #[allow(missing_docs)]
+    pub sid: TxoSID,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    #[allow(missing_docs)]
+    pub amount: u64,
+    /// The amount which can be spent freely in the next block
+    pub vested: u64,
+    /// The amount which must be carried over when spent in the next block
+    pub locked: u64,
+    #[allow(missing_docs)]
+    pub schedule: VestingSchedule,
+}
+
+/// The vesting outputs of an address at the last committed height
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VestingStatus {
+    /// The last committed height
+    pub height: u64,
+    #[allow(missing_docs)]
+    pub outputs: Vec<VestingOutput>,
+    /// The locked amounts of all outputs, by asset
+    #[serde(with = "globutils::serde_pairs")]
+    pub locked: BTreeMap<AssetTypeCode, u64>,
+}
+
+/// query the vested and locked amounts of the outputs owned by an address
+pub async fn query_vesting(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<web::Json<VestingStatus>> {
+    let pk = globutils::address::parse_address(address.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let qs = data.read();
+    let height = qs.ledger_cloned.get_tendermint_height();
+    let status = qs.ledger_cloned.get_status();
+    let vesting = status.get_vesting_state();
+
+    let mut res = VestingStatus {
+        height,
+        outputs: vec![],
+        locked: BTreeMap::new(),
+    };
+    for sid in status.get_owned_utxos(&pk) {
+        let schedule = match vesting.get(&sid) {
+            Some(s) => s,
+            None => continue,
+        };
+        let record = match qs.ledger_cloned.get_utxo_light(sid) {
+            Some(u) => u.utxo.0.record,
+            None => continue,
+        };
+        if let (XfrAssetType::NonConfidential(val), XfrAmount::NonConfidential(amount)) =
+            (record.asset_type, record.amount)
+        {
+            let asset = AssetTypeCode { val };
+            let locked = schedule.locked(height + 1).min(amount);
+            *res.locked.entry(asset).or_insert(0) += locked;
+            res.outputs.push(VestingOutput {
+                sid,
+                asset,
+                amount,
+                vested: amount - locked,
+                locked,
+                schedule: schedule.clone(),
+            });
+        }
+    }
+    Ok(web::Json(res))
+}
+
+/// query the dust limits in force for the next block, keyed by asset code,
+/// the minimum amounts of the non-confidential outputs of transfers
+pub async fn query_dust_limits(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> actix_web::Result<web::Json<BTreeMap<String, u64>>> {
+    let qs = data.read();
+    let limits = dust_limits(qs.ledger_cloned.get_tendermint_height() + 1)
+        .iter()
+        .map(|(code, limit)| (code.to_base64(), *limit))
+        .collect();
+    Ok(web::Json(limits))
+}
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct ChecksumParams {
+    // comma separated, all of the subsystems if missing
+    subsystems: Option<String>,
+}
+
+/// query the checksums of the subsystems of the ledger,
+/// e.g. `/state_checksums?subsystems=utxo,staking`
+pub async fn query_state_checksums(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<ChecksumParams>,
+) -> actix_web::Result<web::Json<LedgerChecksums>> {
+    let subsystems = info
+        .subsystems
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(Subsystem::from_str)
+        .collect::<Result<Vec<_>>>()
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let qs = data.read();
+    qs.ledger_cloned
+        .checksums(&subsystems)
+        .map(web::Json)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))
+}
+
+/// query tx according to `TxnSID`, in CBOR if accepted, see `http::encoded`
 pub async fn query_txn(
+    req: HttpRequest,
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
-) -> actix_web::Result<String> {
+) -> actix_web::Result<HttpResponse> {
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     if let Ok(txn_sid) = info.parse::<usize>() {
         if let Ok(mut txn) = ruc::info!(ledger.get_transaction(TxnSID(txn_sid))) {
             txn.finalized_txn.set_txo_id();
-            Ok(serde_json::to_string(&txn)?)
+            http::encoded(&req, &txn)
         } else {
             Err(actix_web::error::ErrorNotFound(
                 "Specified transaction does not exist.",
@@ -172,15 +722,16 @@ pub async fn query_txn(
 
 /// query tx according to `TxnSID`, lighter and faster version
 pub async fn query_txn_light(
+    req: HttpRequest,
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
-) -> actix_web::Result<String> {
+) -> actix_web::Result<HttpResponse> {
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     if let Ok(txn_sid) = info.parse::<usize>() {
         if let Ok(mut txn) = ruc::info!(ledger.get_transaction_light(TxnSID(txn_sid))) {
             txn.set_txo_id();
-            Ok(serde_json::to_string(&txn)?)
+            http::encoded(&req, &txn)
         } else {
             Err(actix_web::error::ErrorNotFound(
                 "Specified transaction does not exist.",
@@ -193,6 +744,30 @@ pub async fn query_txn_light(
     }
 }
 
+/// query a finalized block according to its index in the ledger,
+/// the state commitment data after applying this block is included
+pub async fn query_block(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<HttpResponse> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    if let Ok(block_sid) = info.parse::<usize>() {
+        if let Some(block) = ledger.blocks.get(block_sid) {
+            http::encoded(&req, &block)
+        } else {
+            Err(actix_web::error::ErrorNotFound(
+                "Specified block does not exist.",
+            ))
+        }
+    } else {
+        Err(actix_web::error::ErrorBadRequest(
+            "Invalid block sid encoding.",
+        ))
+    }
+}
+
 /// query global state, return (apphash, block count, apphash and block count signatures)
 #[allow(clippy::type_complexity)]
 pub async fn query_global_state(
@@ -216,6 +791,78 @@ pub async fn query_global_state_version(
     web::Json(hash)
 }
 
+/// query the revocation list of a credential issuer according to `cred_issuer_id`
+pub async fn query_credential_registry(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    issuer_id: web::Path<String>,
+) -> web::Json<Option<RevocationRegistry>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    web::Json(
+        ledger
+            .get_status()
+            .get_revocation_registry(issuer_id.as_str())
+            .cloned(),
+    )
+}
+
+/// query the revocation list of a credential issuer along with its proof,
+/// which can be verified against a state commitment without a full node
+pub async fn query_credential_registry_proof(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    issuer_id: web::Path<String>,
+) -> actix_web::Result<web::Json<AuthenticatedRevocationRegistry>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    ledger
+        .get_revocation_registry(issuer_id.as_str())
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("No blocks have been committed."))
+}
+
+// at most so many blocks can be scanned by one query
+const MAX_REGISTRY_UPDATES_RANGE: usize = 1000;
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct BlockRangeParams {
+    start: usize,
+    end: usize,
+}
+
+/// query updates of revocation lists in the block range `[start, end]`,
+/// in the form of `(block sid, txn sid, update)`
+pub async fn query_credential_registry_updates(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<BlockRangeParams>,
+) -> actix_web::Result<web::Json<Vec<(usize, TxnSID, UpdateRevocation)>>> {
+    if info.start > info.end || info.end - info.start >= MAX_REGISTRY_UPDATES_RANGE {
+        return Err(error::ErrorBadRequest(format!(
+            "Invalid block range, at most {} blocks.",
+            MAX_REGISTRY_UPDATES_RANGE
+        )));
+    }
+
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    let mut updates = vec![];
+    for block_sid in info.start..=info.end {
+        let block = match ledger.blocks.get(block_sid) {
+            Some(b) => b,
+            None => break,
+        };
+        for tx in block.txns.iter() {
+            tx.txn.body.operations.iter().for_each(|op| {
+                if let Operation::UpdateRevocation(u) = op {
+                    updates.push((block_sid, tx.tx_id, u.clone()));
+                }
+            });
+        }
+    }
+
+    Ok(web::Json(updates))
+}
+
 /// Query current validator list,
 /// validtors who have not completed self-deletagion will be filtered out.
 #[allow(unused)]
@@ -273,8 +920,8 @@ pub async fn get_delegation_reward(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<DelegationRwdQueryParams>,
 ) -> actix_web::Result<web::Json<Vec<DelegationRwdDetail>>> {
-    // Convert from base64 representation
-    let key: XfrPublicKey = globutils::wallet::public_key_from_base64(&info.address)
+    // Convert from bech32 or base64 representation
+    let key: XfrPublicKey = globutils::address::parse_address(&info.address)
         .c(d!())
         .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
 
@@ -302,6 +949,51 @@ pub async fn get_delegation_reward(
     ))
 }
 
+/// The distributions of the delegation rewards at the close of `epoch`.
+pub async fn query_epoch_rewards(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    epoch: web::Path<u64>,
+) -> actix_web::Result<web::Json<EpochRecord>> {
+    let epoch = epoch.into_inner();
+    let qs = data.read();
+    qs.ledger_cloned
+        .api_cache
+        .as_ref()
+        .and_then(|cache| cache.staking_epoch_hist.get(&epoch))
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("no record of this epoch"))
+}
+
+#[allow(missing_docs)]
+#[derive(Deserialize, Debug)]
+pub struct ValidatorSetQueryParams {
+    height: Option<u64>,
+}
+
+/// The validator set of tendermint in effect at `height`,
+/// default to the last committed height.
+pub async fn query_validator_set(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<ValidatorSetQueryParams>,
+) -> actix_web::Result<web::Json<ValidatorSetChange>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+    let height = info
+        .height
+        .unwrap_or_else(|| ledger.get_staking().cur_height());
+
+    ledger
+        .api_cache
+        .as_ref()
+        .and_then(|cache| {
+            cache
+                .staking_validator_set_hist
+                .get_closest_smaller(&height)
+        })
+        .map(|(_, change)| web::Json(change))
+        .ok_or_else(|| error::ErrorNotFound("no validator set recorded"))
+}
+
 #[allow(missing_docs)]
 #[derive(Deserialize, Debug)]
 pub struct ValidatorDelegationQueryParams {
@@ -577,6 +1269,7 @@ pub async fn query_validator_detail(
                 validator_realtime_apy,
                 kind: v.kind(),
                 delegator_cnt: v.delegators.len() as u64,
+                jailed: v.jailed,
             };
             return Ok(web::Json(resp));
         }
@@ -590,7 +1283,7 @@ pub async fn query_delegation_info(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     address: web::Path<String>,
 ) -> actix_web::Result<web::Json<DelegationInfo>> {
-    let pk = globutils::wallet::public_key_from_base64(address.as_str())
+    let pk = globutils::address::parse_address(address.as_str())
         .c(d!())
         .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
 
@@ -676,6 +1369,137 @@ pub async fn query_delegation_info(
     Ok(web::Json(resp))
 }
 
+/// The active delegations of `address`, by validator.
+pub async fn query_delegations(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<web::Json<DelegatorDelegations>> {
+    let pk = globutils::address::parse_address(address.as_str())
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let qs = data.read();
+    let staking = qs.ledger_cloned.get_staking();
+
+    let delegations = staking
+        .delegation_get(&pk)
+        .filter(|d| {
+            DelegationState::Bond == d.state && BLOCK_HEIGHT_MAX == d.end_height()
+        })
+        .map(|d| {
+            d.delegations
+                .iter()
+                .filter(|(_, am)| 0 < **am)
+                .filter_map(|(pk, am)| {
+                    staking
+                        .validator_app_pk_to_td_addr(pk)
+                        .ok()
+                        .map(|validator| BondEntry {
+                            validator,
+                            amount: *am,
+                            since: d.start_height(),
+                        })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(web::Json(DelegatorDelegations {
+        height: staking.cur_height(),
+        delegations,
+    }))
+}
+
+/// The undelegations of `address` not paid back yet,
+/// the partial ones and the whole delegation once undelegated.
+pub async fn query_unbondings(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<web::Json<DelegatorUnbondings>> {
+    let pk = globutils::address::parse_address(address.as_str())
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let qs = data.read();
+    let staking = qs.ledger_cloned.get_staking();
+
+    let mut unbondings = vec![];
+    if let Some(d) = staking.delegation_get(&pk) {
+        let unbonding = |d: &Delegation| {
+            d.delegations
+                .iter()
+                .filter(|(_, am)| 0 < **am)
+                .filter_map(|(pk, am)| {
+                    staking
+                        .validator_app_pk_to_td_addr(pk)
+                        .ok()
+                        .map(|validator| UnbondingEntry {
+                            validator,
+                            amount: *am,
+                            end_height: d.end_height(),
+                        })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // the temporary delegations created by partial undelegations
+        d.tmp_delegators
+            .keys()
+            .filter_map(|tmp| staking.delegation_get(tmp))
+            .filter(|tmp| DelegationState::Paid != tmp.state)
+            .for_each(|tmp| unbondings.extend(unbonding(tmp)));
+
+        let undelegated = match d.state {
+            DelegationState::Bond => BLOCK_HEIGHT_MAX != d.end_height(),
+            DelegationState::Free => true,
+            DelegationState::Paid => false,
+        };
+        if undelegated {
+            unbondings.extend(unbonding(d));
+        }
+    }
+
+    Ok(web::Json(DelegatorUnbondings {
+        height: staking.cur_height(),
+        unbondings,
+    }))
+}
+
+/// The rewards accrued by `address` and not paid yet.
+pub async fn query_delegator_rewards(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+) -> actix_web::Result<web::Json<DelegatorRewards>> {
+    let pk = globutils::address::parse_address(address.as_str())
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let qs = data.read();
+    let staking = qs.ledger_cloned.get_staking();
+
+    let mut resp = DelegatorRewards {
+        height: staking.cur_height(),
+        ..Default::default()
+    };
+    if let Some(d) = staking
+        .delegation_get(&pk)
+        .filter(|d| DelegationState::Paid != d.state)
+    {
+        resp.rewards = d.rwd_amount;
+        resp.unbonding_rewards = d
+            .tmp_delegators
+            .keys()
+            .filter_map(|tmp| staking.delegation_get(tmp))
+            .filter(|tmp| DelegationState::Paid != tmp.state)
+            .map(|tmp| tmp.rwd_amount)
+            .sum();
+        resp.delegation_rwd_cnt = d.delegation_rwd_cnt;
+        resp.proposer_rwd_cnt = d.proposer_rwd_cnt;
+    }
+
+    Ok(web::Json(resp))
+}
+
 /// query utxos according `public_key`
 pub async fn query_owned_utxos(
     data: web::Data<Arc<RwLock<QueryServer>>>,
@@ -683,7 +1507,7 @@ pub async fn query_owned_utxos(
 ) -> actix_web::Result<web::Json<BTreeMap<TxoSID, (Utxo, Option<OwnerMemo>)>>> {
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
-    globutils::wallet::public_key_from_base64(owner.as_str())
+    globutils::address::parse_address(owner.as_str())
         .c(d!())
         .map_err(|e| error::ErrorBadRequest(e.to_string()))
         .map(|pk| web::Json(pnk!(ledger.get_owned_utxos(&pk))))
@@ -696,15 +1520,44 @@ pub enum ApiRoutes {
     UtxoSidList,
     AssetIssuanceNum,
     AssetToken,
+    AssetMetadata,
+    AssetTracingPolicies,
+    AssetTracerKeys,
     GlobalState,
     TxnSid,
     TxnSidLight,
+    BlockSid,
     GlobalStateVersion,
     OwnedUtxos,
     ValidatorList,
     DelegationInfo,
     DelegatorList,
     ValidatorDetail,
+    CredentialRegistry,
+    CredentialRegistryProof,
+    CredentialRegistryUpdates,
+    PrismState,
+    AssetMirrors,
+    MirroredAsset,
+    IbcClient,
+    IbcProof,
+    KvValue,
+    AnchoredHash,
+    AnchoredHashProof,
+    AnchorReceipts,
+    PullAuthorization,
+    PaymentStream,
+    DustLimits,
+    DesignatedTransfer,
+    SpendingPolicy,
+    SessionKeys,
+    Vesting,
+    Delegations,
+    Unbondings,
+    DelegatorRewards,
+    ValidatorSet,
+    EpochRewards,
+    StateChecksums,
 }
 
 impl NetworkRoute for ApiRoutes {
@@ -715,15 +1568,44 @@ impl NetworkRoute for ApiRoutes {
             ApiRoutes::UtxoSidList => "utxo_sid_list",
             ApiRoutes::AssetIssuanceNum => "asset_issuance_num",
             ApiRoutes::AssetToken => "asset_token",
+            ApiRoutes::AssetMetadata => "asset_metadata",
+            ApiRoutes::AssetTracingPolicies => "asset_tracing_policies",
+            ApiRoutes::AssetTracerKeys => "asset_tracer_keys",
             ApiRoutes::GlobalState => "global_state",
             ApiRoutes::TxnSid => "txn_sid",
             ApiRoutes::TxnSidLight => "txn_sid_light",
+            ApiRoutes::BlockSid => "block_sid",
             ApiRoutes::GlobalStateVersion => "global_state_version",
             ApiRoutes::OwnedUtxos => "owned_utxos",
             ApiRoutes::ValidatorList => "validator_list",
             ApiRoutes::DelegationInfo => "delegation_info",
             ApiRoutes::DelegatorList => "delegator_list",
             ApiRoutes::ValidatorDetail => "validator_detail",
+            ApiRoutes::CredentialRegistry => "credential_registry",
+            ApiRoutes::CredentialRegistryProof => "credential_registry_proof",
+            ApiRoutes::CredentialRegistryUpdates => "credential_registry_updates",
+            ApiRoutes::PrismState => "prism_state",
+            ApiRoutes::AssetMirrors => "asset_mirrors",
+            ApiRoutes::MirroredAsset => "mirrored_asset",
+            ApiRoutes::IbcClient => "ibc_client",
+            ApiRoutes::IbcProof => "ibc_proof",
+            ApiRoutes::KvValue => "kv_value",
+            ApiRoutes::AnchoredHash => "anchored_hash",
+            ApiRoutes::AnchoredHashProof => "anchored_hash_proof",
+            ApiRoutes::AnchorReceipts => "anchor_receipts",
+            ApiRoutes::PullAuthorization => "pull_authorization",
+            ApiRoutes::PaymentStream => "payment_stream",
+            ApiRoutes::DustLimits => "dust_limits",
+            ApiRoutes::DesignatedTransfer => "designated_transfer",
+            ApiRoutes::SpendingPolicy => "spending_policy",
+            ApiRoutes::SessionKeys => "session_keys",
+            ApiRoutes::Vesting => "vesting",
+            ApiRoutes::Delegations => "delegations",
+            ApiRoutes::Unbondings => "unbondings",
+            ApiRoutes::DelegatorRewards => "delegator_rewards",
+            ApiRoutes::ValidatorSet => "validator_set",
+            ApiRoutes::EpochRewards => "epoch_rewards",
+            ApiRoutes::StateChecksums => "state_checksums",
         };
         "/".to_owned() + endpoint
     }
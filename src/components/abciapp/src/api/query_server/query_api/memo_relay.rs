@@ -0,0 +1,130 @@
+//!
+//! # Owner memo relay
+//!
+//! Confidential outputs can only be opened with their owner memos, the relay
+//! delivers the memos that are not carried by the transactions themselves.
+//!
+//! Senders post the memo of an output under the key of its owner,
+//! i.e. `finutils::api::memo_relay_key`, and the owner fetches all of its memos
+//! with the same key. The output must be on chain and owned by the recipient of the key.
+//!
+//! Enabled by `CFG.enable_memo_relay`, memos are appended to
+//! `<ledger_dir>/relayed_memos.jsonl` and reloaded on restart.
+//!
+
+use {
+    super::server::QueryServer,
+    actix_web::{error, web},
+    config::abci::global_cfg::CFG,
+    finutils::api::{memo_relay_key, RelayedMemo},
+    lazy_static::lazy_static,
+    parking_lot::RwLock,
+    ruc::*,
+    std::{
+        collections::HashMap,
+        fs::{self, OpenOptions},
+        io::{ErrorKind, Write},
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+const MEMOS_FILE: &str = "relayed_memos.jsonl";
+
+/// The maximum number of memos kept for a recipient,
+/// the memos of spent outputs are dropped first.
+pub const MAX_MEMOS_PER_RECIPIENT: usize = 1024;
+
+lazy_static! {
+    static ref MEMOS: RwLock<HashMap<String, Vec<RelayedMemo>>> =
+        RwLock::new(pnk!(load_memos()));
+}
+
+/// Fetch the memos relayed to the recipient of `key`
+pub async fn get_relayed_memos(
+    key: web::Path<String>,
+) -> actix_web::Result<web::Json<Vec<RelayedMemo>>> {
+    Ok(web::Json(
+        MEMOS.read().get(key.as_str()).cloned().unwrap_or_default(),
+    ))
+}
+
+/// Relay the memo of an output to its owner, the recipient of `key`
+pub async fn post_relayed_memo(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    key: web::Path<String>,
+    body: web::Json<RelayedMemo>,
+) -> actix_web::Result<String> {
+    let memo = body.into_inner();
+    let key = key.into_inner();
+    let qs = data.read();
+
+    let owner = qs
+        .ledger_cloned
+        .get_utxo_light(memo.txo_sid)
+        .map(|u| u.utxo.0.record.public_key)
+        .ok_or_else(|| error::ErrorNotFound("Unknown or spent output."))?;
+    if memo_relay_key(&owner) != key {
+        return Err(error::ErrorBadRequest(
+            "The output is not owned by the recipient of the key.",
+        ));
+    }
+
+    let mut memos = MEMOS.write();
+    let relayed = memos.entry(key.clone()).or_default();
+    if relayed.iter().any(|m| m.txo_sid == memo.txo_sid) {
+        return Err(error::ErrorConflict("The memo has been relayed."));
+    }
+    if relayed.len() >= MAX_MEMOS_PER_RECIPIENT {
+        relayed.retain(|m| qs.ledger_cloned.get_utxo_light(m.txo_sid).is_some());
+        if relayed.len() >= MAX_MEMOS_PER_RECIPIENT {
+            return Err(error::ErrorTooManyRequests(
+                "Too many memos for the recipient.",
+            ));
+        }
+    }
+
+    append_memo(&key, &memo)
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    relayed.push(memo);
+
+    Ok("ok".to_owned())
+}
+
+fn append_memo(key: &str, memo: &RelayedMemo) -> Result<()> {
+    let line = serde_json::to_string(&(key, memo)).c(d!())? + "\n";
+
+    let path = memos_path();
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .c(d!(path.display()))?
+        .write_all(line.as_bytes())
+        .c(d!())
+}
+
+fn load_memos() -> Result<HashMap<String, Vec<RelayedMemo>>> {
+    let mut memos: HashMap<String, Vec<RelayedMemo>> = HashMap::new();
+    if !CFG.enable_memo_relay {
+        return Ok(memos);
+    }
+
+    let content = match fs::read_to_string(memos_path()) {
+        Ok(c) => c,
+        Err(e) if ErrorKind::NotFound == e.kind() => return Ok(memos),
+        Err(e) => return Err(eg!(e)),
+    };
+
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let (key, memo) = serde_json::from_str::<(String, RelayedMemo)>(line).c(d!())?;
+        memos.entry(key).or_default().push(memo);
+    }
+
+    Ok(memos)
+}
+
+#[inline(always)]
+fn memos_path() -> PathBuf {
+    Path::new(&CFG.ledger_dir).join(MEMOS_FILE)
+}
@@ -5,26 +5,31 @@
 // pub it for doc
 pub mod ledger_api;
 
+pub mod memo_relay;
+pub mod namespace;
 pub mod server;
 pub mod service;
 
 use {
-    actix_cors::Cors,
+    crate::api::http,
+    actix_service::Service,
     actix_web::{error, middleware, web, App, HttpServer},
-    config::abci::{global_cfg::CFG, CheckPointConfig},
+    config::abci::{global_cfg::CFG, ApiServerConfig, CheckPointConfig},
     finutils::api::NetworkRoute,
-    globutils::wallet,
+    globutils::address,
     ledger::{
         data_model::{
-            b64dec, AssetTypeCode, DefineAsset, IssuerPublicKey, Transaction, TxOutput,
+            AssetTypeCode, DefineAsset, IssuerPublicKey, Transaction, TxOutput,
             TxnIDHash, TxnSID, TxoSID, XfrAddress, BLACK_HOLE_PUBKEY,
         },
         staking::{
             ops::mint_fra::MintEntry, FF_PK_EXTRA_120_0000, FRA, FRA_TOTAL_AMOUNT,
         },
+        store::api_cache::{AssetStats, FeeHistory, SearchQuery, SearchResult},
     },
     ledger_api::*,
     log::info,
+    memo_relay::{get_relayed_memos, post_relayed_memo},
     parking_lot::RwLock,
     ruc::*,
     serde::{Deserialize, Serialize},
@@ -33,10 +38,7 @@ use {
         collections::{BTreeMap, HashSet},
         sync::Arc,
     },
-    zei::{
-        serialization::ZeiFromToBytes,
-        xfr::{sig::XfrPublicKey, structs::OwnerMemo},
-    },
+    zei::xfr::{sig::XfrPublicKey, structs::OwnerMemo},
 };
 
 /// Returns the git commit hash and commit date of this build
@@ -102,7 +104,7 @@ pub async fn get_owned_utxos(
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
 
-    let pk = wallet::public_key_from_base64(owner.as_str())
+    let pk = address::parse_address(owner.as_str())
         .map_err(actix_web::error::ErrorServiceUnavailable)?;
 
     let utxos = ledger
@@ -131,6 +133,10 @@ pub enum QueryServerRoutes {
     GetTransactionHash,
     GetTransactionSid,
     GetCommits,
+    MemoRelay,
+    Search,
+    AssetStats,
+    FeeHistory,
 }
 
 impl NetworkRoute for QueryServerRoutes {
@@ -149,6 +155,10 @@ impl NetworkRoute for QueryServerRoutes {
             QueryServerRoutes::GetTransactionHash => "get_transaction_hash",
             QueryServerRoutes::GetTransactionSid => "get_transaction_sid",
             QueryServerRoutes::GetCommits => "get_commits",
+            QueryServerRoutes::MemoRelay => "memo_relay",
+            QueryServerRoutes::Search => "search",
+            QueryServerRoutes::AssetStats => "asset_stats",
+            QueryServerRoutes::FeeHistory => "fee_history",
         };
         "/".to_owned() + endpoint
     }
@@ -159,13 +169,10 @@ pub async fn get_created_assets(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<Vec<DefineAsset>>> {
-    // Convert from base64 representation
-    let key: XfrPublicKey = XfrPublicKey::zei_from_bytes(
-        &b64dec(&*info)
-            .c(d!())
-            .map_err(|e| error::ErrorBadRequest(e.to_string()))?,
-    )
-    .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    // Convert from bech32 or base64 representation
+    let key: XfrPublicKey = address::parse_address(&info)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
     let server = data.read();
     let assets = server.get_created_assets(&IssuerPublicKey { key });
     Ok(web::Json(assets.unwrap_or_default()))
@@ -177,13 +184,10 @@ pub async fn get_issued_records(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<Vec<(TxOutput, Option<OwnerMemo>)>>> {
-    // Convert from base64 representation
-    let key: XfrPublicKey = XfrPublicKey::zei_from_bytes(
-        &b64dec(&*info)
-            .c(d!())
-            .map_err(|e| error::ErrorBadRequest(e.to_string()))?,
-    )
-    .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    // Convert from bech32 or base64 representation
+    let key: XfrPublicKey = address::parse_address(&info)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
     let server = data.read();
     let records = server.get_issued_records(&IssuerPublicKey { key });
     Ok(web::Json(records.unwrap_or_default()))
@@ -298,8 +302,8 @@ pub async fn get_coinbase_oper_list(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<WalletQueryParams>,
 ) -> actix_web::Result<web::Json<CoinbaseOperInfo>> {
-    // Convert from base64 representation
-    let key: XfrPublicKey = wallet::public_key_from_base64(&info.address)
+    // Convert from bech32 or base64 representation
+    let key: XfrPublicKey = address::parse_address(&info.address)
         .c(d!())
         .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
 
@@ -349,8 +353,8 @@ pub async fn get_claim_txns(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<WalletQueryParams>,
 ) -> actix_web::Result<web::Json<Vec<Option<Transaction>>>> {
-    // Convert from base64 representation
-    let key: XfrPublicKey = wallet::public_key_from_base64(&info.address)
+    // Convert from bech32 or base64 representation
+    let key: XfrPublicKey = address::parse_address(&info.address)
         .c(d!())
         .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
 
@@ -387,14 +391,10 @@ pub async fn get_related_txns(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
 ) -> actix_web::Result<web::Json<HashSet<TxnSID>>> {
-    // Convert from base64 representation
-    let key: XfrPublicKey = XfrPublicKey::zei_from_bytes(
-        &b64dec(&*info)
-            .c(d!())
-            .map_err(|e| error::ErrorBadRequest(e.to_string()))?,
-    )
-    .c(d!())
-    .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    // Convert from bech32 or base64 representation
+    let key: XfrPublicKey = address::parse_address(&info)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
     let server = data.read();
     let records = server.get_related_transactions(&XfrAddress { key });
     Ok(web::Json(records.unwrap_or_default()))
@@ -482,6 +482,63 @@ pub async fn get_total_supply(
     Ok(web::Json(res))
 }
 
+/// Search the indexed ledger data by partial asset codes, memo words,
+/// amount ranges of non-confidential outputs and address prefixes
+pub async fn search(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(q): web::Query<SearchQuery>,
+) -> actix_web::Result<web::Json<SearchResult>> {
+    data.read()
+        .search(&q)
+        .map(web::Json)
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))
+}
+
+/// A range of tendermint heights, inclusive.
+#[derive(Debug, Deserialize)]
+pub struct BlockRange {
+    /// the first block, the genesis by default
+    pub from: Option<u64>,
+    /// the last block, the current one by default
+    pub to: Option<u64>,
+}
+
+/// Returns the statistics of an asset over a range of blocks: its holders,
+/// transfers, velocity and the history of its supply
+pub async fn get_asset_stats(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+    web::Query(range): web::Query<BlockRange>,
+) -> actix_web::Result<web::Json<AssetStats>> {
+    let code = AssetTypeCode::new_from_base64(&*info)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    data.read()
+        .get_asset_stats(&code, range.from, range.to)
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("no statistics of this asset"))
+}
+
+/// The number of blocks of a fee history.
+#[derive(Debug, Deserialize)]
+pub struct FeeHistoryQuery {
+    /// `FEE_HISTORY_BLOCKS` by default
+    pub blocks: Option<u64>,
+}
+
+/// Returns the fees paid in the last blocks with transactions, and the depth
+/// of the mempool after them, for wallets to suggest fees
+pub async fn get_fee_history(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(q): web::Query<FeeHistoryQuery>,
+) -> actix_web::Result<web::Json<FeeHistory>> {
+    data.read()
+        .get_fee_history(q.blocks)
+        .map(web::Json)
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub async fn get_checkpoint(
@@ -493,161 +550,56 @@ pub async fn get_checkpoint(
 pub struct QueryApi;
 
 impl QueryApi {
+    /// Serve `server` at the root, and the ledgers of `namespaces` under `/<name>`.
     pub(crate) fn create(
         server: Arc<RwLock<QueryServer>>,
+        namespaces: Vec<(String, Arc<RwLock<QueryServer>>)>,
         addrs: &[(&str, u16)],
+        cfg: ApiServerConfig,
     ) -> Result<QueryApi> {
         let _ = actix_rt::System::new("findora API");
 
         let mut hdr = HttpServer::new(move || {
+            let timeouts = cfg.clone();
+            let names = namespaces
+                .iter()
+                .map(|(n, _)| n.clone())
+                .collect::<Vec<_>>();
             App::new()
+                .wrap_fn(move |req, srv| {
+                    let path = path_in_ledger(&names, req.path());
+                    let timeout = timeouts.timeout_of(path);
+                    let min_size = timeouts.compression_min_size_of(path);
+                    let coding = http::accepted_coding(&req);
+                    http::with_compression(
+                        coding,
+                        min_size,
+                        http::with_timeout(timeout, srv.call(req)),
+                    )
+                })
                 .wrap(middleware::Logger::default())
-                .wrap(Cors::permissive().supports_credentials())
+                .wrap(http::cors(&cfg))
+                .app_data(web::JsonConfig::default().limit(cfg.max_body_size))
+                .app_data(web::PayloadConfig::new(cfg.max_body_size))
+                .configure(|c| {
+                    // first, so no route of the default ledger shadows them
+                    for (name, qs) in namespaces.iter() {
+                        c.service(
+                            web::scope(&format!("/{}", name))
+                                .data(Arc::clone(qs))
+                                .configure(routes),
+                        );
+                    }
+                })
                 .data(Arc::clone(&server))
-                .route("/ping", web::get().to(ping))
-                .route("/version", web::get().to(version))
-                .service(
-                    web::resource("get_total_supply")
-                        .route(web::get().to(get_total_supply)),
-                )
-                .service(
-                    web::resource("circulating_supply")
-                        .route(web::get().to(get_circulating_supply)),
-                )
-                .route(
-                    &QueryServerRoutes::GetAddress.with_arg_template("txo_sid"),
-                    web::get().to(get_address),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnedUtxos.with_arg_template("address"),
-                    web::get().to(get_owned_utxos),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnerMemo.with_arg_template("txo_sid"),
-                    web::get().to(get_owner_memo),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnerMemoBatch
-                        .with_arg_template("txo_sid_list"),
-                    web::get().to(get_owner_memo_batch),
-                )
-                .route(
-                    &QueryServerRoutes::GetRelatedTxns.with_arg_template("address"),
-                    web::get().to(get_related_txns),
-                )
-                .service(
-                    web::resource("claim_history").route(web::get().to(get_claim_txns)),
-                )
-                .service(
-                    web::resource("coinbase_history")
-                        .route(web::get().to(get_coinbase_oper_list)),
-                )
-                .route(
-                    &QueryServerRoutes::GetRelatedXfrs.with_arg_template("asset_token"),
-                    web::get().to(get_related_xfrs),
-                )
-                .route(
-                    &QueryServerRoutes::GetCreatedAssets.with_arg_template("address"),
-                    web::get().to(get_created_assets),
-                )
-                .route(
-                    &QueryServerRoutes::GetIssuedRecords.with_arg_template("address"),
-                    web::get().to(get_issued_records),
-                )
-                .route(
-                    &QueryServerRoutes::GetIssuedRecordsByCode
-                        .with_arg_template("asset_token"),
-                    web::get().to(get_issued_records_by_code),
-                )
-                .route(
-                    &QueryServerRoutes::GetAuthencatedTxnIDHash
-                        .with_arg_template("txo_sid"),
-                    web::get().to(get_authenticated_txnid_hash),
-                )
-                .route(
-                    &QueryServerRoutes::GetTransactionHash.with_arg_template("txn_sid"),
-                    web::get().to(get_transaction_hash),
-                )
-                .route(
-                    &QueryServerRoutes::GetTransactionSid.with_arg_template("txn_hash"),
-                    web::get().to(get_transaction_sid),
-                )
-                .route(
-                    &QueryServerRoutes::GetCommits.route(),
-                    web::get().to(get_commits),
-                )
-                .route(
-                    &ApiRoutes::UtxoSid.with_arg_template("sid"),
-                    web::get().to(query_utxo),
-                )
-                .route(
-                    &ApiRoutes::UtxoSidLight.with_arg_template("sid"),
-                    web::get().to(query_utxo_light),
-                )
-                .route(
-                    &ApiRoutes::UtxoSidList.with_arg_template("sid_list"),
-                    web::get().to(query_utxos),
-                )
-                .route(
-                    &ApiRoutes::AssetIssuanceNum.with_arg_template("code"),
-                    web::get().to(query_asset_issuance_num),
-                )
-                .route(
-                    &ApiRoutes::AssetToken.with_arg_template("code"),
-                    web::get().to(query_asset),
-                )
-                .route(
-                    &ApiRoutes::GlobalState.route(),
-                    web::get().to(query_global_state),
-                )
-                .route(
-                    &ApiRoutes::TxnSid.with_arg_template("sid"),
-                    web::get().to(query_txn),
-                )
-                .route(
-                    &ApiRoutes::TxnSidLight.with_arg_template("sid"),
-                    web::get().to(query_txn_light),
-                )
-                .route(
-                    &ApiRoutes::GlobalStateVersion.with_arg_template("version"),
-                    web::get().to(query_global_state_version),
-                )
-                .route(
-                    &ApiRoutes::OwnedUtxos.with_arg_template("owner"),
-                    web::get().to(query_owned_utxos),
-                )
-                .route(
-                    &ApiRoutes::ValidatorList.route(),
-                    web::get().to(query_validators),
-                )
-                .route(
-                    &ApiRoutes::DelegationInfo.with_arg_template("XfrPublicKey"),
-                    web::get().to(query_delegation_info),
-                )
-                .route(
-                    &ApiRoutes::DelegatorList.with_arg_template("NodeAddress"),
-                    web::get().to(query_delegator_list),
-                )
-                .service(
-                    web::resource("/delegator_list")
-                        .route(web::get().to(get_delegators_with_params)),
-                )
-                .service(
-                    web::resource("/delegation_rewards")
-                        .route(web::get().to(get_delegation_reward)),
-                )
-                .service(
-                    web::resource("/validator_delegation")
-                        .route(web::get().to(get_validator_delegation_history)),
-                )
-                .route(
-                    &ApiRoutes::ValidatorDetail.with_arg_template("NodeAddress"),
-                    web::get().to(query_validator_detail),
-                )
-                .service(
-                    web::resource("/display_checkpoint")
-                        .route(web::get().to(get_checkpoint)),
-                )
+                .configure(routes)
+                .configure(|cfg| {
+                    if CFG.enable_memo_relay {
+                        let path = QueryServerRoutes::MemoRelay.with_arg_template("key");
+                        cfg.route(&path, web::get().to(get_relayed_memos))
+                            .route(&path, web::post().to(post_relayed_memo));
+                    }
+                })
         });
 
         for (host, port) in addrs.iter() {
@@ -661,3 +613,275 @@ impl QueryApi {
         Ok(QueryApi)
     }
 }
+
+// the path in the ledger of a namespace, the route timeouts apply to
+fn path_in_ledger<'a>(namespaces: &[String], path: &'a str) -> &'a str {
+    namespaces
+        .iter()
+        .find_map(|n| {
+            path.strip_prefix('/')
+                .and_then(|p| p.strip_prefix(n.as_str()))
+                .filter(|p| p.starts_with('/'))
+        })
+        .unwrap_or(path)
+}
+
+// the routes served for each ledger, the default one and the namespaces
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/ping", web::get().to(ping))
+        .route("/version", web::get().to(version))
+        .service(
+            web::resource("get_total_supply").route(web::get().to(get_total_supply)),
+        )
+        .service(
+            web::resource("circulating_supply")
+                .route(web::get().to(get_circulating_supply)),
+        )
+        .route(
+            &QueryServerRoutes::GetAddress.with_arg_template("txo_sid"),
+            web::get().to(get_address),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnedUtxos.with_arg_template("address"),
+            web::get().to(get_owned_utxos),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnerMemo.with_arg_template("txo_sid"),
+            web::get().to(get_owner_memo),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnerMemoBatch.with_arg_template("txo_sid_list"),
+            web::get().to(get_owner_memo_batch),
+        )
+        .route(
+            &QueryServerRoutes::GetRelatedTxns.with_arg_template("address"),
+            web::get().to(get_related_txns),
+        )
+        .service(web::resource("claim_history").route(web::get().to(get_claim_txns)))
+        .service(
+            web::resource("coinbase_history")
+                .route(web::get().to(get_coinbase_oper_list)),
+        )
+        .route(
+            &QueryServerRoutes::GetRelatedXfrs.with_arg_template("asset_token"),
+            web::get().to(get_related_xfrs),
+        )
+        .route(
+            &QueryServerRoutes::GetCreatedAssets.with_arg_template("address"),
+            web::get().to(get_created_assets),
+        )
+        .route(
+            &QueryServerRoutes::GetIssuedRecords.with_arg_template("address"),
+            web::get().to(get_issued_records),
+        )
+        .route(
+            &QueryServerRoutes::GetIssuedRecordsByCode.with_arg_template("asset_token"),
+            web::get().to(get_issued_records_by_code),
+        )
+        .route(
+            &QueryServerRoutes::GetAuthencatedTxnIDHash.with_arg_template("txo_sid"),
+            web::get().to(get_authenticated_txnid_hash),
+        )
+        .route(
+            &QueryServerRoutes::GetTransactionHash.with_arg_template("txn_sid"),
+            web::get().to(get_transaction_hash),
+        )
+        .route(
+            &QueryServerRoutes::GetTransactionSid.with_arg_template("txn_hash"),
+            web::get().to(get_transaction_sid),
+        )
+        .route(
+            &QueryServerRoutes::GetCommits.route(),
+            web::get().to(get_commits),
+        )
+        .route(&QueryServerRoutes::Search.route(), web::get().to(search))
+        .route(
+            &QueryServerRoutes::AssetStats.with_arg_template("code"),
+            web::get().to(get_asset_stats),
+        )
+        .route(
+            &QueryServerRoutes::FeeHistory.route(),
+            web::get().to(get_fee_history),
+        )
+        .route(
+            &ApiRoutes::UtxoSid.with_arg_template("sid"),
+            web::get().to(query_utxo),
+        )
+        .route(
+            &ApiRoutes::UtxoSidLight.with_arg_template("sid"),
+            web::get().to(query_utxo_light),
+        )
+        .route(
+            &ApiRoutes::UtxoSidList.with_arg_template("sid_list"),
+            web::get().to(query_utxos),
+        )
+        .route(
+            &ApiRoutes::AssetIssuanceNum.with_arg_template("code"),
+            web::get().to(query_asset_issuance_num),
+        )
+        .route(
+            &ApiRoutes::AssetToken.with_arg_template("code"),
+            web::get().to(query_asset),
+        )
+        .route(
+            &ApiRoutes::AssetMetadata.with_arg_template("code"),
+            web::get().to(query_asset_metadata),
+        )
+        .route(
+            &ApiRoutes::AssetTracingPolicies.with_arg_template("code"),
+            web::get().to(query_asset_tracing_policies),
+        )
+        .route(
+            &ApiRoutes::AssetTracerKeys.with_arg_template("code"),
+            web::get().to(query_asset_tracer_keys),
+        )
+        .route(
+            &ApiRoutes::GlobalState.route(),
+            web::get().to(query_global_state),
+        )
+        .route(
+            &ApiRoutes::TxnSid.with_arg_template("sid"),
+            web::get().to(query_txn),
+        )
+        .route(
+            &ApiRoutes::TxnSidLight.with_arg_template("sid"),
+            web::get().to(query_txn_light),
+        )
+        .route(
+            &ApiRoutes::BlockSid.with_arg_template("sid"),
+            web::get().to(query_block),
+        )
+        .route(
+            &ApiRoutes::GlobalStateVersion.with_arg_template("version"),
+            web::get().to(query_global_state_version),
+        )
+        .route(
+            &ApiRoutes::OwnedUtxos.with_arg_template("owner"),
+            web::get().to(query_owned_utxos),
+        )
+        .route(
+            &ApiRoutes::ValidatorList.route(),
+            web::get().to(query_validators),
+        )
+        .route(
+            &ApiRoutes::DelegationInfo.with_arg_template("XfrPublicKey"),
+            web::get().to(query_delegation_info),
+        )
+        .route(
+            &ApiRoutes::DelegatorList.with_arg_template("NodeAddress"),
+            web::get().to(query_delegator_list),
+        )
+        .service(
+            web::resource("/delegator_list")
+                .route(web::get().to(get_delegators_with_params)),
+        )
+        .service(
+            web::resource("/delegation_rewards")
+                .route(web::get().to(get_delegation_reward)),
+        )
+        .service(
+            web::resource("/validator_delegation")
+                .route(web::get().to(get_validator_delegation_history)),
+        )
+        .route(
+            &ApiRoutes::ValidatorDetail.with_arg_template("NodeAddress"),
+            web::get().to(query_validator_detail),
+        )
+        .route(
+            &ApiRoutes::CredentialRegistry.with_arg_template("issuer_id"),
+            web::get().to(query_credential_registry),
+        )
+        .route(
+            &ApiRoutes::CredentialRegistryProof.with_arg_template("issuer_id"),
+            web::get().to(query_credential_registry_proof),
+        )
+        .route(
+            &ApiRoutes::CredentialRegistryUpdates.route(),
+            web::get().to(query_credential_registry_updates),
+        )
+        .route(
+            &ApiRoutes::PrismState.route(),
+            web::get().to(query_prism_state),
+        )
+        .route(
+            &ApiRoutes::AssetMirrors.with_arg_template("code"),
+            web::get().to(query_asset_mirrors),
+        )
+        .route(
+            &ApiRoutes::MirroredAsset.route(),
+            web::get().to(query_mirrored_asset),
+        )
+        .route(
+            &ApiRoutes::IbcClient.with_arg_template("client_id"),
+            web::get().to(query_ibc_client),
+        )
+        .route(&ApiRoutes::IbcProof.route(), web::get().to(query_ibc_proof))
+        .route(&ApiRoutes::KvValue.route(), web::get().to(query_kv_value))
+        .route(
+            &ApiRoutes::AnchoredHash.with_arg_template("hash"),
+            web::get().to(query_anchored_hash),
+        )
+        .route(
+            &ApiRoutes::AnchoredHashProof.route(),
+            web::get().to(query_anchored_hash_proof),
+        )
+        .route(
+            &ApiRoutes::AnchorReceipts.route(),
+            web::get().to(query_anchor_receipts),
+        )
+        .route(
+            &ApiRoutes::PullAuthorization.with_arg_template("id"),
+            web::get().to(query_pull_authorization),
+        )
+        .route(
+            &ApiRoutes::PaymentStream.with_arg_template("id"),
+            web::get().to(query_payment_stream),
+        )
+        .route(
+            &ApiRoutes::DustLimits.route(),
+            web::get().to(query_dust_limits),
+        )
+        .route(
+            &ApiRoutes::DesignatedTransfer.with_arg_template("id"),
+            web::get().to(query_designated_transfer),
+        )
+        .route(
+            &ApiRoutes::SpendingPolicy.with_arg_template("address"),
+            web::get().to(query_spending_policy),
+        )
+        .route(
+            &ApiRoutes::SessionKeys.with_arg_template("address"),
+            web::get().to(query_session_keys),
+        )
+        .route(
+            &ApiRoutes::Vesting.with_arg_template("address"),
+            web::get().to(query_vesting),
+        )
+        .route(
+            &ApiRoutes::Delegations.with_arg_template("address"),
+            web::get().to(query_delegations),
+        )
+        .route(
+            &ApiRoutes::Unbondings.with_arg_template("address"),
+            web::get().to(query_unbondings),
+        )
+        .route(
+            &ApiRoutes::DelegatorRewards.with_arg_template("address"),
+            web::get().to(query_delegator_rewards),
+        )
+        .route(
+            &ApiRoutes::ValidatorSet.route(),
+            web::get().to(query_validator_set),
+        )
+        .route(
+            &ApiRoutes::EpochRewards.with_arg_template("epoch"),
+            web::get().to(query_epoch_rewards),
+        )
+        .route(
+            &ApiRoutes::StateChecksums.route(),
+            web::get().to(query_state_checksums),
+        )
+        .service(
+            web::resource("/display_checkpoint").route(web::get().to(get_checkpoint)),
+        );
+}
@@ -0,0 +1,106 @@
+//!
+//! # Namespaces of the query server
+//!
+//! The ledgers of other networks, eg. a testnet next to a local dev chain,
+//! served by the same query server under `/<name>`, eg. `/testnet/global_state`.
+//!
+//! Each one has its own ledger in `<ledger-dir>/namespaces/<name>` and its own
+//! query cache, fed by the block archive recorded by a node of the network,
+//! see `abcid --block-archive` and `abcid --query-namespaces`.
+//!
+
+use {
+    super::server::QueryServer,
+    config::abci::global_cfg::CFG,
+    ledger::store::{archive, LedgerState},
+    parking_lot::RwLock,
+    ruc::*,
+    std::{fs, sync::Arc, thread},
+};
+
+/// How often the archives are polled for new blocks
+pub const FOLLOW_ITV_MS: u64 = 1000;
+
+/// A ledger served under `/<name>`
+pub struct Namespace {
+    #[allow(missing_docs)]
+    pub name: String,
+    #[allow(missing_docs)]
+    pub server: Arc<RwLock<QueryServer>>,
+}
+
+/// Open the namespace `name`, load the blocks of the archive in `archive_path`
+/// it misses, then follow the archive in a thread.
+///
+/// The ledger is kept across restarts, remove its directory to rebuild it,
+/// eg. after a divergence from the archive.
+pub fn open(name: &str, archive_path: &str) -> Result<Namespace> {
+    let basedir = format!("{}/namespaces/{}", CFG.ledger_dir, name);
+    let mut ledger =
+        LedgerState::new(&basedir, Some(&format!("ns_{}", name))).c(d!())?;
+
+    let mut offset = catch_up(name, &mut ledger, archive_path, &basedir, 0)
+        .c(d!())?
+        .map(|(offset, _)| offset);
+    let ledger = Arc::new(RwLock::new(ledger));
+    let server = Arc::new(RwLock::new(QueryServer::new(Arc::clone(&ledger))));
+
+    let ns = Namespace {
+        name: name.to_owned(),
+        server: Arc::clone(&server),
+    };
+    let name = name.to_owned();
+    let archive_path = archive_path.to_owned();
+    thread::spawn(move || {
+        while let Some(o) = offset {
+            sleep_ms!(FOLLOW_ITV_MS);
+            let res = catch_up(&name, &mut ledger.write(), &archive_path, &basedir, o);
+            match res {
+                Ok(Some((o, blocks))) => {
+                    if 0 < blocks {
+                        server.write().update();
+                    }
+                    offset = Some(o);
+                }
+                Ok(None) => offset = None,
+                Err(e) => e.print(None),
+            }
+        }
+    });
+
+    Ok(ns)
+}
+
+// replay the new blocks of the archive, returns the offset to resume from
+// and the number of blocks replayed, or `None` if the ledger has diverged
+fn catch_up(
+    name: &str,
+    ledger: &mut LedgerState,
+    archive_path: &str,
+    basedir: &str,
+    offset: u64,
+) -> Result<Option<(u64, u64)>> {
+    let (report, offset) = archive::catch_up(ledger, archive_path, offset).c(d!())?;
+
+    if let Some(d) = report.divergence {
+        log::error!(
+            target: "abciapp",
+            "namespace {} diverged from {} at height {}: {:?} {}, no longer followed",
+            name,
+            archive_path,
+            d.height,
+            d.fields,
+            d.error.unwrap_or_default()
+        );
+        return Ok(None);
+    }
+
+    if 0 < report.blocks {
+        let path = format!("{}/{}", basedir, ledger.get_status().snapshot_file);
+        serde_json::to_vec(ledger.get_status())
+            .c(d!())
+            .and_then(|s| fs::write(&path, s).c(d!(path)))?;
+    }
+
+    Ok(Some((offset, report.blocks)))
+}
@@ -10,7 +10,10 @@ use {
             TxnIDHash, TxnSID, TxoSID, XfrAddress,
         },
         staking::{ops::mint_fra::MintEntry, BlockHeight},
-        store::LedgerState,
+        store::{
+            api_cache::{AssetStats, FeeHistory, SearchQuery, SearchResult},
+            LedgerState,
+        },
     },
     parking_lot::{Condvar, Mutex, RwLock},
     ruc::*,
@@ -69,6 +72,44 @@ impl QueryServer {
             .get(code)
     }
 
+    /// Search the indexed ledger data, see `SearchQuery`.
+    #[inline(always)]
+    pub fn search(&self, q: &SearchQuery) -> Result<SearchResult> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .c(d!("the history of the ledger is not kept"))
+            .and_then(|c| c.search(q).c(d!()))
+    }
+
+    /// The statistics of an asset over the blocks `[from, to]`,
+    /// up to the current height by default.
+    pub fn get_asset_stats(
+        &self,
+        code: &AssetTypeCode,
+        from: Option<BlockHeight>,
+        to: Option<BlockHeight>,
+    ) -> Result<Option<AssetStats>> {
+        let to = to.unwrap_or_else(|| self.ledger_cloned.get_tendermint_height());
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .c(d!("the history of the ledger is not kept"))
+            .and_then(|c| c.asset_stats(code, from.unwrap_or(0), to).c(d!()))
+    }
+
+    /// The fees of the last `blocks` blocks with transactions,
+    /// see `ApiCache::fee_history`.
+    #[inline(always)]
+    pub fn get_fee_history(&self, blocks: Option<u64>) -> Result<FeeHistory> {
+        let to = self.ledger_cloned.get_tendermint_height();
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .c(d!("the history of the ledger is not kept"))
+            .map(|c| c.fee_history(to, blocks))
+    }
+
     /// return `DefineAsset` according to `IssuerPublicKey`
     #[inline(always)]
     pub fn get_created_assets(
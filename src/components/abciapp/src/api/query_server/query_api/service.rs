@@ -4,32 +4,49 @@
 
 use {
     super::{
+        namespace,
         server::{QueryServer, BLOCK_CREATED},
         QueryApi,
     },
+    config::abci::ApiServerConfig,
     ledger::store::LedgerState,
     parking_lot::RwLock,
     ruc::*,
     std::{sync::Arc, thread},
 };
 
+/// `namespaces` are the names and the block archives of the ledgers
+/// of other networks, see `namespace`.
 pub(crate) fn start_query_server(
     ledger: Arc<RwLock<LedgerState>>,
+    namespaces: &[(String, String)],
     addrs: &[(&str, u16)],
+    cfg: ApiServerConfig,
 ) -> Result<Arc<RwLock<QueryServer>>> {
     let qs = Arc::new(RwLock::new(QueryServer::new(ledger)));
     let qs1 = Arc::clone(&qs);
     let qs2 = Arc::clone(&qs);
 
-    QueryApi::create(qs1, addrs).c(d!()).map(|_| {
-        thread::spawn(move || loop {
-            let mut created = BLOCK_CREATED.0.lock();
-            if !*created {
-                BLOCK_CREATED.1.wait(&mut created);
-            }
-            qs2.write().update();
-            *created = false;
-        });
-        qs
-    })
+    let namespaces = namespaces
+        .iter()
+        .map(|(name, archive)| {
+            namespace::open(name, archive)
+                .c(d!(format!("namespace {}", name)))
+                .map(|ns| (ns.name, ns.server))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    QueryApi::create(qs1, namespaces, addrs, cfg)
+        .c(d!())
+        .map(|_| {
+            thread::spawn(move || loop {
+                let mut created = BLOCK_CREATED.0.lock();
+                if !*created {
+                    BLOCK_CREATED.1.wait(&mut created);
+                }
+                qs2.write().update();
+                *created = false;
+            });
+            qs
+        })
 }
@@ -0,0 +1,187 @@
+//!
+//! # Access lists of the submissions
+//!
+//! The IPs and keys allowed or denied to submit transactions, see `SubmissionAccess`,
+//! refused attempts are logged under the `audit` target.
+//!
+
+use {
+    config::abci::{RuntimeConfig, SubmissionAccess},
+    globutils::address,
+    ruc::*,
+    std::net::IpAddr,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// Check the lists of `SubmissionAccess`, `what` describes the submission
+/// in the audit logs, `signed_by` tells if it is signed by a key.
+pub fn check_access<F>(ip: Option<IpAddr>, what: &str, signed_by: F) -> Result<()>
+where
+    F: Fn(&XfrPublicKey) -> bool,
+{
+    let access = RuntimeConfig::current().submission_access;
+    check(&access, ip, &signed_by).map_err(|e| {
+        log::warn!(
+            target: "audit",
+            "submission refused: {}, from {}, {}",
+            what,
+            ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_owned()),
+            e
+        );
+        e
+    })
+}
+
+/// Check the format of the IPs and keys in `access`.
+pub fn validate(access: &SubmissionAccess) -> Result<()> {
+    let localhost = IpAddr::from([127, 0, 0, 1]);
+    for rule in access.allow_ips.iter().chain(access.deny_ips.iter()) {
+        ip_matches(rule, localhost).c(d!())?;
+    }
+    for key in access.allow_keys.iter().chain(access.deny_keys.iter()) {
+        address::parse_address(key).c(d!())?;
+    }
+    Ok(())
+}
+
+fn check(
+    access: &SubmissionAccess,
+    ip: Option<IpAddr>,
+    signed_by: &dyn Fn(&XfrPublicKey) -> bool,
+) -> Result<()> {
+    let ip_in = |rules: &[String]| {
+        ip.map(|ip| rules.iter().any(|r| ip_matches(r, ip).unwrap_or(false)))
+            .unwrap_or(false)
+    };
+    let signed_by_any = |keys: &[String]| {
+        keys.iter()
+            .filter_map(|k| address::parse_address(k).ok())
+            .any(|pk| signed_by(&pk))
+    };
+
+    if ip_in(&access.deny_ips) {
+        return Err(eg!("denied IP"));
+    }
+    if !access.allow_ips.is_empty() && !ip_in(&access.allow_ips) {
+        return Err(eg!("IP not allowed"));
+    }
+    if signed_by_any(&access.deny_keys) {
+        return Err(eg!("denied key"));
+    }
+    if !access.allow_keys.is_empty() && !signed_by_any(&access.allow_keys) {
+        return Err(eg!("not signed by an allowed key"));
+    }
+    Ok(())
+}
+
+// whether `ip` is `rule`, or in it if `rule` is a CIDR block
+fn ip_matches(rule: &str, ip: IpAddr) -> Result<bool> {
+    let (addr, prefix_len) = match rule.trim().split_once('/') {
+        Some((addr, len)) => (addr, Some(len.parse::<u32>().c(d!(rule.to_owned()))?)),
+        None => (rule.trim(), None),
+    };
+    let addr = addr.parse::<IpAddr>().c(d!(rule.to_owned()))?;
+
+    macro_rules! in_block {
+        ($a: expr, $b: expr, $ty: ty) => {{
+            let bits = <$ty>::BITS;
+            let len = prefix_len.unwrap_or(bits);
+            if len > bits {
+                return Err(eg!(format!("invalid prefix length: {}", rule)));
+            }
+            let mask = <$ty>::MAX.checked_shl(bits - len).unwrap_or(0);
+            Ok(<$ty>::from($a) & mask == <$ty>::from($b) & mask)
+        }};
+    }
+
+    match (addr, ip) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => in_block!(a, b, u32),
+        (IpAddr::V6(a), IpAddr::V6(b)) => in_block!(a, b, u128),
+        (IpAddr::V4(a), IpAddr::V6(b)) => match b.to_ipv4() {
+            // an IPv4 client of a dual-stack listener
+            Some(b) => in_block!(a, b, u32),
+            None => Ok(false),
+        },
+        (IpAddr::V6(_), IpAddr::V4(_)) => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*, globutils::wallet, rand_chacha::ChaChaRng, rand_core::SeedableRng,
+        zei::xfr::sig::XfrKeyPair,
+    };
+
+    fn ip(s: &str) -> IpAddr {
+        pnk!(s.parse::<IpAddr>())
+    }
+
+    fn ips(rules: &[&str]) -> Vec<String> {
+        rules.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn ip_matches_cidr() {
+        // the bounds of a block
+        assert!(pnk!(ip_matches("10.1.2.0/24", ip("10.1.2.0"))));
+        assert!(pnk!(ip_matches("10.1.2.0/24", ip("10.1.2.255"))));
+        assert!(!pnk!(ip_matches("10.1.2.0/24", ip("10.1.3.0"))));
+        assert!(!pnk!(ip_matches("10.1.2.0/24", ip("10.1.1.255"))));
+
+        // /0 for all, /32 for a single IP, as without a prefix
+        assert!(pnk!(ip_matches("0.0.0.0/0", ip("203.0.113.7"))));
+        assert!(pnk!(ip_matches("203.0.113.7/32", ip("203.0.113.7"))));
+        assert!(!pnk!(ip_matches("203.0.113.7/32", ip("203.0.113.8"))));
+        assert!(pnk!(ip_matches("203.0.113.7", ip("203.0.113.7"))));
+        assert!(ip_matches("10.0.0.0/33", ip("10.0.0.1")).is_err());
+
+        assert!(pnk!(ip_matches("2001:db8::/32", ip("2001:db8:ffff::1"))));
+        assert!(!pnk!(ip_matches("2001:db8::/32", ip("2001:db9::"))));
+        assert!(pnk!(ip_matches("::/0", ip("2001:db9::1"))));
+        assert!(pnk!(ip_matches("2001:db8::1/128", ip("2001:db8::1"))));
+        assert!(!pnk!(ip_matches("2001:db8::1/128", ip("2001:db8::2"))));
+        assert!(ip_matches("2001:db8::/129", ip("2001:db8::1")).is_err());
+
+        // an IPv4 client of a dual-stack listener
+        assert!(pnk!(ip_matches("10.1.2.0/24", ip("::ffff:10.1.2.3"))));
+        assert!(!pnk!(ip_matches("2001:db8::/32", ip("10.1.2.3"))));
+
+        assert!(ip_matches("10.1.2.x/24", ip("10.1.2.3")).is_err());
+    }
+
+    #[test]
+    fn check_lists() {
+        let unsigned = |_: &XfrPublicKey| false;
+
+        // anything is allowed by empty lists
+        let mut access = SubmissionAccess::default();
+        pnk!(check(&access, Some(ip("203.0.113.7")), &unsigned));
+        pnk!(check(&access, None, &unsigned));
+
+        // a denied IP is refused even if allowed
+        access.allow_ips = ips(&["10.0.0.0/8"]);
+        access.deny_ips = ips(&["10.6.0.0/16"]);
+        pnk!(check(&access, Some(ip("10.1.0.1")), &unsigned));
+        assert!(check(&access, Some(ip("10.6.0.1")), &unsigned).is_err());
+        assert!(check(&access, Some(ip("192.168.0.1")), &unsigned).is_err());
+        // the IP is required by an allowlist
+        assert!(check(&access, None, &unsigned).is_err());
+
+        // so are the keys
+        let mut prng = ChaChaRng::from_seed([0; 32]);
+        let allowed = XfrKeyPair::generate(&mut prng).get_pk();
+        let denied = XfrKeyPair::generate(&mut prng).get_pk();
+        let access = SubmissionAccess {
+            allow_keys: vec![
+                wallet::public_key_to_bech32(&allowed),
+                wallet::public_key_to_bech32(&denied),
+            ],
+            deny_keys: vec![wallet::public_key_to_bech32(&denied)],
+            ..Default::default()
+        };
+        pnk!(check(&access, None, &|pk| *pk == allowed));
+        assert!(check(&access, None, &|pk| *pk == allowed || *pk == denied).is_err());
+        assert!(check(&access, None, &unsigned).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//!
+//! # Double spends in the mempool
+//!
+//! The outputs spent by the transactions in the mempool of tendermint, so `check_tx`
+//! rejects a transaction spending one of them again at once, naming the pending
+//! transaction it conflicts with, instead of letting it fail in the block.
+//!
+//! The claims are dropped on each commit, and taken again by the rechecks of the
+//! transactions left in the mempool, so `recheck` of tendermint should stay on.
+//!
+//! The accepted transactions not delivered yet are counted the same way,
+//! the depth of the mempool recorded in the fee history on each commit.
+//!
+
+#[cfg(test)]
+mod test;
+
+use {
+    lazy_static::lazy_static,
+    ledger::data_model::{
+        Operation, Transaction, TxnCheckCode, TxnCheckError, TxoRef, TxoSID,
+    },
+    parking_lot::Mutex,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{HashMap, HashSet, VecDeque},
+        sync::atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// How many of the latest conflicts are kept for `stats`
+pub const MAX_RECENT_CONFLICTS: usize = 64;
+
+static DOUBLE_SPENDS: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    // the handles of the pending transactions by the outputs they spend
+    static ref CLAIMS: Mutex<HashMap<TxoSID, String>> = Mutex::new(HashMap::new());
+    // the handles of the accepted transactions, not delivered yet
+    static ref PENDING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    static ref RECENT_CONFLICTS: Mutex<VecDeque<Conflict>> =
+        Mutex::new(VecDeque::new());
+}
+
+/// A transaction rejected for spending the output of a pending one
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Conflict {
+    #[allow(missing_docs)]
+    pub txo_sid: TxoSID,
+    /// the handle of the rejected transaction
+    pub handle: String,
+    /// the handle of the pending transaction spending the same output
+    pub conflicting_handle: String,
+    /// the tendermint height of the rejection
+    pub height: u64,
+}
+
+/// The double spends seen by the mempool
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MempoolStats {
+    /// rejected since the start of the node
+    pub double_spends: u64,
+    /// the number of outputs spent by the pending transactions
+    pub pending_spends: usize,
+    /// the number of the pending transactions
+    pub pending_txns: usize,
+    /// the latest ones, the newest first
+    pub recent_conflicts: Vec<Conflict>,
+}
+
+/// Claim the outputs spent by `tx` at the tendermint height `height`,
+/// nothing is claimed if one of them is claimed by another transaction.
+pub fn claim(tx: &Transaction, height: u64) -> Result<(), TxnCheckError> {
+    let handle = tx.handle();
+    let inputs = spent_txos(tx);
+
+    let mut claims = CLAIMS.lock();
+    let conflict = inputs.iter().find_map(|sid| {
+        claims
+            .get(sid)
+            .filter(|h| **h != handle)
+            .map(|h| (*sid, h.clone()))
+    });
+    if let Some((txo_sid, conflicting_handle)) = conflict {
+        drop(claims);
+        DOUBLE_SPENDS.fetch_add(1, Ordering::Relaxed);
+        let msg = format!(
+            "output {} is spent by the pending transaction {}",
+            txo_sid.0, conflicting_handle
+        );
+        let mut recent = RECENT_CONFLICTS.lock();
+        recent.push_front(Conflict {
+            txo_sid,
+            handle,
+            conflicting_handle,
+            height,
+        });
+        recent.truncate(MAX_RECENT_CONFLICTS);
+        return Err(TxnCheckError::new(TxnCheckCode::DoubleSpend, None, msg));
+    }
+
+    inputs.into_iter().for_each(|sid| {
+        claims.insert(sid, handle.clone());
+    });
+    drop(claims);
+    PENDING.lock().insert(handle);
+    Ok(())
+}
+
+/// `tx` is no longer pending, on its delivery in a block.
+#[inline(always)]
+pub fn delivered(tx: &Transaction) {
+    PENDING.lock().remove(&tx.handle());
+}
+
+/// The number of the transactions accepted and not delivered yet,
+/// ie. left in the mempool on a commit.
+#[inline(always)]
+pub fn depth() -> usize {
+    PENDING.lock().len()
+}
+
+/// Drop all claims, on the commit of a block.
+#[inline(always)]
+pub fn reset() {
+    CLAIMS.lock().clear();
+    PENDING.lock().clear();
+}
+
+#[allow(missing_docs)]
+pub fn stats() -> MempoolStats {
+    MempoolStats {
+        double_spends: DOUBLE_SPENDS.load(Ordering::Relaxed),
+        pending_spends: CLAIMS.lock().len(),
+        pending_txns: depth(),
+        recent_conflicts: RECENT_CONFLICTS.lock().iter().cloned().collect(),
+    }
+}
+
+// the outputs of the ledger spent by `tx`, only transfers spend them
+fn spent_txos(tx: &Transaction) -> Vec<TxoSID> {
+    tx.body
+        .operations
+        .iter()
+        .filter_map(|op| match op {
+            Operation::TransferAsset(t) => Some(t.body.inputs.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|i| match i {
+            TxoRef::Absolute(sid) => Some(*sid),
+            _ => None,
+        })
+        .collect()
+}
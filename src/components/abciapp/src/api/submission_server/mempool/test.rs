@@ -0,0 +1,93 @@
+#![allow(missing_docs)]
+
+use {
+    super::*,
+    finutils::txn_builder::{TransactionBuilder, TransferOperationBuilder},
+    ledger::data_model::{TransferType, ASSET_TYPE_FRA},
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    zei::xfr::{
+        asset_record::AssetRecordType,
+        sig::XfrKeyPair,
+        structs::{AssetRecord, AssetRecordTemplate},
+    },
+};
+
+// a transfer spending the outputs `sids` of the ledger, only its inputs matter
+fn spend(prng: &mut ChaChaRng, kp: &XfrKeyPair, sids: &[u64]) -> Transaction {
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        100,
+        ASSET_TYPE_FRA,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        kp.get_pk(),
+    );
+    let mut trans_builder = TransferOperationBuilder::new();
+    for sid in sids.iter() {
+        let ar = pnk!(AssetRecord::from_template_no_identity_tracing(
+            prng, &template
+        ));
+        pnk!(trans_builder.add_input(
+            TxoRef::Absolute(TxoSID(*sid)),
+            ar.open_asset_record,
+            None,
+            None,
+            100
+        ));
+        pnk!(trans_builder.add_output(&template, None, None, None));
+    }
+    let op = pnk!(trans_builder
+        .create(TransferType::Standard)
+        .c(d!())
+        .and_then(|b| b.sign(kp).c(d!()))
+        .and_then(|b| b.transaction().c(d!())));
+
+    let mut builder = TransactionBuilder::from_seq_id(0);
+    builder.add_operation(op).sign(kp);
+    builder.take_transaction()
+}
+
+// the claims are global, all the steps in a single test
+#[test]
+fn mempool_double_spends() {
+    let mut prng = ChaChaRng::from_entropy();
+    let kp = XfrKeyPair::generate(&mut prng);
+    let a = spend(&mut prng, &kp, &[1, 2]);
+    let b = spend(&mut prng, &kp, &[2, 3]);
+    let c = spend(&mut prng, &kp, &[3]);
+    reset();
+    let double_spends = stats().double_spends;
+
+    pnk!(claim(&a, 1));
+    // the recheck of the same transaction
+    pnk!(claim(&a, 1));
+
+    let e = claim(&b, 1).unwrap_err();
+    assert_eq!(TxnCheckCode::DoubleSpend, e.code);
+    // nothing of `b` is claimed
+    pnk!(claim(&c, 1));
+
+    let s = stats();
+    assert_eq!(1 + double_spends, s.double_spends);
+    assert_eq!(3, s.pending_spends);
+    assert_eq!(2, s.pending_txns);
+    let conflict = &s.recent_conflicts[0];
+    assert_eq!(TxoSID(2), conflict.txo_sid);
+    assert_eq!(b.handle(), conflict.handle);
+    assert_eq!(a.handle(), conflict.conflicting_handle);
+    assert_eq!(1, conflict.height);
+
+    // `a` is in the block, `c` is left in the mempool
+    delivered(&a);
+    assert_eq!(1, depth());
+    reset();
+    assert_eq!(0, depth());
+    assert_eq!(0, stats().pending_spends);
+
+    // claimed again by the recheck of `c`, `b` still conflicts with it
+    pnk!(claim(&c, 2));
+    let e = claim(&b, 2).unwrap_err();
+    assert_eq!(TxnCheckCode::DoubleSpend, e.code);
+    assert_eq!(c.handle(), stats().recent_conflicts[0].conflicting_handle);
+    assert_eq!(1, depth());
+}
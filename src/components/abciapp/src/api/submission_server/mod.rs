@@ -2,21 +2,41 @@
 //! # service of operating tx
 //!
 
+pub mod access;
+pub mod mempool;
+pub mod ordering;
 pub mod submission_api;
 
 use {
     fp_utils::tx::EVM_TX_TAG,
     ledger::{
-        data_model::{BlockEffect, Transaction, TxnEffect, TxnSID, TxnTempSID, TxoSID},
+        data_model::{
+            BlockEffect, CancelTxn, Transaction, TxnCheckCode, TxnCheckError, TxnEffect,
+            TxnSID, TxnTempSID, TxoSID,
+        },
         store::LedgerState,
     },
+    ordering::{apply_ordering, Fifo, OrderingPolicy},
     parking_lot::RwLock,
     rand_core::{CryptoRng, RngCore},
     ruc::*,
     serde::{Deserialize, Serialize},
-    std::{collections::HashMap, fmt, sync::Arc},
+    std::{collections::HashMap, fmt, mem, sync::Arc},
 };
 
+/// How many blocks a transaction waits for its parents, see `TxoRef::Pending`
+pub const MAX_WAITING_BLOCKS: u64 = 32;
+
+/// How many transactions can wait for their parents at the same time
+pub const MAX_WAITING_TXNS: usize = 1024;
+
+/// How many blocks submissions are remembered, duplicates submitted meanwhile
+/// get the handles of the originals, see `handle_transaction_with_id`
+pub const DEDUP_BLOCKS: u64 = 128;
+
+/// Max length of the request ids of the clients
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
 /// Query handle for user
 #[derive(Debug, Hash, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TxnHandle(pub String);
@@ -38,9 +58,26 @@ impl fmt::Display for TxnHandle {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[allow(missing_docs)]
 pub enum TxnStatus {
-    Rejected(String),
+    /// With the code, the offending operation if known, and the details
+    Rejected(TxnCheckError),
     Committed((TxnSID, Vec<TxoSID>)),
+    /// In the block being built, or forwarded to the mempool after waiting
     Pending,
+    /// Not forwarded yet, the outputs of these transactions are spent by it
+    Waiting(Vec<TxnHandle>),
+    /// Cancelled by its submitter before being forwarded
+    Cancelled,
+    /// Replaced by its submitter before being forwarded
+    Replaced(TxnHandle),
+}
+
+// A transaction waiting for the commitment of its parents
+struct WaitingTxn {
+    handle: TxnHandle,
+    parents: Vec<[u8; 32]>,
+    txn: Transaction,
+    // the tendermint height when it was submitted
+    since: u64,
 }
 
 /// use to create submissionServer
@@ -71,10 +108,16 @@ where
     block: Option<BlockEffect>,
     pending_txns: Vec<(TxnTempSID, TxnHandle, Transaction)>,
     txn_status: HashMap<TxnHandle, TxnStatus>,
+    waiting_txns: Vec<WaitingTxn>,
+    // forwarded transactions not in a block yet, with the heights of their forwarding
+    forwarded: HashMap<TxnHandle, u64>,
+    // handles by the request ids of the clients, with the heights of their submissions
+    request_ids: HashMap<String, (TxnHandle, u64)>,
     block_capacity: usize,
     prng: RNG,
     commit_mode: CommitMode,
     txn_forwarder: TF,
+    ordering: Box<dyn OrderingPolicy>,
 }
 
 impl<RNG, TF> SubmissionServer<RNG, TF>
@@ -94,10 +137,14 @@ where
             block: None,
             txn_status: HashMap::new(),
             pending_txns: vec![],
+            waiting_txns: vec![],
+            forwarded: HashMap::new(),
+            request_ids: HashMap::new(),
             prng,
             block_capacity,
             commit_mode: CommitMode::FullBlock,
             txn_forwarder,
+            ordering: Box::new(Fifo),
         })
     }
 
@@ -112,13 +159,29 @@ where
             block: None,
             txn_status: HashMap::new(),
             pending_txns: vec![],
+            waiting_txns: vec![],
+            forwarded: HashMap::new(),
+            request_ids: HashMap::new(),
             prng,
             block_capacity: 0,
             commit_mode: CommitMode::Manual,
             txn_forwarder,
+            ordering: Box::new(Fifo),
         })
     }
 
+    /// Replace the ordering policy of the batches of transactions, `Fifo` by default,
+    /// only the waiting transactions released together are ordered by it,
+    /// see `handle_transaction`
+    pub fn set_ordering_policy(&mut self, policy: Box<dyn OrderingPolicy>) {
+        self.ordering = policy;
+    }
+
+    /// Reorder a batch of transactions entering a block by the ordering policy
+    pub fn order_txns(&self, txns: Vec<Transaction>) -> Vec<Transaction> {
+        apply_ordering(&*self.ordering, txns, |tx| tx)
+    }
+
     /// Query operation results
     pub fn get_txn_status(&self, txn_handle: &TxnHandle) -> Option<TxnStatus> {
         self.txn_status.get(txn_handle).cloned()
@@ -178,7 +241,7 @@ where
 
     /// In abci's end_block, this method will be called
     /// if the block is not empty and the block in the submission_server is not empty,
-    /// it is the logic to write the block to the ledgerState
+    /// it is the logic to write the block to the ledgerState, see `after_block`
     pub fn end_block(&mut self) -> Result<()> {
         if let Some(block) = self.block.take() {
             let mut ledger = self.committed_state.write();
@@ -198,6 +261,31 @@ where
         Err(eg!("Cannot finish block because there are no pending txns"))
     }
 
+    /// In abci's end_block, this method will be called on every block, empty or not,
+    /// after `end_block` if any: the waiting transactions whose parents are committed
+    /// are forwarded, the ones which waited too long are rejected.
+    pub fn after_block(&mut self) {
+        let height = self.committed_state.read().get_tendermint_height();
+
+        // the ones forwarded but not in a block in time can be submitted again
+        let expired = self
+            .forwarded
+            .iter()
+            .filter(|(_, since)| height > **since + DEDUP_BLOCKS)
+            .map(|(h, _)| h.clone())
+            .collect::<Vec<_>>();
+        for h in expired.iter() {
+            self.forwarded.remove(h);
+            if let Some(TxnStatus::Pending) = self.txn_status.get(h) {
+                self.txn_status.remove(h);
+            }
+        }
+        self.request_ids
+            .retain(|_, (_, since)| height <= *since + DEDUP_BLOCKS);
+
+        self.forward_waiting_txns();
+    }
+
     /// Get txs number of pending
     pub fn block_txn_count(&self) -> usize {
         self.pending_txns.len()
@@ -215,13 +303,20 @@ where
         let mut block = self.block.as_mut().unwrap();
         let ledger = self.committed_state.read();
         let handle = TxnHandle::new(&txn);
-        let temp_sid = TxnEffect::compute_effect(txn.clone())
-            .c(d!("Failed to compute txn effect"))
-            .and_then(|txn_effect| {
-                ledger
-                    .apply_transaction(&mut block, txn_effect)
-                    .c(d!("Failed to apply transaction"))
-            });
+        self.forwarded.remove(&handle);
+        let temp_sid = if txn.is_expired(ledger.get_tendermint_height() + 1) {
+            Err(rejection(
+                TxnCheckCode::Expired,
+                "Transaction expired".to_owned(),
+            ))
+        } else {
+            TxnEffect::check_stateless(txn.clone())
+        }
+        .and_then(|txn_effect| {
+            ledger
+                .apply_transaction(&mut block, txn_effect)
+                .map_err(|e| rejection(TxnCheckCode::InvalidState, e.to_string()))
+        });
         match temp_sid {
             Ok(temp_sid) => {
                 self.pending_txns.push((temp_sid, handle.clone(), txn));
@@ -229,26 +324,259 @@ where
                 Ok(handle)
             }
             Err(e) => {
-                self.txn_status
-                    .insert(handle, TxnStatus::Rejected(e.to_string()));
-                Err(e)
+                let msg = e.to_string();
+                self.txn_status.insert(handle, TxnStatus::Rejected(e));
+                Err(eg!(msg))
             }
         }
     }
 
     /// Handle the whole process when there's a new transaction
+    ///
+    /// A transaction spending the outputs of uncommitted ones, by `TxoRef::Pending`,
+    /// waits for their commitment, at most `MAX_WAITING_BLOCKS` blocks,
+    /// it is rejected if one of them is rejected. The waiting transactions are
+    /// forwarded together by `after_block`, in the order of the ordering policy,
+    /// the other ones are forwarded on arrival.
+    ///
+    /// A transaction already waiting, forwarded or in a block is not handled again,
+    /// its handle is returned.
     pub fn handle_transaction(&mut self, txn: Transaction) -> Result<TxnHandle> {
         let txn_handle = TxnHandle::new(&txn);
-        self.txn_forwarder.forward_txn(txn).c(d!())?;
+        if self.is_duplicate(&txn_handle) {
+            return Ok(txn_handle);
+        }
+
+        let ledger = self.committed_state.read();
+        let since = ledger.get_tendermint_height();
+        let mut parents = vec![];
+        for p in txn.pending_parents() {
+            if ledger.get_status().get_recent_txn_outputs(&p).is_some() {
+                continue;
+            }
+            match self.txn_status.get(&parent_handle(&p)) {
+                Some(TxnStatus::Rejected(_)) => {
+                    return Err(eg!("parent transaction rejected"));
+                }
+                Some(TxnStatus::Committed(_)) => {
+                    return Err(eg!("parent transaction committed too long ago"));
+                }
+                _ => parents.push(p),
+            }
+        }
+        drop(ledger);
+
+        if parents.is_empty() {
+            self.txn_forwarder.forward_txn(txn).c(d!())?;
+            self.forwarded.insert(txn_handle.clone(), since);
+        } else {
+            if self.waiting_txns.len() >= MAX_WAITING_TXNS {
+                return Err(eg!("too many transactions waiting for their parents"));
+            }
+            self.txn_status.insert(
+                txn_handle.clone(),
+                TxnStatus::Waiting(parents.iter().map(parent_handle).collect()),
+            );
+            self.waiting_txns.push(WaitingTxn {
+                handle: txn_handle.clone(),
+                parents,
+                txn,
+                since,
+            });
+        }
+
         Ok(txn_handle)
     }
 
+    /// Handle a transaction along with a request id chosen by the client,
+    /// see `handle_transaction`.
+    ///
+    /// Retries with the same id in `DEDUP_BLOCKS` blocks get the handle
+    /// of the first submission, the id can not be used by another transaction.
+    pub fn handle_transaction_with_id(
+        &mut self,
+        txn: Transaction,
+        request_id: Option<String>,
+    ) -> Result<TxnHandle> {
+        let id = match request_id {
+            Some(id) => id,
+            None => return self.handle_transaction(txn).c(d!()),
+        };
+        if id.is_empty() || id.len() > MAX_REQUEST_ID_LEN {
+            return Err(eg!("invalid length of the request id"));
+        }
+
+        let txn_handle = TxnHandle::new(&txn);
+        if let Some((h, _)) = self.request_ids.get(&id) {
+            if *h != txn_handle {
+                return Err(eg!("the request id is used by another transaction"));
+            }
+            return Ok(txn_handle);
+        }
+
+        self.handle_transaction(txn).c(d!())?;
+        let height = self.committed_state.read().get_tendermint_height();
+        self.request_ids.insert(id, (txn_handle.clone(), height));
+        Ok(txn_handle)
+    }
+
+    // Whether the transaction is waiting for its parents, forwarded, or in a block,
+    // rejected or cancelled ones can be submitted again.
+    fn is_duplicate(&self, handle: &TxnHandle) -> bool {
+        self.forwarded.contains_key(handle)
+            || matches!(
+                self.txn_status.get(handle),
+                Some(TxnStatus::Waiting(_))
+                    | Some(TxnStatus::Pending)
+                    | Some(TxnStatus::Committed(_))
+            )
+    }
+
+    /// Cancel or replace a transaction waiting for its parents,
+    /// returns the handle of the replacement if any.
+    ///
+    /// Only the waiting transactions are held by the submission server, the ones
+    /// forwarded to the mempool of tendermint, maybe gossiped to other nodes already,
+    /// can not be cancelled anymore.
+    ///
+    /// The replacement must be signed by the same submitter,
+    /// and its fee must not be lower than the one of the cancelled transaction.
+    pub fn cancel_transaction(&mut self, req: CancelTxn) -> Result<Option<TxnHandle>> {
+        let handle = TxnHandle(req.body.handle.clone());
+        let idx = match self.waiting_txns.iter().position(|w| w.handle == handle) {
+            Some(idx) => idx,
+            None if self.forwarded.contains_key(&handle) => {
+                return Err(eg!(
+                    "forwarded to the mempool, only waiting transactions can be cancelled"
+                ));
+            }
+            None => {
+                return Err(eg!(match self.txn_status.get(&handle) {
+                    Some(TxnStatus::Pending) | Some(TxnStatus::Committed(_)) => {
+                        "the transaction has entered a block"
+                    }
+                    Some(TxnStatus::Rejected(_)) => "the transaction has been rejected",
+                    Some(TxnStatus::Cancelled) | Some(TxnStatus::Replaced(_)) => {
+                        "the transaction has been cancelled"
+                    }
+                    _ => "unknown transaction, or forwarded to the mempool",
+                }));
+            }
+        };
+
+        let w = &self.waiting_txns[idx];
+        req.verify(&w.txn).c(d!())?;
+        if let Some(tx) = req.body.replacement.as_ref() {
+            req.verify(tx).c(d!("replacement"))?;
+            if tx.fee() < w.txn.fee() {
+                return Err(eg!("the fee of the replacement is lower"));
+            }
+        }
+
+        let w = self.waiting_txns.remove(idx);
+        match req.body.replacement {
+            Some(tx) => {
+                if TxnHandle::new(&tx) == handle {
+                    self.waiting_txns.insert(idx, w);
+                    return Err(eg!("the replacement is the same transaction"));
+                }
+                match self.handle_transaction(tx) {
+                    Ok(new_handle) => {
+                        self.txn_status
+                            .insert(handle, TxnStatus::Replaced(new_handle.clone()));
+                        Ok(Some(new_handle))
+                    }
+                    Err(e) => {
+                        self.waiting_txns.insert(idx, w);
+                        Err(e).c(d!())
+                    }
+                }
+            }
+            None => {
+                self.txn_status.insert(handle, TxnStatus::Cancelled);
+                Ok(None)
+            }
+        }
+    }
+
+    // Forward the waiting transactions whose parents are all committed,
+    // reject the ones whose parents are rejected or which waited too long.
+    fn forward_waiting_txns(&mut self) {
+        if self.waiting_txns.is_empty() {
+            return;
+        }
+
+        let state = self.committed_state.clone();
+        let ledger = state.read();
+        let height = ledger.get_tendermint_height();
+
+        // the ones whose parents are all committed, forwarded together in the end
+        let mut ready = vec![];
+        for mut w in mem::take(&mut self.waiting_txns) {
+            w.parents
+                .retain(|p| ledger.get_status().get_recent_txn_outputs(p).is_none());
+            let rejected = w.parents.iter().any(|p| {
+                matches!(
+                    self.txn_status.get(&parent_handle(p)),
+                    Some(TxnStatus::Rejected(_))
+                )
+            });
+
+            let e = if rejected {
+                rejection(
+                    TxnCheckCode::ParentRejected,
+                    "parent transaction rejected".to_owned(),
+                )
+            } else if w.parents.is_empty() {
+                ready.push(w);
+                continue;
+            } else if w.since + MAX_WAITING_BLOCKS < height {
+                rejection(
+                    TxnCheckCode::ParentTimeout,
+                    "parent transactions not committed in time".to_owned(),
+                )
+            } else {
+                self.txn_status.insert(
+                    w.handle.clone(),
+                    TxnStatus::Waiting(w.parents.iter().map(parent_handle).collect()),
+                );
+                self.waiting_txns.push(w);
+                continue;
+            };
+            self.txn_status.insert(w.handle, TxnStatus::Rejected(e));
+        }
+
+        for w in apply_ordering(&*self.ordering, ready, |w| &w.txn) {
+            match self.txn_forwarder.forward_txn(w.txn) {
+                Ok(_) => {
+                    self.txn_status.insert(w.handle.clone(), TxnStatus::Pending);
+                    self.forwarded.insert(w.handle, height);
+                }
+                Err(e) => {
+                    let e = rejection(TxnCheckCode::NotForwarded, e.to_string());
+                    self.txn_status.insert(w.handle, TxnStatus::Rejected(e));
+                }
+            }
+        }
+    }
+
     #[allow(missing_docs)]
     pub fn get_fwder(&self) -> &TF {
         &self.txn_forwarder
     }
 }
 
+// A rejection found outside the operations
+#[inline(always)]
+fn rejection(code: TxnCheckCode, msg: String) -> TxnCheckError {
+    TxnCheckError::new(code, None, msg)
+}
+
+#[inline(always)]
+fn parent_handle(digest: &[u8; 32]) -> TxnHandle {
+    TxnHandle(hex::encode(digest))
+}
+
 /// Convert incoming tx data to the proper Transaction format
 #[inline(always)]
 pub fn convert_tx(tx: &[u8]) -> Result<Transaction> {
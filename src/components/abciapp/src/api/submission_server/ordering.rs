@@ -0,0 +1,153 @@
+//!
+//! # Ordering policies of transactions
+//!
+//! The order of a batch of transactions released together, which is kept by the
+//! blocks: the blocks of the standalone ledger, and in a node, the transactions
+//! waiting for their parents released to tendermint, whose mempool keeps their order,
+//! once the parents are committed, see `SubmissionServer::after_block`.
+//!
+//! The other submissions of a node are forwarded to tendermint on arrival, they are
+//! not buffered, so they enter the blocks in the order of arrival whatever the policy.
+//!
+//! The policy of a node is chosen by `abcid --txn-ordering`, other policies
+//! can be plugged in by `SubmissionServer::set_ordering_policy`.
+//!
+
+use {
+    ledger::data_model::{Operation, Transaction},
+    ruc::*,
+    std::collections::HashMap,
+};
+
+/// The name of the default policy
+pub const DEFAULT_ORDERING_POLICY: &str = "fifo";
+
+/// Orders a batch of transactions.
+///
+/// It must be deterministic, ie. only depend on the transactions and their order
+/// of arrival, so the nodes running the same policy form the same blocks.
+pub trait OrderingPolicy: Send + Sync {
+    #[allow(missing_docs)]
+    fn name(&self) -> &str;
+
+    /// The indexes of `txns`, in the order of arrival, in their new order.
+    fn order(&self, txns: &[&Transaction]) -> Vec<usize>;
+}
+
+/// In the order of arrival, the default one.
+pub struct Fifo;
+
+impl OrderingPolicy for Fifo {
+    fn name(&self) -> &str {
+        "fifo"
+    }
+
+    fn order(&self, txns: &[&Transaction]) -> Vec<usize> {
+        (0..txns.len()).collect()
+    }
+}
+
+/// The highest fees first, the ones paying the same fee in the order of arrival.
+pub struct FeePriority;
+
+impl OrderingPolicy for FeePriority {
+    fn name(&self) -> &str {
+        "fee"
+    }
+
+    fn order(&self, txns: &[&Transaction]) -> Vec<usize> {
+        let mut idxs = (0..txns.len()).collect::<Vec<_>>();
+        // stable, so the ties keep their order
+        idxs.sort_by_key(|i| std::cmp::Reverse(txns[*i].fee()));
+        idxs
+    }
+}
+
+/// One transaction of each sender in turn, the senders in the order of their first
+/// transactions, so a busy sender can not push the others to the end of a batch.
+///
+/// The sender is the owner of the first input of the transfers,
+/// a transaction without any is a sender by itself.
+pub struct SenderRoundRobin;
+
+impl OrderingPolicy for SenderRoundRobin {
+    fn name(&self) -> &str {
+        "sender"
+    }
+
+    fn order(&self, txns: &[&Transaction]) -> Vec<usize> {
+        let mut senders: Vec<Vec<usize>> = vec![];
+        let mut sender_idx = HashMap::new();
+        for (i, tx) in txns.iter().enumerate() {
+            match sender(tx) {
+                Some(s) => {
+                    let idx = *sender_idx.entry(s).or_insert_with(|| {
+                        senders.push(vec![]);
+                        senders.len() - 1
+                    });
+                    senders[idx].push(i);
+                }
+                None => senders.push(vec![i]),
+            }
+        }
+
+        let rounds = senders.iter().map(|s| s.len()).max().unwrap_or(0);
+        (0..rounds)
+            .flat_map(|r| senders.iter().filter_map(move |s| s.get(r).copied()))
+            .collect()
+    }
+}
+
+/// The built-in policy named `name`: `fifo`, `fee` or `sender`.
+pub fn ordering_policy(name: &str) -> Result<Box<dyn OrderingPolicy>> {
+    match name {
+        "fifo" => Ok(Box::new(Fifo)),
+        "fee" => Ok(Box::new(FeePriority)),
+        "sender" => Ok(Box::new(SenderRoundRobin)),
+        _ => Err(eg!(format!(
+            "unknown ordering policy: {}, expected fifo/fee/sender",
+            name
+        ))),
+    }
+}
+
+/// Reorder `items` by `policy`, `txn` gives the transaction of an item.
+///
+/// An order which is not a permutation of the batch is ignored,
+/// the batch is kept in the order of arrival then.
+pub fn apply_ordering<T, F>(policy: &dyn OrderingPolicy, items: Vec<T>, txn: F) -> Vec<T>
+where
+    F: Fn(&T) -> &Transaction,
+{
+    let order = policy.order(&items.iter().map(&txn).collect::<Vec<_>>());
+
+    let mut seen = vec![false; items.len()];
+    let valid = order.len() == items.len()
+        && order
+            .iter()
+            .all(|i| *i < seen.len() && !std::mem::replace(&mut seen[*i], true));
+    if !valid {
+        log::error!(
+            target: "abciapp",
+            "invalid order from the policy `{}`, ignored",
+            policy.name()
+        );
+        return items;
+    }
+
+    let mut items = items.into_iter().map(Some).collect::<Vec<_>>();
+    order.into_iter().filter_map(|i| items[i].take()).collect()
+}
+
+// the owner of the first input of the transfers
+fn sender(tx: &Transaction) -> Option<Vec<u8>> {
+    tx.body.operations.iter().find_map(|op| match op {
+        Operation::TransferAsset(t) => t
+            .body
+            .transfer
+            .inputs
+            .first()
+            .map(|i| i.public_key.as_bytes().to_vec()),
+        _ => None,
+    })
+}
@@ -3,19 +3,38 @@
 //!
 
 use {
-    super::{SubmissionServer, TxnForward, TxnHandle},
-    actix_cors::Cors,
-    actix_web::{error, middleware, web, App, HttpServer},
+    super::{
+        access::check_access,
+        mempool::{self, MempoolStats},
+        SubmissionServer, TxnForward, TxnHandle,
+    },
+    crate::{abci, api::http},
+    actix_service::Service,
+    actix_web::{error, middleware, web, App, HttpRequest, HttpServer},
+    config::abci::{ApiServerConfig, RuntimeConfig},
     finutils::api::NetworkRoute,
-    ledger::data_model::Transaction,
+    lazy_static::lazy_static,
+    ledger::data_model::{CancelTxn, Transaction, TxnEffect},
     log::info,
-    parking_lot::RwLock,
+    parking_lot::{Mutex, RwLock},
     rand_core::{CryptoRng, RngCore},
     ruc::*,
+    serde::Deserialize,
     std::result::Result as StdResult,
-    std::sync::Arc,
+    std::{
+        collections::HashMap,
+        net::IpAddr,
+        sync::{atomic::Ordering, Arc},
+        time::{SystemTime, UNIX_EPOCH},
+    },
 };
 
+lazy_static! {
+    // the second, and the submissions in it by the IPs of the clients
+    static ref SUBMISSIONS: Mutex<(u64, HashMap<IpAddr, u64>)> =
+        Mutex::new((0, HashMap::new()));
+}
+
 /// Ping route to check for liveness of API
 #[allow(clippy::unnecessary_wraps)]
 async fn ping() -> actix_web::Result<String> {
@@ -32,20 +51,146 @@ async fn version() -> actix_web::Result<String> {
     ))
 }
 
-/// Sending transactions to tendermint
+/// The double spends rejected by the mempool, for the operators of the node
+#[allow(clippy::unnecessary_wraps)]
+async fn mempool_stats() -> actix_web::Result<web::Json<MempoolStats>> {
+    Ok(web::Json(mempool::stats()))
+}
+
+// the IP of the client, behind one of `trusted_proxies` or not
+fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+    req.headers()
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+        .or(Some(peer))
+}
+
+// see `RuntimeConfig::max_submissions_per_sec`
+fn check_rate(req: &HttpRequest) -> StdResult<(), actix_web::error::Error> {
+    let cfg = RuntimeConfig::current();
+    let limit = cfg.max_submissions_per_sec;
+    let ip = match client_ip(req, &cfg.trusted_proxies) {
+        Some(ip) if 0 < limit => ip,
+        _ => return Ok(()),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut submissions = SUBMISSIONS.lock();
+    if submissions.0 != now {
+        *submissions = (now, HashMap::new());
+    }
+    let cnt = submissions.1.entry(ip).or_insert(0);
+    *cnt += 1;
+    if *cnt > limit {
+        return Err(error::ErrorTooManyRequests(
+            "too many submissions, retry later",
+        ));
+    }
+    Ok(())
+}
+
+// new submissions are refused during the shutdown
+fn check_not_exiting() -> StdResult<(), actix_web::error::Error> {
+    if abci::IS_EXITING.load(Ordering::Acquire) {
+        return Err(error::ErrorServiceUnavailable("the node is shutting down"));
+    }
+    Ok(())
+}
+
+/// Optional parameters of `submit_transaction`
+#[derive(Deserialize, Debug)]
+pub struct SubmitParams {
+    /// Chosen by the client, retries with the same id are not handled again
+    request_id: Option<String>,
+}
+
+/// Sending transactions to tendermint,
+/// duplicates of the recent submissions get the handles of the originals.
+///
+/// The transaction is in JSON, or in CBOR with `Content-Type: application/cbor`.
 pub async fn submit_transaction<RNG, TF>(
+    req: HttpRequest,
     data: web::Data<Arc<RwLock<SubmissionServer<RNG, TF>>>>,
-    body: web::Json<Transaction>,
+    params: web::Query<SubmitParams>,
+    body: web::Bytes,
 ) -> StdResult<web::Json<TxnHandle>, actix_web::error::Error>
 where
     RNG: RngCore + CryptoRng,
     TF: TxnForward + Sync + Send,
 {
-    let tx = body.into_inner();
+    check_not_exiting()?;
+    check_rate(&req)?;
+    let tx: Transaction = http::decode_body(&req, &body)?;
+    check_access(
+        req.peer_addr().map(|a| a.ip()),
+        &format!("transaction {}", tx.handle()),
+        |pk| {
+            tx.check_has_signature(pk).is_ok()
+                || tx.check_has_signature_from_map(pk).is_ok()
+        },
+    )
+    .map_err(|e| error::ErrorForbidden(e.to_string()))?;
+
+    // reject malformed transactions before forwarding them,
+    // the body of the response is the `TxnCheckError` in JSON
+    if let Err(e) = TxnEffect::check_stateless(tx.clone()) {
+        return Err(error::ErrorBadRequest(
+            serde_json::to_string(&e).unwrap_or_else(|_| e.to_string()),
+        ));
+    }
+
+    let mut submission_server = data.write();
+    submission_server
+        .handle_transaction_with_id(tx, params.into_inner().request_id)
+        .map(web::Json)
+        .map_err(|e| {
+            e.print(None);
+            error::ErrorBadRequest(e.to_string())
+        })
+}
+
+/// Cancel or replace a transaction waiting for its parents in the submission server,
+/// the ones forwarded to the mempool of tendermint can not be cancelled,
+/// the response is the handle of the replacement, `null` if cancelled only.
+pub async fn cancel_transaction<RNG, TF>(
+    http_req: HttpRequest,
+    data: web::Data<Arc<RwLock<SubmissionServer<RNG, TF>>>>,
+    body: web::Json<CancelTxn>,
+) -> StdResult<web::Json<Option<TxnHandle>>, actix_web::error::Error>
+where
+    RNG: RngCore + CryptoRng,
+    TF: TxnForward + Sync + Send,
+{
+    check_not_exiting()?;
+    check_rate(&http_req)?;
+    let req = body.into_inner();
+    check_access(
+        http_req.peer_addr().map(|a| a.ip()),
+        &format!("cancellation of {}", req.body.handle),
+        |pk| *pk == req.pubkey,
+    )
+    .map_err(|e| error::ErrorForbidden(e.to_string()))?;
+
+    if let Some(tx) = req.body.replacement.as_ref() {
+        if let Err(e) = TxnEffect::check_stateless(tx.clone()) {
+            return Err(error::ErrorBadRequest(
+                serde_json::to_string(&e).unwrap_or_else(|_| e.to_string()),
+            ));
+        }
+    }
 
     let mut submission_server = data.write();
     submission_server
-        .handle_transaction(tx)
+        .cancel_transaction(req)
         .map(web::Json)
         .map_err(|e| {
             e.print(None);
@@ -84,18 +229,22 @@ pub struct SubmissionApi;
 #[allow(missing_docs)]
 pub enum SubmissionRoutes {
     SubmitTransaction,
+    CancelTransaction,
     TxnStatus,
     Ping,
     Version,
+    MempoolStats,
 }
 
 impl NetworkRoute for SubmissionRoutes {
     fn route(&self) -> String {
         let endpoint = match *self {
             SubmissionRoutes::SubmitTransaction => "submit_transaction",
+            SubmissionRoutes::CancelTransaction => "cancel_transaction",
             SubmissionRoutes::TxnStatus => "txn_status",
             SubmissionRoutes::Ping => "ping",
             SubmissionRoutes::Version => "version",
+            SubmissionRoutes::MempoolStats => "mempool_stats",
         };
         "/".to_owned() + endpoint
     }
@@ -110,21 +259,42 @@ impl SubmissionApi {
         submission_server: Arc<RwLock<SubmissionServer<RNG, TF>>>,
         host: &str,
         port: u16,
+        cfg: ApiServerConfig,
     ) -> Result<SubmissionApi> {
         let _ = actix_rt::System::new("findora API");
 
         HttpServer::new(move || {
+            let timeouts = cfg.clone();
             App::new()
+                .wrap_fn(move |req, srv| {
+                    let timeout = timeouts.timeout_of(req.path());
+                    let min_size = timeouts.compression_min_size_of(req.path());
+                    let coding = http::accepted_coding(&req);
+                    http::with_compression(
+                        coding,
+                        min_size,
+                        http::with_timeout(timeout, srv.call(req)),
+                    )
+                })
                 .wrap(middleware::Logger::default())
-                .wrap(Cors::permissive().supports_credentials())
-                .data(web::JsonConfig::default().limit(2048 * 1024))
+                .wrap(http::cors(&cfg))
+                .app_data(web::JsonConfig::default().limit(cfg.max_body_size))
+                .app_data(web::PayloadConfig::new(cfg.max_body_size))
                 .data(submission_server.clone())
                 .route(
                     &SubmissionRoutes::SubmitTransaction.route(),
                     web::post().to(submit_transaction::<RNG, TF>),
                 )
+                .route(
+                    &SubmissionRoutes::CancelTransaction.route(),
+                    web::post().to(cancel_transaction::<RNG, TF>),
+                )
                 .route(&SubmissionRoutes::Ping.route(), web::get().to(ping))
                 .route(&SubmissionRoutes::Version.route(), web::get().to(version))
+                .route(
+                    &SubmissionRoutes::MempoolStats.route(),
+                    web::get().to(mempool_stats),
+                )
                 .route(
                     &SubmissionRoutes::TxnStatus.with_arg_template("handle"),
                     web::get().to(txn_status::<RNG, TF>),
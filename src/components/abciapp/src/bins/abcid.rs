@@ -5,10 +5,7 @@
 use {
     abciapp::abci,
     ruc::*,
-    std::{
-        sync::{atomic::Ordering, mpsc::channel},
-        thread,
-    },
+    std::{process, thread},
 };
 
 fn main() {
@@ -20,22 +17,15 @@ fn main() {
         env!("VERGEN_BUILD_DATE")
     ));
 
-    let thread = thread::spawn(|| pnk!(abci::run()));
+    thread::spawn(|| pnk!(abci::run()));
 
-    let (tx, rx) = channel();
-
-    pnk!(ctrlc::set_handler(move || {
+    pnk!(ctrlc::set_handler(|| {
         println!("Waiting to exit.");
-        abci::IS_EXITING.store(true, Ordering::Release);
-        while abci::IN_SAFE_ITV.load(Ordering::SeqCst) {
-            sleep_ms!(1);
-        }
-        pnk!(tx.send(()));
+        abci::request_shutdown();
     }));
 
-    pnk!(rx.recv());
-
+    // the ABCI server never returns, exit once the state is safe on disk
+    pnk!(abci::shutdown());
     println!("Exiting...");
-    thread.thread().unpark();
-    thread.join().unwrap();
+    process::exit(0);
 }
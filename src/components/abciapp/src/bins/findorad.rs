@@ -84,6 +84,9 @@ fn node_command() -> Result<()> {
     convert_arg!(snapshot_algo);
     convert_arg!(snapshot_rollback_to);
     convert_arg!(snapshot_rollback_to_exact);
+    convert_arg!(anchor_webhook);
+    convert_arg!(anchor_itv);
+    convert_arg!(state_sync_itv);
 
     let mut abcid_child = abcid
         .stdin(Stdio::null())
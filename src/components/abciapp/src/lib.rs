@@ -9,3 +9,4 @@
 
 pub mod abci;
 pub mod api;
+pub mod standalone;
@@ -0,0 +1,291 @@
+//!
+//! # In-process ledger for integration tests
+//!
+//! A ledger driven without tendermint, blocks are produced on demand by
+//! `advance_block`, so the results of component tests are deterministic
+//! and they no longer depend on externally launched binaries.
+//!
+//! - `StandaloneLedger::new`: fully in-memory, transactions are submitted by `submit`
+//! - `StandaloneLedger::with_servers`: the submission, query, ledger and admin servers
+//!   are started on random local ports too, see `StandaloneLedger::ports`
+//!
+//! NOTE: staking system operations(coinbase, validator updates, governance)
+//! and EVM transactions are not simulated.
+//!
+
+#[cfg(test)]
+mod test;
+
+use {
+    crate::api::{
+        admin_server::AdminApi,
+        query_server::{query_api::service::start_query_server, BLOCK_CREATED},
+        submission_server::{
+            ordering::OrderingPolicy, submission_api::SubmissionApi, SubmissionServer,
+            TxnForward, TxnHandle, TxnStatus,
+        },
+    },
+    config::abci::ApiServerConfig,
+    ledger::{
+        data_model::{CancelTxn, Transaction},
+        store::{api_cache, LedgerState},
+    },
+    parking_lot::{Mutex, RwLock},
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    std::{
+        env, mem,
+        net::TcpListener,
+        sync::Arc,
+        thread,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+const LOCAL_HOST: &str = "127.0.0.1";
+
+/// Collects submitted transactions until the next block.
+#[derive(Clone, Default)]
+pub struct LocalForward {
+    queue: Arc<Mutex<Vec<Transaction>>>,
+}
+
+impl AsRef<str> for LocalForward {
+    fn as_ref(&self) -> &str {
+        "local"
+    }
+}
+
+impl TxnForward for LocalForward {
+    fn forward_txn(&self, txn: Transaction) -> Result<()> {
+        self.queue.lock().push(txn);
+        Ok(())
+    }
+}
+
+/// Ports of the servers started by `StandaloneLedger::with_servers`.
+#[derive(Clone, Copy, Debug)]
+pub struct StandalonePorts {
+    #[allow(missing_docs)]
+    pub submission: u16,
+    #[allow(missing_docs)]
+    pub query: u16,
+    #[allow(missing_docs)]
+    pub ledger: u16,
+    #[allow(missing_docs)]
+    pub admin: u16,
+}
+
+/// An in-process ledger.
+pub struct StandaloneLedger {
+    submission: Arc<RwLock<SubmissionServer<ChaChaRng, LocalForward>>>,
+    forward: LocalForward,
+    ports: Option<StandalonePorts>,
+}
+
+impl StandaloneLedger {
+    /// A fully in-memory ledger, no server will be started.
+    pub fn new() -> Result<StandaloneLedger> {
+        let forward = LocalForward::default();
+        let submission = SubmissionServer::new_no_auto_commit(
+            ChaChaRng::from_seed([0; 32]),
+            Arc::new(RwLock::new(LedgerState::tmp_ledger())),
+            forward.clone(),
+        )
+        .c(d!())?;
+
+        Ok(StandaloneLedger {
+            submission: Arc::new(RwLock::new(submission)),
+            forward,
+            ports: None,
+        })
+    }
+
+    /// A ledger along with its submission, query, ledger and admin servers,
+    /// listening on random ports of `127.0.0.1`.
+    ///
+    /// NOTE: the history data used by the query server is kept only if
+    /// no ledger has been created in this process before.
+    pub fn with_servers() -> Result<StandaloneLedger> {
+        env::set_var("FINDORAD_KEEP_HIST", "1");
+        let mut sl = Self::new().c(d!())?;
+
+        let ports = StandalonePorts {
+            submission: free_port().c(d!())?,
+            query: free_port().c(d!())?,
+            ledger: free_port().c(d!())?,
+            admin: free_port().c(d!())?,
+        };
+
+        let ledger = sl.ledger();
+        start_query_server(
+            ledger,
+            &[],
+            &[(LOCAL_HOST, ports.query), (LOCAL_HOST, ports.ledger)],
+            ApiServerConfig::default(),
+        )
+        .c(d!())?
+        .write()
+        .update();
+
+        let submission = Arc::clone(&sl.submission);
+        thread::spawn(move || {
+            pnk!(SubmissionApi::create(
+                submission,
+                LOCAL_HOST,
+                ports.submission,
+                ApiServerConfig::default(),
+            ));
+        });
+
+        thread::spawn(move || pnk!(AdminApi::create(ports.admin)));
+
+        wait_for_port(ports.submission).c(d!())?;
+        wait_for_port(ports.admin).c(d!())?;
+        sl.ports = Some(ports);
+        Ok(sl)
+    }
+
+    /// Ports of the servers, `None` if created by `new`.
+    pub fn ports(&self) -> Option<StandalonePorts> {
+        self.ports
+    }
+
+    /// The address of the servers, in the format expected by clients, eg. `fn setup -S`.
+    pub fn serv_addr(&self) -> String {
+        format!("http://{}", LOCAL_HOST)
+    }
+
+    /// The underlying ledger state.
+    pub fn ledger(&self) -> Arc<RwLock<LedgerState>> {
+        self.submission.read().borrowable_ledger_state()
+    }
+
+    /// Current block height.
+    pub fn height(&self) -> u64 {
+        self.ledger().read().get_tendermint_height()
+    }
+
+    /// Replace the policy ordering the transactions of a block, `Fifo` by default.
+    pub fn set_ordering_policy(&self, policy: Box<dyn OrderingPolicy>) {
+        self.submission.write().set_ordering_policy(policy);
+    }
+
+    /// Submit a transaction, it will be applied in the next block.
+    pub fn submit(&self, txn: Transaction) -> Result<TxnHandle> {
+        self.submission.write().handle_transaction(txn).c(d!())
+    }
+
+    /// Submit a transaction along with a request id, see `submit`,
+    /// retries with the same id get the handle of the first submission.
+    pub fn submit_with_id(
+        &self,
+        txn: Transaction,
+        request_id: &str,
+    ) -> Result<TxnHandle> {
+        self.submission
+            .write()
+            .handle_transaction_with_id(txn, Some(request_id.to_owned()))
+            .c(d!())
+    }
+
+    /// Cancel or replace a transaction waiting for its parents.
+    pub fn cancel(&self, req: CancelTxn) -> Result<Option<TxnHandle>> {
+        self.submission.write().cancel_transaction(req).c(d!())
+    }
+
+    /// The status of a submitted transaction.
+    pub fn txn_status(&self, handle: &TxnHandle) -> Option<TxnStatus> {
+        self.submission.read().get_txn_status(handle)
+    }
+
+    /// Produce a block with all transactions submitted since the last block,
+    /// in the order of the ordering policy, returns the status of each one.
+    pub fn advance_block(&self) -> Result<Vec<(TxnHandle, TxnStatus)>> {
+        let txns = mem::take(&mut *self.forward.queue.lock());
+
+        let mut la = self.submission.write();
+        let txns = la.order_txns(txns);
+        let height = la.get_committed_state().read().get_tendermint_height() + 1;
+
+        la.get_committed_state()
+            .write()
+            .get_staking_mut()
+            .set_custom_block_height(height);
+        la.get_committed_state().write().set_block_time(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        );
+        if la.all_commited() {
+            la.begin_block();
+        } else {
+            la.update_staking_simulator().c(d!())?;
+        }
+
+        let handles = txns
+            .into_iter()
+            .map(|txn| {
+                let handle = TxnHandle::new(&txn);
+                // rejected transactions are recorded in their status
+                omit!(la.cache_transaction(txn));
+                handle
+            })
+            .collect::<Vec<_>>();
+
+        if !la.all_commited() && la.block_txn_count() != 0 {
+            la.end_block().c(d!())?;
+        }
+        la.after_block();
+
+        {
+            let mut state = la.get_committed_state().write();
+            state.set_tendermint_height(height);
+            api_cache::update_api_cache(&mut state).c(d!())?;
+        }
+
+        let statuses = handles
+            .into_iter()
+            .map(|h| {
+                let status = la.get_txn_status(&h).unwrap_or(TxnStatus::Pending);
+                (h, status)
+            })
+            .collect();
+        drop(la);
+
+        if self.ports.is_some() {
+            let mut created = BLOCK_CREATED.0.lock();
+            *created = true;
+            BLOCK_CREATED.1.notify_one();
+        }
+
+        Ok(statuses)
+    }
+
+    /// Produce `n` blocks, see `advance_block`.
+    pub fn advance_blocks(&self, n: u64) -> Result<()> {
+        for _ in 0..n {
+            self.advance_block().c(d!())?;
+        }
+        Ok(())
+    }
+}
+
+fn free_port() -> Result<u16> {
+    TcpListener::bind((LOCAL_HOST, 0))
+        .c(d!())
+        .and_then(|l| l.local_addr().c(d!()))
+        .map(|addr| addr.port())
+}
+
+fn wait_for_port(port: u16) -> Result<()> {
+    for _ in 0..100 {
+        if std::net::TcpStream::connect((LOCAL_HOST, port)).is_ok() {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    Err(eg!("server on port {} is not ready", port))
+}
@@ -0,0 +1,321 @@
+#![allow(missing_docs)]
+
+use {
+    super::StandaloneLedger,
+    crate::api::submission_server::{
+        ordering::OrderingPolicy, TxnHandle, TxnStatus, MAX_WAITING_BLOCKS,
+    },
+    finutils::txn_builder::{TransactionBuilder, TransferOperationBuilder},
+    ledger::{
+        data_model::{
+            CancelTxn, CancelTxnBody, PendingTxoRef, Transaction, TransferType,
+            TxnCheckCode, TxoRef,
+        },
+        store::utils::fra_gen_initial_tx,
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    zei::xfr::{
+        asset_record::{open_blind_asset_record, AssetRecordType},
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::{AssetRecordTemplate, OpenAssetRecord},
+    },
+};
+
+#[test]
+fn standalone_ledger() {
+    let sl = pnk!(StandaloneLedger::new());
+    assert!(sl.ports().is_none());
+
+    pnk!(sl.advance_blocks(2));
+    assert_eq!(2, sl.height());
+
+    let root_kp = XfrKeyPair::generate(&mut ChaChaRng::from_seed([0; 32]));
+    let tx = fra_gen_initial_tx(&root_kp);
+
+    let handle = pnk!(sl.submit(tx.clone()));
+    assert!(sl.txn_status(&handle).is_none());
+
+    let statuses = pnk!(sl.advance_block());
+    assert_eq!(3, sl.height());
+    assert_eq!(1, statuses.len());
+    assert_eq!(handle, statuses[0].0);
+    assert!(matches!(statuses[0].1, TxnStatus::Committed(_)));
+    assert!(matches!(
+        sl.txn_status(&handle),
+        Some(TxnStatus::Committed(_))
+    ));
+
+    // duplicated
+    assert_eq!(handle, pnk!(sl.submit(tx)));
+    assert!(pnk!(sl.advance_block()).is_empty());
+
+    // defined again
+    pnk!(sl.submit(fra_gen_initial_tx(&root_kp)));
+    let statuses = pnk!(sl.advance_block());
+    assert!(matches!(
+        &statuses[0].1,
+        TxnStatus::Rejected(e) if TxnCheckCode::InvalidState == e.code
+    ));
+}
+
+// a transfer of the whole `input` to `receivers`
+fn transfer(
+    sl: &StandaloneLedger,
+    kp: &XfrKeyPair,
+    input: (TxoRef, OpenAssetRecord),
+    receivers: &[(XfrPublicKey, u64)],
+) -> Transaction {
+    let (am, asset_type) = (input.1.amount, input.1.asset_type);
+    let mut trans_builder = TransferOperationBuilder::new();
+    pnk!(trans_builder.add_input(input.0, input.1, None, None, am));
+    for (pk, am) in receivers.iter() {
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            *am,
+            asset_type,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            *pk,
+        );
+        pnk!(trans_builder.add_output(&template, None, None, None));
+    }
+    let op = pnk!(trans_builder
+        .create(TransferType::Standard)
+        .c(d!())
+        .and_then(|b| b.sign(kp).c(d!()))
+        .and_then(|b| b.transaction().c(d!())));
+
+    let mut builder =
+        TransactionBuilder::from_seq_id(sl.ledger().read().get_block_commit_count());
+    builder.add_operation(op).sign(kp);
+    builder.take_transaction()
+}
+
+// a transaction sending the first output of `root_kp` to `bob_kp`
+fn parent_of(
+    sl: &StandaloneLedger,
+    root_kp: &XfrKeyPair,
+    bob_kp: &XfrKeyPair,
+) -> Transaction {
+    let (sid, utxo) = {
+        let ledger = sl.ledger();
+        let ledger = ledger.read();
+        let sid = *ledger
+            .get_status()
+            .get_owned_utxos(root_kp.get_pk_ref())
+            .iter()
+            .min()
+            .unwrap();
+        (sid, ledger.get_utxo_light(sid).unwrap().utxo)
+    };
+    let oar = pnk!(open_blind_asset_record(&utxo.0.record, &None, root_kp));
+    let am = oar.amount;
+    transfer(
+        sl,
+        root_kp,
+        (TxoRef::Absolute(sid), oar),
+        &[(bob_kp.get_pk(), am)],
+    )
+}
+
+// a transaction sending the output of `parent` back to `root_kp`
+fn child_of(
+    sl: &StandaloneLedger,
+    parent: &Transaction,
+    root_kp: &XfrKeyPair,
+    bob_kp: &XfrKeyPair,
+) -> Transaction {
+    let bar = parent.get_outputs_ref(false)[0].record.clone();
+    let oar = pnk!(open_blind_asset_record(&bar, &None, bob_kp));
+    let am = oar.amount;
+    let pending = pnk!(PendingTxoRef::from_handle(&parent.handle(), 0));
+    transfer(
+        sl,
+        bob_kp,
+        (TxoRef::Pending(pending), oar),
+        &[(root_kp.get_pk(), am)],
+    )
+}
+
+#[test]
+fn standalone_idempotent_submission() {
+    let sl = pnk!(StandaloneLedger::new());
+    let mut prng = ChaChaRng::from_seed([2; 32]);
+    let root_kp = XfrKeyPair::generate(&mut prng);
+    let bob_kp = XfrKeyPair::generate(&mut prng);
+
+    pnk!(sl.submit(fra_gen_initial_tx(&root_kp)));
+    pnk!(sl.advance_block());
+    let tx = parent_of(&sl, &root_kp, &bob_kp);
+    let other = parent_of(&sl, &root_kp, &bob_kp);
+
+    // retries are forwarded once
+    let handle = pnk!(sl.submit_with_id(tx.clone(), "req-1"));
+    assert_eq!(handle, pnk!(sl.submit_with_id(tx.clone(), "req-1")));
+    assert_eq!(handle, pnk!(sl.submit(tx.clone())));
+    assert!(sl.submit_with_id(other, "req-1").is_err());
+    assert!(sl.submit_with_id(tx.clone(), &"x".repeat(129)).is_err());
+
+    let statuses = pnk!(sl.advance_block());
+    assert_eq!(1, statuses.len());
+    assert!(matches!(statuses[0].1, TxnStatus::Committed(_)));
+
+    // the original status is kept
+    assert_eq!(handle, pnk!(sl.submit_with_id(tx, "req-2")));
+    assert!(pnk!(sl.advance_block()).is_empty());
+    assert!(matches!(
+        sl.txn_status(&handle),
+        Some(TxnStatus::Committed(_))
+    ));
+}
+
+#[test]
+fn standalone_pending_parents() {
+    let sl = pnk!(StandaloneLedger::new());
+    let mut prng = ChaChaRng::from_seed([0; 32]);
+    let root_kp = XfrKeyPair::generate(&mut prng);
+    let bob_kp = XfrKeyPair::generate(&mut prng);
+
+    pnk!(sl.submit(fra_gen_initial_tx(&root_kp)));
+    pnk!(sl.advance_block());
+    let parent = parent_of(&sl, &root_kp, &bob_kp);
+    let child = child_of(&sl, &parent, &root_kp, &bob_kp);
+
+    // the child waits for its parent
+    let parent_handle = pnk!(sl.submit(parent));
+    let child_handle = pnk!(sl.submit(child));
+    assert_eq!(
+        Some(TxnStatus::Waiting(vec![parent_handle.clone()])),
+        sl.txn_status(&child_handle)
+    );
+
+    // forwarded once the parent is committed
+    let statuses = pnk!(sl.advance_block());
+    assert_eq!(1, statuses.len());
+    assert!(matches!(statuses[0].1, TxnStatus::Committed(_)));
+    assert_eq!(Some(TxnStatus::Pending), sl.txn_status(&child_handle));
+
+    let statuses = pnk!(sl.advance_block());
+    assert_eq!(child_handle, statuses[0].0);
+    assert!(matches!(statuses[0].1, TxnStatus::Committed(_)));
+}
+
+#[test]
+fn standalone_waiting_timeout() {
+    let sl = pnk!(StandaloneLedger::new());
+    let mut prng = ChaChaRng::from_seed([2; 32]);
+    let root_kp = XfrKeyPair::generate(&mut prng);
+    let bob_kp = XfrKeyPair::generate(&mut prng);
+
+    pnk!(sl.submit(fra_gen_initial_tx(&root_kp)));
+    pnk!(sl.advance_block());
+    let parent = parent_of(&sl, &root_kp, &bob_kp);
+    let child = child_of(&sl, &parent, &root_kp, &bob_kp);
+
+    // the parent is never submitted, the blocks are all empty
+    let child_handle = pnk!(sl.submit(child));
+    pnk!(sl.advance_blocks(MAX_WAITING_BLOCKS));
+    assert!(matches!(
+        sl.txn_status(&child_handle),
+        Some(TxnStatus::Waiting(_))
+    ));
+
+    pnk!(sl.advance_blocks(2));
+    assert!(matches!(
+        sl.txn_status(&child_handle),
+        Some(TxnStatus::Rejected(e)) if TxnCheckCode::ParentTimeout == e.code
+    ));
+}
+
+#[test]
+fn standalone_cancel_waiting() {
+    let sl = pnk!(StandaloneLedger::new());
+    let mut prng = ChaChaRng::from_seed([1; 32]);
+    let root_kp = XfrKeyPair::generate(&mut prng);
+    let bob_kp = XfrKeyPair::generate(&mut prng);
+
+    pnk!(sl.submit(fra_gen_initial_tx(&root_kp)));
+    pnk!(sl.advance_block());
+    let parent = parent_of(&sl, &root_kp, &bob_kp);
+    let child = child_of(&sl, &parent, &root_kp, &bob_kp);
+    let replacement = child_of(&sl, &parent, &root_kp, &bob_kp);
+
+    let parent_handle = pnk!(sl.submit(parent));
+    let child_handle = pnk!(sl.submit(child));
+    let cancel = |kp: &XfrKeyPair, handle: &TxnHandle, replacement| {
+        let body = CancelTxnBody {
+            handle: handle.0.clone(),
+            replacement,
+        };
+        sl.cancel(CancelTxn::new(kp, body))
+    };
+
+    // forwarded to the mempool already
+    assert!(cancel(&root_kp, &parent_handle, None).is_err());
+    assert!(cancel(&bob_kp, &parent_handle, None).is_err());
+
+    // only the submitter can cancel it
+    assert!(cancel(&root_kp, &child_handle, None).is_err());
+
+    let new_handle = pnk!(cancel(&bob_kp, &child_handle, Some(replacement))).unwrap();
+    assert_eq!(
+        Some(TxnStatus::Replaced(new_handle.clone())),
+        sl.txn_status(&child_handle)
+    );
+    assert!(matches!(
+        sl.txn_status(&new_handle),
+        Some(TxnStatus::Waiting(_))
+    ));
+
+    assert!(pnk!(cancel(&bob_kp, &new_handle, None)).is_none());
+    assert_eq!(Some(TxnStatus::Cancelled), sl.txn_status(&new_handle));
+    assert!(cancel(&bob_kp, &new_handle, None).is_err());
+
+    // only the parent is committed
+    assert_eq!(1, pnk!(sl.advance_block()).len());
+    assert!(pnk!(sl.advance_block()).is_empty());
+}
+
+// the latest arrivals first
+struct Lifo;
+
+impl OrderingPolicy for Lifo {
+    fn name(&self) -> &str {
+        "lifo"
+    }
+
+    fn order(&self, txns: &[&Transaction]) -> Vec<usize> {
+        (0..txns.len()).rev().collect()
+    }
+}
+
+#[test]
+fn standalone_ordering_policy() {
+    let sl = pnk!(StandaloneLedger::new());
+    sl.set_ordering_policy(Box::new(Lifo));
+    let mut prng = ChaChaRng::from_seed([3; 32]);
+    let root_kp = XfrKeyPair::generate(&mut prng);
+    let bob_kp = XfrKeyPair::generate(&mut prng);
+
+    pnk!(sl.submit(fra_gen_initial_tx(&root_kp)));
+    pnk!(sl.advance_block());
+    pnk!(sl.submit(parent_of(&sl, &root_kp, &bob_kp)));
+    pnk!(sl.advance_block());
+
+    let first = pnk!(sl.submit(parent_of(&sl, &root_kp, &bob_kp)));
+    let second = pnk!(sl.submit(parent_of(&sl, &bob_kp, &root_kp)));
+    let statuses = pnk!(sl.advance_block());
+    assert_eq!(
+        vec![second, first],
+        statuses.iter().map(|(h, _)| h.clone()).collect::<Vec<_>>()
+    );
+
+    let sids = statuses
+        .into_iter()
+        .map(|(_, s)| match s {
+            TxnStatus::Committed((sid, _)) => sid,
+            s => panic!("{:?}", s),
+        })
+        .collect::<Vec<_>>();
+    assert!(sids[0] < sids[1]);
+}
@@ -0,0 +1,102 @@
+//!
+//! The runtime configuration reloaded without a restart,
+//! in a test binary of its own as the settings in force are global to the process.
+//!
+
+use {
+    abciapp::{
+        api::{
+            admin_server::AdminRoutes,
+            submission_server::submission_api::SubmissionRoutes,
+        },
+        standalone::StandaloneLedger,
+    },
+    config::abci::RuntimeConfig,
+    finutils::api::NetworkRoute,
+    ledger::store::utils::fra_gen_initial_tx,
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    std::{env, fs},
+    zei::xfr::sig::XfrKeyPair,
+};
+
+#[test]
+fn runtime_config_reload() {
+    let dir = pnk!(tempfile::tempdir());
+    let path = dir.path().join("runtime.toml");
+    let path = path.to_str().unwrap();
+    // read by the node on its first use of `CFG`
+    env::set_var("RUNTIME_CONFIG", path);
+
+    // the defaults without a file
+    let cfg = pnk!(RuntimeConfig::from_file(path));
+    assert_eq!(0, cfg.max_submissions_per_sec);
+    assert!(cfg.mempool_min_fee.is_none());
+
+    pnk!(fs::write(path, "max_submissions_per_sec = \"2\""));
+    assert!(RuntimeConfig::from_file(path).is_err());
+
+    pnk!(fs::write(
+        path,
+        "max_submissions_per_sec = 2\nmempool_min_fee = 20000\n"
+    ));
+    let cfg = pnk!(RuntimeConfig::from_file(path));
+    assert_eq!(2, cfg.max_submissions_per_sec);
+    assert_eq!(Some(20000), cfg.mempool_min_fee);
+
+    let sl = pnk!(StandaloneLedger::with_servers());
+    let ports = sl.ports().unwrap();
+    let url = |port: u16, route: &str| format!("{}:{}{}", sl.serv_addr(), port, route);
+    let tx = pnk!(serde_json::to_vec(&fra_gen_initial_tx(
+        &XfrKeyPair::generate(&mut ChaChaRng::from_entropy())
+    )));
+    let submit = |forwarded_for: &str| {
+        pnk!(attohttpc::post(url(
+            ports.submission,
+            &SubmissionRoutes::SubmitTransaction.route()
+        ))
+        .header(attohttpc::header::CONTENT_TYPE, "application/json")
+        .header("X-Forwarded-For", forwarded_for)
+        .bytes(&tx)
+        .send()
+        .c(d!()))
+        .status()
+        .as_u16()
+    };
+    let reload = |port: u16| {
+        pnk!(
+            attohttpc::post(url(port, &AdminRoutes::ReloadConfig.route()))
+                .send()
+                .c(d!())
+        )
+        .status()
+        .as_u16()
+    };
+
+    // unlimited before the reload
+    assert!((0..5).all(|_| 200 == submit("10.0.0.1")));
+
+    // not served by the public servers
+    assert_eq!(404, reload(ports.submission));
+    assert_eq!(0, RuntimeConfig::current().max_submissions_per_sec);
+
+    // put in force, the counts are by seconds,
+    // five submissions in a row span at most two of them
+    assert_eq!(200, reload(ports.admin));
+    assert_eq!(2, RuntimeConfig::current().max_submissions_per_sec);
+    assert!((0..5)
+        .map(|_| submit("10.0.0.1"))
+        .any(|status| 429 == status));
+
+    // the test client is a trusted proxy, its clients are counted apart
+    pnk!(fs::write(
+        path,
+        "max_submissions_per_sec = 2\ntrusted_proxies = [\"127.0.0.1\"]\n"
+    ));
+    assert_eq!(200, reload(ports.admin));
+    assert!((0..5).all(|i| 200 == submit(&format!("10.0.0.1, 10.0.1.{}", i))));
+    assert!((0..5)
+        .map(|_| submit("10.0.0.1, 10.0.2.1"))
+        .any(|status| 429 == status));
+}
@@ -0,0 +1,60 @@
+//!
+//! The graceful shutdown of a node, in a process of its own,
+//! submissions are refused by all the servers of the process once requested.
+//!
+
+use {
+    abciapp::{
+        abci,
+        api::{
+            admin_server::AdminRoutes,
+            submission_server::submission_api::SubmissionRoutes,
+        },
+        standalone::StandaloneLedger,
+    },
+    finutils::api::NetworkRoute,
+    ledger::{data_model::Transaction, store::utils::fra_gen_initial_tx},
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    std::sync::atomic::Ordering,
+    zei::xfr::sig::XfrKeyPair,
+};
+
+#[test]
+fn shutdown_refuses_submissions() {
+    let sl = pnk!(StandaloneLedger::with_servers());
+    let ports = sl.ports().unwrap();
+    let url = |port: u16, route: &str| format!("{}:{}{}", sl.serv_addr(), port, route);
+    let submit = |tx: &Transaction| {
+        pnk!(attohttpc::post(url(
+            ports.submission,
+            &SubmissionRoutes::SubmitTransaction.route()
+        ))
+        .header(attohttpc::header::CONTENT_TYPE, "application/json")
+        .bytes(&pnk!(serde_json::to_vec(tx)))
+        .send()
+        .c(d!()))
+    };
+
+    let root_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    assert!(submit(&fra_gen_initial_tx(&root_kp)).is_success());
+
+    // not served by the public servers
+    let resp = pnk!(attohttpc::post(url(ports.submission, "/shutdown"))
+        .send()
+        .c(d!()));
+    assert_eq!(404, resp.status().as_u16());
+    assert!(!abci::IS_EXITING.load(Ordering::Acquire));
+
+    let resp = pnk!(
+        attohttpc::post(url(ports.admin, &AdminRoutes::Shutdown.route()))
+            .send()
+            .c(d!())
+    );
+    assert!(resp.is_success());
+    assert!(abci::IS_EXITING.load(Ordering::Acquire));
+
+    let resp = submit(&fra_gen_initial_tx(&root_kp));
+    assert_eq!(503, resp.status().as_u16());
+}
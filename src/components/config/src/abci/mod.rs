@@ -1,13 +1,19 @@
 use {
     global_cfg::CFG,
+    lazy_static::lazy_static,
     ruc::*,
     serde::{Deserialize, Serialize},
     std::{
+        collections::BTreeMap,
         convert::TryFrom,
-        env,
+        env, fmt,
         fs::{self, File},
         io::{ErrorKind, Read, Write},
+        net::IpAddr,
         path::Path,
+        str::FromStr,
+        sync::RwLock,
+        time::Duration,
     },
     toml,
 };
@@ -80,6 +86,33 @@ pub struct CheckPointConfig {
     // Fix the amount in the delegators that staking did not modify when it punished the validator.
     pub fix_delegators_am_height: u64,
     pub validators_limit_v2_height: u64,
+
+    // Reject transfers creating outputs below the dust limits of their assets.
+    #[serde(default = "never")]
+    pub dust_policy_height: u64,
+
+    // Validators self-delegating less than this amount are jailed at the next
+    // update of the validator set, until they top up and unjail; zero to disable.
+    #[serde(default)]
+    pub min_self_delegation: u64,
+    // Jail the validators under `min_self_delegation` from this height.
+    #[serde(default = "never")]
+    pub min_self_delegation_height: u64,
+
+    // Accumulate delegation rewards in per-validator pools,
+    // distributed at the close of every epoch.
+    #[serde(default = "never")]
+    pub epoch_rewards_height: u64,
+
+    // Minimum amounts of the non-confidential outputs of transfers,
+    // keyed by the base64 codes of the assets, e.g. `AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=` for FRA.
+    // Must stay the last field, tables come after values in TOML.
+    #[serde(default)]
+    pub dust_limits: BTreeMap<String, u64>,
+}
+
+fn never() -> u64 {
+    u64::MAX
 }
 
 impl CheckPointConfig {
@@ -111,6 +144,11 @@ impl CheckPointConfig {
                                 utxo_checktx_height: 0,
                                 fix_delegators_am_height: 0,
                                 validators_limit_v2_height: 0,
+                                dust_policy_height: 0,
+                                min_self_delegation: 0,
+                                min_self_delegation_height: 0,
+                                epoch_rewards_height: 0,
+                                dust_limits: BTreeMap::new(),
                             };
                             #[cfg(not(feature = "debug_env"))]
                             let config = CheckPointConfig {
@@ -132,6 +170,11 @@ impl CheckPointConfig {
                                 utxo_checktx_height: 30000000,
                                 fix_delegators_am_height: 30000000,
                                 validators_limit_v2_height: 30000000,
+                                dust_policy_height: 30000000,
+                                min_self_delegation: 0,
+                                min_self_delegation_height: 30000000,
+                                epoch_rewards_height: 30000000,
+                                dust_limits: BTreeMap::new(),
                             };
                             let content = toml::to_string(&config).unwrap();
                             file.write_all(content.as_bytes()).unwrap();
@@ -247,18 +290,179 @@ impl ABCIConfig {
     }
 }
 
+/// The settings of the http servers of a node:
+/// the submission server, the query server and the ledger service.
+#[derive(Clone, Debug)]
+pub struct ApiServerConfig {
+    /// the origins allowed by CORS, any origin if empty
+    pub cors_origins: Vec<String>,
+    /// the maximal size of request bodies, in bytes
+    pub max_body_size: usize,
+    /// the timeout of requests, in seconds, none if 0
+    pub timeout: u64,
+    /// `(path prefix, timeout)`, overriding `timeout` for the routes under the prefixes
+    pub route_timeouts: Vec<(String, u64)>,
+    /// the minimal size of the responses compressed by gzip or zstd, in bytes,
+    /// as accepted by the clients, none if 0
+    pub compression_min_size: usize,
+    /// `(path prefix, size)`, overriding `compression_min_size` for the routes
+    /// under the prefixes
+    pub route_compression_min_sizes: Vec<(String, usize)>,
+}
+
+impl Default for ApiServerConfig {
+    fn default() -> Self {
+        ApiServerConfig {
+            cors_origins: vec![],
+            max_body_size: 2048 * 1024,
+            timeout: 0,
+            route_timeouts: vec![],
+            compression_min_size: 1024,
+            route_compression_min_sizes: vec![],
+        }
+    }
+}
+
+impl ApiServerConfig {
+    /// The timeout of the route of `path`, the one of the longest matching prefix.
+    pub fn timeout_of(&self, path: &str) -> Option<Duration> {
+        let t = self
+            .route_timeouts
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, t)| *t)
+            .unwrap_or(self.timeout);
+        alt!(0 == t, None, Some(Duration::from_secs(t)))
+    }
+
+    /// The minimal size of the compressed responses of the route of `path`,
+    /// the one of the longest matching prefix.
+    pub fn compression_min_size_of(&self, path: &str) -> Option<usize> {
+        let s = self
+            .route_compression_min_sizes
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, s)| *s)
+            .unwrap_or(self.compression_min_size);
+        alt!(0 == s, None, Some(s))
+    }
+
+    /// Parse the timeouts of routes, in the format of `/path=secs,/path=secs`.
+    pub fn parse_route_timeouts(s: &str) -> Result<Vec<(String, u64)>> {
+        Self::parse_route_values(s).c(d!())
+    }
+
+    /// Parse the minimal sizes of the compressed responses of routes,
+    /// in the format of `/path=bytes,/path=bytes`.
+    pub fn parse_route_compression_min_sizes(s: &str) -> Result<Vec<(String, usize)>> {
+        Self::parse_route_values(s).c(d!())
+    }
+
+    fn parse_route_values<T: FromStr>(s: &str) -> Result<Vec<(String, T)>>
+    where
+        <T as FromStr>::Err: fmt::Debug + fmt::Display,
+    {
+        s.split(',')
+            .map(|r| r.trim())
+            .filter(|r| !r.is_empty())
+            .map(|r| {
+                let (path, t) = r.split_once('=').c(d!(r.to_owned()))?;
+                if !path.starts_with('/') {
+                    return Err(eg!("paths must start with '/'"));
+                }
+                t.parse::<T>()
+                    .c(d!(r.to_owned()))
+                    .map(|t| (path.to_owned(), t))
+            })
+            .collect()
+    }
+}
+
+lazy_static! {
+    static ref RUNTIME_CFG: RwLock<RuntimeConfig> =
+        RwLock::new(RuntimeConfig::default());
+}
+
+/// The settings of a node which can be reloaded without a restart,
+/// by SIGHUP or the `reload_config` route of the admin server,
+/// from the TOML file of `--runtime-config`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// the filter of the logs, in the format of `RUST_LOG`
+    pub log_filter: Option<String>,
+    /// overriding `ApiServerConfig::cors_origins`
+    pub cors_origins: Option<Vec<String>>,
+    /// the submissions accepted from a client per second, unlimited if 0,
+    /// the clients are told apart by their IPs, see `trusted_proxies`
+    pub max_submissions_per_sec: u64,
+    /// the proxies in front of the submission server, the IP of a client behind
+    /// them is the last one of `X-Forwarded-For`, the one seen by the proxy,
+    /// instead of the IP of the proxy shared by all its clients
+    pub trusted_proxies: Vec<IpAddr>,
+    /// the minimal fee of the transactions accepted by the mempool,
+    /// the ones exempted from fees are not affected
+    pub mempool_min_fee: Option<u64>,
+    /// must stay the last field, tables come after values in TOML
+    pub submission_access: SubmissionAccess,
+}
+
+/// Who can submit transactions to the submission server, eg. in private deployments,
+/// the denials take precedence over the allowances.
+///
+/// The IPs are single addresses or CIDR blocks, eg. `10.0.0.0/8`,
+/// the keys are in any format of addresses, a transaction matches a key
+/// if it is signed by it.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SubmissionAccess {
+    /// any IP is allowed if empty
+    pub allow_ips: Vec<String>,
+    #[allow(missing_docs)]
+    pub deny_ips: Vec<String>,
+    /// any key is allowed if empty
+    pub allow_keys: Vec<String>,
+    #[allow(missing_docs)]
+    pub deny_keys: Vec<String>,
+}
+
+impl RuntimeConfig {
+    /// Load from the file in `path`, the defaults if it does not exist.
+    pub fn from_file(path: &str) -> Result<RuntimeConfig> {
+        match fs::read_to_string(path) {
+            Ok(s) => toml::from_str(&s).c(d!(path.to_owned())),
+            Err(e) if ErrorKind::NotFound == e.kind() => Ok(RuntimeConfig::default()),
+            Err(e) => Err(e).c(d!(path.to_owned())),
+        }
+    }
+
+    /// The settings in force.
+    pub fn current() -> RuntimeConfig {
+        RUNTIME_CFG.read().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// Put in force.
+    pub fn apply(self) {
+        if let Ok(mut c) = RUNTIME_CFG.write() {
+            *c = self;
+        }
+    }
+}
+
 pub mod global_cfg {
-    use crate::abci::CheckPointConfig;
+    use crate::abci::{ApiServerConfig, CheckPointConfig};
     #[cfg(target_os = "linux")]
     use btm::BtmCfg;
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     #[cfg(target_os = "linux")]
     use btm::{SnapAlgo, SnapMode, STEP_CNT};
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     use clap::{crate_authors, App, Arg, ArgMatches};
     use lazy_static::lazy_static;
     use ruc::*;
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     use std::{env, process::exit};
 
     lazy_static! {
@@ -273,6 +477,7 @@ pub mod global_cfg {
         pub tendermint_host: String,
         pub tendermint_port: u16,
         pub submission_service_port: u16,
+        pub admin_port: u16,
         pub ledger_service_port: u16,
         pub enable_query_service: bool,
         pub disable_eth_empty_blocks: bool,
@@ -285,9 +490,28 @@ pub mod global_cfg {
         #[cfg(target_os = "linux")]
         pub btmcfg: BtmCfg,
         pub checkpoint: CheckPointConfig,
+        pub anchor_webhook: Option<String>,
+        pub anchor_itv: u64,
+        pub state_sync_itv: u64,
+        pub enable_memo_relay: bool,
+        pub block_archive: Option<String>,
+        pub replay_archive: Option<String>,
+        pub replay_until: Option<u64>,
+        pub export_state: Option<String>,
+        pub txn_ordering: Option<String>,
+        pub ledger_sync_mode: Option<String>,
+        pub runtime_config: String,
+        pub query_namespaces: Vec<(String, String)>,
+        pub api: ApiServerConfig,
     }
 
-    #[cfg(test)]
+    // there are no command line arguments in browsers
+    #[cfg(target_arch = "wasm32")]
+    fn get_config() -> Result<Config> {
+        Ok(Config::default())
+    }
+
+    #[cfg(all(test, not(target_arch = "wasm32")))]
     fn get_config() -> Result<Config> {
         Ok(Config {
             ledger_dir: globutils::fresh_tmp_dir().to_string_lossy().into_owned(),
@@ -295,7 +519,7 @@ pub mod global_cfg {
         })
     }
 
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     fn get_config() -> Result<Config> {
         let m = App::new("abcid")
             .version(env!("VERGEN_SHA"))
@@ -307,6 +531,7 @@ pub mod global_cfg {
             .arg_from_usage("--tendermint-port=[Tendermint Port]")
             .arg_from_usage("--submission-service-port=[Submission Service Port]")
             .arg_from_usage("--ledger-service-port=[Ledger Service Port]")
+            .arg_from_usage("--admin-port=[Admin Port] 'the port of the admin server, eg. for the shutdown route, only listening on 127.0.0.1, default to 8670'")
             .arg_from_usage("-q, --enable-query-service")
             .arg_from_usage("--disable-eth-empty-blocks 'not generate empty ethereum blocks when no evm transaction'")
             .arg_from_usage("--enable-eth-api-service")
@@ -326,6 +551,24 @@ pub mod global_cfg {
             .arg_from_usage("--snapshot-rollback 'rollback to the last available snapshot'")
             .arg_from_usage("-r, --snapshot-rollback-to=[Height] 'rollback to a custom height, will try the closest smaller height if the target does not exist'")
             .arg_from_usage("-R, --snapshot-rollback-to-exact=[Height] 'rollback to a custom height exactly, an error will be reported if the target does not exist'")
+            .arg_from_usage("--anchor-webhook=[URL] 'publish state commitments to an external chain through this webhook'")
+            .arg_from_usage("--anchor-itv=[Iterval] 'interval between adjacent anchors, default to 100 blocks'")
+            .arg_from_usage("--state-sync-itv=[Iterval] 'interval between adjacent state-sync snapshots, default to 0(disabled)'")
+            .arg_from_usage("--enable-memo-relay 'relay the owner memos delivered off-chain, requires the query service'")
+            .arg_from_usage("--block-archive=[Path] 'append the committed blocks to this file, to be replayed by fn dev replay'")
+            .arg_from_usage("--replay-archive=[Path] 'replay a block archive into an empty ledger in the ledger dir, then exit'")
+            .arg_from_usage("--replay-until=[Height] 'stop the replay of --replay-archive after this height'")
+            .arg_from_usage("--export-state=[Path] 'export the state of the ledger in the ledger dir to this file, after the replay if any, then exit'")
+            .arg_from_usage("--txn-ordering=[Policy] 'the order of the transactions waiting for their parents, released together to tendermint once these are committed: fifo/fee/sender, default to fifo, the other ones are forwarded in the order of arrival'")
+            .arg_from_usage("--ledger-sync-mode=[Mode] 'when the utxo map and the merkle trees are synced to disk: always/off/batched[:<Blocks>], batched with a write-ahead log and a group commit every 100 blocks by default, default to always'")
+            .arg_from_usage("--runtime-config=[Path] 'the settings reloadable by SIGHUP, eg. the log filter and the CORS origins, default to <ledger-dir>/runtime.toml'")
+            .arg_from_usage("--query-namespaces=[Namespaces] 'serve the ledgers of other networks under /<name>, followed from the block archives of their nodes, eg. testnet=/data/testnet.archive,dev=/tmp/dev.archive'")
+            .arg_from_usage("--api-cors-origins=[Origins] 'the origins allowed to call the http servers, separated by commas, any origin by default'")
+            .arg_from_usage("--api-max-body-size=[Bytes] 'the maximal size of the requests to the http servers, default to 2MB'")
+            .arg_from_usage("--api-timeout=[Secs] 'the timeout of the requests to the http servers, default to 0(disabled)'")
+            .arg_from_usage("--api-route-timeouts=[Routes] 'timeouts of some routes, overriding --api-timeout, eg. /submit_transaction=10,/search=30'")
+            .arg_from_usage("--api-compression-min-size=[Bytes] 'the minimal size of the responses compressed by gzip or zstd, if accepted by the clients, default to 1024, 0 to disable'")
+            .arg_from_usage("--api-route-compression-min-sizes=[Routes] 'minimal sizes of some routes, overriding --api-compression-min-size, eg. /owned_utxos=256,/ping=0'")
             .arg(Arg::with_name("_a").long("ignored").hidden(true))
             .arg(Arg::with_name("_b").long("nocapture").hidden(true))
             .arg(Arg::with_name("_c").long("test-threads").hidden(true))
@@ -365,6 +608,13 @@ pub mod global_cfg {
             .unwrap_or_else(|| "8669".to_owned())
             .parse::<u16>()
             .c(d!())?;
+        let adp = m
+            .value_of("admin-port")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("ADMIN_PORT").ok())
+            .unwrap_or_else(|| "8670".to_owned())
+            .parse::<u16>()
+            .c(d!())?;
         let lsp = m
             .value_of("ledger-service-port")
             .map(|v| v.to_owned())
@@ -412,6 +662,57 @@ pub mod global_cfg {
             .value_of("checkpoint-file")
             .map(|v| v.to_owned())
             .unwrap_or_else(|| String::from("./checkpoint.toml"));
+        let aw = m
+            .value_of("anchor-webhook")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("ANCHOR_WEBHOOK").ok());
+        let ai = m
+            .value_of("anchor-itv")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("ANCHOR_ITV").ok())
+            .unwrap_or_else(|| "100".to_owned())
+            .parse::<u64>()
+            .c(d!())?;
+        let ssi = m
+            .value_of("state-sync-itv")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("STATE_SYNC_ITV").ok())
+            .unwrap_or_else(|| "0".to_owned())
+            .parse::<u64>()
+            .c(d!())?;
+        let emr =
+            m.is_present("enable-memo-relay") || env::var("ENABLE_MEMO_RELAY").is_ok();
+        let ba = m
+            .value_of("block-archive")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("BLOCK_ARCHIVE").ok());
+        let ra = m.value_of("replay-archive").map(|v| v.to_owned());
+        let ru = m
+            .value_of("replay-until")
+            .map(|v| v.parse::<u64>().c(d!()))
+            .transpose()?;
+        let es = m.value_of("export-state").map(|v| v.to_owned());
+        let to = m
+            .value_of("txn-ordering")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("TXN_ORDERING").ok());
+        let lsm = m
+            .value_of("ledger-sync-mode")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("LEDGER_SYNC_MODE").ok());
+        let rc = m
+            .value_of("runtime-config")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("RUNTIME_CONFIG").ok())
+            .unwrap_or_else(|| format!("{}/runtime.toml", ld));
+        let qn = m
+            .value_of("query-namespaces")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("QUERY_NAMESPACES").ok())
+            .map(|v| parse_query_namespaces(&v).c(d!()))
+            .transpose()?
+            .unwrap_or_default();
+        let api = parse_api_cfg(&m).c(d!())?;
 
         let res = Config {
             abci_host: ah,
@@ -419,6 +720,7 @@ pub mod global_cfg {
             tendermint_host: th,
             tendermint_port: tp,
             submission_service_port: ssp,
+            admin_port: adp,
             ledger_service_port: lsp,
             enable_query_service: eqs,
             disable_eth_empty_blocks: eeb,
@@ -431,12 +733,88 @@ pub mod global_cfg {
             #[cfg(target_os = "linux")]
             btmcfg: parse_btmcfg(&m).c(d!())?,
             checkpoint: CheckPointConfig::from_file(&checkpoint_path).unwrap(),
+            anchor_webhook: aw,
+            anchor_itv: ai,
+            state_sync_itv: ssi,
+            enable_memo_relay: emr,
+            block_archive: ba,
+            replay_archive: ra,
+            replay_until: ru,
+            export_state: es,
+            txn_ordering: to,
+            ledger_sync_mode: lsm,
+            runtime_config: rc,
+            query_namespaces: qn,
+            api,
         };
 
         Ok(res)
     }
 
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
+    fn parse_api_cfg(m: &ArgMatches) -> Result<ApiServerConfig> {
+        let arg = |name: &str, var: &str| {
+            m.value_of(name)
+                .map(|v| v.to_owned())
+                .or_else(|| env::var(var).ok())
+        };
+
+        let mut res = ApiServerConfig::default();
+        if let Some(o) = arg("api-cors-origins", "API_CORS_ORIGINS") {
+            res.cors_origins = o
+                .split(',')
+                .map(|o| o.trim().to_owned())
+                .filter(|o| !o.is_empty())
+                .collect();
+        }
+        if let Some(s) = arg("api-max-body-size", "API_MAX_BODY_SIZE") {
+            res.max_body_size = s.parse::<usize>().c(d!())?;
+        }
+        if let Some(t) = arg("api-timeout", "API_TIMEOUT") {
+            res.timeout = t.parse::<u64>().c(d!())?;
+        }
+        if let Some(r) = arg("api-route-timeouts", "API_ROUTE_TIMEOUTS") {
+            res.route_timeouts = ApiServerConfig::parse_route_timeouts(&r).c(d!())?;
+        }
+        if let Some(s) = arg("api-compression-min-size", "API_COMPRESSION_MIN_SIZE") {
+            res.compression_min_size = s.parse::<usize>().c(d!())?;
+        }
+        if let Some(r) = arg(
+            "api-route-compression-min-sizes",
+            "API_ROUTE_COMPRESSION_MIN_SIZES",
+        ) {
+            res.route_compression_min_sizes =
+                ApiServerConfig::parse_route_compression_min_sizes(&r).c(d!())?;
+        }
+
+        Ok(res)
+    }
+
+    #[cfg(not(any(test, target_arch = "wasm32")))]
+    fn parse_query_namespaces(s: &str) -> Result<Vec<(String, String)>> {
+        let mut res: Vec<(String, String)> = vec![];
+        for ns in s.split(',').map(|ns| ns.trim()).filter(|ns| !ns.is_empty()) {
+            let (name, path) = ns
+                .split_once('=')
+                .map(|(n, p)| (n.trim(), p.trim()))
+                .c(d!(format!("expected <name>=<archive path>: {}", ns)))?;
+            if name.is_empty()
+                || path.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || '-' == c || '_' == c)
+            {
+                return Err(eg!(format!("invalid query namespace: {}", ns)));
+            }
+            if res.iter().any(|(n, _)| n == name) {
+                return Err(eg!(format!("duplicate query namespace: {}", name)));
+            }
+            res.push((name.to_owned(), path.to_owned()));
+        }
+        Ok(res)
+    }
+
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     fn print_version(m: &ArgMatches) {
         if m.is_present("version") {
             println!("{}", env!("VERGEN_SHA"));
@@ -444,7 +822,7 @@ pub mod global_cfg {
         }
     }
 
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     #[cfg(target_os = "linux")]
     fn parse_btmcfg(m: &ArgMatches) -> Result<BtmCfg> {
         let mut res = BtmCfg::new();
@@ -500,7 +878,7 @@ pub mod global_cfg {
         Ok(res)
     }
 
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     #[cfg(target_os = "linux")]
     fn list_snapshots(cfg: &BtmCfg) -> Result<()> {
         println!("Available snapshots are listed below:");
@@ -514,7 +892,7 @@ pub mod global_cfg {
         exit(0);
     }
 
-    #[cfg(not(test))]
+    #[cfg(not(any(test, target_arch = "wasm32")))]
     #[cfg(target_os = "linux")]
     fn check_rollback(m: &ArgMatches, cfg: &BtmCfg) -> Result<()> {
         const HINTS: &str = r#"    NOTE:
@@ -175,6 +175,9 @@ pub mod config {
         pub snapshot_rollback: bool,
         pub snapshot_rollback_to: Option<String>,
         pub snapshot_rollback_to_exact: Option<String>,
+        pub anchor_webhook: Option<String>,
+        pub anchor_itv: Option<String>,
+        pub state_sync_itv: Option<String>,
     }
 
     fn get_config() -> Result<Config> {
@@ -206,7 +209,10 @@ pub mod config {
                 .arg_from_usage("--snapshot-rollback 'rollback to the last available snapshot'")
                 .arg_from_usage("-r, --snapshot-rollback-to=[Height] 'rollback to a custom height, will try the closest smaller height if the target does not exist'")
                 .arg_from_usage("--checkpoint-file=[Path]")
-                .arg_from_usage("-R, --snapshot-rollback-to-exact=[Height] 'rollback to a custom height exactly, an error will be reported if the target does not exist'");
+                .arg_from_usage("-R, --snapshot-rollback-to-exact=[Height] 'rollback to a custom height exactly, an error will be reported if the target does not exist'")
+                .arg_from_usage("--anchor-webhook=[URL] 'publish state commitments to an external chain through this webhook'")
+                .arg_from_usage("--anchor-itv=[Iterval] 'interval between adjacent anchors, default to 100 blocks'")
+                .arg_from_usage("--state-sync-itv=[Iterval] 'interval between adjacent state-sync snapshots, default to 0(disabled)'");
 
             let init = SubCommand::with_name("init")
                 .about("Initialize the configurations of findorad")
@@ -365,6 +371,9 @@ pub mod config {
             snapshot_rollback_to_exact: m
                 .value_of("snapshot-rollback-to-exact")
                 .map(|v| v.to_owned()),
+            anchor_webhook: m.value_of("anchor-webhook").map(|v| v.to_owned()),
+            anchor_itv: m.value_of("anchor-itv").map(|v| v.to_owned()),
+            state_sync_itv: m.value_of("state-sync-itv").map(|v| v.to_owned()),
         };
 
         Ok(res)
@@ -3,7 +3,7 @@ use core::convert::TryFrom;
 use core::fmt::Formatter;
 use core::str::FromStr;
 use fp_utils::{ecdsa, hashing::keccak_256};
-use globutils::wallet;
+use globutils::address;
 use hex::FromHex;
 use primitive_types::{H160, H256};
 use ruc::{d, eg, RucResult};
@@ -304,10 +304,8 @@ impl FromStr for MultiSigner {
             let address_hex = &s[2..];
             let inner = <[u8; 20]>::from_hex(address_hex).c(d!())?;
             Ok(MultiSigner::Ethereum(H160(inner)))
-        } else if let Ok(address) = wallet::public_key_from_base64(s) {
-            Ok(MultiSigner::Xfr(address))
         } else {
-            let address = wallet::public_key_from_bech32(s)?;
+            let address = address::parse_address(s)?;
             Ok(MultiSigner::Xfr(address))
         }
     }
@@ -3,10 +3,16 @@
 //!
 
 use {
-    ledger::staking::{
-        self, StakerMemo, TendermintAddr, MAX_POWER_PERCENT_PER_VALIDATOR,
+    globutils::HashOfBytes,
+    ledger::{
+        data_model::TxoSID,
+        staking::{self, StakerMemo, TendermintAddr, MAX_POWER_PERCENT_PER_VALIDATOR},
     },
     serde::{Deserialize, Serialize},
+    zei::{
+        serialization::ZeiFromToBytes,
+        xfr::{sig::XfrPublicKey, structs::OwnerMemo},
+    },
 };
 
 /// A list of basic validator information of current height
@@ -95,6 +101,9 @@ pub struct ValidatorDetail {
     pub validator_realtime_apy: [u128; 2],
     /// total number of its delegators
     pub delegator_cnt: u64,
+    /// removed from the active set for a self-delegation below the minimum
+    #[serde(default)]
+    pub jailed: bool,
 }
 
 #[allow(missing_docs)]
@@ -185,6 +194,77 @@ impl DelegationInfo {
     }
 }
 
+/// A delegation bonded to one validator
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BondEntry {
+    #[allow(missing_docs)]
+    pub validator: TendermintAddr,
+    #[allow(missing_docs)]
+    pub amount: u64,
+    /// The height since which the amount is bonded
+    pub since: u64,
+}
+
+/// The active delegations of a delegator
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DelegatorDelegations {
+    /// The last committed height
+    pub height: u64,
+    #[allow(missing_docs)]
+    pub delegations: Vec<BondEntry>,
+}
+
+/// An undelegated amount in its unbonding period
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UnbondingEntry {
+    #[allow(missing_docs)]
+    pub validator: TendermintAddr,
+    #[allow(missing_docs)]
+    pub amount: u64,
+    /// The height at which the amount is paid back, with its rewards
+    pub end_height: u64,
+}
+
+/// The undelegations of a delegator which have not been paid back yet
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DelegatorUnbondings {
+    /// The last committed height
+    pub height: u64,
+    #[allow(missing_docs)]
+    pub unbondings: Vec<UnbondingEntry>,
+}
+
+/// The rewards accrued by a delegator and not paid yet
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DelegatorRewards {
+    /// The last committed height
+    pub height: u64,
+    /// The rewards of the delegation
+    pub rewards: u64,
+    /// The rewards of the partial undelegations in their unbonding periods,
+    /// paid back with them
+    pub unbonding_rewards: u64,
+    /// how many times of rewards received
+    pub delegation_rwd_cnt: u64,
+    /// how many times of proposing this validator has performed
+    pub proposer_rwd_cnt: u64,
+}
+
+/// An owner memo delivered through the memo relay of a query server.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RelayedMemo {
+    /// The output that the memo opens
+    pub txo_sid: TxoSID,
+    #[allow(missing_docs)]
+    pub memo: OwnerMemo,
+}
+
+/// The key under which the memos of `recipient` are relayed,
+/// the hex of the sha256 hash of the public key.
+pub fn memo_relay_key(recipient: &XfrPublicKey) -> String {
+    hex::encode(HashOfBytes::new(&recipient.zei_to_bytes()))
+}
+
 #[allow(missing_docs)]
 pub trait NetworkRoute {
     fn route(&self) -> String;
@@ -14,6 +14,7 @@
 //!     - "--validator-memo=[StakingMemo, default to empty]"
 //! - claim
 //!     - "--amount=[Amout <Optional, default to 'all'>]"
+//! - unjail
 //! - unstake
 //! - show, query real-time state of your staking
 //! - setup
@@ -29,13 +30,28 @@ use {
     clap::{crate_authors, load_yaml, App},
     finutils::common::{
         self,
-        dev::{EnvCfg, Ops},
+        auditor::{
+            self, ColdAuditCfg, DaemonCfg, GossipCfg, GraphCfg, GraphFormat, ReportCfg,
+            ReportFormat,
+        },
+        checksum, deposit, designated_transfer,
+        dev::{
+            parse_mix, BenchCfg, ChaosOps, EnvCfg, Host, LoadgenCfg, ManifestKind, Ops,
+            ReplayCfg, UpgradeCfg,
+        },
         evm::*,
+        explorer, inspect, invoice, kv, memo_relay, node, notary, notify, offline,
+        payment_request, payment_stream, prism, pull_payment, session_key, signing_log,
+        spending_policy, swap, sync, template, txo_label, vesting, viewing,
     },
+    finutils::txn_builder::SWEEP_MAX_INPUTS,
     fp_utils::ecdsa::SecpPair,
-    globutils::wallet,
+    globutils::{address, payment_request::PaymentRequest, wallet},
     ledger::{
-        data_model::{AssetTypeCode, FRA_DECIMALS},
+        converter::{kv::KvAction, prism::PrismWithdrawBody},
+        data_model::{
+            AssetMetadata, AssetTypeCode, TxoSID, ASSET_TYPE_FRA, FRA_DECIMALS,
+        },
         staking::StakerMemo,
     },
     ruc::*,
@@ -57,6 +73,10 @@ fn run() -> Result<()> {
         .author(crate_authors!())
         .get_matches();
 
+    if let Some(ttl) = matches.value_of("ttl") {
+        common::utils::set_tx_ttl(ttl.parse::<u64>().c(d!("invalid `--ttl`"))?);
+    }
+
     if matches.is_present("version") {
         println!("{}", env!("VERGEN_SHA"));
     } else if matches.is_present("genkey") {
@@ -81,9 +101,67 @@ fn run() -> Result<()> {
                 None
             };
             common::show_account(seckey.as_deref(), asset).c(d!())?;
+        } else if m.is_present("memos") {
+            let seckey = match m.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            common::show_memos(seckey.as_deref()).c(d!())?;
+        } else if m.is_present("sweep") {
+            let seckey = match m.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            let asset = m
+                .value_of("asset")
+                .filter(|code| "fra" != code.to_lowercase().as_str());
+            let max_inputs = m
+                .value_of("max-inputs")
+                .map(|n| n.parse::<usize>().c(d!()))
+                .transpose()?
+                .unwrap_or(SWEEP_MAX_INPUTS);
+            common::sweep(
+                seckey.as_deref(),
+                asset,
+                max_inputs,
+                m.is_present("dry-run"),
+            )
+            .c(d!())?;
         } else {
             println!("{}", m.usage());
         }
+    } else if let Some(m) = matches.subcommand_matches("asset-mirror") {
+        let code = m
+            .value_of("code")
+            .c(d!())
+            .and_then(|c| AssetTypeCode::new_from_base64(c).c(d!()))?;
+        if m.is_present("show") {
+            common::show_asset_mirrors(code).c(d!())?;
+        } else {
+            let seckey = match m.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            let chain_id = m.value_of("chain-id").c(d!())?.parse::<u64>().c(d!())?;
+            let contract = m
+                .value_of("contract")
+                .map(|c| {
+                    hex::decode(c.trim_start_matches("0x"))
+                        .c(d!())
+                        .and_then(|c| {
+                            <[u8; 20]>::try_from(c.as_slice()).c(d!("invalid address"))
+                        })
+                })
+                .transpose()?;
+            common::update_asset_mirror(seckey.as_deref(), code, chain_id, contract)
+                .c(d!())?;
+        }
     } else if let Some(m) = matches.subcommand_matches("delegate") {
         let seckey = match m.value_of("seckey") {
             Some(path) => {
@@ -91,17 +169,30 @@ fn run() -> Result<()> {
             }
             None => None,
         };
-        let amount = m.value_of("amount");
-        let validator = m.value_of("validator");
+        let amounts = m
+            .values_of("amount")
+            .map(|v| v.collect::<Vec<_>>())
+            .unwrap_or_default();
+        let validators = m
+            .values_of("validator")
+            .map(|v| v.collect::<Vec<_>>())
+            .unwrap_or_default();
         let show_info = m.is_present("info");
 
-        if amount.is_some() && validator.is_some() {
+        if 1 == amounts.len() && 1 == validators.len() {
             common::delegate(
                 seckey.as_deref(),
-                amount.unwrap().parse::<u64>().c(d!())?,
-                validator.unwrap(),
+                amounts[0].parse::<u64>().c(d!())?,
+                validators[0],
             )
             .c(d!())?;
+        } else if !amounts.is_empty() && amounts.len() == validators.len() {
+            let targets = validators
+                .into_iter()
+                .zip(amounts)
+                .map(|(v, am)| am.parse::<u64>().c(d!()).map(|am| (v, am)))
+                .collect::<Result<Vec<_>>>()?;
+            common::delegate_multi(seckey.as_deref(), &targets).c(d!())?;
         } else if show_info {
             common::show_delegations(seckey.as_deref()).c(d!())?;
         } else {
@@ -136,11 +227,22 @@ fn run() -> Result<()> {
                 }
                 None => None,
             };
-            let memo = m.value_of("memo");
+            let memo = if let Some(path) = m.value_of("metadata") {
+                let metadata = fs::read(path)
+                    .c(d!("Failed to read the metadata file"))
+                    .and_then(|r| {
+                        serde_json::from_slice::<AssetMetadata>(&r)
+                            .c(d!("invalid metadata"))
+                    })?;
+                Some(metadata.to_memo().c(d!())?.0)
+            } else {
+                m.value_of("memo").map(|m| m.to_owned())
+            };
             if memo.is_none() {
                 println!("{}", m.usage());
                 return Ok(());
             }
+            let memo = memo.as_deref();
             let transferable = m.is_present("transferable");
             let decimal = if let Some(num) = m.value_of("decimal") {
                 num.parse::<u8>()
@@ -157,15 +259,28 @@ fn run() -> Result<()> {
                 None
             };
             let token_code = m.value_of("code");
-            common::create_asset(
-                seckey.as_deref(),
-                memo.unwrap(),
-                decimal,
-                max_units,
-                transferable,
-                token_code,
-            )
-            .c(d!())?;
+            if let Some(path) = m.value_of("rules") {
+                let rules = fs::read(path)
+                    .c(d!("Failed to read the rules file"))
+                    .and_then(|r| serde_json::from_slice(&r).c(d!("invalid rules")))?;
+                common::create_asset_with_rules(
+                    seckey.as_deref(),
+                    memo.unwrap(),
+                    rules,
+                    token_code,
+                )
+                .c(d!())?;
+            } else {
+                common::create_asset(
+                    seckey.as_deref(),
+                    memo.unwrap(),
+                    decimal,
+                    max_units,
+                    transferable,
+                    token_code,
+                )
+                .c(d!())?;
+            }
         } else if m.is_present("show") {
             let addr = m.value_of("addr");
             if addr.is_none() {
@@ -285,9 +400,30 @@ fn run() -> Result<()> {
             None => None,
         };
         common::claim(am, seckey.as_deref()).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("unjail") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        common::unjail(seckey.as_deref()).c(d!())?;
     } else if let Some(m) = matches.subcommand_matches("show") {
         let basic = m.is_present("basic");
         common::show(basic).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("staker-status") {
+        let td_addr = m.value_of("validator-td-addr");
+        let itv = if m.is_present("watch") {
+            Some(
+                m.value_of("interval")
+                    .unwrap_or("5")
+                    .parse::<u64>()
+                    .c(d!())?,
+            )
+        } else {
+            None
+        };
+        common::staker_status(td_addr, itv).c(d!())?;
     } else if let Some(m) = matches.subcommand_matches("setup") {
         let sa = m.value_of("serv-addr");
         let om = m.value_of("owner-mnemonic-path");
@@ -304,35 +440,184 @@ fn run() -> Result<()> {
             }
             None => None,
         };
-        let asset = m.value_of("asset").unwrap_or("FRA");
-        let t = m
-            .value_of("to-pubkey")
-            .c(d!())
-            .and_then(|pk| wallet::public_key_from_base64(pk).c(d!()))
-            .or_else(|_| {
-                m.value_of("to-wallet-address").c(d!()).and_then(|addr| {
-                    wallet::public_key_from_bech32(addr).c(d!("invalid wallet address"))
-                })
-            })?;
-        let am = m.value_of("amount");
+        let request = m
+            .value_of("request")
+            .map(|uri| {
+                PaymentRequest::from_uri(uri)
+                    .c(d!())
+                    .and_then(|req| req.check_expiry().c(d!()).map(|_| req))
+            })
+            .transpose()?;
+        // the parameters set by the request can not be changed
+        let fill = |name: &str, requested: Option<String>| -> Result<Option<String>> {
+            match (m.value_of(name), requested) {
+                (Some(v), Some(r)) if v != r => Err(eg!(format!(
+                    "`--{}` conflicts with the payment request",
+                    name
+                ))),
+                (v, r) => Ok(r.or_else(|| v.map(|v| v.to_owned()))),
+            }
+        };
+        let asset = fill("asset", request.as_ref().and_then(|r| r.asset.clone()))?
+            .unwrap_or_else(|| "FRA".to_owned());
+        let am = fill(
+            "amount",
+            request
+                .as_ref()
+                .and_then(|r| r.amount.map(|am| am.to_string())),
+        )?;
+        let memo = fill("memo", request.as_ref().and_then(|r| r.memo.clone()))?;
+        let t = match request.as_ref() {
+            Some(req) => req.receiver().c(d!())?,
+            None => m
+                .value_of("to-pubkey")
+                .c(d!())
+                .and_then(|pk| address::parse_address(pk).c(d!()))
+                .or_else(|_| {
+                    m.value_of("to-wallet-address").c(d!()).and_then(|addr| {
+                        wallet::public_key_from_bech32(addr)
+                            .c(d!("invalid wallet address"))
+                    })
+                })?,
+        };
 
         if am.is_none() {
             println!("{}", m.usage());
         } else {
-            let token_code = if asset.to_uppercase() != "FRA" {
-                Some(AssetTypeCode::new_from_base64(asset).c(d!())?)
-            } else {
-                None
+            let parse_code = |asset: &str| -> Result<Option<AssetTypeCode>> {
+                if asset.to_uppercase() != "FRA" {
+                    AssetTypeCode::new_from_base64(asset).c(d!()).map(Some)
+                } else {
+                    Ok(None)
+                }
             };
-            common::transfer_asset(
-                f.as_deref(),
-                t,
-                token_code,
-                am.unwrap(),
-                m.is_present("confidential-amount"),
-                m.is_present("confidential-type"),
+            let token_code = parse_code(&asset)?;
+
+            if let Some(extra) = m.values_of("extra-asset") {
+                let mut assets = vec![(token_code, am.as_deref().unwrap())];
+                for i in extra {
+                    let (asset, am) = i
+                        .split_once(':')
+                        .c(d!("'extra-asset' must be in the form of ASSET:AMOUNT"))?;
+                    assets.push((parse_code(asset)?, am));
+                }
+                common::transfer_multi_asset(
+                    f.as_deref(),
+                    t,
+                    &assets,
+                    m.is_present("confidential-amount"),
+                    m.is_present("confidential-type"),
+                    m.is_present("fresh-change"),
+                    memo.as_deref(),
+                )
+                .c(d!())?;
+            } else {
+                common::transfer_asset(
+                    f.as_deref(),
+                    t,
+                    token_code,
+                    am.as_deref().unwrap(),
+                    m.is_present("confidential-amount"),
+                    m.is_present("confidential-type"),
+                    m.is_present("fresh-change"),
+                    memo.as_deref(),
+                )
+                .c(d!())?;
+            }
+        }
+    } else if let Some(m) = matches.subcommand_matches("request-payment") {
+        let receiver = match m.value_of("address") {
+            Some(addr) => address::parse_address(addr).c(d!())?,
+            None => common::get_keypair().c(d!())?.get_pk(),
+        };
+        let mut req = PaymentRequest::new(&receiver);
+        if let Some(asset) = m.value_of("asset").filter(|a| a.to_uppercase() != "FRA") {
+            AssetTypeCode::new_from_base64(asset).c(d!("invalid asset code"))?;
+            req.asset = Some(asset.to_owned());
+        }
+        req.amount = m
+            .value_of("amount")
+            .map(|am| am.parse::<u64>().c(d!("invalid amount")))
+            .transpose()?;
+        req.memo = m.value_of("memo").map(|memo| memo.to_owned());
+        if let Some(secs) = m.value_of("expires-in") {
+            let secs = secs.parse::<u64>().c(d!("invalid `--expires-in`"))?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .c(d!())?
+                .as_secs();
+            req.expiry = Some(now + secs);
+        }
+        payment_request::request_payment(&req, !m.is_present("no-qr")).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("invoice") {
+        let seckey = |sm: &clap::ArgMatches| {
+            sm.value_of("seckey")
+                .map(|path| fs::read_to_string(path).c(d!("Failed to read seckey file")))
+                .transpose()
+        };
+        if let Some(sm) = m.subcommand_matches("create") {
+            let amount = sm
+                .value_of("amount")
+                .c(d!())?
+                .parse::<u64>()
+                .c(d!("invalid amount"))?;
+            let expires_in = sm
+                .value_of("expires-in")
+                .map(|s| s.parse::<u64>().c(d!("invalid `--expires-in`")))
+                .transpose()?;
+            let inv = invoice::create(
+                seckey(sm)?.as_deref(),
+                amount,
+                sm.value_of("asset"),
+                sm.value_of("reference"),
+                expires_in,
             )
             .c(d!())?;
+            println!("\x1b[31;01mInvoice:\x1b[00m {}", inv.reference);
+            payment_request::request_payment(&inv.to_request(), !sm.is_present("no-qr"))
+                .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("share") {
+            let inv = invoice::get(sm.value_of("reference").c(d!())?).c(d!())?;
+            if matches!(
+                inv.status,
+                invoice::InvoiceStatus::Settled | invoice::InvoiceStatus::Cancelled
+            ) {
+                return Err(eg!(format!("invoice `{}` is not open", inv.reference)));
+            }
+            payment_request::request_payment(&inv.to_request(), !sm.is_present("no-qr"))
+                .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("sync") {
+            for reference in invoice::sync(seckey(sm)?.as_deref()).c(d!())? {
+                println!("{}: settled", reference);
+            }
+        } else if let Some(sm) = m.subcommand_matches("cancel") {
+            invoice::cancel(sm.value_of("reference").c(d!())?).c(d!())?;
+        } else if m.subcommand_matches("list").is_some() {
+            invoice::show().c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("deposit") {
+        if let Some(sm) = m.subcommand_matches("address") {
+            let (idx, addr) =
+                deposit::address_of(sm.value_of("customer").c(d!())?).c(d!())?;
+            println!("{} {}: {}", idx, addr.customer, addr.address);
+        } else if let Some(sm) = m.subcommand_matches("scan") {
+            let credits = deposit::scan().c(d!())?;
+            if sm.is_present("json") {
+                println!("{}", serde_json::to_string_pretty(&credits).c(d!())?);
+            } else {
+                for c in credits {
+                    println!(
+                        "txo {}: {} {} to {} (index {})",
+                        c.txo_sid.0, c.amount, c.asset, c.customer, c.index
+                    );
+                }
+            }
+        } else if m.subcommand_matches("list").is_some() {
+            deposit::show().c(d!())?;
+        } else {
+            println!("{}", m.usage());
         }
     } else if let Some(m) = matches.subcommand_matches("transfer-batch") {
         let f = match m.value_of("from-seckey") {
@@ -347,7 +632,7 @@ fn run() -> Result<()> {
             .and_then(|f| {
                 fs::read_to_string(f).c(d!()).and_then(|pks| {
                     pks.lines()
-                        .map(|pk| wallet::public_key_from_base64(pk.trim()))
+                        .map(address::parse_address)
                         .collect::<Result<Vec<_>>>()
                         .c(d!("invalid file"))
                 })
@@ -375,6 +660,8 @@ fn run() -> Result<()> {
                 am.unwrap(),
                 m.is_present("confidential-amount"),
                 m.is_present("confidential-type"),
+                m.is_present("fresh-change"),
+                m.value_of("memo"),
             )
             .c(d!())?;
         }
@@ -400,11 +687,567 @@ fn run() -> Result<()> {
         let address = m.value_of("addr");
         let eth_key = m.value_of("eth-key");
         transfer_from_account(amount.parse::<u64>().c(d!())?, address, eth_key)?
+    } else if let Some(m) = matches.subcommand_matches("prism") {
+        if let Some(sm) = m.subcommand_matches("deposit") {
+            let amount = sm.value_of("amount").c(d!())?;
+            prism::deposit(
+                amount.parse::<u64>().c(d!())?,
+                sm.value_of("code"),
+                sm.value_of("addr").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("withdraw") {
+            let burn_tx =
+                hex::decode(sm.value_of("burn-tx").c(d!())?.trim_start_matches("0x"))
+                    .c(d!())?;
+            let body = PrismWithdrawBody {
+                chain_id: sm.value_of("chain-id").c(d!())?.parse::<u64>().c(d!())?,
+                burn_tx: <[u8; 32]>::try_from(burn_tx.as_slice())
+                    .c(d!("invalid hash"))?,
+                asset: sm
+                    .value_of("code")
+                    .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+                    .transpose()?
+                    .unwrap_or(AssetTypeCode {
+                        val: ASSET_TYPE_FRA,
+                    }),
+                receiver: address::parse_address(sm.value_of("addr").c(d!())?)?,
+                amount: sm.value_of("amount").c(d!())?.parse::<u64>().c(d!())?,
+            };
+            prism::withdraw(body, sm.is_present("sign-only"), sm.value_of("signatures"))
+                .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("update-authorities") {
+            let keys = sm
+                .value_of("keys")
+                .c(d!())?
+                .split(',')
+                .map(|k| k.trim())
+                .filter(|k| !k.is_empty())
+                .map(address::parse_address)
+                .collect::<Result<Vec<_>>>()?;
+            prism::update_authorities(
+                keys,
+                sm.value_of("threshold").c(d!())?.parse::<usize>().c(d!())?,
+                sm.is_present("sign-only"),
+                sm.value_of("signatures"),
+            )
+            .c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("swap") {
+        // ASSET:AMOUNT, the asset is FRA or a base64 code
+        let parse_leg = |leg: &str| -> Result<(Option<AssetTypeCode>, u64)> {
+            let (asset, am) = leg
+                .split_once(':')
+                .c(d!("must be in the form of ASSET:AMOUNT"))?;
+            let code = if asset.to_uppercase() != "FRA" {
+                Some(AssetTypeCode::new_from_base64(asset).c(d!())?)
+            } else {
+                None
+            };
+            Ok((
+                code,
+                am.parse::<u64>().c(d!("'amount' must be an integer"))?,
+            ))
+        };
+
+        if let Some(sm) = m.subcommand_matches("initiate") {
+            swap::initiate(
+                address::parse_address(sm.value_of("counterparty").c(d!())?)?,
+                parse_leg(sm.value_of("give").c(d!())?)?,
+                parse_leg(sm.value_of("want").c(d!())?)?,
+                sm.is_present("confidential-amount"),
+                sm.is_present("confidential-type"),
+                sm.value_of("out").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("accept") {
+            swap::accept(
+                sm.value_of("proposal").c(d!())?,
+                sm.value_of("out").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("finish") {
+            swap::finish(
+                sm.value_of("proposal").c(d!())?,
+                sm.value_of("accepted").c(d!())?,
+            )
+            .c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("pull-payment") {
+        if let Some(sm) = m.subcommand_matches("authorize") {
+            pull_payment::authorize(
+                address::parse_address(sm.value_of("payee").c(d!())?)?,
+                sm.value_of("amount").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("limit").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("period").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("code"),
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("pull") {
+            pull_payment::pull(
+                sm.value_of("id").c(d!())?,
+                sm.value_of("amount").c(d!())?.parse::<u64>().c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("revoke") {
+            pull_payment::revoke(sm.value_of("id").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let auth =
+                pull_payment::get_authorization(sm.value_of("id").c(d!())?).c(d!())?;
+            println!("{}", serde_json::to_string_pretty(&auth).c(d!())?);
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("stream") {
+        if let Some(sm) = m.subcommand_matches("create") {
+            payment_stream::create(
+                address::parse_address(sm.value_of("recipient").c(d!())?)?,
+                sm.value_of("amount").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("start").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("end").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("code"),
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("claim") {
+            let amount = sm
+                .value_of("amount")
+                .map(|am| am.parse::<u64>().c(d!()))
+                .transpose()?;
+            payment_stream::claim(sm.value_of("id").c(d!())?, amount).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("cancel") {
+            payment_stream::cancel(sm.value_of("id").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let status =
+                payment_stream::get_stream(sm.value_of("id").c(d!())?).c(d!())?;
+            println!("{}", serde_json::to_string_pretty(&status).c(d!())?);
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("designated") {
+        if let Some(sm) = m.subcommand_matches("send") {
+            designated_transfer::send(
+                address::parse_address(sm.value_of("recipient").c(d!())?)?,
+                sm.value_of("amount").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("blocks").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("code"),
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("claim") {
+            designated_transfer::claim(sm.value_of("id").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("reclaim") {
+            designated_transfer::reclaim(sm.value_of("id").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let status = designated_transfer::get_designated(sm.value_of("id").c(d!())?)
+                .c(d!())?;
+            println!("{}", serde_json::to_string_pretty(&status).c(d!())?);
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("policy") {
+        if let Some(sm) = m.subcommand_matches("set") {
+            let amount = |name: &str| {
+                sm.value_of(name)
+                    .map(|v| v.parse::<u64>().c(d!()))
+                    .transpose()
+            };
+            let args = spending_policy::PolicyArgs {
+                code: sm.value_of("code"),
+                limit: amount("limit")?,
+                period: amount("period")?,
+                cosigner: sm
+                    .value_of("cosigner")
+                    .map(address::parse_address)
+                    .transpose()?,
+                cosign_above: amount("cosign-above")?,
+                allowed: sm
+                    .values_of("allow")
+                    .map(|v| v.map(address::parse_address).collect::<Result<Vec<_>>>())
+                    .transpose()?
+                    .unwrap_or_default(),
+            };
+            spending_policy::set(args, sm.value_of("cosigner-key")).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("remove") {
+            spending_policy::remove(sm.value_of("cosigner-key")).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let status = spending_policy::get_policy(sm.value_of("address")).c(d!())?;
+            println!("{}", serde_json::to_string_pretty(&status).c(d!())?);
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("session") {
+        if let Some(sm) = m.subcommand_matches("authorize") {
+            session_key::authorize(
+                address::parse_address(sm.value_of("session").c(d!())?)?,
+                sm.value_of("allowance").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("expires-at").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("code"),
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("revoke") {
+            session_key::revoke(address::parse_address(
+                sm.value_of("session").c(d!())?,
+            )?)
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("send") {
+            session_key::send(
+                &address::parse_address(sm.value_of("primary").c(d!())?)?,
+                sm.value_of("session-key").c(d!())?,
+                &address::parse_address(sm.value_of("to").c(d!())?)?,
+                sm.value_of("amount").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("code"),
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let status = session_key::get_sessions(sm.value_of("address")).c(d!())?;
+            println!("{}", serde_json::to_string_pretty(&status).c(d!())?);
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("kv") {
+        let value = |sm: &clap::ArgMatches| -> Result<Vec<u8>> {
+            match (sm.value_of("value"), sm.value_of("file")) {
+                (Some(v), None) => Ok(v.as_bytes().to_vec()),
+                (None, Some(f)) => fs::read(f).c(d!(f)),
+                _ => Err(eg!("one of `--value` and `--file` is needed")),
+            }
+        };
+        if let Some(sm) = m.subcommand_matches("put") {
+            kv::send(sm.value_of("key").c(d!())?, KvAction::Put(value(sm)?)).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("update") {
+            kv::send(sm.value_of("key").c(d!())?, KvAction::Update(value(sm)?))
+                .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("delete") {
+            kv::send(sm.value_of("key").c(d!())?, KvAction::Delete).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let v = kv::get_value(sm.value_of("key").c(d!())?).c(d!())?;
+            println!(
+                "owner: {}\nheight: {}\nvalue: {}",
+                wallet::public_key_to_bech32(&v.entry.owner),
+                v.entry.height,
+                String::from_utf8_lossy(&v.entry.value)
+            );
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("template") {
+        if let Some(sm) = m.subcommand_matches("save") {
+            let list = sm
+                .value_of("receivers")
+                .c(d!())
+                .and_then(|f| fs::read_to_string(f).c(d!(f)))?;
+            let t = template::TransferTemplate::parse(
+                &list,
+                sm.value_of("asset"),
+                sm.value_of("memo"),
+            )
+            .c(d!())?;
+            template::save(sm.value_of("name").c(d!())?, &t).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("use") {
+            let amounts = sm
+                .value_of("amounts")
+                .map(|ams| {
+                    ams.split(',')
+                        .map(|am| am.trim().parse::<u64>().c(d!(am)))
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()?;
+            let seckey = sm
+                .value_of("seckey")
+                .map(|path| fs::read_to_string(path).c(d!("Failed to read seckey file")))
+                .transpose()?;
+            template::send(
+                sm.value_of("name").c(d!())?,
+                amounts.as_deref(),
+                seckey.as_deref(),
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let t = template::load(sm.value_of("name").c(d!())?).c(d!())?;
+            println!("{}", serde_json::to_string_pretty(&t).c(d!())?);
+        } else if let Some(sm) = m.subcommand_matches("remove") {
+            template::remove(sm.value_of("name").c(d!())?).c(d!())?;
+        } else if m.subcommand_matches("list").is_some() {
+            template::list()
+                .c(d!())?
+                .iter()
+                .for_each(|name| println!("{}", name));
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("explorer") {
+        let blocks = m
+            .value_of("blocks")
+            .map(|b| b.parse::<u64>().c(d!("invalid number of blocks")))
+            .transpose()?
+            .unwrap_or(10);
+        explorer::run(blocks).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("notify") {
+        let addresses = match m.value_of("addresses") {
+            Some(addrs) => addrs
+                .split(',')
+                .map(|a| a.trim())
+                .filter(|a| !a.is_empty())
+                .map(|a| address::parse_address(a).c(d!()))
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![common::get_keypair().c(d!())?.get_pk()],
+        };
+        let cfg = notify::NotifyCfg {
+            addresses,
+            from_block: m
+                .value_of("from-block")
+                .map(|b| b.parse::<usize>().c(d!("invalid block sid")))
+                .transpose()?,
+            interval_secs: m
+                .value_of("interval")
+                .unwrap_or("5")
+                .parse::<u64>()
+                .c(d!("invalid interval"))?,
+            desktop: m.is_present("desktop"),
+            webhook: m.value_of("webhook").map(|u| u.to_owned()),
+            email: m.value_of("email").map(|e| e.to_owned()),
+        };
+        notify::run(&cfg).c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("sync") {
+        let password = m
+            .value_of("password-file")
+            .map(|f| fs::read_to_string(f).c(d!(f)).map(|p| p.trim().to_owned()))
+            .transpose()?;
+        let prefer = m
+            .value_of("prefer")
+            .map(|p| alt!("local" == p, sync::Prefer::Local, sync::Prefer::Remote));
+        sync::sync(
+            m.value_of("url"),
+            password.as_deref(),
+            m.is_present("with-keys"),
+            prefer,
+        )
+        .c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("signing-log") {
+        if let Some(sm) = m.subcommand_matches("show") {
+            let mut events = signing_log::load().c(d!())?;
+            if let Some(n) = sm.value_of("last") {
+                let n = n.parse::<usize>().c(d!("invalid number"))?;
+                events = events.split_off(events.len().saturating_sub(n));
+            }
+            if sm.is_present("json") {
+                println!("{}", serde_json::to_string_pretty(&events).c(d!())?);
+            } else {
+                for e in events {
+                    println!(
+                        "#{} [{}] {} by {}: {}",
+                        e.seq,
+                        e.timestamp,
+                        e.tx_hash,
+                        e.keys.join(", "),
+                        e.operations.join(", ")
+                    );
+                }
+            }
+        } else if m.subcommand_matches("verify").is_some() {
+            let n = signing_log::verify().c(d!())?;
+            println!("the chain of {} entries is intact", n);
+        } else if let Some(sm) = m.subcommand_matches("nickname") {
+            signing_log::set_nickname(
+                sm.value_of("address").c(d!())?,
+                sm.value_of("name").c(d!())?,
+            )
+            .c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("txo") {
+        let sid = |sm: &clap::ArgMatches| {
+            sm.value_of("sid")
+                .c(d!())
+                .and_then(|s| s.parse::<u64>().c(d!("invalid txo sid")))
+                .map(TxoSID)
+        };
+        if let Some(sm) = m.subcommand_matches("label") {
+            txo_label::set_label(sid(sm)?, sm.value_of("label").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("freeze") {
+            txo_label::set_frozen(sid(sm)?, true).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("unfreeze") {
+            txo_label::set_frozen(sid(sm)?, false).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("clear") {
+            txo_label::clear(sid(sm)?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("list") {
+            let seckey = sm
+                .value_of("seckey")
+                .map(|path| fs::read_to_string(path).c(d!("Failed to read seckey file")))
+                .transpose()?;
+            txo_label::show(seckey.as_deref()).c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("notary") {
+        if let Some(sm) = m.subcommand_matches("anchor") {
+            let hash =
+                notary::parse_hash(sm.value_of("hash"), sm.value_of("file")).c(d!())?;
+            notary::anchor(hash, sm.value_of("tag")).c(d!())?;
+            println!("\x1b[31;01mHash:\x1b[00m {}", hex::encode(hash));
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let hash =
+                notary::parse_hash(sm.value_of("hash"), sm.value_of("file")).c(d!())?;
+            if let Some(addr) = sm.value_of("address") {
+                let r =
+                    notary::get_record(&hash, &address::parse_address(addr)?).c(d!())?;
+                println!("{}", serde_json::to_string_pretty(&r.record).c(d!())?);
+            } else {
+                let records = notary::get_records(&hash).c(d!())?;
+                println!("{}", serde_json::to_string_pretty(&records).c(d!())?);
+            }
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("vesting") {
+        if let Some(sm) = m.subcommand_matches("issue") {
+            let tranches = sm
+                .values_of("tranche")
+                .c(d!())?
+                .map(vesting::parse_tranche)
+                .collect::<Result<Vec<_>>>()?;
+            vesting::issue(
+                sm.value_of("code").c(d!())?,
+                sm.value_of("amount").c(d!())?.parse::<u64>().c(d!())?,
+                tranches,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("show") {
+            let status = vesting::get_vesting(sm.value_of("address")).c(d!())?;
+            println!("{}", serde_json::to_string_pretty(&status).c(d!())?);
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("memo") {
+        if let Some(sm) = m.subcommand_matches("post") {
+            memo_relay::post(
+                &address::parse_address(sm.value_of("recipient").c(d!())?)?,
+                sm.value_of("txo-sid").c(d!())?.parse::<u64>().c(d!())?,
+                sm.value_of("memo-file").c(d!())?,
+            )
+            .c(d!())?;
+        } else if m.subcommand_matches("fetch").is_some() {
+            memo_relay::fetch().c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("offline") {
+        if let Some(sm) = m.subcommand_matches("gen-td-key") {
+            offline::gen_td_key(sm.value_of("out").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("gen-registration") {
+            offline::gen_registration(
+                sm.value_of("td-key").c(d!())?,
+                sm.value_of("staker").c(d!())?,
+                sm.value_of("amount").c(d!())?,
+                sm.value_of("commission-rate").c(d!())?,
+                sm.value_of("validator-memo"),
+                sm.value_of("out").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("build") {
+            offline::build_registration(
+                sm.value_of("registration").c(d!())?,
+                sm.value_of("out").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("sign") {
+            offline::sign_registration(
+                sm.value_of("unsigned").c(d!())?,
+                sm.value_of("staker-key").c(d!())?,
+                sm.value_of("td-key").c(d!())?,
+                sm.value_of("out").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("submit") {
+            offline::submit_registration(
+                sm.value_of("unsigned").c(d!())?,
+                sm.value_of("signatures").c(d!())?,
+            )
+            .c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("node") {
+        if let Some(sm) = m.subcommand_matches("export-blocks") {
+            let height = |name: &str| {
+                sm.value_of(name)
+                    .c(d!())
+                    .and_then(|h| h.parse::<u64>().c(d!("invalid height")))
+            };
+            let chunk_size = sm
+                .value_of("chunk-size")
+                .map(|n| n.parse::<usize>().c(d!("invalid chunk size")))
+                .transpose()?
+                .unwrap_or(node::DEFAULT_CHUNK_SIZE);
+            node::export_blocks(
+                sm.value_of("archive").c(d!())?,
+                height("from")?,
+                height("to")?,
+                chunk_size,
+                sm.value_of("out-dir").c(d!())?,
+            )
+            .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("import-blocks") {
+            node::import_blocks(
+                sm.value_of("input").c(d!())?,
+                sm.value_of("archive").c(d!())?,
+            )
+            .c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("state-diff") {
+        common::state_diff(
+            m.value_of("left").c(d!())?,
+            m.value_of("right").c(d!())?,
+            m.value_of("out"),
+        )
+        .c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("state-checksum") {
+        let endpoint = m.value_of("endpoint");
+        let subsystems = m.value_of("subsystems");
+        let json = m.is_present("json");
+        if let Some(other) = m.value_of("compare") {
+            checksum::compare(endpoint, other, subsystems, json).c(d!())?;
+        } else {
+            checksum::show(endpoint, subsystems, json).c(d!())?;
+        }
+    } else if let Some(m) = matches.subcommand_matches("decode-tx") {
+        let decoded = if let Some(path) = m.value_of("file") {
+            inspect::decode_txn_file(path).c(d!())?
+        } else if let Some(tx) = m.value_of("tx") {
+            inspect::decode_txn(tx).c(d!())?
+        } else {
+            println!("{}", m.usage());
+            return Ok(());
+        };
+        if m.is_present("json") {
+            println!("{}", serde_json::to_string_pretty(&decoded).c(d!())?);
+        } else {
+            print!("{}", decoded);
+        }
+    } else if let Some(m) = matches.subcommand_matches("cancel-tx") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        common::cancel_tx(
+            m.value_of("handle").c(d!())?,
+            m.value_of("replacement"),
+            seckey.as_deref(),
+        )
+        .c(d!())?;
     } else if let Some(m) = matches.subcommand_matches("replace_staker") {
         let target = m
             .value_of("target")
             .c(d!())
-            .and_then(wallet::public_key_from_base64)?;
+            .and_then(address::parse_address)?;
         // let new_td_addr_pk = if let Some(new_td_address_str) = m.value_of("td_address") {
         //     let new_td_address = hex::decode(new_td_address_str)
         //         .c(d!("`td_address` is invalid hex. "))?;
@@ -428,6 +1271,180 @@ fn run() -> Result<()> {
         //     None
         // };
         common::replace_staker(target, None)?;
+    } else if let Some(m) = matches.subcommand_matches("credential") {
+        let seckey = match m.value_of("seckey") {
+            Some(path) => {
+                Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+            }
+            None => None,
+        };
+        let issuer = m
+            .value_of("issuer-pubkey")
+            .c(d!())
+            .and_then(|path| fs::read(path).c(d!("Failed to read issuer pubkey file")))
+            .and_then(|c| serde_json::from_slice(&c).c(d!("invalid issuer pubkey")))?;
+        let values = |name| -> Vec<&str> {
+            m.values_of(name).map(|v| v.collect()).unwrap_or_default()
+        };
+        let revoke = values("revoke");
+        let restore = values("restore");
+        let renew = values("renew");
+        let expiries = if renew.is_empty() {
+            vec![]
+        } else {
+            let h = m
+                .value_of("expire-at")
+                .c(d!())?
+                .parse::<u64>()
+                .c(d!("height should be a 64-bits unsigned integer"))?;
+            renew.into_iter().map(|id| (id, h)).collect()
+        };
+        if revoke.is_empty() && restore.is_empty() && expiries.is_empty() {
+            println!("{}", m.usage());
+            return Ok(());
+        }
+
+        common::update_credential_status(
+            seckey.as_deref(),
+            issuer,
+            m.value_of("id-attr").unwrap_or_default(),
+            &revoke,
+            &restore,
+            &expiries,
+        )
+        .c(d!())?;
+    } else if let Some(m) = matches.subcommand_matches("view-key") {
+        if let Some(sm) = m.subcommand_matches("export") {
+            let seckey = match sm.value_of("seckey") {
+                Some(path) => {
+                    Some(fs::read_to_string(path).c(d!("Failed to read seckey file"))?)
+                }
+                None => None,
+            };
+            viewing::export_view_key(seckey.as_deref(), sm.value_of("output"))
+                .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("import") {
+            viewing::import_view_key(sm.value_of("file").c(d!())?).c(d!())?;
+        } else if m.subcommand_matches("show").is_some() {
+            viewing::show_view_keys().c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
+    } else if let Some(m) = matches.subcommand_matches("audit") {
+        if let Some(sm) = m.subcommand_matches("report") {
+            let cfg = ReportCfg {
+                tracer_keys: sm.value_of("tracer-keys").c(d!())?.to_owned(),
+                from_height: sm
+                    .value_of("from-height")
+                    .unwrap_or("1")
+                    .parse::<u64>()
+                    .c(d!())?,
+                to_height: sm
+                    .value_of("to-height")
+                    .map(|h| h.parse::<u64>().c(d!()))
+                    .transpose()?,
+                format: sm
+                    .value_of("format")
+                    .unwrap_or("json")
+                    .parse::<ReportFormat>()
+                    .c(d!())?,
+                output: sm.value_of("output").map(|o| o.to_owned()),
+            };
+            auditor::gen_report(&cfg).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("daemon") {
+            let mut cfg = DaemonCfg {
+                nodes: sm
+                    .value_of("nodes")
+                    .map(|n| {
+                        n.split(',')
+                            .map(|n| n.trim().trim_end_matches('/').to_owned())
+                            .filter(|n| !n.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                interval_secs: sm
+                    .value_of("interval")
+                    .unwrap_or("5")
+                    .parse::<u64>()
+                    .c(d!())?,
+                webhook: sm.value_of("webhook").map(|w| w.to_owned()),
+                gossip: None,
+            };
+            if let Some(listen) = sm.value_of("p2p-listen") {
+                let split = |v: Option<&str>| {
+                    v.map(|v| {
+                        v.split(',')
+                            .map(|i| i.trim())
+                            .filter(|i| !i.is_empty())
+                            .map(|i| i.to_owned())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default()
+                };
+                cfg.gossip = Some(GossipCfg {
+                    listen: listen.to_owned(),
+                    bootnodes: split(sm.value_of("p2p-bootnodes")),
+                    auditors: split(sm.value_of("auditors"))
+                        .iter()
+                        .map(|pk| address::parse_address(pk))
+                        .collect::<Result<_>>()?,
+                    quorum: sm.value_of("quorum").c(d!())?.parse::<usize>().c(d!())?,
+                    checkpoint_itv: sm
+                        .value_of("checkpoint-interval")
+                        .unwrap_or("100")
+                        .parse::<usize>()
+                        .c(d!())?,
+                    timeout_secs: sm
+                        .value_of("quorum-timeout")
+                        .unwrap_or("120")
+                        .parse::<u64>()
+                        .c(d!())?,
+                });
+            }
+            auditor::run_daemon(&cfg).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("graph") {
+            let cfg = GraphCfg {
+                from_height: sm
+                    .value_of("from-height")
+                    .unwrap_or("1")
+                    .parse::<u64>()
+                    .c(d!())?,
+                to_height: sm
+                    .value_of("to-height")
+                    .map(|h| h.parse::<u64>().c(d!()))
+                    .transpose()?,
+                asset: sm
+                    .value_of("asset")
+                    .map(|code| AssetTypeCode::new_from_base64(code).c(d!()))
+                    .transpose()?,
+                min_amount: sm
+                    .value_of("min-amount")
+                    .map(|am| am.parse::<u64>().c(d!()))
+                    .transpose()?,
+                format: sm
+                    .value_of("format")
+                    .unwrap_or("graphml")
+                    .parse::<GraphFormat>()
+                    .c(d!())?,
+                output: sm.value_of("output").map(|o| o.to_owned()),
+            };
+            auditor::gen_graph(&cfg).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("reserves") {
+            auditor::prove_reserves(sm.value_of("keys").c(d!())?, sm.value_of("output"))
+                .c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("verify-reserves") {
+            auditor::verify_reserves(sm.value_of("statement").c(d!())?).c(d!())?;
+        } else if let Some(sm) = m.subcommand_matches("cold") {
+            let cfg = ColdAuditCfg {
+                expected: sm.value_of("expected").c(d!())?.to_owned(),
+                endpoint: sm.value_of("endpoint").map(|e| e.to_owned()),
+                signer_key: sm.value_of("signer-key").map(|k| k.to_owned()),
+                output: sm.value_of("output").map(|o| o.to_owned()),
+            };
+            auditor::audit_cold_storage(&cfg).c(d!())?;
+        } else {
+            println!("{}", m.usage());
+        }
     } else if let Some(m) = matches.subcommand_matches("dev") {
         let mut envcfg = EnvCfg::default();
 
@@ -435,6 +1452,12 @@ fn run() -> Result<()> {
             if let Some(name) = sm.value_of("env_name") {
                 envcfg.name = name.to_owned();
             }
+            if let Some(path) = sm.value_of("env_template") {
+                envcfg.apply_template(path).c(d!())?;
+            }
+            if let Some(snapshot) = sm.value_of("fork_from") {
+                envcfg.fork_from = Some(snapshot.to_owned());
+            }
             if let Some(id) = sm.value_of("evm_chain_id") {
                 envcfg.evm_chain_id = id.parse::<u64>().c(d!())?;
             }
@@ -455,6 +1478,17 @@ fn run() -> Result<()> {
             if let Some(ip) = sm.value_of("host_ip") {
                 envcfg.host_ip = Some(ip.to_owned());
             }
+            if let Some(hosts) = sm.value_of("hosts") {
+                envcfg.hosts = hosts
+                    .split(',')
+                    .map(|h| h.trim().parse::<Host>().c(d!()))
+                    .collect::<Result<Vec<_>>>()?;
+                let key = sm.value_of("ssh_key").map(|k| k.to_owned());
+                envcfg
+                    .hosts
+                    .iter_mut()
+                    .for_each(|h| h.ssh_key = key.clone());
+            }
             if let Some(abcid_bin) = sm.value_of("abcid_bin_path") {
                 envcfg.abcid_bin = Some(abcid_bin.to_owned());
             }
@@ -470,6 +1504,12 @@ fn run() -> Result<()> {
             if sm.is_present("force") {
                 envcfg.force_create = true;
             }
+            if let Some(kind) = sm.value_of("manifest") {
+                envcfg.manifest = Some(kind.parse::<ManifestKind>().c(d!())?);
+            }
+            if let Some(image) = sm.value_of("manifest_image") {
+                envcfg.manifest_image = Some(image.to_owned());
+            }
             Ops::Create
         } else if let Some(sm) = m.subcommand_matches("destroy") {
             if let Some(name) = sm.value_of("env_name") {
@@ -518,6 +1558,109 @@ fn run() -> Result<()> {
             Ops::Init
         } else if m.subcommand_matches("init-all").is_some() {
             Ops::InitAll
+        } else if let Some(sm) = m.subcommand_matches("upgrade") {
+            if let Some(name) = sm.value_of("env_name") {
+                envcfg.name = name.to_owned();
+            }
+            let wait_blocks = sm
+                .value_of("wait_blocks")
+                .map(|n| n.parse::<u64>().c(d!()))
+                .unwrap_or(Ok(5))?;
+            envcfg.upgrade = Some(UpgradeCfg {
+                abcid_bin: sm.value_of("abcid_bin_path").c(d!())?.to_owned(),
+                scope: sm.value_of("scope").unwrap_or("1").to_owned(),
+                wait_blocks,
+            });
+            Ops::Upgrade
+        } else if let Some(sm) = m.subcommand_matches("chaos") {
+            if let Some(name) = sm.value_of("env_name") {
+                envcfg.name = name.to_owned();
+            }
+            let chaos = if sm.is_present("kill_restart") {
+                let times = sm
+                    .value_of("times")
+                    .map(|n| n.parse::<u32>().c(d!()))
+                    .unwrap_or(Ok(1))?;
+                let down_secs = sm
+                    .value_of("down_secs")
+                    .map(|n| n.parse::<u64>().c(d!()))
+                    .unwrap_or(Ok(10))?;
+                ChaosOps::KillRestart { times, down_secs }
+            } else if let Some(ms) = sm.value_of("latency") {
+                ChaosOps::Latency(ms.parse::<u32>().c(d!())?)
+            } else if sm.is_present("partition") {
+                ChaosOps::Partition
+            } else if sm.is_present("heal") {
+                ChaosOps::Heal
+            } else if sm.is_present("pause") {
+                ChaosOps::Pause
+            } else {
+                ChaosOps::Resume
+            };
+            envcfg.chaos = Some(chaos);
+            Ops::Chaos
+        } else if let Some(sm) = m.subcommand_matches("bench") {
+            if let Some(name) = sm.value_of("env_name") {
+                envcfg.name = name.to_owned();
+            }
+            let accounts = sm
+                .value_of("accounts")
+                .map(|n| n.parse::<usize>().c(d!()))
+                .unwrap_or(Ok(10))?;
+            let txns = sm
+                .value_of("txns")
+                .map(|n| n.parse::<usize>().c(d!()))
+                .unwrap_or(Ok(1000))?;
+            let queries = sm
+                .value_of("queries")
+                .map(|n| n.parse::<usize>().c(d!()))
+                .unwrap_or(Ok(100))?;
+            envcfg.bench = Some(BenchCfg {
+                accounts,
+                txns,
+                queries,
+                out: sm.value_of("out").map(|p| p.to_owned()),
+            });
+            Ops::Bench
+        } else if let Some(sm) = m.subcommand_matches("loadgen") {
+            if let Some(name) = sm.value_of("env_name") {
+                envcfg.name = name.to_owned();
+            }
+            let accounts = sm
+                .value_of("accounts")
+                .map(|n| n.parse::<usize>().c(d!()))
+                .unwrap_or(Ok(10))?;
+            let tps = sm
+                .value_of("tps")
+                .map(|n| n.parse::<f64>().c(d!()))
+                .unwrap_or(Ok(10.0))?;
+            let secs = sm
+                .value_of("duration")
+                .map(|n| n.parse::<u64>().c(d!()))
+                .unwrap_or(Ok(60))?;
+            let mix = parse_mix(sm.value_of("mix").unwrap_or("transfer=1")).c(d!())?;
+            envcfg.loadgen = Some(LoadgenCfg {
+                accounts,
+                tps,
+                secs,
+                mix,
+                out: sm.value_of("out").map(|p| p.to_owned()),
+            });
+            Ops::Loadgen
+        } else if let Some(sm) = m.subcommand_matches("replay") {
+            if let Some(name) = sm.value_of("env_name") {
+                envcfg.name = name.to_owned();
+            }
+            let until = sm
+                .value_of("until")
+                .map(|h| h.parse::<u64>().c(d!()))
+                .transpose()?;
+            envcfg.replay = Some(ReplayCfg {
+                archive: sm.value_of("archive").c(d!())?.to_owned(),
+                until,
+                export: sm.value_of("export").map(|p| p.to_owned()),
+            });
+            Ops::Replay
         } else {
             if let Some(name) = m.value_of("env_name") {
                 envcfg.name = name.to_owned();
@@ -14,7 +14,7 @@ mod init;
 use {
     clap::{crate_authors, App, SubCommand},
     finutils::common,
-    globutils::wallet,
+    globutils::{address, wallet},
     lazy_static::lazy_static,
     ledger::{
         data_model::{gen_random_keypair, Transaction, BLACK_HOLE_PUBKEY_STAKING},
@@ -172,7 +172,7 @@ fn run() -> Result<()> {
                 let target_pk = search_kp(receiver)
                     .c(d!())
                     .map(|kp| kp.get_pk())
-                    .or_else(|e| wallet::public_key_from_base64(receiver).c(d!(e)))?;
+                    .or_else(|e| address::parse_address(receiver).c(d!(e)))?;
                 common::utils::transfer(owner_kp, &target_pk, am, None, false, false)
                     .c(d!())?;
             }
@@ -249,6 +249,7 @@ mod issue {
                         id: None,
                         record: ba,
                         lien: None,
+                        memo: None,
                     },
                     None,
                 )
@@ -0,0 +1,239 @@
+//!
+//! # Test vectors
+//!
+//! Emit the canonical encodings of the ledger for a fixed seed, so the wasm and
+//! FFI bindings, and other implementations, can check their bytes against them:
+//!
+//! - key pairs, the public keys in base64 and bech32
+//! - signed transactions, a definition and an issuance of an asset,
+//!   in JSON, with their tendermint hashes, handles and signatures
+//! - the transaction Merkle tree of them, its root and the proofs of the leaves
+//! - the state commitment of a block holding them
+//!
+//! The same seed always gives the same output.
+//!
+//! Usage: `platform-testvectors [SEED] [OUTPUT FILE]`, seed 0 and stdout by default.
+//!
+
+use {
+    globutils::{wallet, HashOf, SignatureOf},
+    ledger::data_model::{
+        AssetRules, AssetTypeCode, DefineAsset, DefineAssetBody, IssueAsset,
+        IssueAssetBody, IssuerKeyPair, IssuerPublicKey, Memo, NoReplayToken, Operation,
+        StateCommitmentData, Transaction, TxOutput, TxnSID,
+    },
+    merkle_tree::AppendOnlyMerkle,
+    rand_chacha::ChaChaRng,
+    rand_core::{RngCore, SeedableRng},
+    ruc::*,
+    serde_json::{json, Value},
+    std::{env, fs},
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{build_blind_asset_record, AssetRecordType},
+            sig::XfrKeyPair,
+            structs::AssetRecordTemplate,
+        },
+    },
+};
+
+// bumped on any change of the layout of the output
+const VECTORS_VERSION: u64 = 1;
+
+const N_KEYS: usize = 3;
+
+fn main() {
+    pnk!(run());
+}
+
+fn run() -> Result<()> {
+    let seed = env::args()
+        .nth(1)
+        .map(|s| s.parse::<u64>().c(d!("invalid seed")))
+        .transpose()?
+        .unwrap_or(0);
+    let vectors = gen(seed).c(d!())?;
+    let contents = serde_json::to_string_pretty(&vectors).c(d!())?;
+
+    match env::args().nth(2) {
+        Some(path) => fs::write(&path, contents).c(d!(path)),
+        None => {
+            println!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+fn gen(seed: u64) -> Result<Value> {
+    let mut seed_bytes = [0u8; 32];
+    seed_bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    let mut prng = ChaChaRng::from_seed(seed_bytes);
+
+    let keys = (0..N_KEYS)
+        .map(|_| XfrKeyPair::generate(&mut prng))
+        .collect::<Vec<_>>();
+
+    let txns = gen_txns(&mut prng, &keys).c(d!())?;
+
+    let dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+    let mut txn_merkle =
+        AppendOnlyMerkle::create(&format!("{}/txn_merkle", &dir)).c(d!())?;
+    let mut block_merkle =
+        AppendOnlyMerkle::create(&format!("{}/block_merkle", &dir)).c(d!())?;
+
+    let leaves = txns
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| tx.hash(TxnSID(i)).0.hash.into())
+        .collect::<Vec<_>>();
+    for leaf in leaves.iter() {
+        txn_merkle.append_hash(leaf).c(d!())?;
+    }
+    let proofs = (0..leaves.len() as u64)
+        .map(|i| {
+            txn_merkle.get_proof(i, 0).c(d!()).map(|mut proof| {
+                // local to the generating node, not covered by the checks
+                proof.ledger = String::new();
+                proof.time = 0;
+                proof
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let txns_in_block_hash = HashOf::new(&txns);
+    block_merkle
+        .append_hash(&txns_in_block_hash.0.hash.into())
+        .c(d!())?;
+    // the utxo bitmap is not part of the vectors, its checksum stays zero
+    let state = StateCommitmentData {
+        bitmap: Default::default(),
+        block_merkle: block_merkle.get_root_hash(),
+        txns_in_block_hash,
+        previous_state_commitment: HashOf::new(&None),
+        transaction_merkle_commitment: txn_merkle.get_root_hash(),
+        air_commitment: Default::default(),
+        txo_count: txns
+            .iter()
+            .flat_map(|tx| tx.body.operations.iter())
+            .map(|op| match op {
+                Operation::IssueAsset(i) => i.body.num_outputs as u64,
+                _ => 0,
+            })
+            .sum(),
+        pulse_count: 0,
+        staking: None,
+        revocation: None,
+        ibc: None,
+        kv: None,
+        notary: None,
+    };
+    let commitment = state.compute_commitment();
+
+    let _ = fs::remove_dir_all(&dir);
+
+    Ok(json!({
+        "version": VECTORS_VERSION,
+        "seed": seed,
+        "keys": keys.iter().map(|kp| json!({
+            "secret_key": kp.get_sk_ref(),
+            "public_key": wallet::public_key_to_base64(kp.get_pk_ref()),
+            "address": wallet::public_key_to_bech32(kp.get_pk_ref()),
+        })).collect::<Vec<_>>(),
+        "transactions": txns.iter().map(|tx| json!({
+            "json": serde_json::to_string(tx).unwrap_or_default(),
+            "hash": hex::encode(tx.hash_tm_rawbytes()),
+            "handle": tx.handle(),
+            "body_signatures": tx.pubkey_sign_map.iter().map(|(pk, sig)| json!({
+                "public_key": wallet::public_key_to_base64(pk),
+                "signature": sig,
+                "body_hash": hex::encode(HashOf::new(&tx.body).0.hash.as_ref()),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "message_signatures": keys.iter().map(|kp| {
+            let msg = "findora test vector";
+            json!({
+                "public_key": wallet::public_key_to_base64(kp.get_pk_ref()),
+                "message": msg,
+                "signature": SignatureOf::new(kp, &msg.to_owned()),
+            })
+        }).collect::<Vec<_>>(),
+        "txn_merkle": {
+            "leaves": leaves.iter().map(|l| hex::encode(l.hash)).collect::<Vec<_>>(),
+            "root": hex::encode(txn_merkle.get_root_hash().hash),
+            "proofs": proofs,
+        },
+        "state_commitment": {
+            "data": state,
+            "commitment": hex::encode(commitment.0.hash.as_ref()),
+        },
+    }))
+}
+
+// a definition of an asset by the first key, and its issuance to the others,
+// the records are not confidential, so they only depend on the seed
+fn gen_txns(prng: &mut ChaChaRng, keys: &[XfrKeyPair]) -> Result<Vec<Transaction>> {
+    let issuer = IssuerKeyPair { keypair: &keys[0] };
+    let mut code = vec![0u8; 32];
+    prng.fill_bytes(&mut code);
+    let code = AssetTypeCode::new_from_vec(code);
+
+    let definition = DefineAssetBody::new(
+        &code,
+        &IssuerPublicKey {
+            key: keys[0].get_pk(),
+        },
+        AssetRules::default(),
+        Some(Memo("test vector".to_owned())),
+        None,
+    )
+    .and_then(|body| DefineAsset::new(body, &issuer))
+    .c(d!())?;
+
+    let records = keys[1..]
+        .iter()
+        .enumerate()
+        .map(|(i, kp)| {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                1000 * (1 + i as u64),
+                code.val,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                kp.get_pk(),
+            );
+            let (record, _, owner_memo) = build_blind_asset_record(
+                &mut *prng,
+                &PublicParams::default().pc_gens,
+                &template,
+                vec![],
+            );
+            (
+                TxOutput {
+                    id: None,
+                    record,
+                    lien: None,
+                    memo: None,
+                },
+                owner_memo,
+            )
+        })
+        .collect::<Vec<_>>();
+    let issuance = IssueAssetBody::new(&code, 0, &records)
+        .and_then(|body| IssueAsset::new(body, &issuer))
+        .c(d!())?;
+
+    Ok([
+        Operation::DefineAsset(definition),
+        Operation::IssueAsset(issuance),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(seq_id, op)| {
+        let mut tx = Transaction::default();
+        tx.body.no_replay_token =
+            NoReplayToken::unsafe_new(prng.next_u64(), seq_id as u64);
+        tx.add_operation(op);
+        tx.sign_to_map(&keys[0]);
+        tx
+    })
+    .collect())
+}
@@ -0,0 +1,237 @@
+//!
+//! Audits of cold storage,
+//! the balances of some public keys checked against their expected values.
+//!
+//! Only public keys are accepted in the list, no secret of the cold storage is
+//! ever loaded, so the confidential outputs can not be opened: they are counted
+//! apart, and an address holding any of them is not verified.
+//!
+//! Every output is checked against its `AuthenticatedUtxo` proof, and the report
+//! is signed by the auditor, with the key set by `fn setup` by default.
+//!
+
+use {
+    super::reserves::{http_get, record_value, Commitment, MAX_ATTEMPTS},
+    crate::common::{get_keypair, get_serv_addr, utils::get_owned_utxos_x},
+    globutils::{address, wallet, SignatureOf},
+    ledger::data_model::{AssetTypeCode, AuthenticatedUtxo, ASSET_TYPE_FRA},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// Options of `fn audit cold`
+#[derive(Debug, Clone)]
+pub struct ColdAuditCfg {
+    /// one expectation per line: `<address> <asset code or FRA> <expected amount>`
+    pub expected: String,
+    /// the query endpoint of the ledger, eg. `http://127.0.0.1:8668`,
+    /// the one of `fn setup` by default
+    pub endpoint: Option<String>,
+    /// the file of the mnemonic or the secret key signing the report
+    pub signer_key: Option<String>,
+    #[allow(missing_docs)]
+    pub output: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ColdEntry {
+    address: String,
+    // base64-formated asset code
+    asset: String,
+    expected: u64,
+    // the sum of the non-confidential outputs
+    found: u128,
+    confidential_outputs: usize,
+    verified: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ColdAuditReport {
+    state_commitment: Commitment,
+    // the index of `state_commitment` in the ledger, starts from 1
+    block_count: u64,
+    // unix timestamp in seconds
+    generated_at: u64,
+    entries: Vec<ColdEntry>,
+    all_verified: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct SignedColdAuditReport {
+    report: ColdAuditReport,
+    signer: XfrPublicKey,
+    signature: SignatureOf<ColdAuditReport>,
+}
+
+/// Audit the balances of `cfg.expected`, the signed report is written
+/// to `cfg.output` or stdout, a summary is printed to stderr.
+pub fn audit_cold_storage(cfg: &ColdAuditCfg) -> Result<()> {
+    let expected = load_expected(&cfg.expected).c(d!())?;
+    let signer = match cfg.signer_key.as_deref() {
+        Some(path) => fs::read_to_string(path)
+            .c(d!(path.to_owned()))
+            .and_then(|k| {
+                address::parse_keypair(k.trim()).c(d!("invalid signer key"))
+            })?,
+        None => get_keypair().c(d!())?,
+    };
+    let endpoint = match cfg.endpoint.as_deref() {
+        Some(ep) => ep.trim_end_matches('/').to_owned(),
+        None => format!("{}:8668", get_serv_addr().c(d!())?),
+    };
+
+    let mut report = None;
+    for _ in 0..MAX_ATTEMPTS {
+        if let Some(r) = collect(&endpoint, &expected).c(d!())? {
+            report = Some(r);
+            break;
+        }
+    }
+    let report = report.c(d!("the ledger keeps moving, please try again later"))?;
+
+    for e in report.entries.iter() {
+        eprintln!(
+            "{} {} {}: expected {}, found {}{}",
+            alt!(
+                e.verified,
+                "\x1b[32;01mOK\x1b[00m",
+                "\x1b[31;01mFAIL\x1b[00m"
+            ),
+            e.address,
+            e.asset,
+            e.expected,
+            e.found,
+            alt!(
+                0 == e.confidential_outputs,
+                String::new(),
+                format!(", {} confidential outputs", e.confidential_outputs)
+            )
+        );
+    }
+
+    let signed = sign(&signer, report);
+    let contents = serde_json::to_string_pretty(&signed).c(d!())?;
+    if let Some(path) = cfg.output.as_deref() {
+        fs::write(path, contents).c(d!())
+    } else {
+        println!("{}", contents);
+        Ok(())
+    }
+}
+
+fn sign(signer: &XfrKeyPair, report: ColdAuditReport) -> SignedColdAuditReport {
+    SignedColdAuditReport {
+        signature: SignatureOf::new(signer, &report),
+        signer: signer.get_pk(),
+        report,
+    }
+}
+
+// `None` if the ledger moved forward during the collection
+fn collect(
+    endpoint: &str,
+    expected: &[(XfrPublicKey, AssetTypeCode, u64)],
+) -> Result<Option<ColdAuditReport>> {
+    let (state_commitment, block_count, _) =
+        http_get::<(Commitment, u64, String)>(&format!("{}/global_state", endpoint))
+            .c(d!())?;
+
+    // (amount by asset, confidential outputs) of each key
+    let mut balances: BTreeMap<Vec<u8>, (BTreeMap<String, u128>, usize)> =
+        BTreeMap::new();
+    for (pk, _, _) in expected.iter() {
+        if balances.contains_key(pk.as_bytes()) {
+            continue;
+        }
+        let mut b = (BTreeMap::new(), 0);
+        for sid in get_owned_utxos_x(Some(endpoint), pk).c(d!())?.keys() {
+            let proof = http_get::<AuthenticatedUtxo>(&format!(
+                "{}/utxo_sid/{}",
+                endpoint, sid.0
+            ))
+            .c(d!())?;
+            if proof.state_commitment_data.compute_commitment() != state_commitment {
+                return Ok(None);
+            }
+            if !proof.is_valid(state_commitment.clone())
+                || proof.utxo.0.record.public_key != *pk
+            {
+                return Err(eg!(format!("invalid proof of txo {}", sid.0)));
+            }
+            match record_value(&proof) {
+                Some((amount, asset)) => {
+                    *b.0.entry(asset).or_insert(0) += amount as u128
+                }
+                None => b.1 += 1,
+            }
+        }
+        balances.insert(pk.as_bytes().to_vec(), b);
+    }
+
+    let entries = expected
+        .iter()
+        .map(|(pk, code, am)| {
+            let asset = code.to_base64();
+            let (amounts, confidential_outputs) = &balances[pk.as_bytes()];
+            let found = amounts.get(&asset).copied().unwrap_or(0);
+            ColdEntry {
+                address: wallet::public_key_to_bech32(pk),
+                verified: 0 == *confidential_outputs && found == *am as u128,
+                asset,
+                expected: *am,
+                found,
+                confidential_outputs: *confidential_outputs,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Some(ColdAuditReport {
+        state_commitment,
+        block_count,
+        generated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .c(d!())?
+            .as_secs(),
+        all_verified: entries.iter().all(|e| e.verified),
+        entries,
+    }))
+}
+
+// the secret keys are refused, the list must contain public keys only
+fn load_expected(path: &str) -> Result<Vec<(XfrPublicKey, AssetTypeCode, u64)>> {
+    let expected = fs::read_to_string(path)
+        .c(d!(path.to_owned()))?
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            let fields = l.split_whitespace().collect::<Vec<_>>();
+            if 3 != fields.len() {
+                return Err(eg!(format!("invalid line: {}", l)));
+            }
+            let pk = address::parse_address(fields[0])
+                .c(d!(format!("not a public key: {}", fields[0])))?;
+            let code = if fields[1].eq_ignore_ascii_case("FRA") {
+                AssetTypeCode {
+                    val: ASSET_TYPE_FRA,
+                }
+            } else {
+                AssetTypeCode::new_from_base64(fields[1]).c(d!("invalid asset code"))?
+            };
+            let am = fields[2].parse::<u64>().c(d!("invalid amount"))?;
+            Ok((pk, code, am))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    alt!(
+        expected.is_empty(),
+        Err(eg!("no expectations found")),
+        Ok(expected)
+    )
+}
@@ -0,0 +1,241 @@
+//!
+//! Continuous ledger verification,
+//! follow the chain and check the state commitments published by nodes.
+//!
+//! For each ledger block:
+//! - `txns_in_block_hash`, `transaction_merkle_commitment` and `block_merkle`
+//!   are recomputed from the raw transactions of the block
+//! - `previous_state_commitment` should be the hash of the prior state commitment data
+//! - all nodes should publish the same state commitment
+//!
+//! Verified checkpoints can be attested to other auditors, see `super::gossip`.
+//!
+//! NOTE: the utxo bitmap and the staking hash can only be recomputed by a full replay,
+//! so they are only compared between nodes.
+//!
+
+use {
+    super::gossip::{Gossip, GossipCfg},
+    crate::common::{get_keypair, get_serv_addr, utils::get_block},
+    globutils::HashOf,
+    ledger::data_model::{StateCommitmentData, Transaction},
+    merkle_tree::AppendOnlyMerkle,
+    ruc::*,
+    serde::Serialize,
+};
+
+/// Options of `fn audit daemon`
+#[derive(Debug, Clone)]
+pub struct DaemonCfg {
+    /// URLs of the nodes to be checked, eg. "https://prod-mainnet.prod.findora.org",
+    /// the first one is used as the source of raw blocks
+    pub nodes: Vec<String>,
+    /// seconds to wait when no new blocks
+    pub interval_secs: u64,
+    /// alerts will be POSTed to this URL in JSON
+    pub webhook: Option<String>,
+    /// join the gossip network of auditors if set
+    pub gossip: Option<GossipCfg>,
+}
+
+#[derive(Debug, Serialize)]
+struct Alert<'a> {
+    block_sid: usize,
+    node: &'a str,
+    reason: String,
+}
+
+/// Follow the chain from the first block, never returns unless an unrecoverable error occurs.
+pub fn run_daemon(cfg: &DaemonCfg) -> Result<()> {
+    let nodes = if cfg.nodes.is_empty() {
+        vec![get_serv_addr().c(d!())?.to_owned()]
+    } else {
+        cfg.nodes.clone()
+    };
+
+    let gossip = match cfg.gossip.clone() {
+        Some(g) => Some(Gossip::start(g, get_keypair().c(d!())?).c(d!())?),
+        None => None,
+    };
+
+    // historical blocks are not attested,
+    // other auditors have moved on and will never attest them again
+    let mut synced = false;
+
+    let mut v = Verifier::new().c(d!())?;
+    loop {
+        if let Some(g) = gossip.as_ref() {
+            for (block_sid, reason) in g.alerts() {
+                let a = Alert {
+                    block_sid,
+                    node: "gossip",
+                    reason,
+                };
+                emit_alert(&a, cfg.webhook.as_deref());
+            }
+        }
+
+        match v.verify_next(&nodes) {
+            Ok(Some(alerts)) => {
+                for a in alerts.iter() {
+                    emit_alert(a, cfg.webhook.as_deref());
+                }
+                if 0 == v.next_block_sid % 1000 {
+                    println!("[{}] {} blocks verified", datetime!(), v.next_block_sid);
+                }
+                if let (Some(g), Some(state), true) = (
+                    gossip.as_ref(),
+                    v.prev_state.as_ref(),
+                    synced && alerts.is_empty(),
+                ) {
+                    info_omit!(
+                        g.attest(v.next_block_sid - 1, state.compute_commitment())
+                    );
+                }
+            }
+            // no new blocks
+            Ok(None) => {
+                synced = true;
+                sleep_ms!(cfg.interval_secs.max(1) * 1000);
+            }
+            // network issues, retry later
+            Err(e) => {
+                e.print(None);
+                sleep_ms!(cfg.interval_secs.max(1) * 1000);
+            }
+        }
+    }
+}
+
+struct Verifier {
+    next_block_sid: usize,
+    next_txn_sid: usize,
+    txn_merkle: AppendOnlyMerkle,
+    block_merkle: AppendOnlyMerkle,
+    prev_state: Option<StateCommitmentData>,
+}
+
+impl Verifier {
+    fn new() -> Result<Self> {
+        let dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+        Ok(Self {
+            next_block_sid: 0,
+            next_txn_sid: 0,
+            txn_merkle: AppendOnlyMerkle::create(&format!("{}/txn_merkle", &dir))
+                .c(d!())?,
+            block_merkle: AppendOnlyMerkle::create(&format!("{}/block_merkle", &dir))
+                .c(d!())?,
+            prev_state: None,
+        })
+    }
+
+    // - `Ok(None)` if the next block has not been committed
+    // - `Ok(Some(alerts))` if the next block has been verified
+    fn verify_next<'a>(
+        &mut self,
+        nodes: &'a [String],
+    ) -> Result<Option<Vec<Alert<'a>>>> {
+        let sid = self.next_block_sid;
+        let block = match get_block(&nodes[0], sid).c(d!())? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+
+        let mut alerts = vec![];
+        macro_rules! alert {
+            ($node: expr, $($arg: tt)*) => {
+                alerts.push(Alert {
+                    block_sid: sid,
+                    node: $node,
+                    reason: format!($($arg)*),
+                })
+            };
+        }
+
+        // 1. recompute from the raw transactions
+        for tx in block.txns.iter() {
+            if tx.tx_id.0 != self.next_txn_sid {
+                alert!(&nodes[0], "unexpected txn sid: {}", tx.tx_id.0);
+            }
+            self.next_txn_sid = 1 + tx.tx_id.0;
+            self.txn_merkle
+                .append_hash(&tx.hash().0.hash.into())
+                .c(d!())?;
+        }
+
+        let txns = block
+            .txns
+            .iter()
+            .map(|tx| tx.txn.clone())
+            .collect::<Vec<Transaction>>();
+        let txns_hash = HashOf::new(&txns);
+        self.block_merkle
+            .append_hash(&txns_hash.0.hash.into())
+            .c(d!())?;
+
+        let state = &block.state;
+        if state.txns_in_block_hash != txns_hash {
+            alert!(&nodes[0], "`txns_in_block_hash` mismatch");
+        }
+        if state.transaction_merkle_commitment != self.txn_merkle.get_root_hash() {
+            alert!(&nodes[0], "`transaction_merkle_commitment` mismatch");
+        }
+        if state.block_merkle != self.block_merkle.get_root_hash() {
+            alert!(&nodes[0], "`block_merkle` mismatch");
+        }
+
+        // 2. check the linkage of state commitments
+        if state.previous_state_commitment != HashOf::new(&self.prev_state) {
+            alert!(&nodes[0], "`previous_state_commitment` mismatch");
+        }
+        if let Some(prev) = self.prev_state.as_ref() {
+            if state.txo_count < prev.txo_count {
+                alert!(
+                    &nodes[0],
+                    "`txo_count` decreased: {} -> {}",
+                    prev.txo_count,
+                    state.txo_count
+                );
+            }
+        }
+        let commitment = state.compute_commitment();
+
+        // 3. all nodes should agree on the same state
+        for node in nodes.iter().skip(1) {
+            match get_block(node, sid) {
+                Ok(Some(b)) => {
+                    if b.state.compute_commitment() != commitment {
+                        alert!(node, "state commitment diverged from {}", &nodes[0]);
+                    }
+                }
+                // the node may be lagging behind
+                Ok(None) => {}
+                Err(e) => alert!(node, "unreachable: {}", e),
+            }
+        }
+
+        self.prev_state = Some(block.state);
+        self.next_block_sid += 1;
+
+        Ok(Some(alerts))
+    }
+}
+
+fn emit_alert(alert: &Alert, webhook: Option<&str>) {
+    eprintln!(
+        "\x1b[31;01m[{}] ALERT\x1b[00m block {}, node {}: {}",
+        datetime!(),
+        alert.block_sid,
+        alert.node,
+        &alert.reason
+    );
+
+    if let Some(url) = webhook {
+        let res = attohttpc::post(url)
+            .json(alert)
+            .c(d!())
+            .and_then(|req| req.send().c(d!()))
+            .and_then(|resp| resp.error_for_status().c(d!()));
+        info_omit!(res);
+    }
+}
@@ -0,0 +1,412 @@
+//!
+//! Gossip network of auditors.
+//!
+//! Auditors form a libp2p gossipsub mesh,
+//! peers are discovered by mDNS in a LAN and by Kademlia in a WAN,
+//! all connections are encrypted by Noise.
+//!
+//! Every `checkpoint_itv` blocks, each auditor publishes a signed attestation
+//! of the state commitment it has verified; a checkpoint is flagged if
+//! it does not collect enough matching attestations in time.
+//!
+
+use {
+    futures::StreamExt,
+    globutils::{wallet, HashOf, SignatureOf},
+    ledger::data_model::StateCommitmentData,
+    libp2p::{
+        core::upgrade,
+        gossipsub::{
+            Gossipsub, GossipsubConfigBuilder, GossipsubEvent, IdentTopic,
+            MessageAuthenticity, ValidationMode,
+        },
+        identity,
+        kad::{store::MemoryStore, Kademlia, KademliaEvent},
+        mdns,
+        multiaddr::Protocol,
+        noise,
+        swarm::{NetworkBehaviour, SwarmEvent},
+        tcp, yamux, Multiaddr, PeerId, Swarm, Transport,
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        sync::mpsc as std_mpsc,
+        thread,
+        time::{Duration, Instant},
+    },
+    tokio::{runtime::Runtime, sync::mpsc},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+const TOPIC: &str = "findora-audit-attestations";
+
+// checkpoints older than this will be dropped from the memory
+const CHECKPOINTS_KEPT: usize = 1000;
+
+type Commitment = HashOf<Option<StateCommitmentData>>;
+
+/// Options of the gossip network
+#[derive(Debug, Clone)]
+pub struct GossipCfg {
+    /// multiaddr to listen on, eg. "/ip4/0.0.0.0/tcp/9527"
+    pub listen: String,
+    /// multiaddrs of known auditors, eg. "/ip4/1.2.3.4/tcp/9527/p2p/12D3Koo...",
+    /// used to bootstrap the Kademlia routing table
+    pub bootnodes: Vec<String>,
+    /// only the attestations signed by these keys will be counted
+    pub auditors: Vec<XfrPublicKey>,
+    /// how many matching attestations(including the local one) a checkpoint needs
+    pub quorum: usize,
+    /// attest every N blocks
+    pub checkpoint_itv: usize,
+    /// seconds to wait for a checkpoint to reach quorum
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Attestation {
+    block_sid: usize,
+    commitment: Commitment,
+    pubkey: XfrPublicKey,
+    signature: SignatureOf<(usize, Commitment)>,
+}
+
+impl Attestation {
+    fn new(kp: &XfrKeyPair, block_sid: usize, commitment: Commitment) -> Self {
+        let signature = SignatureOf::new(kp, &(block_sid, commitment.clone()));
+        Self {
+            block_sid,
+            commitment,
+            pubkey: kp.get_pk(),
+            signature,
+        }
+    }
+
+    fn verify(&self) -> Result<()> {
+        self.signature
+            .verify(&self.pubkey, &(self.block_sid, self.commitment.clone()))
+            .c(d!())
+    }
+}
+
+/// The local side of the gossip network,
+/// the network itself runs in a background thread.
+pub struct Gossip {
+    checkpoint_itv: usize,
+    attest_tx: mpsc::UnboundedSender<(usize, Commitment)>,
+    alert_rx: std_mpsc::Receiver<(usize, String)>,
+}
+
+impl Gossip {
+    /// Join the gossip network, attestations will be signed by `kp`.
+    pub fn start(cfg: GossipCfg, kp: XfrKeyPair) -> Result<Self> {
+        if cfg.auditors.is_empty() || 0 == cfg.quorum || 0 == cfg.checkpoint_itv {
+            return Err(eg!("invalid gossip options"));
+        }
+
+        let (attest_tx, attest_rx) = mpsc::unbounded_channel();
+        let (alert_tx, alert_rx) = std_mpsc::channel();
+        let checkpoint_itv = cfg.checkpoint_itv;
+
+        let rt = Runtime::new().c(d!())?;
+        let mut net = rt.block_on(async { Network::new(cfg, kp) }).c(d!())?;
+        thread::spawn(move || {
+            rt.block_on(net.run(attest_rx, alert_tx));
+        });
+
+        Ok(Self {
+            checkpoint_itv,
+            attest_tx,
+            alert_rx,
+        })
+    }
+
+    /// Attest the state commitment of a verified block,
+    /// blocks that are not checkpoints are ignored.
+    pub fn attest(&self, block_sid: usize, commitment: Commitment) -> Result<()> {
+        if 0 != block_sid % self.checkpoint_itv {
+            return Ok(());
+        }
+        self.attest_tx
+            .send((block_sid, commitment))
+            .map_err(|e| eg!(e))
+    }
+
+    /// Checkpoints that failed to reach quorum, in the form of `(block_sid, reason)`.
+    pub fn alerts(&self) -> Vec<(usize, String)> {
+        self.alert_rx.try_iter().collect()
+    }
+}
+
+#[derive(NetworkBehaviour)]
+#[behaviour(out_event = "Event")]
+struct Behaviour {
+    gossipsub: Gossipsub,
+    kademlia: Kademlia<MemoryStore>,
+    mdns: mdns::tokio::Behaviour,
+}
+
+enum Event {
+    Gossipsub(GossipsubEvent),
+    Kademlia(KademliaEvent),
+    Mdns(mdns::Event),
+}
+
+impl From<GossipsubEvent> for Event {
+    fn from(e: GossipsubEvent) -> Self {
+        Self::Gossipsub(e)
+    }
+}
+
+impl From<KademliaEvent> for Event {
+    fn from(e: KademliaEvent) -> Self {
+        Self::Kademlia(e)
+    }
+}
+
+impl From<mdns::Event> for Event {
+    fn from(e: mdns::Event) -> Self {
+        Self::Mdns(e)
+    }
+}
+
+struct Network {
+    swarm: Swarm<Behaviour>,
+    topic: IdentTopic,
+    kp: XfrKeyPair,
+    cfg: GossipCfg,
+    // base64-formated pubkeys
+    auditors: BTreeSet<String>,
+    // checkpoint => (auditor => commitment)
+    seen: BTreeMap<usize, BTreeMap<String, Commitment>>,
+    // checkpoint => (local commitment, deadline)
+    pending: BTreeMap<usize, (Commitment, Instant)>,
+}
+
+impl Network {
+    fn new(cfg: GossipCfg, kp: XfrKeyPair) -> Result<Self> {
+        // the identity of a peer is irrelevant to the trust of attestations,
+        // so an ephemeral one is enough
+        let id_keys = identity::Keypair::generate_ed25519();
+        let peer_id = PeerId::from(id_keys.public());
+
+        let transport = tcp::tokio::Transport::new(tcp::Config::default().nodelay(true))
+            .upgrade(upgrade::Version::V1)
+            .authenticate(noise::NoiseAuthenticated::xx(&id_keys).c(d!())?)
+            .multiplex(yamux::YamuxConfig::default())
+            .boxed();
+
+        let gossipsub_cfg = GossipsubConfigBuilder::default()
+            .validation_mode(ValidationMode::Strict)
+            .build()
+            .map_err(|e| eg!(e))?;
+        let mut gossipsub =
+            Gossipsub::new(MessageAuthenticity::Signed(id_keys), gossipsub_cfg)
+                .map_err(|e| eg!(e))?;
+        let topic = IdentTopic::new(TOPIC);
+        gossipsub.subscribe(&topic).c(d!())?;
+
+        let mut kademlia = Kademlia::new(peer_id, MemoryStore::new(peer_id));
+        for node in cfg.bootnodes.iter() {
+            let addr = node.parse::<Multiaddr>().c(d!(node.to_owned()))?;
+            let id = match addr.iter().last() {
+                Some(Protocol::P2p(hash)) => PeerId::from_multihash(hash)
+                    .map_err(|_| eg!("invalid peer id: {}", node))?,
+                _ => return Err(eg!("peer id is missing: {}", node)),
+            };
+            kademlia.add_address(&id, addr);
+        }
+
+        let behaviour = Behaviour {
+            gossipsub,
+            kademlia,
+            mdns: mdns::tokio::Behaviour::new(mdns::Config::default()).c(d!())?,
+        };
+
+        let mut swarm = Swarm::with_tokio_executor(transport, behaviour, peer_id);
+        swarm
+            .listen_on(cfg.listen.parse::<Multiaddr>().c(d!())?)
+            .c(d!())?;
+        for node in cfg.bootnodes.iter() {
+            info_omit!(swarm.dial(node.parse::<Multiaddr>().c(d!())?).c(d!()));
+        }
+
+        let auditors = cfg
+            .auditors
+            .iter()
+            .map(wallet::public_key_to_base64)
+            .collect();
+
+        Ok(Self {
+            swarm,
+            topic,
+            kp,
+            cfg,
+            auditors,
+            seen: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        })
+    }
+
+    async fn run(
+        &mut self,
+        mut attest_rx: mpsc::UnboundedReceiver<(usize, Commitment)>,
+        alert_tx: std_mpsc::Sender<(usize, String)>,
+    ) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        let mut bootstrap_ticker = tokio::time::interval(Duration::from_secs(300));
+
+        loop {
+            tokio::select! {
+                Some((block_sid, commitment)) = attest_rx.recv() => {
+                    self.attest_local(block_sid, commitment);
+                }
+                event = self.swarm.select_next_some() => {
+                    self.handle_event(event);
+                }
+                _ = ticker.tick() => {
+                    for alert in self.check_quorum() {
+                        // the daemon has exited
+                        alt!(alert_tx.send(alert).is_err(), return);
+                    }
+                }
+                _ = bootstrap_ticker.tick() => {
+                    if !self.cfg.bootnodes.is_empty() {
+                        omit!(self.swarm.behaviour_mut().kademlia.bootstrap());
+                    }
+                }
+            }
+        }
+    }
+
+    fn attest_local(&mut self, block_sid: usize, commitment: Commitment) {
+        let att = Attestation::new(&self.kp, block_sid, commitment.clone());
+        let deadline = Instant::now() + Duration::from_secs(self.cfg.timeout_secs);
+        self.pending.insert(block_sid, (commitment, deadline));
+        self.record(att.clone());
+
+        // `InsufficientPeers` is expected before any peer is found,
+        // the checkpoint will be flagged later if quorum is not reached
+        if let Ok(msg) = serde_json::to_vec(&att) {
+            info_omit!(self
+                .swarm
+                .behaviour_mut()
+                .gossipsub
+                .publish(self.topic.clone(), msg)
+                .c(d!()));
+        }
+    }
+
+    fn handle_event<E>(&mut self, event: SwarmEvent<Event, E>) {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                println!(
+                    "[{}] gossip: listening on {}/p2p/{}",
+                    datetime!(),
+                    address,
+                    self.swarm.local_peer_id()
+                );
+            }
+            SwarmEvent::Behaviour(Event::Mdns(mdns::Event::Discovered(peers))) => {
+                for (peer, addr) in peers {
+                    let b = self.swarm.behaviour_mut();
+                    b.gossipsub.add_explicit_peer(&peer);
+                    b.kademlia.add_address(&peer, addr);
+                }
+            }
+            // peers found in a WAN
+            SwarmEvent::Behaviour(Event::Kademlia(KademliaEvent::RoutingUpdated {
+                peer,
+                ..
+            })) => {
+                self.swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .add_explicit_peer(&peer);
+            }
+            SwarmEvent::Behaviour(Event::Mdns(mdns::Event::Expired(peers))) => {
+                for (peer, _) in peers {
+                    self.swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .remove_explicit_peer(&peer);
+                }
+            }
+            SwarmEvent::Behaviour(Event::Gossipsub(GossipsubEvent::Message {
+                message,
+                ..
+            })) => {
+                if let Ok(att) = serde_json::from_slice::<Attestation>(&message.data) {
+                    self.record(att);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // attestations from unknown auditors or with invalid signatures are dropped
+    fn record(&mut self, att: Attestation) {
+        let pubkey = wallet::public_key_to_base64(&att.pubkey);
+        if !self.auditors.contains(&pubkey)
+            && pubkey != wallet::public_key_to_base64(&self.kp.get_pk())
+        {
+            return;
+        }
+        if 0 != att.block_sid % self.cfg.checkpoint_itv || att.verify().is_err() {
+            return;
+        }
+
+        self.seen
+            .entry(att.block_sid)
+            .or_default()
+            .insert(pubkey, att.commitment);
+
+        while self.seen.len() > CHECKPOINTS_KEPT {
+            self.seen.pop_first();
+        }
+    }
+
+    // flag expired checkpoints that do not have enough matching attestations
+    fn check_quorum(&mut self) -> Vec<(usize, String)> {
+        let now = Instant::now();
+        let expired = self
+            .pending
+            .iter()
+            .filter(|(_, (_, deadline))| *deadline <= now)
+            .map(|(sid, _)| *sid)
+            .collect::<Vec<_>>();
+
+        let mut alerts = vec![];
+        for sid in expired {
+            let (local, _) = self.pending.remove(&sid).unwrap();
+            let attestations = self.seen.get(&sid).cloned().unwrap_or_default();
+
+            let matched = attestations.values().filter(|c| **c == local).count();
+            if matched < self.cfg.quorum {
+                alerts.push((
+                    sid,
+                    format!(
+                        "checkpoint failed to reach quorum: {}/{}",
+                        matched, self.cfg.quorum
+                    ),
+                ));
+            }
+
+            let conflicting = attestations
+                .iter()
+                .filter(|(_, c)| **c != local)
+                .map(|(k, _)| k.as_str())
+                .collect::<Vec<_>>();
+            if !conflicting.is_empty() {
+                alerts.push((
+                    sid,
+                    format!("conflicting attestations from: {}", conflicting.join(", ")),
+                ));
+            }
+        }
+
+        alerts
+    }
+}
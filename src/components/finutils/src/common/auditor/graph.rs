@@ -0,0 +1,196 @@
+//!
+//! Asset flow graph over a block range,
+//! addresses are nodes and transfers are edges.
+//!
+
+use {
+    super::get_block_txs,
+    crate::common::{get_serv_addr, utils::get_block_height},
+    globutils::wallet,
+    ledger::data_model::{AssetTypeCode, Operation},
+    ruc::*,
+    serde::Serialize,
+    std::{collections::BTreeSet, fmt::Write as _, fs, str::FromStr},
+    zei::xfr::structs::{XfrAmount, XfrAssetType},
+};
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy)]
+pub enum GraphFormat {
+    GraphMl,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = Box<dyn RucError>;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "graphml" => Ok(Self::GraphMl),
+            "json" => Ok(Self::Json),
+            _ => Err(eg!("unknown format: {}", s)),
+        }
+    }
+}
+
+/// Options of `fn audit graph`
+#[derive(Debug, Clone)]
+pub struct GraphCfg {
+    /// the first block to scan
+    pub from_height: u64,
+    /// the last block to scan, default to the latest one
+    pub to_height: Option<u64>,
+    /// only keep transfers of this asset,
+    /// outputs with a confidential asset type will be dropped
+    pub asset: Option<AssetTypeCode>,
+    /// only keep transfers of at least this amount,
+    /// outputs with a confidential amount will be dropped
+    pub min_amount: Option<u64>,
+    #[allow(missing_docs)]
+    pub format: GraphFormat,
+    /// the graph will be printed to stdout if not set
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Edge {
+    from: String,
+    to: String,
+    height: u64,
+    tx_hash: String,
+    // `None` if confidential
+    asset: Option<String>,
+    // `None` if confidential, or shared by multiple senders
+    amount: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Graph {
+    // bech32-formated addresses
+    nodes: BTreeSet<String>,
+    edges: Vec<Edge>,
+}
+
+/// Scan committed blocks, and export the asset flow graph.
+pub fn gen_graph(cfg: &GraphCfg) -> Result<()> {
+    let to_height = match cfg.to_height {
+        Some(h) => h,
+        None => get_block_height(get_serv_addr().c(d!())?),
+    };
+    if 0 == cfg.from_height || cfg.from_height > to_height {
+        return Err(eg!(
+            "invalid block range: [{}, {}]",
+            cfg.from_height,
+            to_height
+        ));
+    }
+
+    let mut graph = Graph::default();
+    for h in cfg.from_height..=to_height {
+        for tx in get_block_txs(h).c(d!())? {
+            let tx_hash = hex::encode(tx.hash_tm_rawbytes());
+            for op in tx.body.operations.iter() {
+                let body = match op {
+                    Operation::TransferAsset(t) => &t.body.transfer,
+                    _ => continue,
+                };
+
+                let senders =
+                    body.inputs.iter().map(|i| i.public_key).collect::<Vec<_>>();
+                let mut uniq_senders = senders.clone();
+                uniq_senders.sort_by_key(wallet::public_key_to_base64);
+                uniq_senders.dedup();
+
+                // changes back to the senders are not flows
+                for o in body
+                    .outputs
+                    .iter()
+                    .filter(|o| !senders.contains(&o.public_key))
+                {
+                    let asset = match o.asset_type {
+                        XfrAssetType::NonConfidential(at) => {
+                            Some(AssetTypeCode { val: at })
+                        }
+                        XfrAssetType::Confidential(_) => None,
+                    };
+                    let amount = match o.amount {
+                        XfrAmount::NonConfidential(am) => Some(am),
+                        XfrAmount::Confidential(_) => None,
+                    };
+
+                    if let Some(code) = cfg.asset.as_ref() {
+                        alt!(asset.as_ref() != Some(code), continue);
+                    }
+                    if let Some(min) = cfg.min_amount {
+                        alt!(amount.map(|am| am < min).unwrap_or(true), continue);
+                    }
+
+                    let to = wallet::public_key_to_bech32(&o.public_key);
+                    for s in uniq_senders.iter() {
+                        let from = wallet::public_key_to_bech32(s);
+                        graph.nodes.insert(from.clone());
+                        graph.nodes.insert(to.clone());
+                        graph.edges.push(Edge {
+                            from,
+                            to: to.clone(),
+                            height: h,
+                            tx_hash: tx_hash.clone(),
+                            asset: asset.as_ref().map(|a| a.to_base64()),
+                            amount: alt!(1 == uniq_senders.len(), amount, None),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let contents = match cfg.format {
+        GraphFormat::Json => serde_json::to_string_pretty(&graph).c(d!())?,
+        GraphFormat::GraphMl => to_graphml(&graph),
+    };
+
+    if let Some(path) = cfg.output.as_deref() {
+        fs::write(path, contents).c(d!())
+    } else {
+        println!("{}", contents);
+        Ok(())
+    }
+}
+
+fn to_graphml(graph: &Graph) -> String {
+    let mut g = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="height" for="edge" attr.name="height" attr.type="long"/>
+  <key id="tx_hash" for="edge" attr.name="tx_hash" attr.type="string"/>
+  <key id="asset" for="edge" attr.name="asset" attr.type="string"/>
+  <key id="amount" for="edge" attr.name="amount" attr.type="long"/>
+  <graph id="asset_flow" edgedefault="directed">
+"#
+    .to_owned();
+
+    for n in graph.nodes.iter() {
+        writeln!(g, r#"    <node id="{}"/>"#, n).unwrap();
+    }
+
+    // all values are either numbers or base64/hex/bech32 strings,
+    // none of them need to be escaped
+    for (i, e) in graph.edges.iter().enumerate() {
+        writeln!(
+            g,
+            r#"    <edge id="e{}" source="{}" target="{}">"#,
+            i, &e.from, &e.to
+        )
+        .unwrap();
+        writeln!(g, r#"      <data key="height">{}</data>"#, e.height).unwrap();
+        writeln!(g, r#"      <data key="tx_hash">{}</data>"#, &e.tx_hash).unwrap();
+        if let Some(asset) = e.asset.as_deref() {
+            writeln!(g, r#"      <data key="asset">{}</data>"#, asset).unwrap();
+        }
+        if let Some(amount) = e.amount {
+            writeln!(g, r#"      <data key="amount">{}</data>"#, amount).unwrap();
+        }
+        g.push_str("    </edge>\n");
+    }
+
+    g.push_str("  </graph>\n</graphml>\n");
+    g
+}
@@ -0,0 +1,65 @@
+//!
+//! # Auditor
+//!
+//! Tools for the holders of tracer keys(see `TracingPolicy`),
+//! all data are fetched from the RPC endpoint of a full node.
+//!
+
+mod cold;
+mod daemon;
+mod gossip;
+mod graph;
+mod report;
+mod reserves;
+
+pub use cold::{audit_cold_storage, ColdAuditCfg};
+pub use daemon::{run_daemon, DaemonCfg};
+pub use gossip::GossipCfg;
+pub use graph::{gen_graph, GraphCfg, GraphFormat};
+pub use report::{gen_report, ReportCfg, ReportFormat};
+pub use reserves::{prove_reserves, verify_reserves};
+
+use {
+    crate::common::get_serv_addr, ledger::data_model::Transaction, ruc::*, std::fs,
+    zei::xfr::structs::AssetTracerKeyPair,
+};
+
+/// Load tracer keys from a JSON file,
+/// the content can be a single `AssetTracerKeyPair` or a list of them.
+pub fn load_tracer_keys(path: &str) -> Result<Vec<AssetTracerKeyPair>> {
+    let c = fs::read(path).c(d!(path.to_owned()))?;
+    serde_json::from_slice::<Vec<AssetTracerKeyPair>>(&c)
+        .or_else(|_| serde_json::from_slice::<AssetTracerKeyPair>(&c).map(|k| vec![k]))
+        .c(d!("invalid tracer keys"))
+        .and_then(|keys| {
+            alt!(keys.is_empty(), Err(eg!("no tracer keys found")), Ok(keys))
+        })
+}
+
+/// Findora transactions of a committed block,
+/// EVM transactions and other unknown contents are ignored.
+pub fn get_block_txs(height: u64) -> Result<Vec<Transaction>> {
+    let url = format!("{}:26657/block?height={}", get_serv_addr().c(d!())?, height);
+
+    let block = attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).c(d!()))?;
+
+    // `txs` is `null` for an empty block
+    let txs = match block["result"]["block"]["data"]["txs"].as_array() {
+        Some(txs) => txs,
+        None => return Ok(vec![]),
+    };
+
+    Ok(txs
+        .iter()
+        .filter_map(|tx| tx.as_str())
+        .filter_map(|tx| base64::decode(tx).ok())
+        .filter_map(|tx| serde_json::from_slice::<Transaction>(&tx).ok())
+        .collect())
+}
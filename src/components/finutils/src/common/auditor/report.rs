@@ -0,0 +1,197 @@
+//!
+//! Compliance reports of traced assets over a block range.
+//!
+
+use {
+    super::{get_block_txs, load_tracer_keys},
+    crate::common::{get_serv_addr, utils::get_block_height},
+    globutils::wallet,
+    ledger::data_model::{AssetTypeCode, Operation},
+    ruc::*,
+    serde::Serialize,
+    std::{collections::BTreeMap, fmt::Write as _, fs, str::FromStr},
+    zei::xfr::{
+        lib::trace_assets,
+        sig::XfrPublicKey,
+        structs::{AssetTracerKeyPair, AssetType as ZeiAssetType, XfrBody},
+    },
+};
+
+// (amount, asset type, identity attributes, owner)
+type RecordData = (u64, ZeiAssetType, Vec<u32>, XfrPublicKey);
+
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = Box<dyn RucError>;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(eg!("unknown format: {}", s)),
+        }
+    }
+}
+
+/// Options of `fn audit report`
+#[derive(Debug, Clone)]
+pub struct ReportCfg {
+    /// path of the tracer keys, see `load_tracer_keys`
+    pub tracer_keys: String,
+    /// the first block to scan
+    pub from_height: u64,
+    /// the last block to scan, default to the latest one
+    pub to_height: Option<u64>,
+    #[allow(missing_docs)]
+    pub format: ReportFormat,
+    /// the report will be printed to stdout if not set
+    pub output: Option<String>,
+}
+
+// A transfer output decrypted by a tracer key
+#[derive(Debug, Serialize)]
+struct TracedRecord {
+    height: u64,
+    tx_hash: String,
+    op_idx: usize,
+    amount: u64,
+    owner: String,
+    // identity attributes, empty if not traced
+    identity: Vec<u32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct AssetReport {
+    total_amount: u128,
+    records: Vec<TracedRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct ComplianceReport {
+    from_height: u64,
+    to_height: u64,
+    // base64-formated asset code => report
+    assets: BTreeMap<String, AssetReport>,
+}
+
+/// Scan committed blocks, decrypt the tracer memos of transfers,
+/// and emit a per-asset compliance report.
+pub fn gen_report(cfg: &ReportCfg) -> Result<()> {
+    let keys = load_tracer_keys(&cfg.tracer_keys).c(d!())?;
+
+    let to_height = match cfg.to_height {
+        Some(h) => h,
+        None => get_block_height(get_serv_addr().c(d!())?),
+    };
+    if 0 == cfg.from_height || cfg.from_height > to_height {
+        return Err(eg!(
+            "invalid block range: [{}, {}]",
+            cfg.from_height,
+            to_height
+        ));
+    }
+
+    let mut report = ComplianceReport {
+        from_height: cfg.from_height,
+        to_height,
+        assets: BTreeMap::new(),
+    };
+
+    for h in cfg.from_height..=to_height {
+        for tx in get_block_txs(h).c(d!())? {
+            let tx_hash = hex::encode(tx.hash_tm_rawbytes());
+            for (op_idx, op) in tx.body.operations.iter().enumerate() {
+                let body = match op {
+                    Operation::TransferAsset(t) => &t.body.transfer,
+                    _ => continue,
+                };
+                for kp in keys.iter() {
+                    for (amount, asset_type, identity, owner) in
+                        trace_outputs(body, kp).c(d!())?
+                    {
+                        let r = report
+                            .assets
+                            .entry(AssetTypeCode { val: asset_type }.to_base64())
+                            .or_default();
+                        r.total_amount += amount as u128;
+                        r.records.push(TracedRecord {
+                            height: h,
+                            tx_hash: tx_hash.clone(),
+                            op_idx,
+                            amount,
+                            owner: wallet::public_key_to_bech32(&owner),
+                            identity,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let contents = match cfg.format {
+        ReportFormat::Json => serde_json::to_string_pretty(&report).c(d!())?,
+        ReportFormat::Csv => to_csv(&report),
+    };
+
+    if let Some(path) = cfg.output.as_deref() {
+        fs::write(path, contents).c(d!())
+    } else {
+        println!("{}", contents);
+        Ok(())
+    }
+}
+
+// Only outputs are traced, inputs are outputs of earlier transfers,
+// tracing them again would count the same amount twice.
+//
+// Memos belonging to other tracers are also removed,
+// they can not be decrypted by the current key.
+fn trace_outputs(body: &XfrBody, kp: &AssetTracerKeyPair) -> Result<Vec<RecordData>> {
+    let mut body = body.clone();
+    let n = body.inputs.len().min(body.asset_tracing_memos.len());
+    body.asset_tracing_memos.drain(..n);
+    body.inputs.clear();
+
+    body.asset_tracing_memos
+        .iter_mut()
+        .for_each(|memos| memos.retain(|m| m.enc_key == kp.enc_key));
+    if body
+        .asset_tracing_memos
+        .iter()
+        .all(|memos| memos.is_empty())
+    {
+        return Ok(vec![]);
+    }
+
+    trace_assets(&body, kp).c(d!())
+}
+
+fn to_csv(report: &ComplianceReport) -> String {
+    let mut csv = "asset,height,tx_hash,op_idx,amount,owner,identity\n".to_owned();
+    for (asset, r) in report.assets.iter() {
+        for i in r.records.iter() {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                asset,
+                i.height,
+                &i.tx_hash,
+                i.op_idx,
+                i.amount,
+                &i.owner,
+                i.identity
+                    .iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
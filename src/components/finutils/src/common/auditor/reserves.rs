@@ -0,0 +1,369 @@
+//!
+//! Proof of reserves,
+//! a signed statement tying the unspent outputs of some keys to a state commitment.
+//!
+//! Every output is accompanied by its `AuthenticatedUtxo` proof,
+//! so anyone can check with a light client that it exists and is unspent
+//! at the given state commitment, without trusting the prover or the node.
+//!
+//! NOTE: the amounts of confidential outputs are opened by the viewing keys of the prover,
+//! they are covered by the signature of the statement but can not be recomputed by others.
+//!
+
+use {
+    crate::common::{get_keypair, get_serv_addr, utils::get_owned_utxos},
+    globutils::{address, wallet, HashOf, SignatureOf},
+    ledger::data_model::{
+        AssetTypeCode, AuthenticatedUtxo, StateCommitmentData, TxoSID,
+    },
+    ruc::*,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::{
+        asset_record::open_blind_asset_record,
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::{XfrAmount, XfrAssetType},
+    },
+};
+
+// the ledger may move forward while proofs are being fetched
+pub(super) const MAX_ATTEMPTS: usize = 5;
+
+pub(super) type Commitment = HashOf<Option<StateCommitmentData>>;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ReserveRecord {
+    txo_sid: TxoSID,
+    proof: AuthenticatedUtxo,
+    // (amount, base64-formated asset code), only for confidential outputs
+    opened: Option<(u64, String)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ReservesStatement {
+    state_commitment: Commitment,
+    // the index of `state_commitment` in the ledger, starts from 1
+    block_count: u64,
+    owners: Vec<XfrPublicKey>,
+    // base64-formated asset code => total amount
+    totals: BTreeMap<String, u128>,
+    records: Vec<ReserveRecord>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SignedStatement {
+    statement: ReservesStatement,
+    signer: XfrPublicKey,
+    signature: SignatureOf<ReservesStatement>,
+}
+
+/// Generate a proof-of-reserves statement for the keys listed in `keys_path`,
+/// the statement will be signed by the key set by `fn setup`.
+///
+/// The file contains one key per line, either a public key(bech32 or base64),
+/// or a viewing key(a base64-formated secret key or a mnemonic) that can open
+/// confidential outputs; confidential outputs of plain public keys are not counted.
+pub fn prove_reserves(keys_path: &str, output: Option<&str>) -> Result<()> {
+    let keys = load_keys(keys_path).c(d!())?;
+    let signer = get_keypair().c(d!())?;
+
+    let mut statement = None;
+    for _ in 0..MAX_ATTEMPTS {
+        if let Some(s) = collect_reserves(&keys).c(d!())? {
+            statement = Some(s);
+            break;
+        }
+    }
+    let statement =
+        statement.c(d!("the ledger keeps moving, please try again later"))?;
+
+    let signed = sign_statement(&signer, statement);
+    let contents = serde_json::to_string_pretty(&signed).c(d!())?;
+
+    if let Some(path) = output {
+        fs::write(path, contents).c(d!())
+    } else {
+        println!("{}", contents);
+        Ok(())
+    }
+}
+
+/// Verify a proof-of-reserves statement:
+/// - the signature of the statement
+/// - the state commitment matches the one of the ledger at the same height
+/// - every output is proved to be unspent and owned by the listed keys
+/// - the totals match the sum of all outputs
+pub fn verify_reserves(path: &str) -> Result<()> {
+    let signed = fs::read(path)
+        .c(d!())
+        .and_then(|c| serde_json::from_slice::<SignedStatement>(&c).c(d!()))?;
+    let s = &signed.statement;
+
+    check_statement(&signed).c(d!())?;
+
+    let url = format!(
+        "{}:8668/global_state_version/{}",
+        get_serv_addr().c(d!())?,
+        s.block_count
+    );
+    let commitment = http_get::<Option<Commitment>>(&url)
+        .c(d!())?
+        .c(d!("unknown block count"))?;
+    if commitment != s.state_commitment {
+        return Err(eg!("state commitment mismatch"));
+    }
+
+    println!(
+        "Valid statement signed by {}, at block {}:",
+        wallet::public_key_to_bech32(&signed.signer),
+        s.block_count
+    );
+    for (asset, amount) in s.totals.iter() {
+        println!("  {}: {}", asset, amount);
+    }
+
+    Ok(())
+}
+
+fn sign_statement(signer: &XfrKeyPair, statement: ReservesStatement) -> SignedStatement {
+    SignedStatement {
+        signature: SignatureOf::new(signer, &statement),
+        signer: signer.get_pk(),
+        statement,
+    }
+}
+
+// all but the state commitment, which has to be looked up in the ledger
+fn check_statement(signed: &SignedStatement) -> Result<()> {
+    let s = &signed.statement;
+
+    signed
+        .signature
+        .verify(&signed.signer, s)
+        .c(d!("invalid signature"))?;
+
+    for r in s.records.iter() {
+        if !r.proof.is_valid(s.state_commitment.clone()) {
+            return Err(eg!("invalid proof of txo {}", r.txo_sid.0));
+        }
+        if !s.owners.contains(&r.proof.utxo.0.record.public_key) {
+            return Err(eg!("txo {} is not owned by the listed keys", r.txo_sid.0));
+        }
+    }
+    if sum_records(&s.records).c(d!())? != s.totals {
+        return Err(eg!("totals mismatch"));
+    }
+
+    Ok(())
+}
+
+// base64-formated asset code => total amount
+fn sum_records(records: &[ReserveRecord]) -> Result<BTreeMap<String, u128>> {
+    let mut totals = BTreeMap::new();
+    for r in records.iter() {
+        let (amount, asset) = record_value(&r.proof)
+            .or_else(|| r.opened.clone())
+            .c(d!("txo {} is not opened", r.txo_sid.0))?;
+        *totals.entry(asset).or_insert(0) += amount as u128;
+    }
+    Ok(totals)
+}
+
+// `None` if the ledger moved forward during the collection
+fn collect_reserves(
+    keys: &[(XfrPublicKey, Option<XfrKeyPair>)],
+) -> Result<Option<ReservesStatement>> {
+    let addr = get_serv_addr().c(d!())?;
+    let (state_commitment, block_count, _) =
+        http_get::<(Commitment, u64, String)>(&format!("{}:8668/global_state", addr))
+            .c(d!())?;
+
+    let mut records = vec![];
+    for (pk, kp) in keys.iter() {
+        let mut utxos = get_owned_utxos(pk).c(d!())?.into_iter().collect::<Vec<_>>();
+        utxos.sort_by_key(|(sid, _)| sid.0);
+
+        for (sid, (utxo, memo)) in utxos {
+            let proof = http_get::<AuthenticatedUtxo>(&format!(
+                "{}:8668/utxo_sid/{}",
+                addr, sid.0
+            ))
+            .c(d!())?;
+            if proof.state_commitment_data.compute_commitment() != state_commitment {
+                return Ok(None);
+            }
+
+            let opened = match (record_value(&proof), kp) {
+                (Some(_), _) => None,
+                (None, Some(kp)) => {
+                    let oar =
+                        open_blind_asset_record(&utxo.0.record, &memo, kp).c(d!())?;
+                    Some((
+                        oar.amount,
+                        AssetTypeCode {
+                            val: oar.asset_type,
+                        }
+                        .to_base64(),
+                    ))
+                }
+                (None, None) => {
+                    eprintln!(
+                        "\x1b[33;01mWARNING: confidential txo {} is skipped, no viewing key\x1b[00m",
+                        sid.0
+                    );
+                    continue;
+                }
+            };
+
+            records.push(ReserveRecord {
+                txo_sid: sid,
+                proof,
+                opened,
+            });
+        }
+    }
+
+    Ok(Some(ReservesStatement {
+        state_commitment,
+        block_count,
+        owners: keys.iter().map(|(pk, _)| *pk).collect(),
+        totals: sum_records(&records).c(d!())?,
+        records,
+    }))
+}
+
+// (amount, base64-formated asset code) of a non-confidential output
+pub(super) fn record_value(proof: &AuthenticatedUtxo) -> Option<(u64, String)> {
+    let record = &proof.utxo.0.record;
+    match (&record.amount, &record.asset_type) {
+        (XfrAmount::NonConfidential(am), XfrAssetType::NonConfidential(at)) => {
+            Some((*am, AssetTypeCode { val: *at }.to_base64()))
+        }
+        _ => None,
+    }
+}
+
+fn load_keys(path: &str) -> Result<Vec<(XfrPublicKey, Option<XfrKeyPair>)>> {
+    let keys = fs::read_to_string(path)
+        .c(d!(path.to_owned()))?
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|k| {
+            if let Ok(pk) = address::parse_address(k) {
+                Ok((pk, None))
+            } else {
+                address::parse_keypair(k)
+                    .c(d!("invalid key"))
+                    .map(|kp| (kp.get_pk(), Some(kp)))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    alt!(keys.is_empty(), Err(eg!("no keys found")), Ok(keys))
+}
+
+pub(super) fn http_get<T: DeserializeOwned>(url: &str) -> Result<T> {
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ledger::{
+            data_model::ASSET_TYPE_FRA,
+            staking::FRA_PRE_ISSUE_AMOUNT,
+            store::{
+                helpers::apply_transaction, utils::fra_gen_initial_tx, LedgerState,
+            },
+        },
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+    };
+
+    #[test]
+    fn reserves_statement() {
+        let mut prng = ChaChaRng::from_entropy();
+        let kp = XfrKeyPair::generate(&mut prng);
+        let other_kp = XfrKeyPair::generate(&mut prng);
+
+        let mut ledger = LedgerState::tmp_ledger();
+        let (_, txos) = apply_transaction(&mut ledger, fra_gen_initial_tx(&kp));
+        let (state_commitment, block_count) = ledger.get_state_commitment();
+        let records = txos
+            .iter()
+            .map(|sid| ReserveRecord {
+                txo_sid: *sid,
+                proof: ledger.get_utxo(*sid).unwrap(),
+                opened: None,
+            })
+            .collect::<Vec<_>>();
+        let statement = ReservesStatement {
+            state_commitment,
+            block_count,
+            owners: vec![kp.get_pk()],
+            totals: pnk!(sum_records(&records)),
+            records,
+        };
+        let fra = AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        }
+        .to_base64();
+        assert_eq!(
+            Some(&((FRA_PRE_ISSUE_AMOUNT / 2 * 2) as u128)),
+            statement.totals.get(&fra)
+        );
+
+        let signed = sign_statement(&kp, statement.clone());
+        pnk!(check_statement(&signed));
+        // signed by another key than the owner
+        pnk!(check_statement(&sign_statement(
+            &other_kp,
+            statement.clone()
+        )));
+
+        // altered after the signature
+        let mut tampered = signed.clone();
+        tampered.statement.block_count += 1;
+        assert!(check_statement(&tampered).is_err());
+        let mut tampered = signed.clone();
+        tampered.signer = other_kp.get_pk();
+        assert!(check_statement(&tampered).is_err());
+
+        // altered and signed again
+        let resigned = |f: &dyn Fn(&mut ReservesStatement)| {
+            let mut s = statement.clone();
+            f(&mut s);
+            check_statement(&sign_statement(&kp, s))
+        };
+        assert!(resigned(&|s| {
+            *s.totals.get_mut(&fra).unwrap() += 1;
+        })
+        .is_err());
+        assert!(resigned(&|s| {
+            s.records.pop();
+        })
+        .is_err());
+        assert!(resigned(&|s| s.owners = vec![other_kp.get_pk()]).is_err());
+        assert!(resigned(&|s| {
+            s.records[0].proof.utxo.0.record.public_key = other_kp.get_pk();
+            s.owners.push(other_kp.get_pk());
+        })
+        .is_err());
+        assert!(resigned(&|s| {
+            s.records[0].proof.utxo.0.record.amount = XfrAmount::NonConfidential(1);
+            s.totals = pnk!(sum_records(&s.records));
+        })
+        .is_err());
+        // the proofs are bound to the state commitment
+        assert!(resigned(&|s| s.state_commitment = HashOf::new(&None)).is_err());
+    }
+}
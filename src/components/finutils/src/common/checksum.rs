@@ -0,0 +1,140 @@
+//!
+//! # Checksums of the ledger state
+//!
+//! `fn state-checksum` queries the checksums of the subsystems of the ledger,
+//! the unspent outputs, the assets, the revocation lists of AIR and the staking
+//! state, from the query server of a node, and compares them with the ones of
+//! another node with `--compare`, without moving full exports around.
+//!
+//! The two nodes are queried again until they are at a same height,
+//! the checksums of different heights are meaningless to compare.
+//!
+
+use {
+    super::get_serv_addr,
+    ledger::store::checksum::{LedgerChecksums, Subsystem},
+    ruc::*,
+    std::str::FromStr,
+};
+
+// the nodes are queried at most this many times to meet at a same height
+const MAX_ATTEMPTS: usize = 10;
+
+/// Query the checksums of `subsystems`, comma separated, all of them if missing,
+/// from `endpoint`, the query server of `fn setup` by default.
+pub fn show(endpoint: Option<&str>, subsystems: Option<&str>, json: bool) -> Result<()> {
+    let endpoint = endpoint_or_default(endpoint).c(d!())?;
+    let subsystems = parse_subsystems(subsystems).c(d!())?;
+    let checksums = fetch(&endpoint, &subsystems).c(d!())?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checksums).c(d!())?);
+        return Ok(());
+    }
+
+    println!(
+        "\x1b[31;01mHeight:\x1b[00m {}, \x1b[31;01mState commitment:\x1b[00m {}",
+        checksums.height,
+        hex::encode(checksums.state_commitment.0.hash.as_ref())
+    );
+    for (sub, c) in checksums.subsystems.iter() {
+        println!("{:<8} {} ({} entries)", sub, c.checksum, c.entries);
+    }
+    Ok(())
+}
+
+/// Compare the checksums of `subsystems` of `endpoint` and `other` at a same
+/// height, an error is returned on any mismatch.
+pub fn compare(
+    endpoint: Option<&str>,
+    other: &str,
+    subsystems: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let endpoint = endpoint_or_default(endpoint).c(d!())?;
+    let subsystems = parse_subsystems(subsystems).c(d!())?;
+
+    let mut attempts = 0;
+    let (left, right) = loop {
+        let left = fetch(&endpoint, &subsystems).c(d!())?;
+        let right = fetch(other, &subsystems).c(d!())?;
+        if left.height == right.height {
+            break (left, right);
+        }
+        attempts += 1;
+        if MAX_ATTEMPTS <= attempts {
+            return Err(eg!(format!(
+                "the nodes are not at a same height: {} vs {}",
+                left.height, right.height
+            )));
+        }
+        sleep_ms!(1000);
+    };
+    let mismatches = left.mismatches(&right);
+
+    if json {
+        let res = serde_json::json!({
+            "height": left.height,
+            "left": left,
+            "right": right,
+            "mismatches": mismatches,
+        });
+        println!("{}", serde_json::to_string_pretty(&res).c(d!())?);
+    } else {
+        println!("\x1b[31;01mHeight:\x1b[00m {}", left.height);
+        for sub in left.subsystems.keys() {
+            println!(
+                "{:<8} {}",
+                sub,
+                alt!(mismatches.contains(sub), "MISMATCH", "OK")
+            );
+        }
+    }
+
+    alt!(
+        mismatches.is_empty(),
+        Ok(()),
+        Err(eg!(format!(
+            "mismatched subsystems: {}",
+            mismatches
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )))
+    )
+}
+
+fn fetch(endpoint: &str, subsystems: &[Subsystem]) -> Result<LedgerChecksums> {
+    let url = format!("{}/state_checksums", endpoint.trim_end_matches('/'));
+    let subsystems = subsystems
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    attohttpc::get(&url)
+        .param("subsystems", subsystems)
+        .send()
+        .c(d!(url.clone()))?
+        .error_for_status()
+        .c(d!(url))?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn endpoint_or_default(endpoint: Option<&str>) -> Result<String> {
+    match endpoint {
+        Some(e) => Ok(e.to_owned()),
+        None => get_serv_addr().c(d!()).map(|addr| format!("{}:8668", addr)),
+    }
+}
+
+fn parse_subsystems(subsystems: Option<&str>) -> Result<Vec<Subsystem>> {
+    subsystems
+        .unwrap_or_default()
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| Subsystem::from_str(s).c(d!()))
+        .collect()
+}
@@ -0,0 +1,216 @@
+//!
+//! # Deposit addresses
+//!
+//! One deposit address per customer, eg. of an exchange, all derived from the
+//! mnemonic of `fn setup` at `m/44'/917'/<account>'/0/<index>`, the account
+//! being `DEPOSIT_ACCOUNT` by default to stay apart from the main address.
+//!
+//! The indexes of the customers and the credited outputs are saved in the
+//! config directory of `fn`, `scan` attributes the new unspent outputs of the
+//! deposit addresses to their customers, each output is credited once.
+//!
+
+use {
+    super::{get_mnemonic, utils, DEPOSIT_FILE},
+    globutils::wallet,
+    ledger::data_model::{AssetTypeCode, TxoSID},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::{asset_record::open_blind_asset_record, sig::XfrKeyPair},
+};
+
+/// The bip44 account of the deposit addresses, account 0 is the main one
+pub const DEPOSIT_ACCOUNT: u32 = 1;
+
+const FRA: u32 = 917;
+
+/// A deposit address
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DepositAddress {
+    /// the id of the customer in the books of the exchange
+    pub customer: String,
+    /// in bech32
+    pub address: String,
+}
+
+/// An output credited to a customer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Credit {
+    #[allow(missing_docs)]
+    pub txo_sid: TxoSID,
+    #[allow(missing_docs)]
+    pub index: u32,
+    #[allow(missing_docs)]
+    pub customer: String,
+    /// the asset code in base64
+    pub asset: String,
+    #[allow(missing_docs)]
+    pub amount: u64,
+}
+
+/// The local state of the deposit addresses
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DepositBook {
+    /// the bip44 account of the addresses, fixed on the first derivation
+    pub account: u32,
+    /// the addresses by their indexes
+    pub addresses: BTreeMap<u32, DepositAddress>,
+    /// the credited outputs
+    pub credits: BTreeMap<TxoSID, Credit>,
+}
+
+impl Default for DepositBook {
+    fn default() -> Self {
+        DepositBook {
+            account: DEPOSIT_ACCOUNT,
+            addresses: BTreeMap::new(),
+            credits: BTreeMap::new(),
+        }
+    }
+}
+
+impl DepositBook {
+    /// The index of `customer`, if an address has been derived for it.
+    pub fn index_of(&self, customer: &str) -> Option<u32> {
+        self.addresses
+            .iter()
+            .find(|(_, a)| a.customer == customer)
+            .map(|(idx, _)| *idx)
+    }
+}
+
+#[allow(missing_docs)]
+pub fn load() -> Result<DepositBook> {
+    match fs::read(&*DEPOSIT_FILE) {
+        Ok(b) => serde_json::from_slice(&b).c(d!()),
+        Err(_) => Ok(DepositBook::default()),
+    }
+}
+
+fn save(book: &DepositBook) -> Result<()> {
+    serde_json::to_vec_pretty(book)
+        .c(d!())
+        .and_then(|b| fs::write(&*DEPOSIT_FILE, b).c(d!()))
+}
+
+/// The key pair of the deposit address `index` of `account`.
+pub fn derive_keypair(phrase: &str, account: u32, index: u32) -> Result<XfrKeyPair> {
+    wallet::restore_keypair_from_mnemonic_bip44(
+        phrase.trim(),
+        "en",
+        &wallet::BipPath::new(FRA, account, 0, index),
+    )
+    .c(d!("deposit addresses can only be derived from a mnemonic"))
+}
+
+/// The deposit address of `customer`, a new one is derived
+/// at the next index if it has none.
+pub fn address_of(customer: &str) -> Result<(u32, DepositAddress)> {
+    let customer = customer.trim();
+    if customer.is_empty() {
+        return Err(eg!("empty customer id"));
+    }
+
+    let mut book = load().c(d!())?;
+    if let Some(idx) = book.index_of(customer) {
+        return Ok((idx, book.addresses[&idx].clone()));
+    }
+
+    let idx = book
+        .addresses
+        .keys()
+        .next_back()
+        .map(|i| 1 + i)
+        .unwrap_or(0);
+    let phrase = get_mnemonic().c(d!())?;
+    let kp = derive_keypair(&phrase, book.account, idx).c(d!())?;
+    let addr = DepositAddress {
+        customer: customer.to_owned(),
+        address: wallet::public_key_to_bech32(kp.get_pk_ref()),
+    };
+    book.addresses.insert(idx, addr.clone());
+    save(&book).c(d!())?;
+
+    Ok((idx, addr))
+}
+
+/// Credit the unspent outputs of the deposit addresses not credited yet,
+/// the new credits are returned.
+pub fn scan() -> Result<Vec<Credit>> {
+    let mut book = load().c(d!())?;
+    if book.addresses.is_empty() {
+        return Ok(vec![]);
+    }
+    let phrase = get_mnemonic().c(d!())?;
+
+    let mut new_credits = vec![];
+    for (idx, addr) in book.addresses.iter() {
+        let kp = derive_keypair(&phrase, book.account, *idx).c(d!())?;
+        // a changed mnemonic would credit the outputs of others
+        if wallet::public_key_to_bech32(kp.get_pk_ref()) != addr.address {
+            return Err(eg!(format!(
+                "the mnemonic does not derive the address of index {}",
+                idx
+            )));
+        }
+
+        let mut utxos = utils::get_owned_utxos(kp.get_pk_ref())
+            .c(d!())?
+            .into_iter()
+            .filter(|(sid, _)| !book.credits.contains_key(sid))
+            .collect::<Vec<_>>();
+        utxos.sort_by_key(|(sid, _)| sid.0);
+
+        for (sid, (utxo, owner_memo)) in utxos {
+            let oar =
+                open_blind_asset_record(&utxo.0.record, &owner_memo, &kp).c(d!())?;
+            new_credits.push(Credit {
+                txo_sid: sid,
+                index: *idx,
+                customer: addr.customer.clone(),
+                asset: AssetTypeCode {
+                    val: oar.asset_type,
+                }
+                .to_base64(),
+                amount: oar.amount,
+            });
+        }
+    }
+
+    for c in new_credits.iter() {
+        book.credits.insert(c.txo_sid, c.clone());
+    }
+    save(&book).c(d!())?;
+
+    Ok(new_credits)
+}
+
+/// Print the deposit addresses with their credited amounts.
+pub fn show() -> Result<()> {
+    let book = load().c(d!())?;
+    for (idx, addr) in book.addresses.iter() {
+        let mut totals: BTreeMap<&str, u64> = BTreeMap::new();
+        book.credits
+            .values()
+            .filter(|c| c.index == *idx)
+            .for_each(|c| {
+                let t = totals.entry(c.asset.as_str()).or_insert(0);
+                *t = t.saturating_add(c.amount);
+            });
+        println!(
+            "{} {}: {} (m/44'/{}'/{}'/0/{}){}",
+            idx,
+            addr.customer,
+            addr.address,
+            FRA,
+            book.account,
+            idx,
+            totals
+                .iter()
+                .map(|(asset, am)| format!(", {} {}", am, asset))
+                .collect::<String>()
+        );
+    }
+    Ok(())
+}
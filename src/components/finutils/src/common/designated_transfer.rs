@@ -0,0 +1,147 @@
+//!
+//! Two-step transfers, claimed by the recipient within a number of blocks.
+//!
+//! The sender locks assets with `send`, the recipient gets them with `claim`,
+//! and the sender may `reclaim` them if they are not claimed in time,
+//! e.g. sent to a mistyped address.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    ledger::{
+        converter::designated_transfer::DesignatedTransfer,
+        data_model::{AssetTypeCode, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    zei::xfr::{asset_record::AssetRecordType, sig::XfrPublicKey},
+};
+
+/// The response of `designated_transfer`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DesignatedStatus {
+    #[allow(missing_docs)]
+    pub transfer: DesignatedTransfer,
+    /// The last committed height
+    pub height: u64,
+    /// Whether the recipient can claim it in the next block,
+    /// otherwise the sender can reclaim it
+    pub claimable: bool,
+}
+
+/// Lock `amount` of the asset for `recipient`, who must claim it
+/// within `claim_period` blocks, the identifier of the transfer is printed.
+pub fn send(
+    recipient: XfrPublicKey,
+    amount: u64,
+    claim_period: u64,
+    code: Option<&str>,
+) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let code = code
+        .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+        .transpose()?
+        .unwrap_or(AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        });
+
+    let transfer_op = utils::gen_transfer_op(
+        &kp,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
+        Some(code),
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(transfer_op);
+    let id = builder.add_operation_send_designated(
+        &kp,
+        recipient,
+        code,
+        amount,
+        claim_period,
+    );
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())?;
+    println!("\x1b[31;01mDesignated transfer:\x1b[00m {}", id);
+    Ok(())
+}
+
+/// Claim the designated transfer `id` as its recipient.
+pub fn claim(id: &str) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let status = get_designated(id).c(d!())?;
+    if !status.claimable {
+        return Err(eg!(format!(
+            "the claim period ended at height {}",
+            status.transfer.expires_at
+        )));
+    }
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_claim_designated(
+        &kp,
+        id.to_owned(),
+        status.transfer.asset,
+        status.transfer.amount,
+    );
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Reclaim the designated transfer `id` as its sender, once the claim period is over.
+pub fn reclaim(id: &str) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let status = get_designated(id).c(d!())?;
+    if status.claimable {
+        return Err(eg!(format!(
+            "the claim period ends at height {}",
+            status.transfer.expires_at
+        )));
+    }
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_reclaim_designated(
+        &kp,
+        id.to_owned(),
+        status.transfer.asset,
+        status.transfer.amount,
+    );
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Query a pending designated transfer from the ledger.
+pub fn get_designated(id: &str) -> Result<DesignatedStatus> {
+    let url = format!(
+        "{}:8668/designated_transfer/{}",
+        get_serv_addr().c(d!())?,
+        id
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
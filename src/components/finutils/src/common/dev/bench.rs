@@ -0,0 +1,414 @@
+//!
+//! Load generator for a running env,
+//! measures the transaction throughput and the latency of the query APIs.
+//!
+//! NOTE: the env should have been initialized,
+//! all sender accounts are funded by the bank account.
+//!
+
+use super::{
+    init::{gen_8668_endpoint, new_tx_builder, send_tx},
+    Env, BANK_ACCOUNT_SECKEY,
+};
+use crate::{
+    common::utils::gen_transfer_op_xx,
+    txn_builder::{TransactionBuilder, TransferOperationBuilder},
+};
+use globutils::wallet;
+use ledger::data_model::{
+    Transaction, TransferType, TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
+    TX_FEE_MIN,
+};
+use ruc::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, thread,
+    time::{Duration, Instant},
+};
+use zei::xfr::{
+    asset_record::{open_blind_asset_record, AssetRecordType},
+    sig::{XfrKeyPair, XfrPublicKey, XfrSecretKey},
+    structs::{AssetRecordTemplate, OwnerMemo},
+};
+
+// max number of outputs in one funding transaction
+const FUNDING_BATCH: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct BenchCfg {
+    // number of sender accounts
+    pub accounts: usize,
+
+    // number of transactions to submit
+    pub txns: usize,
+
+    // number of requests sent to each query API
+    pub queries: usize,
+
+    // also write the JSON report to this file
+    pub out: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BenchReport {
+    env_name: String,
+    accounts: usize,
+    submitted: usize,
+    committed: usize,
+    rejected: usize,
+    // accepted by the submission API per second
+    submit_tps: f64,
+    // committed per second, from the first submission to the last commit
+    commit_tps: f64,
+    // query API => latency
+    queries: BTreeMap<String, Latency>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct Latency {
+    samples: usize,
+    min_ms: f64,
+    avg_ms: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+impl Latency {
+    // `None` if there are no samples
+    pub(super) fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let len = samples.len();
+        Some(Latency {
+            samples: len,
+            min_ms: samples[0],
+            avg_ms: samples.iter().sum::<f64>() / len as f64,
+            p50_ms: samples[len / 2],
+            p90_ms: samples[(len * 9 / 10).min(len - 1)],
+            p99_ms: samples[(len * 99 / 100).min(len - 1)],
+            max_ms: samples[len - 1],
+        })
+    }
+}
+
+pub(super) enum Status {
+    Committed,
+    Rejected,
+    Pending,
+}
+
+impl Env {
+    pub(super) fn bench(&self, cfg: &BenchCfg) -> Result<()> {
+        if 0 == cfg.accounts || 0 == cfg.txns {
+            return Err(eg!("no accounts or transactions"));
+        }
+
+        let accounts = (0..cfg.accounts)
+            .map(|_| XfrKeyPair::generate(&mut rand::thread_rng()))
+            .collect::<Vec<_>>();
+
+        println!(
+            "[ {} ] >>> Fund {} accounts ...",
+            &self.name,
+            accounts.len()
+        );
+        self.bench_fund(&accounts, cfg.txns).c(d!())?;
+
+        println!("[ {} ] >>> Build {} transactions ...", &self.name, cfg.txns);
+        let txns = self.bench_txns(&accounts, cfg.txns).c(d!())?;
+        let handles = txns.iter().map(|tx| tx.handle()).collect::<Vec<_>>();
+
+        println!("[ {} ] >>> Submit transactions ...", &self.name);
+        let started = Instant::now();
+        let mut submitted = 0;
+        for tx in txns.iter() {
+            // rejected ones are counted by their status
+            if send_tx(self, tx).is_ok() {
+                submitted += 1;
+            }
+        }
+        let submit_secs = started.elapsed().as_secs_f64();
+
+        println!("[ {} ] >>> Wait for commits ...", &self.name);
+        let timeout = Duration::from_secs(20 * self.block_itv_secs as u64);
+        let (committed, rejected) = self
+            .bench_wait(&handles, Instant::now() + timeout)
+            .c(d!())?;
+        let commit_secs = started.elapsed().as_secs_f64();
+
+        println!("[ {} ] >>> Measure query latency ...", &self.name);
+        let queries = self
+            .bench_queries(accounts[0].get_pk_ref(), cfg.queries)
+            .c(d!())?;
+
+        let report = BenchReport {
+            env_name: self.name.clone(),
+            accounts: accounts.len(),
+            submitted,
+            committed,
+            rejected,
+            submit_tps: submitted as f64 / submit_secs,
+            commit_tps: committed as f64 / commit_secs,
+            queries,
+        };
+        let report = serde_json::to_string_pretty(&report).c(d!())?;
+        println!("{}", &report);
+
+        if let Some(path) = cfg.out.as_ref() {
+            fs::write(path, &report).c(d!(path))?;
+        }
+
+        Ok(())
+    }
+
+    // every account gets enough outputs to send `txns` transactions in total,
+    // one output is spent by one transaction
+    fn bench_fund(&self, accounts: &[XfrKeyPair], txns: usize) -> Result<()> {
+        let targets = (0..txns)
+            .map(|i| (accounts[i % accounts.len()].get_pk_ref(), 2 * TX_FEE_MIN))
+            .collect::<Vec<_>>();
+        self.bench_fund_x(&targets).c(d!())
+    }
+
+    // one output of FRA for each target, sent by the bank account
+    pub(super) fn bench_fund_x(&self, targets: &[(&XfrPublicKey, u64)]) -> Result<()> {
+        let bank_kp = serde_json::from_str::<XfrSecretKey>(&format!(
+            "\"{}\"",
+            BANK_ACCOUNT_SECKEY
+        ))
+        .c(d!())?
+        .into_keypair();
+        let endpoint = gen_8668_endpoint(self).c(d!())?;
+
+        // the bank account spends its own change in every batch,
+        // so wait for each batch to be committed
+        for batch in targets.chunks(FUNDING_BATCH) {
+            let mut builder = new_tx_builder(self).c(d!())?;
+            let op = gen_transfer_op_xx(
+                Some(&endpoint),
+                &bank_kp,
+                batch.to_vec(),
+                None,
+                true,
+                false,
+                false,
+                None,
+                None,
+                None,
+            )
+            .c(d!())?;
+            builder.add_operation(op);
+            let mut tx = builder.take_transaction();
+            tx.sign(&bank_kp);
+            send_tx(self, &tx).c(d!())?;
+
+            let timeout = Duration::from_secs(10 * self.block_itv_secs as u64);
+            let (committed, _) = self
+                .bench_wait(&[tx.handle()], Instant::now() + timeout)
+                .c(d!())?;
+            if 0 == committed {
+                return Err(eg!("funding transaction not committed"));
+            }
+        }
+
+        Ok(())
+    }
+
+    // one input, a fee output and a transfer to a random account
+    fn bench_txns(
+        &self,
+        accounts: &[XfrKeyPair],
+        txns: usize,
+    ) -> Result<Vec<Transaction>> {
+        let seq_id = new_tx_builder(self)
+            .c(d!())?
+            .transaction()
+            .body
+            .no_replay_token
+            .get_seq_id();
+
+        let mut res = vec![];
+        for kp in accounts.iter() {
+            let utxos = self.bench_owned_utxos(kp.get_pk_ref()).c(d!())?;
+            for (sid, (utxo, owner_memo)) in utxos {
+                let oar =
+                    open_blind_asset_record(&utxo.0.record, &owner_memo, kp).c(d!())?;
+                let receiver =
+                    accounts[rand::random::<usize>() % accounts.len()].get_pk();
+                let amount = oar.amount;
+
+                let op = TransferOperationBuilder::new()
+                    .add_input(TxoRef::Absolute(sid), oar, None, None, amount)
+                    .c(d!())?
+                    .add_output(
+                        &fra_template(TX_FEE_MIN, *BLACK_HOLE_PUBKEY),
+                        None,
+                        None,
+                        None,
+                    )
+                    .c(d!())?
+                    .add_output(
+                        &fra_template(amount - TX_FEE_MIN, receiver),
+                        None,
+                        None,
+                        None,
+                    )
+                    .c(d!())?
+                    .create(TransferType::Standard)
+                    .c(d!())?
+                    .sign(kp)
+                    .c(d!())?
+                    .transaction()
+                    .c(d!())?;
+
+                let mut builder = TransactionBuilder::from_seq_id(seq_id);
+                builder.add_operation(op);
+                let mut tx = builder.take_transaction();
+                tx.sign(kp);
+                res.push(tx);
+            }
+        }
+
+        res.truncate(txns);
+        Ok(res)
+    }
+
+    // returns the number of (committed, rejected) transactions,
+    // the remaining ones are still pending at the deadline
+    fn bench_wait(
+        &self,
+        handles: &[String],
+        deadline: Instant,
+    ) -> Result<(usize, usize)> {
+        let (mut committed, mut rejected) = (0, 0);
+        let mut pending = handles.iter().collect::<Vec<_>>();
+
+        while !pending.is_empty() && Instant::now() < deadline {
+            let mut left = vec![];
+            for h in pending {
+                match self.bench_txn_status(h).c(d!())? {
+                    Status::Committed => committed += 1,
+                    Status::Rejected => rejected += 1,
+                    Status::Pending => left.push(h),
+                }
+            }
+            pending = left;
+            alt!(
+                !pending.is_empty(),
+                thread::sleep(Duration::from_millis(200))
+            );
+        }
+
+        Ok((committed, rejected))
+    }
+
+    pub(super) fn bench_txn_status(&self, handle: &str) -> Result<Status> {
+        let n = self.nodes.values().next().c(d!())?;
+        let url = format!(
+            "http://{}:{}/txn_status/{}",
+            n.ip(&self.host_ip),
+            n.ports.app_8669,
+            handle
+        );
+        let resp = attohttpc::get(&url)
+            .send()
+            .c(d!(url))?
+            .error_for_status()
+            .c(d!())?
+            .text()
+            .c(d!())?;
+
+        // unknown handles are reported in plain text,
+        // the transaction may not have been delivered yet
+        let status = match serde_json::from_str::<Value>(&resp) {
+            Ok(v) if v.get("Committed").is_some() => Status::Committed,
+            Ok(v) if v.get("Rejected").is_some() => Status::Rejected,
+            _ => Status::Pending,
+        };
+        Ok(status)
+    }
+
+    pub(super) fn bench_owned_utxos(
+        &self,
+        pk: &XfrPublicKey,
+    ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
+        let url = format!(
+            "{}/owned_utxos/{}",
+            gen_8668_endpoint(self).c(d!())?,
+            wallet::public_key_to_base64(pk)
+        );
+        attohttpc::get(&url)
+            .send()
+            .c(d!(url))?
+            .error_for_status()
+            .c(d!())?
+            .bytes()
+            .c(d!())
+            .and_then(|b| serde_json::from_slice(&b).c(d!()))
+    }
+
+    fn bench_queries(
+        &self,
+        owner: &XfrPublicKey,
+        n: usize,
+    ) -> Result<BTreeMap<String, Latency>> {
+        let node = self.nodes.values().next().c(d!())?;
+        let ledger = gen_8668_endpoint(self).c(d!())?;
+        let pk = wallet::public_key_to_base64(owner);
+
+        let mut routes = vec![
+            ("global_state", format!("{}/global_state", &ledger)),
+            ("owned_utxos", format!("{}/owned_utxos/{}", &ledger, &pk)),
+            (
+                "tm_status",
+                format!(
+                    "http://{}:{}/status",
+                    node.ip(&self.host_ip),
+                    node.ports.tm_rpc
+                ),
+            ),
+        ];
+        // authenticated utxos, along with their merkle proofs
+        if let Some(sid) = self.bench_owned_utxos(owner).c(d!())?.keys().next() {
+            routes.push(("utxo_sid", format!("{}/utxo_sid/{}", &ledger, sid.0)));
+        }
+
+        routes
+            .into_iter()
+            .map(|(name, url)| latency(&url, n).map(|l| (name.to_owned(), l)))
+            .collect()
+    }
+}
+
+pub(super) fn fra_template(amount: u64, pk: XfrPublicKey) -> AssetRecordTemplate {
+    AssetRecordTemplate::with_no_asset_tracing(
+        amount,
+        ASSET_TYPE_FRA,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        pk,
+    )
+}
+
+fn latency(url: &str, n: usize) -> Result<Latency> {
+    let samples = (0..n.max(1))
+        .map(|_| {
+            let t = Instant::now();
+            attohttpc::get(url)
+                .send()
+                .c(d!(url))?
+                .error_for_status()
+                .c(d!())?
+                .bytes()
+                .c(d!())?;
+            Ok(t.elapsed().as_secs_f64() * 1000.0)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Latency::from_samples(samples).c(d!())
+}
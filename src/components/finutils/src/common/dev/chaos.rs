@@ -0,0 +1,125 @@
+//!
+//! Fault injections for a running env,
+//! used to exercise the consensus and resync logic locally.
+//!
+//! NOTE: latency injection depends on `tc`,
+//! so root privileges are needed.
+//!
+
+use super::Env;
+use ruc::{cmd, *};
+use std::{thread, time::Duration};
+
+#[derive(Debug, Clone, Copy)]
+pub enum ChaosOps {
+    // kill a random node, and restart it after some seconds
+    KillRestart { times: u32, down_secs: u64 },
+    // add latency(in milliseconds) to all local traffics
+    Latency(u32),
+    // split nodes into two isolated groups
+    Partition,
+    // remove all network injections
+    Heal,
+    // pause block production
+    Pause,
+    // resume block production
+    Resume,
+}
+
+impl Env {
+    pub(super) fn chaos(&mut self, ops: ChaosOps) -> Result<()> {
+        match ops {
+            ChaosOps::KillRestart { times, down_secs } => {
+                self.chaos_kill_restart(times, down_secs).c(d!())
+            }
+            ChaosOps::Latency(ms) => self.chaos_latency(ms).c(d!()),
+            ChaosOps::Partition => self.chaos_partition().c(d!()),
+            ChaosOps::Heal => self.chaos_heal().c(d!()),
+            ChaosOps::Pause => self.chaos_signal("STOP").c(d!()),
+            ChaosOps::Resume => self.chaos_signal("CONT").c(d!()),
+        }
+    }
+
+    fn chaos_kill_restart(&mut self, times: u32, down_secs: u64) -> Result<()> {
+        let ids = self.nodes.keys().copied().collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Err(eg!("no nodes in this env"));
+        }
+
+        for _ in 0..times {
+            let id = ids[rand::random::<usize>() % ids.len()];
+            println!("[ {} ] >>> Kill node {}", &self.name, id);
+            self.nodes.get(&id).c(d!())?.stop().c(d!())?;
+
+            thread::sleep(Duration::from_secs(down_secs));
+
+            println!("[ {} ] >>> Restart node {}", &self.name, id);
+            self.start(Some(id)).c(d!())?;
+        }
+
+        Ok(())
+    }
+
+    // `tc` can not distinguish different ports on the loopback device easily,
+    // so the latency is applied to all local traffics
+    fn chaos_latency(&self, ms: u32) -> Result<()> {
+        let cmd = format!(
+            "tc qdisc replace dev lo root netem delay {}ms {}ms",
+            ms,
+            ms / 10
+        );
+        cmd::exec_output(&cmd)
+            .c(d!())
+            .map(|_| println!("[ {} ] >>> Latency injected: {}ms", &self.name, ms))
+    }
+
+    // split nodes into two groups, seed nodes stay in the first group,
+    // nodes can only connect to the peers of the same group
+    // and peer exchanging is disabled during the partition
+    fn chaos_partition(&mut self) -> Result<()> {
+        let mut a = self.nodes.keys().copied().collect::<Vec<_>>();
+        if 2 > a.len() {
+            return Err(eg!("at least 2 nodes are needed"));
+        }
+
+        let b = a.split_off(a.len() / 2);
+        a.extend(self.seeds.keys().copied());
+
+        println!("[ {} ] >>> Partition: {:?} <-x-> {:?}", &self.name, &a, &b);
+
+        self.partition = vec![a.into_iter().collect(), b.into_iter().collect()];
+        self.start(None).c(d!())
+    }
+
+    fn chaos_heal(&mut self) -> Result<()> {
+        omit!(cmd::exec_output("tc qdisc del dev lo root"));
+        if !self.partition.is_empty() {
+            self.partition.clear();
+            self.start(None).c(d!())?;
+        }
+        println!("[ {} ] >>> Network healed", &self.name);
+        Ok(())
+    }
+
+    // send a signal to the tendermint processes of all validators
+    fn chaos_signal(&self, sig: &str) -> Result<()> {
+        for n in self.nodes.values() {
+            let cmd = format!(
+                "for i in \
+                    $(ps ax -o pid,args \
+                        | grep ' node --home {} ' \
+                        | grep -v 'grep' \
+                        | grep -Eo '^ *[0-9]+' \
+                        | sed 's/ //g' \
+                    ); \
+                 do kill -{} $i; done",
+                &n.home, sig
+            );
+            let outputs = n.exec(&cmd).c(d!())?;
+            n.write_fn_log(&format!("{}\n{}", &cmd, outputs.as_str()))
+                .c(d!())?;
+        }
+        println!("[ {} ] >>> Signal sent: SIG{}", &self.name, sig);
+        Ok(())
+    }
+}
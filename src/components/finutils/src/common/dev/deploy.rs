@@ -0,0 +1,246 @@
+//!
+//! Generate deployment manifests(docker-compose/k8s) for an existing env,
+//! so the same topology can be deployed on remote infrastructures.
+//!
+
+use super::{Env, Kind, Node, NodeId};
+use ruc::*;
+use std::{fmt::Write as _, fs, str::FromStr};
+
+// the image used by generated manifests,
+// it should contain both the `tendermint` and the `abcid` binaries
+const DEPLOY_IMAGE_DEFAULT: &str = "findoranetwork/findorad:latest";
+
+// the mount point of node data inside containers
+const DATA_DIR: &str = "/data";
+
+#[derive(Debug, Clone, Copy)]
+pub enum ManifestKind {
+    DockerCompose,
+    K8s,
+}
+
+impl ManifestKind {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Self::DockerCompose => "docker-compose.yml",
+            Self::K8s => "k8s.yml",
+        }
+    }
+}
+
+impl FromStr for ManifestKind {
+    type Err = Box<dyn RucError>;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "docker-compose" | "compose" => Ok(Self::DockerCompose),
+            "k8s" | "kubernetes" => Ok(Self::K8s),
+            _ => Err(eg!("Invalid manifest kind, should be 'docker-compose' or 'k8s'")),
+        }
+    }
+}
+
+impl Env {
+    // write manifests into `${ENV_HOME}/deploy/`,
+    // return the path of the generated manifest
+    pub(super) fn gen_manifest(
+        &self,
+        kind: ManifestKind,
+        image: Option<&str>,
+    ) -> Result<String> {
+        let image = image.unwrap_or(DEPLOY_IMAGE_DEFAULT);
+        let dir = format!("{}/deploy", &self.home);
+        fs::create_dir_all(&dir).c(d!())?;
+
+        // the checkpoint file will be mounted along with the data of each node
+        if let Some(checkpoint) = self.checkpoint_file.as_deref() {
+            for n in self.seeds.values().chain(self.nodes.values()) {
+                fs::copy(checkpoint, format!("{}/checkpoint.toml", &n.home)).c(d!())?;
+            }
+        }
+
+        let contents = match kind {
+            ManifestKind::DockerCompose => {
+                fs::write(format!("{}/genesis.json", &dir), &self.genesis).c(d!())?;
+                self.render_compose(image)
+            }
+            ManifestKind::K8s => self.render_k8s(image),
+        };
+
+        let path = format!("{}/{}", &dir, kind.file_name());
+        fs::write(&path, contents).c(d!()).map(|_| path)
+    }
+
+    fn render_compose(&self, image: &str) -> String {
+        let mut res = format!(
+            "# generated by `fn dev`, env: {}\nversion: \"3.8\"\n\nservices:\n",
+            &self.name
+        );
+
+        for n in self.seeds.values().chain(self.nodes.values()) {
+            write!(
+                res,
+                "  {name}:\n    image: {image}\n    container_name: {env}-{name}\n    \
+                hostname: {name}\n    entrypoint: [\"bash\", \"-c\"]\n    \
+                command:\n      - >-\n        {cmd}\n    ports:\n",
+                name = service_name(n.id),
+                image = image,
+                env = self.name.to_lowercase(),
+                cmd = self.container_cmd(n),
+            )
+            .unwrap();
+            for p in exposed_ports(n) {
+                writeln!(res, "      - \"{0}:{0}\"", p).unwrap();
+            }
+            write!(
+                res,
+                "    volumes:\n      - {home}:{data}\n      \
+                - ./genesis.json:{data}/config/genesis.json:ro\n",
+                home = &n.home,
+                data = DATA_DIR,
+            )
+            .unwrap();
+            res.push_str("    restart: unless-stopped\n\n");
+        }
+
+        res
+    }
+
+    fn render_k8s(&self, image: &str) -> String {
+        let ns = format!("findora-{}", self.name.to_lowercase());
+
+        let mut res = format!(
+            "# generated by `fn dev`, env: {0}\n\
+            apiVersion: v1\nkind: Namespace\nmetadata:\n  name: {1}\n---\n\
+            apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: genesis\n  namespace: {1}\n\
+            data:\n  genesis.json: |\n    {2}\n",
+            &self.name, &ns, &self.genesis
+        );
+
+        for n in self.seeds.values().chain(self.nodes.values()) {
+            let name = service_name(n.id);
+
+            write!(
+                res,
+                "---\napiVersion: v1\nkind: Service\nmetadata:\n  name: {0}\n  \
+                namespace: {1}\nspec:\n  selector:\n    app: {0}\n  ports:\n",
+                &name, &ns
+            )
+            .unwrap();
+            for p in exposed_ports(n) {
+                writeln!(res, "    - name: port-{0}\n      port: {0}", p).unwrap();
+            }
+
+            write!(
+                res,
+                "---\napiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: {name}\n  \
+                namespace: {ns}\nspec:\n  replicas: 1\n  strategy:\n    type: Recreate\n  \
+                selector:\n    matchLabels:\n      app: {name}\n  template:\n    \
+                metadata:\n      labels:\n        app: {name}\n        kind: {kind}\n    \
+                spec:\n      containers:\n        - name: {name}\n          \
+                image: {image}\n          command: [\"bash\", \"-c\"]\n          \
+                args:\n            - >-\n              {cmd}\n          \
+                volumeMounts:\n            - name: data\n              \
+                mountPath: {data}\n            - name: genesis\n              \
+                mountPath: {data}/config/genesis.json\n              \
+                subPath: genesis.json\n      volumes:\n        - name: data\n          \
+                hostPath:\n            path: {home}\n            type: Directory\n        \
+                - name: genesis\n          configMap:\n            name: genesis\n",
+                name = &name,
+                ns = &ns,
+                kind = alt!(matches!(n.kind, Kind::Seed), "seed", "node"),
+                image = image,
+                cmd = self.container_cmd(n),
+                data = DATA_DIR,
+                home = &n.home,
+            )
+            .unwrap();
+        }
+
+        res
+    }
+
+    // the starting command of a node inside its container,
+    // peers are addressed by their service names instead of `host_ip`
+    fn container_cmd(&self, n: &Node) -> String {
+        let peer_list = |nodes: &mut dyn Iterator<Item = &Node>| {
+            nodes
+                .map(|p| {
+                    format!("{}@{}:{}", &p.tm_id, service_name(p.id), p.ports.tm_p2p)
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let seeds = peer_list(&mut self.seeds.values());
+        let peers = peer_list(&mut self.nodes.values().filter(|p| p.id != n.id));
+
+        let mut cmd = format!(
+            "{tm} node --home {data} \
+            --proxy_app tcp://127.0.0.1:{abci} \
+            --rpc.laddr tcp://0.0.0.0:{rpc} \
+            --p2p.laddr tcp://0.0.0.0:{p2p} \
+            --p2p.seeds '{seeds}' \
+            --p2p.persistent_peers '{peers}' {tm_flags} & \
+            EVM_CHAIN_ID={chain_id} FINDORA_BLOCK_ITV={itv} exec {abcid} \
+            --enable-query-service \
+            --enable-eth-api-service \
+            --tendermint-host 0.0.0.0 \
+            --tendermint-port {rpc} \
+            --abcid-port {abci} \
+            --submission-service-port {p8669} \
+            --ledger-service-port {p8668} \
+            --evm-http-port {web3_http} \
+            --evm-ws-port {web3_ws} \
+            --ledger-dir {data}/__findora__ \
+            --tendermint-node-key-config-path {data}/config/priv_validator_key.json",
+            tm = file_name(&self.tendermint_bin),
+            abcid = file_name(n.abcid_bin.as_deref().unwrap_or(&self.abcid_bin)),
+            data = DATA_DIR,
+            abci = n.ports.app_abci,
+            rpc = n.ports.tm_rpc,
+            p2p = n.ports.tm_p2p,
+            p8669 = n.ports.app_8669,
+            p8668 = n.ports.app_8668,
+            web3_http = n.ports.web3_http,
+            web3_ws = n.ports.web3_ws,
+            seeds = seeds,
+            peers = alt!(matches!(n.kind, Kind::Seed), String::new(), peers),
+            tm_flags = self.tendermint_extra_flags.as_deref().unwrap_or_default(),
+            chain_id = self.evm_chain_id,
+            itv = self.block_itv_secs,
+        );
+
+        if self.checkpoint_file.is_some() {
+            write!(cmd, " --checkpoint-file {}/checkpoint.toml", DATA_DIR).unwrap();
+        }
+        if let Some(flags) = self.abcid_extra_flags.as_deref() {
+            write!(cmd, " {}", flags).unwrap();
+        }
+
+        cmd
+    }
+}
+
+fn service_name(id: NodeId) -> String {
+    format!("node-{}", id)
+}
+
+// the binaries are expected to be in the `$PATH` of the image,
+// so only the file names of custom binaries are kept
+fn file_name(bin: &str) -> &str {
+    bin.rsplit('/').next().unwrap_or(bin)
+}
+
+// tendermint p2p/rpc and all web services of abcid,
+// the inner abci port is not exposed
+fn exposed_ports(n: &Node) -> [u16; 6] {
+    [
+        n.ports.tm_p2p,
+        n.ports.tm_rpc,
+        n.ports.app_8669,
+        n.ports.app_8668,
+        n.ports.web3_http,
+        n.ports.web3_ws,
+    ]
+}
@@ -0,0 +1,83 @@
+//!
+//! Mainnet-fork mode: bootstrap an env from the ledger snapshot of an existing network.
+//!
+//! - the snapshot is a copy of the `--ledger-dir` of an existing node,
+//!   either a local directory or a URL of a `.tar.gz` package
+//! - the ledger states are kept, but the tendermint chain is a new one,
+//!   so blocks will be produced from height 1 by local validators
+//! - the validator set of the snapshot is cleared, the local one will be set by `fn dev init`
+//!
+
+use super::Env;
+use ledger::staking::ValidatorData;
+use ruc::{cmd, *};
+use serde_json::Value;
+use std::fs;
+
+// the snapshot file of `LedgerStatus`
+const LEDGER_STATUS: &str = "ledger_status";
+
+// same as the `--ledger-dir` of abcid started by `fn dev`
+const LEDGER_DIR_NAME: &str = "__findora__";
+
+impl Env {
+    // prepare the ledger data of all nodes,
+    // must be called before the first starting of nodes
+    pub(super) fn fork_from(&self, snapshot: &str) -> Result<()> {
+        let src = self.fetch_snapshot(snapshot).c(d!())?;
+        println!("[ {} ] >>> Fork from: {}", &self.name, &src);
+
+        for n in self.seeds.values().chain(self.nodes.values()) {
+            let dst = format!("{}/{}", &n.home, LEDGER_DIR_NAME);
+            omit!(fs::remove_dir_all(&dst));
+            cmd::exec_output(&format!("cp -r {} {}", &src, &dst)).c(d!())?;
+            rewrite_ledger_status(&format!("{}/{}", dst, LEDGER_STATUS)).c(d!())?;
+        }
+
+        Ok(())
+    }
+
+    // return the path of the ledger dir in the snapshot
+    fn fetch_snapshot(&self, snapshot: &str) -> Result<String> {
+        let dir = if snapshot.starts_with("http://") || snapshot.starts_with("https://")
+        {
+            let dir = format!("{}/fork_snapshot", &self.home);
+            let pkg = format!("{}/fork_snapshot.tar.gz", &self.home);
+            fs::create_dir_all(&dir).c(d!())?;
+
+            println!("[ {} ] >>> Download snapshot ...", &self.name);
+            cmd::exec_output(&format!(
+                "curl -fL -o {0} '{1}' && tar -xpf {0} -C {2} && rm -f {0}",
+                &pkg, snapshot, &dir
+            ))
+            .c(d!())?;
+            dir
+        } else {
+            snapshot.trim_end_matches('/').to_owned()
+        };
+
+        [dir.clone(), format!("{}/{}", &dir, LEDGER_DIR_NAME)]
+            .into_iter()
+            .find(|d| fs::metadata(format!("{}/{}", d, LEDGER_STATUS)).is_ok())
+            .c(d!("`{}` not found in the snapshot", LEDGER_STATUS))
+    }
+}
+
+// - reset the tendermint height, so a new chain can be started
+// - clear the validator set, it will be replaced by the local one
+fn rewrite_ledger_status(path: &str) -> Result<()> {
+    let mut status = fs::read(path)
+        .c(d!())
+        .and_then(|c| serde_json::from_slice::<Value>(&c).c(d!()))?;
+
+    status["td_commit_height"] = Value::from(0u64);
+
+    let staking = status.get_mut("staking").c(d!("invalid ledger status"))?;
+    staking["cur_height"] = Value::from(0u64);
+    staking["vi"] =
+        serde_json::to_value(map! {B 0u64 => ValidatorData::default()}).c(d!())?;
+
+    serde_json::to_vec(&status)
+        .c(d!())
+        .and_then(|c| fs::write(path, c).c(d!()))
+}
@@ -1,22 +1,26 @@
 use super::{Env, InitialValidator, StakingValidator, BANK_ACCOUNT_SECKEY, FRA};
 use crate::{
     common::{self, utils::gen_transfer_op_xx},
+    genesis::{Genesis, GenesisAsset},
     txn_builder::TransactionBuilder,
 };
-use globutils::{HashOf, SignatureOf};
-use ledger::{
-    data_model::{
-        AssetTypeCode, StateCommitmentData, Transaction, BLACK_HOLE_PUBKEY_STAKING,
-    },
-    store::utils::fra_gen_initial_tx,
+use config::abci::CheckPointConfig;
+use globutils::{wallet, HashOf, SignatureOf};
+use ledger::data_model::{
+    AssetRules, AssetTypeCode, StateCommitmentData, Transaction, ASSET_TYPE_FRA,
+    BLACK_HOLE_PUBKEY_STAKING,
 };
 use ruc::*;
 use serde::Deserialize;
+use std::{collections::BTreeMap, fs};
 use zei::xfr::{
     asset_record::AssetRecordType,
-    sig::{XfrKeyPair, XfrPublicKey, XfrSecretKey},
+    sig::{XfrKeyPair, XfrSecretKey},
 };
 
+// the amount delegated by the bank account to each initial validator, in FRA
+const BANK_DELEGATION: u64 = 1_0000;
+
 #[derive(Deserialize)]
 struct TmValidators {
     result: TmValidatorsRet,
@@ -39,11 +43,13 @@ struct TmPubKey {
 }
 
 pub fn init(env: &mut Env) -> Result<()> {
-    let tmrpc = env.nodes.values().next().c(d!())?.ports.tm_rpc;
+    let n = env.nodes.values().next().c(d!())?;
     let page_size = env.initial_validator_num;
     let tmrpc_endpoint = format!(
         "http://{}:{}/validators?per_page={}",
-        &env.host_ip, tmrpc, page_size
+        n.ip(&env.host_ip),
+        n.ports.tm_rpc,
+        page_size
     );
 
     let tm_validators = attohttpc::get(&tmrpc_endpoint)
@@ -67,7 +73,26 @@ pub fn init(env: &mut Env) -> Result<()> {
         env.initial_validators.push(iv);
     });
 
-    setup_initial_validators(env).c(d!())?;
+    let root_kp =
+        serde_json::from_str::<XfrSecretKey>(&format!("\"{}\"", BANK_ACCOUNT_SECKEY))
+            .c(d!())?
+            .into_keypair();
+    let genesis = gen_genesis(env, &root_kp).c(d!())?;
+    let genesis_path = format!("{}/ledger_genesis.json", &env.home);
+    fs::write(&genesis_path, genesis.to_bytes().c(d!())?).c(d!(genesis_path))?;
+
+    println!("[ {} ] >>> Set up the initial validators ...", &env.name);
+    send_tx(env, &genesis.validator_set_tx(seq_id(env)?).c(d!())?).c(d!())?;
+
+    // FRA has been issued in the forked network,
+    // and the bank account has no balance there
+    if env.fork_from.is_some() {
+        println!(
+            "[ {} ] >>> Forked env, only the validator set is replaced",
+            &env.name
+        );
+        return Ok(());
+    }
 
     macro_rules! sleep_n_block {
         ($n_block: expr) => {{
@@ -77,80 +102,170 @@ pub fn init(env: &mut Env) -> Result<()> {
         }};
     }
 
-    let root_kp =
-        serde_json::from_str::<XfrSecretKey>(&format!("\"{}\"", BANK_ACCOUNT_SECKEY))
-            .c(d!())?
-            .into_keypair();
     println!(
         "[ {} ] >>> Block interval: {} seconds",
         &env.name, env.block_itv_secs
     );
 
-    println!("[ {} ] >>> Define and issue FRA ...", &env.name);
-    send_tx(env, &fra_gen_initial_tx(&root_kp)).c(d!())?;
-
-    println!("[ {} ] >>> Wait 2 block ...", &env.name);
-    sleep_n_block!(2);
-
-    let target_list = env
-        .initial_validators
-        .iter()
-        .map(|v| (v.xfr_keypair.get_pk_ref(), 500_0000 * FRA))
-        .collect::<Vec<_>>();
-
-    println!("[ {} ] >>> Transfer FRAs to validators ...", &env.name);
-    transfer_batch(env, &root_kp, target_list, None, true, true).c(d!())?;
+    println!(
+        "[ {} ] >>> Define and issue assets, fund the validators ...",
+        &env.name
+    );
+    send_tx(env, &genesis.assets_tx(&root_kp, seq_id(env)?).c(d!())?).c(d!())?;
 
     println!("[ {} ] >>> Wait 2 block ...", &env.name);
     sleep_n_block!(2);
 
+    // all self-delegations are applied in one transaction, or none of them
     println!("[ {} ] >>> Propose self-delegations ...", &env.name);
-    for (i, v) in env.initial_validators.iter().enumerate() {
-        let mut builder = new_tx_builder(env).c(d!())?;
-        let am = (400_0000 + i as u64 * 1_0000) * FRA;
+    let mut builder = new_tx_builder(env).c(d!())?;
+    let endpoint = gen_8668_endpoint(env).c(d!())?;
+    for (iv, v) in env.initial_validators.iter().zip(genesis.validators.iter()) {
         gen_transfer_op_xx(
-            Some(&gen_8668_endpoint(env).c(d!())?),
-            &v.xfr_keypair,
-            vec![(&BLACK_HOLE_PUBKEY_STAKING, am)],
+            Some(&endpoint),
+            &iv.xfr_keypair,
+            vec![(&BLACK_HOLE_PUBKEY_STAKING, v.td_power)],
             None,
             true,
             false,
             false,
             Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+            None,
+            None,
         )
         .c(d!())
         .map(|principal_op| {
             builder.add_operation(principal_op);
             builder.add_operation_delegation(
-                &v.xfr_keypair,
-                am,
-                v.tendermint_addr.clone(),
+                &iv.xfr_keypair,
+                v.td_power,
+                iv.tendermint_addr.clone(),
             );
         })?;
-        let mut tx = builder.take_transaction();
-        tx.sign(&v.xfr_keypair);
-        send_tx(env, &tx).c(d!())?;
     }
+    let mut tx = builder.take_transaction();
+    env.initial_validators
+        .iter()
+        .for_each(|v| tx.sign(&v.xfr_keypair));
+    send_tx(env, &tx).c(d!())?;
+
+    println!("[ {} ] >>> Wait 2 block ...", &env.name);
+    sleep_n_block!(2);
+
+    // a single principal of the bank account split across all the validators
+    println!("[ {} ] >>> Delegate to the validators ...", &env.name);
+    let targets = env
+        .initial_validators
+        .iter()
+        .map(|iv| (iv.tendermint_addr.clone(), BANK_DELEGATION * FRA))
+        .collect::<Vec<_>>();
+    let principal = BANK_DELEGATION * FRA * targets.len() as u64;
+    let mut builder = new_tx_builder(env).c(d!())?;
+    gen_transfer_op_xx(
+        Some(&endpoint),
+        &root_kp,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, principal)],
+        None,
+        true,
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+        None,
+        None,
+    )
+    .c(d!())
+    .map(|principal_op| {
+        builder.add_operation(principal_op);
+        builder.add_operation_multi_delegation(&root_kp, targets);
+    })?;
+    let mut tx = builder.take_transaction();
+    tx.sign(&root_kp);
+    send_tx(env, &tx).c(d!())?;
+
+    // record the real codes, they will be shown by `fn dev show`
+    env.economics
+        .assets
+        .iter_mut()
+        .zip(genesis.assets.iter().skip(1))
+        .for_each(|(a, ga)| a.code = Some(ga.code.to_base64()));
 
     println!("[ {} ] >>> Init work done !", &env.name);
     Ok(())
 }
 
-fn setup_initial_validators(env: &Env) -> Result<()> {
-    let mut builder = new_tx_builder(env).c(d!())?;
+// the bank account issues all assets,
+// and funds the initial validators and the extra receivers
+fn gen_genesis(env: &Env, root_kp: &XfrKeyPair) -> Result<Genesis> {
+    let mut genesis = Genesis::new(root_kp.get_pk());
+    let fra = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
 
-    env.initial_validators
-        .iter()
-        .map(|iv| StakingValidator::try_from(iv).c(d!()))
-        .collect::<Result<Vec<_>>>()
-        .and_then(|vs| builder.add_operation_update_validator(&[], 1, vs).c(d!()))?;
+    for (i, iv) in env.initial_validators.iter().enumerate() {
+        let mut v = StakingValidator::try_from(iv).c(d!())?;
+        v.td_power = env.economics.self_delegation_of(i);
+        genesis.add_validator(v);
+        genesis
+            .allocate(
+                &fra,
+                iv.xfr_keypair.get_pk(),
+                env.economics.validator_balance * FRA,
+            )
+            .c(d!())?;
+    }
+
+    for (addr, am) in env.economics.extra_balances.iter() {
+        let pk = wallet::public_key_from_bech32(addr).c(d!(addr.to_owned()))?;
+        genesis.allocate(&fra, pk, am * FRA).c(d!())?;
+    }
+
+    for a in env.economics.assets.iter() {
+        let code = match a.code.as_deref() {
+            Some(c) => AssetTypeCode::new_from_base64(c).c(d!())?,
+            None => AssetTypeCode::gen_random(),
+        };
+
+        let mut rules = AssetRules::default();
+        rules
+            .set_decimals(a.decimals)
+            .c(d!())?
+            .set_max_units(a.max_units)
+            .set_transferable(a.transferable)
+            .set_updatable(a.updatable);
+
+        genesis
+            .add_asset(GenesisAsset {
+                code,
+                memo: a.memo.clone(),
+                rules,
+                issue_amount: a.issue_amount,
+                allocations: BTreeMap::new(),
+            })
+            .c(d!())?;
+    }
+
+    if let Some(checkpoint) = env
+        .checkpoint_file
+        .as_deref()
+        .and_then(CheckPointConfig::from_file)
+    {
+        genesis.set_checkpoint(checkpoint);
+    }
 
-    send_tx(env, &builder.take_transaction()).c(d!())
+    genesis.check().c(d!()).map(|_| genesis)
 }
 
-fn send_tx(env: &Env, tx: &Transaction) -> Result<()> {
-    let port = env.nodes.values().next().c(d!())?.ports.app_8669;
-    let rpc_endpoint = format!("http://{}:{}/submit_transaction", &env.host_ip, port);
+fn seq_id(env: &Env) -> Result<u64> {
+    new_tx_builder(env).c(d!()).map(|b| b.get_seq_id())
+}
+
+pub(super) fn send_tx(env: &Env, tx: &Transaction) -> Result<()> {
+    let n = env.nodes.values().next().c(d!())?;
+    let rpc_endpoint = format!(
+        "http://{}:{}/submit_transaction",
+        n.ip(&env.host_ip),
+        n.ports.app_8669
+    );
     attohttpc::post(&rpc_endpoint)
         .header(attohttpc::header::CONTENT_TYPE, "application/json")
         .bytes(&serde_json::to_vec(tx).c(d!())?)
@@ -161,35 +276,7 @@ fn send_tx(env: &Env, tx: &Transaction) -> Result<()> {
         .map(|_| ())
 }
 
-fn transfer_batch(
-    env: &Env,
-    owner_kp: &XfrKeyPair,
-    target_list: Vec<(&XfrPublicKey, u64)>,
-    token_code: Option<AssetTypeCode>,
-    confidential_am: bool,
-    confidential_ty: bool,
-) -> Result<()> {
-    let mut builder = new_tx_builder(env).c(d!())?;
-    let op = gen_transfer_op_xx(
-        Some(&gen_8668_endpoint(env).c(d!())?),
-        owner_kp,
-        target_list,
-        token_code,
-        true,
-        confidential_am,
-        confidential_ty,
-        None,
-    )
-    .c(d!())?;
-    builder.add_operation(op);
-
-    let mut tx = builder.take_transaction();
-    tx.sign(owner_kp);
-
-    send_tx(env, &tx).c(d!())
-}
-
-fn new_tx_builder(env: &Env) -> Result<TransactionBuilder> {
+pub(super) fn new_tx_builder(env: &Env) -> Result<TransactionBuilder> {
     type Resp = (
         HashOf<Option<StateCommitmentData>>,
         u64,
@@ -210,10 +297,10 @@ fn new_tx_builder(env: &Env) -> Result<TransactionBuilder> {
         .map(TransactionBuilder::from_seq_id)
 }
 
-fn gen_8668_endpoint(env: &Env) -> Result<String> {
+pub(super) fn gen_8668_endpoint(env: &Env) -> Result<String> {
     env.nodes
         .values()
         .next()
         .c(d!())
-        .map(|n| format!("http://{}:{}", &env.host_ip, n.ports.app_8668))
+        .map(|n| format!("http://{}:{}", n.ip(&env.host_ip), n.ports.app_8668))
 }
@@ -0,0 +1,440 @@
+//!
+//! Load generator for a running env, submits a configurable mix
+//! of transaction types at a target rate, and reports the acceptance rate
+//! and the commit latency of each type.
+//!
+//! NOTE: the env should have been initialized,
+//! all senders are funded by the bank account.
+//!
+
+use super::{
+    bench::{fra_template, Latency, Status},
+    init::{new_tx_builder, send_tx},
+    Env,
+};
+use crate::txn_builder::{TransactionBuilder, TransferOperationBuilder};
+use ledger::{
+    data_model::{
+        AssetRules, AssetTypeCode, Transaction, TransferType, TxoRef, TxoSID,
+        ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
+    },
+    staking::FRA,
+};
+use ruc::*;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    str::FromStr,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use zei::{
+    setup::PublicParams,
+    xfr::{
+        asset_record::{open_blind_asset_record, AssetRecordType},
+        sig::XfrKeyPair,
+        structs::{AssetRecordTemplate, OpenAssetRecord},
+    },
+};
+
+// the amount of each delegation
+const DELEGATION_AMOUNT: u64 = FRA;
+
+// the amount of each issuance
+const ISSUE_AMOUNT: u64 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxKind {
+    Define,
+    // defines an asset and issues it in the same transaction
+    Issue,
+    Transfer,
+    ConfidentialTransfer,
+    Delegation,
+}
+
+impl FromStr for TxKind {
+    type Err = Box<dyn RucError>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "define" => Ok(TxKind::Define),
+            "issue" => Ok(TxKind::Issue),
+            "transfer" => Ok(TxKind::Transfer),
+            "confidential" => Ok(TxKind::ConfidentialTransfer),
+            "delegation" => Ok(TxKind::Delegation),
+            _ => Err(eg!(format!("unknown transaction type: {}", s))),
+        }
+    }
+}
+
+impl TxKind {
+    // the FRA needed by one transaction, including the fee
+    fn cost(self) -> u64 {
+        match self {
+            TxKind::Define | TxKind::Issue => TX_FEE_MIN,
+            TxKind::Transfer | TxKind::ConfidentialTransfer => 2 * TX_FEE_MIN,
+            TxKind::Delegation => TX_FEE_MIN + DELEGATION_AMOUNT,
+        }
+    }
+}
+
+/// Parse a mix of transaction types, eg. `transfer=5,issue=1,delegation=1`.
+pub fn parse_mix(mix: &str) -> Result<Vec<(TxKind, u32)>> {
+    let mix = mix
+        .split(',')
+        .map(|m| {
+            let (kind, weight) = m.split_once('=').c(d!(m))?;
+            Ok((
+                kind.trim().parse::<TxKind>().c(d!())?,
+                weight.trim().parse::<u32>().c(d!(m))?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if 0 == mix.iter().map(|(_, w)| *w).sum::<u32>() {
+        return Err(eg!("all weights are 0"));
+    }
+    Ok(mix)
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadgenCfg {
+    // number of sender accounts, each delegation has its own sender
+    pub accounts: usize,
+
+    // transactions submitted per second
+    pub tps: f64,
+
+    // how long to submit transactions
+    pub secs: u64,
+
+    // transaction types with their weights
+    pub mix: Vec<(TxKind, u32)>,
+
+    // also write the JSON report to this file
+    pub out: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct KindReport {
+    planned: usize,
+    // accepted by the submission API
+    accepted: usize,
+    committed: usize,
+    rejected: usize,
+    // from the submission to the commit
+    commit_latency: Option<Latency>,
+}
+
+#[derive(Debug, Serialize)]
+struct LoadgenReport {
+    env_name: String,
+    target_tps: f64,
+    submit_tps: f64,
+    acceptance_rate: f64,
+    kinds: BTreeMap<TxKind, KindReport>,
+}
+
+struct Planned {
+    kind: TxKind,
+    sender: usize,
+}
+
+impl Env {
+    pub(super) fn loadgen(&self, cfg: &LoadgenCfg) -> Result<()> {
+        if 0 == cfg.accounts || 0.0 >= cfg.tps || 0 == cfg.secs {
+            return Err(eg!("no accounts, transactions or duration"));
+        }
+        let n = (cfg.tps * cfg.secs as f64).ceil() as usize;
+
+        // the delegators come after the other senders
+        let mut plan = vec![];
+        let mut senders = cfg.accounts;
+        let total_weight = cfg.mix.iter().map(|(_, w)| *w).sum::<u32>();
+        for i in 0..n {
+            let mut r = rand::random::<u32>() % total_weight;
+            let kind = cfg
+                .mix
+                .iter()
+                .find(|(_, w)| {
+                    let hit = r < *w;
+                    r = r.saturating_sub(*w);
+                    hit
+                })
+                .map(|(k, _)| *k)
+                .c(d!())?;
+            let sender = if TxKind::Delegation == kind {
+                senders += 1;
+                senders - 1
+            } else {
+                i % cfg.accounts
+            };
+            plan.push(Planned { kind, sender });
+        }
+        let senders = (0..senders)
+            .map(|_| XfrKeyPair::generate(&mut rand::thread_rng()))
+            .collect::<Vec<_>>();
+
+        println!("[ {} ] >>> Fund {} senders ...", &self.name, senders.len());
+        let targets = plan
+            .iter()
+            .map(|p| (senders[p.sender].get_pk_ref(), p.kind.cost()))
+            .collect::<Vec<_>>();
+        self.bench_fund_x(&targets).c(d!())?;
+
+        println!("[ {} ] >>> Build {} transactions ...", &self.name, n);
+        let txns = self.loadgen_txns(&plan, &senders).c(d!())?;
+
+        println!(
+            "[ {} ] >>> Submit at {} TPS for {}s ...",
+            &self.name, cfg.tps, cfg.secs
+        );
+        let (sender, receiver) = mpsc::channel();
+        let env = self.clone();
+        let deadline = Duration::from_secs(cfg.secs + 20 * self.block_itv_secs as u64);
+        let poller = thread::spawn(move || env.loadgen_poll(receiver, deadline));
+
+        let itv = Duration::from_secs_f64(1.0 / cfg.tps);
+        let started = Instant::now();
+        let mut accepted = BTreeMap::new();
+        for (i, (kind, tx)) in txns.iter().enumerate() {
+            let at = started + itv * i as u32;
+            if let Some(d) = at.checked_duration_since(Instant::now()) {
+                thread::sleep(d);
+            }
+            if send_tx(self, tx).is_ok() {
+                *accepted.entry(*kind).or_insert(0) += 1;
+                omit!(sender.send((tx.handle(), *kind, Instant::now())));
+            }
+        }
+        let submit_secs = started.elapsed().as_secs_f64();
+        drop(sender);
+
+        println!("[ {} ] >>> Wait for commits ...", &self.name);
+        let results = poller.join().map_err(|_| eg!("poller panicked"))??;
+
+        let mut kinds = BTreeMap::new();
+        for p in plan.iter() {
+            kinds
+                .entry(p.kind)
+                .or_insert_with(KindReport::default)
+                .planned += 1;
+        }
+        let mut latencies = BTreeMap::new();
+        for (kind, latency) in results {
+            let r = kinds.get_mut(&kind).c(d!())?;
+            match latency {
+                Some(l) => {
+                    r.committed += 1;
+                    latencies.entry(kind).or_insert_with(Vec::new).push(l);
+                }
+                None => r.rejected += 1,
+            }
+        }
+        for (kind, r) in kinds.iter_mut() {
+            r.accepted = accepted.get(kind).copied().unwrap_or(0);
+            r.commit_latency = latencies.remove(kind).and_then(Latency::from_samples);
+        }
+
+        let report = LoadgenReport {
+            env_name: self.name.clone(),
+            target_tps: cfg.tps,
+            submit_tps: n as f64 / submit_secs,
+            acceptance_rate: accepted.values().sum::<usize>() as f64 / n as f64,
+            kinds,
+        };
+        let report = serde_json::to_string_pretty(&report).c(d!())?;
+        println!("{}", &report);
+
+        if let Some(path) = cfg.out.as_ref() {
+            fs::write(path, &report).c(d!(path))?;
+        }
+
+        Ok(())
+    }
+
+    // every planned transaction spends one output of its sender,
+    // the outputs of a sender are matched by their amounts
+    fn loadgen_txns(
+        &self,
+        plan: &[Planned],
+        senders: &[XfrKeyPair],
+    ) -> Result<Vec<(TxKind, Transaction)>> {
+        let seq_id = new_tx_builder(self).c(d!())?.get_seq_id();
+        let validator = self
+            .initial_validators
+            .first()
+            .map(|v| v.tendermint_addr.clone())
+            .c(d!("no validators"))?;
+        let params = PublicParams::default();
+
+        let mut inputs: HashMap<usize, Vec<(TxoSID, OpenAssetRecord)>> = HashMap::new();
+        let mut res = vec![];
+        for p in plan.iter() {
+            let kp = &senders[p.sender];
+            if !inputs.contains_key(&p.sender) {
+                let mut oars = self
+                    .bench_owned_utxos(kp.get_pk_ref())
+                    .c(d!())?
+                    .into_iter()
+                    .map(|(sid, (utxo, owner_memo))| {
+                        open_blind_asset_record(&utxo.0.record, &owner_memo, kp)
+                            .c(d!())
+                            .map(|oar| (sid, oar))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                // the largest ones are popped first
+                oars.sort_by_key(|(_, oar)| oar.amount);
+                inputs.insert(p.sender, oars);
+            }
+            let oars = inputs.get_mut(&p.sender).c(d!())?;
+            let idx = oars
+                .iter()
+                .rposition(|(_, oar)| oar.amount == p.kind.cost())
+                .c(d!("funding output not found"))?;
+            let (sid, oar) = oars.remove(idx);
+
+            // the fee, and the transfer or the principal of a delegation
+            let amount = oar.amount;
+            let mut op = TransferOperationBuilder::new();
+            op.add_input(TxoRef::Absolute(sid), oar, None, None, amount)
+                .c(d!())?
+                .add_output(
+                    &fra_template(TX_FEE_MIN, *BLACK_HOLE_PUBKEY),
+                    None,
+                    None,
+                    None,
+                )
+                .c(d!())?;
+            match p.kind {
+                TxKind::Transfer | TxKind::ConfidentialTransfer => {
+                    let receiver =
+                        senders[rand::random::<usize>() % senders.len()].get_pk();
+                    let art = alt!(
+                        TxKind::Transfer == p.kind,
+                        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType
+                    );
+                    op.add_output(
+                        &AssetRecordTemplate::with_no_asset_tracing(
+                            amount - TX_FEE_MIN,
+                            ASSET_TYPE_FRA,
+                            art,
+                            receiver,
+                        ),
+                        None,
+                        None,
+                        None,
+                    )
+                    .c(d!())?;
+                }
+                TxKind::Delegation => {
+                    op.add_output(
+                        &fra_template(DELEGATION_AMOUNT, *BLACK_HOLE_PUBKEY_STAKING),
+                        None,
+                        None,
+                        None,
+                    )
+                    .c(d!())?;
+                }
+                TxKind::Define | TxKind::Issue => {}
+            }
+            let op = op
+                .create(TransferType::Standard)
+                .c(d!())?
+                .sign(kp)
+                .c(d!())?
+                .transaction()
+                .c(d!())?;
+
+            let mut builder = TransactionBuilder::from_seq_id(seq_id);
+            builder.add_operation(op);
+            match p.kind {
+                TxKind::Define | TxKind::Issue => {
+                    let code = AssetTypeCode::gen_random();
+                    builder
+                        .add_operation_create_asset(
+                            kp,
+                            Some(code),
+                            AssetRules::default(),
+                            "loadgen",
+                        )
+                        .c(d!())?;
+                    if TxKind::Issue == p.kind {
+                        builder
+                            .add_basic_issue_asset(
+                                kp,
+                                &code,
+                                seq_id,
+                                ISSUE_AMOUNT,
+                                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                                &params,
+                            )
+                            .c(d!())?;
+                    }
+                }
+                TxKind::Delegation => {
+                    builder.add_operation_delegation(
+                        kp,
+                        DELEGATION_AMOUNT,
+                        validator.clone(),
+                    );
+                }
+                TxKind::Transfer | TxKind::ConfidentialTransfer => {}
+            }
+            let mut tx = builder.take_transaction();
+            tx.sign(kp);
+            res.push((p.kind, tx));
+        }
+
+        Ok(res)
+    }
+
+    // polls the status of the submitted transactions until all are settled
+    // or the deadline, returns the commit latencies in milliseconds,
+    // `None` for the rejected ones
+    fn loadgen_poll(
+        &self,
+        submitted: mpsc::Receiver<(String, TxKind, Instant)>,
+        deadline: Duration,
+    ) -> Result<Vec<(TxKind, Option<f64>)>> {
+        let deadline = Instant::now() + deadline;
+        let mut pending = vec![];
+        let mut done = false;
+        let mut res = vec![];
+
+        while Instant::now() < deadline {
+            loop {
+                match submitted.try_recv() {
+                    Ok(s) => pending.push(s),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        done = true;
+                        break;
+                    }
+                }
+            }
+            if done && pending.is_empty() {
+                break;
+            }
+
+            let mut left = vec![];
+            for (h, kind, at) in pending {
+                match self.bench_txn_status(&h).c(d!())? {
+                    Status::Committed => {
+                        res.push((kind, Some(at.elapsed().as_secs_f64() * 1000.0)))
+                    }
+                    Status::Rejected => res.push((kind, None)),
+                    Status::Pending => left.push((h, kind, at)),
+                }
+            }
+            pending = left;
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(res)
+    }
+}
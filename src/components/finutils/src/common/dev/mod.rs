@@ -7,7 +7,25 @@
 #![deny(warnings)]
 #![allow(missing_docs)]
 
+mod bench;
+mod chaos;
+mod deploy;
+mod fork;
 mod init;
+mod loadgen;
+mod remote;
+mod replay;
+mod template;
+mod upgrade;
+
+pub use bench::BenchCfg;
+pub use chaos::ChaosOps;
+pub use deploy::ManifestKind;
+pub use loadgen::{parse_mix, LoadgenCfg, TxKind};
+pub use remote::Host;
+pub use replay::ReplayCfg;
+pub use template::{Economics, EnvTemplate};
+pub use upgrade::UpgradeCfg;
 
 use ledger::staking::{
     td_addr_to_bytes, Validator as StakingValidator, ValidatorKind, FRA, VALIDATORS_MIN,
@@ -96,6 +114,41 @@ pub struct EnvCfg {
     pub tendermint_extra_flags: Option<String>,
 
     pub force_create: bool,
+
+    // only used in `Ops::Create`,
+    // emit a docker-compose.yml or k8s manifests for the new env
+    pub manifest: Option<ManifestKind>,
+
+    // only used in `Ops::Create`,
+    // the image used in the generated manifests
+    pub manifest_image: Option<String>,
+
+    // only used in `Ops::Chaos`
+    pub chaos: Option<ChaosOps>,
+
+    // only used in `Ops::Create`,
+    // balances, delegations and assets applied by `Ops::Init`
+    pub economics: Economics,
+
+    // only used in `Ops::Create`,
+    // a ledger snapshot(local path or URL) of an existing network
+    pub fork_from: Option<String>,
+
+    // only used in `Ops::Upgrade`
+    pub upgrade: Option<UpgradeCfg>,
+
+    // only used in `Ops::Bench`
+    pub bench: Option<BenchCfg>,
+
+    // only used in `Ops::Loadgen`
+    pub loadgen: Option<LoadgenCfg>,
+
+    // only used in `Ops::Replay`
+    pub replay: Option<ReplayCfg>,
+
+    // only used in `Ops::Create`,
+    // nodes are placed on these hosts in turn, empty means the localhost
+    pub hosts: Vec<Host>,
 }
 
 impl Default for EnvCfg {
@@ -113,6 +166,16 @@ impl Default for EnvCfg {
             abcid_extra_flags: None,
             tendermint_extra_flags: None,
             force_create: false,
+            manifest: None,
+            manifest_image: None,
+            chaos: None,
+            economics: Economics::default(),
+            fork_from: None,
+            upgrade: None,
+            bench: None,
+            loadgen: None,
+            replay: None,
+            hosts: vec![],
         }
     }
 }
@@ -155,6 +218,41 @@ impl EnvCfg {
                 .and_then(|mut env| env.init().c(d!()))
                 .map(|_| None),
             Ops::InitAll => Env::init_all().c(d!()).map(|_| None),
+            Ops::Upgrade => {
+                let cfg = self.upgrade.as_ref().c(d!("upgrade settings not found"))?;
+                Env::load_cfg(self)
+                    .c(d!())
+                    .and_then(|mut env| env.upgrade(cfg).c(d!()))
+                    .map(|_| None)
+            }
+            Ops::Chaos => {
+                let ops = self.chaos.c(d!("chaos operation not specified"))?;
+                Env::load_cfg(self)
+                    .c(d!())
+                    .and_then(|mut env| env.chaos(ops).c(d!()))
+                    .map(|_| None)
+            }
+            Ops::Bench => {
+                let cfg = self.bench.as_ref().c(d!("bench settings not found"))?;
+                Env::load_cfg(self)
+                    .c(d!())
+                    .and_then(|env| env.bench(cfg).c(d!()))
+                    .map(|_| None)
+            }
+            Ops::Loadgen => {
+                let cfg = self.loadgen.as_ref().c(d!("loadgen settings not found"))?;
+                Env::load_cfg(self)
+                    .c(d!())
+                    .and_then(|env| env.loadgen(cfg).c(d!()))
+                    .map(|_| None)
+            }
+            Ops::Replay => {
+                let cfg = self.replay.as_ref().c(d!("replay settings not found"))?;
+                Env::load_cfg(self)
+                    .c(d!())
+                    .and_then(|env| env.replay(cfg).c(d!()))
+                    .map(|_| None)
+            }
         }
     }
 }
@@ -200,9 +298,18 @@ pub struct Env {
     // default value: 2152
     evm_chain_id: u64,
 
+    // genesis economics, used by `fn dev init`
+    #[serde(default)]
+    economics: Economics,
+
     // path of the checkpoint file, if any
     checkpoint_file: Option<String>,
 
+    // the ledger snapshot this env is forked from, if any
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fork_from: Option<String>,
+
     #[serde(rename = "seed_nodes")]
     seeds: BTreeMap<NodeId, Node>,
 
@@ -215,12 +322,25 @@ pub struct Env {
     // the contents of `genesis.json` of all nodes
     #[serde(rename = "tendermint_genesis_config")]
     genesis: String,
+
+    // isolated groups of nodes, set by `fn dev chaos --partition`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    partition: Vec<BTreeSet<NodeId>>,
+
+    // remote hosts of nodes, empty means all nodes run on the localhost
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hosts: Vec<Host>,
 }
 
 impl Env {
     // - initilize a new env
     // - `genesis.json` will be created
     fn create(cfg: &EnvCfg) -> Result<Env> {
+        // the number of validators may have been changed by the command line
+        cfg.economics.check(Some(cfg.initial_validator_num)).c(d!())?;
+
         let home = format!("{}/envs/{}", ENV_BASE_DIR, &cfg.name);
 
         if cfg.force_create {
@@ -239,6 +359,8 @@ impl Env {
             home,
             block_itv_secs: cfg.block_itv_secs,
             evm_chain_id: cfg.evm_chain_id,
+            economics: cfg.economics.clone(),
+            fork_from: cfg.fork_from.clone(),
             checkpoint_file: cfg.checkpoint_file.clone(),
             initial_validator_num: cfg.initial_validator_num,
             host_ip: cfg.host_ip.as_deref().unwrap_or("127.0.0.1").to_owned(),
@@ -250,6 +372,7 @@ impl Env {
                 .to_owned(),
             abcid_extra_flags: cfg.abcid_extra_flags.clone(),
             tendermint_extra_flags: cfg.tendermint_extra_flags.clone(),
+            hosts: cfg.hosts.clone(),
             ..Self::default()
         };
 
@@ -269,9 +392,22 @@ impl Env {
 
         env.gen_genesis()
             .c(d!())
-            .and_then(|_| env.apply_genesis(None).c(d!()))
-            .and_then(|_| env.start(None).c(d!()))
-            .map(|_| env)
+            .and_then(|_| env.apply_genesis(None).c(d!()))?;
+
+        if let Some(snapshot) = cfg.fork_from.as_deref() {
+            env.fork_from(snapshot).c(d!())?;
+        }
+
+        env.start(None).c(d!())?;
+
+        if let Some(kind) = cfg.manifest {
+            let path = env
+                .gen_manifest(kind, cfg.manifest_image.as_deref())
+                .c(d!())?;
+            println!("[ {} ] >>> Manifest generated: {}", &env.name, path);
+        }
+
+        Ok(env)
     }
 
     // start one or all nodes
@@ -288,34 +424,26 @@ impl Env {
             .c(d!())
             .and_then(|_| self.write_cfg().c(d!()))?;
 
+        self.distribute_bins().c(d!())?;
+
         for i in ids.iter() {
-            if let Some(n) = self.nodes.get_mut(i) {
-                n.start(
-                    &self.host_ip,
-                    self.block_itv_secs,
-                    self.evm_chain_id,
-                    self.checkpoint_file.as_deref(),
-                    &self.abcid_bin,
-                    &self.tendermint_bin,
-                    self.abcid_extra_flags.as_deref().unwrap_or_default(),
-                    self.tendermint_extra_flags.as_deref().unwrap_or_default(),
-                )
-                .c(d!())?;
-            } else if let Some(n) = self.seeds.get_mut(i) {
-                n.start(
-                    &self.host_ip,
-                    self.block_itv_secs,
-                    self.evm_chain_id,
-                    self.checkpoint_file.as_deref(),
-                    &self.abcid_bin,
-                    &self.tendermint_bin,
-                    self.abcid_extra_flags.as_deref().unwrap_or_default(),
-                    self.tendermint_extra_flags.as_deref().unwrap_or_default(),
-                )
-                .c(d!())?;
-            } else {
-                return Err(eg!("not exist"));
-            }
+            let n = self
+                .nodes
+                .get(i)
+                .or_else(|| self.seeds.get(i))
+                .c(d!("not exist"))?;
+            self.sync_node(n).c(d!())?;
+            n.start(
+                n.ip(&self.host_ip),
+                self.block_itv_secs,
+                self.evm_chain_id,
+                self.checkpoint_file.as_deref(),
+                &self.bin_path(n, n.abcid_bin.as_deref().unwrap_or(&self.abcid_bin)),
+                &self.bin_path(n, &self.tendermint_bin),
+                self.abcid_extra_flags.as_deref().unwrap_or_default(),
+                self.tendermint_extra_flags.as_deref().unwrap_or_default(),
+            )
+            .c(d!())?;
         }
 
         Ok(())
@@ -480,6 +608,12 @@ impl Env {
         let home = format!("{}/{}", self.home, id);
         fs::create_dir_all(&home).c(d!())?;
 
+        let host = self.pick_host(id);
+        let ip = host
+            .as_ref()
+            .map(|h| h.addr.clone())
+            .unwrap_or_else(|| self.host_ip.clone());
+
         let cfg_path = format!("{}/config/config.toml", &home);
         let mut cfg = fs::read_to_string(&cfg_path)
             .c(d!())
@@ -493,10 +627,8 @@ impl Env {
             })
             .and_then(|c| c.parse::<Document>().c(d!()))?;
 
-        cfg["proxy_app"] =
-            toml_value(format!("tcp://{}:{}", &self.host_ip, ports.app_abci));
-        cfg["rpc"]["laddr"] =
-            toml_value(format!("tcp://{}:{}", &self.host_ip, ports.tm_rpc));
+        cfg["proxy_app"] = toml_value(format!("tcp://{}:{}", &ip, ports.app_abci));
+        cfg["rpc"]["laddr"] = toml_value(format!("tcp://{}:{}", &ip, ports.tm_rpc));
 
         let mut arr = Array::new();
         arr.push("*");
@@ -507,8 +639,7 @@ impl Env {
         cfg["p2p"]["persistent_peers_max_dial_period"] = toml_value("3s");
         cfg["p2p"]["send_rate"] = toml_value(64 * MB);
         cfg["p2p"]["recv_rate"] = toml_value(64 * MB);
-        cfg["p2p"]["laddr"] =
-            toml_value(format!("tcp://{}:{}", &self.host_ip, ports.tm_p2p));
+        cfg["p2p"]["laddr"] = toml_value(format!("tcp://{}:{}", &ip, ports.tm_p2p));
 
         cfg["consensus"]["timeout_propose"] = toml_value("16s");
         cfg["consensus"]["timeout_propose_delta"] = toml_value("100ms");
@@ -563,6 +694,8 @@ impl Env {
             home: format!("{}/{}", &self.home, id),
             kind,
             ports,
+            abcid_bin: None,
+            host,
         };
 
         match kind {
@@ -575,16 +708,30 @@ impl Env {
     }
 
     fn update_peer_cfg(&self) -> Result<()> {
+        // nodes in different groups can not see each other
+        let same_group = |a: NodeId, b: NodeId| {
+            self.partition
+                .iter()
+                .all(|g| g.contains(&a) == g.contains(&b))
+        };
+
         for n in self.nodes.values() {
             let cfg_path = format!("{}/config/config.toml", &n.home);
             let mut cfg = fs::read_to_string(&cfg_path)
                 .c(d!())
                 .and_then(|c| c.parse::<Document>().c(d!()))?;
+            cfg["p2p"]["pex"] = toml_value(self.partition.is_empty());
             cfg["p2p"]["seeds"] = toml_value(
                 self.seeds
                     .values()
+                    .filter(|s| same_group(s.id, n.id))
                     .map(|n| {
-                        format!("{}@{}:{}", &n.tm_id, &self.host_ip, n.ports.tm_p2p)
+                        format!(
+                            "{}@{}:{}",
+                            &n.tm_id,
+                            n.ip(&self.host_ip),
+                            n.ports.tm_p2p
+                        )
                     })
                     .collect::<Vec<_>>()
                     .join(","),
@@ -592,9 +739,14 @@ impl Env {
             cfg["p2p"]["persistent_peers"] = toml_value(
                 self.nodes
                     .values()
-                    .filter(|peer| peer.id != n.id)
+                    .filter(|peer| peer.id != n.id && same_group(peer.id, n.id))
                     .map(|n| {
-                        format!("{}@{}:{}", &n.tm_id, &self.host_ip, n.ports.tm_p2p)
+                        format!(
+                            "{}@{}:{}",
+                            &n.tm_id,
+                            n.ip(&self.host_ip),
+                            n.ports.tm_p2p
+                        )
                     })
                     .collect::<Vec<_>>()
                     .join(","),
@@ -801,6 +953,7 @@ impl TryFrom<&InitialValidator> for StakingValidator {
             kind: ValidatorKind::Initiator,
             signed_last_block: false,
             signed_cnt: 0,
+            jailed: false,
             delegators: Default::default(),
         })
     }
@@ -816,6 +969,14 @@ struct Node {
     kind: Kind,
     #[serde(rename = "occupied_ports")]
     ports: Ports,
+    // a custom abcid binary of this node, set by `fn dev upgrade`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    abcid_bin: Option<String>,
+    // the remote host of this node, `None` means the localhost
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<Host>,
 }
 
 impl Node {
@@ -824,7 +985,7 @@ impl Node {
     // - update meta
     #[allow(clippy::too_many_arguments)]
     fn start(
-        &mut self,
+        &self,
         host_ip: &str,
         block_itv: u8,
         evm_chain_id: u64,
@@ -875,6 +1036,10 @@ impl Node {
                 )
                 .unwrap();
 
+                if let Some(h) = self.host.as_ref() {
+                    cmd = h.ssh_cmd(&format!("ulimit -n 100000; {}", cmd));
+                }
+
                 pnk!(self.write_fn_log(&cmd));
                 pnk!(exec_spawn(&cmd));
 
@@ -897,7 +1062,7 @@ impl Node {
              do kill -9 $i; done",
             &self.home
         );
-        let outputs = cmd::exec_output(&cmd).c(d!())?;
+        let outputs = self.exec(&cmd).c(d!())?;
         let contents = format!("{}\n{}", &cmd, outputs.as_str());
         self.write_fn_log(&contents).c(d!())
     }
@@ -932,6 +1097,10 @@ impl Node {
             PortsCache::remove(port).c(d!())?;
         }
 
+        if self.host.is_some() {
+            self.exec(&format!("rm -rf {}", &self.home)).c(d!())?;
+        }
+
         fs::remove_dir_all(&self.home).c(d!())
     }
 }
@@ -975,6 +1144,11 @@ pub enum Ops {
     PopNode,
     Init,
     InitAll,
+    Chaos,
+    Upgrade,
+    Bench,
+    Loadgen,
+    Replay,
     Show,
     ShowAll,
     List,
@@ -0,0 +1,189 @@
+//!
+//! Multi-host support, nodes of an env can be placed on remote hosts,
+//! all remote operations are done through `ssh` and `rsync`.
+//!
+//! - configs are rendered locally, then synced to the same paths on the remote hosts
+//! - the binaries of tendermint and abcid are distributed to `${ENV_HOME}/bin/`
+//! - the host keys are trusted on first use, OpenSSH 7.6 or later is required
+//!
+
+use super::{Env, Node, NodeId};
+use ruc::{cmd, *};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// directory of the distributed binaries, relative to the env home
+const BIN_DIR: &str = "bin";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Host {
+    // the IP that nodes listen on, also used to connect the host
+    pub addr: String,
+    pub user: String,
+    pub ssh_port: u16,
+    // path of the private key, use the default one of ssh if not set
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<String>,
+}
+
+// format: `[user@]addr[:ssh_port]`
+impl FromStr for Host {
+    type Err = Box<dyn RucError>;
+    fn from_str(s: &str) -> Result<Self> {
+        let (user, rest) = s.split_once('@').unwrap_or(("root", s));
+        let (addr, port) = match rest.split_once(':') {
+            Some((addr, port)) => (addr, port.parse::<u16>().c(d!())?),
+            None => (rest, 22),
+        };
+        if addr.is_empty() || user.is_empty() {
+            return Err(eg!("invalid host: {}", s));
+        }
+        Ok(Self {
+            addr: addr.to_owned(),
+            user: user.to_owned(),
+            ssh_port: port,
+            ssh_key: None,
+        })
+    }
+}
+
+impl Host {
+    // the key of a new host is added to `known_hosts` on the first connection,
+    // the connections to a known host presenting another key are refused
+    fn ssh_opts(&self) -> String {
+        let mut opts = format!(
+            "-o StrictHostKeyChecking=accept-new -o BatchMode=yes -p {}",
+            self.ssh_port
+        );
+        if let Some(key) = self.ssh_key.as_deref() {
+            opts.push_str(" -i ");
+            opts.push_str(key);
+        }
+        opts
+    }
+
+    // wrap a command to run it on this host
+    pub(super) fn ssh_cmd(&self, cmd: &str) -> String {
+        format!(
+            "ssh {} {}@{} '{}'",
+            self.ssh_opts(),
+            &self.user,
+            &self.addr,
+            cmd.replace('\'', r"'\''")
+        )
+    }
+
+    pub(super) fn exec(&self, cmd: &str) -> Result<String> {
+        cmd::exec_output(&self.ssh_cmd(cmd)).c(d!())
+    }
+
+    // copy a local file or directory to the same path on this host
+    fn push(&self, path: &str, rsync_opts: &str) -> Result<()> {
+        let parent = path.rsplit_once('/').map(|(p, _)| p).unwrap_or("/");
+        self.exec(&format!("mkdir -p {}", parent)).c(d!())?;
+        cmd::exec_output(&format!(
+            "rsync -a {} -e 'ssh {}' {} {}@{}:{}",
+            rsync_opts,
+            self.ssh_opts(),
+            path,
+            &self.user,
+            &self.addr,
+            parent
+        ))
+        .c(d!())
+        .map(|_| ())
+    }
+}
+
+impl Node {
+    // the address used by other nodes and clients
+    pub(super) fn ip<'a>(&'a self, default: &'a str) -> &'a str {
+        self.host
+            .as_ref()
+            .map(|h| h.addr.as_str())
+            .unwrap_or(default)
+    }
+
+    // run a command on the host of this node
+    pub(super) fn exec(&self, cmd: &str) -> Result<String> {
+        match self.host.as_ref() {
+            Some(h) => h.exec(cmd).c(d!()),
+            None => cmd::exec_output(cmd).c(d!()),
+        }
+    }
+}
+
+impl Env {
+    // round-robin placement, an empty host list means the localhost
+    pub(super) fn pick_host(&self, id: NodeId) -> Option<Host> {
+        alt!(self.hosts.is_empty(), return None);
+        Some(self.hosts[id as usize % self.hosts.len()].clone())
+    }
+
+    pub(super) fn bin_dir(&self) -> String {
+        format!("{}/{}", &self.home, BIN_DIR)
+    }
+
+    // copy the binaries to all remote hosts
+    pub(super) fn distribute_bins(&self) -> Result<()> {
+        if self.hosts.is_empty() {
+            return Ok(());
+        }
+
+        let dir = self.bin_dir();
+        let bins = self
+            .nodes
+            .values()
+            .filter_map(|n| n.abcid_bin.as_deref())
+            .chain([self.abcid_bin.as_str(), self.tendermint_bin.as_str()])
+            .map(|b| cmd::exec_output(&format!("which {}", b)).c(d!()))
+            .collect::<Result<Vec<_>>>()?;
+
+        cmd::exec_output(&format!("mkdir -p {0} && cp -f {1} {0}/", &dir, {
+            let mut b = bins.iter().map(|b| b.trim()).collect::<Vec<_>>();
+            b.sort_unstable();
+            b.dedup();
+            b.join(" ")
+        }))
+        .c(d!())?;
+
+        for h in self.hosts.iter() {
+            println!("[ {} ] >>> Distribute binaries to {}", &self.name, &h.addr);
+            h.push(&dir, "").c(d!())?;
+        }
+
+        Ok(())
+    }
+
+    // sync the local home of a node to its remote host,
+    // - missing files(eg. the initial `priv_validator_state.json`) are created
+    // - configs are overwritten, the data produced remotely are kept
+    pub(super) fn sync_node(&self, n: &Node) -> Result<()> {
+        match n.host.as_ref() {
+            Some(h) => h
+                .push(&n.home, "--ignore-existing")
+                .and_then(|_| {
+                    h.push(
+                        &n.home,
+                        "--exclude 'data/' --exclude '__findora__/' --exclude '*.log'",
+                    )
+                })
+                .c(d!()),
+            None => Ok(()),
+        }
+    }
+
+    // the path of a binary on the host of a node
+    pub(super) fn bin_path(&self, n: &Node, bin: &str) -> String {
+        if n.host.is_some() {
+            format!(
+                "{}/{}",
+                self.bin_dir(),
+                bin.rsplit('/').next().unwrap_or(bin)
+            )
+        } else {
+            bin.to_owned()
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//!
+//! Replay a block archive through an empty ledger, and compare the state commitment
+//! of every block with the recorded one.
+//!
+//! - the archive is recorded by a node of the env started with
+//!   `--abcid-extra-flags '--block-archive <PATH>'`
+//! - the replay runs in abcid(`--replay-archive`), with the checkpoint file of the env
+//! - it stops at the first divergence, the differing fields, the offending transaction
+//!   and its decoded effects are printed
+//! - the replay can stop at a height, and export the state of the ledger there,
+//!   to be compared with `fn state-diff`
+//!
+
+use super::{exec_spawn, Env};
+use ruc::*;
+use std::fs;
+
+// the `--ledger-dir` of the replay, recreated every time
+const REPLAY_DIR_NAME: &str = "__replay__";
+
+#[derive(Debug, Clone)]
+pub struct ReplayCfg {
+    // path of the block archive
+    pub archive: String,
+    // stop after the block of this height
+    pub until: Option<u64>,
+    // export the state of the ledger to this file at last
+    pub export: Option<String>,
+}
+
+impl Env {
+    pub(super) fn replay(&self, cfg: &ReplayCfg) -> Result<()> {
+        let archive = fs::canonicalize(&cfg.archive).c(d!(cfg.archive.clone()))?;
+        let dir = format!("{}/{}", &self.home, REPLAY_DIR_NAME);
+        omit!(fs::remove_dir_all(&dir));
+        fs::create_dir_all(&dir).c(d!())?;
+
+        let mut cmd = format!(
+            "{} --ledger-dir {} --replay-archive {}",
+            &self.abcid_bin,
+            &dir,
+            archive.to_string_lossy()
+        );
+        if let Some(checkpoint) = self.checkpoint_file.as_deref() {
+            cmd.push_str(&format!(" --checkpoint-file {}", checkpoint));
+        }
+        if let Some(h) = cfg.until {
+            cmd.push_str(&format!(" --replay-until {}", h));
+        }
+        if let Some(export) = cfg.export.as_deref() {
+            cmd.push_str(&format!(" --export-state {}", export));
+        }
+
+        println!(
+            "[ {} ] >>> Replay: {}",
+            &self.name,
+            archive.to_string_lossy()
+        );
+        exec_spawn(&cmd).c(d!("diverged, see the report above"))
+    }
+}
@@ -0,0 +1,168 @@
+//!
+//! `EnvTemplate`: a JSON file describing the genesis economics of an env,
+//! all fields are optional, the default values are same as the old hard-coded ones.
+//!
+//! ```json
+//! {
+//!   "block_itv_secs": 1,
+//!   "initial_validator_num": 7,
+//!   "economics": {
+//!     "validator_balance": 5000000,
+//!     "self_delegation": 4000000,
+//!     "self_delegation_step": 10000,
+//!     "extra_balances": { "fra1...": 100 },
+//!     "assets": [
+//!       { "memo": "USDT", "decimals": 6, "max_units": 1000000000, "issue_amount": 100000000 }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+
+use super::{EnvCfg, FRA};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs};
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct EnvTemplate {
+    // seconds between two blocks
+    pub block_itv_secs: Option<u8>,
+
+    // how many initial validators should be created
+    pub initial_validator_num: Option<u8>,
+
+    #[serde(default)]
+    pub economics: Economics,
+}
+
+impl EnvTemplate {
+    pub fn load(path: &str) -> Result<Self> {
+        fs::read(path)
+            .c(d!(path.to_owned()))
+            .and_then(|c| serde_json::from_slice::<Self>(&c).c(d!()))
+            .and_then(|t| {
+                t.economics
+                    .check(t.initial_validator_num)
+                    .c(d!())
+                    .map(|_| t)
+            })
+    }
+}
+
+impl EnvCfg {
+    // options specified in the command line take precedence,
+    // so the template should be applied before them
+    pub fn apply_template(&mut self, path: &str) -> Result<()> {
+        let t = EnvTemplate::load(path).c(d!())?;
+        if let Some(itv) = t.block_itv_secs {
+            self.block_itv_secs = itv;
+        }
+        if let Some(n) = t.initial_validator_num {
+            self.initial_validator_num = n;
+        }
+        self.economics = t.economics;
+        Ok(())
+    }
+}
+
+// all amounts are in FRA(not in the minimal unit)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Economics {
+    // FRAs transferred to each initial validator
+    pub validator_balance: u64,
+
+    // the self-delegation amount of the first validator
+    pub self_delegation: u64,
+
+    // the N-th validator will self-delegate `self_delegation + N * self_delegation_step`,
+    // so the voting powers of validators are different
+    pub self_delegation_step: u64,
+
+    // `wallet address => FRA amount`, funded from the bank account
+    pub extra_balances: BTreeMap<String, u64>,
+
+    // custom assets defined and issued by the bank account
+    pub assets: Vec<InitialAsset>,
+}
+
+impl Default for Economics {
+    fn default() -> Self {
+        Self {
+            validator_balance: 500_0000,
+            self_delegation: 400_0000,
+            self_delegation_step: 1_0000,
+            extra_balances: BTreeMap::new(),
+            assets: vec![],
+        }
+    }
+}
+
+impl Economics {
+    // checked again by `Env::create`, after the options of the command line
+    pub(super) fn check(&self, validator_num: Option<u8>) -> Result<()> {
+        let n = validator_num.unwrap_or_else(|| EnvCfg::default().initial_validator_num);
+        let max_delegation = self
+            .self_delegation_step
+            .checked_mul(n.saturating_sub(1) as u64)
+            .and_then(|i| i.checked_add(self.self_delegation))
+            .c(d!("delegation overflow"))?;
+
+        // fees are paid from the balance
+        if max_delegation >= self.validator_balance {
+            return Err(eg!(
+                "`validator_balance` should be greater than the max self-delegation: {}",
+                max_delegation
+            ));
+        }
+        if 0 == self.self_delegation {
+            return Err(eg!("`self_delegation` can not be zero"));
+        }
+
+        self.validator_balance
+            .checked_mul(FRA)
+            .c(d!("`validator_balance` overflow"))?;
+        for am in self.extra_balances.values() {
+            am.checked_mul(FRA).c(d!("balance overflow"))?;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn self_delegation_of(&self, idx: usize) -> u64 {
+        (self.self_delegation + idx as u64 * self.self_delegation_step) * FRA
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InitialAsset {
+    // base64-formated asset code, a random one will be used if not set
+    pub code: Option<String>,
+
+    pub memo: String,
+
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+
+    pub max_units: Option<u64>,
+
+    #[serde(default = "default_true")]
+    pub transferable: bool,
+
+    #[serde(default)]
+    pub updatable: bool,
+
+    // in the minimal unit of this asset,
+    // issued to the bank account
+    #[serde(default)]
+    pub issue_amount: u64,
+}
+
+fn default_decimals() -> u8 {
+    6
+}
+
+fn default_true() -> bool {
+    true
+}
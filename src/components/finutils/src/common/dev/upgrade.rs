@@ -0,0 +1,192 @@
+//!
+//! Rolling-upgrade harness,
+//! swap the abcid binary of some validators and check the health of the whole env.
+//!
+
+use super::{Env, Node, NodeId};
+use ruc::*;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    thread,
+    time::Duration,
+};
+
+#[derive(Debug, Clone)]
+pub struct UpgradeCfg {
+    // path of the new abcid binary
+    pub abcid_bin: String,
+
+    // how many validators should be upgraded,
+    // in the form of `N`(a number) or `N%`(a percentage)
+    pub scope: String,
+
+    // how many blocks to wait after upgrading each node
+    pub wait_blocks: u64,
+}
+
+impl Env {
+    // upgrade validators one by one, in the order of their IDs,
+    // the env will be left in the final state for further inspections
+    pub(super) fn upgrade(&mut self, cfg: &UpgradeCfg) -> Result<()> {
+        let targets = self.upgrade_targets(&cfg.scope).c(d!())?;
+        println!(
+            "[ {} ] >>> Upgrade validators {:?} to {}",
+            &self.name, &targets, &cfg.abcid_bin
+        );
+
+        let mut reports = vec![];
+        for id in targets {
+            let h0 = self.min_height().c(d!())?;
+
+            self.nodes.get_mut(&id).c(d!())?.abcid_bin = Some(cfg.abcid_bin.clone());
+            self.start(Some(id)).c(d!())?;
+
+            // leave enough time for the upgraded node to catch up
+            let timeout_secs = (cfg.wait_blocks + 10) * self.block_itv_secs as u64;
+            let report = self.wait_blocks(h0 + cfg.wait_blocks, timeout_secs);
+            println!("[ {} ] >>> Node {}: {}", &self.name, id, &report);
+            reports.push((id, report));
+        }
+
+        self.write_cfg().c(d!())?;
+
+        if reports.iter().all(|(_, r)| r.is_ok()) {
+            println!("[ {} ] \x1b[32;01m>>> Upgrade passed !\x1b[00m", &self.name);
+            Ok(())
+        } else {
+            Err(eg!(reports
+                .into_iter()
+                .filter(|(_, r)| !r.is_ok())
+                .map(|(id, r)| format!("node {}: {}", id, r))
+                .collect::<Vec<_>>()
+                .join("\n")))
+        }
+    }
+
+    fn upgrade_targets(&self, scope: &str) -> Result<Vec<NodeId>> {
+        let total = self.nodes.len();
+        let n = if let Some(percent) = scope.strip_suffix('%') {
+            let p = percent.parse::<usize>().c(d!())?;
+            alt!(100 < p, return Err(eg!("invalid percentage")));
+            (total * p + 99) / 100
+        } else {
+            scope.parse::<usize>().c(d!())?
+        };
+
+        if 0 == n || n > total {
+            return Err(eg!("should be in the range [1, {}]", total));
+        }
+
+        Ok(self.nodes.keys().copied().take(n).collect())
+    }
+
+    // wait until all nodes reach the target height,
+    // then compare their app hashes at a common height
+    fn wait_blocks(&self, target: u64, timeout_secs: u64) -> UpgradeReport {
+        let mut h = 0;
+        for _ in 0..timeout_secs.max(1) {
+            thread::sleep(Duration::from_secs(1));
+            if let Ok(i) = self.min_height() {
+                h = i;
+                alt!(h >= target, break);
+            }
+        }
+
+        if h < target {
+            return UpgradeReport::Stalled { height: h, target };
+        }
+
+        // the app hash of height `h` is the state commitment of height `h - 1`,
+        // all nodes should have the same one
+        let mut hashes = BTreeMap::new();
+        for n in self.nodes.values() {
+            match self.app_hash_at(n, h) {
+                Ok(hash) => hashes.insert(n.id, hash),
+                Err(e) => return UpgradeReport::RpcError(e.to_string()),
+            };
+        }
+
+        if 1 < hashes.values().collect::<BTreeSet<_>>().len() {
+            UpgradeReport::HashMismatch { height: h, hashes }
+        } else {
+            UpgradeReport::Ok { height: h }
+        }
+    }
+
+    // the lowest height of all validators
+    fn min_height(&self) -> Result<u64> {
+        self.nodes
+            .values()
+            .map(|n| {
+                self.tm_rpc(n, "status").and_then(|v| {
+                    v["result"]["sync_info"]["latest_block_height"]
+                        .as_str()
+                        .c(d!())
+                        .and_then(|h| h.parse::<u64>().c(d!()))
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .and_then(|hs| hs.into_iter().min().c(d!()))
+    }
+
+    fn app_hash_at(&self, n: &Node, h: u64) -> Result<String> {
+        self.tm_rpc(n, &format!("block?height={}", h))
+            .and_then(|v| {
+                v["result"]["block"]["header"]["app_hash"]
+                    .as_str()
+                    .map(|h| h.to_owned())
+                    .c(d!())
+            })
+    }
+
+    fn tm_rpc(&self, n: &Node, path: &str) -> Result<Value> {
+        let url = format!("http://{}:{}/{}", n.ip(&self.host_ip), n.ports.tm_rpc, path);
+        attohttpc::get(&url)
+            .send()
+            .c(d!(url))?
+            .error_for_status()
+            .c(d!())?
+            .bytes()
+            .c(d!())
+            .and_then(|b| serde_json::from_slice(&b).c(d!()))
+    }
+}
+
+#[derive(Debug)]
+enum UpgradeReport {
+    Ok {
+        height: u64,
+    },
+    // the env did not produce enough blocks in time
+    Stalled {
+        height: u64,
+        target: u64,
+    },
+    HashMismatch {
+        height: u64,
+        hashes: BTreeMap<NodeId, String>,
+    },
+    RpcError(String),
+}
+
+impl UpgradeReport {
+    fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok { .. })
+    }
+}
+
+impl std::fmt::Display for UpgradeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Ok { height } => write!(f, "ok, all nodes agree at height {}", height),
+            Self::Stalled { height, target } => {
+                write!(f, "stalled at height {}, expected {}", height, target)
+            }
+            Self::HashMismatch { height, hashes } => {
+                write!(f, "app hashes mismatch at height {}: {:?}", height, hashes)
+            }
+            Self::RpcError(e) => write!(f, "rpc error: {}", e),
+        }
+    }
+}
@@ -87,13 +87,7 @@ pub fn transfer_from_account(
     let fra_kp = get_keypair()?;
 
     let target = match address {
-        Some(s) => {
-            if let Ok(address) = globutils::wallet::public_key_from_base64(s) {
-                address
-            } else {
-                globutils::wallet::public_key_from_bech32(s)?
-            }
-        }
+        Some(s) => globutils::address::parse_address(s)?,
         None => fra_kp.get_pk(),
     };
 
@@ -0,0 +1,245 @@
+//!
+//! A read-only explorer in the terminal,
+//! fed by the tendermint RPC and the query server of a node.
+//!
+//! The overview shows the latest blocks, the depth of the mempool
+//! and the validator set, commands drill into blocks, transactions and addresses.
+//!
+
+use {
+    super::{get_serv_addr, inspect, utils},
+    globutils::{address, wallet},
+    ledger::data_model::TxnSID,
+    ruc::*,
+    serde::de::DeserializeOwned,
+    serde_json::Value,
+    std::{
+        collections::HashSet,
+        io::{self, BufRead, Write},
+    },
+};
+
+const HELP: &str = "\
+    <Enter>        refresh the overview
+    b <HEIGHT>     show a block
+    t <HASH>       show a transaction
+    a <ADDRESS>    show an address
+    h              show this help
+    q              quit";
+
+// the transactions listed for an address
+const MAX_ADDR_TXNS: usize = 20;
+
+/// Run the explorer against the node of `fn setup`, with `blocks` latest blocks
+/// in the overview, until `q` or the end of the input.
+pub fn run(blocks: u64) -> Result<()> {
+    let serv_addr = get_serv_addr().c(d!())?;
+    let explorer = Explorer {
+        rpc: format!("{}:26657", serv_addr),
+        query: format!("{}:8667", serv_addr),
+        blocks: blocks.max(1),
+    };
+
+    let mut page = explorer.overview();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        // clear the screen, and move to the top
+        print!("\x1b[2J\x1b[H");
+        match page {
+            Ok(p) => println!("{}", p),
+            Err(e) => println!("\x1b[31;01mError:\x1b[00m {}", e),
+        }
+        print!("\n[h for help] > ");
+        io::stdout().flush().c(d!())?;
+
+        let line = match lines.next() {
+            Some(l) => l.c(d!())?,
+            None => return Ok(()),
+        };
+        let mut cmd = line.split_whitespace();
+        page = match (cmd.next(), cmd.next()) {
+            (None, _) => explorer.overview(),
+            (Some("b"), Some(h)) => h
+                .parse::<u64>()
+                .c(d!("invalid height"))
+                .and_then(|h| explorer.block(h)),
+            (Some("t"), Some(hash)) => explorer.tx(hash),
+            (Some("a"), Some(addr)) => explorer.address(addr),
+            (Some("q"), _) => return Ok(()),
+            _ => Ok(HELP.to_owned()),
+        };
+    }
+}
+
+struct Explorer {
+    rpc: String,
+    query: String,
+    blocks: u64,
+}
+
+impl Explorer {
+    fn overview(&self) -> Result<String> {
+        let status = self.rpc_get("status").c(d!())?;
+        let height = status["sync_info"]["latest_block_height"]
+            .as_str()
+            .and_then(|h| h.parse::<u64>().ok())
+            .c(d!("invalid status"))?;
+        let mempool = self.rpc_get("num_unconfirmed_txs").c(d!())?;
+        let validators = self.rpc_get("validators?per_page=100").c(d!())?;
+
+        let mut p = format!(
+            "\x1b[31;01mNetwork\x1b[00m {}, height {}, catching up: {}\n",
+            status["node_info"]["network"].as_str().unwrap_or_default(),
+            height,
+            status["sync_info"]["catching_up"]
+        );
+        p += &format!(
+            "\x1b[31;01mMempool\x1b[00m {} transaction(s), {} bytes\n\n",
+            mempool["total"].as_str().unwrap_or("0"),
+            mempool["total_bytes"].as_str().unwrap_or("0")
+        );
+
+        p += "\x1b[31;01mLatest blocks\x1b[00m\n";
+        let from = height.saturating_sub(self.blocks - 1).max(1);
+        let chain = self
+            .rpc_get(&format!(
+                "blockchain?minHeight={}&maxHeight={}",
+                from, height
+            ))
+            .c(d!())?;
+        for meta in chain["block_metas"].as_array().into_iter().flatten() {
+            p += &format!(
+                "  {:>10}  {}  {:>4} tx(s)  proposer {}\n",
+                meta["header"]["height"].as_str().unwrap_or_default(),
+                meta["header"]["time"].as_str().unwrap_or_default(),
+                meta["num_txs"].as_str().unwrap_or("0"),
+                meta["header"]["proposer_address"]
+                    .as_str()
+                    .unwrap_or_default()
+            );
+        }
+
+        p += &format!(
+            "\n\x1b[31;01mValidators\x1b[00m {}\n",
+            validators["total"].as_str().unwrap_or_default()
+        );
+        for v in validators["validators"].as_array().into_iter().flatten() {
+            p += &format!(
+                "  {}  power {}\n",
+                v["address"].as_str().unwrap_or_default(),
+                v["voting_power"].as_str().unwrap_or_default()
+            );
+        }
+
+        Ok(p)
+    }
+
+    fn block(&self, height: u64) -> Result<String> {
+        let block = self.rpc_get(&format!("block?height={}", height)).c(d!())?;
+        let header = &block["block"]["header"];
+        let mut p = format!(
+            "\x1b[31;01mBlock\x1b[00m {}\n  hash: {}\n  time: {}\n  proposer: {}\n  app hash: {}\n",
+            height,
+            block["block_id"]["hash"].as_str().unwrap_or_default(),
+            header["time"].as_str().unwrap_or_default(),
+            header["proposer_address"].as_str().unwrap_or_default(),
+            header["app_hash"].as_str().unwrap_or_default()
+        );
+
+        // `txs` is `null` for an empty block
+        let txs = block["block"]["data"]["txs"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        p += &format!("  transactions: {}\n", txs.len());
+        for tx in txs.iter().filter_map(|tx| tx.as_str()) {
+            match inspect::decode_txn(tx) {
+                Ok(tx) => {
+                    let kinds = tx
+                        .operations
+                        .iter()
+                        .map(|op| op.kind.as_str())
+                        .collect::<Vec<_>>();
+                    p += &format!("    {}  {}\n", tx.hash, kinds.join(", "));
+                }
+                Err(_) => p += "    (EVM or unknown)\n",
+            }
+        }
+
+        Ok(p)
+    }
+
+    fn tx(&self, hash: &str) -> Result<String> {
+        let hash = hash.trim_start_matches("0x");
+        let tx = self.rpc_get(&format!("tx?hash=0x{}", hash)).c(d!())?;
+        let decoded = tx["tx"]
+            .as_str()
+            .c(d!("invalid transaction"))
+            .and_then(|tx| inspect::decode_txn(tx).c(d!()))?;
+        Ok(format!(
+            "{}\n  height: {}\n  result code: {} {}\n",
+            decoded,
+            tx["height"].as_str().unwrap_or_default(),
+            tx["tx_result"]["code"],
+            tx["tx_result"]["log"].as_str().unwrap_or_default()
+        ))
+    }
+
+    fn address(&self, addr: &str) -> Result<String> {
+        let pk = address::parse_address(addr).c(d!())?;
+        let utxos = utils::get_owned_utxos(&pk).c(d!())?;
+        let mut sids = self
+            .query_get::<HashSet<TxnSID>>(&format!(
+                "get_related_txns/{}",
+                wallet::public_key_to_base64(&pk)
+            ))
+            .c(d!())?
+            .into_iter()
+            .collect::<Vec<_>>();
+        sids.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let mut p = format!(
+            "\x1b[31;01mAddress\x1b[00m {}\n  unspent outputs: {}\n  related transactions: {}\n",
+            wallet::public_key_to_bech32(&pk),
+            utxos.len(),
+            sids.len()
+        );
+        for sid in sids.iter().take(MAX_ADDR_TXNS) {
+            let hash = self
+                .query_get::<String>(&format!("get_transaction_hash/{}", sid.0))
+                .unwrap_or_default();
+            p += &format!("    {:>10}  {}\n", sid.0, hash);
+        }
+        if MAX_ADDR_TXNS < sids.len() {
+            p += "    ...\n";
+        }
+
+        Ok(p)
+    }
+
+    // the `result` of a tendermint RPC
+    fn rpc_get(&self, path: &str) -> Result<Value> {
+        get::<Value>(&format!("{}/{}", self.rpc, path))
+            .c(d!())
+            .and_then(|mut v| {
+                if !v["error"].is_null() {
+                    return Err(eg!(v["error"].to_string()));
+                }
+                Ok(v["result"].take())
+            })
+    }
+
+    fn query_get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        get(&format!("{}/{}", self.query, path)).c(d!())
+    }
+}
+
+fn get<T: DeserializeOwned>(url: &str) -> Result<T> {
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
@@ -0,0 +1,386 @@
+//!
+//! Decode a serialized transaction into a human-readable breakdown,
+//! without any access to the network, so it can be checked before being signed.
+//!
+//! The transaction can be given:
+//! - in JSON, as sent to the submission server
+//! - in base64, as the bytes delivered to tendermint and returned by its RPC
+//! - wrapped in a JSON object with a `tx` field, eg. the files of `fn offline`
+//!
+
+use {
+    globutils::wallet,
+    ledger::data_model::{
+        AssetTypeCode, Operation, Transaction, TxOutput, TxoRef, ASSET_TYPE_FRA,
+    },
+    ruc::*,
+    serde::Serialize,
+    serde_json::Value,
+    std::{fmt, fs},
+    zei::xfr::{
+        sig::XfrPublicKey,
+        structs::{BlindAssetRecord, XfrAmount, XfrAssetType},
+    },
+};
+
+/// A decoded transaction.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedTxn {
+    /// the hash used by tendermint
+    pub hash: String,
+    #[allow(missing_docs)]
+    pub seq_id: u64,
+    /// the signers of the whole transaction
+    pub signers: Vec<Signer>,
+    /// the number of signatures without a public key
+    pub anonymous_signatures: usize,
+    #[allow(missing_docs)]
+    pub operations: Vec<DecodedOp>,
+    #[allow(missing_docs)]
+    pub memos: Vec<String>,
+    #[allow(missing_docs)]
+    pub credentials: usize,
+    #[allow(missing_docs)]
+    pub policy_options: Option<Value>,
+}
+
+/// A public key which has signed, or should sign, a transaction or an operation.
+#[derive(Clone, Debug, Serialize)]
+pub struct Signer {
+    /// in bech32 format
+    pub address: String,
+    /// whether the signature is valid, `None` if there is no signature to check
+    pub valid: Option<bool>,
+}
+
+/// A decoded operation.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedOp {
+    /// the name of the operation
+    pub kind: String,
+    #[allow(missing_docs)]
+    pub signers: Vec<Signer>,
+    #[allow(missing_docs)]
+    pub inputs: Vec<DecodedInput>,
+    #[allow(missing_docs)]
+    pub outputs: Vec<DecodedOutput>,
+    /// the tracing policies of a transfer, or the rules of a new asset
+    pub policies: Option<Value>,
+    /// the whole operation, for the kinds without inputs or outputs
+    pub details: Option<Value>,
+}
+
+/// An asset record, the amount or the type is `None` if confidential.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedRecord {
+    #[allow(missing_docs)]
+    pub owner: String,
+    #[allow(missing_docs)]
+    pub asset: Option<String>,
+    #[allow(missing_docs)]
+    pub amount: Option<u64>,
+}
+
+/// An input of a transfer.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedInput {
+    /// `sid:<N>`, `relative:<N>` or `handle:<handle>:<N>`
+    pub txo: String,
+    #[allow(missing_docs)]
+    pub record: DecodedRecord,
+}
+
+/// An output of a transfer or an issuance.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecodedOutput {
+    #[allow(missing_docs)]
+    pub record: DecodedRecord,
+    /// an encrypted memo is attached for the recipient
+    pub memo: bool,
+    /// an owner memo is attached, only in issuances
+    pub owner_memo: bool,
+    #[allow(missing_docs)]
+    pub lien: bool,
+}
+
+/// Decode the transaction in `tx`, see the module doc for the accepted formats.
+pub fn decode_txn(tx: &str) -> Result<DecodedTxn> {
+    parse_txn(tx).c(d!()).map(|tx| DecodedTxn::new(&tx))
+}
+
+/// Decode the transaction in the file at `path`.
+pub fn decode_txn_file(path: &str) -> Result<DecodedTxn> {
+    fs::read_to_string(path)
+        .c(d!(path.to_owned()))
+        .and_then(|tx| decode_txn(&tx).c(d!()))
+}
+
+/// Parse a transaction in any of the accepted formats.
+pub fn parse_txn(tx: &str) -> Result<Transaction> {
+    let tx = tx.trim();
+    let bytes = if tx.starts_with('{') {
+        tx.as_bytes().to_vec()
+    } else {
+        base64::decode(tx)
+            .or_else(|_| base64::decode_config(tx, base64::URL_SAFE))
+            .c(d!("neither JSON nor base64"))?
+    };
+
+    let v = serde_json::from_slice::<Value>(&bytes).c(d!())?;
+    let v = match v.get("tx") {
+        Some(inner) if v.get("body").is_none() => inner.clone(),
+        _ => v,
+    };
+    serde_json::from_value(v).c(d!("not a transaction"))
+}
+
+impl DecodedTxn {
+    #[allow(missing_docs)]
+    pub fn new(tx: &Transaction) -> Self {
+        let body = &tx.body;
+        DecodedTxn {
+            hash: hex::encode(tx.hash_tm_rawbytes()),
+            seq_id: body.no_replay_token.get_seq_id(),
+            signers: tx
+                .pubkey_sign_map
+                .iter()
+                .map(|(pk, sig)| signer(pk, Some(tx.check_signature(pk, sig).is_ok())))
+                .collect(),
+            anonymous_signatures: tx.signatures.len(),
+            operations: body.operations.iter().map(decode_op).collect(),
+            memos: body.memos.iter().map(|m| m.0.clone()).collect(),
+            credentials: body.credentials.len(),
+            policy_options: body
+                .policy_options
+                .as_ref()
+                .and_then(|p| serde_json::to_value(p).ok()),
+        }
+    }
+}
+
+fn decode_op(op: &Operation) -> DecodedOp {
+    // operations are externally tagged: `{"<kind>": {...}}`
+    let (kind, inner) = match serde_json::to_value(op) {
+        Ok(Value::Object(m)) if 1 == m.len() => m.into_iter().next().unwrap(),
+        _ => ("Unknown".to_owned(), Value::Null),
+    };
+    let mut res = DecodedOp {
+        kind,
+        signers: vec![],
+        inputs: vec![],
+        outputs: vec![],
+        policies: None,
+        details: None,
+    };
+
+    macro_rules! staking {
+        ($i: expr, verified) => {{
+            let valid = $i.verify().is_ok();
+            res.signers = keys_to_signers(&$i.get_related_pubkeys(), Some(valid));
+            res.details = Some(inner);
+        }};
+        ($i: expr) => {{
+            res.signers = keys_to_signers(&$i.get_related_pubkeys(), None);
+            res.details = Some(inner);
+        }};
+    }
+
+    match op {
+        Operation::TransferAsset(i) => {
+            res.signers = i
+                .body_signatures
+                .iter()
+                .map(|s| signer(&s.address.key, Some(s.verify(&i.body))))
+                .collect();
+            res.inputs = i
+                .body
+                .inputs
+                .iter()
+                .zip(i.body.transfer.inputs.iter())
+                .map(|(txo, r)| DecodedInput {
+                    txo: match txo {
+                        TxoRef::Absolute(sid) => format!("sid:{}", sid.0),
+                        TxoRef::Relative(n) => format!("relative:{}", n),
+                        TxoRef::Pending(p) => format!("handle:{}:{}", p.handle(), p.idx),
+                    },
+                    record: decode_record(r),
+                })
+                .collect();
+            res.outputs = i
+                .body
+                .outputs
+                .iter()
+                .map(|o| decode_output(o, false))
+                .collect();
+            res.policies = serde_json::to_value(&i.body.policies).ok();
+        }
+        Operation::IssueAsset(i) => {
+            let valid = i.signature.verify(&i.pubkey.key, &i.body).is_ok();
+            res.signers = vec![signer(&i.pubkey.key, Some(valid))];
+            res.outputs = i
+                .body
+                .records
+                .iter()
+                .map(|(o, om)| decode_output(o, om.is_some()))
+                .collect();
+            res.details = Some(serde_json::json!({
+                "code": asset_name(&i.body.code),
+                "seq_num": i.body.seq_num,
+                "vesting": i.body.vesting,
+            }));
+        }
+        Operation::DefineAsset(i) => {
+            let valid = i.signature.verify(&i.pubkey.key, &i.body).is_ok();
+            res.signers = vec![signer(&i.pubkey.key, Some(valid))];
+            let asset = &i.body.asset;
+            res.policies = serde_json::to_value(&asset.asset_rules).ok();
+            res.details = Some(serde_json::json!({
+                "code": asset_name(&asset.code),
+                "memo": asset.memo.0,
+            }));
+        }
+        Operation::UpdateMemo(i) => {
+            let valid = i.signature.verify(&i.pubkey, &i.body).is_ok();
+            res.signers = vec![signer(&i.pubkey, Some(valid))];
+            res.details = Some(inner);
+        }
+        Operation::UpdateStaker(i) => staking!(i, verified),
+        Operation::Delegation(i) => staking!(i, verified),
+        Operation::UnDelegation(i) => staking!(i, verified),
+        Operation::Claim(i) => staking!(i, verified),
+        Operation::ReplaceStaker(i) => staking!(i, verified),
+        Operation::Unjail(i) => staking!(i, verified),
+        Operation::UpdateValidator(i) => staking!(i),
+        Operation::Governance(i) => staking!(i),
+        Operation::FraDistribution(i) => staking!(i),
+        Operation::MintFra(i) => staking!(i),
+        // signed by the signers of the whole transaction
+        _ => res.details = Some(inner),
+    }
+
+    res
+}
+
+fn decode_record(r: &BlindAssetRecord) -> DecodedRecord {
+    DecodedRecord {
+        owner: wallet::public_key_to_bech32(&r.public_key),
+        asset: match r.asset_type {
+            XfrAssetType::NonConfidential(val) => {
+                Some(asset_name(&AssetTypeCode { val }))
+            }
+            XfrAssetType::Confidential(_) => None,
+        },
+        amount: match r.amount {
+            XfrAmount::NonConfidential(am) => Some(am),
+            XfrAmount::Confidential(_) => None,
+        },
+    }
+}
+
+fn decode_output(o: &TxOutput, owner_memo: bool) -> DecodedOutput {
+    DecodedOutput {
+        record: decode_record(&o.record),
+        memo: o.memo.is_some(),
+        owner_memo,
+        lien: o.lien.is_some(),
+    }
+}
+
+fn asset_name(code: &AssetTypeCode) -> String {
+    if ASSET_TYPE_FRA == code.val {
+        "FRA".to_owned()
+    } else {
+        code.to_base64()
+    }
+}
+
+fn signer(pk: &XfrPublicKey, valid: Option<bool>) -> Signer {
+    Signer {
+        address: wallet::public_key_to_bech32(pk),
+        valid,
+    }
+}
+
+fn keys_to_signers(pks: &[XfrPublicKey], valid: Option<bool>) -> Vec<Signer> {
+    pks.iter().map(|pk| signer(pk, valid)).collect()
+}
+
+impl fmt::Display for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = match self.valid {
+            Some(true) => "signed",
+            Some(false) => "INVALID SIGNATURE",
+            None => "unchecked",
+        };
+        write!(f, "{} ({})", self.address, v)
+    }
+}
+
+impl fmt::Display for DecodedRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let amount = self
+            .amount
+            .map(|am| am.to_string())
+            .unwrap_or_else(|| "<confidential amount>".to_owned());
+        let asset = self.asset.as_deref().unwrap_or("<confidential asset>");
+        write!(f, "{} {} to {}", amount, asset, self.owner)
+    }
+}
+
+impl fmt::Display for DecodedTxn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "\x1b[31;01mTransaction\x1b[00m {}", self.hash)?;
+        writeln!(f, "  seq id: {}", self.seq_id)?;
+        writeln!(f, "  signers:")?;
+        for s in self.signers.iter() {
+            writeln!(f, "    {}", s)?;
+        }
+        if 0 < self.anonymous_signatures {
+            writeln!(
+                f,
+                "    {} signature(s) without public keys",
+                self.anonymous_signatures
+            )?;
+        }
+        for m in self.memos.iter() {
+            writeln!(f, "  memo: {}", m)?;
+        }
+        if 0 < self.credentials {
+            writeln!(f, "  credentials: {}", self.credentials)?;
+        }
+        if let Some(p) = self.policy_options.as_ref() {
+            writeln!(f, "  policy options: {}", p)?;
+        }
+
+        for (idx, op) in self.operations.iter().enumerate() {
+            writeln!(f, "  \x1b[35;01m[{}] {}\x1b[00m", idx, op.kind)?;
+            for s in op.signers.iter() {
+                writeln!(f, "      signer: {}", s)?;
+            }
+            for i in op.inputs.iter() {
+                writeln!(f, "      input {}: {}", i.txo, i.record)?;
+            }
+            for o in op.outputs.iter() {
+                write!(f, "      output: {}", o.record)?;
+                if o.memo {
+                    write!(f, ", with a memo")?;
+                }
+                if o.owner_memo {
+                    write!(f, ", with an owner memo")?;
+                }
+                if o.lien {
+                    write!(f, ", with a lien")?;
+                }
+                writeln!(f)?;
+            }
+            if let Some(p) = op.policies.as_ref() {
+                writeln!(f, "      policies: {}", p)?;
+            }
+            if let Some(d) = op.details.as_ref() {
+                writeln!(f, "      {}", d)?;
+            }
+        }
+
+        Ok(())
+    }
+}
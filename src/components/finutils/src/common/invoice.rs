@@ -0,0 +1,511 @@
+//!
+//! # Invoices
+//!
+//! Invoices of a payee, saved in the config directory of `fn`, and shared
+//! as payment requests whose memo is the reference of the invoice.
+//!
+//! An invoice is settled by the transfers to the payee of its asset whose
+//! encrypted memo is its reference, `sync` finds them in the blocks committed
+//! since the last sync, the memos can only be decrypted by the payee.
+//!
+
+use {
+    super::{get_serv_addr, restore_keypair_from_str_with_default, utils, INVOICE_FILE},
+    globutils::{payment_request::PaymentRequest, wallet},
+    ledger::data_model::{AssetTypeCode, FinalizedBlock, Operation, ASSET_TYPE_FRA},
+    rand::RngCore,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    zei::xfr::{asset_record::open_blind_asset_record, sig::XfrKeyPair},
+};
+
+/// Max length of a reference, it must fit in an encrypted memo
+pub const MAX_INVOICE_REF_LEN: usize = 64;
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Open,
+    /// paid in part
+    Partial,
+    Settled,
+    Cancelled,
+}
+
+/// A transfer paying an invoice
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Payment {
+    #[allow(missing_docs)]
+    pub block_sid: usize,
+    #[allow(missing_docs)]
+    pub txn_sid: usize,
+    #[allow(missing_docs)]
+    pub amount: u64,
+}
+
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Invoice {
+    /// unique among the invoices, carried by the memos of the payments
+    pub reference: String,
+    /// in bech32
+    pub payee: String,
+    /// the asset code in base64, FRA if not set
+    pub asset: Option<String>,
+    pub amount: u64,
+    /// unix timestamps in seconds
+    pub created_at: u64,
+    pub expiry: Option<u64>,
+    pub status: InvoiceStatus,
+    pub payments: Vec<Payment>,
+}
+
+impl Invoice {
+    #[allow(missing_docs)]
+    pub fn paid(&self) -> u64 {
+        self.payments
+            .iter()
+            .fold(0u64, |acc, p| acc.saturating_add(p.amount))
+    }
+
+    /// The payment request to share with the payer.
+    pub fn to_request(&self) -> PaymentRequest {
+        PaymentRequest {
+            address: self.payee.clone(),
+            asset: self.asset.clone(),
+            amount: Some(self.amount.saturating_sub(self.paid())),
+            memo: Some(self.reference.clone()),
+            expiry: self.expiry,
+        }
+    }
+
+    #[inline(always)]
+    fn is_open(&self) -> bool {
+        matches!(self.status, InvoiceStatus::Open | InvoiceStatus::Partial)
+    }
+}
+
+/// The local invoices, and the next block to be synced
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct InvoiceBook {
+    #[allow(missing_docs)]
+    pub invoices: BTreeMap<String, Invoice>,
+    #[allow(missing_docs)]
+    pub next_block: Option<usize>,
+}
+
+impl InvoiceBook {
+    /// Record the payments to `kp` of the open invoices in the block `block_sid`,
+    /// the references of the invoices settled by them are returned.
+    pub fn apply_block(
+        &mut self,
+        kp: &XfrKeyPair,
+        block_sid: usize,
+        block: &FinalizedBlock,
+    ) -> Vec<String> {
+        let payee = wallet::public_key_to_bech32(kp.get_pk_ref());
+        let mut settled = vec![];
+
+        for ftx in block.txns.iter() {
+            let transfers = ftx.txn.body.operations.iter().filter_map(|op| match op {
+                Operation::TransferAsset(t) => Some(t),
+                _ => None,
+            });
+            for t in transfers {
+                for (i, o) in t.body.outputs.iter().enumerate() {
+                    if o.record.public_key != *kp.get_pk_ref() {
+                        continue;
+                    }
+                    let reference = match o
+                        .memo
+                        .as_ref()
+                        .and_then(|m| m.decrypt(kp).ok())
+                        .and_then(|m| String::from_utf8(m).ok())
+                    {
+                        Some(r) => r,
+                        None => continue,
+                    };
+                    let inv = match self
+                        .invoices
+                        .get_mut(&reference)
+                        .filter(|inv| inv.is_open() && inv.payee == payee)
+                    {
+                        Some(inv) => inv,
+                        None => continue,
+                    };
+                    let owner_memo =
+                        t.body.transfer.owners_memos.get(i).cloned().flatten();
+                    let oar = match open_blind_asset_record(&o.record, &owner_memo, kp) {
+                        Ok(oar) => oar,
+                        Err(_) => continue,
+                    };
+                    let asset = match inv.asset.as_deref() {
+                        Some(a) => match AssetTypeCode::new_from_base64(a) {
+                            Ok(code) => code.val,
+                            Err(_) => continue,
+                        },
+                        None => ASSET_TYPE_FRA,
+                    };
+                    if oar.asset_type != asset {
+                        continue;
+                    }
+
+                    inv.payments.push(Payment {
+                        block_sid,
+                        txn_sid: ftx.tx_id.0,
+                        amount: oar.amount,
+                    });
+                    if inv.paid() >= inv.amount {
+                        inv.status = InvoiceStatus::Settled;
+                        settled.push(reference);
+                    } else {
+                        inv.status = InvoiceStatus::Partial;
+                    }
+                }
+            }
+        }
+
+        settled
+    }
+}
+
+#[allow(missing_docs)]
+pub fn load() -> Result<InvoiceBook> {
+    match fs::read(&*INVOICE_FILE) {
+        Ok(b) => serde_json::from_slice(&b).c(d!()),
+        Err(_) => Ok(InvoiceBook::default()),
+    }
+}
+
+fn save(book: &InvoiceBook) -> Result<()> {
+    serde_json::to_vec_pretty(book)
+        .c(d!())
+        .and_then(|b| fs::write(&*INVOICE_FILE, b).c(d!()))
+}
+
+/// Create an invoice to the account of `sk_str`, a random reference is
+/// generated if not set, `expires_in` is in seconds.
+pub fn create(
+    sk_str: Option<&str>,
+    amount: u64,
+    asset: Option<&str>,
+    reference: Option<&str>,
+    expires_in: Option<u64>,
+) -> Result<Invoice> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+    let asset = asset
+        .filter(|a| a.to_uppercase() != "FRA")
+        .map(|a| {
+            AssetTypeCode::new_from_base64(a)
+                .c(d!("invalid asset code"))
+                .map(|_| a.to_owned())
+        })
+        .transpose()?;
+    if 0 == amount {
+        return Err(eg!("the amount must be positive"));
+    }
+
+    let mut book = load().c(d!())?;
+    let reference = match reference {
+        Some(r) => {
+            let r = r.trim();
+            if r.is_empty() || r.len() > MAX_INVOICE_REF_LEN {
+                return Err(eg!("invalid length of the reference"));
+            }
+            if book.invoices.contains_key(r) {
+                return Err(eg!(format!("invoice `{}` exists", r)));
+            }
+            r.to_owned()
+        }
+        None => loop {
+            let mut nonce = [0u8; 8];
+            rand::thread_rng().fill_bytes(&mut nonce);
+            let r = format!("inv-{}", hex::encode(nonce));
+            if !book.invoices.contains_key(&r) {
+                break r;
+            }
+        },
+    };
+
+    // the payments can not be in the blocks committed before
+    if !book.invoices.values().any(|inv| inv.is_open()) {
+        book.next_block = Some(utils::get_seq_id().c(d!())? as usize);
+    }
+
+    let now = now().c(d!())?;
+    let inv = Invoice {
+        reference: reference.clone(),
+        payee: wallet::public_key_to_bech32(kp.get_pk_ref()),
+        asset,
+        amount,
+        created_at: now,
+        expiry: expires_in.map(|secs| now + secs),
+        status: InvoiceStatus::Open,
+        payments: vec![],
+    };
+    book.invoices.insert(reference, inv.clone());
+    save(&book).c(d!())?;
+
+    Ok(inv)
+}
+
+#[allow(missing_docs)]
+pub fn get(reference: &str) -> Result<Invoice> {
+    load()
+        .c(d!())?
+        .invoices
+        .remove(reference)
+        .c(d!(format!("invoice `{}` not found", reference)))
+}
+
+/// Cancel an open invoice, its later payments are not recorded.
+pub fn cancel(reference: &str) -> Result<()> {
+    let mut book = load().c(d!())?;
+    let inv = book
+        .invoices
+        .get_mut(reference)
+        .c(d!(format!("invoice `{}` not found", reference)))?;
+    if !inv.is_open() {
+        return Err(eg!(format!("invoice `{}` is not open", reference)));
+    }
+    inv.status = InvoiceStatus::Cancelled;
+    save(&book).c(d!())
+}
+
+/// Record the payments in the blocks committed since the last sync,
+/// the references of the invoices settled by them are returned.
+pub fn sync(sk_str: Option<&str>) -> Result<Vec<String>> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+    let node = get_serv_addr().c(d!())?;
+    let mut book = load().c(d!())?;
+    if !book.invoices.values().any(|inv| inv.is_open()) {
+        return Ok(vec![]);
+    }
+
+    let mut next = book.next_block.unwrap_or(0);
+    let mut settled = vec![];
+    let res = loop {
+        match utils::get_block(node, next) {
+            Ok(Some(block)) => {
+                settled.extend(book.apply_block(&kp, next, &block));
+                next += 1;
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+
+    // keep the progress even if the node is gone in the middle
+    book.next_block = Some(next);
+    save(&book).c(d!())?;
+    res.c(d!()).map(|_| settled)
+}
+
+/// Print the invoices, the expired open ones are marked.
+pub fn show() -> Result<()> {
+    let now = now().c(d!())?;
+    for inv in load().c(d!())?.invoices.values() {
+        let expired = inv.is_open() && inv.expiry.map(|e| e < now).unwrap_or(false);
+        println!(
+            "{}: {}/{} {} to {}, {:?}{}",
+            inv.reference,
+            inv.paid(),
+            inv.amount,
+            inv.asset.as_deref().unwrap_or("FRA"),
+            inv.payee,
+            inv.status,
+            alt!(expired, " (expired)", "")
+        );
+    }
+    Ok(())
+}
+
+fn now() -> Result<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .c(d!())
+        .map(|d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::txn_builder::{TransactionBuilder, TransferOperationBuilder},
+        ledger::{
+            data_model::{
+                BlockSID, FinalizedTransaction, Transaction, TransferType, TxnEffect,
+                TxnSID, TxoRef, TxoSID,
+            },
+            store::{utils::fra_gen_initial_tx, LedgerState},
+        },
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        zei::xfr::{
+            asset_record::AssetRecordType,
+            structs::{AssetRecord, AssetRecordTemplate, AssetType},
+        },
+    };
+
+    // a transfer of `amount` to `payee`, its memo is `reference`,
+    // only its outputs matter
+    fn pay(
+        prng: &mut ChaChaRng,
+        payer: &XfrKeyPair,
+        payee: &XfrKeyPair,
+        asset: AssetType,
+        amount: u64,
+        reference: &str,
+    ) -> Transaction {
+        let template = |owner: &XfrKeyPair| {
+            AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                asset,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                owner.get_pk(),
+            )
+        };
+        let input = pnk!(AssetRecord::from_template_no_identity_tracing(
+            prng,
+            &template(payer)
+        ));
+        let mut op = TransferOperationBuilder::new();
+        pnk!(op.add_input(
+            TxoRef::Absolute(TxoSID(0)),
+            input.open_asset_record,
+            None,
+            None,
+            amount
+        ));
+        pnk!(op.add_output(&template(payee), None, None, None));
+        pnk!(op.attach_memo(0, reference.as_bytes()));
+        let op = pnk!(op
+            .create(TransferType::Standard)
+            .c(d!())
+            .and_then(|b| b.sign(payer).c(d!()))
+            .and_then(|b| b.transaction().c(d!())));
+
+        let mut builder = TransactionBuilder::from_seq_id(0);
+        builder.add_operation(op).sign(payer);
+        builder.take_transaction()
+    }
+
+    // only the transactions of the block are looked at
+    fn block(ledger: &LedgerState, txns: Vec<Transaction>) -> FinalizedBlock {
+        let mut b = ledger.get_block(BlockSID(0)).unwrap().block;
+        b.txns = txns
+            .into_iter()
+            .enumerate()
+            .map(|(i, txn)| FinalizedTransaction {
+                txn,
+                tx_id: TxnSID(i),
+                txo_ids: vec![],
+                merkle_id: 0,
+            })
+            .collect();
+        b
+    }
+
+    #[test]
+    fn invoice_settlement() {
+        let mut prng = ChaChaRng::from_entropy();
+        let payer = XfrKeyPair::generate(&mut prng);
+        let payee = XfrKeyPair::generate(&mut prng);
+        let other = XfrKeyPair::generate(&mut prng);
+        let code = AssetTypeCode::gen_random_with_rng(&mut prng);
+
+        let mut ledger = LedgerState::tmp_ledger();
+        let effect = pnk!(TxnEffect::compute_effect(fra_gen_initial_tx(&payer)));
+        let mut b = pnk!(ledger.start_block());
+        pnk!(ledger.apply_transaction(&mut b, effect));
+        pnk!(ledger.finish_block(b));
+
+        let mut book = InvoiceBook::default();
+        for (reference, asset) in [
+            ("partial", None),
+            ("over", None),
+            ("asset", Some(code.to_base64())),
+            ("cancelled", None),
+        ] {
+            book.invoices.insert(
+                reference.to_owned(),
+                Invoice {
+                    reference: reference.to_owned(),
+                    payee: wallet::public_key_to_bech32(payee.get_pk_ref()),
+                    asset,
+                    amount: 100,
+                    created_at: 0,
+                    expiry: None,
+                    status: InvoiceStatus::Open,
+                    payments: vec![],
+                },
+            );
+        }
+        book.invoices.get_mut("cancelled").unwrap().status = InvoiceStatus::Cancelled;
+        let status = |book: &InvoiceBook, r: &str| {
+            let inv = &book.invoices[r];
+            (inv.status, inv.paid())
+        };
+
+        let b = block(
+            &ledger,
+            vec![
+                pay(&mut prng, &payer, &payee, ASSET_TYPE_FRA, 40, "partial"),
+                pay(&mut prng, &payer, &payee, ASSET_TYPE_FRA, 150, "over"),
+                // in another asset than the one of the invoice
+                pay(&mut prng, &payer, &payee, ASSET_TYPE_FRA, 100, "asset"),
+                pay(&mut prng, &payer, &payee, ASSET_TYPE_FRA, 100, "cancelled"),
+                // to another key, the memo can not be decrypted by the payee
+                pay(&mut prng, &payer, &other, ASSET_TYPE_FRA, 100, "partial"),
+                // an unknown reference
+                pay(&mut prng, &payer, &payee, ASSET_TYPE_FRA, 100, "unknown"),
+            ],
+        );
+        assert_eq!(vec!["over".to_owned()], book.apply_block(&payee, 1, &b));
+        assert_eq!((InvoiceStatus::Partial, 40), status(&book, "partial"));
+        assert_eq!((InvoiceStatus::Settled, 150), status(&book, "over"));
+        assert_eq!((InvoiceStatus::Open, 0), status(&book, "asset"));
+        assert_eq!((InvoiceStatus::Cancelled, 0), status(&book, "cancelled"));
+        assert!(!book.invoices.contains_key("unknown"));
+        assert_eq!(
+            vec![Payment {
+                block_sid: 1,
+                txn_sid: 0,
+                amount: 40
+            }],
+            book.invoices["partial"].payments
+        );
+
+        let b = block(
+            &ledger,
+            vec![
+                pay(&mut prng, &payer, &payee, code.val, 50, "partial"),
+                pay(&mut prng, &payer, &payee, ASSET_TYPE_FRA, 60, "partial"),
+                // the settled ones are left alone
+                pay(&mut prng, &payer, &payee, ASSET_TYPE_FRA, 10, "over"),
+                pay(&mut prng, &payer, &payee, code.val, 100, "asset"),
+            ],
+        );
+        assert_eq!(
+            vec!["partial".to_owned(), "asset".to_owned()],
+            book.apply_block(&payee, 2, &b)
+        );
+        assert_eq!((InvoiceStatus::Settled, 100), status(&book, "partial"));
+        assert_eq!(
+            vec![(1, 0, 40), (2, 1, 60)],
+            book.invoices["partial"]
+                .payments
+                .iter()
+                .map(|p| (p.block_sid, p.txn_sid, p.amount))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!((InvoiceStatus::Settled, 150), status(&book, "over"));
+        assert_eq!((InvoiceStatus::Settled, 100), status(&book, "asset"));
+    }
+}
@@ -0,0 +1,59 @@
+//!
+//! Small data blobs anchored on chain under keys owned by the current key.
+//!
+//! Each operation pays the minimal fee, plus `KV_FEE_PER_KB`
+//! for each started KB of its key and value.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    ledger::{
+        converter::kv::{kv_fee, KvAction},
+        data_model::{AuthenticatedKvValue, BLACK_HOLE_PUBKEY},
+    },
+    ruc::*,
+};
+
+/// Put, update or delete `key`, values are created by `put`
+/// and can only be changed by their owners.
+pub fn send(key: &str, action: KvAction) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+
+    let fee = kv_fee(key, &action);
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_kv(&kp, key.to_owned(), action);
+    utils::gen_transfer_op(
+        &kp,
+        vec![(&BLACK_HOLE_PUBKEY, fee)],
+        None,
+        false,
+        false,
+        None,
+    )
+    .c(d!())
+    .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Query the entry of `key` with its proof, the proof is checked
+/// against the state commitment it carries.
+pub fn get_value(key: &str) -> Result<AuthenticatedKvValue> {
+    let url = format!("{}:8668/kv_value", get_serv_addr().c(d!())?);
+    let v: AuthenticatedKvValue = attohttpc::get(&url)
+        .param("key", key)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))?;
+    if !v.is_valid(v.state_commitment.clone()) {
+        return Err(eg!("invalid proof"));
+    }
+    Ok(v)
+}
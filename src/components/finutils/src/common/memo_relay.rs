@@ -0,0 +1,118 @@
+//!
+//! Owner memos delivered through the memo relay of a query server,
+//! instead of exchanging them out of band.
+//!
+//! Memos fetched from the relay are used to open the owned outputs
+//! whose transactions carry no memo.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr},
+    crate::api::{memo_relay_key, RelayedMemo},
+    ledger::data_model::{AssetTypeCode, TxoSID, UnAuthenticatedUtxo},
+    ruc::*,
+    std::fs,
+    zei::xfr::{
+        asset_record::open_blind_asset_record,
+        sig::XfrPublicKey,
+        structs::{BlindAssetRecord, OwnerMemo},
+    },
+};
+
+/// Post the owner memo in `memo_path`, in JSON, of the output `txo_sid` to its owner.
+pub fn post(recipient: &XfrPublicKey, txo_sid: u64, memo_path: &str) -> Result<()> {
+    let memo = fs::read_to_string(memo_path)
+        .c(d!(memo_path))
+        .and_then(|m| serde_json::from_str::<OwnerMemo>(&m).c(d!()))?;
+    relay_memo(
+        recipient,
+        &RelayedMemo {
+            txo_sid: TxoSID(txo_sid),
+            memo,
+        },
+    )
+    .c(d!())
+}
+
+/// List the memos relayed to the current key,
+/// with the amounts and asset types they open.
+pub fn fetch() -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    for m in get_relayed_memos(kp.get_pk_ref()).c(d!())? {
+        let opened = get_record(m.txo_sid)
+            .c(d!())?
+            .map(|r| open_blind_asset_record(&r, &Some(m.memo), &kp).c(d!()))
+            .transpose()?;
+        match opened {
+            Some(oar) => println!(
+                "txo {}: {} of {}",
+                m.txo_sid.0,
+                oar.amount,
+                AssetTypeCode {
+                    val: oar.asset_type
+                }
+                .to_base64()
+            ),
+            None => println!("txo {}: spent", m.txo_sid.0),
+        }
+    }
+    Ok(())
+}
+
+/// Relay an owner memo to the owner of its output.
+pub fn relay_memo(recipient: &XfrPublicKey, memo: &RelayedMemo) -> Result<()> {
+    let url = format!(
+        "{}:8668/memo_relay/{}",
+        get_serv_addr().c(d!())?,
+        memo_relay_key(recipient)
+    );
+    attohttpc::post(&url)
+        .json(memo)
+        .c(d!())?
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())
+        .map(|_| ())
+}
+
+/// The memos relayed to `owner`.
+pub fn get_relayed_memos(owner: &XfrPublicKey) -> Result<Vec<RelayedMemo>> {
+    get_relayed_memos_x(None, owner).c(d!())
+}
+
+pub(super) fn get_relayed_memos_x(
+    rpc_endpoint: Option<&str>,
+    owner: &XfrPublicKey,
+) -> Result<Vec<RelayedMemo>> {
+    let default_endpoint = format!("{}:8668", get_serv_addr().c(d!())?);
+    let url = format!(
+        "{}/memo_relay/{}",
+        rpc_endpoint.unwrap_or(default_endpoint.as_str()),
+        memo_relay_key(owner)
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+// `None` if the TXO has been spent
+fn get_record(sid: TxoSID) -> Result<Option<BlindAssetRecord>> {
+    let url = format!("{}:8668/utxo_sid_light/{}", get_serv_addr().c(d!())?, sid.0);
+    let resp = attohttpc::get(&url).send().c(d!())?;
+    if resp.status() == attohttpc::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    resp.error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<UnAuthenticatedUtxo>(&b).c(d!()))
+        .map(|u| Some(u.utxo.0.record))
+}
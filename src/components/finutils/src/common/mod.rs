@@ -6,27 +6,62 @@
 //! This module is the library part of FN.
 //!
 
+pub mod auditor;
+pub mod checksum;
+pub mod deposit;
+pub mod designated_transfer;
 pub mod dev;
 pub mod evm;
+pub mod explorer;
+pub mod inspect;
+pub mod invoice;
+pub mod kv;
+pub mod memo_relay;
+pub mod node;
+pub mod notary;
+pub mod notify;
+pub mod offline;
+pub mod payment_request;
+pub mod payment_stream;
+pub mod prism;
+pub mod pull_payment;
+pub mod session_key;
+pub mod signing_log;
+pub mod spending_policy;
+pub mod swap;
+pub mod sync;
+pub mod template;
+pub mod txo_label;
 pub mod utils;
+pub mod vesting;
+pub mod viewing;
 
 use {
-    crate::api::DelegationInfo,
-    globutils::wallet,
+    crate::{
+        api::{DelegationInfo, ValidatorDetail},
+        txn_builder::{build_sweep_op, plan_sweep, SeqTracker},
+    },
+    credentials::CredIssuerPublicKey,
+    globutils::{address, wallet},
     lazy_static::lazy_static,
     ledger::{
         data_model::{
-            gen_random_keypair, AssetRules, AssetTypeCode, Transaction,
-            BLACK_HOLE_PUBKEY_STAKING,
+            gen_random_keypair, AssetMetadata, AssetRules, AssetTypeCode, CancelTxn,
+            CancelTxnBody, Transaction, TxoRef, ASSET_TYPE_FRA,
+            BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
         },
         staking::{
             check_delegation_amount, td_addr_to_bytes, td_pubkey_to_td_addr,
             td_pubkey_to_td_addr_bytes, PartialUnDelegation, StakerMemo,
             TendermintAddrRef,
         },
+        store::export::{self, LedgerExport},
     },
     ruc::*,
-    std::{env, fs},
+    std::{
+        collections::{HashMap, VecDeque},
+        env, fs,
+    },
     tendermint::PrivateKey,
     utils::{
         get_block_height, get_local_block_height, get_validator_detail,
@@ -35,7 +70,7 @@ use {
     zei::{
         setup::PublicParams,
         xfr::{
-            asset_record::AssetRecordType,
+            asset_record::{open_blind_asset_record, AssetRecordType},
             sig::{XfrKeyPair, XfrPublicKey, XfrSecretKey},
         },
     },
@@ -54,6 +89,13 @@ lazy_static! {
     static ref TD_KEY_FILE: String = format!("{}/tendermint_keys", &*CFG_PATH);
     static ref SERV_ADDR: Option<String> = fs::read_to_string(&*SERV_ADDR_FILE).ok();
     static ref SERV_ADDR_FILE: String = format!("{}/serv_addr", &*CFG_PATH);
+    static ref CHANGE_IDX_FILE: String = format!("{}/change_index", &*CFG_PATH);
+    static ref TEMPLATE_DIR: String = format!("{}/templates", &*CFG_PATH);
+    static ref TXO_LABEL_FILE: String = format!("{}/txo_labels.json", &*CFG_PATH);
+    static ref INVOICE_FILE: String = format!("{}/invoices.json", &*CFG_PATH);
+    static ref DEPOSIT_FILE: String = format!("{}/deposits.json", &*CFG_PATH);
+    static ref SIGNING_LOG_FILE: String = format!("{}/signing_log.jsonl", &*CFG_PATH);
+    static ref KEY_NICKNAME_FILE: String = format!("{}/key_nicknames.json", &*CFG_PATH);
 }
 
 /// Updating the information of a staker includes commission_rate and staker_memo
@@ -267,6 +309,24 @@ pub fn claim(am: Option<&str>, sk_str: Option<&str>) -> Result<()> {
     utils::send_tx(&tx).c(d!())
 }
 
+/// Bring the validator of the current key back to tendermint,
+/// once jailed for a self-delegation below the minimum.
+pub fn unjail(sk_str: Option<&str>) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+
+    utils::gen_fee_op(&kp).c(d!()).map(|op| {
+        builder.add_operation(op);
+        builder.add_operation_unjail(&kp);
+    })?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())
+}
+
 /// Show information of current node, including following sections:
 ///     Server URL
 ///     Findora Wallet Address
@@ -354,6 +414,160 @@ pub fn show(basic: bool) -> Result<()> {
     }
 }
 
+/// Show the operating status of a validator, including following sections:
+///     Signing performance
+///     Missed blocks
+///     Voting power
+///     Pending rewards (if the validator is the local one)
+///     Delegation inflows (in the watch mode)
+///
+/// The local validator is used if `td_addr` is not specified,
+/// the status is refreshed every `watch_itv` seconds if it is specified.
+pub fn staker_status(td_addr: Option<&str>, watch_itv: Option<u64>) -> Result<()> {
+    let td_addr = match td_addr {
+        Some(addr) => addr.to_uppercase(),
+        None => get_td_pubkey()
+            .c(d!())
+            .map(|pk| td_pubkey_to_td_addr(&pk))?,
+    };
+    let is_local = get_td_pubkey()
+        .map(|pk| td_pubkey_to_td_addr(&pk) == td_addr)
+        .unwrap_or(false);
+
+    let itv = match watch_itv {
+        Some(itv) => itv.max(1),
+        None => {
+            return StakerStatus::new(&td_addr)
+                .c(d!())?
+                .render(is_local)
+                .c(d!());
+        }
+    };
+
+    let mut status = StakerStatus::new(&td_addr).c(d!())?;
+    loop {
+        // clear the screen
+        print!("\x1b[2J\x1b[H");
+        ruc::info_omit!(status.render(is_local));
+        println!("\nRefresh every {} seconds, press Ctrl-C to exit.", itv);
+
+        sleep_ms!(itv * 1000);
+        ruc::info_omit!(status.refresh());
+    }
+}
+
+// at most so many blocks will be checked in one refreshing
+const MISSED_BLOCKS_CHECK_MAX: u64 = 100;
+
+struct StakerStatus {
+    td_addr: String,
+    // the first sample, used to calculate the changes in the watch mode
+    initial: ValidatorDetail,
+    latest: ValidatorDetail,
+    // the latest height whose signatures have been checked
+    checked_height: u64,
+    // blocks checked and missed in the watch mode
+    checked_cnt: u64,
+    missed: Vec<u64>,
+}
+
+impl StakerStatus {
+    fn new(td_addr: &str) -> Result<Self> {
+        let detail = get_validator_detail(td_addr).c(d!("Validator not found"))?;
+        Ok(Self {
+            td_addr: td_addr.to_owned(),
+            checked_height: detail.cur_height.saturating_sub(1),
+            initial: detail.clone(),
+            latest: detail,
+            checked_cnt: 0,
+            missed: vec![],
+        })
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        self.latest = get_validator_detail(&self.td_addr).c(d!())?;
+
+        // the signatures of the latest block are not available yet
+        let end = self.latest.cur_height.saturating_sub(1);
+        let start =
+            (1 + self.checked_height).max(end.saturating_sub(MISSED_BLOCKS_CHECK_MAX));
+        for h in start..=end {
+            let signers = utils::get_block_signers(h).c(d!())?;
+            if !signers.contains(&self.td_addr) {
+                self.missed.push(h);
+            }
+            self.checked_cnt += 1;
+            self.checked_height = h;
+        }
+
+        Ok(())
+    }
+
+    fn render(&self, is_local: bool) -> Result<()> {
+        let d = &self.latest;
+
+        println!(
+            "\x1b[31;01mValidator Node Addr:\x1b[00m\n{} ({})\n",
+            &self.td_addr,
+            alt!(d.is_online, "online", "offline")
+        );
+
+        let total = d.cur_height.saturating_sub(d.start_height);
+        println!(
+            "\x1b[31;01mSigning Performance:\x1b[00m\n{}/{} blocks signed ({:.2}%), {} blocks proposed\n",
+            d.block_signed_cnt,
+            total,
+            alt!(0 == total, 0.0, d.block_signed_cnt as f64 * 100.0 / total as f64),
+            d.block_proposed_cnt
+        );
+
+        println!(
+            "\x1b[31;01mMissed Blocks:\x1b[00m\n{} in total",
+            total.saturating_sub(d.block_signed_cnt)
+        );
+        if 0 < self.checked_cnt {
+            println!(
+                "{}/{} since watching, latest: {:?}",
+                self.missed.len(),
+                self.checked_cnt,
+                self.missed.iter().rev().take(10).collect::<Vec<_>>()
+            );
+        }
+        println!();
+
+        println!(
+            "\x1b[31;01mVoting Power:\x1b[00m\n{} FRA units, rank {}, self-staking {} FRA units\n",
+            d.voting_power, d.voting_power_rank, d.self_staking
+        );
+
+        if is_local {
+            let rewards = get_keypair()
+                .c(d!())
+                .and_then(|kp| utils::get_delegation_info(kp.get_pk_ref()).c(d!()))
+                .map(|di| di.rewards);
+            ruc::info!(rewards).map(|i| {
+                println!("\x1b[31;01mPending Rewards:\x1b[00m\n{} FRA units\n", i);
+            })?;
+        }
+
+        println!(
+            "\x1b[31;01mDelegations:\x1b[00m\n{} delegators, commission rate {}/{}",
+            d.delegator_cnt, d.commission_rate[0], d.commission_rate[1]
+        );
+        if d.cur_height > self.initial.cur_height {
+            println!(
+                "inflows since watching: {:+} FRA units, {:+} delegators",
+                d.voting_power as i128 - self.initial.voting_power as i128,
+                d.delegator_cnt as i128 - self.initial.delegator_cnt as i128
+            );
+        }
+
+        println!("\n\x1b[31;01mBlock Height:\x1b[00m\n{}", d.cur_height);
+
+        Ok(())
+    }
+}
+
 /// Setup for a cli tool
 ///    Server URL
 ///    Owner mnemonic path
@@ -396,6 +610,7 @@ pub fn setup(
 }
 
 #[allow(missing_docs)]
+#[allow(clippy::too_many_arguments)]
 pub fn transfer_asset(
     owner_sk: Option<&str>,
     target_addr: XfrPublicKey,
@@ -403,6 +618,8 @@ pub fn transfer_asset(
     am: &str,
     confidential_am: bool,
     confidential_ty: bool,
+    fresh_change: bool,
+    memo: Option<&str>,
 ) -> Result<()> {
     transfer_asset_batch(
         owner_sk,
@@ -411,6 +628,8 @@ pub fn transfer_asset(
         am,
         confidential_am,
         confidential_ty,
+        fresh_change,
+        memo,
     )
     .c(d!())
 }
@@ -435,7 +654,8 @@ pub fn transfer_asset_x(
     .c(d!())
 }
 
-#[allow(missing_docs)]
+/// @memo: a message encrypted to every receiver, see `EncryptedMemo`
+#[allow(clippy::too_many_arguments)]
 pub fn transfer_asset_batch(
     owner_sk: Option<&str>,
     target_addr: &[XfrPublicKey],
@@ -443,17 +663,77 @@ pub fn transfer_asset_batch(
     am: &str,
     confidential_am: bool,
     confidential_ty: bool,
+    fresh_change: bool,
+    memo: Option<&str>,
 ) -> Result<()> {
     let from = restore_keypair_from_str_with_default(owner_sk)?;
     let am = am.parse::<u64>().c(d!("'amount' must be an integer"))?;
 
-    transfer_asset_batch_x(
+    let change_kp = if fresh_change {
+        if owner_sk.is_some() {
+            return Err(eg!(
+                "fresh keys can only be derived from the owner mnemonic"
+            ));
+        }
+        Some(gen_change_keypair().c(d!())?)
+    } else {
+        None
+    };
+
+    utils::transfer_batch_x(
         &from,
-        target_addr,
+        target_addr.iter().map(|addr| (addr, am)).collect(),
         token_code,
-        am,
         confidential_am,
         confidential_ty,
+        change_kp.as_ref().map(|kp| kp.get_pk_ref()),
+        memo.map(|m| m.as_bytes()),
+    )
+    .c(d!())
+}
+
+/// Transfer several asset types to `target_addr` atomically in one operation
+///
+/// @assets: (asset code, amount), FRA if the code is `None`
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_multi_asset(
+    owner_sk: Option<&str>,
+    target_addr: XfrPublicKey,
+    assets: &[(Option<AssetTypeCode>, &str)],
+    confidential_am: bool,
+    confidential_ty: bool,
+    fresh_change: bool,
+    memo: Option<&str>,
+) -> Result<()> {
+    let from = restore_keypair_from_str_with_default(owner_sk)?;
+    let assets = assets
+        .iter()
+        .map(|(code, am)| {
+            am.parse::<u64>()
+                .c(d!("'amount' must be an integer"))
+                .map(|am| (*code, am))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let change_kp = if fresh_change {
+        if owner_sk.is_some() {
+            return Err(eg!(
+                "fresh keys can only be derived from the owner mnemonic"
+            ));
+        }
+        Some(gen_change_keypair().c(d!())?)
+    } else {
+        None
+    };
+
+    utils::transfer_multi_asset(
+        &from,
+        &target_addr,
+        &assets,
+        confidential_am,
+        confidential_ty,
+        change_kp.as_ref().map(|kp| kp.get_pk_ref()),
+        memo.map(|m| m.as_bytes()),
     )
     .c(d!())
 }
@@ -477,6 +757,50 @@ pub fn transfer_asset_batch_x(
     .c(d!())
 }
 
+/// Derive a fresh key from the HD tree of the owner mnemonic to receive changes,
+/// the path is "m/44'/917'/0'/1/{index}", and the index increases after each derivation,
+/// so all changes can be recovered from the mnemonic.
+// the mnemonic of `fn setup --owner-mnemonic-path`
+fn get_mnemonic() -> Result<String> {
+    MNEMONIC
+        .as_ref()
+        .c(d!("'owner-mnemonic-path' has not been set"))
+        .and_then(|m_path| {
+            fs::read_to_string(m_path)
+                .c(d!("can not read mnemonic from 'owner-mnemonic-path'"))
+        })
+}
+
+fn gen_change_keypair() -> Result<XfrKeyPair> {
+    const FRA: u32 = 917;
+
+    let phrase = get_mnemonic().c(d!())?;
+    let idx = fs::read_to_string(&*CHANGE_IDX_FILE)
+        .ok()
+        .and_then(|i| i.trim().parse::<u32>().ok())
+        .unwrap_or(0);
+
+    let kp = wallet::restore_keypair_from_mnemonic_bip44(
+        phrase.trim(),
+        "en",
+        &wallet::BipPath::new(FRA, 0, 1, idx),
+    )
+    .c(d!("fresh keys can only be derived from a mnemonic"))?;
+
+    // never reuse a change address, even if the transaction fails
+    fs::write(&*CHANGE_IDX_FILE, (1 + idx).to_string())
+        .c(d!("fail to cache the change index"))?;
+
+    println!(
+        "\x1b[31;01mChange Address:\x1b[00m\n{} (m/44'/{}'/0'/1/{})\n",
+        wallet::public_key_to_bech32(kp.get_pk_ref()),
+        FRA,
+        idx
+    );
+
+    Ok(kp)
+}
+
 /// Mainly for official usage,
 /// and can be also used in test scenes.
 pub fn set_initial_validators() -> Result<()> {
@@ -497,12 +821,7 @@ pub fn get_keypair() -> Result<XfrKeyPair> {
     if let Some(m_path) = MNEMONIC.as_ref() {
         fs::read_to_string(m_path)
             .c(d!("can not read mnemonic from 'owner-mnemonic-path'"))
-            .and_then(|m| {
-                let k = m.trim();
-                wallet::restore_keypair_from_mnemonic_default(k)
-                    .c(d!("invalid 'owner-mnemonic'"))
-                    .or_else(|e| wallet::restore_keypair_from_seckey_base64(k).c(d!(e)))
-            })
+            .and_then(|m| address::parse_keypair(&m).c(d!("invalid 'owner-mnemonic'")))
     } else {
         Err(eg!("'owner-mnemonic-path' has not been set"))
     }
@@ -595,6 +914,139 @@ pub fn show_account(sk_str: Option<&str>, asset: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+/// Decrypt the memos attached to the unspent outputs of a findora account
+pub fn show_memos(sk_str: Option<&str>) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let mut utxos = utils::get_owned_utxos(kp.get_pk_ref())
+        .c(d!())?
+        .into_iter()
+        .collect::<Vec<_>>();
+    utxos.sort_by_key(|(sid, _)| sid.0);
+
+    for (sid, (utxo, _)) in utxos {
+        if let Some(memo) = utxo.0.memo.as_ref() {
+            match memo.decrypt(&kp) {
+                Ok(msg) => match String::from_utf8(msg) {
+                    Ok(s) => println!("txo {}: {}", sid.0, s),
+                    Err(e) => println!("txo {}: 0x{}", sid.0, hex::encode(e.as_bytes())),
+                },
+                Err(e) => println!("txo {}: {}", sid.0, e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the transactions of a sweep stay well below the default `max_tx_bytes` of tendermint
+const SWEEP_MAX_TX_SIZE: usize = 512 * 1024;
+
+/// Consolidate the small outputs of an asset of a findora account into a few ones,
+/// with one transaction per `max_inputs` outputs, each paying the fee.
+///
+/// With `dry_run`, the transactions are built and reported, but not sent.
+pub fn sweep(
+    sk_str: Option<&str>,
+    asset: Option<&str>,
+    max_inputs: usize,
+    dry_run: bool,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let asset_type = asset
+        .map(|asset| AssetTypeCode::new_from_base64(asset).c(d!("Invalid asset code")))
+        .transpose()?
+        .map(|code| code.val)
+        .unwrap_or(ASSET_TYPE_FRA);
+
+    let mut swept = HashMap::new();
+    let mut fee_inputs = vec![];
+    let frozen = txo_label::get_frozen().c(d!())?;
+    for (sid, (utxo, owner_memo)) in utils::get_owned_utxos(kp.get_pk_ref()).c(d!())? {
+        if frozen.contains(&sid) {
+            continue;
+        }
+        let oar = open_blind_asset_record(&utxo.0.record, &owner_memo, &kp).c(d!())?;
+        if oar.asset_type == asset_type {
+            swept.insert(sid, oar);
+        } else if ASSET_TYPE_FRA == oar.asset_type && TX_FEE_MIN <= oar.amount {
+            fee_inputs.push((sid, oar));
+        }
+    }
+    // the largest FRA outputs pay the fees of the other assets
+    fee_inputs.sort_by_key(|(_, oar)| oar.amount);
+
+    let mut batches = plan_sweep(
+        swept.iter().map(|(sid, oar)| (*sid, oar.amount)).collect(),
+        max_inputs,
+    )
+    .into_iter()
+    .collect::<VecDeque<_>>();
+    if batches.is_empty() {
+        println!("Nothing to sweep: {} output(s)", swept.len());
+        return Ok(());
+    }
+
+    let mut txs = vec![];
+    let mut cnt = 0;
+    while let Some(batch) = batches.pop_front() {
+        let inputs = batch
+            .iter()
+            .map(|sid| (TxoRef::Absolute(*sid), swept[sid].clone()))
+            .collect::<Vec<_>>();
+        let fee_input = if ASSET_TYPE_FRA == asset_type {
+            None
+        } else {
+            let (sid, oar) = fee_inputs
+                .pop()
+                .c(d!("insufficient FRA outputs to pay the fees"))?;
+            Some((TxoRef::Absolute(sid), oar))
+        };
+
+        let op = build_sweep_op(&kp, inputs, fee_input.clone()).c(d!())?;
+        let size = serde_json::to_vec(&op).c(d!())?.len();
+        if SWEEP_MAX_TX_SIZE < size && 3 < batch.len() {
+            // too large, retry with the halves of the batch
+            let (a, b) = batch.split_at(batch.len() / 2);
+            batches.push_front(b.to_vec());
+            batches.push_front(a.to_vec());
+            if let Some((TxoRef::Absolute(sid), oar)) = fee_input {
+                fee_inputs.push((sid, oar));
+            }
+            continue;
+        }
+
+        let amount = batch.iter().map(|sid| swept[sid].amount).sum::<u64>();
+        println!(
+            "tx {}: {} outputs, {} in total, {} bytes",
+            txs.len(),
+            batch.len(),
+            amount,
+            size
+        );
+        cnt += batch.len();
+        txs.push(op);
+    }
+    println!(
+        "{} output(s) swept by {} transaction(s), {} FRA units in fees",
+        cnt,
+        txs.len(),
+        txs.len() as u64 * TX_FEE_MIN
+    );
+
+    if dry_run {
+        return Ok(());
+    }
+    for op in txs {
+        let mut builder = utils::new_tx_builder().c(d!())?;
+        builder.add_operation(op);
+        let mut tx = builder.take_transaction();
+        tx.sign_to_map(&kp);
+        utils::send_tx(&tx).c(d!())?;
+    }
+
+    Ok(())
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn delegate(sk_str: Option<&str>, amount: u64, validator: &str) -> Result<()> {
@@ -611,6 +1063,15 @@ pub fn delegate_x(kp: &XfrKeyPair, amount: u64, validator: &str) -> Result<()> {
         .and_then(|tx| utils::send_tx(&tx).c(d!()))
 }
 
+/// Delegate to several validators in one transaction,
+/// `targets` is in the form of `(validator, amount)`.
+pub fn delegate_multi(sk_str: Option<&str>, targets: &[(&str, u64)]) -> Result<()> {
+    restore_keypair_from_str_with_default(sk_str)
+        .c(d!())
+        .and_then(|kp| gen_multi_delegate_tx(&kp, targets).c(d!()))
+        .and_then(|tx| utils::send_tx(&tx).c(d!()))
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn undelegate(sk_str: Option<&str>, param: Option<(u64, &str)>) -> Result<()> {
@@ -674,8 +1135,20 @@ fn gen_delegate_tx(
     owner_kp: &XfrKeyPair,
     amount: u64,
     validator: &str,
+) -> Result<Transaction> {
+    gen_multi_delegate_tx(owner_kp, &[(validator, amount)]).c(d!())
+}
+
+// a single principal pays for all the delegations
+fn gen_multi_delegate_tx(
+    owner_kp: &XfrKeyPair,
+    targets: &[(&str, u64)],
 ) -> Result<Transaction> {
     let mut builder = utils::new_tx_builder().c(d!())?;
+    let amount = targets
+        .iter()
+        .try_fold(0u64, |acc, (_, am)| acc.checked_add(*am))
+        .c(d!("overflow"))?;
 
     utils::gen_transfer_op(
         owner_kp,
@@ -688,7 +1161,13 @@ fn gen_delegate_tx(
     .c(d!())
     .map(|principal_op| {
         builder.add_operation(principal_op);
-        builder.add_operation_delegation(owner_kp, amount, validator.to_owned());
+        builder.add_operation_multi_delegation(
+            owner_kp,
+            targets
+                .iter()
+                .map(|(v, am)| ((*v).to_owned(), *am))
+                .collect(),
+        );
     })?;
 
     let mut tx = builder.take_transaction();
@@ -721,6 +1200,21 @@ pub fn create_asset(
         .map(|_| ())
 }
 
+/// Create a custom asset with user supplied rules, see `AssetRules::check`
+pub fn create_asset_with_rules(
+    sk_str: Option<&str>,
+    memo: &str,
+    rules: AssetRules,
+    token_code: Option<&str>,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let code = token_code
+        .map(|c| AssetTypeCode::new_from_base64(c).c(d!("invalid asset code")))
+        .transpose()?;
+
+    define_asset_x(&kp, memo, rules, code).c(d!()).map(|_| ())
+}
+
 #[allow(missing_docs)]
 pub fn create_asset_x(
     kp: &XfrKeyPair,
@@ -730,13 +1224,24 @@ pub fn create_asset_x(
     transferable: bool,
     code: Option<AssetTypeCode>,
 ) -> Result<AssetTypeCode> {
-    let code = code.unwrap_or_else(AssetTypeCode::gen_random);
-
     let mut rules = AssetRules::default();
     rules.set_decimals(decimal).c(d!())?;
     rules.set_max_units(max_units);
     rules.set_transferable(transferable);
 
+    define_asset_x(kp, memo, rules, code)
+}
+
+#[allow(missing_docs)]
+pub fn define_asset_x(
+    kp: &XfrKeyPair,
+    memo: &str,
+    rules: AssetRules,
+    code: Option<AssetTypeCode>,
+) -> Result<AssetTypeCode> {
+    let code = code.unwrap_or_else(AssetTypeCode::gen_random);
+
+    // the rules are checked by the builder
     let mut builder = utils::new_tx_builder().c(d!())?;
     builder
         .add_operation_create_asset(kp, Some(code), rules, memo)
@@ -773,11 +1278,13 @@ pub fn issue_asset_x(
     let confidentiality_flags = AssetRecordType::from_flags(hidden, false);
 
     let mut builder = utils::new_tx_builder().c(d!())?;
+    let seq_num = SeqTracker::new(builder.get_seq_id())
+        .next_issuance_num(code, utils::get_issuance_num(code).c(d!())?);
     builder
         .add_basic_issue_asset(
             kp,
             code,
-            builder.get_seq_id(),
+            seq_num,
             amount,
             confidentiality_flags,
             &PublicParams::default(),
@@ -797,9 +1304,130 @@ pub fn issue_asset_x(
 pub fn show_asset(addr: &str) -> Result<()> {
     let pk = wallet::public_key_from_bech32(addr).c(d!())?;
     let assets = utils::get_created_assets(&pk).c(d!())?;
-    assets
+    assets.iter().for_each(|asset| {
+        let code = asset.body.asset.code.to_base64();
+        match AssetMetadata::from_memo(&asset.body.asset.memo) {
+            Some(m) => println!("{}: {} ({})", code, m.name, m.symbol),
+            None => println!("{}", code),
+        }
+    });
+    Ok(())
+}
+
+/// Update the on-chain status of credentials issued by `issuer`:
+/// revoke, restore, or set the expiry heights of them(issuing or renewing),
+/// credentials are identified by the value of their `id_attr` attribute.
+pub fn update_credential_status(
+    sk_str: Option<&str>,
+    issuer: CredIssuerPublicKey,
+    id_attr: &str,
+    revoke: &[&str],
+    restore: &[&str],
+    expiries: &[(&str, u64)],
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.as_bytes().to_vec()).collect();
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_update_revocation(
+        &kp,
+        issuer,
+        id_attr,
+        ids(revoke),
+        ids(restore),
+        expiries
+            .iter()
+            .map(|(id, h)| (id.as_bytes().to_vec(), *h))
+            .collect(),
+    );
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Register the mirror of an asset on an external chain,
+/// `None` removes the current mirror on the chain.
+pub fn update_asset_mirror(
+    sk_str: Option<&str>,
+    code: AssetTypeCode,
+    chain_id: u64,
+    contract: Option<[u8; 20]>,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str)?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_update_asset_mirror(&kp, code, chain_id, contract);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Show the mirrors of an asset on external chains
+pub fn show_asset_mirrors(code: AssetTypeCode) -> Result<()> {
+    utils::get_asset_mirrors(&code)
+        .c(d!())?
         .iter()
-        .for_each(|asset| println!("{}", asset.body.asset.code.to_base64()));
+        .for_each(|m| println!("{}: 0x{}", m.chain_id, hex::encode(m.contract)));
+    Ok(())
+}
+
+/// Compare two exports of the ledger state, see `abcid --export-state`,
+/// the differences are printed in JSON, and also written to `out` if specified.
+pub fn state_diff(left: &str, right: &str, out: Option<&str>) -> Result<()> {
+    let read = |path: &str| {
+        fs::read(path)
+            .c(d!(path.to_owned()))
+            .and_then(|e| serde_json::from_slice::<LedgerExport>(&e).c(d!()))
+    };
+    let diff = export::diff(&read(left)?, &read(right)?).c(d!())?;
+    let diff = serde_json::to_string_pretty(&diff).c(d!())?;
+
+    println!("{}", diff);
+    if let Some(path) = out {
+        fs::write(path, diff).c(d!(path.to_owned()))?;
+    }
+
+    Ok(())
+}
+
+/// Cancel a transaction which waits for its parents in the submission server,
+/// or replace it with the one in the file `replacement`,
+/// signed by the key in `sk_str`, or the one of `fn setup`.
+///
+/// The transactions forwarded to the mempool can not be cancelled.
+pub fn cancel_tx(
+    handle: &str,
+    replacement: Option<&str>,
+    sk_str: Option<&str>,
+) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+    let replacement = replacement
+        .map(|path| {
+            fs::read_to_string(path)
+                .c(d!(path.to_owned()))
+                .and_then(|tx| inspect::parse_txn(&tx).c(d!()))
+        })
+        .transpose()?;
+    let body = CancelTxnBody {
+        handle: handle.to_owned(),
+        replacement,
+    };
+
+    match utils::cancel_tx(&CancelTxn::new(&kp, body)).c(d!())? {
+        Some(h) => println!("\x1b[31;01mReplaced by:\x1b[00m {}", h),
+        None => println!("\x1b[31;01mCancelled:\x1b[00m {}", handle),
+    }
+
     Ok(())
 }
 
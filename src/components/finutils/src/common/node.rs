@@ -0,0 +1,100 @@
+//!
+//! # Block archives in chunks
+//!
+//! `fn node export-blocks` cuts a range of heights of a block archive, recorded by
+//! `abcid --block-archive`, into chunk files, each with a header, the blocks and
+//! their transactions, and the state commitments they chain from and to.
+//!
+//! `fn node import-blocks` verifies chunk files and appends them, in order, to
+//! another archive, which can then be replayed by `abcid --replay-archive`, or
+//! served by the query server, without access to the network.
+//!
+
+use {
+    ledger::store::archive::{self, ArchiveChunk},
+    ruc::*,
+    std::{fs, path::Path},
+};
+
+/// The blocks of a chunk by default
+pub const DEFAULT_CHUNK_SIZE: usize = 1000;
+
+const CHUNK_PREFIX: &str = "blocks-";
+const CHUNK_SUFFIX: &str = ".json";
+
+/// Export the blocks of `archive` from the height `from` to the height `to`
+/// as chunks of `chunk_size` blocks in the directory `out_dir`.
+pub fn export_blocks(
+    archive: &str,
+    from: u64,
+    to: u64,
+    chunk_size: usize,
+    out_dir: &str,
+) -> Result<()> {
+    fs::create_dir_all(out_dir).c(d!(out_dir.to_owned()))?;
+    let n = archive::export_chunks(archive, from, to, chunk_size, |chunk| {
+        // zero-padded, so the names sort as the heights
+        let path = Path::new(out_dir).join(format!(
+            "{}{:012}-{:012}{}",
+            CHUNK_PREFIX, chunk.header.from, chunk.header.to, CHUNK_SUFFIX
+        ));
+        let contents = serde_json::to_vec(&chunk).c(d!())?;
+        fs::write(&path, contents).c(d!(path.to_string_lossy().into_owned()))?;
+        println!(
+            "{}: {} blocks, {}..={}",
+            path.to_string_lossy(),
+            chunk.header.count,
+            chunk.header.from,
+            chunk.header.to
+        );
+        Ok(())
+    })
+    .c(d!())?;
+
+    alt!(
+        0 == n,
+        Err(eg!(format!("no blocks between {} and {}", from, to))),
+        Ok(())
+    )
+}
+
+/// Verify the chunks of `input`, a chunk file or a directory of them,
+/// and append them in order to `archive`.
+pub fn import_blocks(input: &str, archive: &str) -> Result<()> {
+    let paths = if Path::new(input).is_dir() {
+        let mut paths = fs::read_dir(input)
+            .c(d!(input.to_owned()))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with(CHUNK_PREFIX) && n.ends_with(CHUNK_SUFFIX))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    } else {
+        vec![Path::new(input).to_path_buf()]
+    };
+    if paths.is_empty() {
+        return Err(eg!(format!("no chunks found in {}", input)));
+    }
+
+    for path in paths {
+        let name = path.to_string_lossy().into_owned();
+        let chunk = fs::read(&path)
+            .c(d!(name.clone()))
+            .and_then(|c| serde_json::from_slice::<ArchiveChunk>(&c).c(d!()))?;
+        let n = archive::import_chunk(archive, &chunk).c(d!(name.clone()))?;
+        if 0 == n {
+            println!("{}: already imported", name);
+        } else {
+            println!(
+                "{}: {} blocks imported, {}..={}",
+                name, n, chunk.header.from, chunk.header.to
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//!
+//! Hashes of documents anchored on chain, with the timestamps of their blocks.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    ledger::{
+        converter::notary::{document_hash, NotarizedHash},
+        data_model::AuthenticatedNotarizedHash,
+    },
+    ruc::*,
+    serde::de::DeserializeOwned,
+    std::fs,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// The hash in `hash`, 32 bytes in hex, or the hash of the file in `file`.
+pub fn parse_hash(hash: Option<&str>, file: Option<&str>) -> Result<[u8; 32]> {
+    match (hash, file) {
+        (Some(h), None) => hex::decode(h)
+            .c(d!())
+            .and_then(|h| <[u8; 32]>::try_from(h.as_slice()).c(d!("not 32 bytes"))),
+        (None, Some(f)) => fs::read(f).c(d!(f)).map(|d| document_hash(&d)),
+        _ => Err(eg!("one of `--hash` and `--file` is needed")),
+    }
+}
+
+/// Anchor `hash` under the current key.
+pub fn anchor(hash: [u8; 32], tag: Option<&str>) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_anchor_hash(&kp, hash, tag.map(|t| t.to_owned()));
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// The records of `hash` by all keys anchoring it.
+pub fn get_records(hash: &[u8; 32]) -> Result<Vec<NotarizedHash>> {
+    let url = format!(
+        "{}:8668/anchored_hash/{}",
+        get_serv_addr().c(d!())?,
+        hex::encode(hash)
+    );
+    get(attohttpc::get(&url)).c(d!())
+}
+
+/// The record of `hash` anchored by `owner`, the proof is checked
+/// against the state commitment it carries.
+pub fn get_record(
+    hash: &[u8; 32],
+    owner: &XfrPublicKey,
+) -> Result<AuthenticatedNotarizedHash> {
+    let url = format!("{}:8668/anchored_hash_proof", get_serv_addr().c(d!())?);
+    let r: AuthenticatedNotarizedHash = get(attohttpc::get(&url)
+        .param("hash", hex::encode(hash))
+        .param("owner", globutils::wallet::public_key_to_base64(owner)))
+    .c(d!())?;
+    if !r.is_valid(r.state_commitment.clone()) {
+        return Err(eg!("invalid proof"));
+    }
+    Ok(r)
+}
+
+fn get<T: DeserializeOwned>(req: attohttpc::RequestBuilder) -> Result<T> {
+    req.send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
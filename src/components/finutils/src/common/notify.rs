@@ -0,0 +1,289 @@
+//!
+//! # Wallet notifications
+//!
+//! Follow the blocks of the node of `fn setup` and notify the events
+//! of a set of addresses:
+//! - incoming transfers, the change sent back to the sender excluded
+//! - outgoing spends
+//! - delegation rewards, ie. the FRAs minted by claims
+//!
+//! An event is dispatched to the terminal, and to the desktop, a webhook
+//! or an email address if configured, a failed dispatch is only reported.
+//!
+
+use {
+    crate::common::{get_serv_addr, utils},
+    globutils::wallet,
+    ledger::{
+        data_model::{AssetTypeCode, FinalizedBlock, Operation, ASSET_TYPE_FRA},
+        staking::ops::mint_fra::MintKind,
+    },
+    ruc::*,
+    serde::Serialize,
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        io::Write,
+        process::{Command, Stdio},
+    },
+    zei::xfr::{
+        sig::XfrPublicKey,
+        structs::{XfrAmount, XfrAssetType},
+    },
+};
+
+// the sums of amounts by assets, `None` for the confidential ones
+type Sums = BTreeMap<Option<AssetTypeCode>, Option<u64>>;
+
+/// Options of `fn notify`
+#[derive(Debug, Clone)]
+pub struct NotifyCfg {
+    /// the addresses to watch
+    pub addresses: Vec<XfrPublicKey>,
+    /// the first block to follow, the next one to be committed by default
+    pub from_block: Option<usize>,
+    /// seconds to wait when no new blocks
+    pub interval_secs: u64,
+    /// show the events as desktop notifications
+    pub desktop: bool,
+    /// the events will be POSTed to this URL in JSON
+    pub webhook: Option<String>,
+    /// the events will be mailed to this address by `sendmail`
+    pub email: Option<String>,
+}
+
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Incoming,
+    Outgoing,
+    Reward,
+}
+
+/// An event of a watched address
+#[derive(Clone, Debug, Serialize)]
+pub struct Event {
+    #[allow(missing_docs)]
+    pub kind: EventKind,
+    #[allow(missing_docs)]
+    pub block_sid: usize,
+    #[allow(missing_docs)]
+    pub txn_sid: usize,
+    /// the watched address, in bech32
+    pub address: String,
+    /// the asset code in base64, `None` if confidential
+    pub asset: Option<String>,
+    /// the amount received or sent to others, `None` if confidential
+    pub amount: Option<u64>,
+}
+
+impl Event {
+    fn summary(&self) -> String {
+        let what = match self.kind {
+            EventKind::Incoming => "received",
+            EventKind::Outgoing => "sent",
+            EventKind::Reward => "rewarded",
+        };
+        let amount = match (self.amount, self.asset.as_deref()) {
+            (Some(am), Some(asset)) => format!("{} of {}", am, asset),
+            _ => "a confidential amount".to_owned(),
+        };
+        format!(
+            "{} {} {}, transaction {} in block {}",
+            self.address, what, amount, self.txn_sid, self.block_sid
+        )
+    }
+}
+
+/// Follow the chain and dispatch the events,
+/// never returns unless an unrecoverable error occurs.
+pub fn run(cfg: &NotifyCfg) -> Result<()> {
+    if cfg.addresses.is_empty() {
+        return Err(eg!("no addresses to watch"));
+    }
+    let node = get_serv_addr().c(d!())?;
+    let watched = cfg.addresses.iter().copied().collect::<HashSet<_>>();
+
+    let mut next = match cfg.from_block {
+        Some(sid) => sid,
+        None => utils::get_seq_id().c(d!())? as usize,
+    };
+    println!(
+        "[{}] watching {} addresses from block {}",
+        datetime!(),
+        watched.len(),
+        next
+    );
+
+    loop {
+        match utils::get_block(node, next) {
+            Ok(Some(block)) => {
+                for e in events_of_block(next, &block, &watched) {
+                    dispatch(&e, cfg);
+                }
+                next += 1;
+            }
+            // no new blocks
+            Ok(None) => sleep_ms!(cfg.interval_secs.max(1) * 1000),
+            // network issues, retry later
+            Err(e) => {
+                e.print(None);
+                sleep_ms!(cfg.interval_secs.max(1) * 1000);
+            }
+        }
+    }
+}
+
+/// The events of the addresses in `watched` in the block `block_sid`.
+pub fn events_of_block(
+    block_sid: usize,
+    block: &FinalizedBlock,
+    watched: &HashSet<XfrPublicKey>,
+) -> Vec<Event> {
+    let mut events = vec![];
+    let mut event = |kind, txn_sid, pk: &XfrPublicKey, asset, amount| {
+        events.push(Event {
+            kind,
+            block_sid,
+            txn_sid,
+            address: wallet::public_key_to_bech32(pk),
+            asset: asset.map(|a: AssetTypeCode| a.to_base64()),
+            amount,
+        })
+    };
+
+    for ftx in block.txns.iter() {
+        let txn_sid = ftx.tx_id.0;
+        for op in ftx.txn.body.operations.iter() {
+            match op {
+                Operation::TransferAsset(t) => {
+                    let senders = t
+                        .body
+                        .transfer
+                        .inputs
+                        .iter()
+                        .map(|i| i.public_key)
+                        .collect::<HashSet<_>>();
+
+                    let mut received: HashMap<XfrPublicKey, Sums> = HashMap::new();
+                    let mut sent: Sums = BTreeMap::new();
+                    for o in t.body.transfer.outputs.iter() {
+                        let asset = match o.asset_type {
+                            XfrAssetType::NonConfidential(val) => {
+                                Some(AssetTypeCode { val })
+                            }
+                            XfrAssetType::Confidential(_) => None,
+                        };
+                        let amount = match o.amount {
+                            XfrAmount::NonConfidential(am) => Some(am),
+                            XfrAmount::Confidential(_) => None,
+                        };
+                        let add = |sums: &mut Sums| {
+                            let sum = sums.entry(asset).or_insert(Some(0));
+                            *sum =
+                                sum.and_then(|s| amount.map(|am| s.saturating_add(am)));
+                        };
+                        if senders.contains(&o.public_key) {
+                            continue;
+                        }
+                        add(&mut sent);
+                        if watched.contains(&o.public_key) {
+                            add(received.entry(o.public_key).or_default());
+                        }
+                    }
+
+                    for (pk, sums) in received.iter() {
+                        for (asset, amount) in sums.iter() {
+                            event(EventKind::Incoming, txn_sid, pk, *asset, *amount);
+                        }
+                    }
+                    for pk in senders.iter().filter(|pk| watched.contains(pk)) {
+                        for (asset, amount) in sent.iter() {
+                            event(EventKind::Outgoing, txn_sid, pk, *asset, *amount);
+                        }
+                    }
+                }
+                Operation::MintFra(m) => {
+                    for e in m.entries.iter() {
+                        if watched.contains(&e.target_pk) {
+                            let kind = alt!(
+                                MintKind::Claim == e.kind,
+                                EventKind::Reward,
+                                EventKind::Incoming
+                            );
+                            let asset = Some(AssetTypeCode {
+                                val: ASSET_TYPE_FRA,
+                            });
+                            event(kind, txn_sid, &e.target_pk, asset, Some(e.amount));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    events
+}
+
+fn dispatch(e: &Event, cfg: &NotifyCfg) {
+    let summary = e.summary();
+    println!("[{}] {}", datetime!(), &summary);
+
+    if cfg.desktop {
+        info_omit!(notify_desktop(&summary));
+    }
+
+    if let Some(url) = cfg.webhook.as_deref() {
+        let res = attohttpc::post(url)
+            .json(e)
+            .c(d!())
+            .and_then(|req| req.send().c(d!()))
+            .and_then(|resp| resp.error_for_status().c(d!()));
+        info_omit!(res);
+    }
+
+    if let Some(to) = cfg.email.as_deref() {
+        info_omit!(send_email(to, e, &summary));
+    }
+}
+
+fn notify_desktop(summary: &str) -> Result<()> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("osascript");
+        cmd.arg("-e").arg(format!(
+            "display notification {:?} with title \"Findora\"",
+            summary
+        ));
+        cmd
+    } else {
+        let mut cmd = Command::new("notify-send");
+        cmd.arg("Findora").arg(summary);
+        cmd
+    };
+    let status = cmd.status().c(d!())?;
+    alt!(status.success(), Ok(()), Err(eg!(status.to_string())))
+}
+
+fn send_email(to: &str, e: &Event, summary: &str) -> Result<()> {
+    let mut child = Command::new("sendmail")
+        .arg("-t")
+        .stdin(Stdio::piped())
+        .spawn()
+        .c(d!("`sendmail` is required to send emails"))?;
+
+    let body = serde_json::to_string_pretty(e).c(d!())?;
+    let mail = format!(
+        "To: {}\nSubject: [Findora] {}\n\n{}\n\n{}\n",
+        to, summary, summary, body
+    );
+    child
+        .stdin
+        .take()
+        .c(d!())?
+        .write_all(mail.as_bytes())
+        .c(d!())?;
+
+    let status = child.wait().c(d!())?;
+    alt!(status.success(), Ok(()), Err(eg!(status.to_string())))
+}
@@ -0,0 +1,318 @@
+//!
+//! Validator onboarding with keys kept on an offline machine.
+//!
+//! - offline: `gen_td_key` generates the consensus key of the validator,
+//!   `gen_registration` the registration of the validator
+//! - online: `build_registration` creates the unsigned transaction of the registration,
+//!   from the outputs of the staker and the current sequence id
+//! - offline: `sign_registration` signs it with the keys of the staker and the validator,
+//!   only the signatures are exported
+//! - online: `submit_registration` imports the signatures and sends the transaction
+//!
+
+use {
+    super::{
+        convert_commission_rate, utils,
+        utils::{parse_td_validator_keys, ValidatorKey},
+        vesting,
+    },
+    crate::txn_builder::TransferOperationBuilder,
+    globutils::{address, SignatureOf},
+    ledger::{
+        data_model::{
+            gen_random_keypair, IndexedSignature, Operation, Transaction,
+            TransactionBody, TransferAssetBody, TransferType, TxoRef, ASSET_TYPE_FRA,
+            BLACK_HOLE_PUBKEY, BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
+        },
+        staking::{
+            check_delegation_amount, ops::delegation::DelegationOps, td_addr_to_string,
+            td_pubkey_to_td_addr, Validator,
+        },
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{collections::HashSet, fs},
+    tendermint::{private_key::Ed25519, PrivateKey},
+    zei::xfr::{
+        asset_record::{open_blind_asset_record, AssetRecordType},
+        sig::XfrKeyPair,
+        structs::AssetRecordTemplate,
+    },
+};
+
+/// The registration of a validator, created offline
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Registration {
+    #[allow(missing_docs)]
+    pub validator: Validator,
+    /// the self-delegation of the validator
+    pub amount: u64,
+}
+
+/// The registration with its transaction, signed by nothing
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UnsignedRegistration {
+    #[allow(missing_docs)]
+    pub registration: Registration,
+    /// the transaction without the delegation operation,
+    /// which is created when signing
+    pub tx: Transaction,
+}
+
+/// The signatures of an `UnsignedRegistration`, no secret in it
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegistrationSignatures {
+    /// the signed delegation operation, the first one of the transaction
+    pub delegation: DelegationOps,
+    /// the signatures of the transfer operations, in order
+    pub transfers: Vec<IndexedSignature<TransferAssetBody>>,
+    /// the signature of the staker on the whole transaction
+    pub tx: SignatureOf<TransactionBody>,
+}
+
+/// Generate a tendermint consensus key, in the format of `priv_validator_key.json`.
+pub fn gen_td_key(out: &str) -> Result<()> {
+    if fs::metadata(out).is_ok() {
+        return Err(eg!(format!("{} exists, it will not be overwritten", out)));
+    }
+
+    let priv_key =
+        PrivateKey::Ed25519(Ed25519::generate(&mut ChaChaRng::from_entropy()));
+    let pub_key = priv_key.public_key();
+    let key = ValidatorKey {
+        address: td_pubkey_to_td_addr(&pub_key.to_vec()),
+        pub_key,
+        priv_key,
+    };
+
+    write_json(out, &key).c(d!())?;
+    println!("\x1b[31;01mTendermint address:\x1b[00m {}", key.address);
+
+    Ok(())
+}
+
+/// Create the registration of the validator of the consensus key in `td_key`,
+/// staked by `staker`.
+pub fn gen_registration(
+    td_key: &str,
+    staker: &str,
+    amount: &str,
+    commission_rate: &str,
+    memo: Option<&str>,
+    out: &str,
+) -> Result<()> {
+    let td_pubkey = read_td_key(td_key).c(d!())?.pub_key.to_vec();
+    let staker = address::parse_address(staker).c(d!())?;
+    let amount = amount.parse::<u64>().c(d!("'amount' must be an integer"))?;
+    check_delegation_amount(amount, false).c(d!())?;
+    let commission_rate = commission_rate
+        .parse::<f64>()
+        .c(d!("commission rate must be a float number"))
+        .and_then(|cr| convert_commission_rate(cr).c(d!()))?;
+    let memo = memo
+        .map(|m| serde_json::from_str(m).c(d!("invalid memo")))
+        .transpose()?
+        .unwrap_or_default();
+
+    let validator =
+        Validator::new_staker(td_pubkey, staker, commission_rate, memo).c(d!())?;
+
+    write_json(out, &Registration { validator, amount })
+}
+
+/// Create the unsigned transaction of the registration in `registration`.
+///
+/// Only the non-confidential FRA outputs of the staker are spent,
+/// they can be opened without its key.
+pub fn build_registration(registration: &str, out: &str) -> Result<()> {
+    let registration = read_json::<Registration>(registration).c(d!())?;
+    let staker = registration.validator.id;
+    let amount = registration.amount;
+    check_delegation_amount(amount, false).c(d!())?;
+
+    let locked = vesting::get_vesting_x(None, &staker)
+        .map(|v| {
+            v.outputs
+                .into_iter()
+                .filter(|o| 0 < o.locked)
+                .map(|o| o.sid)
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+    let opener = gen_random_keypair();
+
+    let mut trans_builder = TransferOperationBuilder::new();
+    let mut am = amount.checked_add(TX_FEE_MIN).c(d!("overflow"))?;
+    for (sid, (utxo, _)) in utils::get_owned_utxos(&staker).c(d!())? {
+        if locked.contains(&sid) {
+            continue;
+        }
+        let oar = match open_blind_asset_record(&utxo.0.record, &None, &opener) {
+            Ok(oar) if oar.asset_type == ASSET_TYPE_FRA => oar,
+            _ => continue,
+        };
+        let i_am = oar.amount.min(am);
+        am -= i_am;
+        trans_builder
+            .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
+            .c(d!())?;
+        if 0 == am {
+            break;
+        }
+    }
+    if 0 != am {
+        return Err(eg!("insufficient balance"));
+    }
+
+    let outputs = [
+        (*BLACK_HOLE_PUBKEY_STAKING, amount),
+        (*BLACK_HOLE_PUBKEY, TX_FEE_MIN),
+    ];
+    for (pk, am) in outputs.iter() {
+        trans_builder
+            .add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    *am,
+                    ASSET_TYPE_FRA,
+                    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                    *pk,
+                ),
+                None,
+                None,
+                None,
+            )
+            .c(d!())?;
+    }
+    let op = trans_builder
+        .balance(None)
+        .c(d!())?
+        .create(TransferType::Standard)
+        .c(d!())?
+        .transaction()
+        .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(op);
+
+    write_json(
+        out,
+        &UnsignedRegistration {
+            registration,
+            tx: builder.take_transaction(),
+        },
+    )
+}
+
+/// Sign the unsigned registration in `unsigned` with the key of the staker
+/// in `staker_key`, a secret key or mnemonic, and the consensus key in `td_key`.
+pub fn sign_registration(
+    unsigned: &str,
+    staker_key: &str,
+    td_key: &str,
+    out: &str,
+) -> Result<()> {
+    let unsigned = read_json::<UnsignedRegistration>(unsigned).c(d!())?;
+    let kp = fs::read_to_string(staker_key)
+        .c(d!(staker_key))
+        .and_then(|k| address::parse_keypair(&k).c(d!()))?;
+    let td_key = read_td_key(td_key).c(d!())?;
+
+    let v = unsigned.registration.validator;
+    if kp.get_pk() != v.id {
+        return Err(eg!("the key is not the one of the staker"));
+    }
+    if td_key.pub_key.to_vec() != v.td_pubkey {
+        return Err(eg!("the consensus key is not the one of the validator"));
+    }
+
+    let mut tx = unsigned.tx;
+    let transfers = sign_transfers(&mut tx, &kp).c(d!())?;
+    let delegation = DelegationOps::new(
+        &kp,
+        Some(&td_key.priv_key),
+        unsigned.registration.amount,
+        td_addr_to_string(&v.td_addr),
+        Some(v),
+        tx.body.no_replay_token,
+    );
+    tx.body
+        .operations
+        .insert(0, Operation::Delegation(delegation.clone()));
+
+    write_json(
+        out,
+        &RegistrationSignatures {
+            delegation,
+            transfers,
+            tx: SignatureOf::new(&kp, &tx.body),
+        },
+    )
+}
+
+/// Import the signatures in `signatures` into the unsigned registration
+/// in `unsigned`, and send the transaction.
+pub fn submit_registration(unsigned: &str, signatures: &str) -> Result<()> {
+    let unsigned = read_json::<UnsignedRegistration>(unsigned).c(d!())?;
+    let sigs = read_json::<RegistrationSignatures>(signatures).c(d!())?;
+    let staker = unsigned.registration.validator.id;
+
+    sigs.delegation.verify().c(d!())?;
+    if sigs.delegation.get_related_pubkeys() != vec![staker] {
+        return Err(eg!("the delegation is not signed by the staker"));
+    }
+
+    let mut tx = unsigned.tx;
+    let mut transfer_sigs = sigs.transfers.into_iter();
+    for op in tx.body.operations.iter_mut() {
+        if let Operation::TransferAsset(trn) = op {
+            trn.attach_signature(transfer_sigs.next().c(d!("missing signatures"))?)
+                .c(d!("invalid signature"))?;
+        }
+    }
+    if transfer_sigs.next().is_some() {
+        return Err(eg!("too many signatures"));
+    }
+
+    tx.body
+        .operations
+        .insert(0, Operation::Delegation(sigs.delegation));
+    tx.check_signature(&staker, &sigs.tx).c(d!())?;
+    tx.pubkey_sign_map.insert(staker, sigs.tx);
+
+    utils::send_tx(&tx).c(d!())
+}
+
+fn sign_transfers(
+    tx: &mut Transaction,
+    kp: &XfrKeyPair,
+) -> Result<Vec<IndexedSignature<TransferAssetBody>>> {
+    let mut sigs = vec![];
+    for op in tx.body.operations.iter_mut() {
+        if let Operation::TransferAsset(trn) = op {
+            let sig = trn.create_input_signature(kp);
+            trn.attach_signature(sig.clone()).c(d!())?;
+            sigs.push(sig);
+        }
+    }
+    Ok(sigs)
+}
+
+fn read_td_key(path: &str) -> Result<ValidatorKey> {
+    fs::read_to_string(path)
+        .c(d!(path.to_owned()))
+        .and_then(|k| parse_td_validator_keys(&k).c(d!()))
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T> {
+    fs::read(path)
+        .c(d!(path.to_owned()))
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn write_json<T: Serialize>(path: &str, v: &T) -> Result<()> {
+    serde_json::to_vec_pretty(v)
+        .c(d!())
+        .and_then(|b| fs::write(path, b).c(d!(path.to_owned())))
+}
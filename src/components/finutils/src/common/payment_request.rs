@@ -0,0 +1,34 @@
+//!
+//! # Payment requests
+//!
+//! `fn request-payment` shares a `PaymentRequest` as a URI and a QR code
+//! in the terminal, `fn transfer --request` pays one.
+//!
+
+use {
+    globutils::payment_request::PaymentRequest,
+    qrcode::{render::unicode::Dense1x2, QrCode},
+    ruc::*,
+};
+
+/// Print the URI of `req`, along with its QR code if `with_qr`.
+pub fn request_payment(req: &PaymentRequest, with_qr: bool) -> Result<()> {
+    let uri = req.to_uri();
+    if with_qr {
+        println!("{}", render_qr(&uri).c(d!())?);
+    }
+    println!("{}", uri);
+    Ok(())
+}
+
+/// Render `data` as a QR code of unicode half blocks, with a quiet zone,
+/// the colors inverted for the terminals of dark backgrounds.
+pub fn render_qr(data: &str) -> Result<String> {
+    QrCode::new(data.as_bytes()).c(d!()).map(|code| {
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .quiet_zone(true)
+            .build()
+    })
+}
@@ -0,0 +1,126 @@
+//!
+//! Payments streamed to the recipient, vesting linearly over a range of blocks.
+//!
+//! The sender locks assets with `create`, the recipient claims the vested part
+//! at any time, and the sender may `cancel` the stream to get the unvested part back.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    ledger::{
+        converter::payment_stream::PaymentStream,
+        data_model::{AssetTypeCode, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    zei::xfr::{asset_record::AssetRecordType, sig::XfrPublicKey},
+};
+
+// the refund of a cancellation is the unvested amount this many blocks later,
+// within which the transaction is expected to be included
+const CANCEL_MARGIN: u64 = 10;
+
+/// The response of `payment_stream`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StreamStatus {
+    #[allow(missing_docs)]
+    pub stream: PaymentStream,
+    /// The last committed height
+    pub height: u64,
+    /// Amount vested and not claimed yet
+    pub claimable: u64,
+    /// Amount not vested yet
+    pub unvested: u64,
+}
+
+/// Lock `amount` of the asset, vesting to `recipient` linearly
+/// from `start` to `end`, the identifier of the stream is printed.
+pub fn create(
+    recipient: XfrPublicKey,
+    amount: u64,
+    start: u64,
+    end: u64,
+    code: Option<&str>,
+) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let code = code
+        .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+        .transpose()?
+        .unwrap_or(AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        });
+
+    let transfer_op = utils::gen_transfer_op(
+        &kp,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
+        Some(code),
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(transfer_op);
+    let id =
+        builder.add_operation_create_stream(&kp, recipient, code, amount, start, end);
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())?;
+    println!("\x1b[31;01mStream:\x1b[00m {}", id);
+    Ok(())
+}
+
+/// Claim `amount` vested in the stream `id`, default to all of the claimable amount.
+pub fn claim(id: &str, amount: Option<u64>) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let status = get_stream(id).c(d!())?;
+    let amount = amount.unwrap_or(status.claimable);
+    if 0 == amount {
+        return Err(eg!("nothing to claim"));
+    }
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_claim_stream(&kp, id.to_owned(), status.stream.asset, amount);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Cancel the stream `id`, the unvested assets are refunded to the sender.
+pub fn cancel(id: &str) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let status = get_stream(id).c(d!())?;
+    let refund = status.stream.unvested(status.height + CANCEL_MARGIN);
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_cancel_stream(&kp, id.to_owned(), status.stream.asset, refund);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Query the status of a stream from the ledger.
+pub fn get_stream(id: &str) -> Result<StreamStatus> {
+    let url = format!("{}:8668/payment_stream/{}", get_serv_addr().c(d!())?, id);
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
@@ -0,0 +1,155 @@
+//!
+//! Operations of the prism bridge.
+//!
+//! Deposits are sent by the owners of the assets,
+//! withdrawals and updates of the authorities are signed by each authority
+//! with `--sign-only`, then the collected signatures are submitted by any of them.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    fp_types::crypto::MultiSigner,
+    globutils::SignatureOf,
+    ledger::{
+        converter::prism::{
+            PrismAuthorities, PrismSignature, PrismWithdrawBody,
+            UpdatePrismAuthoritiesBody,
+        },
+        data_model::{AssetTypeCode, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING},
+    },
+    ruc::*,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{fs, str::FromStr},
+    zei::xfr::{asset_record::AssetRecordType, sig::XfrPublicKey},
+};
+
+// the fields used by the CLI in the response of `prism_state`
+#[derive(Deserialize)]
+struct PrismStateView {
+    version: u64,
+}
+
+/// Lock assets in the bridge, the authorities credit `receiver` on the account side.
+pub fn deposit(amount: u64, code: Option<&str>, receiver: &str) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let code = code
+        .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+        .transpose()?
+        .unwrap_or(AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        });
+    let receiver = MultiSigner::from_str(receiver).c(d!())?;
+
+    let transfer_op = utils::gen_transfer_op(
+        &kp,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
+        Some(code),
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder
+        .add_operation(transfer_op)
+        .add_operation_prism_deposit(&kp, code, receiver, amount);
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Release assets burned on the account side.
+///
+/// With `sign_only`, print the signature of the local key,
+/// otherwise submit the withdrawal with the signatures in `sigs_file`.
+pub fn withdraw(
+    body: PrismWithdrawBody,
+    sign_only: bool,
+    sigs_file: Option<&str>,
+) -> Result<()> {
+    if sign_only {
+        return print_signature(&body);
+    }
+
+    let kp = get_keypair().c(d!())?;
+    let sigs = read_signatures(sigs_file.c(d!("signatures are missing"))?).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_prism_withdraw(body, sigs);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Replace the bridge authorities, the version is the current one plus one.
+///
+/// With `sign_only`, print the signature of the local key,
+/// otherwise submit the update with the signatures in `sigs_file`.
+pub fn update_authorities(
+    keys: Vec<XfrPublicKey>,
+    threshold: usize,
+    sign_only: bool,
+    sigs_file: Option<&str>,
+) -> Result<()> {
+    let authorities = PrismAuthorities { keys, threshold };
+    authorities.check().c(d!())?;
+
+    let url = format!("{}:8668/prism_state", get_serv_addr().c(d!())?);
+    let state = attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<PrismStateView>(&b).c(d!()))?;
+
+    let body = UpdatePrismAuthoritiesBody {
+        authorities,
+        version: state.version + 1,
+    };
+    if sign_only {
+        return print_signature(&body);
+    }
+
+    let kp = get_keypair().c(d!())?;
+    let sigs = read_signatures(sigs_file.c(d!("signatures are missing"))?).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_update_prism_authorities(body, sigs);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+// one line of JSON, collected by the submitter
+fn print_signature<T: Serialize + DeserializeOwned>(body: &T) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let sig: PrismSignature<T> = (kp.get_pk(), SignatureOf::new(&kp, body));
+    println!("{}", serde_json::to_string(&sig).c(d!())?);
+    Ok(())
+}
+
+// one signature per line, as printed by `print_signature`
+fn read_signatures<T: DeserializeOwned>(path: &str) -> Result<Vec<PrismSignature<T>>> {
+    fs::read_to_string(path)
+        .c(d!(path))?
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| serde_json::from_str(l).c(d!()))
+        .collect()
+}
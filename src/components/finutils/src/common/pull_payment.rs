@@ -0,0 +1,107 @@
+//!
+//! Recurring payments pulled by the payee.
+//!
+//! The payer locks assets with `authorize`, then the payee pulls them
+//! within the authorized limit of each period, until the payer revokes it.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    ledger::{
+        converter::pull_payment::PullAuthorization,
+        data_model::{AssetTypeCode, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING},
+    },
+    ruc::*,
+    zei::xfr::{asset_record::AssetRecordType, sig::XfrPublicKey},
+};
+
+/// Lock `amount` of the asset, and allow `payee` to pull up to `limit` of it
+/// per `period` blocks, the identifier of the authorization is printed.
+pub fn authorize(
+    payee: XfrPublicKey,
+    amount: u64,
+    limit: u64,
+    period: u64,
+    code: Option<&str>,
+) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let code = code
+        .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+        .transpose()?
+        .unwrap_or(AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        });
+
+    let transfer_op = utils::gen_transfer_op(
+        &kp,
+        vec![(&BLACK_HOLE_PUBKEY_STAKING, amount)],
+        Some(code),
+        false,
+        false,
+        Some(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType),
+    )
+    .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(transfer_op);
+    let id =
+        builder.add_operation_authorize_pull(&kp, payee, code, limit, period, amount);
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())?;
+    println!("\x1b[31;01mAuthorization:\x1b[00m {}", id);
+    Ok(())
+}
+
+/// Pull `amount` from the authorization `id` to a new UTXO of the payee.
+pub fn pull(id: &str, amount: u64) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let auth = get_authorization(id).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_pull(&kp, id.to_owned(), auth.asset, amount);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// End the authorization `id`, the remaining assets are released to the payer.
+pub fn revoke(id: &str) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let auth = get_authorization(id).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_revoke_pull(&kp, id.to_owned(), auth.asset, auth.remaining);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Query an authorization from the ledger.
+pub fn get_authorization(id: &str) -> Result<PullAuthorization> {
+    let url = format!(
+        "{}:8668/pull_authorization/{}",
+        get_serv_addr().c(d!())?,
+        id
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
@@ -0,0 +1,172 @@
+//!
+//! Session keys, signing the transfers of the current key up to an allowance.
+//!
+//! The owner authorizes a session key with `authorize`, a hot service holding only
+//! the session key sends the assets of the owner with `send`, paying the fee itself.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    crate::txn_builder::TransferOperationBuilder,
+    globutils::address,
+    ledger::{
+        converter::session_key::SessionKey,
+        data_model::{AssetTypeCode, TransferType, TxoRef, ASSET_TYPE_FRA},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::{
+        asset_record::{open_blind_asset_record, AssetRecordType},
+        sig::XfrPublicKey,
+        structs::AssetRecordTemplate,
+    },
+};
+
+/// The response of `session_keys`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionKeysStatus {
+    /// The last committed height
+    pub height: u64,
+    /// The session keys which can sign in the next block
+    pub sessions: BTreeMap<XfrPublicKey, SessionKey>,
+}
+
+/// Authorize `session` to send at most `allowance` of the asset
+/// for the current key, until the height `expires_at`.
+pub fn authorize(
+    session: XfrPublicKey,
+    allowance: u64,
+    expires_at: u64,
+    code: Option<&str>,
+) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let code = parse_code(code).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_authorize_session(&kp, session, code, allowance, expires_at);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Revoke the session key `session` of the current key.
+pub fn revoke(session: XfrPublicKey) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_revoke_session(&kp, session);
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Send `amount` of the asset of `primary` to `receiver`, signed by the session key
+/// in `session_key`, a secret key or mnemonic; the fee is paid by the session key.
+pub fn send(
+    primary: &XfrPublicKey,
+    session_key: &str,
+    receiver: &XfrPublicKey,
+    amount: u64,
+    code: Option<&str>,
+) -> Result<()> {
+    let session_kp = fs::read_to_string(session_key)
+        .c(d!(session_key))
+        .and_then(|k| address::parse_keypair(&k).c(d!()))?;
+    let code = parse_code(code).c(d!())?;
+
+    // only the non-confidential outputs can be opened without the primary key
+    let mut trans_builder = TransferOperationBuilder::new();
+    let mut am = amount;
+    for (sid, (utxo, _)) in utils::get_owned_utxos(primary).c(d!())? {
+        let oar = match open_blind_asset_record(&utxo.0.record, &None, &session_kp) {
+            Ok(oar) if oar.asset_type == code.val => oar,
+            _ => continue,
+        };
+        let i_am = oar.amount.min(am);
+        am -= i_am;
+        trans_builder
+            .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
+            .c(d!())?;
+        if 0 == am {
+            break;
+        }
+    }
+    if 0 != am {
+        return Err(eg!("insufficient balance"));
+    }
+
+    let op = trans_builder
+        .add_output(
+            &AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                code.val,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                *receiver,
+            ),
+            None,
+            None,
+            None,
+        )
+        .c(d!())?
+        .balance(None)
+        .c(d!())?
+        .create(TransferType::Standard)
+        .c(d!())?
+        .sign(&session_kp)
+        .c(d!())?
+        .transaction()
+        .c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(op);
+    utils::gen_fee_op(&session_kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&session_kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Query the session keys authorized by `addr`, default to the current key.
+pub fn get_sessions(addr: Option<&str>) -> Result<SessionKeysStatus> {
+    let pk = match addr {
+        Some(addr) => address::parse_address(addr).c(d!())?,
+        None => get_keypair().c(d!())?.get_pk(),
+    };
+    let url = format!(
+        "{}:8668/session_keys/{}",
+        get_serv_addr().c(d!())?,
+        globutils::wallet::public_key_to_base64(&pk)
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn parse_code(code: Option<&str>) -> Result<AssetTypeCode> {
+    code.map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+        .transpose()
+        .map(|c| {
+            c.unwrap_or(AssetTypeCode {
+                val: ASSET_TYPE_FRA,
+            })
+        })
+}
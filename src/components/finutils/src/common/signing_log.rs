@@ -0,0 +1,236 @@
+//!
+//! # Signing log
+//!
+//! Every transaction sent by `fn` is recorded before its submission, with its
+//! signers, its hash and its operations, in an append-only log of the config
+//! directory, one JSON entry per line.
+//!
+//! Each entry carries the hash of the previous one, so a removed or altered
+//! entry breaks the chain, see `verify`. The signers can be given nicknames,
+//! eg. the operators of a shared wallet, recorded along with their addresses.
+//!
+
+use {
+    super::{inspect::DecodedTxn, CFG_PATH, KEY_NICKNAME_FILE, SIGNING_LOG_FILE},
+    globutils::{address, wallet},
+    ledger::data_model::Transaction,
+    ring::digest,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fs::{self, OpenOptions},
+        io::Write,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+// the `prev_hash` of the first entry
+const GENESIS_HASH: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// An entry of the log
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SigningEvent {
+    /// starts from 0
+    pub seq: u64,
+    /// unix timestamp in seconds
+    pub timestamp: u64,
+    /// the signers, in bech32, with their nicknames if any
+    pub keys: Vec<String>,
+    /// the hash used by tendermint
+    pub tx_hash: String,
+    /// the kinds of the operations
+    pub operations: Vec<String>,
+    /// the hash of the previous entry
+    pub prev_hash: String,
+    /// the hash of this entry, all the other fields included
+    pub hash: String,
+}
+
+impl SigningEvent {
+    fn compute_hash(&self) -> Result<String> {
+        let fields = (
+            self.seq,
+            self.timestamp,
+            &self.keys,
+            &self.tx_hash,
+            &self.operations,
+            &self.prev_hash,
+        );
+        serde_json::to_vec(&fields)
+            .c(d!())
+            .map(|b| hex::encode(digest::digest(&digest::SHA256, &b)))
+    }
+}
+
+/// Record the signing of `tx`, before it is sent.
+pub fn record(tx: &Transaction) -> Result<()> {
+    let decoded = DecodedTxn::new(tx);
+    let nicknames = load_nicknames().c(d!())?;
+    let keys = decoded
+        .signers
+        .iter()
+        .chain(decoded.operations.iter().flat_map(|op| op.signers.iter()))
+        .map(|s| s.address.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|addr| match nicknames.get(&addr) {
+            Some(n) => format!("{} ({})", n, addr),
+            None => addr,
+        })
+        .collect();
+
+    let last = load().c(d!())?.pop();
+    let mut event = SigningEvent {
+        seq: last.as_ref().map(|e| 1 + e.seq).unwrap_or(0),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .c(d!())?
+            .as_secs(),
+        keys,
+        tx_hash: decoded.hash,
+        operations: decoded.operations.into_iter().map(|op| op.kind).collect(),
+        prev_hash: last
+            .map(|e| e.hash)
+            .unwrap_or_else(|| GENESIS_HASH.to_owned()),
+        hash: String::new(),
+    };
+    event.hash = event.compute_hash().c(d!())?;
+
+    let mut line = serde_json::to_string(&event).c(d!())?;
+    line.push('\n');
+    fs::create_dir_all(&*CFG_PATH).c(d!())?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&*SIGNING_LOG_FILE)
+        .c(d!())?
+        .write_all(line.as_bytes())
+        .c(d!())
+}
+
+/// All entries, the oldest first.
+pub fn load() -> Result<Vec<SigningEvent>> {
+    let log = match fs::read_to_string(&*SIGNING_LOG_FILE) {
+        Ok(l) => l,
+        Err(_) => return Ok(vec![]),
+    };
+    log.lines()
+        .filter(|l| !l.trim().is_empty())
+        .enumerate()
+        .map(|(i, l)| {
+            serde_json::from_str(l).c(d!(format!("invalid entry at line {}", 1 + i)))
+        })
+        .collect()
+}
+
+/// Check the chain of the entries, the number of entries is returned,
+/// or an error naming the first broken one.
+pub fn verify() -> Result<usize> {
+    load()
+        .c(d!())
+        .and_then(|events| check_chain(&events).c(d!()))
+}
+
+fn check_chain(events: &[SigningEvent]) -> Result<usize> {
+    let mut prev_hash = GENESIS_HASH.to_owned();
+    for (i, e) in events.iter().enumerate() {
+        if e.seq != i as u64 {
+            return Err(eg!(format!("entry {}: out of sequence, seq {}", i, e.seq)));
+        }
+        if e.prev_hash != prev_hash {
+            return Err(eg!(format!("entry {}: the previous entry is changed", i)));
+        }
+        if e.hash != e.compute_hash().c(d!())? {
+            return Err(eg!(format!("entry {}: altered", i)));
+        }
+        prev_hash = e.hash.clone();
+    }
+    Ok(events.len())
+}
+
+/// Name a signer in the later entries, an empty name removes the nickname.
+pub fn set_nickname(addr: &str, name: &str) -> Result<()> {
+    let pk = address::parse_address(addr).c(d!())?;
+    let addr = wallet::public_key_to_bech32(&pk);
+    let mut nicknames = load_nicknames().c(d!())?;
+    if name.trim().is_empty() {
+        nicknames.remove(&addr);
+    } else {
+        nicknames.insert(addr, name.trim().to_owned());
+    }
+    serde_json::to_vec_pretty(&nicknames)
+        .c(d!())
+        .and_then(|n| fs::write(&*KEY_NICKNAME_FILE, n).c(d!()))
+}
+
+fn load_nicknames() -> Result<BTreeMap<String, String>> {
+    match fs::read(&*KEY_NICKNAME_FILE) {
+        Ok(n) => serde_json::from_slice(&n).c(d!()),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a chain of `n` entries
+    fn chain(n: u64) -> Vec<SigningEvent> {
+        let mut events: Vec<SigningEvent> = vec![];
+        for seq in 0..n {
+            let mut e = SigningEvent {
+                seq,
+                timestamp: 1_600_000_000 + seq,
+                keys: vec![format!("key-{}", seq)],
+                tx_hash: format!("{:064x}", seq),
+                operations: vec!["TransferAsset".to_owned()],
+                prev_hash: events
+                    .last()
+                    .map(|e| e.hash.clone())
+                    .unwrap_or_else(|| GENESIS_HASH.to_owned()),
+                hash: String::new(),
+            };
+            e.hash = pnk!(e.compute_hash());
+            events.push(e);
+        }
+        events
+    }
+
+    #[test]
+    fn signing_log_chain() {
+        assert_eq!(0, pnk!(check_chain(&[])));
+        let events = chain(4);
+        assert_eq!(4, pnk!(check_chain(&events)));
+        // truncated at the end, undetectable without an external anchor
+        assert_eq!(3, pnk!(check_chain(&events[..3])));
+    }
+
+    #[test]
+    fn signing_log_tampered() {
+        let events = chain(4);
+
+        // altered, with or without its hash recomputed
+        let mut altered = events.clone();
+        altered[1].tx_hash = format!("{:064x}", 99);
+        assert!(check_chain(&altered).is_err());
+        altered[1].hash = pnk!(altered[1].compute_hash());
+        assert!(check_chain(&altered).is_err());
+
+        // removed
+        let mut removed = events.clone();
+        removed.remove(1);
+        assert!(check_chain(&removed).is_err());
+        assert!(check_chain(&events[1..]).is_err());
+
+        // reordered
+        let mut reordered = events.clone();
+        reordered.swap(1, 2);
+        assert!(check_chain(&reordered).is_err());
+        // even renumbered
+        reordered[1].seq = 1;
+        reordered[2].seq = 2;
+        assert!(check_chain(&reordered).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//!
+//! Spending policies bound to the current key, enforced by the ledger on its transfers.
+//!
+//! The changes of a policy with a co-signer must be co-signed,
+//! the key of the co-signer is read from `cosigner_key`, a secret key or mnemonic.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    globutils::address,
+    ledger::{
+        converter::spending_policy::{CoSigner, KeyPolicy, SpendingPolicy},
+        data_model::{AssetTypeCode, ASSET_TYPE_FRA},
+        staking::BLOCK_INTERVAL,
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// The response of `spending_policy`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PolicyStatus {
+    #[allow(missing_docs)]
+    pub policy: KeyPolicy,
+    /// The last committed height
+    pub height: u64,
+    /// The amounts that can still be sent within the limits in the next block
+    #[serde(with = "globutils::serde_pairs")]
+    pub remaining: BTreeMap<AssetTypeCode, u64>,
+}
+
+/// The rules of `set`, on a single asset
+pub struct PolicyArgs<'a> {
+    /// Asset code of the limit and the threshold, default to FRA
+    pub code: Option<&'a str>,
+    /// The maximum amount sent to others per period
+    pub limit: Option<u64>,
+    /// Blocks of a period, default to about a day
+    pub period: Option<u64>,
+    #[allow(missing_docs)]
+    pub cosigner: Option<XfrPublicKey>,
+    /// Transactions sending more than this must be co-signed
+    pub cosign_above: Option<u64>,
+    /// The only receivers allowed, any if empty
+    pub allowed: Vec<XfrPublicKey>,
+}
+
+/// Register or update the policy of the current key.
+pub fn set(args: PolicyArgs, cosigner_key: Option<&str>) -> Result<()> {
+    let code = args
+        .code
+        .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+        .transpose()?
+        .unwrap_or(AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        });
+    if args.cosign_above.is_some() && args.cosigner.is_none() {
+        return Err(eg!("a threshold needs a co-signer"));
+    }
+
+    let policy = SpendingPolicy {
+        limits: args.limit.map(|l| (code, l)).into_iter().collect(),
+        period: args.period.unwrap_or(24 * 3600 / *BLOCK_INTERVAL),
+        cosigner: args.cosigner.map(|pubkey| CoSigner {
+            pubkey,
+            thresholds: args.cosign_above.map(|t| (code, t)).into_iter().collect(),
+        }),
+        allowed_destinations: args.allowed.into_iter().collect(),
+    };
+
+    let kp = get_keypair().c(d!())?;
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_set_spending_policy(&kp, policy);
+    send(builder, &kp, cosigner_key).c(d!())
+}
+
+/// Remove the policy of the current key.
+pub fn remove(cosigner_key: Option<&str>) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation_remove_spending_policy(&kp);
+    send(builder, &kp, cosigner_key).c(d!())
+}
+
+/// Query the policy bound to `addr`, default to the current key.
+pub fn get_policy(addr: Option<&str>) -> Result<PolicyStatus> {
+    let pk = match addr {
+        Some(addr) => address::parse_address(addr).c(d!())?,
+        None => get_keypair().c(d!())?.get_pk(),
+    };
+    let url = format!(
+        "{}:8668/spending_policy/{}",
+        get_serv_addr().c(d!())?,
+        globutils::wallet::public_key_to_base64(&pk)
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn send(
+    mut builder: crate::txn_builder::TransactionBuilder,
+    kp: &XfrKeyPair,
+    cosigner_key: Option<&str>,
+) -> Result<()> {
+    utils::gen_fee_op(kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(kp);
+    if let Some(path) = cosigner_key {
+        let cosigner = fs::read_to_string(path)
+            .c(d!(path))
+            .and_then(|k| address::parse_keypair(&k).c(d!()))?;
+        tx.sign_to_map(&cosigner);
+    }
+
+    utils::send_tx(&tx)
+}
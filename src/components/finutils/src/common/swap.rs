@@ -0,0 +1,107 @@
+//!
+//! Delivery-versus-payment swaps between two parties, settled in one transfer.
+//!
+//! The initiator writes a proposal with `initiate`, the counterparty signs it
+//! with `accept`, then the initiator checks and submits it with `finish`;
+//! the files are exchanged out-of-band.
+//!
+
+use {
+    super::{get_keypair, utils},
+    crate::txn_builder::{SwapBuilder, SwapTerms},
+    ledger::data_model::{AssetTypeCode, TxoRef, ASSET_TYPE_FRA},
+    ruc::*,
+    std::fs,
+    zei::xfr::{
+        asset_record::open_blind_asset_record,
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::OpenAssetRecord,
+    },
+};
+
+/// Propose to deliver `give` in exchange for `want` from `counterparty`,
+/// the proposal is written to `out`, and must be kept until `finish`.
+pub fn initiate(
+    counterparty: XfrPublicKey,
+    give: (Option<AssetTypeCode>, u64),
+    want: (Option<AssetTypeCode>, u64),
+    confidential_am: bool,
+    confidential_ty: bool,
+    out: &str,
+) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let fra = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+    let terms = SwapTerms {
+        initiator: kp.get_pk(),
+        counterparty,
+        give: (give.0.unwrap_or(fra), give.1),
+        want: (want.0.unwrap_or(fra), want.1),
+        confidential_am,
+        confidential_ty,
+    };
+
+    let swap = SwapBuilder::initiate(terms, owned_inputs(&kp).c(d!())?).c(d!())?;
+    write_swap(&swap, out).c(d!())
+}
+
+/// Deliver the leg of the counterparty in the proposal at `proposal`,
+/// the signed swap is written to `out`, and sent back to the initiator.
+pub fn accept(proposal: &str, out: &str) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let mut swap = read_swap(proposal).c(d!())?;
+
+    println!(
+        "\x1b[31;01mGive:\x1b[00m {} {}\n\x1b[31;01mGet:\x1b[00m {} {}\n",
+        swap.terms.want.1,
+        swap.terms.want.0.to_base64(),
+        swap.terms.give.1,
+        swap.terms.give.0.to_base64(),
+    );
+
+    swap.accept(&kp, owned_inputs(&kp).c(d!())?).c(d!())?;
+    write_swap(&swap, out).c(d!())
+}
+
+/// Check the swap accepted by the counterparty against the proposal,
+/// then sign and submit it.
+pub fn finish(proposal: &str, accepted: &str) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let proposal = read_swap(proposal).c(d!())?;
+    let accepted = read_swap(accepted).c(d!())?;
+
+    let op = proposal.finish(accepted, &kp).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder.add_operation(op);
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx).c(d!())
+}
+
+fn owned_inputs(kp: &XfrKeyPair) -> Result<Vec<(TxoRef, OpenAssetRecord)>> {
+    utils::get_owned_utxos(kp.get_pk_ref())
+        .c(d!())?
+        .into_iter()
+        .map(|(sid, (utxo, owner_memo))| {
+            open_blind_asset_record(&utxo.0.record, &owner_memo, kp)
+                .c(d!())
+                .map(|oar| (TxoRef::Absolute(sid), oar))
+        })
+        .collect()
+}
+
+fn read_swap(path: &str) -> Result<SwapBuilder> {
+    fs::read(path)
+        .c(d!(path))
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
+fn write_swap(swap: &SwapBuilder, path: &str) -> Result<()> {
+    serde_json::to_vec(swap)
+        .c(d!())
+        .and_then(|b| fs::write(path, b).c(d!(path)))
+}
@@ -0,0 +1,388 @@
+//!
+//! Sync the local state of `fn` between machines through a remote snapshot,
+//! any HTTP endpoint storing an object by `GET` and `PUT` works,
+//! eg. a WebDAV file or a presigned S3 url.
+//!
+//! The synced entries are the server address, the change index, the TXO labels
+//! and the transfer templates; the mnemonic is synced only on demand,
+//! encrypted with a password.
+//!
+//! Each entry carries a version, bumped on every change pushed. An entry changed
+//! both locally and remotely since the last sync is a conflict, reported
+//! unless one side is preferred, and nothing is synced then.
+//!
+
+use {
+    super::{CFG_PATH, TEMPLATE_DIR},
+    aes_gcm::{
+        aead::{generic_array::GenericArray, Aead, NewAead},
+        Aes256Gcm,
+    },
+    rand::Rng,
+    ring::{digest, pbkdf2},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs, num::NonZeroU32, path::PathBuf},
+};
+
+lazy_static::lazy_static! {
+    static ref SYNC_STATE_FILE: String = format!("{}/sync_state.json", &*CFG_PATH);
+    static ref SYNC_URL_FILE: String = format!("{}/sync_url", &*CFG_PATH);
+}
+
+// the entries always synced, besides the templates
+const SYNC_FILES: [&str; 5] = [
+    "serv_addr",
+    "change_index",
+    "txo_labels.json",
+    "invoices.json",
+    "deposits.json",
+];
+
+const MNEMONIC_ENTRY: &str = "mnemonic";
+
+const SALT_LEN: usize = 32;
+const IV_LEN: usize = 12;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// An entry of the remote snapshot
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SyncEntry {
+    #[allow(missing_docs)]
+    pub version: u64,
+    /// The content in base64, `None` if the entry is removed
+    pub data: Option<String>,
+    /// Encrypted with the sync password
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+/// The remote snapshot, entries by their paths in the config directory
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SyncSnapshot {
+    #[allow(missing_docs)]
+    pub entries: BTreeMap<String, SyncEntry>,
+}
+
+// an entry as of the last sync
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct SyncedEntry {
+    version: u64,
+    // hash of the plaintext, `None` if the entry is removed
+    hash: Option<String>,
+}
+
+/// The side to keep on conflicts
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Prefer {
+    #[allow(missing_docs)]
+    Local,
+    #[allow(missing_docs)]
+    Remote,
+}
+
+/// Sync with the snapshot at `url`, or the one of the last sync if `None`.
+///
+/// The mnemonic is pushed only with `with_keys`, and is pulled
+/// only if `password` is given.
+pub fn sync(
+    url: Option<&str>,
+    password: Option<&str>,
+    with_keys: bool,
+    prefer: Option<Prefer>,
+) -> Result<()> {
+    let url = match url {
+        Some(url) => url.to_owned(),
+        None => fs::read_to_string(&*SYNC_URL_FILE)
+            .map(|u| u.trim().to_owned())
+            .c(d!("no sync url, see `--url`"))?,
+    };
+    if with_keys && password.is_none() {
+        return Err(eg!("a password is needed to sync the mnemonic"));
+    }
+
+    let (mut remote, etag) = fetch(&url).c(d!())?;
+    let mut state = load_state().c(d!())?;
+    let mut local = read_local(with_keys || password.is_some()).c(d!())?;
+
+    let mut names = local.keys().cloned().collect::<Vec<_>>();
+    names.extend(state.keys().cloned());
+    names.extend(remote.entries.keys().cloned());
+    names.sort();
+    names.dedup();
+    // the entries of newer versions of `fn` are left alone
+    names.retain(|n| {
+        (with_keys || password.is_some() || MNEMONIC_ENTRY != n) && local_path(n).is_ok()
+    });
+
+    let mut pushed = vec![];
+    let mut pulled = vec![];
+    let mut conflicts = vec![];
+    for name in names {
+        let base = state.get(&name).cloned().unwrap_or_default();
+        let local_data = local.get(&name).cloned();
+        let local_hash = local_data.as_deref().map(hash);
+        let remote_entry = remote.entries.get(&name).cloned().unwrap_or_default();
+        let remote_data = match remote_entry.data.as_deref() {
+            Some(d) => {
+                let d = base64::decode(d).c(d!())?;
+                if remote_entry.encrypted {
+                    match password {
+                        Some(pw) => Some(decrypt(&d, pw).c(d!(name.clone()))?),
+                        // can not be pulled
+                        None => continue,
+                    }
+                } else {
+                    Some(d)
+                }
+            }
+            None => None,
+        };
+        let remote_hash = remote_data.as_deref().map(hash);
+
+        let local_changed = local_hash != base.hash;
+        let remote_changed = remote_entry.version > base.version;
+        let use_local = match (local_changed, remote_changed) {
+            (false, false) => continue,
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) if local_hash == remote_hash => false,
+            (true, true) => match prefer {
+                Some(Prefer::Local) => true,
+                Some(Prefer::Remote) => false,
+                None => {
+                    conflicts.push(name);
+                    continue;
+                }
+            },
+        };
+
+        if use_local {
+            // never push the mnemonic unless asked
+            if MNEMONIC_ENTRY == name && !with_keys {
+                continue;
+            }
+            let version = 1 + remote_entry.version.max(base.version);
+            let data = match local_data.as_deref() {
+                Some(d) if MNEMONIC_ENTRY == name => {
+                    Some(encrypt(d, password.c(d!())?).c(d!())?)
+                }
+                Some(d) => Some(d.to_vec()),
+                None => None,
+            };
+            remote.entries.insert(
+                name.clone(),
+                SyncEntry {
+                    version,
+                    data: data.map(base64::encode),
+                    encrypted: MNEMONIC_ENTRY == name,
+                },
+            );
+            state.insert(
+                name.clone(),
+                SyncedEntry {
+                    version,
+                    hash: local_hash,
+                },
+            );
+            pushed.push(name);
+        } else {
+            match remote_data {
+                Some(d) => local.insert(name.clone(), d),
+                None => local.remove(&name),
+            };
+            state.insert(
+                name.clone(),
+                SyncedEntry {
+                    version: remote_entry.version,
+                    hash: remote_hash,
+                },
+            );
+            pulled.push(name);
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(eg!(format!(
+            "changed on both sides: {}, see `--prefer`",
+            conflicts.join(", ")
+        )));
+    }
+
+    if !pushed.is_empty() {
+        upload(&url, &remote, etag.as_deref()).c(d!())?;
+    }
+    for name in pulled.iter() {
+        let path = local_path(name).c(d!())?;
+        match local.get(name) {
+            Some(d) => {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).c(d!())?;
+                }
+                fs::write(&path, d).c(d!())?;
+            }
+            None => {
+                omit!(fs::remove_file(&path));
+            }
+        }
+    }
+    save_state(&state).c(d!())?;
+    fs::write(&*SYNC_URL_FILE, &url).c(d!())?;
+
+    pushed.iter().for_each(|n| println!("pushed: {}", n));
+    pulled.iter().for_each(|n| println!("pulled: {}", n));
+
+    Ok(())
+}
+
+// the snapshot, with its etag if the server gives one
+fn fetch(url: &str) -> Result<(SyncSnapshot, Option<String>)> {
+    let resp = attohttpc::get(url).send().c(d!())?;
+    if resp.status() == attohttpc::StatusCode::NOT_FOUND {
+        return Ok((SyncSnapshot::default(), None));
+    }
+    let resp = resp.error_for_status().c(d!())?;
+    let etag = resp
+        .headers()
+        .get(attohttpc::header::ETAG)
+        .and_then(|e| e.to_str().ok())
+        .map(|e| e.to_owned());
+    resp.bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+        .map(|s| (s, etag))
+}
+
+// the etag guards against the pushes of others in the meantime
+fn upload(url: &str, snapshot: &SyncSnapshot, etag: Option<&str>) -> Result<()> {
+    let mut req = attohttpc::put(url);
+    if let Some(etag) = etag {
+        req = req.header(attohttpc::header::IF_MATCH, etag);
+    }
+    let resp = req.json(snapshot).c(d!())?.send().c(d!())?;
+    if resp.status() == attohttpc::StatusCode::PRECONDITION_FAILED {
+        return Err(eg!("the remote snapshot is changed during the sync, retry"));
+    }
+    resp.error_for_status().c(d!()).map(|_| ())
+}
+
+fn read_local(with_mnemonic: bool) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut names = SYNC_FILES.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    if with_mnemonic {
+        names.push(MNEMONIC_ENTRY.to_owned());
+    }
+    if let Ok(dir) = fs::read_dir(&*TEMPLATE_DIR) {
+        names.extend(dir.filter_map(|e| e.ok()).filter_map(|e| {
+            e.file_name()
+                .to_str()
+                .filter(|n| n.ends_with(".json"))
+                .map(|n| format!("templates/{}", n))
+        }));
+    }
+
+    let mut local = BTreeMap::new();
+    for name in names {
+        if let Ok(d) = fs::read(local_path(&name).c(d!())?) {
+            local.insert(name, d);
+        }
+    }
+    Ok(local)
+}
+
+// only the known entries, so a snapshot can not write elsewhere
+fn local_path(name: &str) -> Result<PathBuf> {
+    let known = SYNC_FILES.contains(&name)
+        || MNEMONIC_ENTRY == name
+        || name
+            .strip_prefix("templates/")
+            .and_then(|n| n.strip_suffix(".json"))
+            .map_or(false, |n| {
+                !n.is_empty()
+                    && n.chars()
+                        .all(|c| c.is_ascii_alphanumeric() || '-' == c || '_' == c)
+            });
+    if !known {
+        return Err(eg!(format!("unknown entry: {}", name)));
+    }
+    Ok(PathBuf::from(&*CFG_PATH).join(name))
+}
+
+fn load_state() -> Result<BTreeMap<String, SyncedEntry>> {
+    match fs::read(&*SYNC_STATE_FILE) {
+        Ok(s) => serde_json::from_slice(&s).c(d!()),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+fn save_state(state: &BTreeMap<String, SyncedEntry>) -> Result<()> {
+    serde_json::to_vec_pretty(state)
+        .c(d!())
+        .and_then(|s| fs::write(&*SYNC_STATE_FILE, s).c(d!()))
+}
+
+#[inline(always)]
+fn hash(data: &[u8]) -> String {
+    hex::encode(digest::digest(&digest::SHA256, data))
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+        salt,
+        password.as_bytes(),
+        &mut key,
+    );
+    Aes256Gcm::new(GenericArray::from_slice(&key))
+}
+
+// salt || iv || ciphertext
+fn encrypt(data: &[u8], password: &str) -> Result<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; SALT_LEN];
+    rng.fill(&mut salt);
+    let mut iv = [0u8; IV_LEN];
+    rng.fill(&mut iv);
+
+    let ciphertext = cipher(password, &salt)
+        .encrypt(GenericArray::from_slice(&iv), data)
+        .map_err(|_| eg!("encryption failed"))?;
+    Ok([&salt[..], &iv[..], &ciphertext].concat())
+}
+
+fn decrypt(data: &[u8], password: &str) -> Result<Vec<u8>> {
+    if data.len() <= SALT_LEN + IV_LEN {
+        return Err(eg!("invalid ciphertext"));
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (iv, ciphertext) = rest.split_at(IV_LEN);
+    cipher(password, salt)
+        .decrypt(GenericArray::from_slice(iv), ciphertext)
+        .map_err(|_| eg!("wrong password"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_mnemonic_encryption() {
+        let mnemonic = b"abandon ability able about above absent absorb abstract";
+        let encrypted = pnk!(encrypt(mnemonic, "correct horse"));
+        assert_eq!(
+            &mnemonic[..],
+            &pnk!(decrypt(&encrypted, "correct horse"))[..]
+        );
+
+        // salted, never the same ciphertext
+        assert_ne!(encrypted, pnk!(encrypt(mnemonic, "correct horse")));
+
+        assert!(decrypt(&encrypted, "wrong horse").is_err());
+        assert!(decrypt(&encrypted, "").is_err());
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&tampered, "correct horse").is_err());
+        assert!(decrypt(&encrypted[..SALT_LEN + IV_LEN], "correct horse").is_err());
+    }
+}
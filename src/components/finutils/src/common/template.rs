@@ -0,0 +1,168 @@
+//!
+//! Reusable transfer templates, e.g. a monthly payroll to the same receivers,
+//! saved in the config directory of `fn` and instantiated with updated amounts.
+//!
+
+use {
+    super::{restore_keypair_from_str_with_default, utils, TEMPLATE_DIR},
+    globutils::{address, wallet},
+    ledger::data_model::AssetTypeCode,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{fs, path::PathBuf},
+};
+
+/// Max length of the name of a template
+pub const MAX_TEMPLATE_NAME_LEN: usize = 64;
+
+/// A saved transfer
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransferTemplate {
+    /// Code of the asset in base64, FRA if `None`
+    pub asset: Option<String>,
+    /// Addresses of the receivers in bech32, with their default amounts
+    pub receivers: Vec<(String, u64)>,
+    #[allow(missing_docs)]
+    pub memo: Option<String>,
+}
+
+impl TransferTemplate {
+    /// Receivers and amounts from the lines of `list`, each is `<address> <amount>`
+    pub fn parse(
+        list: &str,
+        asset: Option<&str>,
+        memo: Option<&str>,
+    ) -> Result<TransferTemplate> {
+        if let Some(code) = asset {
+            AssetTypeCode::new_from_base64(code).c(d!("invalid asset code"))?;
+        }
+        let receivers = list
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                let mut it = l.split_whitespace();
+                let pk = it
+                    .next()
+                    .c(d!())
+                    .and_then(|a| address::parse_address(a).c(d!(a)))?;
+                let am = it
+                    .next()
+                    .c(d!("missing amount"))
+                    .and_then(|a| a.parse::<u64>().c(d!(a)))?;
+                Ok((wallet::public_key_to_bech32(&pk), am))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if receivers.is_empty() {
+            return Err(eg!("no receivers"));
+        }
+        Ok(TransferTemplate {
+            asset: asset.map(|a| a.to_owned()),
+            receivers,
+            memo: memo.map(|m| m.to_owned()),
+        })
+    }
+
+    /// The receivers with the amounts of this time,
+    /// `amounts` replaces the default ones in order, a single one is for everyone
+    pub fn instantiate(&self, amounts: Option<&[u64]>) -> Result<Vec<(String, u64)>> {
+        let mut receivers = self.receivers.clone();
+        match amounts {
+            None => {}
+            Some([am]) => receivers.iter_mut().for_each(|(_, a)| *a = *am),
+            Some(ams) if ams.len() == receivers.len() => receivers
+                .iter_mut()
+                .zip(ams.iter())
+                .for_each(|((_, a), am)| *a = *am),
+            Some(_) => {
+                return Err(eg!(format!(
+                    "expect 1 or {} amounts",
+                    self.receivers.len()
+                )));
+            }
+        }
+        Ok(receivers)
+    }
+}
+
+/// Save `template` as `name`, an existing one is replaced
+pub fn save(name: &str, template: &TransferTemplate) -> Result<()> {
+    let path = template_path(name).c(d!())?;
+    fs::create_dir_all(&*TEMPLATE_DIR).c(d!())?;
+    serde_json::to_vec_pretty(template)
+        .c(d!())
+        .and_then(|t| fs::write(path, t).c(d!()))
+}
+
+#[allow(missing_docs)]
+pub fn load(name: &str) -> Result<TransferTemplate> {
+    let path = template_path(name).c(d!())?;
+    fs::read(&path)
+        .c(d!(format!("template `{}` not found", name)))
+        .and_then(|t| serde_json::from_slice(&t).c(d!()))
+}
+
+#[allow(missing_docs)]
+pub fn remove(name: &str) -> Result<()> {
+    template_path(name).and_then(|p| fs::remove_file(p).c(d!()))
+}
+
+/// Names of the saved templates
+pub fn list() -> Result<Vec<String>> {
+    let dir = match fs::read_dir(&*TEMPLATE_DIR) {
+        Ok(dir) => dir,
+        Err(_) => return Ok(vec![]),
+    };
+    let mut names = dir
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            e.file_name()
+                .to_str()
+                .and_then(|n| n.strip_suffix(".json"))
+                .map(|n| n.to_owned())
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
+
+/// Send the transfer of the template `name`, see `TransferTemplate::instantiate`
+pub fn send(name: &str, amounts: Option<&[u64]>, sk_str: Option<&str>) -> Result<()> {
+    let template = load(name).c(d!())?;
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+    let code = template
+        .asset
+        .as_deref()
+        .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+        .transpose()?;
+    let receivers = template
+        .instantiate(amounts)
+        .c(d!())?
+        .into_iter()
+        .map(|(addr, am)| wallet::public_key_from_bech32(&addr).map(|pk| (pk, am)))
+        .collect::<Result<Vec<_>>>()
+        .c(d!())?;
+
+    utils::transfer_batch_x(
+        &kp,
+        receivers.iter().map(|(pk, am)| (pk, *am)).collect(),
+        code,
+        false,
+        false,
+        None,
+        template.memo.as_ref().map(|m| m.as_bytes()),
+    )
+    .c(d!())
+}
+
+fn template_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty()
+        || name.len() > MAX_TEMPLATE_NAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || '-' == c || '_' == c)
+    {
+        return Err(eg!("invalid template name"));
+    }
+    Ok(PathBuf::from(&*TEMPLATE_DIR).join(format!("{}.json", name)))
+}
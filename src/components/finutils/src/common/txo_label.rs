@@ -0,0 +1,112 @@
+//!
+//! Local labels of unspent outputs, e.g. "treasury" or "do-not-spend",
+//! saved in the config directory of `fn`.
+//!
+//! Frozen outputs are left out of the automatic coin selection of transfers.
+//!
+
+use {
+    super::{restore_keypair_from_str_with_default, utils, TXO_LABEL_FILE},
+    ledger::data_model::{AssetTypeCode, TxoSID},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::asset_record::open_blind_asset_record,
+};
+
+/// Max length of a label
+pub const MAX_TXO_LABEL_LEN: usize = 64;
+
+/// The local attributes of an output
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TxoLabel {
+    #[allow(missing_docs)]
+    pub label: Option<String>,
+    /// Not spent by automatic coin selection
+    pub frozen: bool,
+}
+
+impl TxoLabel {
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.label.is_none() && !self.frozen
+    }
+}
+
+/// All labels, by the ids of the outputs
+pub fn load() -> Result<BTreeMap<TxoSID, TxoLabel>> {
+    match fs::read(&*TXO_LABEL_FILE) {
+        Ok(l) => serde_json::from_slice(&l).c(d!()),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+/// Ids of the frozen outputs
+pub fn get_frozen() -> Result<Vec<TxoSID>> {
+    load().c(d!()).map(|labels| {
+        labels
+            .into_iter()
+            .filter(|(_, l)| l.frozen)
+            .map(|(sid, _)| sid)
+            .collect()
+    })
+}
+
+/// Label an output, an existing label is replaced
+pub fn set_label(sid: TxoSID, label: &str) -> Result<()> {
+    if label.trim().is_empty() || label.len() > MAX_TXO_LABEL_LEN {
+        return Err(eg!("invalid length of the label"));
+    }
+    update(sid, |l| l.label = Some(label.to_owned())).c(d!())
+}
+
+/// Freeze or unfreeze an output
+pub fn set_frozen(sid: TxoSID, frozen: bool) -> Result<()> {
+    update(sid, |l| l.frozen = frozen).c(d!())
+}
+
+/// Remove the label of an output, and unfreeze it
+pub fn clear(sid: TxoSID) -> Result<()> {
+    update(sid, |l| *l = TxoLabel::default()).c(d!())
+}
+
+/// Print the unspent outputs of a findora account, with their labels
+pub fn show(sk_str: Option<&str>) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+    let labels = load().c(d!())?;
+    let mut utxos = utils::get_owned_utxos(kp.get_pk_ref())
+        .c(d!())?
+        .into_iter()
+        .collect::<Vec<_>>();
+    utxos.sort_by_key(|(sid, _)| sid.0);
+
+    for (sid, (utxo, owner_memo)) in utxos {
+        let oar = open_blind_asset_record(&utxo.0.record, &owner_memo, &kp).c(d!())?;
+        let l = labels.get(&sid).cloned().unwrap_or_default();
+        println!(
+            "txo {}: {} {}{}{}",
+            sid.0,
+            oar.amount,
+            AssetTypeCode {
+                val: oar.asset_type
+            }
+            .to_base64(),
+            l.label.map(|l| format!(" [{}]", l)).unwrap_or_default(),
+            alt!(l.frozen, " (frozen)", "")
+        );
+    }
+
+    Ok(())
+}
+
+fn update(sid: TxoSID, f: impl FnOnce(&mut TxoLabel)) -> Result<()> {
+    let mut labels = load().c(d!())?;
+    let l = labels.entry(sid).or_default();
+    f(l);
+    if l.is_empty() {
+        labels.remove(&sid);
+    }
+    serde_json::to_vec_pretty(&labels)
+        .c(d!())
+        .and_then(|l| fs::write(&*TXO_LABEL_FILE, l).c(d!()))
+}
@@ -5,21 +5,25 @@
 use {
     crate::{
         api::{DelegationInfo, ValidatorDetail},
-        common::get_serv_addr,
+        common::{get_serv_addr, memo_relay, signing_log, txo_label, vesting},
         txn_builder::{TransactionBuilder, TransferOperationBuilder},
     },
     globutils::{wallet, HashOf, SignatureOf},
     ledger::{
         data_model::{
-            AssetType, AssetTypeCode, DefineAsset, Operation, StateCommitmentData,
-            Transaction, TransferType, TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA,
-            BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+            AssetMirror, AssetType, AssetTypeCode, CancelTxn, DefineAsset,
+            FinalizedBlock, Operation, StateCommitmentData, Transaction, TransferType,
+            TxnCheckError, TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
+            TX_FEE_MIN,
         },
         staking::{init::get_inital_validators, TendermintAddrRef, FRA_TOTAL_AMOUNT},
     },
     ruc::*,
     serde::{self, Deserialize, Serialize},
-    std::collections::HashMap,
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        sync::atomic::{AtomicU64, Ordering},
+    },
     tendermint::{PrivateKey, PublicKey},
     zei::xfr::{
         asset_record::{open_blind_asset_record, AssetRecordType},
@@ -32,24 +36,67 @@ use {
 // Part 1: utils for transfer assets //
 ///////////////////////////////////////
 
+// the `--ttl` of the command, 0 if not set
+static TX_TTL: AtomicU64 = AtomicU64::new(0);
+
+/// Transactions built later expire if not included
+/// within `ttl` blocks after the current one
 #[inline(always)]
+pub fn set_tx_ttl(ttl: u64) {
+    TX_TTL.store(ttl, Ordering::Relaxed);
+}
+
 #[allow(missing_docs)]
 pub fn new_tx_builder() -> Result<TransactionBuilder> {
-    get_seq_id().c(d!()).map(TransactionBuilder::from_seq_id)
+    let mut builder = get_seq_id().c(d!()).map(TransactionBuilder::from_seq_id)?;
+    let ttl = TX_TTL.load(Ordering::Relaxed);
+    if 0 < ttl {
+        let height = get_network_status(get_serv_addr().c(d!())?)
+            .c(d!())
+            .and_then(|ts| ts.sync_info.latest_block_height.parse::<u64>().c(d!()))?;
+        builder.set_expiry_height(height + ttl);
+    }
+    Ok(builder)
 }
 
-#[inline(always)]
-#[allow(missing_docs)]
+/// Send a transaction to the submission server,
+/// its signing is recorded in the signing log first.
 pub fn send_tx(tx: &Transaction) -> Result<()> {
+    signing_log::record(tx).c(d!("fail to record the signing"))?;
+
     let url = format!("{}:8669/submit_transaction", get_serv_addr().c(d!())?);
-    attohttpc::post(&url)
+    let resp = attohttpc::post(&url)
         .header(attohttpc::header::CONTENT_TYPE, "application/json")
         .bytes(&serde_json::to_vec(tx).c(d!())?)
         .send()
-        .c(d!("fail to send transaction"))?
+        .c(d!("fail to send transaction"))?;
+    if resp.is_success() {
+        return Ok(());
+    }
+
+    // malformed transactions are answered with the reason in JSON
+    let status = resp.status();
+    let body = resp.text().c(d!())?;
+    match serde_json::from_str::<TxnCheckError>(&body) {
+        Ok(e) => Err(eg!(format!("transaction rejected: {}", e))),
+        Err(_) => Err(eg!(format!("{}: {}", status, body))),
+    }
+}
+
+/// Cancel or replace a transaction not forwarded by the submission server yet,
+/// returns the handle of the replacement if any.
+pub fn cancel_tx(req: &CancelTxn) -> Result<Option<String>> {
+    let url = format!("{}:8669/cancel_transaction", get_serv_addr().c(d!())?);
+    attohttpc::post(&url)
+        .header(attohttpc::header::CONTENT_TYPE, "application/json")
+        .bytes(&serde_json::to_vec(req).c(d!())?)
+        .send()
+        .c(d!("fail to send the cancellation"))?
         .error_for_status()
+        .c(d!())?
+        .bytes()
         .c(d!())
-        .map(|_| ())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
 }
 
 /// Fee is needless in a `UpdateValidator` operation
@@ -97,15 +144,42 @@ pub fn transfer_batch(
     token_code: Option<AssetTypeCode>,
     confidential_am: bool,
     confidential_ty: bool,
+) -> Result<()> {
+    transfer_batch_x(
+        owner_kp,
+        target_list,
+        token_code,
+        confidential_am,
+        confidential_ty,
+        None,
+        None,
+    )
+    .c(d!())
+}
+
+/// Same as `transfer_batch`, but the changes will be sent to `change_to` if specified,
+/// and `memo` will be encrypted to every receiver if specified.
+pub fn transfer_batch_x(
+    owner_kp: &XfrKeyPair,
+    target_list: Vec<(&XfrPublicKey, u64)>,
+    token_code: Option<AssetTypeCode>,
+    confidential_am: bool,
+    confidential_ty: bool,
+    change_to: Option<&XfrPublicKey>,
+    memo: Option<&[u8]>,
 ) -> Result<()> {
     let mut builder = new_tx_builder().c(d!())?;
-    let op = gen_transfer_op(
+    let op = gen_transfer_op_xx(
+        None,
         owner_kp,
         target_list,
         token_code,
+        true,
         confidential_am,
         confidential_ty,
         None,
+        change_to,
+        memo,
     )
     .c(d!())?;
     builder.add_operation(op);
@@ -158,11 +232,15 @@ pub fn gen_transfer_op_x(
         confidential_am,
         confidential_ty,
         balance_type,
+        None,
+        None,
     )
     .c(d!())
 }
 
-#[allow(missing_docs)]
+/// @change_to: the receiver of changes, default to the owner itself;
+///     a fresh key can be used here to improve the unlinkability
+/// @memo: encrypted to every receiver in `target_list`, see `EncryptedMemo`
 #[allow(clippy::too_many_arguments)]
 pub fn gen_transfer_op_xx(
     rpc_endpoint: Option<&str>,
@@ -173,6 +251,8 @@ pub fn gen_transfer_op_xx(
     confidential_am: bool,
     confidential_ty: bool,
     balance_type: Option<AssetRecordType>,
+    change_to: Option<&XfrPublicKey>,
+    memo: Option<&[u8]>,
 ) -> Result<Operation> {
     let mut op_fee: u64 = 0;
     if auto_fee {
@@ -191,11 +271,31 @@ pub fn gen_transfer_op_xx(
         op_fee = 0;
     }
     let mut i_am;
-    let utxos = get_owned_utxos_x(rpc_endpoint, owner_kp.get_pk_ref())
+    // (amount, asset type, record type) of the changes to `change_to`
+    let mut changes = vec![];
+    let mut utxos = get_owned_utxos_x(rpc_endpoint, owner_kp.get_pk_ref())
         .c(d!())?
         .into_iter();
+    // the locked amounts would have to be carried over, leave them alone
+    let locked = vesting::get_vesting_x(rpc_endpoint, owner_kp.get_pk_ref())
+        .map(|v| {
+            v.outputs
+                .into_iter()
+                .filter(|o| 0 < o.locked)
+                .map(|o| o.sid)
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+    // the outputs frozen in the local labels are never selected
+    let frozen = txo_label::get_frozen()
+        .c(d!())?
+        .into_iter()
+        .collect::<HashSet<_>>();
 
-    for (sid, (utxo, owner_memo)) in utxos {
+    for (sid, (utxo, owner_memo)) in utxos.by_ref() {
+        if locked.contains(&sid) || frozen.contains(&sid) {
+            continue;
+        }
         let oar =
             open_blind_asset_record(&utxo.0.record, &owner_memo, owner_kp).c(d!())?;
 
@@ -206,6 +306,11 @@ pub fn gen_transfer_op_xx(
             alt!(oar.amount < op_fee, i_am = oar.amount, i_am = op_fee);
             op_fee -= i_am;
 
+            if change_to.is_some() && i_am < oar.amount {
+                changes.push((oar.amount - i_am, oar.asset_type, oar.get_record_type()));
+                i_am = oar.amount;
+            }
+
             trans_builder
                 .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
                 .c(d!())?;
@@ -216,6 +321,11 @@ pub fn gen_transfer_op_xx(
             //am = am.saturating_sub(i_am);
             am -= i_am;
 
+            if change_to.is_some() && i_am < oar.amount {
+                changes.push((oar.amount - i_am, oar.asset_type, oar.get_record_type()));
+                i_am = oar.amount;
+            }
+
             trans_builder
                 .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
                 .c(d!())?;
@@ -261,10 +371,66 @@ pub fn gen_transfer_op_xx(
         )
     });
 
-    for output in outputs {
+    // the fee output comes first
+    let first_idx = alt!(auto_fee, 1, 0);
+    for (i, output) in outputs.enumerate() {
         trans_builder
             .add_output(&output, None, None, None)
             .c(d!())?;
+        if let Some(msg) = memo {
+            trans_builder.attach_memo(first_idx + i, msg).c(d!())?;
+        }
+    }
+
+    // inputs have been fully spent, so nothing will be sent back to the owner
+    if let Some(pk) = change_to {
+        for (n, ty, rt) in changes {
+            trans_builder
+                .add_output(
+                    &AssetRecordTemplate::with_no_asset_tracing(
+                        n,
+                        ty,
+                        balance_type.unwrap_or(rt),
+                        *pk,
+                    ),
+                    None,
+                    None,
+                    None,
+                )
+                .c(d!())?;
+        }
+    }
+
+    // the dust of the spent assets is merged into the changes,
+    // so that no output of the owner is left below the dust limits
+    let dust_limits = get_dust_limits(rpc_endpoint).c(d!())?;
+    if !dust_limits.is_empty() {
+        let spent = [asset_type, alt!(auto_fee, ASSET_TYPE_FRA, asset_type)];
+        for (sid, (utxo, owner_memo)) in utxos {
+            if frozen.contains(&sid) {
+                continue;
+            }
+            let oar =
+                match open_blind_asset_record(&utxo.0.record, &owner_memo, owner_kp) {
+                    Ok(oar) if spent.contains(&oar.asset_type) => oar,
+                    _ => continue,
+                };
+            let code = AssetTypeCode {
+                val: oar.asset_type,
+            };
+            if dust_limits.get(&code).map_or(false, |l| oar.amount < *l) {
+                trans_builder
+                    .add_input(TxoRef::Absolute(sid), oar, None, None, 0)
+                    .c(d!())?;
+            }
+        }
+        trans_builder
+            .merge_changes(
+                change_to.unwrap_or_else(|| owner_kp.get_pk_ref()),
+                balance_type,
+                &dust_limits,
+            )
+            .c(d!())?;
     }
 
     trans_builder
@@ -278,6 +444,163 @@ pub fn gen_transfer_op_xx(
         .c(d!())
 }
 
+/// Send several asset types to `target` in one `TransferAsset`,
+/// so either all of them are transferred or none of them.
+///
+/// @assets: (asset type, amount), FRA if the type is `None`
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_multi_asset(
+    owner_kp: &XfrKeyPair,
+    target: &XfrPublicKey,
+    assets: &[(Option<AssetTypeCode>, u64)],
+    confidential_am: bool,
+    confidential_ty: bool,
+    change_to: Option<&XfrPublicKey>,
+    memo: Option<&[u8]>,
+) -> Result<()> {
+    let mut builder = new_tx_builder().c(d!())?;
+    let op = gen_multi_asset_transfer_op(
+        owner_kp,
+        target,
+        assets,
+        confidential_am,
+        confidential_ty,
+        change_to,
+        memo,
+    )
+    .c(d!())?;
+    builder.add_operation(op);
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(owner_kp);
+
+    send_tx(&tx).c(d!())
+}
+
+/// The fee is paid within the same operation,
+/// amounts of each asset type are balanced on their own.
+#[allow(clippy::too_many_arguments)]
+pub fn gen_multi_asset_transfer_op(
+    owner_kp: &XfrKeyPair,
+    target: &XfrPublicKey,
+    assets: &[(Option<AssetTypeCode>, u64)],
+    confidential_am: bool,
+    confidential_ty: bool,
+    change_to: Option<&XfrPublicKey>,
+    memo: Option<&[u8]>,
+) -> Result<Operation> {
+    let fra = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+    let assets = assets
+        .iter()
+        .map(|(code, am)| (code.unwrap_or(fra), *am))
+        .collect::<Vec<_>>();
+
+    let mut needs = HashMap::new();
+    needs.insert(fra, TX_FEE_MIN);
+    for (code, am) in assets.iter() {
+        let need = needs.entry(*code).or_insert(0u64);
+        *need = need.checked_add(*am).c(d!("overflow"))?;
+    }
+
+    let mut trans_builder = TransferOperationBuilder::new();
+
+    // (amount, asset type, record type) of the changes to `change_to`
+    let mut changes = vec![];
+    let frozen = txo_label::get_frozen().c(d!())?;
+    for (sid, (utxo, owner_memo)) in get_owned_utxos(owner_kp.get_pk_ref()).c(d!())? {
+        if frozen.contains(&sid) {
+            continue;
+        }
+        let oar =
+            open_blind_asset_record(&utxo.0.record, &owner_memo, owner_kp).c(d!())?;
+        let need = match needs.get_mut(&AssetTypeCode {
+            val: oar.asset_type,
+        }) {
+            Some(n) if 0 < *n => n,
+            _ => continue,
+        };
+
+        let mut i_am = alt!(oar.amount < *need, oar.amount, *need);
+        *need -= i_am;
+
+        if change_to.is_some() && i_am < oar.amount {
+            changes.push((oar.amount - i_am, oar.asset_type, oar.get_record_type()));
+            i_am = oar.amount;
+        }
+
+        trans_builder
+            .add_input(TxoRef::Absolute(sid), oar, None, None, i_am)
+            .c(d!())?;
+
+        alt!(needs.values().all(|n| 0 == *n), break);
+    }
+
+    if let Some((code, _)) = needs.iter().find(|(_, n)| 0 != **n) {
+        return Err(eg!(format!("insufficient balance of {}", code.to_base64())));
+    }
+
+    trans_builder
+        .add_output(
+            &AssetRecordTemplate::with_no_asset_tracing(
+                TX_FEE_MIN,
+                ASSET_TYPE_FRA,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                *BLACK_HOLE_PUBKEY,
+            ),
+            None,
+            None,
+            None,
+        )
+        .c(d!())?;
+
+    let art = match (confidential_am, confidential_ty) {
+        (true, true) => AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+        (true, false) => AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        (false, true) => AssetRecordType::NonConfidentialAmount_ConfidentialAssetType,
+        _ => AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+    };
+
+    // the fee output comes first
+    for (i, (code, am)) in assets.into_iter().enumerate() {
+        trans_builder
+            .add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(am, code.val, art, *target),
+                None,
+                None,
+                None,
+            )
+            .c(d!())?;
+        if let Some(msg) = memo {
+            trans_builder.attach_memo(1 + i, msg).c(d!())?;
+        }
+    }
+
+    if let Some(pk) = change_to {
+        for (n, ty, rt) in changes {
+            trans_builder
+                .add_output(
+                    &AssetRecordTemplate::with_no_asset_tracing(n, ty, rt, *pk),
+                    None,
+                    None,
+                    None,
+                )
+                .c(d!())?;
+        }
+    }
+
+    trans_builder
+        .balance_all(None)
+        .c(d!())?
+        .create(TransferType::Standard)
+        .c(d!())?
+        .sign(owner_kp)
+        .c(d!())?
+        .transaction()
+        .c(d!())
+}
+
 /// for scenes that need to pay a standalone fee without other transfers
 #[inline(always)]
 #[allow(missing_docs)]
@@ -399,6 +722,37 @@ pub fn get_local_block_height() -> u64 {
     get_block_height(addr)
 }
 
+/// Retrieve the tendermint addresses of the validators who signed the specified block,
+/// these signatures are recorded in the `last_commit` of the next block.
+pub fn get_block_signers(height: u64) -> Result<Vec<String>> {
+    let url = format!(
+        "{}:26657/block?height={}",
+        get_serv_addr().c(d!())?,
+        1 + height
+    );
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).c(d!()))
+        .and_then(|v| {
+            v["result"]["block"]["last_commit"]["signatures"]
+                .as_array()
+                .c(d!("server returned invalid data"))
+                .map(|sigs| {
+                    sigs.iter()
+                        .filter_map(|s| s["validator_address"].as_str())
+                        .filter(|addr| !addr.is_empty())
+                        .map(|addr| addr.to_uppercase())
+                        .collect()
+                })
+        })
+}
+
 /// Retrieve custom asset(aka token) type of a findora network with asset code
 pub fn get_asset_type(code: &str) -> Result<AssetType> {
     let url = format!("{}:8668/asset_token/{}", get_serv_addr().c(d!())?, code);
@@ -413,6 +767,45 @@ pub fn get_asset_type(code: &str) -> Result<AssetType> {
         .and_then(|b| serde_json::from_slice::<AssetType>(&b).c(d!()))
 }
 
+/// Retrieve the least issuance sequence number accepted for an asset,
+/// `None` if it has not been issued
+pub fn get_issuance_num(code: &AssetTypeCode) -> Result<Option<u64>> {
+    let url = format!(
+        "{}:8668/asset_issuance_num/{}",
+        get_serv_addr().c(d!())?,
+        code.to_base64()
+    );
+
+    let resp = attohttpc::get(&url).send().c(d!())?;
+    if resp.status() == attohttpc::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    resp.error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<u64>(&b).c(d!()))
+        .map(Some)
+}
+
+/// Retrieve the mirrors of an asset on external chains
+pub fn get_asset_mirrors(code: &AssetTypeCode) -> Result<Vec<AssetMirror>> {
+    let url = format!(
+        "{}:8668/asset_mirrors/{}",
+        get_serv_addr().c(d!())?,
+        code.to_base64()
+    );
+
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
+
 /// Retrieve a list of assets created by the specified findora account
 pub fn get_created_assets(addr: &XfrPublicKey) -> Result<Vec<DefineAsset>> {
     let url = format!(
@@ -457,13 +850,40 @@ pub fn get_asset_balance(kp: &XfrKeyPair, asset: Option<AssetTypeCode>) -> Resul
     Ok(balance)
 }
 
-fn get_owned_utxos(
+/// The dust limits in force, the minimum amounts of the non-confidential outputs
+/// of transfers, empty if the node does not enforce any.
+pub fn get_dust_limits(
+    rpc_endpoint: Option<&str>,
+) -> Result<BTreeMap<AssetTypeCode, u64>> {
+    let default_endpoint = format!("{}:8668", get_serv_addr().c(d!())?);
+    let url = format!(
+        "{}/dust_limits",
+        rpc_endpoint.unwrap_or(default_endpoint.as_str())
+    );
+    let resp = attohttpc::get(&url).send().c(d!())?;
+    if resp.status() == attohttpc::StatusCode::NOT_FOUND {
+        return Ok(BTreeMap::new());
+    }
+
+    resp.error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<BTreeMap<String, u64>>(&b).c(d!()))?
+        .into_iter()
+        .map(|(code, limit)| AssetTypeCode::new_from_base64(&code).map(|c| (c, limit)))
+        .collect::<Result<_>>()
+        .c(d!())
+}
+
+/// Unspent outputs owned by a public key, along with their owner memos
+pub fn get_owned_utxos(
     addr: &XfrPublicKey,
 ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
     get_owned_utxos_x(None, addr).c(d!())
 }
 
-fn get_owned_utxos_x(
+pub(crate) fn get_owned_utxos_x(
     rpc_endpoint: Option<&str>,
     addr: &XfrPublicKey,
 ) -> Result<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>> {
@@ -474,7 +894,7 @@ fn get_owned_utxos_x(
         wallet::public_key_to_base64(addr)
     );
 
-    attohttpc::get(&url)
+    let mut utxos = attohttpc::get(&url)
         .send()
         .c(d!())?
         .error_for_status()
@@ -484,11 +904,30 @@ fn get_owned_utxos_x(
         .and_then(|b| {
             serde_json::from_slice::<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>>(&b)
                 .c(d!())
-        })
+        })?;
+
+    // the memos not carried by the transactions may have been relayed,
+    // the relay is optional, so it is only asked when some memo is missing
+    let missing = utxos.values().any(|(utxo, memo)| {
+        memo.is_none()
+            && (utxo.0.record.amount.is_confidential()
+                || utxo.0.record.asset_type.is_confidential())
+    });
+    if missing {
+        if let Ok(memos) = memo_relay::get_relayed_memos_x(rpc_endpoint, addr) {
+            for m in memos {
+                if let Some((_, memo @ None)) = utxos.get_mut(&m.txo_sid) {
+                    *memo = Some(m.memo);
+                }
+            }
+        }
+    }
+
+    Ok(utxos)
 }
 
 #[inline(always)]
-fn get_seq_id() -> Result<u64> {
+pub(crate) fn get_seq_id() -> Result<u64> {
     type Resp = (
         HashOf<Option<StateCommitmentData>>,
         u64,
@@ -508,6 +947,22 @@ fn get_seq_id() -> Result<u64> {
         .map(|resp| resp.1)
 }
 
+/// The ledger block `sid` of the node at `node`, `None` if it does not exist.
+pub fn get_block(node: &str, sid: usize) -> Result<Option<FinalizedBlock>> {
+    let url = format!("{}:8668/block_sid/{}", node, sid);
+    let resp = attohttpc::get(&url).send().c(d!())?;
+    if resp.status() == attohttpc::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    resp.error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+        .map(Some)
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn get_owner_memo_batch(ids: &[TxoSID]) -> Result<Vec<Option<OwnerMemo>>> {
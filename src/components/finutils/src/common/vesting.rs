@@ -0,0 +1,119 @@
+//!
+//! Vesting schedules on issued assets.
+//!
+//! The issuer issues to itself an output vesting by a schedule, the locked amount
+//! follows the assets when they are sent, see `ledger::converter::vesting`.
+//!
+
+use {
+    super::{get_keypair, get_serv_addr, utils},
+    globutils::{address, wallet},
+    ledger::{
+        converter::vesting::{Tranche, VestingSchedule},
+        data_model::{AssetTypeCode, TxoSID},
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// An unspent output with a vesting schedule
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VestingOutput {
+    #[allow(missing_docs)]
+    pub sid: TxoSID,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    #[allow(missing_docs)]
+    pub amount: u64,
+    /// The amount which can be spent freely in the next block
+    pub vested: u64,
+    /// The amount which must be carried over when spent in the next block
+    pub locked: u64,
+    #[allow(missing_docs)]
+    pub schedule: VestingSchedule,
+}
+
+/// The response of `vesting`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VestingStatus {
+    /// The last committed height
+    pub height: u64,
+    #[allow(missing_docs)]
+    pub outputs: Vec<VestingOutput>,
+    /// The locked amounts of all outputs, by asset
+    #[serde(with = "globutils::serde_pairs")]
+    pub locked: BTreeMap<AssetTypeCode, u64>,
+}
+
+/// Parse a tranche in the form of `amount:start:cliff:end`.
+pub fn parse_tranche(s: &str) -> Result<Tranche> {
+    let fields = s
+        .split(':')
+        .map(|f| f.trim().parse::<u64>().c(d!(f.to_owned())))
+        .collect::<Result<Vec<_>>>()?;
+    if let [amount, start, cliff, end] = fields[..] {
+        let t = Tranche {
+            amount,
+            start,
+            cliff,
+            end,
+        };
+        t.check().c(d!())?;
+        Ok(t)
+    } else {
+        Err(eg!("a tranche is in the form of `amount:start:cliff:end`"))
+    }
+}
+
+/// Issue `amount` of the asset `code` to the current key, vesting by `tranches`.
+pub fn issue(code: &str, amount: u64, tranches: Vec<Tranche>) -> Result<()> {
+    let kp = get_keypair().c(d!())?;
+    let code = AssetTypeCode::new_from_base64(code).c(d!())?;
+    let schedule = VestingSchedule { tranches };
+    schedule.check(amount).c(d!())?;
+
+    let mut builder = utils::new_tx_builder().c(d!())?;
+    builder
+        .add_vesting_issue_asset(&kp, &code, builder.get_seq_id(), amount, schedule)
+        .c(d!())?;
+    utils::gen_fee_op(&kp)
+        .c(d!())
+        .map(|op| builder.add_operation(op))?;
+
+    let mut tx = builder.take_transaction();
+    tx.sign_to_map(&kp);
+
+    utils::send_tx(&tx)
+}
+
+/// Query the vesting outputs owned by `addr`, default to the current key.
+pub fn get_vesting(addr: Option<&str>) -> Result<VestingStatus> {
+    let pk = match addr {
+        Some(addr) => address::parse_address(addr).c(d!())?,
+        None => get_keypair().c(d!())?.get_pk(),
+    };
+    get_vesting_x(None, &pk).c(d!())
+}
+
+#[allow(missing_docs)]
+pub fn get_vesting_x(
+    rpc_endpoint: Option<&str>,
+    addr: &XfrPublicKey,
+) -> Result<VestingStatus> {
+    let default_endpoint = format!("{}:8668", get_serv_addr().c(d!())?);
+    let url = format!(
+        "{}/vesting/{}",
+        rpc_endpoint.unwrap_or(default_endpoint.as_str()),
+        wallet::public_key_to_base64(addr)
+    );
+    attohttpc::get(&url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
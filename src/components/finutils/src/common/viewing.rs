@@ -0,0 +1,225 @@
+//!
+//! View keys of confidential outputs,
+//! an owner can hand them to an auditor to disclose the amounts and asset types
+//! of its confidential TXOs without granting spend authority.
+//!
+//! NOTE: owner memos are encrypted to the same secret that signs the inputs,
+//! so a view key is not a decryption key, but the openings of the confidential
+//! TXOs owned at export time, signed by the owner. TXOs received later are not
+//! covered, a new view key should be exported for them.
+//!
+
+use {
+    super::{get_serv_addr, restore_keypair_from_str_with_default, CFG_PATH},
+    crate::common::utils::get_owned_utxos,
+    globutils::{wallet, SignatureOf},
+    lazy_static::lazy_static,
+    ledger::data_model::{AssetTypeCode, AuthenticatedUtxo, TxoSID},
+    ruc::*,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{collections::BTreeMap, fs},
+    zei::xfr::{
+        asset_record::open_blind_asset_record,
+        sig::XfrPublicKey,
+        structs::{BlindAssetRecord, XfrAmount, XfrAssetType},
+    },
+};
+
+lazy_static! {
+    static ref VIEW_KEYS_DIR: String = format!("{}/view_keys", &*CFG_PATH);
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ViewEntry {
+    txo_sid: TxoSID,
+    record: BlindAssetRecord,
+    amount: u64,
+    asset_type: AssetTypeCode,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ViewKeyBody {
+    owner: XfrPublicKey,
+    // the block count of the ledger at export time
+    block_count: u64,
+    entries: Vec<ViewEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ViewKey {
+    body: ViewKeyBody,
+    signature: SignatureOf<ViewKeyBody>,
+}
+
+/// Export a view key of all confidential TXOs owned by the given key,
+/// default to the one set by `fn setup`.
+pub fn export_view_key(sk_str: Option<&str>, output: Option<&str>) -> Result<()> {
+    let kp = restore_keypair_from_str_with_default(sk_str).c(d!())?;
+
+    let (_, block_count, _) = http_get::<(serde_json::Value, u64, String)>(&format!(
+        "{}:8668/global_state",
+        get_serv_addr().c(d!())?
+    ))
+    .c(d!())?;
+
+    let mut utxos = get_owned_utxos(kp.get_pk_ref())
+        .c(d!())?
+        .into_iter()
+        .collect::<Vec<_>>();
+    utxos.sort_by_key(|(sid, _)| sid.0);
+
+    let mut entries = vec![];
+    for (sid, (utxo, memo)) in utxos {
+        let record = utxo.0.record;
+        if let (XfrAmount::NonConfidential(_), XfrAssetType::NonConfidential(_)) =
+            (&record.amount, &record.asset_type)
+        {
+            continue;
+        }
+        let oar = open_blind_asset_record(&record, &memo, &kp).c(d!())?;
+        entries.push(ViewEntry {
+            txo_sid: sid,
+            amount: oar.amount,
+            asset_type: AssetTypeCode {
+                val: oar.asset_type,
+            },
+            record,
+        });
+    }
+
+    let body = ViewKeyBody {
+        owner: kp.get_pk(),
+        block_count,
+        entries,
+    };
+    let vk = ViewKey {
+        signature: SignatureOf::new(&kp, &body),
+        body,
+    };
+    let contents = serde_json::to_string_pretty(&vk).c(d!())?;
+
+    if let Some(path) = output {
+        fs::write(path, contents).c(d!())
+    } else {
+        println!("{}", contents);
+        Ok(())
+    }
+}
+
+/// Verify a view key and save it locally, see `show_view_keys`:
+/// - the signature of the owner
+/// - every disclosed record is owned by the signer, and matches the one in the ledger
+///
+/// Spent TXOs are kept in the view key, but marked as spent when shown.
+pub fn import_view_key(path: &str) -> Result<()> {
+    let vk = fs::read(path)
+        .c(d!())
+        .and_then(|c| serde_json::from_slice::<ViewKey>(&c).c(d!()))?;
+
+    vk.signature
+        .verify(&vk.body.owner, &vk.body)
+        .c(d!("invalid signature"))?;
+
+    for e in vk.body.entries.iter() {
+        if e.record.public_key != vk.body.owner {
+            return Err(eg!("txo {} is not owned by the signer", e.txo_sid.0));
+        }
+        if let Some(proof) = get_utxo(e.txo_sid).c(d!())? {
+            if proof.utxo.0.record != e.record {
+                return Err(eg!("txo {} mismatch with the ledger", e.txo_sid.0));
+            }
+        }
+    }
+
+    fs::create_dir_all(&*VIEW_KEYS_DIR).c(d!())?;
+    let dest = format!(
+        "{}/{}.json",
+        &*VIEW_KEYS_DIR,
+        wallet::public_key_to_bech32(&vk.body.owner)
+    );
+    fs::write(&dest, serde_json::to_vec(&vk).c(d!())?).c(d!())?;
+
+    println!(
+        "View key of {} imported, {} confidential TXOs at block {}",
+        wallet::public_key_to_bech32(&vk.body.owner),
+        vk.body.entries.len(),
+        vk.body.block_count
+    );
+    Ok(())
+}
+
+/// Show the disclosed TXOs of all imported view keys,
+/// along with the per-asset totals of the unspent ones.
+pub fn show_view_keys() -> Result<()> {
+    let mut paths = match fs::read_dir(&*VIEW_KEYS_DIR) {
+        Ok(dir) => dir
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect::<Vec<_>>(),
+        Err(_) => vec![],
+    };
+    if paths.is_empty() {
+        println!("No view keys imported");
+        return Ok(());
+    }
+    paths.sort();
+
+    for p in paths {
+        let vk = fs::read(&p)
+            .c(d!())
+            .and_then(|c| serde_json::from_slice::<ViewKey>(&c).c(d!()))?;
+
+        println!(
+            "\x1b[31;01m{}\x1b[00m, exported at block {}:",
+            wallet::public_key_to_bech32(&vk.body.owner),
+            vk.body.block_count
+        );
+        let mut totals = BTreeMap::new();
+        for e in vk.body.entries.iter() {
+            let spent = get_utxo(e.txo_sid).c(d!())?.is_none();
+            println!(
+                "  txo {}: {} of {}{}",
+                e.txo_sid.0,
+                e.amount,
+                e.asset_type.to_base64(),
+                alt!(spent, " (spent)", "")
+            );
+            if !spent {
+                *totals.entry(e.asset_type.to_base64()).or_insert(0u128) +=
+                    e.amount as u128;
+            }
+        }
+        for (asset, amount) in totals.iter() {
+            println!("  total unspent {}: {}", asset, amount);
+        }
+    }
+
+    Ok(())
+}
+
+// `None` if the TXO has been spent
+fn get_utxo(sid: TxoSID) -> Result<Option<AuthenticatedUtxo>> {
+    let url = format!("{}:8668/utxo_sid/{}", get_serv_addr().c(d!())?, sid.0);
+    let resp = attohttpc::get(&url).send().c(d!())?;
+    if resp.status() == attohttpc::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    resp.error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+        .map(Some)
+}
+
+fn http_get<T: DeserializeOwned>(url: &str) -> Result<T> {
+    attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice(&b).c(d!()))
+}
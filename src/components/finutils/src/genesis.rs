@@ -0,0 +1,307 @@
+//!
+//! # Genesis
+//!
+//! `Genesis` describes the initial state of a chain: the assets with their
+//! allocations, the initial validator set and the chain parameters.
+//!
+//! It serializes deterministically, all maps are ordered, and it is turned into
+//! the transactions creating that state on an empty chain:
+//!
+//! - `validator_set_tx`, the initial validator set
+//! - `assets_tx`, the definitions and issuances of all assets, FRA first,
+//!   and the transfers of the allocations from the issuer
+//!
+//! The self-delegations of the validators, `Validator::td_power`, are signed
+//! by the validators themselves once they have received their allocations.
+//!
+
+use {
+    crate::txn_builder::{TransactionBuilder, TransferOperationBuilder},
+    config::abci::CheckPointConfig,
+    globutils::HashOf,
+    ledger::{
+        data_model::{
+            AssetRules, AssetTypeCode, Transaction, TransferType, TxOutput, TxoRef,
+            ASSET_TYPE_FRA, FRA_DECIMALS,
+        },
+        staking::{Validator, FRA_PRE_ISSUE_AMOUNT},
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeMap, BTreeSet},
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{
+                build_blind_asset_record, open_blind_asset_record, AssetRecordType,
+            },
+            sig::{XfrKeyPair, XfrPublicKey},
+            structs::AssetRecordTemplate,
+        },
+    },
+};
+
+/// An asset defined and issued in the genesis.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GenesisAsset {
+    #[allow(missing_docs)]
+    pub code: AssetTypeCode,
+    #[allow(missing_docs)]
+    pub memo: String,
+    #[allow(missing_docs)]
+    pub rules: AssetRules,
+    /// the amount issued to the issuer, in minimal units
+    pub issue_amount: u64,
+    /// `receiver => amount` transferred from the issuer
+    pub allocations: BTreeMap<XfrPublicKey, u64>,
+}
+
+impl GenesisAsset {
+    /// FRA, all issued amounts are pre-issued in the genesis.
+    pub fn fra() -> Self {
+        GenesisAsset {
+            code: AssetTypeCode {
+                val: ASSET_TYPE_FRA,
+            },
+            memo: "FRA".to_owned(),
+            rules: AssetRules {
+                max_units: Some(1000 + FRA_PRE_ISSUE_AMOUNT),
+                decimals: FRA_DECIMALS,
+                ..AssetRules::default()
+            },
+            issue_amount: FRA_PRE_ISSUE_AMOUNT,
+            allocations: BTreeMap::new(),
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        let allocated = self
+            .allocations
+            .values()
+            .try_fold(0u64, |acc, am| acc.checked_add(*am))
+            .c(d!("allocation overflow"))?;
+        if allocated > self.issue_amount {
+            return Err(eg!(
+                "the allocations of {} exceed its issued amount",
+                self.code.to_base64()
+            ));
+        }
+        if self.allocations.values().any(|am| 0 == *am) {
+            return Err(eg!("empty allocation"));
+        }
+        if let Some(max) = self.rules.max_units {
+            if self.issue_amount > max {
+                return Err(eg!(
+                    "the issued amount of {} exceeds its max units",
+                    self.code.to_base64()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The initial state of a chain.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Genesis {
+    /// the issuer of all assets
+    pub issuer: XfrPublicKey,
+    /// FRA first, in the order of their definitions
+    pub assets: Vec<GenesisAsset>,
+    /// the initial validator set, `td_power` is the self-delegation
+    pub validators: Vec<Validator>,
+    /// the chain parameters, if not the defaults
+    #[serde(default)]
+    pub checkpoint: Option<CheckPointConfig>,
+}
+
+impl Genesis {
+    /// A genesis issuing FRA to `issuer`, with no validators.
+    pub fn new(issuer: XfrPublicKey) -> Self {
+        Genesis {
+            issuer,
+            assets: vec![GenesisAsset::fra()],
+            validators: vec![],
+            checkpoint: None,
+        }
+    }
+
+    /// Transfer `amount` of the asset `code` from the issuer to `receiver`,
+    /// the allocations of a same receiver are added up.
+    pub fn allocate(
+        &mut self,
+        code: &AssetTypeCode,
+        receiver: XfrPublicKey,
+        amount: u64,
+    ) -> Result<&mut Self> {
+        let asset = self
+            .assets
+            .iter_mut()
+            .find(|a| a.code == *code)
+            .c(d!("asset not defined"))?;
+        let am = asset.allocations.entry(receiver).or_insert(0);
+        *am = am.checked_add(amount).c(d!("overflow"))?;
+        Ok(self)
+    }
+
+    /// Define and issue a custom asset.
+    pub fn add_asset(&mut self, asset: GenesisAsset) -> Result<&mut Self> {
+        if self.assets.iter().any(|a| a.code == asset.code) {
+            return Err(eg!("asset defined twice"));
+        }
+        self.assets.push(asset);
+        Ok(self)
+    }
+
+    #[allow(missing_docs)]
+    pub fn add_validator(&mut self, v: Validator) -> &mut Self {
+        self.validators.push(v);
+        self
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_checkpoint(&mut self, checkpoint: CheckPointConfig) -> &mut Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    #[allow(missing_docs)]
+    pub fn check(&self) -> Result<()> {
+        if self.assets.first().map(|a| a.code.val) != Some(ASSET_TYPE_FRA) {
+            return Err(eg!("FRA must be the first asset"));
+        }
+        for a in self.assets.iter() {
+            a.check().c(d!())?;
+        }
+
+        let mut td_addrs = BTreeSet::new();
+        for v in self.validators.iter() {
+            if !td_addrs.insert(&v.td_addr) {
+                return Err(eg!("duplicate validators"));
+            }
+            if 0 == v.td_power {
+                return Err(eg!("empty self-delegation"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The serialized genesis, same genesis, same bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).c(d!())
+    }
+
+    #[allow(missing_docs)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice::<Self>(bytes)
+            .c(d!())
+            .and_then(|g| g.check().c(d!()).map(|_| g))
+    }
+
+    #[allow(missing_docs)]
+    pub fn hash(&self) -> HashOf<Genesis> {
+        HashOf::new(self)
+    }
+
+    /// The transaction of the initial validator set.
+    pub fn validator_set_tx(&self, seq_id: u64) -> Result<Transaction> {
+        let mut builder = TransactionBuilder::from_seq_id(seq_id);
+        builder
+            .add_operation_update_validator(&[], 1, self.validators.clone())
+            .c(d!())?;
+        Ok(builder.take_transaction())
+    }
+
+    /// The transaction of the assets, signed by the issuer.
+    ///
+    /// No fee is needed, the definition of FRA is in it.
+    pub fn assets_tx(&self, issuer_kp: &XfrKeyPair, seq_id: u64) -> Result<Transaction> {
+        self.check().c(d!())?;
+        if issuer_kp.get_pk() != self.issuer {
+            return Err(eg!("not the key of the issuer"));
+        }
+
+        let mut builder = TransactionBuilder::from_seq_id(seq_id);
+        let params = PublicParams::default();
+
+        for a in self.assets.iter() {
+            builder
+                .add_operation_create_asset(
+                    issuer_kp,
+                    Some(a.code),
+                    a.rules.clone(),
+                    &a.memo,
+                )
+                .c(d!())?;
+            if 0 == a.issue_amount {
+                continue;
+            }
+
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                a.issue_amount,
+                a.code.val,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                self.issuer,
+            );
+            let (ba, _, _) = build_blind_asset_record(
+                &mut ChaChaRng::from_entropy(),
+                &params.pc_gens,
+                &template,
+                vec![],
+            );
+            let oar = open_blind_asset_record(&ba, &None, issuer_kp).c(d!())?;
+            let output = TxOutput {
+                id: None,
+                record: ba,
+                lien: None,
+                memo: None,
+            };
+            builder
+                .add_operation_issue_asset(issuer_kp, &a.code, 0, &[(output, None)])
+                .c(d!())?;
+            if a.allocations.is_empty() {
+                continue;
+            }
+
+            // the issued output is the last one of the transaction
+            let allocated: u64 = a.allocations.values().sum();
+            let mut trans_builder = TransferOperationBuilder::new();
+            trans_builder
+                .add_input(TxoRef::Relative(0), oar, None, None, allocated)
+                .c(d!())?;
+            for (pk, am) in a.allocations.iter() {
+                trans_builder
+                    .add_output(
+                        &AssetRecordTemplate::with_no_asset_tracing(
+                            *am,
+                            a.code.val,
+                            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                            *pk,
+                        ),
+                        None,
+                        None,
+                        None,
+                    )
+                    .c(d!())?;
+            }
+            let op = trans_builder
+                .balance(None)
+                .c(d!())?
+                .create(TransferType::Standard)
+                .c(d!())?
+                .sign(issuer_kp)
+                .c(d!())?
+                .transaction()
+                .c(d!())?;
+            builder.add_operation(op);
+        }
+
+        let mut tx = builder.take_transaction();
+        tx.sign_to_map(issuer_kp);
+
+        Ok(tx)
+    }
+}
@@ -5,8 +5,8 @@
 #![deny(warnings)]
 #![deny(missing_docs)]
 
-#[cfg(feature = "std")]
 pub mod api;
 #[cfg(feature = "std")]
 pub mod common;
+pub mod genesis;
 pub mod txn_builder;
@@ -6,19 +6,56 @@
 #![allow(clippy::needless_borrow)]
 
 use {
-    credentials::CredUserSecretKey,
+    credentials::{
+        CredIssuerPublicKey, CredUserSecretKey, Credential as PlatformCredential,
+    },
     curve25519_dalek::scalar::Scalar,
     fp_types::crypto::MultiSigner,
     globutils::SignatureOf,
     ledger::{
-        converter::ConvertAccount,
+        converter::{
+            designated_transfer::{
+                DesignatedId, DesignatedTransferOperation, ReleaseDesignated,
+                ReleaseDesignatedBody, SendDesignated, SendDesignatedBody,
+            },
+            ibc::{
+                CreateIbcClient, CreateIbcClientBody, IbcHash, IbcOperation,
+                SendIbcPacket, SendIbcPacketBody,
+            },
+            kv::{KvAction, KvOperation, KvOperationBody},
+            notary::{AnchorHash, AnchorHashBody},
+            payment_stream::{
+                CancelStream, CancelStreamBody, ClaimStream, ClaimStreamBody,
+                CreateStream, CreateStreamBody, PaymentStreamOperation, StreamId,
+            },
+            prism::{
+                PrismDeposit, PrismDepositBody, PrismSignature, PrismWithdraw,
+                PrismWithdrawBody, UpdatePrismAuthorities, UpdatePrismAuthoritiesBody,
+            },
+            pull_payment::{
+                AuthorizePull, AuthorizePullBody, Pull, PullAuthorizationId, PullBody,
+                PullPaymentOperation, RevokePull, RevokePullBody,
+            },
+            session_key::{
+                AuthorizeSession, AuthorizeSessionBody, RevokeSession,
+                RevokeSessionBody, SessionKeyOperation,
+            },
+            spending_policy::{
+                RemoveSpendingPolicy, RemoveSpendingPolicyBody, SetSpendingPolicy,
+                SetSpendingPolicyBody, SpendingPolicy, SpendingPolicyOperation,
+            },
+            vesting::VestingSchedule,
+            ConvertAccount,
+        },
         data_model::{
-            AssetRules, AssetTypeCode, ConfidentialMemo, DefineAsset, DefineAssetBody,
-            IndexedSignature, IssueAsset, IssueAssetBody, IssuerKeyPair,
-            IssuerPublicKey, Memo, NoReplayToken, Operation, Transaction,
-            TransactionBody, TransferAsset, TransferAssetBody, TransferType, TxOutput,
-            TxoRef, UpdateMemo, UpdateMemoBody, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
-            TX_FEE_MIN,
+            AssetMetadata, AssetRules, AssetTypeCode, ConfidentialMemo, DefineAsset,
+            DefineAssetBody, EncryptedMemo, IdentityDisclosure, IndexedSignature,
+            IssueAsset, IssueAssetBody, IssuerKeyPair, IssuerPublicKey, Memo,
+            NoReplayToken, Operation, TracerKeyRotation, Transaction, TransactionBody,
+            TransferAsset, TransferAssetBody, TransferType, TxOutput, TxoRef, TxoSID,
+            UpdateAssetMirror, UpdateAssetMirrorBody, UpdateMemo, UpdateMemoBody,
+            UpdateRevocation, UpdateRevocationBody, UpdateTracerKey,
+            UpdateTracerKeyBody, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
         },
         staking::{
             is_valid_tendermint_addr,
@@ -29,6 +66,7 @@ use {
                 governance::{ByzantineKind, GovernanceOps},
                 replace_staker::ReplaceStakerOps,
                 undelegation::UnDelegationOps,
+                unjail::UnjailOps,
                 update_staker::UpdateStakerOps,
                 update_validator::UpdateValidatorOps,
             },
@@ -42,7 +80,8 @@ use {
     serde::{Deserialize, Serialize},
     std::{
         cmp::Ordering,
-        collections::{BTreeMap, HashSet},
+        collections::{BTreeMap, HashMap, HashSet},
+        ops::Range,
     },
     tendermint::PrivateKey,
     zei::{
@@ -60,8 +99,8 @@ use {
             lib::XfrNotePolicies,
             sig::{XfrKeyPair, XfrPublicKey},
             structs::{
-                AssetRecord, AssetRecordTemplate, BlindAssetRecord, OpenAssetRecord,
-                OwnerMemo, TracingPolicies, TracingPolicy,
+                AssetRecord, AssetRecordTemplate, AssetTracerEncKeys, BlindAssetRecord,
+                OpenAssetRecord, OwnerMemo, TracingPolicies, TracingPolicy,
             },
         },
     },
@@ -321,9 +360,49 @@ impl TransactionBuilder {
                     id: None,
                     record: ba,
                     lien: None,
+                    memo: None,
+                },
+                owner_memo,
+            )],
+        )
+        .c(d!())
+    }
+
+    /// Issue `amount` to the issuer, in a non-confidential record vesting by `schedule`
+    pub fn add_vesting_issue_asset(
+        &mut self,
+        key_pair: &XfrKeyPair,
+        token_code: &AssetTypeCode,
+        seq_num: u64,
+        amount: u64,
+        schedule: VestingSchedule,
+    ) -> Result<&mut Self> {
+        let ar = AssetRecordTemplate::with_no_asset_tracing(
+            amount,
+            token_code.val,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            key_pair.get_pk(),
+        );
+        let (ba, _, owner_memo) = build_blind_asset_record(
+            &mut ChaChaRng::from_entropy(),
+            &PublicParams::default().pc_gens,
+            &ar,
+            vec![],
+        );
+        self.add_operation_vesting_issue_asset(
+            key_pair,
+            token_code,
+            seq_num,
+            &[(
+                TxOutput {
+                    id: None,
+                    record: ba,
+                    lien: None,
+                    memo: None,
                 },
                 owner_memo,
             )],
+            vec![(0, schedule)],
         )
         .c(d!())
     }
@@ -344,6 +423,13 @@ impl TransactionBuilder {
         self
     }
 
+    /// Reject the transaction if it is not included at or before
+    /// the tendermint height `height`, must be set before signing
+    pub fn set_expiry_height(&mut self, height: u64) -> &mut Self {
+        self.txn.body.expires_at = Some(height);
+        self
+    }
+
     /// Add asset creating operation to builder an return modified builder
     pub fn add_operation_create_asset(
         &mut self,
@@ -352,6 +438,8 @@ impl TransactionBuilder {
         asset_rules: AssetRules,
         memo: &str,
     ) -> Result<&mut Self> {
+        asset_rules.check().c(d!())?;
+        AssetMetadata::check_memo(&Memo(memo.into())).c(d!())?;
         let token_code = match token_code {
             Some(code) => code,
             None => AssetTypeCode::gen_random(),
@@ -384,15 +472,34 @@ impl TransactionBuilder {
         token_code: &AssetTypeCode,
         seq_num: u64,
         records_and_memos: &[(TxOutput, Option<OwnerMemo>)],
+    ) -> Result<&mut Self> {
+        self.add_operation_vesting_issue_asset(
+            key_pair,
+            token_code,
+            seq_num,
+            records_and_memos,
+            vec![],
+        )
+        .c(d!())
+    }
+
+    /// Add asset issuing operation, with vesting schedules on the records
+    /// at the given indices, to builder and return modified builder
+    pub fn add_operation_vesting_issue_asset(
+        &mut self,
+        key_pair: &XfrKeyPair,
+        token_code: &AssetTypeCode,
+        seq_num: u64,
+        records_and_memos: &[(TxOutput, Option<OwnerMemo>)],
+        vesting: Vec<(usize, VestingSchedule)>,
     ) -> Result<&mut Self> {
         let iss_keypair = IssuerKeyPair { keypair: &key_pair };
 
+        let mut body =
+            IssueAssetBody::new(token_code, seq_num, &records_and_memos).c(d!())?;
+        body.vesting = vesting;
         self.txn.add_operation(Operation::IssueAsset(
-            IssueAsset::new(
-                IssueAssetBody::new(token_code, seq_num, &records_and_memos).c(d!())?,
-                &iss_keypair,
-            )
-            .c(d!())?,
+            IssueAsset::new(body, &iss_keypair).c(d!())?,
         ));
         Ok(self)
     }
@@ -469,6 +576,176 @@ impl TransactionBuilder {
         self
     }
 
+    /// Add a operation to updating the revocation list of a credential issuer,
+    /// `id_attr` only takes effect when the list is created.
+    ///
+    /// `expiries` sets the expiry heights of credentials when they are issued or renewed,
+    /// a renewal does not change the credential nor its binding to the owner.
+    pub fn add_operation_update_revocation(
+        &mut self,
+        auth_key_pair: &XfrKeyPair,
+        issuer: CredIssuerPublicKey,
+        id_attr: &str,
+        revoke: Vec<Vec<u8>>,
+        restore: Vec<Vec<u8>>,
+        expiries: Vec<(Vec<u8>, u64)>,
+    ) -> &mut Self {
+        let update = UpdateRevocation::new(
+            UpdateRevocationBody {
+                issuer,
+                id_attr: id_attr.to_owned(),
+                revoke,
+                restore,
+                expiries,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            auth_key_pair,
+        );
+        self.txn.add_operation(Operation::UpdateRevocation(update));
+        self
+    }
+
+    /// Add a operation to rotating the tracer key of an asset tracing policy,
+    /// the new key decrypts tracer memos from `effective_height` on.
+    pub fn add_operation_update_tracer_key(
+        &mut self,
+        auth_key_pair: &XfrKeyPair,
+        asset_code: AssetTypeCode,
+        policy_idx: usize,
+        effective_height: u64,
+        enc_keys: AssetTracerEncKeys,
+    ) -> &mut Self {
+        let update = UpdateTracerKey::new(
+            UpdateTracerKeyBody {
+                asset_type: asset_code,
+                rotation: TracerKeyRotation {
+                    policy_idx,
+                    effective_height,
+                    enc_keys,
+                },
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            auth_key_pair,
+        );
+        self.txn.add_operation(Operation::UpdateTracerKey(update));
+        self
+    }
+
+    /// Add a operation to registering the mirror of an asset on an external chain,
+    /// `None` removes the current mirror on the chain.
+    pub fn add_operation_update_asset_mirror(
+        &mut self,
+        auth_key_pair: &XfrKeyPair,
+        asset_code: AssetTypeCode,
+        chain_id: u64,
+        contract: Option<[u8; 20]>,
+    ) -> &mut Self {
+        let update = UpdateAssetMirror::new(
+            UpdateAssetMirrorBody {
+                asset_type: asset_code,
+                chain_id,
+                contract,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            auth_key_pair,
+        );
+        self.txn.add_operation(Operation::UpdateAssetMirror(update));
+        self
+    }
+
+    /// Add a operation to creating the light client of a counterparty chain,
+    /// trusting `root` at `height` of the counterparty.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_operation_create_ibc_client(
+        &mut self,
+        keypair: &XfrKeyPair,
+        client_id: String,
+        chain_id: String,
+        counterparty_client_id: String,
+        validators: Vec<(XfrPublicKey, u64)>,
+        height: u64,
+        root: IbcHash,
+    ) -> &mut Self {
+        let op = CreateIbcClient::new(
+            CreateIbcClientBody {
+                client_id,
+                chain_id,
+                counterparty_client_id,
+                validators,
+                height,
+                root,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            keypair,
+        );
+        self.txn
+            .add_operation(Operation::Ibc(IbcOperation::CreateClient(op)));
+        self
+    }
+
+    /// Add a operation to sending a packet to a counterparty chain,
+    /// the sequence is assigned when it is committed.
+    pub fn add_operation_send_ibc_packet(
+        &mut self,
+        keypair: &XfrKeyPair,
+        client_id: String,
+        data: Vec<u8>,
+        timeout_height: u64,
+    ) -> &mut Self {
+        let op = SendIbcPacket::new(
+            SendIbcPacketBody {
+                client_id,
+                data,
+                timeout_height,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            keypair,
+        );
+        self.txn
+            .add_operation(Operation::Ibc(IbcOperation::SendPacket(op)));
+        self
+    }
+
+    /// Add a operation to putting, updating or deleting a key of the key-value store,
+    /// the fee of the transaction must cover `kv_fee` of it.
+    pub fn add_operation_kv(
+        &mut self,
+        keypair: &XfrKeyPair,
+        key: String,
+        action: KvAction,
+    ) -> &mut Self {
+        let op = KvOperation::new(
+            KvOperationBody {
+                key,
+                action,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            keypair,
+        );
+        self.txn.add_operation(Operation::Kv(op));
+        self
+    }
+
+    /// Add a operation to anchoring `hash` under the key of `keypair`,
+    /// with the timestamp of the block including it.
+    pub fn add_operation_anchor_hash(
+        &mut self,
+        keypair: &XfrKeyPair,
+        hash: [u8; 32],
+        tag: Option<String>,
+    ) -> &mut Self {
+        let op = AnchorHash::new(
+            AnchorHashBody {
+                hash,
+                tag,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            keypair,
+        );
+        self.txn.add_operation(Operation::AnchorHash(op));
+        self
+    }
+
     /// Add a operation to delegating findora account to a tendermint validator.
     /// The transfer operation to BLACK_HOLE_PUBKEY_STAKING should be sent along with.
     pub fn add_operation_delegation(
@@ -488,6 +765,21 @@ impl TransactionBuilder {
         self.add_operation(Operation::Delegation(op))
     }
 
+    /// Add operations to delegating findora account to several tendermint validators,
+    /// in the form of `(validator, amount)`, they take effect all together or not at all.
+    /// The transfer operation to BLACK_HOLE_PUBKEY_STAKING of the sum of the amounts
+    /// should be sent along with.
+    pub fn add_operation_multi_delegation(
+        &mut self,
+        keypair: &XfrKeyPair,
+        targets: Vec<(TendermintAddr, u64)>,
+    ) -> &mut Self {
+        for (validator, amount) in targets {
+            self.add_operation_delegation(keypair, amount, validator);
+        }
+        self
+    }
+
     /// Add a operation to updating staker memo and commission_rate
     pub fn add_operation_update_staker(
         &mut self,
@@ -576,6 +868,13 @@ impl TransactionBuilder {
         self.add_operation(Operation::Claim(op))
     }
 
+    /// Add a operation to bring a jailed validator back,
+    /// signed by the validator after topping up its self-delegation
+    pub fn add_operation_unjail(&mut self, keypair: &XfrKeyPair) -> &mut Self {
+        let op = UnjailOps::new(keypair, self.txn.body.no_replay_token);
+        self.add_operation(Operation::Unjail(op))
+    }
+
     #[allow(missing_docs)]
     pub fn add_operation_fra_distribution(
         &mut self,
@@ -651,6 +950,319 @@ impl TransactionBuilder {
         Ok(self)
     }
 
+    /// Add a operation to deposit assets to the prism bridge.
+    /// The transfer operation to BLACK_HOLE_PUBKEY_STAKING should be sent along with.
+    pub fn add_operation_prism_deposit(
+        &mut self,
+        kp: &XfrKeyPair,
+        asset: AssetTypeCode,
+        receiver: MultiSigner,
+        amount: u64,
+    ) -> &mut Self {
+        let deposit = PrismDeposit::new(
+            PrismDepositBody {
+                asset,
+                receiver,
+                amount,
+                no_replay_token: self.txn.body.no_replay_token,
+            },
+            kp,
+        );
+        self.add_operation(Operation::PrismDeposit(deposit))
+    }
+
+    /// Add a operation to release assets locked by the prism bridge,
+    /// signed by the bridge authorities.
+    pub fn add_operation_prism_withdraw(
+        &mut self,
+        body: PrismWithdrawBody,
+        signatures: Vec<PrismSignature<PrismWithdrawBody>>,
+    ) -> &mut Self {
+        self.add_operation(Operation::PrismWithdraw(PrismWithdraw { body, signatures }))
+    }
+
+    /// Add a operation to replace the prism bridge authorities,
+    /// signed by the current authorities.
+    pub fn add_operation_update_prism_authorities(
+        &mut self,
+        body: UpdatePrismAuthoritiesBody,
+        signatures: Vec<PrismSignature<UpdatePrismAuthoritiesBody>>,
+    ) -> &mut Self {
+        self.add_operation(Operation::UpdatePrismAuthorities(UpdatePrismAuthorities {
+            body,
+            signatures,
+        }))
+    }
+
+    /// Add a operation to authorize `payee` to pull up to `limit` per `period` blocks,
+    /// the transfer operation locking `amount` to BLACK_HOLE_PUBKEY_STAKING
+    /// should be sent along with.
+    ///
+    /// Returns the identifier of the new authorization.
+    pub fn add_operation_authorize_pull(
+        &mut self,
+        kp: &XfrKeyPair,
+        payee: XfrPublicKey,
+        asset: AssetTypeCode,
+        limit: u64,
+        period: u64,
+        amount: u64,
+    ) -> PullAuthorizationId {
+        let body = AuthorizePullBody {
+            payee,
+            asset,
+            limit,
+            period,
+            amount,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        let id = body.id();
+        self.add_operation(Operation::PullPayment(PullPaymentOperation::Authorize(
+            AuthorizePull::new(body, kp),
+        )));
+        id
+    }
+
+    /// Add a operation to pull `amount` from an authorization, signed by the payee.
+    pub fn add_operation_pull(
+        &mut self,
+        kp: &XfrKeyPair,
+        id: PullAuthorizationId,
+        asset: AssetTypeCode,
+        amount: u64,
+    ) -> &mut Self {
+        let body = PullBody {
+            id,
+            asset,
+            amount,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::PullPayment(PullPaymentOperation::Pull(
+            Pull::new(body, kp),
+        )))
+    }
+
+    /// Add a operation to revoke an authorization, signed by the payer,
+    /// `refund` must be the remaining amount of it.
+    pub fn add_operation_revoke_pull(
+        &mut self,
+        kp: &XfrKeyPair,
+        id: PullAuthorizationId,
+        asset: AssetTypeCode,
+        refund: u64,
+    ) -> &mut Self {
+        let body = RevokePullBody {
+            id,
+            asset,
+            refund,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::PullPayment(PullPaymentOperation::Revoke(
+            RevokePull::new(body, kp),
+        )))
+    }
+
+    /// Add a operation to stream `amount` to `recipient`, vesting linearly
+    /// from `start_height` to `end_height`, the transfer operation locking `amount`
+    /// to BLACK_HOLE_PUBKEY_STAKING should be sent along with.
+    ///
+    /// Returns the identifier of the new stream.
+    pub fn add_operation_create_stream(
+        &mut self,
+        kp: &XfrKeyPair,
+        recipient: XfrPublicKey,
+        asset: AssetTypeCode,
+        amount: u64,
+        start_height: u64,
+        end_height: u64,
+    ) -> StreamId {
+        let body = CreateStreamBody {
+            recipient,
+            asset,
+            amount,
+            start_height,
+            end_height,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        let id = body.id();
+        self.add_operation(Operation::PaymentStream(PaymentStreamOperation::Create(
+            CreateStream::new(body, kp),
+        )));
+        id
+    }
+
+    /// Add a operation to claim `amount` vested in a stream, signed by the recipient.
+    pub fn add_operation_claim_stream(
+        &mut self,
+        kp: &XfrKeyPair,
+        id: StreamId,
+        asset: AssetTypeCode,
+        amount: u64,
+    ) -> &mut Self {
+        let body = ClaimStreamBody {
+            id,
+            asset,
+            amount,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::PaymentStream(PaymentStreamOperation::Claim(
+            ClaimStream::new(body, kp),
+        )))
+    }
+
+    /// Add a operation to cancel a stream, signed by the sender,
+    /// `refund` must not exceed the unvested amount when it is included.
+    pub fn add_operation_cancel_stream(
+        &mut self,
+        kp: &XfrKeyPair,
+        id: StreamId,
+        asset: AssetTypeCode,
+        refund: u64,
+    ) -> &mut Self {
+        let body = CancelStreamBody {
+            id,
+            asset,
+            refund,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::PaymentStream(PaymentStreamOperation::Cancel(
+            CancelStream::new(body, kp),
+        )))
+    }
+
+    /// Add a operation to send `amount` to `recipient`, who must claim it
+    /// within `claim_period` blocks, the transfer operation locking `amount`
+    /// to BLACK_HOLE_PUBKEY_STAKING should be sent along with.
+    ///
+    /// Returns the identifier of the new designated transfer.
+    pub fn add_operation_send_designated(
+        &mut self,
+        kp: &XfrKeyPair,
+        recipient: XfrPublicKey,
+        asset: AssetTypeCode,
+        amount: u64,
+        claim_period: u64,
+    ) -> DesignatedId {
+        let body = SendDesignatedBody {
+            recipient,
+            asset,
+            amount,
+            claim_period,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        let id = body.id();
+        self.add_operation(Operation::DesignatedTransfer(
+            DesignatedTransferOperation::Send(SendDesignated::new(body, kp)),
+        ));
+        id
+    }
+
+    /// Add a operation to claim a designated transfer, signed by the recipient,
+    /// `amount` must be the transferred amount.
+    pub fn add_operation_claim_designated(
+        &mut self,
+        kp: &XfrKeyPair,
+        id: DesignatedId,
+        asset: AssetTypeCode,
+        amount: u64,
+    ) -> &mut Self {
+        let body = ReleaseDesignatedBody {
+            id,
+            asset,
+            amount,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::DesignatedTransfer(
+            DesignatedTransferOperation::Claim(ReleaseDesignated::new(body, kp)),
+        ))
+    }
+
+    /// Add a operation to reclaim an expired designated transfer, signed by the sender.
+    pub fn add_operation_reclaim_designated(
+        &mut self,
+        kp: &XfrKeyPair,
+        id: DesignatedId,
+        asset: AssetTypeCode,
+        amount: u64,
+    ) -> &mut Self {
+        let body = ReleaseDesignatedBody {
+            id,
+            asset,
+            amount,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::DesignatedTransfer(
+            DesignatedTransferOperation::Reclaim(ReleaseDesignated::new(body, kp)),
+        ))
+    }
+
+    /// Add a operation to register or update the spending policy of `kp`,
+    /// the transaction must also be signed by the co-signer of the current policy if any.
+    pub fn add_operation_set_spending_policy(
+        &mut self,
+        kp: &XfrKeyPair,
+        policy: SpendingPolicy,
+    ) -> &mut Self {
+        let body = SetSpendingPolicyBody {
+            policy,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::SpendingPolicy(SpendingPolicyOperation::Set(
+            SetSpendingPolicy::new(body, kp),
+        )))
+    }
+
+    /// Add a operation to remove the spending policy of `kp`,
+    /// the transaction must also be signed by the co-signer of the policy if any.
+    pub fn add_operation_remove_spending_policy(
+        &mut self,
+        kp: &XfrKeyPair,
+    ) -> &mut Self {
+        let body = RemoveSpendingPolicyBody {
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::SpendingPolicy(SpendingPolicyOperation::Remove(
+            RemoveSpendingPolicy::new(body, kp),
+        )))
+    }
+
+    /// Add a operation to authorize `session` to sign the transfers of `kp`,
+    /// sending at most `allowance` of `asset` until `expires_at`.
+    pub fn add_operation_authorize_session(
+        &mut self,
+        kp: &XfrKeyPair,
+        session: XfrPublicKey,
+        asset: AssetTypeCode,
+        allowance: u64,
+        expires_at: u64,
+    ) -> &mut Self {
+        let body = AuthorizeSessionBody {
+            session,
+            asset,
+            allowance,
+            expires_at,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::SessionKey(SessionKeyOperation::Authorize(
+            AuthorizeSession::new(body, kp),
+        )))
+    }
+
+    /// Add a operation to revoke the session key `session` of `kp`
+    pub fn add_operation_revoke_session(
+        &mut self,
+        kp: &XfrKeyPair,
+        session: XfrPublicKey,
+    ) -> &mut Self {
+        let body = RevokeSessionBody {
+            session,
+            no_replay_token: self.txn.body.no_replay_token,
+        };
+        self.add_operation(Operation::SessionKey(SessionKeyOperation::Revoke(
+            RevokeSession::new(body, kp),
+        )))
+    }
+
     #[allow(missing_docs)]
     pub fn add_operation(&mut self, op: Operation) -> &mut Self {
         self.txn.add_operation(op);
@@ -703,28 +1315,11 @@ pub(crate) fn build_record_and_get_blinds<R: CryptoRng + RngCore>(
 ) -> Result<(AssetRecord, (Scalar, Scalar), Scalar)> {
     // Check input consistency:
     // - if no policy, then no identity proof needed
-    // - if policy and identity tracing, then identity proof is needed
-    // - if policy but no identity tracing, then no identity proof is needed
-    let asset_tracing = !template.asset_tracing_policies.is_empty();
-    if !asset_tracing && identity_proof.is_some()
-        || asset_tracing
-            && (template
-                .asset_tracing_policies
-                .get_policy(0)
-                .as_ref()
-                .c(d!())?
-                .identity_tracing
-                .is_some()
-                && identity_proof.is_none()
-                || template
-                    .asset_tracing_policies
-                    .get_policy(0)
-                    .as_ref()
-                    .unwrap()
-                    .identity_tracing
-                    .is_none()
-                    && identity_proof.is_some())
-    {
+    // - if a policy with identity tracing, then identity proof is needed
+    // - if policies but no identity tracing, then no identity proof is needed
+    let identity_policy_idx =
+        identity_tracing_policy(&template.asset_tracing_policies)?.map(|(idx, _)| idx);
+    if identity_policy_idx.is_some() != identity_proof.is_some() {
         return Err(eg!());
     }
     // 1. get ciphertext and proofs from identity proof structure
@@ -735,20 +1330,30 @@ pub(crate) fn build_record_and_get_blinds<R: CryptoRng + RngCore>(
             (Some(c.into_iter().map(|i| (0u32, i)).collect()), Some(p))
         }
     };
-    // 2. Use record template and ciphertexts to build open asset record
+    // 2. Use record template and ciphertexts to build open asset record,
+    //    ciphertexts are aligned with policies, only the identity tracing one has them
+    let mut attrs_ctexts = template
+        .asset_tracing_policies
+        .get_policies()
+        .iter()
+        .map(|_| vec![])
+        .collect::<Vec<_>>();
+    if attrs_ctexts.is_empty() {
+        attrs_ctexts.push(vec![]);
+    }
+    if let (Some(idx), Some(c)) = (identity_policy_idx, attr_ctext) {
+        attrs_ctexts[idx] = c;
+    }
     let params = PublicParams::default();
-    let (open_asset_record, asset_tracing_memos, owner_memo) = build_open_asset_record(
-        prng,
-        &params.pc_gens,
-        template,
-        vec![attr_ctext.unwrap_or_default()],
-    );
+    let (open_asset_record, asset_tracing_memos, owner_memo) =
+        build_open_asset_record(prng, &params.pc_gens, template, attrs_ctexts);
     // 3. Return record input containing open asset record, tracing policy, identity reveal proof,
     //    asset_tracer_memo, and owner_memo
 
     let mut identity_proofs = vec![];
-    if reveal_proof.is_some() {
-        identity_proofs.push(reveal_proof);
+    if let (Some(idx), Some(p)) = (identity_policy_idx, reveal_proof) {
+        identity_proofs.resize(idx, None);
+        identity_proofs.push(Some(p));
     }
 
     Ok((
@@ -767,6 +1372,102 @@ pub(crate) fn build_record_and_get_blinds<R: CryptoRng + RngCore>(
     ))
 }
 
+/// The only policy tracing identities and its index, at most one per record.
+fn identity_tracing_policy(
+    policies: &TracingPolicies,
+) -> Result<Option<(usize, &TracingPolicy)>> {
+    let mut found = policies
+        .get_policies()
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.identity_tracing.is_some());
+    let ret = found.next();
+    alt!(
+        found.next().is_some(),
+        Err(eg!("at most one policy can trace identities")),
+        Ok(ret)
+    )
+}
+
+/// Sequence numbers tracked locally by a client
+///
+/// The sequence id of transactions, ie. the block commit count of the ledger,
+/// is fetched once; the issuance sequence numbers of an asset start from it,
+/// or from the one of the ledger if greater, and increase locally,
+/// so several issuances can be built without querying the ledger.
+///
+/// A rejected issuance leaves a gap in the numbers, which is harmless since
+/// the ledger only requires them to increase; `issuance_gap` finds it
+/// and `resync_issuance` restarts the numbers from the ledger.
+#[derive(Clone, Debug, Default)]
+pub struct SeqTracker {
+    seq_id: u64,
+    // the next issuance number of each asset
+    issuance_nums: HashMap<AssetTypeCode, u64>,
+}
+
+impl SeqTracker {
+    /// `seq_id` is the block commit count of the ledger
+    pub fn new(seq_id: u64) -> Self {
+        SeqTracker {
+            seq_id,
+            issuance_nums: HashMap::new(),
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get_seq_id(&self) -> u64 {
+        self.seq_id
+    }
+
+    /// Refresh the sequence id, transactions carrying an old one
+    /// are rejected once it falls behind the window of the ledger
+    pub fn set_seq_id(&mut self, seq_id: u64) {
+        self.seq_id = seq_id;
+    }
+
+    /// A builder of a transaction with the tracked sequence id
+    pub fn new_tx_builder(&self) -> TransactionBuilder {
+        TransactionBuilder::from_seq_id(self.seq_id)
+    }
+
+    /// Take the next issuance number of `code`,
+    /// `committed` is the one of the ledger, ie. `asset_issuance_num`, if known
+    pub fn next_issuance_num(
+        &mut self,
+        code: &AssetTypeCode,
+        committed: Option<u64>,
+    ) -> u64 {
+        let next = self
+            .issuance_nums
+            .get(code)
+            .copied()
+            .unwrap_or(self.seq_id)
+            .max(committed.unwrap_or(0));
+        self.issuance_nums.insert(*code, next + 1);
+        next
+    }
+
+    /// The numbers of `code` taken but not committed yet, by the one of the ledger,
+    /// they are lost by rejected transactions once none of them is pending
+    pub fn issuance_gap(
+        &self,
+        code: &AssetTypeCode,
+        committed: u64,
+    ) -> Option<Range<u64>> {
+        self.issuance_nums
+            .get(code)
+            .filter(|next| committed < **next)
+            .map(|next| committed..*next)
+    }
+
+    /// Recover after rejected transactions,
+    /// the numbers of `code` restart from the one of the ledger
+    pub fn resync_issuance(&mut self, code: &AssetTypeCode, committed: u64) {
+        self.issuance_nums.insert(*code, committed);
+    }
+}
+
 /// TransferOperationBuilder constructs transfer operations using the factory pattern
 /// Inputs and outputs are added iteratively before being signed by all input record owners
 #[derive(Clone, Serialize, Deserialize, Default)]
@@ -779,6 +1480,8 @@ pub struct TransferOperationBuilder {
     output_records: Vec<AssetRecord>,
     outputs_tracing_policies: Vec<TracingPolicies>,
     output_identity_commitments: Vec<Option<ACCommitment>>,
+    identity_disclosures: Vec<IdentityDisclosure>,
+    output_memos: Vec<(usize, EncryptedMemo)>,
     transfer: Option<TransferAsset>,
     transfer_type: TransferType,
     auto_refund: bool,
@@ -867,6 +1570,74 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Reveal some attributes of a credential for an added output,
+    /// the credential must belong to the owner of the output.
+    pub fn disclose_identity(
+        &mut self,
+        output_idx: usize,
+        user_sk: &CredUserSecretKey,
+        credential: &PlatformCredential,
+        commitment_key: &ACCommitmentKey,
+        reveal_fields: &[String],
+    ) -> Result<&mut Self> {
+        if self.transfer.is_some() {
+            return Err(eg!(
+                ("Cannot mutate a transfer that has been signed".to_string())
+            ));
+        }
+        if self
+            .identity_disclosures
+            .iter()
+            .any(|d| d.output_idx == output_idx)
+        {
+            return Err(eg!("identity of this output has been disclosed"));
+        }
+
+        let owner = self
+            .output_records
+            .get(output_idx)
+            .c(d!("output does not exist"))?
+            .open_asset_record
+            .blind_asset_record
+            .public_key;
+        let disclosure = IdentityDisclosure::new(
+            &mut ChaChaRng::from_entropy(),
+            output_idx,
+            &owner,
+            user_sk,
+            credential,
+            commitment_key,
+            reveal_fields,
+        )
+        .c(d!())?;
+        self.identity_disclosures.push(disclosure);
+        Ok(self)
+    }
+
+    /// Attach a memo to an added output, it will be encrypted to the owner of the output.
+    pub fn attach_memo(&mut self, output_idx: usize, msg: &[u8]) -> Result<&mut Self> {
+        if self.transfer.is_some() {
+            return Err(eg!(
+                ("Cannot mutate a transfer that has been signed".to_string())
+            ));
+        }
+        if self.output_memos.iter().any(|(idx, _)| *idx == output_idx) {
+            return Err(eg!("a memo has been attached to this output"));
+        }
+
+        let owner = self
+            .output_records
+            .get(output_idx)
+            .c(d!("output does not exist"))?
+            .open_asset_record
+            .blind_asset_record
+            .public_key;
+        let memo = EncryptedMemo::encrypt(&mut ChaChaRng::from_entropy(), &owner, msg)
+            .c(d!())?;
+        self.output_memos.push((output_idx, memo));
+        Ok(self)
+    }
+
     /// Adds output to the records, and stores the asset amount blinds and type blind in the blinds parameter passed in.
     pub fn add_output_and_store_blinds<R: CryptoRng + RngCore>(
         &mut self,
@@ -884,12 +1655,14 @@ impl TransferOperationBuilder {
             if let Some((user_secret_key, credential, commitment_key)) =
                 credential_record
             {
-                match asset_record_template.asset_tracing_policies.get_policy(0) {
+                match identity_tracing_policy(
+                    &asset_record_template.asset_tracing_policies,
+                )? {
                     None => {
                         // identity tracing must have asset_tracing policy
                         return Err(eg!());
                     }
-                    Some(policy) => {
+                    Some((_, policy)) => {
                         match &policy.identity_tracing {
                             // policy must have a identity tracing policy
                             None => {
@@ -917,12 +1690,12 @@ impl TransferOperationBuilder {
                     }
                 }
             } else {
-                if let Some(policy) =
-                    asset_record_template.asset_tracing_policies.get_policy(0)
+                if identity_tracing_policy(
+                    &asset_record_template.asset_tracing_policies,
+                )?
+                .is_some()
                 {
-                    if policy.identity_tracing.is_some() {
-                        return Err(eg!());
-                    }
+                    return Err(eg!());
                 }
                 build_record_and_get_blinds(prng, &asset_record_template, None)?
             };
@@ -935,22 +1708,58 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    // Sum up the amounts of each asset type
+    fn sum_by_type<'a>(
+        amounts: impl Iterator<Item = (&'a AssetRecord, u64)>,
+    ) -> Result<BTreeMap<AssetTypeCode, u64>> {
+        let mut sums = BTreeMap::new();
+        for (ar, am) in amounts {
+            let code = AssetTypeCode {
+                val: ar.open_asset_record.asset_type,
+            };
+            let sum = sums.entry(code).or_insert(0u64);
+            *sum = sum.checked_add(am).c(d!("overflow"))?;
+        }
+        Ok(sums)
+    }
+
+    // Check if outputs and inputs of each asset type are balanced
+    fn check_balance_by_type(
+        inputs: &BTreeMap<AssetTypeCode, u64>,
+        outputs: &BTreeMap<AssetTypeCode, u64>,
+    ) -> Result<()> {
+        for code in inputs.keys().chain(outputs.keys()) {
+            let input_total = inputs.get(code).copied().unwrap_or(0);
+            let output_total = outputs.get(code).copied().unwrap_or(0);
+            if input_total != output_total {
+                return Err(eg!(format!(
+                    "{}: {} != {}",
+                    code.to_base64(),
+                    input_total,
+                    output_total
+                )));
+            }
+        }
+        Ok(())
+    }
+
     // Check if outputs and inputs are balanced
 
     fn check_balance(&self) -> Result<()> {
-        let input_total: u64 = self
-            .input_records
-            .iter()
-            .fold(0, |acc, ar| acc + ar.open_asset_record.amount);
-        let output_total = self
-            .output_records
-            .iter()
-            .fold(0, |acc, ar| acc + ar.open_asset_record.amount);
-        if input_total != output_total {
-            return Err(eg!(format!("{} != {}", input_total, output_total)));
-        }
+        let input_totals = Self::sum_by_type(
+            self.input_records
+                .iter()
+                .map(|ar| (ar, ar.open_asset_record.amount)),
+        )
+        .c(d!())?;
+        let output_totals = Self::sum_by_type(
+            self.output_records
+                .iter()
+                .map(|ar| (ar, ar.open_asset_record.amount)),
+        )
+        .c(d!())?;
 
-        Ok(())
+        Self::check_balance_by_type(&input_totals, &output_totals).c(d!())
     }
 
     /// Ensures that outputs and inputs are balanced by adding remainder outputs for leftover asset
@@ -1026,11 +1835,117 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Same as `balance`, but the spent amounts and the outputs of each asset type
+    /// must balance on their own, so that multiple asset types can be transferred
+    /// atomically in one operation, e.g. a swap between two parties.
+    pub fn balance_all(&mut self, rt: Option<AssetRecordType>) -> Result<&mut Self> {
+        if self.transfer.is_some() {
+            return Err(eg!(
+                ("Cannot mutate a transfer that has been signed".to_string())
+            ));
+        }
+
+        let spend_totals = Self::sum_by_type(
+            self.input_records
+                .iter()
+                .zip(self.spend_amounts.iter().copied()),
+        )
+        .c(d!())?;
+        let output_totals = Self::sum_by_type(
+            self.output_records
+                .iter()
+                .map(|ar| (ar, ar.open_asset_record.amount)),
+        )
+        .c(d!())?;
+        Self::check_balance_by_type(&spend_totals, &output_totals).c(d!())?;
+
+        self.balance(rt).c(d!())
+    }
+
+    /// Spend the partially consumed inputs fully, with a single change to `owner`
+    /// for each asset type instead of one per input, so that the dust added
+    /// as inputs with a zero spent amount is merged into the changes.
+    ///
+    /// Fails if a change would be below the dust limit of its asset.
+    pub fn merge_changes(
+        &mut self,
+        owner: &XfrPublicKey,
+        rt: Option<AssetRecordType>,
+        dust_limits: &BTreeMap<AssetTypeCode, u64>,
+    ) -> Result<&mut Self> {
+        if self.transfer.is_some() {
+            return Err(eg!(
+                ("Cannot mutate a transfer that has been signed".to_string())
+            ));
+        }
+
+        // (amount, record type, tracing policies) of the change of each asset type
+        let mut changes = BTreeMap::new();
+        for ((spend_amount, ar), policies) in self
+            .spend_amounts
+            .iter()
+            .zip(self.input_records.iter())
+            .zip(self.inputs_tracing_policies.iter())
+            .filter(|((am, ar), _)| **am < ar.open_asset_record.amount)
+        {
+            let oar = &ar.open_asset_record;
+            let change = changes
+                .entry(AssetTypeCode {
+                    val: oar.asset_type,
+                })
+                .or_insert_with(|| {
+                    (0u64, rt.unwrap_or_else(|| oar.get_record_type()), policies)
+                });
+            change.0 = change
+                .0
+                .checked_add(oar.amount - spend_amount)
+                .c(d!("overflow"))?;
+        }
+        for (code, (am, _, _)) in changes.iter() {
+            if let Some(limit) = dust_limits.get(code).filter(|l| am < *l) {
+                return Err(eg!(format!(
+                    "change below the dust limit of {}: {} < {}",
+                    code.to_base64(),
+                    am,
+                    limit
+                )));
+            }
+        }
+
+        let mut prng = ChaChaRng::from_entropy();
+        let mut outputs = vec![];
+        for (code, (am, record_type, policies)) in changes.into_iter() {
+            let template = AssetRecordTemplate::with_asset_tracing(
+                am,
+                code.val,
+                record_type,
+                *owner,
+                policies.clone(),
+            );
+            let ar =
+                AssetRecord::from_template_no_identity_tracing(&mut prng, &template)
+                    .c(d!())?;
+            outputs.push((ar, policies.clone()));
+        }
+        for (ar, policies) in outputs {
+            self.output_records.push(ar);
+            self.outputs_tracing_policies.push(policies);
+            self.output_identity_commitments.push(None);
+        }
+        for (spend_amount, ar) in
+            self.spend_amounts.iter_mut().zip(self.input_records.iter())
+        {
+            *spend_amount = ar.open_asset_record.amount;
+        }
+
+        Ok(self)
+    }
+
     /// Finalize the transaction and prepare for signing. Once called, the transaction cannot be
     /// modified.
     pub fn create(&mut self, transfer_type: TransferType) -> Result<&mut Self> {
         if self.auto_refund {
-            self.balance(None).c(d!())?;
+            self.balance_all(None).c(d!())?;
         } else {
             self.check_balance().c(d!())?;
         }
@@ -1044,7 +1959,7 @@ impl TransferOperationBuilder {
             self.outputs_tracing_policies.clone(),
             vec![None; num_outputs],
         );
-        let body = TransferAssetBody::new(
+        let mut body = TransferAssetBody::new(
             &mut prng,
             self.input_sids.clone(),
             &self.input_records,
@@ -1054,6 +1969,10 @@ impl TransferOperationBuilder {
             transfer_type,
         )
         .c(d!())?;
+        body.identity_disclosures = self.identity_disclosures.clone();
+        for (idx, memo) in self.output_memos.iter() {
+            body.outputs.get_mut(*idx).c(d!())?.memo = Some(memo.clone());
+        }
         self.transfer = Some(TransferAsset::new(body).c(d!())?);
         Ok(self)
     }
@@ -1136,12 +2055,377 @@ impl TransferOperationBuilder {
     }
 }
 
+/// Terms of a delivery-versus-payment swap, see `SwapBuilder`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SwapTerms {
+    /// Pays the fee, and submits the swap
+    pub initiator: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub counterparty: XfrPublicKey,
+    /// Asset and amount delivered by the initiator
+    pub give: (AssetTypeCode, u64),
+    /// Asset and amount delivered by the counterparty
+    pub want: (AssetTypeCode, u64),
+    /// Mask the amounts of the two legs
+    pub confidential_am: bool,
+    /// Mask the asset types of the two legs
+    pub confidential_ty: bool,
+}
+
+impl SwapTerms {
+    fn leg(
+        &self,
+        (code, am): (AssetTypeCode, u64),
+        receiver: XfrPublicKey,
+    ) -> AssetRecordTemplate {
+        let art = match (self.confidential_am, self.confidential_ty) {
+            (true, true) => AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            (true, false) => {
+                AssetRecordType::ConfidentialAmount_NonConfidentialAssetType
+            }
+            (false, true) => {
+                AssetRecordType::NonConfidentialAmount_ConfidentialAssetType
+            }
+            _ => AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        };
+        AssetRecordTemplate::with_no_asset_tracing(am, code.val, art, receiver)
+    }
+}
+
+/// SwapBuilder settles a delivery-versus-payment swap between two parties
+/// in one transfer, so either both legs are delivered or none of them.
+///
+/// 1. the initiator adds its inputs and the two legs, then sends the proposal
+///    to the counterparty out-of-band;
+/// 2. the counterparty adds its inputs, finalizes and signs the transfer,
+///    then sends it back;
+/// 3. the initiator checks the transfer against its own proposal, signs and submits it.
+///
+/// NOTE: the opened records of the inputs are revealed to the counterparty.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SwapBuilder {
+    /// Terms of the swap
+    pub terms: SwapTerms,
+    builder: TransferOperationBuilder,
+}
+
+impl SwapBuilder {
+    /// Step 1, by the initiator: spend `inputs` for its leg and the fee,
+    /// inputs of other asset types are skipped.
+    pub fn initiate(
+        terms: SwapTerms,
+        inputs: Vec<(TxoRef, OpenAssetRecord)>,
+    ) -> Result<Self> {
+        let mut needs = BTreeMap::new();
+        needs.insert(terms.give.0, terms.give.1);
+        let fee = needs
+            .entry(AssetTypeCode {
+                val: ASSET_TYPE_FRA,
+            })
+            .or_insert(0);
+        *fee = fee.checked_add(TX_FEE_MIN).c(d!("overflow"))?;
+
+        let mut builder = TransferOperationBuilder::new();
+        swap_add_inputs(&mut builder, &terms.initiator, needs, inputs).c(d!())?;
+        builder
+            .add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    TX_FEE_MIN,
+                    ASSET_TYPE_FRA,
+                    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                    *BLACK_HOLE_PUBKEY,
+                ),
+                None,
+                None,
+                None,
+            )
+            .c(d!())?
+            .add_output(&terms.leg(terms.give, terms.counterparty), None, None, None)
+            .c(d!())?
+            .add_output(&terms.leg(terms.want, terms.initiator), None, None, None)
+            .c(d!())?;
+
+        Ok(SwapBuilder { terms, builder })
+    }
+
+    /// Step 2, by the counterparty: spend `inputs` for its leg,
+    /// then finalize the transfer and sign it.
+    pub fn accept(
+        &mut self,
+        kp: &XfrKeyPair,
+        inputs: Vec<(TxoRef, OpenAssetRecord)>,
+    ) -> Result<&mut Self> {
+        if kp.get_pk() != self.terms.counterparty {
+            return Err(eg!("not the counterparty of this swap"));
+        }
+        if self.builder.transfer.is_some() {
+            return Err(eg!("the swap has been accepted"));
+        }
+
+        let mut needs = BTreeMap::new();
+        needs.insert(self.terms.want.0, self.terms.want.1);
+        let spent = swap_add_inputs(&mut self.builder, kp.get_pk_ref(), needs, inputs)
+            .c(d!())?;
+        self.builder
+            .balance_all(None)
+            .c(d!())?
+            .create(TransferType::Standard)
+            .c(d!())?;
+
+        let transfer = self.builder.transfer.as_ref().c(d!())?;
+        check_swap_settlement(transfer, kp, &spent, &[self.terms.want], self.terms.give)
+            .c(d!())?;
+
+        self.builder.sign(kp).c(d!())?;
+        Ok(self)
+    }
+
+    /// Step 3, by the initiator: check `accepted` against the proposal of its own,
+    /// then sign it, the fee has been paid in the transfer.
+    pub fn finish(&self, accepted: SwapBuilder, kp: &XfrKeyPair) -> Result<Operation> {
+        if kp.get_pk() != self.terms.initiator {
+            return Err(eg!("not the initiator of this swap"));
+        }
+        if accepted.terms != self.terms {
+            return Err(eg!("the terms have been changed"));
+        }
+        let mut transfer = accepted.builder.transfer.c(d!("not accepted yet"))?;
+
+        // inputs of the initiator must be the ones of its proposal
+        let mut spent = BTreeMap::new();
+        for (sid, record) in transfer
+            .body
+            .inputs
+            .iter()
+            .zip(transfer.body.transfer.inputs.iter())
+            .filter(|(_, record)| record.public_key == self.terms.initiator)
+        {
+            let oar = self
+                .builder
+                .input_sids
+                .iter()
+                .zip(self.builder.input_records.iter())
+                .find(|(s, ar)| {
+                    *s == sid && ar.open_asset_record.blind_asset_record == *record
+                })
+                .map(|(_, ar)| &ar.open_asset_record)
+                .c(d!("unknown input of the initiator"))?;
+            let am = spent
+                .entry(AssetTypeCode {
+                    val: oar.asset_type,
+                })
+                .or_insert(0u64);
+            *am = am.checked_add(oar.amount).c(d!("overflow"))?;
+        }
+
+        let fee = (
+            AssetTypeCode {
+                val: ASSET_TYPE_FRA,
+            },
+            TX_FEE_MIN,
+        );
+        check_swap_settlement(
+            &transfer,
+            kp,
+            &spent,
+            &[self.terms.give, fee],
+            self.terms.want,
+        )
+        .c(d!())?;
+
+        transfer.sign(kp);
+        Ok(Operation::TransferAsset(transfer))
+    }
+}
+
+// Spend `inputs` of `owner` until `needs` are covered, the rest is refunded by `balance`,
+// returns the amounts of the records spent for each asset type
+fn swap_add_inputs(
+    builder: &mut TransferOperationBuilder,
+    owner: &XfrPublicKey,
+    mut needs: BTreeMap<AssetTypeCode, u64>,
+    inputs: Vec<(TxoRef, OpenAssetRecord)>,
+) -> Result<BTreeMap<AssetTypeCode, u64>> {
+    let mut spent = BTreeMap::new();
+    for (sid, oar) in inputs {
+        let code = AssetTypeCode {
+            val: oar.asset_type,
+        };
+        let need = match needs.get_mut(&code) {
+            Some(n) if 0 < *n => n,
+            _ => continue,
+        };
+        if oar.blind_asset_record.public_key != *owner {
+            return Err(eg!("inputs must be owned by the party itself"));
+        }
+
+        let i_am = alt!(oar.amount < *need, oar.amount, *need);
+        *need -= i_am;
+        let am = spent.entry(code).or_insert(0u64);
+        *am = am.checked_add(oar.amount).c(d!("overflow"))?;
+
+        builder.add_input(sid, oar, None, None, i_am).c(d!())?;
+    }
+
+    if let Some((code, _)) = needs.iter().find(|(_, n)| 0 != **n) {
+        return Err(eg!(format!("insufficient balance of {}", code.to_base64())));
+    }
+
+    Ok(spent)
+}
+
+// A party must receive all it is owed: the amounts of its inputs in `spent`,
+// minus what it `gives`, plus what it `gets`; the changes are refunded to itself
+fn check_swap_settlement(
+    transfer: &TransferAsset,
+    kp: &XfrKeyPair,
+    spent: &BTreeMap<AssetTypeCode, u64>,
+    gives: &[(AssetTypeCode, u64)],
+    gets: (AssetTypeCode, u64),
+) -> Result<()> {
+    let xfr = &transfer.body.transfer;
+    let mut received = BTreeMap::new();
+    for (record, memo) in xfr.outputs.iter().zip(xfr.owners_memos.iter()) {
+        if record.public_key != *kp.get_pk_ref() {
+            continue;
+        }
+        let oar = open_blind_asset_record(record, memo, kp).c(d!())?;
+        let am = received
+            .entry(AssetTypeCode {
+                val: oar.asset_type,
+            })
+            .or_insert(0u64);
+        *am = am.checked_add(oar.amount).c(d!("overflow"))?;
+    }
+
+    let mut owed = spent
+        .iter()
+        .map(|(code, am)| (*code, *am as i128))
+        .collect::<BTreeMap<_, _>>();
+    for (code, am) in gives.iter() {
+        *owed.entry(*code).or_insert(0) -= *am as i128;
+    }
+    *owed.entry(gets.0).or_insert(0) += gets.1 as i128;
+
+    for (code, am) in owed.into_iter() {
+        if (received.get(&code).copied().unwrap_or(0) as i128) < am {
+            return Err(eg!(format!(
+                "{}: received less than owed",
+                code.to_base64()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// The default max number of inputs of a transaction in a sweep
+pub const SWEEP_MAX_INPUTS: usize = 64;
+
+/// Split the outputs of an asset type into the batches of a sweep, smallest first,
+/// each batch is consolidated into a single output by one transaction.
+///
+/// A batch has at least 2 and at most `max_inputs` inputs,
+/// so a left-over output is not swept on its own.
+pub fn plan_sweep(mut utxos: Vec<(TxoSID, u64)>, max_inputs: usize) -> Vec<Vec<TxoSID>> {
+    let max_inputs = max_inputs.max(2);
+    utxos.sort_by_key(|(sid, am)| (*am, sid.0));
+    utxos
+        .chunks(max_inputs)
+        .filter(|batch| 1 < batch.len())
+        .map(|batch| batch.iter().map(|(sid, _)| *sid).collect())
+        .collect()
+}
+
+/// Consolidate `inputs` of the same asset type, owned by `kp`, into one output to `kp`.
+///
+/// The fee is paid from `inputs` if they are FRA, otherwise from `fee_input`,
+/// whose change is returned to `kp`. The merged output is confidential
+/// if any of the inputs is.
+pub fn build_sweep_op(
+    kp: &XfrKeyPair,
+    inputs: Vec<(TxoRef, OpenAssetRecord)>,
+    fee_input: Option<(TxoRef, OpenAssetRecord)>,
+) -> Result<Operation> {
+    let owner = kp.get_pk();
+    let asset_type = inputs.first().c(d!("nothing to sweep"))?.1.asset_type;
+
+    let mut builder = TransferOperationBuilder::new();
+    let mut total = 0u64;
+    let (mut conf_am, mut conf_ty) = (false, false);
+    for (sid, oar) in inputs {
+        if oar.asset_type != asset_type {
+            return Err(eg!("inputs of a sweep must be of the same asset type"));
+        }
+        if oar.blind_asset_record.public_key != owner {
+            return Err(eg!("inputs of a sweep must be owned by the key itself"));
+        }
+        conf_am |= oar.blind_asset_record.amount.is_confidential();
+        conf_ty |= oar.blind_asset_record.asset_type.is_confidential();
+        total = total.checked_add(oar.amount).c(d!("overflow"))?;
+        let am = oar.amount;
+        builder.add_input(sid, oar, None, None, am).c(d!())?;
+    }
+
+    if ASSET_TYPE_FRA == asset_type {
+        total = total
+            .checked_sub(TX_FEE_MIN)
+            .filter(|am| 0 < *am)
+            .c(d!("insufficient amount to pay the fee"))?;
+    } else {
+        let (sid, oar) = fee_input.c(d!("no FRA to pay the fee"))?;
+        if ASSET_TYPE_FRA != oar.asset_type || oar.blind_asset_record.public_key != owner
+        {
+            return Err(eg!("the fee must be paid with FRA of the key itself"));
+        }
+        builder
+            .add_input(sid, oar, None, None, TX_FEE_MIN)
+            .c(d!())?;
+    }
+
+    let art = match (conf_am, conf_ty) {
+        (true, true) => AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+        (true, false) => AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        (false, true) => AssetRecordType::NonConfidentialAmount_ConfidentialAssetType,
+        _ => AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+    };
+    builder
+        .add_output(
+            &AssetRecordTemplate::with_no_asset_tracing(
+                TX_FEE_MIN,
+                ASSET_TYPE_FRA,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                *BLACK_HOLE_PUBKEY,
+            ),
+            None,
+            None,
+            None,
+        )
+        .c(d!())?
+        .add_output(
+            &AssetRecordTemplate::with_no_asset_tracing(total, asset_type, art, owner),
+            None,
+            None,
+            None,
+        )
+        .c(d!())?
+        .balance(None)
+        .c(d!())?
+        .create(TransferType::Standard)
+        .c(d!())?
+        .sign(kp)
+        .c(d!())?
+        .transaction()
+        .c(d!())
+}
+
 #[cfg(test)]
 #[allow(missing_docs)]
 mod tests {
     use {
         super::*,
-        ledger::data_model::{TxnEffect, TxoRef},
+        crate::genesis::{Genesis, GenesisAsset},
+        ledger::data_model::{TxnEffect, TxoRef, TxoSID},
         ledger::store::{utils::fra_gen_initial_tx, LedgerState},
         rand_chacha::ChaChaRng,
         rand_core::SeedableRng,
@@ -1149,6 +2433,7 @@ mod tests {
         zei::xfr::asset_record::AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
         zei::xfr::asset_record::{build_blind_asset_record, open_blind_asset_record},
         zei::xfr::sig::XfrKeyPair,
+        zei::xfr::structs::{AssetType as ZeiAssetType, XfrAmount, XfrAssetType},
     };
 
     // Defines an asset type
@@ -1360,6 +2645,130 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_transfer_op_builder_balance_all() {
+        let mut prng = ChaChaRng::from_entropy();
+        let params = PublicParams::default();
+        let code_1 = AssetTypeCode::gen_random();
+        let code_2 = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let template = |am, code: AssetTypeCode, kp: &XfrKeyPair| {
+            AssetRecordTemplate::with_no_asset_tracing(
+                am,
+                code.val,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                kp.get_pk(),
+            )
+        };
+        let (ba_1, _, memo_1) = build_blind_asset_record(
+            &mut prng,
+            &params.pc_gens,
+            &template(100, code_1, &alice),
+            vec![],
+        );
+        let (ba_2, _, memo_2) = build_blind_asset_record(
+            &mut prng,
+            &params.pc_gens,
+            &template(100, code_2, &bob),
+            vec![],
+        );
+        let oar_1 = pnk!(open_blind_asset_record(&ba_1, &memo_1, &alice));
+        let oar_2 = pnk!(open_blind_asset_record(&ba_2, &memo_2, &bob));
+
+        // the totals are equal, but amounts of each asset type are not
+        let mut unbalanced = TransferOperationBuilder::new();
+        pnk!(unbalanced.add_input(
+            TxoRef::Absolute(TxoSID(0)),
+            oar_1.clone(),
+            None,
+            None,
+            20
+        ));
+        pnk!(unbalanced.add_output(&template(20, code_2, &bob), None, None, None));
+        assert!(unbalanced.balance_all(None).is_err());
+
+        // alice swaps 20 of `code_1` for 30 of `code_2` with bob
+        let mut swap = TransferOperationBuilder::new();
+        pnk!(swap.add_input(TxoRef::Absolute(TxoSID(0)), oar_1, None, None, 20));
+        pnk!(swap.add_input(TxoRef::Absolute(TxoSID(1)), oar_2, None, None, 30));
+        pnk!(swap.add_output(&template(20, code_1, &bob), None, None, None));
+        pnk!(swap.add_output(&template(30, code_2, &alice), None, None, None));
+        pnk!(swap.balance_all(None));
+        pnk!(swap.create(TransferType::Standard));
+        pnk!(swap.sign(&alice));
+        pnk!(swap.sign(&bob));
+        pnk!(swap.validate_signatures());
+
+        let mut tx = Transaction::from_seq_id(0);
+        tx.add_operation(pnk!(swap.transaction()));
+        pnk!(TxnEffect::compute_effect(tx));
+    }
+
+    #[test]
+    fn test_swap_builder() {
+        let mut prng = ChaChaRng::from_entropy();
+        let params = PublicParams::default();
+        let code_1 = AssetTypeCode::gen_random();
+        let code_2 = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let mut gen_input = |am, code: ZeiAssetType, kp: &XfrKeyPair| {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                am,
+                code,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                kp.get_pk(),
+            );
+            let (ba, _, memo) =
+                build_blind_asset_record(&mut prng, &params.pc_gens, &template, vec![]);
+            pnk!(open_blind_asset_record(&ba, &memo, kp))
+        };
+        let alice_inputs = vec![
+            (
+                TxoRef::Absolute(TxoSID(0)),
+                gen_input(100, code_1.val, &alice),
+            ),
+            (
+                TxoRef::Absolute(TxoSID(1)),
+                gen_input(100, ASSET_TYPE_FRA, &alice),
+            ),
+        ];
+        let bob_inputs = vec![(
+            TxoRef::Absolute(TxoSID(2)),
+            gen_input(100, code_2.val, &bob),
+        )];
+
+        let terms = SwapTerms {
+            initiator: alice.get_pk(),
+            counterparty: bob.get_pk(),
+            give: (code_1, 20),
+            want: (code_2, 30),
+            confidential_am: true,
+            confidential_ty: false,
+        };
+        let proposal = pnk!(SwapBuilder::initiate(terms, alice_inputs));
+
+        // exchanged out-of-band
+        let mut accepted: SwapBuilder = pnk!(serde_json::from_str(&pnk!(
+            serde_json::to_string(&proposal)
+        )));
+        assert!(accepted.accept(&alice, bob_inputs.clone()).is_err());
+        pnk!(accepted.accept(&bob, bob_inputs));
+
+        let mut tampered = accepted.clone();
+        tampered.terms.want.1 = 1;
+        assert!(proposal.finish(tampered, &alice).is_err());
+        assert!(proposal.finish(accepted.clone(), &bob).is_err());
+
+        let op = pnk!(proposal.finish(accepted, &alice));
+        let mut tx = Transaction::from_seq_id(0);
+        tx.add_operation(op);
+        pnk!(TxnEffect::compute_effect(tx));
+    }
+
     #[test]
     fn test_check_fee_with_ledger() {
         let mut ledger = LedgerState::tmp_ledger();
@@ -1495,4 +2904,382 @@ mod tests {
         let mut block = ledger.start_block().unwrap();
         assert!(ledger.apply_transaction(&mut block, effect).is_err());
     }
+
+    #[test]
+    fn test_sweep_builder() {
+        let sids = |v: &[u64]| v.iter().map(|i| TxoSID(*i)).collect::<Vec<_>>();
+        let utxos = vec![
+            (TxoSID(1), 50),
+            (TxoSID(2), 10),
+            (TxoSID(3), 40),
+            (TxoSID(4), 20),
+            (TxoSID(5), 30),
+        ];
+        assert_eq!(
+            plan_sweep(utxos.clone(), 2),
+            vec![sids(&[2, 4]), sids(&[5, 3])]
+        );
+        assert_eq!(plan_sweep(utxos.clone(), 8), vec![sids(&[2, 4, 5, 3, 1])]);
+        assert!(plan_sweep(utxos[..1].to_vec(), 8).is_empty());
+
+        let mut ledger = LedgerState::tmp_ledger();
+        let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+        let bob_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+        let tx = fra_gen_initial_tx(&fra_owner_kp);
+        let effect = TxnEffect::compute_effect(tx).unwrap();
+        let mut block = ledger.start_block().unwrap();
+        let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+        let txo_sid = ledger
+            .finish_block(block)
+            .unwrap()
+            .remove(&tmp_sid)
+            .unwrap()
+            .1[0];
+
+        // three small outputs to bob
+        let mut op = TransferOperationBuilder::new();
+        op.add_input(
+            TxoRef::Absolute(txo_sid),
+            open_blind_asset_record(
+                &ledger.get_utxo_light(txo_sid).unwrap().utxo.0.record,
+                &None,
+                &fra_owner_kp,
+            )
+            .unwrap(),
+            None,
+            None,
+            31 * TX_FEE_MIN,
+        )
+        .unwrap();
+        for am in [TX_FEE_MIN, 10 * TX_FEE_MIN, 20 * TX_FEE_MIN] {
+            op.add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    am,
+                    ASSET_TYPE_FRA,
+                    NonConfidentialAmount_NonConfidentialAssetType,
+                    bob_kp.get_pk(),
+                ),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+        let op = op
+            .balance(None)
+            .unwrap()
+            .create(TransferType::Standard)
+            .unwrap()
+            .sign(&fra_owner_kp)
+            .unwrap()
+            .transaction()
+            .unwrap();
+        let mut tx = TransactionBuilder::from_seq_id(1);
+        tx.add_operation(op)
+            .add_fee_relative_auto(&fra_owner_kp)
+            .unwrap();
+        let effect = TxnEffect::compute_effect(tx.into_transaction()).unwrap();
+        let mut block = ledger.start_block().unwrap();
+        let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+        let txo_sids = ledger
+            .finish_block(block)
+            .unwrap()
+            .remove(&tmp_sid)
+            .unwrap()
+            .1;
+
+        let open = |ledger: &LedgerState, sid: TxoSID, kp: &XfrKeyPair| {
+            let record = ledger.get_utxo_light(sid).unwrap().utxo.0.record;
+            open_blind_asset_record(&record, &None, kp).unwrap()
+        };
+        let inputs = txo_sids[..3]
+            .iter()
+            .map(|sid| (TxoRef::Absolute(*sid), open(&ledger, *sid, &bob_kp)))
+            .collect::<Vec<_>>();
+
+        // not owned by the key
+        assert!(build_sweep_op(&fra_owner_kp, inputs.clone(), None).is_err());
+
+        let op = pnk!(build_sweep_op(&bob_kp, inputs, None));
+        let mut tx = TransactionBuilder::from_seq_id(2);
+        tx.add_operation(op);
+        assert!(tx.check_fee());
+        let effect = TxnEffect::compute_effect(tx.into_transaction()).unwrap();
+        let mut block = ledger.start_block().unwrap();
+        let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+        let txo_sids = ledger
+            .finish_block(block)
+            .unwrap()
+            .remove(&tmp_sid)
+            .unwrap()
+            .1;
+
+        // the fee, then all of bob's outputs merged into one
+        assert_eq!(2, txo_sids.len());
+        assert_eq!(30 * TX_FEE_MIN, open(&ledger, txo_sids[1], &bob_kp).amount);
+    }
+
+    #[test]
+    fn test_merge_changes() {
+        let mut prng = ChaChaRng::from_entropy();
+        let params = PublicParams::default();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let limits = [(
+            AssetTypeCode {
+                val: ASSET_TYPE_FRA,
+            },
+            100,
+        )]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+
+        let mut oar = |am: u64| {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                am,
+                ASSET_TYPE_FRA,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                alice.get_pk(),
+            );
+            let (ba, _, memo) =
+                build_blind_asset_record(&mut prng, &params.pc_gens, &template, vec![]);
+            open_blind_asset_record(&ba, &memo, &alice).unwrap()
+        };
+        let to_bob = |am: u64| {
+            AssetRecordTemplate::with_no_asset_tracing(
+                am,
+                ASSET_TYPE_FRA,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                bob.get_pk(),
+            )
+        };
+
+        // the change of 60 alone is dust, merged with a dust input of 50 it is not
+        let (input, dust) = (oar(1000), oar(50));
+        let mut op = TransferOperationBuilder::new();
+        op.add_input(TxoRef::Relative(0), input.clone(), None, None, 940)
+            .unwrap()
+            .add_output(&to_bob(940), None, None, None)
+            .unwrap();
+        assert!(op.merge_changes(alice.get_pk_ref(), None, &limits).is_err());
+
+        op.add_input(TxoRef::Relative(1), dust, None, None, 0)
+            .unwrap()
+            .merge_changes(alice.get_pk_ref(), None, &limits)
+            .unwrap();
+        let op = op
+            .balance(None)
+            .unwrap()
+            .create(TransferType::Standard)
+            .unwrap()
+            .sign(&alice)
+            .unwrap()
+            .transaction()
+            .unwrap();
+        let mut tx = TransactionBuilder::from_seq_id(1);
+        tx.add_operation(op);
+        let tx = tx.take_transaction();
+        if let Operation::TransferAsset(x) = &tx.body.operations[0] {
+            assert_eq!(2, x.body.outputs.len());
+        } else {
+            unreachable!();
+        }
+        pnk!(tx.check_dust(&limits));
+
+        // without merging, the change is rejected by the ledger
+        let mut op = TransferOperationBuilder::new();
+        op.add_input(TxoRef::Relative(0), input, None, None, 940)
+            .unwrap()
+            .add_output(&to_bob(940), None, None, None)
+            .unwrap();
+        let op = op
+            .balance(None)
+            .unwrap()
+            .create(TransferType::Standard)
+            .unwrap()
+            .sign(&alice)
+            .unwrap()
+            .transaction()
+            .unwrap();
+        let mut tx = TransactionBuilder::from_seq_id(2);
+        tx.add_operation(op);
+        assert!(tx.take_transaction().check_dust(&limits).is_err());
+    }
+
+    #[test]
+    fn test_genesis_with_ledger() {
+        let mut ledger = LedgerState::tmp_ledger();
+        let issuer_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+        let alice_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+        let bob_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+        let fra = AssetTypeCode {
+            val: ASSET_TYPE_FRA,
+        };
+        let code = AssetTypeCode::gen_random();
+
+        let mut genesis = Genesis::new(issuer_kp.get_pk());
+        pnk!(genesis.add_asset(GenesisAsset {
+            code,
+            memo: "test".to_owned(),
+            rules: AssetRules::default(),
+            issue_amount: 1000,
+            allocations: Default::default(),
+        }));
+        pnk!(genesis.allocate(&fra, alice_kp.get_pk(), 100 * TX_FEE_MIN));
+        pnk!(genesis.allocate(&fra, bob_kp.get_pk(), 200 * TX_FEE_MIN));
+        pnk!(genesis.allocate(&code, bob_kp.get_pk(), 600));
+        assert!(genesis.allocate(&code, alice_kp.get_pk(), 401).is_ok());
+        assert!(genesis.check().is_err());
+        genesis.assets[1].allocations.remove(alice_kp.get_pk_ref());
+        pnk!(genesis.check());
+
+        let bytes = pnk!(genesis.to_bytes());
+        let restored = pnk!(Genesis::from_bytes(&bytes));
+        assert_eq!(bytes, pnk!(restored.to_bytes()));
+        assert_eq!(genesis.hash(), restored.hash());
+
+        assert!(genesis.assets_tx(&alice_kp, 0).is_err());
+        let tx = pnk!(genesis.assets_tx(&issuer_kp, 0));
+        assert!(tx.check_fee());
+
+        let effect = pnk!(TxnEffect::compute_effect(tx));
+        let mut block = pnk!(ledger.start_block());
+        let tmp_sid = pnk!(ledger.apply_transaction(&mut block, effect));
+        let txo_sids = pnk!(ledger.finish_block(block)).remove(&tmp_sid).unwrap().1;
+
+        let mut owned = BTreeMap::new();
+        for sid in txo_sids {
+            if let Some(utxo) = ledger.get_utxo_light(sid) {
+                let r = &utxo.utxo.0.record;
+                if let (
+                    XfrAssetType::NonConfidential(ty),
+                    XfrAmount::NonConfidential(am),
+                ) = (&r.asset_type, &r.amount)
+                {
+                    *owned.entry((r.public_key, *ty)).or_insert(0) += *am;
+                }
+            }
+        }
+        assert_eq!(
+            owned.get(&(alice_kp.get_pk(), ASSET_TYPE_FRA)),
+            Some(&(100 * TX_FEE_MIN))
+        );
+        assert_eq!(
+            owned.get(&(bob_kp.get_pk(), ASSET_TYPE_FRA)),
+            Some(&(200 * TX_FEE_MIN))
+        );
+        assert_eq!(owned.get(&(bob_kp.get_pk(), code.val)), Some(&600));
+        assert_eq!(owned.get(&(issuer_kp.get_pk(), code.val)), Some(&400));
+    }
+
+    #[test]
+    fn test_seq_tracker() {
+        let code = AssetTypeCode::gen_random();
+        let mut seq = SeqTracker::new(10);
+        assert_eq!(10, seq.new_tx_builder().get_seq_id());
+
+        // numbers increase locally
+        assert_eq!(10, seq.next_issuance_num(&code, None));
+        assert_eq!(11, seq.next_issuance_num(&code, Some(3)));
+        assert_eq!(20, seq.next_issuance_num(&code, Some(20)));
+        assert_eq!(
+            10,
+            seq.next_issuance_num(&AssetTypeCode::gen_random(), None)
+        );
+
+        // 20 is rejected
+        assert_eq!(Some(20..21), seq.issuance_gap(&code, 20));
+        assert_eq!(None, seq.issuance_gap(&code, 21));
+        seq.resync_issuance(&code, 20);
+        seq.set_seq_id(12);
+        assert_eq!(20, seq.next_issuance_num(&code, Some(20)));
+        assert_eq!(12, seq.get_seq_id());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_decode_txn() {
+        use {crate::common::inspect, globutils::wallet};
+
+        let mut prng = ChaChaRng::from_entropy();
+        let params = PublicParams::default();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            1000,
+            ASSET_TYPE_FRA,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk(),
+        );
+        let (ba, _, memo) =
+            build_blind_asset_record(&mut prng, &params.pc_gens, &template, vec![]);
+        let oar = open_blind_asset_record(&ba, &memo, &alice).unwrap();
+
+        let op = TransferOperationBuilder::new()
+            .add_input(TxoRef::Absolute(TxoSID(7)), oar, None, None, 1000)
+            .unwrap()
+            .add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    940,
+                    ASSET_TYPE_FRA,
+                    NonConfidentialAmount_NonConfidentialAssetType,
+                    bob.get_pk(),
+                ),
+                None,
+                None,
+                None,
+            )
+            .unwrap()
+            .balance(None)
+            .unwrap()
+            .create(TransferType::Standard)
+            .unwrap()
+            .sign(&alice)
+            .unwrap()
+            .transaction()
+            .unwrap();
+        let mut builder = TransactionBuilder::from_seq_id(3);
+        builder.add_operation(op);
+        let mut tx = builder.take_transaction();
+        tx.sign_to_map(&alice);
+
+        let json = serde_json::to_string(&tx).unwrap();
+        let decoded = pnk!(inspect::decode_txn(&json));
+        assert_eq!(3, decoded.seq_id);
+        assert_eq!(1, decoded.signers.len());
+        assert_eq!(Some(true), decoded.signers[0].valid);
+        assert_eq!(1, decoded.operations.len());
+
+        let op = &decoded.operations[0];
+        assert_eq!("TransferAsset", op.kind);
+        assert_eq!(Some(true), op.signers[0].valid);
+        assert_eq!("sid:7", op.inputs[0].txo);
+        assert_eq!(Some(1000), op.inputs[0].record.amount);
+        let outputs = op
+            .outputs
+            .iter()
+            .map(|o| (o.record.owner.clone(), o.record.amount))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            vec![
+                (wallet::public_key_to_bech32(bob.get_pk_ref()), Some(940)),
+                (wallet::public_key_to_bech32(alice.get_pk_ref()), Some(60)),
+            ]
+        );
+
+        // the tendermint wire format, and the files of `fn offline`
+        let b64 = base64::encode(json.as_bytes());
+        assert_eq!(decoded.hash, pnk!(inspect::decode_txn(&b64)).hash);
+        let wrapped = format!("{{\"tx\": {}}}", json);
+        assert_eq!(decoded.hash, pnk!(inspect::decode_txn(&wrapped)).hash);
+
+        // a tampered transaction is decoded, with its signatures reported invalid
+        tx.body.memos.push(Memo("tampered".to_owned()));
+        let decoded = pnk!(inspect::decode_txn(&serde_json::to_string(&tx).unwrap()));
+        assert_eq!(Some(false), decoded.signers[0].valid);
+        assert_eq!(vec!["tampered".to_owned()], decoded.memos);
+    }
 }
@@ -0,0 +1,300 @@
+//!
+//! # C FFI of the transaction builder
+//!
+//! Allows native apps(Swift, Kotlin, C++ ...) to build and sign transactions.
+//!
+//! All strings are NUL-terminated UTF-8, strings returned by this library
+//! must be released by `platform_string_free`.
+//!
+//! Functions returning a `bool` or a nullable pointer indicate failures by `false` or `NULL`,
+//! the reason can be fetched by `platform_last_error` in the same thread.
+//!
+//! To generate the C header `platform_ffi.h`, run:
+//! `cargo test --features c-headers -- generate_headers`
+//!
+
+#![deny(missing_docs)]
+
+use {
+    finutils::txn_builder::{TransactionBuilder, TransferOperationBuilder},
+    globutils::{address, wallet},
+    ledger::data_model::{
+        AssetRules, AssetTypeCode, TransferType, TxoRef, TxoSID, ASSET_TYPE_FRA,
+        BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+    },
+    ruc::*,
+    safer_ffi::prelude::*,
+    serde::Deserialize,
+    std::{cell::RefCell, convert::TryInto},
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{open_blind_asset_record, AssetRecordType},
+            sig::{XfrKeyPair, XfrPublicKey},
+            structs::{AssetRecordTemplate, BlindAssetRecord, OwnerMemo},
+        },
+    },
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// An opaque transaction builder,
+/// created by `platform_builder_new` and released by `platform_builder_free`.
+#[derive_ReprC]
+#[ReprC::opaque]
+pub struct PlatformTxBuilder {
+    inner: TransactionBuilder,
+}
+
+/// An input of `platform_builder_add_transfer`,
+/// `record` and `owner_memo` are the ones returned by the `owned_utxos` API.
+#[derive(Deserialize)]
+struct TransferInput {
+    txo_sid: u64,
+    record: BlindAssetRecord,
+    owner_memo: Option<OwnerMemo>,
+    // how much of the record to spend, the rest will be sent back to the owner
+    amount: u64,
+}
+
+/// An output of `platform_builder_add_transfer`.
+#[derive(Deserialize)]
+struct TransferOutput {
+    // bech32 or base64 formated public key
+    recipient: String,
+    amount: u64,
+    // base64 formated asset code, or "FRA"
+    asset: String,
+    #[serde(default)]
+    confidential_amount: bool,
+    #[serde(default)]
+    confidential_type: bool,
+}
+
+/// Create a transaction builder,
+/// `seq_id` is the latest block height, it protects the transaction from replay attacks.
+#[ffi_export]
+fn platform_builder_new(seq_id: u64) -> repr_c::Box<PlatformTxBuilder> {
+    Box::new(PlatformTxBuilder {
+        inner: TransactionBuilder::from_seq_id(seq_id),
+    })
+    .into()
+}
+
+/// Release a transaction builder.
+#[ffi_export]
+fn platform_builder_free(builder: repr_c::Box<PlatformTxBuilder>) {
+    drop(builder)
+}
+
+/// Add an operation to define a new asset, issued by the owner of `seckey`.
+/// - `code`: base64 formated asset code, a random one will be used if `NULL`
+/// - `max_units`: `0` means unlimited
+#[ffi_export]
+fn platform_builder_add_define_asset(
+    builder: &mut PlatformTxBuilder,
+    seckey: char_p::Ref<'_>,
+    code: Option<char_p::Ref<'_>>,
+    memo: char_p::Ref<'_>,
+    decimals: u8,
+    max_units: u64,
+    transferable: bool,
+) -> bool {
+    ffi_call(|| {
+        let kp = restore_keypair(seckey.to_str()).c(d!())?;
+        let code = code
+            .map(|c| AssetTypeCode::new_from_base64(c.to_str()).c(d!()))
+            .transpose()?;
+
+        let mut rules = AssetRules::default();
+        rules
+            .set_decimals(decimals)
+            .c(d!())?
+            .set_max_units(alt!(0 == max_units, None, Some(max_units)))
+            .set_transferable(transferable);
+
+        builder
+            .inner
+            .add_operation_create_asset(&kp, code, rules, memo.to_str())
+            .c(d!())
+            .map(|_| ())
+    })
+    .is_some()
+}
+
+/// Add an operation to issue `amount` units of an asset to its issuer,
+/// `seq_num` should be greater than the one of any prior issuance of the asset.
+#[ffi_export]
+fn platform_builder_add_issue_asset(
+    builder: &mut PlatformTxBuilder,
+    seckey: char_p::Ref<'_>,
+    code: char_p::Ref<'_>,
+    seq_num: u64,
+    amount: u64,
+    confidential_amount: bool,
+) -> bool {
+    ffi_call(|| {
+        let kp = restore_keypair(seckey.to_str()).c(d!())?;
+        let code = AssetTypeCode::new_from_base64(code.to_str()).c(d!())?;
+
+        builder
+            .inner
+            .add_basic_issue_asset(
+                &kp,
+                &code,
+                seq_num,
+                amount,
+                AssetRecordType::from_flags(confidential_amount, false),
+                &PublicParams::default(),
+            )
+            .c(d!())
+            .map(|_| ())
+    })
+    .is_some()
+}
+
+/// Add an operation to transfer assets owned by `seckey`,
+/// unspent parts of the inputs will be sent back to the owner.
+///
+/// - `inputs`: a JSON array of `{"txo_sid", "record", "owner_memo", "amount"}`
+/// - `outputs`: a JSON array of `{"recipient", "amount", "asset",
+///   "confidential_amount", "confidential_type"}`
+///
+/// NOTE: every transaction must pay a fee, that is an output of
+/// `platform_min_fee()` FRA to `platform_fee_receiver()`.
+#[ffi_export]
+fn platform_builder_add_transfer(
+    builder: &mut PlatformTxBuilder,
+    seckey: char_p::Ref<'_>,
+    inputs: char_p::Ref<'_>,
+    outputs: char_p::Ref<'_>,
+) -> bool {
+    ffi_call(|| {
+        let kp = restore_keypair(seckey.to_str()).c(d!())?;
+        let inputs =
+            serde_json::from_str::<Vec<TransferInput>>(inputs.to_str()).c(d!())?;
+        let outputs =
+            serde_json::from_str::<Vec<TransferOutput>>(outputs.to_str()).c(d!())?;
+
+        let mut op = TransferOperationBuilder::new();
+        for i in inputs {
+            let oar = open_blind_asset_record(&i.record, &i.owner_memo, &kp).c(d!())?;
+            op.add_input(
+                TxoRef::Absolute(TxoSID(i.txo_sid)),
+                oar,
+                None,
+                None,
+                i.amount,
+            )
+            .c(d!())?;
+        }
+        for o in outputs {
+            let recipient = restore_pubkey(&o.recipient).c(d!())?;
+            let asset_type = if o.asset.eq_ignore_ascii_case("FRA") {
+                ASSET_TYPE_FRA
+            } else {
+                AssetTypeCode::new_from_base64(&o.asset).c(d!())?.val
+            };
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                o.amount,
+                asset_type,
+                AssetRecordType::from_flags(o.confidential_amount, o.confidential_type),
+                recipient,
+            );
+            op.add_output(&template, None, None, None).c(d!())?;
+        }
+
+        let op = op
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(&kp)
+            .c(d!())?
+            .transaction()
+            .c(d!())?;
+        builder.inner.add_operation(op);
+        Ok(())
+    })
+    .is_some()
+}
+
+/// Sign the transaction, every owner of the inputs and every issuer must sign.
+#[ffi_export]
+fn platform_builder_sign(
+    builder: &mut PlatformTxBuilder,
+    seckey: char_p::Ref<'_>,
+) -> bool {
+    ffi_call(|| {
+        let kp = restore_keypair(seckey.to_str()).c(d!())?;
+        builder.inner.sign_to_map(&kp);
+        Ok(())
+    })
+    .is_some()
+}
+
+/// Serialize the transaction to JSON,
+/// base64 it and broadcast it through the `broadcast_tx_sync` API of tendermint.
+#[ffi_export]
+fn platform_builder_serialize(builder: &PlatformTxBuilder) -> Option<char_p::Box> {
+    ffi_call(|| to_char_p(builder.inner.serialize_str()))
+}
+
+/// The minimal fee of a transaction, in FRA units.
+#[ffi_export]
+fn platform_min_fee() -> u64 {
+    TX_FEE_MIN
+}
+
+/// The base64 formated public key to receive transaction fees.
+#[ffi_export]
+fn platform_fee_receiver() -> char_p::Box {
+    pnk!(to_char_p(wallet::public_key_to_base64(&BLACK_HOLE_PUBKEY)))
+}
+
+/// The reason of the last failure in the current thread, `NULL` if none.
+#[ffi_export]
+fn platform_last_error() -> Option<char_p::Box> {
+    LAST_ERROR
+        .with(|e| e.borrow_mut().take())
+        .and_then(|e| to_char_p(e).ok())
+}
+
+/// Release a string returned by this library.
+#[ffi_export]
+fn platform_string_free(s: char_p::Box) {
+    drop(s)
+}
+
+// record the error, so that it can be fetched by `platform_last_error`
+fn ffi_call<T>(f: impl FnOnce() -> Result<T>) -> Option<T> {
+    match f() {
+        Ok(v) => Some(v),
+        Err(e) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(e.to_string()));
+            None
+        }
+    }
+}
+
+fn to_char_p(s: String) -> Result<char_p::Box> {
+    s.try_into().c(d!("unexpected NUL byte"))
+}
+
+// a base64 formated secret key or a mnemonic
+fn restore_keypair(seckey: &str) -> Result<XfrKeyPair> {
+    address::parse_keypair(seckey).c(d!("invalid secret key"))
+}
+
+fn restore_pubkey(pk: &str) -> Result<XfrPublicKey> {
+    address::parse_address(pk).c(d!("invalid public key"))
+}
+
+/// Generate `platform_ffi.h`, see the doc of this crate.
+#[safer_ffi::cfg_headers]
+#[test]
+fn generate_headers() -> std::io::Result<()> {
+    safer_ffi::headers::builder()
+        .to_file("platform_ffi.h")?
+        .generate()
+}
@@ -0,0 +1,228 @@
+//!
+//! Transaction building
+//!
+
+use {
+    crate::{parse_pubkey, py_err, KeyPair},
+    finutils::txn_builder::{TransactionBuilder, TransferOperationBuilder},
+    ledger::data_model::{
+        AssetRules, AssetTypeCode, TransferType, TxoRef, TxoSID, ASSET_TYPE_FRA,
+        BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+    },
+    pyo3::prelude::*,
+    pythonize::depythonize,
+    ruc::*,
+    serde::Deserialize,
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{open_blind_asset_record, AssetRecordType},
+            structs::{AssetRecordTemplate, BlindAssetRecord, OwnerMemo},
+        },
+    },
+};
+
+/// An input of `TxBuilder.transfer`, an entry of
+/// `LedgerClient.owned_utxos` along with the amount to spend.
+#[derive(Deserialize)]
+struct TransferInput {
+    txo_sid: u64,
+    record: BlindAssetRecord,
+    owner_memo: Option<OwnerMemo>,
+    amount: u64,
+}
+
+/// An output of `TxBuilder.transfer`.
+#[derive(Deserialize)]
+struct TransferOutput {
+    // bech32 or base64 formated public key
+    recipient: String,
+    amount: u64,
+    // base64 formated asset code, or "FRA"
+    asset: String,
+    #[serde(default)]
+    confidential_amount: bool,
+    #[serde(default)]
+    confidential_type: bool,
+}
+
+/// A transaction builder.
+#[pyclass]
+pub struct TxBuilder {
+    inner: TransactionBuilder,
+}
+
+#[pymethods]
+impl TxBuilder {
+    /// `seq_id` is the latest block height, see `LedgerClient.block_height`.
+    #[new]
+    fn new(seq_id: u64) -> Self {
+        TxBuilder {
+            inner: TransactionBuilder::from_seq_id(seq_id),
+        }
+    }
+
+    /// Define a new asset, a random code will be used if `code` is `None`,
+    /// `max_units` is unlimited if `None`.
+    /// Returns the base64 formated code of the asset.
+    #[args(decimals = "6", max_units = "None", transferable = "true")]
+    fn define_asset(
+        &mut self,
+        kp: &KeyPair,
+        code: Option<&str>,
+        memo: &str,
+        decimals: u8,
+        max_units: Option<u64>,
+        transferable: bool,
+    ) -> PyResult<String> {
+        let code = code
+            .map(|c| AssetTypeCode::new_from_base64(c).c(d!()))
+            .transpose()
+            .map_err(py_err)?
+            .unwrap_or_else(AssetTypeCode::gen_random);
+
+        let mut rules = AssetRules::default();
+        rules
+            .set_decimals(decimals)
+            .map_err(py_err)?
+            .set_max_units(max_units)
+            .set_transferable(transferable);
+
+        self.inner
+            .add_operation_create_asset(&kp.inner, Some(code), rules, memo)
+            .map_err(py_err)?;
+        Ok(code.to_base64())
+    }
+
+    /// Issue `amount` units of an asset to its issuer,
+    /// `seq_num` should be greater than the one of any prior issuance of the asset.
+    #[args(confidential_amount = "false")]
+    fn issue_asset(
+        &mut self,
+        kp: &KeyPair,
+        code: &str,
+        seq_num: u64,
+        amount: u64,
+        confidential_amount: bool,
+    ) -> PyResult<()> {
+        let code = AssetTypeCode::new_from_base64(code).map_err(py_err)?;
+        self.inner
+            .add_basic_issue_asset(
+                &kp.inner,
+                &code,
+                seq_num,
+                amount,
+                AssetRecordType::from_flags(confidential_amount, false),
+                &PublicParams::default(),
+            )
+            .map_err(py_err)
+            .map(|_| ())
+    }
+
+    /// Transfer assets owned by `kp`, unspent parts of the inputs
+    /// will be sent back to the owner.
+    ///
+    /// - `inputs`: a list of `{"txo_sid", "record", "owner_memo", "amount"}`
+    /// - `outputs`: a list of `{"recipient", "amount", "asset",
+    ///   "confidential_amount", "confidential_type"}`
+    ///
+    /// NOTE: every transaction must pay a fee, see `add_fee`.
+    fn transfer(
+        &mut self,
+        kp: &KeyPair,
+        inputs: &PyAny,
+        outputs: &PyAny,
+    ) -> PyResult<()> {
+        let inputs = depythonize::<Vec<TransferInput>>(inputs)?;
+        let outputs = depythonize::<Vec<TransferOutput>>(outputs)?;
+
+        let mut op = TransferOperationBuilder::new();
+        for i in inputs {
+            let oar = open_blind_asset_record(&i.record, &i.owner_memo, &kp.inner)
+                .map_err(py_err)?;
+            op.add_input(
+                TxoRef::Absolute(TxoSID(i.txo_sid)),
+                oar,
+                None,
+                None,
+                i.amount,
+            )
+            .map_err(py_err)?;
+        }
+        for o in outputs {
+            let asset_type = if o.asset.eq_ignore_ascii_case("FRA") {
+                ASSET_TYPE_FRA
+            } else {
+                AssetTypeCode::new_from_base64(&o.asset)
+                    .map_err(py_err)?
+                    .val
+            };
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                o.amount,
+                asset_type,
+                AssetRecordType::from_flags(o.confidential_amount, o.confidential_type),
+                parse_pubkey(&o.recipient)?,
+            );
+            op.add_output(&template, None, None, None).map_err(py_err)?;
+        }
+
+        let op = op
+            .create(TransferType::Standard)
+            .and_then(|op| op.sign(&kp.inner))
+            .and_then(|op| op.transaction())
+            .map_err(py_err)?;
+        self.inner.add_operation(op);
+        Ok(())
+    }
+
+    /// Pay the minimal fee from the FRA `inputs` of `kp`,
+    /// `inputs` are in the same format as the ones of `transfer`.
+    fn add_fee(&mut self, kp: &KeyPair, inputs: &PyAny) -> PyResult<()> {
+        let inputs = depythonize::<Vec<TransferInput>>(inputs)?;
+
+        let mut op = TransferOperationBuilder::new();
+        for i in inputs {
+            let oar = open_blind_asset_record(&i.record, &i.owner_memo, &kp.inner)
+                .map_err(py_err)?;
+            op.add_input(
+                TxoRef::Absolute(TxoSID(i.txo_sid)),
+                oar,
+                None,
+                None,
+                i.amount,
+            )
+            .map_err(py_err)?;
+        }
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            TX_FEE_MIN,
+            ASSET_TYPE_FRA,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            *BLACK_HOLE_PUBKEY,
+        );
+        op.add_output(&template, None, None, None).map_err(py_err)?;
+
+        let op = op
+            .create(TransferType::Standard)
+            .and_then(|op| op.sign(&kp.inner))
+            .and_then(|op| op.transaction())
+            .map_err(py_err)?;
+        self.inner.add_operation(op);
+        Ok(())
+    }
+
+    /// Reject the transaction if it is not included at or before the block `height`,
+    /// must be set before signing.
+    fn set_expiry_height(&mut self, height: u64) {
+        self.inner.set_expiry_height(height);
+    }
+
+    /// Sign the transaction, every owner of the inputs and every issuer must sign.
+    fn sign(&mut self, kp: &KeyPair) {
+        self.inner.sign_to_map(&kp.inner);
+    }
+
+    /// The transaction in JSON, see `LedgerClient.submit`.
+    fn to_json(&self) -> String {
+        self.inner.serialize_str()
+    }
+}
@@ -0,0 +1,126 @@
+//!
+//! Clients of the query(8667), ledger(8668) and submission(8669) servers
+//!
+
+use {
+    crate::{parse_pubkey, py_err},
+    globutils::wallet,
+    ledger::data_model::{TxoSID, Utxo},
+    pyo3::prelude::*,
+    pythonize::pythonize,
+    ruc::*,
+    serde::{de::DeserializeOwned, Serialize},
+    std::collections::HashMap,
+    zei::xfr::structs::{BlindAssetRecord, OwnerMemo},
+};
+
+/// An entry of `LedgerClient.owned_utxos`.
+#[derive(Serialize)]
+struct OwnedUtxo {
+    txo_sid: u64,
+    record: BlindAssetRecord,
+    owner_memo: Option<OwnerMemo>,
+}
+
+/// A client of a findora node.
+#[pyclass]
+pub struct LedgerClient {
+    serv_addr: String,
+}
+
+#[pymethods]
+impl LedgerClient {
+    /// `serv_addr` is the address of the node without port, eg. "http://localhost".
+    #[new]
+    fn new(serv_addr: &str) -> Self {
+        LedgerClient {
+            serv_addr: serv_addr.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// The latest block height, see `TxBuilder`.
+    fn block_height(&self) -> PyResult<u64> {
+        self.get::<(serde_json::Value, u64, serde_json::Value)>(8668, "global_state")
+            .map(|resp| resp.1)
+    }
+
+    /// Unspent outputs owned by a bech32 or base64 formated public key, as a list of
+    /// `{"txo_sid", "record", "owner_memo"}`, sorted by `txo_sid`.
+    fn owned_utxos(&self, py: Python, address: &str) -> PyResult<PyObject> {
+        let pk = parse_pubkey(address)?;
+        let mut utxos = self
+            .get::<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>>(
+                8668,
+                &format!("owned_utxos/{}", wallet::public_key_to_base64(&pk)),
+            )?
+            .into_iter()
+            .map(|(sid, (utxo, owner_memo))| OwnedUtxo {
+                txo_sid: sid.0,
+                record: utxo.0.record,
+                owner_memo,
+            })
+            .collect::<Vec<_>>();
+        utxos.sort_by_key(|u| u.txo_sid);
+
+        pythonize(py, &utxos).map_err(Into::into)
+    }
+
+    /// The owner memo of a TXO, `None` if it is not confidential.
+    fn owner_memo(&self, py: Python, txo_sid: u64) -> PyResult<PyObject> {
+        let memo =
+            self.get::<Option<OwnerMemo>>(8667, &format!("get_owner_memo/{}", txo_sid))?;
+        pythonize(py, &memo).map_err(Into::into)
+    }
+
+    /// The definition of an asset, `code` is base64 formated.
+    fn asset_type(&self, py: Python, code: &str) -> PyResult<PyObject> {
+        let asset =
+            self.get::<serde_json::Value>(8668, &format!("asset_token/{}", code))?;
+        pythonize(py, &asset).map_err(Into::into)
+    }
+
+    /// Assets created by a bech32 or base64 formated public key.
+    fn created_assets(&self, py: Python, address: &str) -> PyResult<PyObject> {
+        let pk = parse_pubkey(address)?;
+        let assets = self.get::<serde_json::Value>(
+            8667,
+            &format!("get_created_assets/{}", wallet::public_key_to_base64(&pk)),
+        )?;
+        pythonize(py, &assets).map_err(Into::into)
+    }
+
+    /// Submit a transaction built by `TxBuilder.to_json`,
+    /// returns a handle to query its status by `transaction_status`.
+    fn submit(&self, tx: &str) -> PyResult<String> {
+        let url = format!("{}:8669/submit_transaction", self.serv_addr);
+        attohttpc::post(&url)
+            .header(attohttpc::header::CONTENT_TYPE, "application/json")
+            .text(tx)
+            .send()
+            .c(d!("fail to send transaction"))
+            .and_then(|resp| resp.error_for_status().c(d!()))
+            .and_then(|resp| resp.bytes().c(d!()))
+            .and_then(|b| serde_json::from_slice::<String>(&b).c(d!()))
+            .map_err(py_err)
+    }
+
+    /// The status of a submitted transaction.
+    fn transaction_status(&self, py: Python, handle: &str) -> PyResult<PyObject> {
+        let status =
+            self.get::<serde_json::Value>(8669, &format!("txn_status/{}", handle))?;
+        pythonize(py, &status).map_err(Into::into)
+    }
+}
+
+impl LedgerClient {
+    fn get<T: DeserializeOwned>(&self, port: u16, path: &str) -> PyResult<T> {
+        let url = format!("{}:{}/{}", self.serv_addr, port, path);
+        attohttpc::get(&url)
+            .send()
+            .c(d!())
+            .and_then(|resp| resp.error_for_status().c(d!()))
+            .and_then(|resp| resp.bytes().c(d!()))
+            .and_then(|b| serde_json::from_slice(&b).c(d!()))
+            .map_err(py_err)
+    }
+}
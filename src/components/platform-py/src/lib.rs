@@ -0,0 +1,154 @@
+//!
+//! # Python bindings
+//!
+//! Key generation, transaction building, owner-memo decryption and
+//! the query clients of a findora node, for scripting issuances and audits.
+//!
+//! ```python
+//! import platform_py as fp
+//!
+//! kp = fp.KeyPair.from_mnemonic(fp.generate_mnemonic())
+//! client = fp.LedgerClient("http://localhost")
+//!
+//! builder = fp.TxBuilder(client.block_height())
+//! builder.define_asset(kp, code=None, memo="test", decimals=6)
+//! builder.sign(kp)
+//! client.submit(builder.to_json())
+//! ```
+//!
+//! Build it by `maturin build --release` in this directory.
+//!
+
+#![deny(missing_docs)]
+
+mod builder;
+mod client;
+
+use {
+    globutils::{address, wallet},
+    ledger::data_model::AssetTypeCode,
+    pyo3::{exceptions::PyValueError, prelude::*},
+    pythonize::{depythonize, pythonize},
+    ruc::*,
+    serde::Serialize,
+    zei::xfr::{
+        asset_record::open_blind_asset_record,
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::{BlindAssetRecord, OwnerMemo},
+    },
+};
+
+/// A findora key pair.
+#[pyclass]
+#[derive(Clone)]
+pub struct KeyPair {
+    pub(crate) inner: XfrKeyPair,
+}
+
+#[pymethods]
+impl KeyPair {
+    /// Restore a key pair from a mnemonic.
+    #[staticmethod]
+    fn from_mnemonic(phrase: &str) -> PyResult<Self> {
+        wallet::restore_keypair_from_mnemonic_default(phrase)
+            .map(|inner| KeyPair { inner })
+            .map_err(py_err)
+    }
+
+    /// Restore a key pair from a base64 formated secret key.
+    #[staticmethod]
+    fn from_seckey(seckey: &str) -> PyResult<Self> {
+        wallet::restore_keypair_from_seckey_base64(seckey)
+            .map(|inner| KeyPair { inner })
+            .map_err(py_err)
+    }
+
+    /// The bech32 formated address.
+    #[getter]
+    fn address(&self) -> String {
+        wallet::public_key_to_bech32(self.inner.get_pk_ref())
+    }
+
+    /// The base64 formated public key.
+    #[getter]
+    fn pubkey(&self) -> String {
+        wallet::public_key_to_base64(self.inner.get_pk_ref())
+    }
+
+    /// The base64 formated secret key.
+    #[getter]
+    fn seckey(&self) -> PyResult<String> {
+        serde_json::to_string(self.inner.get_sk_ref())
+            .map(|sk| sk.trim_matches('"').to_owned())
+            .c(d!())
+            .map_err(py_err)
+    }
+}
+
+/// An opened asset record.
+#[derive(Serialize)]
+struct OpenedRecord {
+    amount: u64,
+    asset_type: String,
+}
+
+/// Generate a mnemonic of 24 english words.
+#[pyfunction]
+fn generate_mnemonic() -> PyResult<String> {
+    wallet::generate_mnemonic_custom(24, "en").map_err(py_err)
+}
+
+/// Convert a bech32 formated address to a base64 formated public key.
+#[pyfunction]
+fn address_to_pubkey(address: &str) -> PyResult<String> {
+    wallet::public_key_from_bech32(address)
+        .map(|pk| wallet::public_key_to_base64(&pk))
+        .map_err(py_err)
+}
+
+/// Decrypt the amount and asset type of an asset record with its owner memo,
+/// `record` and `owner_memo` are the ones returned by `LedgerClient.owned_utxos`.
+#[pyfunction]
+fn open_record(
+    py: Python,
+    kp: &KeyPair,
+    record: &PyAny,
+    owner_memo: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    let record = depythonize::<BlindAssetRecord>(record)?;
+    let owner_memo = owner_memo.map(depythonize::<OwnerMemo>).transpose()?;
+
+    let oar =
+        open_blind_asset_record(&record, &owner_memo, &kp.inner).map_err(py_err)?;
+    let opened = OpenedRecord {
+        amount: oar.amount,
+        asset_type: AssetTypeCode {
+            val: oar.asset_type,
+        }
+        .to_base64(),
+    };
+
+    pythonize(py, &opened).map_err(Into::into)
+}
+
+#[pymodule]
+fn platform_py(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<KeyPair>()?;
+    m.add_class::<builder::TxBuilder>()?;
+    m.add_class::<client::LedgerClient>()?;
+    m.add_function(wrap_pyfunction!(generate_mnemonic, m)?)?;
+    m.add_function(wrap_pyfunction!(address_to_pubkey, m)?)?;
+    m.add_function(wrap_pyfunction!(open_record, m)?)?;
+    Ok(())
+}
+
+pub(crate) fn py_err(e: Box<dyn RucError>) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+// a bech32, base64 or hex formated public key
+pub(crate) fn parse_pubkey(pk: &str) -> PyResult<XfrPublicKey> {
+    address::parse_address(pk)
+        .c(d!("invalid public key"))
+        .map_err(py_err)
+}
@@ -0,0 +1,393 @@
+//!
+//! # Clients of the servers of a node
+//!
+
+use {
+    crate::error::{Error, Result},
+    finutils::txn_builder::{SeqTracker, TransactionBuilder},
+    globutils::{wallet, wire::Encoding, HashOf, SignatureOf},
+    ledger::data_model::{
+        AssetType, AssetTypeCode, FinalizedTransaction, StateCommitmentData,
+        Transaction, TxnCheckError, TxnSID, TxoSID, Utxo,
+    },
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+    },
+    zei::xfr::{sig::XfrPublicKey, structs::OwnerMemo},
+};
+
+/// The urls of the servers of a node.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Endpoints {
+    /// the submission server, port 8669 by default
+    pub submission: String,
+    /// the query server, port 8667 by default
+    pub query: String,
+    /// the ledger service, port 8668 by default
+    pub ledger: String,
+}
+
+impl Endpoints {
+    /// The servers of a node on their default ports, `serv_addr` is
+    /// the address of the node with its scheme, eg. `http://127.0.0.1`.
+    pub fn new(serv_addr: &str) -> Self {
+        let serv_addr = serv_addr.trim_end_matches('/');
+        Endpoints {
+            submission: format!("{}:8669", serv_addr),
+            query: format!("{}:8667", serv_addr),
+            ledger: format!("{}:8668", serv_addr),
+        }
+    }
+}
+
+/// The status of a submitted transaction, see the submission server.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[allow(missing_docs)]
+pub enum TxnStatus {
+    Rejected(TxnCheckError),
+    Committed((TxnSID, Vec<TxoSID>)),
+    Pending,
+    /// not forwarded yet, the outputs of these transactions are spent by it
+    Waiting(Vec<String>),
+    Cancelled,
+    /// replaced by the transaction of this handle
+    Replaced(String),
+}
+
+/// A blocking client.
+#[derive(Clone, Debug)]
+pub struct Client {
+    endpoints: Endpoints,
+    // of the transactions submitted and fetched
+    encoding: Encoding,
+}
+
+impl Client {
+    /// A client of the servers of a node on their default ports, see `Endpoints::new`.
+    pub fn new(serv_addr: &str) -> Self {
+        Self::with_endpoints(Endpoints::new(serv_addr))
+    }
+
+    #[allow(missing_docs)]
+    pub fn with_endpoints(endpoints: Endpoints) -> Self {
+        Client {
+            endpoints,
+            encoding: Encoding::default(),
+        }
+    }
+
+    /// Submit and fetch the transactions in `encoding`, JSON by default,
+    /// CBOR is smaller and faster to parse for high volumes.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    #[allow(missing_docs)]
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// The sequence id of the ledger, to be carried by new transactions.
+    pub fn seq_id(&self) -> Result<u64> {
+        type Resp = (
+            HashOf<Option<StateCommitmentData>>,
+            u64,
+            SignatureOf<(HashOf<Option<StateCommitmentData>>, u64)>,
+        );
+        let url = format!("{}/global_state", self.endpoints.ledger);
+        get::<Resp>(&url).map(|resp| resp.1)
+    }
+
+    /// A transaction builder with the current sequence id.
+    pub fn new_tx_builder(&self) -> Result<TransactionBuilder> {
+        self.seq_id().map(TransactionBuilder::from_seq_id)
+    }
+
+    /// The unspent outputs owned by `pk`, with their owner memos.
+    pub fn owned_utxos(
+        &self,
+        pk: &XfrPublicKey,
+    ) -> Result<Vec<(TxoSID, Utxo, Option<OwnerMemo>)>> {
+        let url = format!(
+            "{}/owned_utxos/{}",
+            self.endpoints.ledger,
+            wallet::public_key_to_base64(pk)
+        );
+        let mut utxos = get::<HashMap<TxoSID, (Utxo, Option<OwnerMemo>)>>(&url)?
+            .into_iter()
+            .map(|(sid, (utxo, memo))| (sid, utxo, memo))
+            .collect::<Vec<_>>();
+        utxos.sort_by_key(|(sid, _, _)| *sid);
+        Ok(utxos)
+    }
+
+    /// The outputs of `pk` still locked by vesting schedules, which can not be spent.
+    pub fn locked_utxos(&self, pk: &XfrPublicKey) -> Result<HashSet<TxoSID>> {
+        #[derive(Deserialize)]
+        struct VestingOutput {
+            sid: TxoSID,
+            locked: u64,
+        }
+        #[derive(Deserialize)]
+        struct VestingStatus {
+            outputs: Vec<VestingOutput>,
+        }
+
+        let url = format!(
+            "{}/vesting/{}",
+            self.endpoints.ledger,
+            wallet::public_key_to_base64(pk)
+        );
+        match get::<VestingStatus>(&url) {
+            Ok(v) => Ok(v
+                .outputs
+                .into_iter()
+                .filter(|o| 0 < o.locked)
+                .map(|o| o.sid)
+                .collect()),
+            // nodes without vesting support
+            Err(Error::Status { status: 404, .. }) => Ok(HashSet::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The definition of an asset.
+    pub fn asset_type(&self, code: &AssetTypeCode) -> Result<AssetType> {
+        let url = format!("{}/asset_token/{}", self.endpoints.ledger, code.to_base64());
+        get(&url)
+    }
+
+    /// The least issuance sequence number accepted for an asset,
+    /// `None` if it has not been issued.
+    pub fn issuance_num(&self, code: &AssetTypeCode) -> Result<Option<u64>> {
+        let url = format!(
+            "{}/asset_issuance_num/{}",
+            self.endpoints.ledger,
+            code.to_base64()
+        );
+        match get(&url) {
+            Ok(n) => Ok(Some(n)),
+            Err(Error::Status { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// A tracker of the sequence numbers, from the current sequence id,
+    /// for building many transactions and issuances locally.
+    pub fn seq_tracker(&self) -> Result<SeqTracker> {
+        self.seq_id().map(SeqTracker::new)
+    }
+
+    /// Submit a signed transaction, returns its handle.
+    pub fn submit(&self, tx: &Transaction) -> Result<String> {
+        self.submit_with_id(tx, None)
+    }
+
+    /// Submit a signed transaction along with a request id chosen by the client,
+    /// retries with the same id get the handle of the first submission,
+    /// so they are safe over flaky networks.
+    pub fn submit_with_id(
+        &self,
+        tx: &Transaction,
+        request_id: Option<&str>,
+    ) -> Result<String> {
+        let url = format!("{}/submit_transaction", self.endpoints.submission);
+        let mut req = attohttpc::post(&url);
+        if let Some(id) = request_id {
+            req = req.param("request_id", id);
+        }
+        let body = self
+            .encoding
+            .encode(tx)
+            .map_err(|e| Error::Decode(e.to_string()))?;
+        let resp = req
+            .header(attohttpc::header::CONTENT_TYPE, self.encoding.mime())
+            .bytes(body)
+            .send()
+            .map_err(|e| Error::Http(e.to_string()))?;
+        // malformed transactions are answered with the reason in JSON
+        decode(&url, resp).map_err(|e| match e {
+            Error::Status { ref message, .. } => serde_json::from_str(message)
+                .map(Error::Rejected)
+                .unwrap_or(e),
+            e => e,
+        })
+    }
+
+    /// A committed transaction, with the ids of its outputs.
+    pub fn transaction(&self, sid: TxnSID) -> Result<FinalizedTransaction> {
+        let url = format!("{}/txn_sid_light/{}", self.endpoints.ledger, sid.0);
+        let resp = attohttpc::get(&url)
+            .header(attohttpc::header::ACCEPT, self.encoding.mime())
+            .send()
+            .map_err(|e| Error::Http(e.to_string()))?;
+        decode_negotiated(&url, resp)
+    }
+
+    /// The status of a submitted transaction, `None` if unknown to the node.
+    pub fn txn_status(&self, handle: &str) -> Result<Option<TxnStatus>> {
+        let url = format!("{}/txn_status/{}", self.endpoints.submission, handle);
+        let resp = attohttpc::get(&url)
+            .send()
+            .map_err(|e| Error::Http(e.to_string()))?;
+        // an explanation is answered for the unknown handles
+        let body = text(&url, resp)?;
+        Ok(serde_json::from_str(&body).ok())
+    }
+}
+
+/// A non-blocking client, the requests of a `Client` run on the blocking
+/// threads of the tokio runtime.
+#[derive(Clone, Debug)]
+pub struct AsyncClient {
+    inner: Arc<Client>,
+}
+
+macro_rules! blocking {
+    ($self: ident, |$c: ident| $call: expr) => {{
+        let $c = Arc::clone(&$self.inner);
+        tokio::task::spawn_blocking(move || $call)
+            .await
+            .map_err(|e| Error::Http(e.to_string()))?
+    }};
+}
+
+impl AsyncClient {
+    /// See `Client::new`.
+    pub fn new(serv_addr: &str) -> Self {
+        Self::from(Client::new(serv_addr))
+    }
+
+    /// The blocking client behind.
+    pub fn blocking(&self) -> &Client {
+        &self.inner
+    }
+
+    /// See `Client::seq_id`.
+    pub async fn seq_id(&self) -> Result<u64> {
+        blocking!(self, |c| c.seq_id())
+    }
+
+    /// See `Client::new_tx_builder`.
+    pub async fn new_tx_builder(&self) -> Result<TransactionBuilder> {
+        self.seq_id().await.map(TransactionBuilder::from_seq_id)
+    }
+
+    /// See `Client::owned_utxos`.
+    pub async fn owned_utxos(
+        &self,
+        pk: &XfrPublicKey,
+    ) -> Result<Vec<(TxoSID, Utxo, Option<OwnerMemo>)>> {
+        let pk = *pk;
+        blocking!(self, |c| c.owned_utxos(&pk))
+    }
+
+    /// See `Client::locked_utxos`.
+    pub async fn locked_utxos(&self, pk: &XfrPublicKey) -> Result<HashSet<TxoSID>> {
+        let pk = *pk;
+        blocking!(self, |c| c.locked_utxos(&pk))
+    }
+
+    /// See `Client::asset_type`.
+    pub async fn asset_type(&self, code: &AssetTypeCode) -> Result<AssetType> {
+        let code = *code;
+        blocking!(self, |c| c.asset_type(&code))
+    }
+
+    /// See `Client::issuance_num`.
+    pub async fn issuance_num(&self, code: &AssetTypeCode) -> Result<Option<u64>> {
+        let code = *code;
+        blocking!(self, |c| c.issuance_num(&code))
+    }
+
+    /// See `Client::seq_tracker`.
+    pub async fn seq_tracker(&self) -> Result<SeqTracker> {
+        blocking!(self, |c| c.seq_tracker())
+    }
+
+    /// See `Client::submit`.
+    pub async fn submit(&self, tx: &Transaction) -> Result<String> {
+        let tx = tx.clone();
+        blocking!(self, |c| c.submit(&tx))
+    }
+
+    /// See `Client::submit_with_id`.
+    pub async fn submit_with_id(
+        &self,
+        tx: &Transaction,
+        request_id: Option<&str>,
+    ) -> Result<String> {
+        let (tx, request_id) = (tx.clone(), request_id.map(|id| id.to_owned()));
+        blocking!(self, |c| c.submit_with_id(&tx, request_id.as_deref()))
+    }
+
+    /// See `Client::transaction`.
+    pub async fn transaction(&self, sid: TxnSID) -> Result<FinalizedTransaction> {
+        blocking!(self, |c| c.transaction(sid))
+    }
+
+    /// See `Client::txn_status`.
+    pub async fn txn_status(&self, handle: &str) -> Result<Option<TxnStatus>> {
+        let handle = handle.to_owned();
+        blocking!(self, |c| c.txn_status(&handle))
+    }
+}
+
+impl From<Client> for AsyncClient {
+    fn from(c: Client) -> Self {
+        AsyncClient { inner: Arc::new(c) }
+    }
+}
+
+fn get<T: DeserializeOwned>(url: &str) -> Result<T> {
+    let resp = attohttpc::get(url)
+        .send()
+        .map_err(|e| Error::Http(e.to_string()))?;
+    decode(url, resp)
+}
+
+fn text(url: &str, resp: attohttpc::Response) -> Result<String> {
+    let status = resp.status();
+    let body = resp.text().map_err(|e| Error::Http(e.to_string()))?;
+    if !status.is_success() {
+        return Err(Error::Status {
+            url: url.to_owned(),
+            status: status.as_u16(),
+            message: body,
+        });
+    }
+    Ok(body)
+}
+
+fn decode<T: DeserializeOwned>(url: &str, resp: attohttpc::Response) -> Result<T> {
+    text(url, resp)
+        .and_then(|b| serde_json::from_str(&b).map_err(|e| Error::Decode(e.to_string())))
+}
+
+// in the encoding of the `Content-Type` of the response
+fn decode_negotiated<T: DeserializeOwned>(
+    url: &str,
+    resp: attohttpc::Response,
+) -> Result<T> {
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(Error::Status {
+            url: url.to_owned(),
+            status: status.as_u16(),
+            message: resp.text().map_err(|e| Error::Http(e.to_string()))?,
+        });
+    }
+    let encoding = Encoding::from_content_type(
+        resp.headers()
+            .get(attohttpc::header::CONTENT_TYPE)
+            .and_then(|ct| ct.to_str().ok()),
+    )
+    .map_err(|e| Error::Decode(e.to_string()))?;
+    let body = resp.bytes().map_err(|e| Error::Http(e.to_string()))?;
+    encoding
+        .decode(&body)
+        .map_err(|e| Error::Decode(e.to_string()))
+}
@@ -0,0 +1,89 @@
+//!
+//! # Errors of the SDK
+//!
+
+use {ledger::data_model::TxnCheckError, std::fmt};
+
+/// The errors of the SDK.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// the node can not be reached
+    Http(String),
+    /// the node answered with an error status
+    Status {
+        #[allow(missing_docs)]
+        url: String,
+        #[allow(missing_docs)]
+        status: u16,
+        /// the body of the answer
+        message: String,
+    },
+    /// the transaction is rejected by the node, with the code,
+    /// the offending operation if known, and the details
+    Rejected(TxnCheckError),
+    /// the answer of the node can not be decoded
+    Decode(String),
+    /// invalid keys, addresses or asset codes
+    InvalidInput(String),
+    /// the owned outputs do not cover the amounts to spend
+    InsufficientBalance {
+        /// base64 code of the asset
+        asset: String,
+        #[allow(missing_docs)]
+        needed: u64,
+        #[allow(missing_docs)]
+        available: u64,
+    },
+    /// the transaction can not be built
+    Build(String),
+    /// the transaction is refused by the policy of the wallet, see `WalletPolicy`
+    PolicyViolation(String),
+}
+
+/// The results of the SDK.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "http error: {}", e),
+            Error::Status {
+                url,
+                status,
+                message,
+            } => write!(f, "{} answered {}: {}", url, status, message),
+            Error::Rejected(e) => write!(f, "transaction rejected: {}", e),
+            Error::Decode(e) => write!(f, "invalid answer: {}", e),
+            Error::InvalidInput(e) => write!(f, "invalid input: {}", e),
+            Error::InsufficientBalance {
+                asset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "insufficient balance of {}: {} needed, {} available",
+                asset, needed, available
+            ),
+            Error::Build(e) => write!(f, "fail to build the transaction: {}", e),
+            Error::PolicyViolation(e) => write!(f, "refused by the policy: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// the errors of the internal helpers are reported as strings
+pub(crate) trait ErrorCtx<T> {
+    fn or_build(self) -> Result<T>;
+    fn or_input(self) -> Result<T>;
+}
+
+impl<T, E: fmt::Display> ErrorCtx<T> for std::result::Result<T, E> {
+    fn or_build(self) -> Result<T> {
+        self.map_err(|e| Error::Build(e.to_string()))
+    }
+
+    fn or_input(self) -> Result<T> {
+        self.map_err(|e| Error::InvalidInput(e.to_string()))
+    }
+}
@@ -0,0 +1,37 @@
+//!
+//! # Platform SDK
+//!
+//! The client side of a findora node, for wallets and integrators:
+//!
+//! - `Client`, `AsyncClient`: queries and submissions, against the servers of a node,
+//!   the transactions in JSON or in CBOR, see `Encoding`
+//! - `Wallet`: a key pair, and the transactions it signs: transfers,
+//!   definitions and issuances of assets
+//! - `WalletPolicy`: the limits, receivers and approvals checked by a wallet
+//!   before it signs
+//!
+//! All errors are `Error`, see its variants.
+//!
+//! ```ignore
+//! let client = Client::new("https://prod-mainnet.prod.findora.org");
+//! let wallet = Wallet::from_str(mnemonic)?;
+//! let tx = wallet.transfer(&client, &receiver, 1_000_000, None)?;
+//! let handle = client.submit(&tx)?;
+//! ```
+//!
+
+#![deny(warnings)]
+#![deny(missing_docs)]
+
+mod client;
+mod error;
+mod policy;
+mod wallet;
+
+pub use {
+    client::{AsyncClient, Client, Endpoints, TxnStatus},
+    error::{Error, Result},
+    globutils::wire::Encoding,
+    policy::{SpendProposal, SpendingLimit, WalletPolicy},
+    wallet::Wallet,
+};
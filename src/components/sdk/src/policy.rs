@@ -0,0 +1,313 @@
+//!
+//! # Policies of wallets
+//!
+//! The local guardrails of a `Wallet`, checked before it signs a transaction
+//! sending to others, unlike the spending policies enforced by the ledger:
+//!
+//! - limits per asset, on a transaction and on a rolling period
+//! - the receivers allowed, any if none is set
+//! - the approvals of a second party, by a callback, above a threshold
+//!
+//! The changes, and the fees, are not counted. The spends are recorded when the
+//! transactions are signed, whether they are submitted or not.
+//!
+
+use {
+    crate::error::{Error, Result},
+    globutils::wallet,
+    ledger::data_model::{AssetTypeCode, ASSET_TYPE_FRA},
+    std::{
+        collections::{BTreeMap, HashSet, VecDeque},
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+    zei::xfr::{sig::XfrPublicKey, structs::AssetType as ZeiAssetType},
+};
+
+/// The limits on the amounts of an asset sent to others.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SpendingLimit {
+    /// by a single transaction
+    pub per_txn: Option<u64>,
+    /// (amount, period), by all the transactions signed within the period
+    pub per_period: Option<(u64, Duration)>,
+}
+
+/// A transaction to approve, see `WalletPolicy::require_approval`.
+#[derive(Clone, Debug)]
+pub struct SpendProposal {
+    /// the signer
+    pub wallet: XfrPublicKey,
+    /// (receiver, asset, amount), the changes and the fees excluded
+    pub outputs: Vec<(XfrPublicKey, AssetTypeCode, u64)>,
+}
+
+type Approver = Box<dyn Fn(&SpendProposal) -> bool + Send + Sync>;
+
+/// The policy of a wallet, see the module documentation.
+#[derive(Default)]
+pub struct WalletPolicy {
+    limits: BTreeMap<ZeiAssetType, SpendingLimit>,
+    allowed: HashSet<XfrPublicKey>,
+    // (threshold by asset, callback)
+    approval: Option<(BTreeMap<ZeiAssetType, u64>, Approver)>,
+    // (time, asset, amount) of the spends within the longest period
+    spent: Mutex<VecDeque<(Instant, ZeiAssetType, u64)>>,
+}
+
+impl WalletPolicy {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the spends of an asset, FRA if `code` is `None`.
+    pub fn limit(mut self, code: Option<AssetTypeCode>, limit: SpendingLimit) -> Self {
+        self.limits.insert(asset_of(code), limit);
+        self
+    }
+
+    /// Allow sending to `receiver`, once a receiver is allowed,
+    /// all the others are refused.
+    pub fn allow(mut self, receiver: XfrPublicKey) -> Self {
+        self.allowed.insert(receiver);
+        self
+    }
+
+    /// The transactions sending more than `thresholds` of an asset must be
+    /// approved by `approver`, every one of them if `thresholds` is empty.
+    pub fn require_approval<F>(
+        mut self,
+        thresholds: &[(Option<AssetTypeCode>, u64)],
+        approver: F,
+    ) -> Self
+    where
+        F: Fn(&SpendProposal) -> bool + Send + Sync + 'static,
+    {
+        let thresholds = thresholds
+            .iter()
+            .map(|(code, am)| (asset_of(*code), *am))
+            .collect();
+        self.approval = Some((thresholds, Box::new(approver)));
+        self
+    }
+
+    /// Check the outputs of a transaction of `wallet` before it is signed,
+    /// and record them as spent if they are allowed.
+    pub(crate) fn check(
+        &self,
+        wallet: &XfrPublicKey,
+        outputs: &[(XfrPublicKey, ZeiAssetType, u64)],
+    ) -> Result<()> {
+        // the changes go back to the wallet
+        let outputs = outputs
+            .iter()
+            .filter(|(pk, _, _)| pk != wallet)
+            .copied()
+            .collect::<Vec<_>>();
+        if outputs.is_empty() {
+            return Ok(());
+        }
+
+        if !self.allowed.is_empty() {
+            if let Some((pk, _, _)) =
+                outputs.iter().find(|(pk, _, _)| !self.allowed.contains(pk))
+            {
+                return Err(refused(format!(
+                    "{} is not an allowed receiver",
+                    wallet::public_key_to_bech32(pk)
+                )));
+            }
+        }
+
+        let mut sums = BTreeMap::new();
+        for (_, asset, am) in outputs.iter() {
+            let s = sums.entry(*asset).or_insert(0u64);
+            *s = s.saturating_add(*am);
+        }
+
+        let mut spent = self.spent.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let max_period = self
+            .limits
+            .values()
+            .filter_map(|l| l.per_period.map(|(_, p)| p))
+            .max()
+            .unwrap_or_default();
+        while spent
+            .front()
+            .map_or(false, |(t, _, _)| now.duration_since(*t) > max_period)
+        {
+            spent.pop_front();
+        }
+
+        for (asset, sum) in sums.iter() {
+            let limit = match self.limits.get(asset) {
+                Some(l) => l,
+                None => continue,
+            };
+            let code = AssetTypeCode { val: *asset }.to_base64();
+            if let Some(max) = limit.per_txn.filter(|max| sum > max) {
+                return Err(refused(format!(
+                    "{} of {} exceeds the limit of {} per transaction",
+                    sum, code, max
+                )));
+            }
+            if let Some((max, period)) = limit.per_period {
+                let in_period = spent
+                    .iter()
+                    .filter(|(t, a, _)| a == asset && now.duration_since(*t) <= period)
+                    .fold(0u64, |acc, (_, _, am)| acc.saturating_add(*am));
+                if in_period.saturating_add(*sum) > max {
+                    return Err(refused(format!(
+                        "{} of {} exceeds the limit of {} per {}s, {} spent",
+                        sum,
+                        code,
+                        max,
+                        period.as_secs(),
+                        in_period
+                    )));
+                }
+            }
+        }
+
+        if let Some((thresholds, approver)) = self.approval.as_ref() {
+            let needed = thresholds.is_empty()
+                || sums
+                    .iter()
+                    .any(|(a, s)| thresholds.get(a).map_or(false, |t| s > t));
+            if needed {
+                let proposal = SpendProposal {
+                    wallet: *wallet,
+                    outputs: outputs
+                        .iter()
+                        .map(|(pk, a, am)| (*pk, AssetTypeCode { val: *a }, *am))
+                        .collect(),
+                };
+                if !approver(&proposal) {
+                    return Err(refused("not approved".to_owned()));
+                }
+            }
+        }
+
+        if !max_period.is_zero() {
+            spent.extend(sums.into_iter().map(|(a, s)| (now, a, s)));
+        }
+        Ok(())
+    }
+}
+
+#[inline(always)]
+fn asset_of(code: Option<AssetTypeCode>) -> ZeiAssetType {
+    code.map(|c| c.val).unwrap_or(ASSET_TYPE_FRA)
+}
+
+#[inline(always)]
+fn refused(reason: String) -> Error {
+    Error::PolicyViolation(reason)
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*, rand_chacha::ChaChaRng, rand_core::SeedableRng, std::thread,
+        zei::xfr::sig::XfrKeyPair,
+    };
+
+    fn keys(n: usize) -> Vec<XfrPublicKey> {
+        let mut prng = ChaChaRng::from_seed([0; 32]);
+        (0..n)
+            .map(|_| XfrKeyPair::generate(&mut prng).get_pk())
+            .collect()
+    }
+
+    #[test]
+    fn policy_limits_per_asset() {
+        let pks = keys(2);
+        let (wallet, bob) = (pks[0], pks[1]);
+        let other = AssetTypeCode::gen_random();
+        let policy = WalletPolicy::new().limit(
+            None,
+            SpendingLimit {
+                per_txn: Some(100),
+                per_period: None,
+            },
+        );
+
+        assert!(matches!(
+            policy.check(&wallet, &[(bob, ASSET_TYPE_FRA, 101)]),
+            Err(Error::PolicyViolation(_))
+        ));
+        // summed up by transaction
+        assert!(policy
+            .check(
+                &wallet,
+                &[(bob, ASSET_TYPE_FRA, 60), (bob, ASSET_TYPE_FRA, 60)]
+            )
+            .is_err());
+        policy
+            .check(&wallet, &[(bob, ASSET_TYPE_FRA, 100)])
+            .unwrap();
+        // the changes are not counted, nor the other assets
+        policy
+            .check(
+                &wallet,
+                &[(bob, ASSET_TYPE_FRA, 100), (wallet, ASSET_TYPE_FRA, 1000)],
+            )
+            .unwrap();
+        policy.check(&wallet, &[(bob, other.val, 1000)]).unwrap();
+    }
+
+    #[test]
+    fn policy_limits_per_period() {
+        let pks = keys(2);
+        let (wallet, bob) = (pks[0], pks[1]);
+        let period = Duration::from_millis(300);
+        let policy = WalletPolicy::new().limit(
+            None,
+            SpendingLimit {
+                per_txn: None,
+                per_period: Some((150, period)),
+            },
+        );
+
+        policy
+            .check(&wallet, &[(bob, ASSET_TYPE_FRA, 100)])
+            .unwrap();
+        policy.check(&wallet, &[(bob, ASSET_TYPE_FRA, 50)]).unwrap();
+        assert!(policy.check(&wallet, &[(bob, ASSET_TYPE_FRA, 1)]).is_err());
+
+        // the spends roll out of the period
+        thread::sleep(period + Duration::from_millis(100));
+        policy
+            .check(&wallet, &[(bob, ASSET_TYPE_FRA, 150)])
+            .unwrap();
+        assert!(policy.check(&wallet, &[(bob, ASSET_TYPE_FRA, 1)]).is_err());
+    }
+
+    #[test]
+    fn policy_allowed_receivers() {
+        let pks = keys(3);
+        let (wallet, bob, carol) = (pks[0], pks[1], pks[2]);
+
+        // any receiver if none is set
+        let policy = WalletPolicy::new();
+        policy
+            .check(&wallet, &[(carol, ASSET_TYPE_FRA, 1)])
+            .unwrap();
+
+        let policy = WalletPolicy::new().allow(bob);
+        policy.check(&wallet, &[(bob, ASSET_TYPE_FRA, 1)]).unwrap();
+        assert!(matches!(
+            policy.check(
+                &wallet,
+                &[(bob, ASSET_TYPE_FRA, 1), (carol, ASSET_TYPE_FRA, 1)]
+            ),
+            Err(Error::PolicyViolation(_))
+        ));
+        // the changes go back to the wallet
+        policy
+            .check(&wallet, &[(wallet, ASSET_TYPE_FRA, 1)])
+            .unwrap();
+    }
+}
@@ -0,0 +1,425 @@
+//!
+//! # Wallets
+//!
+//! A `Wallet` signs the transactions of its key pair. The outputs to spend and
+//! the sequence id are fetched by a client, blocking or not, the transactions
+//! are built and signed locally, they are not submitted.
+//!
+//! Only the non-confidential outputs are created, the fees are paid in FRA.
+//!
+//! A `WalletPolicy` set by `with_policy` is checked before signing the
+//! transactions sending to others, a refused one is `Error::PolicyViolation`.
+//!
+
+use {
+    crate::{
+        client::{AsyncClient, Client},
+        error::{Error, ErrorCtx, Result},
+        policy::WalletPolicy,
+    },
+    finutils::txn_builder::{TransactionBuilder, TransferOperationBuilder},
+    globutils::{address, wallet},
+    ledger::data_model::{
+        AssetRules, AssetTypeCode, Operation, Transaction, TransferType, TxOutput,
+        TxoRef, TxoSID, Utxo, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    std::collections::{BTreeMap, HashSet},
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{
+                build_blind_asset_record, open_blind_asset_record, AssetRecordType,
+            },
+            sig::{XfrKeyPair, XfrPublicKey},
+            structs::{AssetRecordTemplate, AssetType as ZeiAssetType, OwnerMemo},
+        },
+    },
+};
+
+const NON_CONFIDENTIAL: AssetRecordType =
+    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType;
+
+/// A key pair, and the transactions it signs.
+pub struct Wallet {
+    kp: XfrKeyPair,
+    policy: Option<WalletPolicy>,
+}
+
+// what a transaction is built from
+struct Inputs {
+    seq_id: u64,
+    utxos: Vec<(TxoSID, Utxo, Option<OwnerMemo>)>,
+    locked: HashSet<TxoSID>,
+}
+
+impl Wallet {
+    /// A new wallet, along with its mnemonic.
+    pub fn generate() -> Result<(Self, String)> {
+        let mnemonic = wallet::generate_mnemonic_default();
+        Self::from_key(&mnemonic).map(|w| (w, mnemonic))
+    }
+
+    /// A wallet restored from a mnemonic or a base64 secret key.
+    pub fn from_key(key: &str) -> Result<Self> {
+        address::parse_keypair(key)
+            .or_input()
+            .map(Self::from_keypair)
+    }
+
+    #[allow(missing_docs)]
+    pub fn from_keypair(kp: XfrKeyPair) -> Self {
+        Wallet { kp, policy: None }
+    }
+
+    /// Check `policy` before signing the transactions sending to others.
+    pub fn with_policy(mut self, policy: WalletPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    #[allow(missing_docs)]
+    pub fn policy(&self) -> Option<&WalletPolicy> {
+        self.policy.as_ref()
+    }
+
+    #[allow(missing_docs)]
+    pub fn keypair(&self) -> &XfrKeyPair {
+        &self.kp
+    }
+
+    #[allow(missing_docs)]
+    pub fn public_key(&self) -> XfrPublicKey {
+        self.kp.get_pk()
+    }
+
+    /// The bech32 address of the wallet.
+    pub fn address(&self) -> String {
+        wallet::public_key_to_bech32(self.kp.get_pk_ref())
+    }
+
+    /// The balance of an asset, FRA if `code` is `None`,
+    /// the outputs locked by vesting schedules included.
+    pub fn balance(&self, client: &Client, code: Option<AssetTypeCode>) -> Result<u64> {
+        client
+            .owned_utxos(self.kp.get_pk_ref())
+            .map(|utxos| self.sum(&utxos, code))
+    }
+
+    /// See `balance`.
+    pub async fn balance_async(
+        &self,
+        client: &AsyncClient,
+        code: Option<AssetTypeCode>,
+    ) -> Result<u64> {
+        client
+            .owned_utxos(self.kp.get_pk_ref())
+            .await
+            .map(|utxos| self.sum(&utxos, code))
+    }
+
+    /// Transfer an asset, FRA if `code` is `None`, to `receiver`.
+    pub fn transfer(
+        &self,
+        client: &Client,
+        receiver: &XfrPublicKey,
+        amount: u64,
+        code: Option<AssetTypeCode>,
+    ) -> Result<Transaction> {
+        self.transfer_batch(client, &[(*receiver, amount)], code)
+    }
+
+    /// Transfer an asset, FRA if `code` is `None`, to several receivers in one operation.
+    pub fn transfer_batch(
+        &self,
+        client: &Client,
+        receivers: &[(XfrPublicKey, u64)],
+        code: Option<AssetTypeCode>,
+    ) -> Result<Transaction> {
+        self.inputs(client)
+            .and_then(|inputs| self.build_transfer(&inputs, receivers, code))
+    }
+
+    /// See `transfer_batch`.
+    pub async fn transfer_batch_async(
+        &self,
+        client: &AsyncClient,
+        receivers: &[(XfrPublicKey, u64)],
+        code: Option<AssetTypeCode>,
+    ) -> Result<Transaction> {
+        let inputs = self.inputs_async(client).await?;
+        self.build_transfer(&inputs, receivers, code)
+    }
+
+    /// Define an asset, with a random code if `code` is `None`.
+    pub fn define_asset(
+        &self,
+        client: &Client,
+        code: Option<AssetTypeCode>,
+        rules: AssetRules,
+        memo: &str,
+    ) -> Result<(AssetTypeCode, Transaction)> {
+        self.inputs(client)
+            .and_then(|inputs| self.build_definition(&inputs, code, rules, memo))
+    }
+
+    /// See `define_asset`.
+    pub async fn define_asset_async(
+        &self,
+        client: &AsyncClient,
+        code: Option<AssetTypeCode>,
+        rules: AssetRules,
+        memo: &str,
+    ) -> Result<(AssetTypeCode, Transaction)> {
+        let inputs = self.inputs_async(client).await?;
+        self.build_definition(&inputs, code, rules, memo)
+    }
+
+    /// Issue `amount` of an asset defined by the wallet, and transfer it
+    /// to the receivers in the same transaction, the rest is kept by the wallet.
+    pub fn issue_and_transfer_asset(
+        &self,
+        client: &Client,
+        code: &AssetTypeCode,
+        amount: u64,
+        receivers: &[(XfrPublicKey, u64)],
+    ) -> Result<Transaction> {
+        self.inputs(client)
+            .and_then(|inputs| self.build_issuance(&inputs, code, amount, receivers))
+    }
+
+    /// See `issue_and_transfer_asset`.
+    pub async fn issue_and_transfer_asset_async(
+        &self,
+        client: &AsyncClient,
+        code: &AssetTypeCode,
+        amount: u64,
+        receivers: &[(XfrPublicKey, u64)],
+    ) -> Result<Transaction> {
+        let inputs = self.inputs_async(client).await?;
+        self.build_issuance(&inputs, code, amount, receivers)
+    }
+
+    fn inputs(&self, client: &Client) -> Result<Inputs> {
+        Ok(Inputs {
+            seq_id: client.seq_id()?,
+            utxos: client.owned_utxos(self.kp.get_pk_ref())?,
+            locked: client.locked_utxos(self.kp.get_pk_ref())?,
+        })
+    }
+
+    async fn inputs_async(&self, client: &AsyncClient) -> Result<Inputs> {
+        Ok(Inputs {
+            seq_id: client.seq_id().await?,
+            utxos: client.owned_utxos(self.kp.get_pk_ref()).await?,
+            locked: client.locked_utxos(self.kp.get_pk_ref()).await?,
+        })
+    }
+
+    fn sum(
+        &self,
+        utxos: &[(TxoSID, Utxo, Option<OwnerMemo>)],
+        code: Option<AssetTypeCode>,
+    ) -> u64 {
+        let asset = code.map(|c| c.val).unwrap_or(ASSET_TYPE_FRA);
+        utxos
+            .iter()
+            .filter_map(|(_, utxo, memo)| {
+                open_blind_asset_record(&utxo.0.record, memo, &self.kp).ok()
+            })
+            .filter(|oar| oar.asset_type == asset)
+            .fold(0u64, |acc, oar| acc.saturating_add(oar.amount))
+    }
+
+    fn build_transfer(
+        &self,
+        inputs: &Inputs,
+        receivers: &[(XfrPublicKey, u64)],
+        code: Option<AssetTypeCode>,
+    ) -> Result<Transaction> {
+        let asset = code.map(|c| c.val).unwrap_or(ASSET_TYPE_FRA);
+        let outputs = receivers
+            .iter()
+            .map(|(pk, am)| (*pk, asset, *am))
+            .collect::<Vec<_>>();
+        let op = self.gen_transfer_op(inputs, &outputs)?;
+        Ok(self.finish(TransactionBuilder::from_seq_id(inputs.seq_id), vec![op]))
+    }
+
+    fn build_definition(
+        &self,
+        inputs: &Inputs,
+        code: Option<AssetTypeCode>,
+        rules: AssetRules,
+        memo: &str,
+    ) -> Result<(AssetTypeCode, Transaction)> {
+        let code = code.unwrap_or_else(AssetTypeCode::gen_random);
+        let mut builder = TransactionBuilder::from_seq_id(inputs.seq_id);
+        builder
+            .add_operation_create_asset(&self.kp, Some(code), rules, memo)
+            .or_build()?;
+        let fee = self.gen_transfer_op(inputs, &[])?;
+        Ok((code, self.finish(builder, vec![fee])))
+    }
+
+    fn build_issuance(
+        &self,
+        inputs: &Inputs,
+        code: &AssetTypeCode,
+        amount: u64,
+        receivers: &[(XfrPublicKey, u64)],
+    ) -> Result<Transaction> {
+        let receivers = receivers
+            .iter()
+            .map(|(pk, am)| (*pk, code.val, *am))
+            .collect::<Vec<_>>();
+        self.check_policy(&receivers)?;
+
+        let mut builder = TransactionBuilder::from_seq_id(inputs.seq_id);
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            amount,
+            code.val,
+            NON_CONFIDENTIAL,
+            self.kp.get_pk(),
+        );
+        let (record, _, _) = build_blind_asset_record(
+            &mut ChaChaRng::from_entropy(),
+            &PublicParams::default().pc_gens,
+            &template,
+            vec![],
+        );
+        let oar = open_blind_asset_record(&record, &None, &self.kp).or_build()?;
+        let output = TxOutput {
+            id: None,
+            record,
+            lien: None,
+            memo: None,
+        };
+        let seq_num = builder.get_seq_id();
+        builder
+            .add_operation_issue_asset(&self.kp, code, seq_num, &[(output, None)])
+            .or_build()?;
+
+        let mut ops = vec![];
+        if !receivers.is_empty() {
+            // the issued output is the last one of the transaction
+            let mut trans_builder = TransferOperationBuilder::new();
+            trans_builder
+                .add_input(TxoRef::Relative(0), oar, None, None, amount)
+                .or_build()?;
+            self.add_outputs(&mut trans_builder, &receivers)?;
+            ops.push(self.sign_transfer(&mut trans_builder)?);
+        }
+        ops.push(self.gen_transfer_op(inputs, &[])?);
+
+        Ok(self.finish(builder, ops))
+    }
+
+    // spend the unlocked outputs of the wallet to `(receiver, asset, amount)`,
+    // and pay the fee, the changes go back to the wallet
+    fn gen_transfer_op(
+        &self,
+        inputs: &Inputs,
+        outputs: &[(XfrPublicKey, ZeiAssetType, u64)],
+    ) -> Result<Operation> {
+        self.check_policy(outputs)?;
+
+        let mut outputs = outputs.to_vec();
+        outputs.push((*BLACK_HOLE_PUBKEY, ASSET_TYPE_FRA, TX_FEE_MIN));
+
+        let mut needs = BTreeMap::new();
+        for (_, asset, am) in outputs.iter() {
+            let n = needs.entry(*asset).or_insert(0u64);
+            *n = n
+                .checked_add(*am)
+                .ok_or_else(|| Error::InvalidInput("amount overflow".to_owned()))?;
+        }
+        let mut missing = needs.clone();
+
+        let mut trans_builder = TransferOperationBuilder::new();
+        for (sid, utxo, memo) in inputs.utxos.iter() {
+            if inputs.locked.contains(sid) {
+                continue;
+            }
+            let oar = match open_blind_asset_record(&utxo.0.record, memo, &self.kp) {
+                Ok(oar) => oar,
+                Err(_) => continue,
+            };
+            let n = match missing.get_mut(&oar.asset_type) {
+                Some(n) if 0 < *n => n,
+                _ => continue,
+            };
+            let am = oar.amount.min(*n);
+            *n -= am;
+            trans_builder
+                .add_input(TxoRef::Absolute(*sid), oar, None, None, am)
+                .or_build()?;
+        }
+        if let Some((asset, n)) = missing.iter().find(|(_, n)| 0 < **n) {
+            let needed = needs[asset];
+            return Err(Error::InsufficientBalance {
+                asset: AssetTypeCode { val: *asset }.to_base64(),
+                needed,
+                available: needed - n,
+            });
+        }
+
+        self.add_outputs(&mut trans_builder, &outputs)?;
+        self.sign_transfer(&mut trans_builder)
+    }
+
+    fn check_policy(&self, outputs: &[(XfrPublicKey, ZeiAssetType, u64)]) -> Result<()> {
+        match self.policy.as_ref() {
+            Some(p) => p.check(self.kp.get_pk_ref(), outputs),
+            None => Ok(()),
+        }
+    }
+
+    fn add_outputs(
+        &self,
+        trans_builder: &mut TransferOperationBuilder,
+        outputs: &[(XfrPublicKey, ZeiAssetType, u64)],
+    ) -> Result<()> {
+        for (pk, asset, am) in outputs.iter() {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                *am,
+                *asset,
+                NON_CONFIDENTIAL,
+                *pk,
+            );
+            trans_builder
+                .add_output(&template, None, None, None)
+                .or_build()?;
+        }
+        Ok(())
+    }
+
+    fn sign_transfer(
+        &self,
+        trans_builder: &mut TransferOperationBuilder,
+    ) -> Result<Operation> {
+        trans_builder
+            .balance(None)
+            .or_build()?
+            .create(TransferType::Standard)
+            .or_build()?
+            .sign(&self.kp)
+            .or_build()?
+            .transaction()
+            .or_build()
+    }
+
+    fn finish(
+        &self,
+        mut builder: TransactionBuilder,
+        ops: Vec<Operation>,
+    ) -> Transaction {
+        for op in ops {
+            builder.add_operation(op);
+        }
+        let mut tx = builder.take_transaction();
+        tx.sign_to_map(&self.kp);
+        tx
+    }
+}
@@ -29,10 +29,13 @@ use {
         CredUserPublicKey, CredUserSecretKey, Credential as PlatformCredential,
     },
     cryptohash::sha256,
-    finutils::txn_builder::{
-        FeeInput as PlatformFeeInput, FeeInputs as PlatformFeeInputs,
-        TransactionBuilder as PlatformTransactionBuilder,
-        TransferOperationBuilder as PlatformTransferOperationBuilder,
+    finutils::{
+        api::RelayedMemo,
+        txn_builder::{
+            FeeInput as PlatformFeeInput, FeeInputs as PlatformFeeInputs,
+            TransactionBuilder as PlatformTransactionBuilder,
+            TransferOperationBuilder as PlatformTransferOperationBuilder,
+        },
     },
     fp_types::{
         actions::xhub::{
@@ -44,12 +47,13 @@ use {
         U256,
     },
     fp_utils::{ecdsa::SecpPair, tx::EvmRawTxWrapper},
-    globutils::{wallet, HashOf},
+    globutils::{wallet, HashOf, SignatureOf},
     ledger::{
         data_model::{
-            gen_random_keypair, AssetTypeCode, AuthenticatedTransaction, Operation,
-            TransferType, TxOutput, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
-            BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
+            gen_random_keypair, AssetTypeCode, AuthenticatedRevocationRegistry,
+            AuthenticatedTransaction, AuthenticatedUtxoStatus, Operation,
+            StateCommitmentData, TransferType, TxOutput, TxoSID, ASSET_TYPE_FRA,
+            BLACK_HOLE_PUBKEY, BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
         },
         staking::{
             td_addr_to_bytes, PartialUnDelegation, TendermintAddr,
@@ -136,6 +140,104 @@ pub fn verify_authenticated_txn(
     Ok(authenticated_txn.is_valid(state_commitment))
 }
 
+#[wasm_bindgen]
+/// Given a serialized state commitment and the status of a UTXO, returns true if the status
+/// correctly hashes up to the state commitment and false otherwise.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} authenticated_status - String representing the status of the UTXO.
+/// @throws Will throw an error if the state commitment or the status fails to deserialize.
+pub fn verify_authenticated_utxo_status(
+    state_commitment: String,
+    authenticated_status: String,
+) -> Result<bool, JsValue> {
+    let authenticated_status =
+        serde_json::from_str::<AuthenticatedUtxoStatus>(&authenticated_status)
+            .c(d!())
+            .map_err(|e| {
+                JsValue::from_str(&format!("Could not deserialize utxo status: {}", e))
+            })?;
+    let state_commitment = state_commitment_from_str(&state_commitment)?;
+    Ok(authenticated_status.is_valid(state_commitment))
+}
+
+#[wasm_bindgen]
+/// Given a serialized state commitment and the revocation registry of a credential issuer,
+/// returns true if the registry correctly hashes up to the state commitment and false otherwise.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} authenticated_registry - String representing the registry.
+/// @throws Will throw an error if the state commitment or the registry fails to deserialize.
+pub fn verify_authenticated_credential_registry(
+    state_commitment: String,
+    authenticated_registry: String,
+) -> Result<bool, JsValue> {
+    let authenticated_registry =
+        serde_json::from_str::<AuthenticatedRevocationRegistry>(&authenticated_registry)
+            .c(d!())
+            .map_err(|e| {
+                JsValue::from_str(&format!("Could not deserialize registry: {}", e))
+            })?;
+    let state_commitment = state_commitment_from_str(&state_commitment)?;
+    Ok(authenticated_registry.is_valid(state_commitment))
+}
+
+#[wasm_bindgen]
+/// Given a serialized state commitment and state commitment data, returns true if the data
+/// hashes to the state commitment and false otherwise, so that the merkle roots and counters
+/// in the data can be trusted.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} state_commitment_data - String representing the state commitment data.
+/// @throws Will throw an error if the state commitment or the data fails to deserialize.
+pub fn verify_state_commitment_data(
+    state_commitment: String,
+    state_commitment_data: String,
+) -> Result<bool, JsValue> {
+    let data = serde_json::from_str::<StateCommitmentData>(&state_commitment_data)
+        .c(d!())
+        .map_err(|e| {
+            JsValue::from_str(&format!(
+                "Could not deserialize state commitment data: {}",
+                e
+            ))
+        })?;
+    let state_commitment = state_commitment_from_str(&state_commitment)?;
+    Ok(data.compute_commitment() == state_commitment)
+}
+
+#[wasm_bindgen]
+/// Returns true if the state commitment of a block has been attested by an auditor,
+/// whose attestations are signatures of `(block_sid, state_commitment)`.
+/// @param {XfrPublicKey} auditor - Public key of a trusted auditor.
+/// @param {BigInt} block_sid - Index of the block, starts from 0.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} signature - String representing the signature of the auditor.
+/// @throws Will throw an error if the state commitment or the signature fails to deserialize.
+pub fn verify_state_commitment_attestation(
+    auditor: &XfrPublicKey,
+    block_sid: u64,
+    state_commitment: String,
+    signature: String,
+) -> Result<bool, JsValue> {
+    let signature = serde_json::from_str::<
+        SignatureOf<(usize, HashOf<Option<StateCommitmentData>>)>,
+    >(&signature)
+    .c(d!())
+    .map_err(|e| {
+        JsValue::from_str(&format!("Could not deserialize signature: {}", e))
+    })?;
+    let state_commitment = state_commitment_from_str(&state_commitment)?;
+    Ok(signature
+        .verify(auditor, &(block_sid as usize, state_commitment))
+        .is_ok())
+}
+
+fn state_commitment_from_str(
+    state_commitment: &str,
+) -> Result<HashOf<Option<StateCommitmentData>>, JsValue> {
+    serde_json::from_str(state_commitment).c(d!()).map_err(|e| {
+        JsValue::from_str(&format!("Could not deserialize state commitment: {}", e))
+    })
+}
+
 #[wasm_bindgen]
 /// ...
 pub fn get_null_pk() -> XfrPublicKey {
@@ -265,6 +367,7 @@ impl TransactionBuilder {
                             id: None,
                             record: new.0,
                             lien: None,
+                            memo: None,
                         },
                     }
                     .to_json()
@@ -526,6 +629,14 @@ impl TransactionBuilder {
         Ok(self)
     }
 
+    /// Rejects the transaction if it is not included at or before a block height,
+    /// must be called before signing.
+    /// @param {BigInt} height - The last block height to include the transaction at.
+    pub fn set_expiry_height(mut self, height: u64) -> TransactionBuilder {
+        self.get_builder_mut().set_expiry_height(height);
+        self
+    }
+
     #[allow(missing_docs)]
     pub fn sign(mut self, kp: &XfrKeyPair) -> Result<TransactionBuilder, JsValue> {
         self.get_builder_mut().sign_to_map(kp);
@@ -674,6 +785,7 @@ impl TransferOperationBuilder {
         asset_record: &ClientAssetRecord,
         owner_memo: Option<OwnerMemo>,
         tracing_policies: Option<&TracingPolicies>,
+        identity_commitment: Option<&CredentialCommitment>,
         key: &XfrKeyPair,
         amount: u64,
     ) -> Result<TransferOperationBuilder, JsValue> {
@@ -691,7 +803,7 @@ impl TransferOperationBuilder {
                 *txo_ref.get_txo(),
                 oar,
                 tracing_policies.map(|policies| policies.get_policies_ref().clone()),
-                None,
+                identity_commitment.map(|c| c.get_ref().clone()),
                 amount,
             )
             .c(d!())
@@ -700,6 +812,7 @@ impl TransferOperationBuilder {
     }
 
     #[allow(missing_docs)]
+    #[allow(clippy::too_many_arguments)]
     pub fn add_output(
         mut self,
         amount: u64,
@@ -708,13 +821,13 @@ impl TransferOperationBuilder {
         code: String,
         conf_amount: bool,
         conf_type: bool,
+        identity: Option<(&CredUserSecretKey, &Credential, &CredentialCommitmentData)>,
     ) -> Result<TransferOperationBuilder, JsValue> {
         let code = AssetTypeCode::new_from_base64(&code)
             .c(d!())
             .map_err(error_to_jsvalue)?;
 
         let asset_record_type = AssetRecordType::from_flags(conf_amount, conf_type);
-        // TODO (noah/keyao) support identity tracing (issue #298)
         let template = if let Some(policies) = tracing_policies {
             AssetRecordTemplate::with_asset_tracing(
                 amount,
@@ -731,12 +844,21 @@ impl TransferOperationBuilder {
                 *recipient,
             )
         };
+        let ac_credential = identity
+            .map(|(_, credential, _)| credential.get_cred_ref().to_ac_credential())
+            .transpose()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
         self.get_builder_mut()
             .add_output(
                 &template,
                 tracing_policies.map(|policies| policies.get_policies_ref().clone()),
-                None,
-                None,
+                identity.map(|(_, _, data)| data.commitment.get_ref().clone()),
+                identity.zip(ac_credential.as_ref()).map(
+                    |((user_sk, _, data), credential)| {
+                        (user_sk, credential, data.commitment_key.get_ref())
+                    },
+                ),
             )
             .c(d!())
             .map_err(error_to_jsvalue)?;
@@ -779,6 +901,39 @@ impl TransferOperationBuilder {
             &asset_record,
             owner_memo,
             Some(tracing_policies),
+            None,
+            key,
+            amount,
+        )
+    }
+
+    /// Wraps around TransferOperationBuilder to add an input bound to a credential commitment.
+    /// @param {TxoRef} txo_ref - Absolute or relative utxo reference
+    /// @param {ClientAssetRecord} asset_record - Client asset record to serve as transfer input.
+    /// @param {OwnerMemo} owner_memo - Opening parameters.
+    /// @param {TracingPolicies} tracing_policies - Tracing policies of the asset.
+    /// @param {CredentialCommitment} identity_commitment - Commitment to the credential of the owner.
+    /// @param {XfrKeyPair} key - Key pair associated with the input.
+    /// @param {BigInt} amount - Amount of input record to transfer.
+    /// @see {@link module:Findora-Wasm.wasm_credential_commit|wasm_credential_commit} for details on credential commitments.
+    /// @throws Will throw an error if the record can not be opened by `key`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_input_with_identity_commitment(
+        self,
+        txo_ref: TxoRef,
+        asset_record: &ClientAssetRecord,
+        owner_memo: Option<OwnerMemo>,
+        tracing_policies: &TracingPolicies,
+        identity_commitment: &CredentialCommitment,
+        key: &XfrKeyPair,
+        amount: u64,
+    ) -> Result<TransferOperationBuilder, JsValue> {
+        self.add_input(
+            txo_ref,
+            asset_record,
+            owner_memo,
+            Some(tracing_policies),
+            Some(identity_commitment),
             key,
             amount,
         )
@@ -804,7 +959,7 @@ impl TransferOperationBuilder {
         key: &XfrKeyPair,
         amount: u64,
     ) -> Result<TransferOperationBuilder, JsValue> {
-        self.add_input(txo_ref, asset_record, owner_memo, None, key, amount)
+        self.add_input(txo_ref, asset_record, owner_memo, None, None, key, amount)
     }
 
     /// Wraps around TransferOperationBuilder to add an output to a transfer operation builder.
@@ -833,6 +988,45 @@ impl TransferOperationBuilder {
             code,
             conf_amount,
             conf_type,
+            None,
+        )
+    }
+
+    /// Wraps around TransferOperationBuilder to add an output whose owner identity is traced,
+    /// the attributes of the credential are encrypted to the identity tracing policy of the asset.
+    ///
+    /// @param {BigInt} amount - amount to transfer to the recipient.
+    /// @param {XfrPublicKey} recipient - public key of the recipient.
+    /// @param {TracingPolicies} tracing_policies - tracing policies of the asset, at most one of them traces identities.
+    /// @param code {string} - String representation of the asset token code.
+    /// @param conf_amount {boolean} - `true` means the output's asset amount is confidential, and `false` means it's nonconfidential.
+    /// @param conf_type {boolean} - `true` means the output's asset type is confidential, and `false` means it's nonconfidential.
+    /// @param {CredUserSecretKey} user_sk - Secret key of the credential user, the recipient.
+    /// @param {Credential} credential - Credential of the recipient.
+    /// @param {CredentialCommitmentData} commitment_data - Commitment to `credential` for `recipient`.
+    /// @see {@link module:Findora-Wasm.wasm_credential_commit|wasm_credential_commit} for details on credential commitments.
+    /// @throws Will throw an error if `code` fails to deserialize, or the credential does not match the policies.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_output_with_identity_tracing(
+        self,
+        amount: u64,
+        recipient: &XfrPublicKey,
+        tracing_policies: &TracingPolicies,
+        code: String,
+        conf_amount: bool,
+        conf_type: bool,
+        user_sk: &CredUserSecretKey,
+        credential: &Credential,
+        commitment_data: &CredentialCommitmentData,
+    ) -> Result<TransferOperationBuilder, JsValue> {
+        self.add_output(
+            amount,
+            recipient,
+            Some(tracing_policies),
+            code,
+            conf_amount,
+            conf_type,
+            Some((user_sk, credential, commitment_data)),
         )
     }
 
@@ -852,7 +1046,59 @@ impl TransferOperationBuilder {
         conf_amount: bool,
         conf_type: bool,
     ) -> Result<TransferOperationBuilder, JsValue> {
-        self.add_output(amount, recipient, None, code, conf_amount, conf_type)
+        self.add_output(amount, recipient, None, code, conf_amount, conf_type, None)
+    }
+
+    /// Wraps around TransferOperationBuilder to reveal some attributes of a credential
+    /// for an added output, the credential must belong to the owner of the output.
+    ///
+    /// @param {number} output_idx - Index of the output, in the order they were added.
+    /// @param {CredUserSecretKey} user_sk - Secret key of the credential user.
+    /// @param {Credential} credential - Credential of the owner of the output.
+    /// @param {CredentialCommitmentData} commitment_data - Commitment to `credential`.
+    /// @param {JsValue} reveal_fields - Array of names of the attributes to reveal, i.e. `["country"]`.
+    /// @throws Will throw an error if the output does not exist or has been disclosed.
+    pub fn disclose_identity(
+        mut self,
+        output_idx: usize,
+        user_sk: &CredUserSecretKey,
+        credential: &Credential,
+        commitment_data: &CredentialCommitmentData,
+        reveal_fields: JsValue,
+    ) -> Result<TransferOperationBuilder, JsValue> {
+        let reveal_fields: Vec<String> = reveal_fields
+            .into_serde()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        self.get_builder_mut()
+            .disclose_identity(
+                output_idx,
+                user_sk,
+                credential.get_cred_ref(),
+                commitment_data.commitment_key.get_ref(),
+                &reveal_fields,
+            )
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// Wraps around TransferOperationBuilder to attach a memo to an added output,
+    /// the memo is encrypted to the owner of the output.
+    ///
+    /// @param {number} output_idx - Index of the output, in the order they were added.
+    /// @param {string} memo - Message for the owner, eg. a payment reference.
+    /// @throws Will throw an error if the output does not exist, or the memo is too long.
+    pub fn attach_memo(
+        mut self,
+        output_idx: usize,
+        memo: String,
+    ) -> Result<TransferOperationBuilder, JsValue> {
+        self.get_builder_mut()
+            .attach_memo(output_idx, memo.as_bytes())
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
     }
 
     /// Wraps around TransferOperationBuilder to ensure the transfer inputs and outputs are balanced.
@@ -892,11 +1138,66 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
-    #[allow(missing_docs)]
+    /// Wraps around TransferOperationBuilder to create a signature of an input owner,
+    /// so that owners can sign on different devices.
+    ///
+    /// @param {XfrKeyPair} kp - key pair of one of the input owners.
+    /// @throws Will throw an error if the transfer has not been created.
+    pub fn create_input_signature(&self, kp: &XfrKeyPair) -> Result<String, JsValue> {
+        self.get_builder()
+            .create_input_signature(kp)
+            .c(d!())
+            .and_then(|sig| serde_json::to_string(&sig).c(d!()))
+            .map_err(error_to_jsvalue)
+    }
+
+    /// Wraps around TransferOperationBuilder to attach a signature created by `create_input_signature`.
+    ///
+    /// @param {string} sig - JSON-encoded signature.
+    /// @throws Will throw an error if the transfer has not been created or the signature is invalid.
+    pub fn attach_signature(
+        mut self,
+        sig: String,
+    ) -> Result<TransferOperationBuilder, JsValue> {
+        let sig = serde_json::from_str(&sig)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        self.get_builder_mut()
+            .attach_signature(sig)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// Wraps around TransferOperationBuilder to check that all input owners have signed.
+    ///
+    /// @throws Will throw an error if any signature is missing or invalid.
+    pub fn validate_signatures(mut self) -> Result<TransferOperationBuilder, JsValue> {
+        self.get_builder_mut()
+            .validate_signatures()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(self)
+    }
+
+    /// Returns the JSON-encoded state of the builder.
+    /// @see {@link module:Findora-Wasm~TransferOperationBuilder#from_builder|TransferOperationBuilder.from_builder}
+    /// to restore it.
     pub fn builder(&self) -> String {
         serde_json::to_string(self.get_builder()).unwrap()
     }
 
+    /// Restore a builder from the JSON-encoded state returned by `builder`.
+    ///
+    /// @param {string} builder - JSON-encoded state of a builder.
+    /// @throws Will throw an error if `builder` fails to deserialize.
+    pub fn from_builder(builder: String) -> Result<TransferOperationBuilder, JsValue> {
+        serde_json::from_str(&builder)
+            .c(d!())
+            .map_err(error_to_jsvalue)
+            .map(|op_builder| TransferOperationBuilder { op_builder })
+    }
+
     /// Wraps around TransferOperationBuilder to extract an operation expression as JSON.
     pub fn transaction(&self) -> Result<String, JsValue> {
         let op = self
@@ -933,6 +1234,41 @@ pub fn open_client_asset_record(
     .and_then(|oa| JsValue::from_serde(&oa).c(d!()).map_err(error_to_jsvalue))
 }
 
+#[wasm_bindgen]
+/// Returns the key under which the owner memos of `recipient` are relayed,
+/// the memos are posted to and fetched from `memo_relay/{key}` of the query server.
+pub fn memo_relay_key(recipient: &XfrPublicKey) -> String {
+    finutils::api::memo_relay_key(recipient)
+}
+
+#[wasm_bindgen]
+/// Returns the JSON body to post to the memo relay,
+/// delivering the owner memo of the output `txo_sid` to its owner.
+pub fn memo_relay_body(txo_sid: u64, owner_memo: OwnerMemo) -> Result<String, JsValue> {
+    serde_json::to_string(&RelayedMemo {
+        txo_sid: TxoSID(txo_sid),
+        memo: owner_memo.memo,
+    })
+    .c(d!())
+    .map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Finds the owner memo of the output `txo_sid` in the JSON response of the memo relay,
+/// `undefined` if it has not been relayed.
+pub fn memo_relay_find(
+    response: &str,
+    txo_sid: u64,
+) -> Result<Option<OwnerMemo>, JsValue> {
+    let memos: Vec<RelayedMemo> = serde_json::from_str(response)
+        .c(d!())
+        .map_err(error_to_jsvalue)?;
+    Ok(memos
+        .into_iter()
+        .find(|m| m.txo_sid == TxoSID(txo_sid))
+        .map(|m| OwnerMemo { memo: m.memo }))
+}
+
 #[wasm_bindgen]
 /// Extracts the public key as a string from a transfer key pair.
 pub fn get_pub_key_str(key_pair: &XfrKeyPair) -> String {
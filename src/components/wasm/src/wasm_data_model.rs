@@ -8,8 +8,8 @@ use {
     globutils::{wallet, HashOf},
     ledger::data_model::{
         AssetRules as PlatformAssetRules, AssetType as PlatformAssetType,
-        AuthenticatedUtxo, SignatureRules as PlatformSignatureRules, TxOutput,
-        TxoRef as PlatformTxoRef, TxoSID,
+        AuthenticatedUtxo, PendingTxoRef, SignatureRules as PlatformSignatureRules,
+        TxOutput, TxoRef as PlatformTxoRef, TxoSID,
     },
     rand_chacha::ChaChaRng,
     rand_core::SeedableRng,
@@ -93,6 +93,22 @@ impl TxoRef {
             txo_ref: PlatformTxoRef::Absolute(TxoSID(idx)),
         }
     }
+
+    /// Creates a reference to an output of a transaction which may not be committed yet.
+    ///
+    /// The submission server holds the transaction spending it until the parent is committed.
+    ///
+    /// # Arguments
+    /// @param {string} handle - Handle of the parent transaction.
+    /// @param {BigInt} idx - Index of the output among the ones recorded on the ledger.
+    pub fn pending(handle: &str, idx: u64) -> Result<TxoRef, JsValue> {
+        PendingTxoRef::from_handle(handle, idx)
+            .c(d!())
+            .map_err(error_to_jsvalue)
+            .map(|r| TxoRef {
+                txo_ref: PlatformTxoRef::Pending(r),
+            })
+    }
 }
 
 impl TxoRef {
@@ -667,6 +683,14 @@ impl AssetRules {
         self
     }
 
+    /// Only trace transfer records above an amount, records with a non-confidential amount
+    /// not exceeding the threshold need no tracer memos, confidential amounts are always traced.
+    /// @param {BigInt} threshold - Amount threshold of tracing.
+    pub fn set_tracing_threshold(mut self, threshold: u64) -> AssetRules {
+        self.rules.set_tracing_threshold(Some(threshold));
+        self
+    }
+
     /// Set a cap on the number of units of this asset that can be issued.
     /// @param {BigInt} max_units - Maximum number of units that can be issued.
     pub fn set_max_units(mut self, max_units: u64) -> AssetRules {
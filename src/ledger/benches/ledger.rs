@@ -0,0 +1,256 @@
+//!
+//! # Ledger benchmarks
+//!
+//! - block application: transactions/second of `compute_effect`,
+//!   `apply_transaction` and `finish_block`
+//! - signature verification: transactions/second of checking the signatures of a batch
+//! - merkle proof: generation and verification of authenticated UTXOs
+//! - block commit: blocks/second of one transaction each, written and synced
+//!   at each block, or in batches with the write-ahead log, see `store::batch`
+//!
+//! Run by `cargo bench -p ledger`, the results are written as JSON to
+//! `target/criterion/<group>/<size>/new/estimates.json`,
+//! use `--save-baseline <name>` and `--baseline <name>` to compare two runs.
+//!
+
+use {
+    criterion::{
+        criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+    },
+    ledger::{
+        data_model::{
+            AssetRules, AssetTypeCode, IssueAsset, IssueAssetBody, IssuerKeyPair,
+            Operation, Transaction, TransferAsset, TransferAssetBody, TransferType,
+            TxOutput, TxnEffect, TxoRef, TxoSID,
+        },
+        store::{
+            batch::SyncMode,
+            helpers::{apply_transaction, create_definition_transaction},
+            LedgerState,
+        },
+    },
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{
+                build_blind_asset_record, open_blind_asset_record, AssetRecordType,
+            },
+            sig::XfrKeyPair,
+            structs::{AssetRecord, AssetRecordTemplate},
+        },
+    },
+};
+
+const NON_CONFIDENTIAL: AssetRecordType =
+    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType;
+
+// transactions per block
+const BATCH_SIZES: [usize; 2] = [16, 128];
+
+struct Fixture {
+    ledger: LedgerState,
+    kp: XfrKeyPair,
+    sids: Vec<TxoSID>,
+    txns: Vec<Transaction>,
+}
+
+// A ledger with `n` unspent outputs, and a signed transfer spending each of them.
+fn setup(n: usize) -> Fixture {
+    let mut prng = ChaChaRng::seed_from_u64(0);
+    let params = PublicParams::default();
+    let kp = XfrKeyPair::generate(&mut prng);
+    let mut ledger = LedgerState::tmp_ledger();
+
+    let code = AssetTypeCode::gen_random_with_rng(&mut prng);
+    let tx = create_definition_transaction(
+        &code,
+        &kp,
+        AssetRules::default(),
+        None,
+        ledger.get_block_commit_count(),
+    )
+    .unwrap();
+    apply_transaction(&mut ledger, tx);
+
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        100,
+        code.val,
+        NON_CONFIDENTIAL,
+        kp.get_pk(),
+    );
+    let outputs = (0..n)
+        .map(|_| {
+            let (record, _, _) =
+                build_blind_asset_record(&mut prng, &params.pc_gens, &template, vec![]);
+            let output = TxOutput {
+                id: None,
+                record,
+                lien: None,
+                memo: None,
+            };
+            (output, None)
+        })
+        .collect::<Vec<_>>();
+    let body = IssueAssetBody::new(&code, 0, &outputs).unwrap();
+    let op = IssueAsset::new(body, &IssuerKeyPair { keypair: &kp }).unwrap();
+    let tx = Transaction::from_operation(
+        Operation::IssueAsset(op),
+        ledger.get_block_commit_count(),
+    );
+    let (_, sids) = apply_transaction(&mut ledger, tx);
+
+    let seq_id = ledger.get_block_commit_count();
+    let txns = sids
+        .iter()
+        .map(|sid| {
+            let record = ledger.get_utxo_light(*sid).unwrap().utxo.0.record;
+            let oar = open_blind_asset_record(&record, &None, &kp).unwrap();
+            let input = AssetRecord::from_open_asset_record_no_asset_tracing(oar);
+            let output =
+                AssetRecord::from_template_no_identity_tracing(&mut prng, &template)
+                    .unwrap();
+            let body = TransferAssetBody::new(
+                &mut prng,
+                vec![TxoRef::Absolute(*sid)],
+                &[input],
+                &[output],
+                None,
+                vec![],
+                TransferType::Standard,
+            )
+            .unwrap();
+            let mut op = TransferAsset::new(body).unwrap();
+            op.sign(&kp);
+
+            let mut tx =
+                Transaction::from_operation(Operation::TransferAsset(op), seq_id);
+            tx.sign(&kp);
+            tx
+        })
+        .collect();
+
+    Fixture {
+        ledger,
+        kp,
+        sids,
+        txns,
+    }
+}
+
+fn block_application(c: &mut Criterion) {
+    let mut group = c.benchmark_group("block_application");
+    group.sample_size(10);
+    for n in BATCH_SIZES {
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter_batched(
+                || setup(n),
+                |mut f| {
+                    let mut block = f.ledger.start_block().unwrap();
+                    for tx in f.txns.drain(..) {
+                        let effect = TxnEffect::compute_effect(tx).unwrap();
+                        f.ledger.apply_transaction(&mut block, effect).unwrap();
+                    }
+                    f.ledger.finish_block(block).unwrap();
+                    // dropped out of the measurement
+                    f
+                },
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn block_commit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("block_commit");
+    group.sample_size(10);
+    // within the window of the no-replay tokens
+    let n = BATCH_SIZES[0];
+    for mode in [
+        SyncMode::Always,
+        SyncMode::Batched { blocks: 8 },
+        SyncMode::Off,
+    ] {
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new(mode.to_string(), n), &n, |b, &n| {
+            b.iter_batched(
+                || {
+                    let mut f = setup(n);
+                    f.ledger.set_sync_mode(mode).unwrap();
+                    f
+                },
+                |mut f| {
+                    for tx in f.txns.drain(..) {
+                        let mut block = f.ledger.start_block().unwrap();
+                        let effect = TxnEffect::compute_effect(tx).unwrap();
+                        f.ledger.apply_transaction(&mut block, effect).unwrap();
+                        f.ledger.finish_block(block).unwrap();
+                    }
+                    // the last group commit is part of the measurement
+                    f.ledger.sync().unwrap();
+                    f
+                },
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn signature_verification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("signature_verification");
+    for n in BATCH_SIZES {
+        let f = setup(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &f, |b, f| {
+            b.iter(|| {
+                for tx in f.txns.iter() {
+                    tx.check_has_signature(f.kp.get_pk_ref()).unwrap();
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn merkle_proof(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merkle_proof");
+    for n in BATCH_SIZES {
+        let f = setup(n);
+        let commitment = f.ledger.get_state_commitment().0;
+        let utxos = f
+            .sids
+            .iter()
+            .map(|sid| f.ledger.get_utxo(*sid).unwrap())
+            .collect::<Vec<_>>();
+
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("generate", n), &f, |b, f| {
+            b.iter(|| {
+                for sid in f.sids.iter() {
+                    f.ledger.get_utxo(*sid).unwrap();
+                }
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("verify", n), &utxos, |b, utxos| {
+            b.iter(|| {
+                for utxo in utxos.iter() {
+                    assert!(utxo.is_valid(commitment.clone()));
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    block_application,
+    block_commit,
+    signature_verification,
+    merkle_proof
+);
+criterion_main!(benches);
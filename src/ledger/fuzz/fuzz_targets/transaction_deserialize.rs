@@ -0,0 +1,18 @@
+//!
+//! Transactions are deserialized from untrusted bytes in `CheckTx`,
+//! which must never panic, and must round-trip once accepted.
+//!
+//! Run by `cargo fuzz run transaction_deserialize` in `src/ledger`.
+//!
+
+#![no_main]
+
+use {ledger::data_model::Transaction, libfuzzer_sys::fuzz_target};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tx) = serde_json::from_slice::<Transaction>(data) {
+        let bytes = serde_json::to_vec(&tx).unwrap();
+        let tx2 = serde_json::from_slice::<Transaction>(&bytes).unwrap();
+        assert_eq!(tx, tx2);
+    }
+});
@@ -0,0 +1,19 @@
+//!
+//! Computing the effect of a deserialized transaction checks its signatures
+//! and proofs, which must reject garbage without panicking.
+//!
+//! Run by `cargo fuzz run txn_effect` in `src/ledger`.
+//!
+
+#![no_main]
+
+use {
+    ledger::data_model::{Transaction, TxnEffect},
+    libfuzzer_sys::fuzz_target,
+};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tx) = serde_json::from_slice::<Transaction>(data) {
+        let _ = TxnEffect::compute_effect(tx);
+    }
+});
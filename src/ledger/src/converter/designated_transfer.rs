@@ -0,0 +1,355 @@
+//!
+//! # Designated transfers
+//!
+//! Two-step transfers, protecting large amounts against mistyped addresses:
+//!
+//! - `Send` locks non-confidential UTXOs of the sender in
+//!   `BLACK_HOLE_PUBKEY_STAKING`, by the transfers of the same transaction,
+//!   the recipient must claim them within `claim_period` blocks
+//! - `Claim` releases all of the locked assets to a new UTXO of the recipient
+//! - `Reclaim` releases them back to the sender, once the claim period is over
+//!
+//! Transfers are swept at the end of each block, those whose claim period is over
+//! are marked as expired and can no longer be claimed.
+//!
+
+use {
+    crate::{
+        converter::pull_payment::released_output,
+        data_model::{AssetTypeCode, NoReplayToken, TxOutput},
+    },
+    globutils::{HashOf, SignatureOf},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// The identifier of a designated transfer, the hash of its `SendDesignatedBody` in hex.
+pub type DesignatedId = String;
+
+/// A designated transfer maintained by the ledger
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DesignatedTransfer {
+    #[allow(missing_docs)]
+    pub sender: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub recipient: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    #[allow(missing_docs)]
+    pub amount: u64,
+    /// The last height at which the recipient can claim the assets
+    pub expires_at: u64,
+    /// Set by the sweep, the assets can only be reclaimed by the sender since then
+    pub expired: bool,
+}
+
+impl DesignatedTransfer {
+    /// Whether the claim period is over at `height`
+    #[inline(always)]
+    pub fn is_expired(&self, height: u64) -> bool {
+        self.expired || height > self.expires_at
+    }
+}
+
+/// Operation body for sending a designated transfer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SendDesignatedBody {
+    #[allow(missing_docs)]
+    pub recipient: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    /// Must be equal to the non-confidential amount of the asset
+    /// sent to `BLACK_HOLE_PUBKEY_STAKING` in the same transaction
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    /// Number of blocks after the one including the transfer,
+    /// within which the recipient can claim it
+    pub claim_period: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+impl SendDesignatedBody {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn id(&self) -> DesignatedId {
+        HashOf::new(self).hex()
+    }
+}
+
+/// Operation data for sending a designated transfer, signed by the sender
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SendDesignated {
+    #[allow(missing_docs)]
+    pub body: SendDesignatedBody,
+    /// The sender
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<SendDesignatedBody>,
+}
+
+impl SendDesignated {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: SendDesignatedBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        SendDesignated {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for releasing a designated transfer,
+/// by claiming it as the recipient or reclaiming it as the sender
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ReleaseDesignatedBody {
+    #[allow(missing_docs)]
+    pub id: DesignatedId,
+    /// The asset, must be the transferred one
+    pub asset: AssetTypeCode,
+    /// Must be the transferred amount, which is released at once
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for releasing a designated transfer, signed by the owner of the new UTXO
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ReleaseDesignated {
+    #[allow(missing_docs)]
+    pub body: ReleaseDesignatedBody,
+    /// The recipient of a claim, or the sender of a reclaim
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<ReleaseDesignatedBody>,
+}
+
+impl ReleaseDesignated {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: ReleaseDesignatedBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        ReleaseDesignated {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operations of designated transfers
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum DesignatedTransferOperation {
+    #[allow(missing_docs)]
+    Send(SendDesignated),
+    /// By the recipient, within the claim period
+    Claim(ReleaseDesignated),
+    /// By the sender, after the claim period
+    Reclaim(ReleaseDesignated),
+}
+
+impl DesignatedTransferOperation {
+    /// The signatures, the replay tokens, and the amounts, the claim periods
+    /// and the recipients of the new transfers
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        match self {
+            DesignatedTransferOperation::Send(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                if 0 == i.body.amount {
+                    return Err(eg!("zero amount"));
+                }
+                if 0 == i.body.claim_period {
+                    return Err(eg!("zero claim period"));
+                }
+                if i.body.recipient == i.pubkey {
+                    return Err(eg!("the sender can not be the recipient"));
+                }
+            }
+            DesignatedTransferOperation::Claim(i)
+            | DesignatedTransferOperation::Reclaim(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Operations on the same transfer can not be included in the same block
+    pub fn conflict_key(&self) -> DesignatedId {
+        match self {
+            DesignatedTransferOperation::Send(i) => i.body.id(),
+            DesignatedTransferOperation::Claim(i)
+            | DesignatedTransferOperation::Reclaim(i) => i.body.id.clone(),
+        }
+    }
+
+    /// The UTXO released by the operation
+    pub fn output(&self) -> Option<TxOutput> {
+        match self {
+            DesignatedTransferOperation::Send(_) => None,
+            DesignatedTransferOperation::Claim(i)
+            | DesignatedTransferOperation::Reclaim(i) => {
+                Some(released_output(i.body.asset, i.body.amount, i.pubkey))
+            }
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get_signer(&self) -> &XfrPublicKey {
+        match self {
+            DesignatedTransferOperation::Send(i) => &i.pubkey,
+            DesignatedTransferOperation::Claim(i)
+            | DesignatedTransferOperation::Reclaim(i) => &i.pubkey,
+        }
+    }
+
+    /// The recipient of a new transfer, to index it for the recipient
+    pub fn get_recipient(&self) -> Option<&XfrPublicKey> {
+        match self {
+            DesignatedTransferOperation::Send(i) => Some(&i.body.recipient),
+            _ => None,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        match self {
+            DesignatedTransferOperation::Send(i) => {
+                i.body.no_replay_token = no_replay_token
+            }
+            DesignatedTransferOperation::Claim(i)
+            | DesignatedTransferOperation::Reclaim(i) => {
+                i.body.no_replay_token = no_replay_token
+            }
+        }
+    }
+}
+
+/// The state of designated transfers, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DesignatedTransferState {
+    /// Pending transfers, released ones are removed
+    pub transfers: BTreeMap<DesignatedId, DesignatedTransfer>,
+    /// The pending transfers not expired yet, by the end of their claim periods
+    expiries: BTreeMap<u64, Vec<DesignatedId>>,
+}
+
+impl DesignatedTransferState {
+    /// Check an operation against the state
+    pub fn check(&self, op: &DesignatedTransferOperation, height: u64) -> Result<()> {
+        match op {
+            DesignatedTransferOperation::Send(i) => {
+                if self.transfers.contains_key(&i.body.id()) {
+                    return Err(eg!("designated transfer exists"));
+                }
+            }
+            DesignatedTransferOperation::Claim(i) => {
+                let t = self.get(&i.body.id).c(d!())?;
+                if t.recipient != i.pubkey {
+                    return Err(eg!("not the recipient"));
+                }
+                if t.is_expired(height) {
+                    return Err(eg!(format!(
+                        "the claim period ended at height {}",
+                        t.expires_at
+                    )));
+                }
+                Self::check_release(t, &i.body).c(d!())?;
+            }
+            DesignatedTransferOperation::Reclaim(i) => {
+                let t = self.get(&i.body.id).c(d!())?;
+                if t.sender != i.pubkey {
+                    return Err(eg!("not the sender"));
+                }
+                if !t.is_expired(height) {
+                    return Err(eg!(format!(
+                        "the claim period ends at height {}",
+                        t.expires_at
+                    )));
+                }
+                Self::check_release(t, &i.body).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_release(
+        t: &DesignatedTransfer,
+        body: &ReleaseDesignatedBody,
+    ) -> Result<()> {
+        if t.asset != body.asset || t.amount != body.amount {
+            return Err(eg!("not the transferred asset or amount"));
+        }
+        Ok(())
+    }
+
+    /// Apply an operation checked by `check`
+    pub fn apply(&mut self, op: DesignatedTransferOperation, height: u64) {
+        let id = op.conflict_key();
+        match op {
+            DesignatedTransferOperation::Send(i) => {
+                let b = i.body;
+                let expires_at = height.saturating_add(b.claim_period);
+                self.expiries
+                    .entry(expires_at)
+                    .or_default()
+                    .push(id.clone());
+                self.transfers.insert(
+                    id,
+                    DesignatedTransfer {
+                        sender: i.pubkey,
+                        recipient: b.recipient,
+                        asset: b.asset,
+                        amount: b.amount,
+                        expires_at,
+                        expired: false,
+                    },
+                );
+            }
+            DesignatedTransferOperation::Claim(_) => {
+                if let Some(t) = self.transfers.remove(&id) {
+                    if let Some(ids) = self.expiries.get_mut(&t.expires_at) {
+                        ids.retain(|i| *i != id);
+                        if ids.is_empty() {
+                            self.expiries.remove(&t.expires_at);
+                        }
+                    }
+                }
+            }
+            DesignatedTransferOperation::Reclaim(_) => {
+                self.transfers.remove(&id);
+            }
+        }
+    }
+
+    /// Mark the transfers whose claim period ends at or before `height` as expired,
+    /// at the end of the block of `height`.
+    pub fn sweep(&mut self, height: u64) {
+        let pending = self.expiries.split_off(&height.saturating_add(1));
+        for id in std::mem::replace(&mut self.expiries, pending)
+            .into_values()
+            .flatten()
+        {
+            if let Some(t) = self.transfers.get_mut(&id) {
+                t.expired = true;
+            }
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get(&self, id: &str) -> Result<&DesignatedTransfer> {
+        self.transfers
+            .get(id)
+            .c(d!(format!("unknown designated transfer: {}", id)))
+    }
+}
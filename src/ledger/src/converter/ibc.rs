@@ -0,0 +1,584 @@
+//!
+//! # Cross-chain packets
+//!
+//! An IBC-style skeleton for exchanging packets with a counterparty chain:
+//!
+//! - a light client tracks the state roots of the counterparty,
+//!   headers must be signed by more than 2/3 of its voting power
+//! - `SendPacket` stores the commitment of a packet in the IBC store,
+//!   the root of the store is committed in `StateCommitmentData`
+//! - `RecvPacket` checks the commitment against a root of the counterparty,
+//!   then stores the acknowledgement
+//! - `AckPacket` checks the acknowledgement against a root of the counterparty,
+//!   then deletes the commitment
+//!
+//! NOTE: the light client verifies headers signed by the counterparty validators directly,
+//! tendermint commits, validator set changes and timeouts on the sending side
+//! are left to a full implementation.
+//!
+
+use {
+    crate::data_model::NoReplayToken,
+    globutils::SignatureOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha256},
+    std::{
+        collections::{BTreeMap, HashSet},
+        mem,
+    },
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// Max size of the data of a packet
+pub const MAX_PACKET_SIZE: usize = 64 * 1024;
+
+/// Roots of the counterparty kept by a client, older ones are pruned
+pub const MAX_TRACKED_ROOTS: usize = 1000;
+
+/// The acknowledgement of a received packet
+pub const ACK_SUCCESS: &[u8] = &[1];
+
+/// A hash in the IBC store
+pub type IbcHash = [u8; 32];
+
+/// A signature of a counterparty validator
+pub type IbcSignature<T> = (XfrPublicKey, SignatureOf<T>);
+
+/// Key of the commitment of a sent packet in the IBC store
+#[inline(always)]
+pub fn commitment_key(client_id: &str, sequence: u64) -> String {
+    format!("commitments/{}/{}", client_id, sequence)
+}
+
+/// Key of the acknowledgement of a received packet in the IBC store
+#[inline(always)]
+pub fn ack_key(client_id: &str, sequence: u64) -> String {
+    format!("acks/{}/{}", client_id, sequence)
+}
+
+/// Hash of the concatenation of some byte strings
+pub fn ibc_hash(data: &[&[u8]]) -> IbcHash {
+    let mut hasher = Sha256::new();
+    data.iter().for_each(|d| hasher.update(d));
+    hasher.finalize().into()
+}
+
+fn leaf_hash(key: &str, value: &IbcHash) -> IbcHash {
+    ibc_hash(&[
+        &[0],
+        &(key.len() as u64).to_be_bytes(),
+        key.as_bytes(),
+        value,
+    ])
+}
+
+fn node_hash(left: &IbcHash, right: &IbcHash) -> IbcHash {
+    ibc_hash(&[&[1], left, right])
+}
+
+/// A merkle proof of a key-value pair in the IBC store
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IbcProof {
+    /// Siblings from the leaf to the root, `true` if the sibling is on the left
+    pub path: Vec<(bool, IbcHash)>,
+}
+
+impl IbcProof {
+    #[allow(missing_docs)]
+    pub fn verify(&self, key: &str, value: &IbcHash, root: &IbcHash) -> bool {
+        let h = self
+            .path
+            .iter()
+            .fold(leaf_hash(key, value), |h, (left, s)| {
+                alt!(*left, node_hash(s, &h), node_hash(&h, s))
+            });
+        h == *root
+    }
+}
+
+/// A packet sent to the counterparty
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IbcPacket {
+    /// Assigned by the sending chain, starting from 1
+    pub sequence: u64,
+    #[allow(missing_docs)]
+    pub data: Vec<u8>,
+    /// Can not be received at or after this height of the receiving chain,
+    /// 0 for no timeout
+    pub timeout_height: u64,
+}
+
+impl IbcPacket {
+    /// The commitment stored by the sending chain
+    pub fn commitment(&self) -> IbcHash {
+        ibc_hash(&[&self.timeout_height.to_be_bytes(), &ibc_hash(&[&self.data])])
+    }
+}
+
+/// The light client of a counterparty chain
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IbcClient {
+    /// The chain ID of the counterparty
+    pub chain_id: String,
+    /// The ID of the client tracking this chain on the counterparty
+    pub counterparty_client_id: String,
+    /// Public keys and voting power of the counterparty validators
+    pub validators: Vec<(XfrPublicKey, u64)>,
+    #[allow(missing_docs)]
+    pub latest_height: u64,
+    /// Roots of the IBC store of the counterparty, keyed by height
+    pub roots: BTreeMap<u64, IbcHash>,
+    /// Sequence of the next packet sent to the counterparty
+    pub next_sequence: u64,
+}
+
+impl IbcClient {
+    /// More than 2/3 of the voting power must have signed,
+    /// the signatures themselves should have been verified.
+    pub fn check_quorum<T>(&self, sigs: &[IbcSignature<T>]) -> Result<()> {
+        let signers = sigs.iter().map(|(pk, _)| pk).collect::<HashSet<_>>();
+        let (signed, total) = self.validators.iter().fold(
+            (0u128, 0u128),
+            |(signed, total), (pk, power)| {
+                let p = *power as u128;
+                (alt!(signers.contains(pk), signed + p, signed), total + p)
+            },
+        );
+        if signed * 3 <= total * 2 {
+            return Err(eg!(format!("{} of {} voting power signed", signed, total)));
+        }
+        Ok(())
+    }
+}
+
+/// Operation body for creating a light client of a counterparty chain
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CreateIbcClientBody {
+    /// Must be unique in this chain
+    pub client_id: String,
+    /// The chain ID of the counterparty
+    pub chain_id: String,
+    /// The ID of the client tracking this chain on the counterparty
+    pub counterparty_client_id: String,
+    /// Public keys and voting power of the counterparty validators
+    pub validators: Vec<(XfrPublicKey, u64)>,
+    /// The trusted height of the counterparty
+    pub height: u64,
+    /// The trusted root of the counterparty at `height`
+    pub root: IbcHash,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for creating a light client, anyone can create one
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CreateIbcClient {
+    #[allow(missing_docs)]
+    pub body: CreateIbcClientBody,
+    /// The findora account publickey
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<CreateIbcClientBody>,
+}
+
+impl CreateIbcClient {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: CreateIbcClientBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        CreateIbcClient {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// A new root of the counterparty
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IbcHeader {
+    #[allow(missing_docs)]
+    pub client_id: String,
+    /// Must be higher than the latest height of the client
+    pub height: u64,
+    #[allow(missing_docs)]
+    pub root: IbcHash,
+}
+
+/// Operation data for updating a light client, submitted by relayers
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdateIbcClient {
+    #[allow(missing_docs)]
+    pub header: IbcHeader,
+    /// Signatures of the counterparty validators
+    pub signatures: Vec<IbcSignature<IbcHeader>>,
+}
+
+/// Operation body for sending a packet to the counterparty
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SendIbcPacketBody {
+    #[allow(missing_docs)]
+    pub client_id: String,
+    #[allow(missing_docs)]
+    pub data: Vec<u8>,
+    /// See `IbcPacket`
+    pub timeout_height: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for sending a packet, the sequence is assigned by the ledger
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SendIbcPacket {
+    #[allow(missing_docs)]
+    pub body: SendIbcPacketBody,
+    /// The findora account publickey
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<SendIbcPacketBody>,
+}
+
+impl SendIbcPacket {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: SendIbcPacketBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        SendIbcPacket {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation data for receiving a packet from the counterparty, submitted by relayers
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RecvIbcPacket {
+    #[allow(missing_docs)]
+    pub client_id: String,
+    #[allow(missing_docs)]
+    pub packet: IbcPacket,
+    /// The height of the root proving the commitment
+    pub proof_height: u64,
+    /// Proof of the commitment in the IBC store of the counterparty
+    pub proof: IbcProof,
+}
+
+/// Operation data for acknowledging a sent packet, submitted by relayers
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AckIbcPacket {
+    #[allow(missing_docs)]
+    pub client_id: String,
+    #[allow(missing_docs)]
+    pub packet: IbcPacket,
+    /// The acknowledgement written by the counterparty
+    pub ack: Vec<u8>,
+    /// The height of the root proving the acknowledgement
+    pub proof_height: u64,
+    /// Proof of the acknowledgement in the IBC store of the counterparty
+    pub proof: IbcProof,
+}
+
+/// Cross-chain operations
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum IbcOperation {
+    #[allow(missing_docs)]
+    CreateClient(CreateIbcClient),
+    #[allow(missing_docs)]
+    UpdateClient(UpdateIbcClient),
+    #[allow(missing_docs)]
+    SendPacket(SendIbcPacket),
+    #[allow(missing_docs)]
+    RecvPacket(RecvIbcPacket),
+    #[allow(missing_docs)]
+    AckPacket(AckIbcPacket),
+}
+
+impl IbcOperation {
+    /// The signatures, the replay tokens, the validators of the new clients,
+    /// and the sizes of the packets
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        match self {
+            IbcOperation::CreateClient(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                check_client_id(&i.body.client_id).c(d!())?;
+                check_client_id(&i.body.counterparty_client_id).c(d!())?;
+
+                let keys = i
+                    .body
+                    .validators
+                    .iter()
+                    .map(|(pk, _)| pk)
+                    .collect::<HashSet<_>>();
+                if keys.is_empty() || keys.len() != i.body.validators.len() {
+                    return Err(eg!("empty or duplicate validators"));
+                }
+                if i.body.validators.iter().any(|(_, power)| 0 == *power) {
+                    return Err(eg!("zero voting power"));
+                }
+            }
+            IbcOperation::UpdateClient(i) => {
+                for (pk, sig) in i.signatures.iter() {
+                    sig.verify(pk, &i.header).c(d!())?;
+                }
+            }
+            IbcOperation::SendPacket(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                if i.body.data.is_empty() || i.body.data.len() > MAX_PACKET_SIZE {
+                    return Err(eg!("invalid packet size"));
+                }
+            }
+            IbcOperation::RecvPacket(i) => {
+                if i.packet.data.len() > MAX_PACKET_SIZE {
+                    return Err(eg!("invalid packet size"));
+                }
+            }
+            IbcOperation::AckPacket(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Operations with the same key can not be included in the same block,
+    /// sends have no key, their sequences are assigned in order.
+    pub fn conflict_key(&self) -> Option<String> {
+        match self {
+            IbcOperation::CreateClient(i) => {
+                Some(format!("clients/{}", i.body.client_id))
+            }
+            IbcOperation::UpdateClient(i) => {
+                Some(format!("headers/{}", i.header.client_id))
+            }
+            IbcOperation::SendPacket(_) => None,
+            IbcOperation::RecvPacket(i) => {
+                Some(ack_key(&i.client_id, i.packet.sequence))
+            }
+            IbcOperation::AckPacket(i) => {
+                Some(commitment_key(&i.client_id, i.packet.sequence))
+            }
+        }
+    }
+
+    /// The signer of the operation, relayed ones are not signed by findora accounts
+    pub fn get_signer(&self) -> Option<&XfrPublicKey> {
+        match self {
+            IbcOperation::CreateClient(i) => Some(&i.pubkey),
+            IbcOperation::SendPacket(i) => Some(&i.pubkey),
+            _ => None,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        match self {
+            IbcOperation::CreateClient(i) => i.body.no_replay_token = no_replay_token,
+            IbcOperation::SendPacket(i) => i.body.no_replay_token = no_replay_token,
+            _ => {}
+        }
+    }
+}
+
+fn check_client_id(id: &str) -> Result<()> {
+    if id.is_empty() || id.contains('/') {
+        return Err(eg!(format!("invalid client id: {}", id)));
+    }
+    Ok(())
+}
+
+/// The cross-chain state, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IbcState {
+    /// Light clients of counterparty chains
+    pub clients: BTreeMap<String, IbcClient>,
+    /// Commitments of sent packets and acknowledgements of received ones,
+    /// the root is committed in `StateCommitmentData`
+    pub store: BTreeMap<String, IbcHash>,
+}
+
+impl IbcState {
+    /// Check an operation against the state
+    pub fn check(&self, op: &IbcOperation, height: u64) -> Result<()> {
+        match op {
+            IbcOperation::CreateClient(i) => {
+                if self.clients.contains_key(&i.body.client_id) {
+                    return Err(eg!("client exists"));
+                }
+            }
+            IbcOperation::UpdateClient(i) => {
+                let client = self.client(&i.header.client_id).c(d!())?;
+                if i.header.height <= client.latest_height {
+                    return Err(eg!("outdated header"));
+                }
+                client.check_quorum(&i.signatures).c(d!())?;
+            }
+            IbcOperation::SendPacket(i) => {
+                self.client(&i.body.client_id).c(d!())?;
+            }
+            IbcOperation::RecvPacket(i) => {
+                let client = self.client(&i.client_id).c(d!())?;
+                if 0 != i.packet.timeout_height && height >= i.packet.timeout_height {
+                    return Err(eg!("packet timed out"));
+                }
+                if self
+                    .store
+                    .contains_key(&ack_key(&i.client_id, i.packet.sequence))
+                {
+                    return Err(eg!("packet received"));
+                }
+                let root = client
+                    .roots
+                    .get(&i.proof_height)
+                    .c(d!("unknown proof height"))?;
+                let key =
+                    commitment_key(&client.counterparty_client_id, i.packet.sequence);
+                if !i.proof.verify(&key, &i.packet.commitment(), root) {
+                    return Err(eg!("invalid commitment proof"));
+                }
+            }
+            IbcOperation::AckPacket(i) => {
+                let client = self.client(&i.client_id).c(d!())?;
+                if self
+                    .store
+                    .get(&commitment_key(&i.client_id, i.packet.sequence))
+                    != Some(&i.packet.commitment())
+                {
+                    return Err(eg!("unknown packet"));
+                }
+                let root = client
+                    .roots
+                    .get(&i.proof_height)
+                    .c(d!("unknown proof height"))?;
+                let key = ack_key(&client.counterparty_client_id, i.packet.sequence);
+                if !i.proof.verify(&key, &ibc_hash(&[&i.ack]), root) {
+                    return Err(eg!("invalid acknowledgement proof"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply an operation checked by `check`.
+    pub fn apply(&mut self, op: IbcOperation) {
+        match op {
+            IbcOperation::CreateClient(i) => {
+                let b = i.body;
+                let mut roots = BTreeMap::new();
+                roots.insert(b.height, b.root);
+                self.clients.insert(
+                    b.client_id,
+                    IbcClient {
+                        chain_id: b.chain_id,
+                        counterparty_client_id: b.counterparty_client_id,
+                        validators: b.validators,
+                        latest_height: b.height,
+                        roots,
+                        next_sequence: 1,
+                    },
+                );
+            }
+            IbcOperation::UpdateClient(i) => {
+                if let Some(client) = self.clients.get_mut(&i.header.client_id) {
+                    client.latest_height = i.header.height;
+                    client.roots.insert(i.header.height, i.header.root);
+                    while client.roots.len() > MAX_TRACKED_ROOTS {
+                        let oldest = *client.roots.keys().next().unwrap();
+                        client.roots.remove(&oldest);
+                    }
+                }
+            }
+            IbcOperation::SendPacket(i) => {
+                if let Some(client) = self.clients.get_mut(&i.body.client_id) {
+                    let packet = IbcPacket {
+                        sequence: client.next_sequence,
+                        data: i.body.data,
+                        timeout_height: i.body.timeout_height,
+                    };
+                    client.next_sequence += 1;
+                    self.store.insert(
+                        commitment_key(&i.body.client_id, packet.sequence),
+                        packet.commitment(),
+                    );
+                }
+            }
+            IbcOperation::RecvPacket(i) => {
+                self.store.insert(
+                    ack_key(&i.client_id, i.packet.sequence),
+                    ibc_hash(&[ACK_SUCCESS]),
+                );
+            }
+            IbcOperation::AckPacket(i) => {
+                self.store
+                    .remove(&commitment_key(&i.client_id, i.packet.sequence));
+            }
+        }
+    }
+
+    /// Root of the IBC store, None if it is empty
+    pub fn root(&self) -> Option<IbcHash> {
+        store_root(&self.store)
+    }
+
+    /// Merkle proof of a key in the IBC store
+    pub fn prove(&self, key: &str) -> Option<IbcProof> {
+        store_prove(&self.store, key)
+    }
+
+    fn client(&self, id: &str) -> Result<&IbcClient> {
+        self.clients
+            .get(id)
+            .c(d!(format!("unknown client: {}", id)))
+    }
+}
+
+/// Root of a store of hashes, None if it is empty,
+/// the proofs of its keys are `IbcProof`s.
+pub(crate) fn store_root(store: &BTreeMap<String, IbcHash>) -> Option<IbcHash> {
+    levels(store).last().map(|l| l[0])
+}
+
+/// Merkle proof of a key in a store of hashes, see `store_root`.
+pub(crate) fn store_prove(
+    store: &BTreeMap<String, IbcHash>,
+    key: &str,
+) -> Option<IbcProof> {
+    let mut idx = store.keys().position(|k| k == key)?;
+    let levels = levels(store);
+    let mut path = vec![];
+    for level in levels.iter().take(levels.len() - 1) {
+        let sibling = idx ^ 1;
+        // the last node of an odd level is promoted as is
+        if let Some(s) = level.get(sibling) {
+            path.push((sibling < idx, *s));
+        }
+        idx /= 2;
+    }
+    Some(IbcProof { path })
+}
+
+// from the leaves, sorted by key, to the root
+fn levels(store: &BTreeMap<String, IbcHash>) -> Vec<Vec<IbcHash>> {
+    let mut level = store
+        .iter()
+        .map(|(k, v)| leaf_hash(k, v))
+        .collect::<Vec<_>>();
+    if level.is_empty() {
+        return vec![];
+    }
+
+    let mut levels = vec![];
+    while level.len() > 1 {
+        let next = level
+            .chunks(2)
+            .map(|c| alt!(2 == c.len(), node_hash(&c[0], &c[1]), c[0]))
+            .collect();
+        levels.push(mem::replace(&mut level, next));
+    }
+    levels.push(level);
+    levels
+}
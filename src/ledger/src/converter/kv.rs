@@ -0,0 +1,245 @@
+//!
+//! # Key-value anchoring
+//!
+//! Small data blobs stored under keys owned by a findora account,
+//! so applications can anchor their records without defining fake assets:
+//!
+//! - `Put` creates a key, the signer becomes its owner
+//! - `Update` replaces the value of a key, signed by its owner
+//! - `Delete` removes a key, signed by its owner
+//!
+//! Every operation pays `KV_FEE_PER_KB` for each started KB of its key and value,
+//! on top of the minimal fee of the transaction.
+//! The root of the store is committed in `StateCommitmentData`,
+//! entries are proved with the merkle proofs of the IBC store.
+//! The entries are kept on disk, only the root is in the snapshots of the ledger.
+//!
+
+use {
+    super::ibc::{ibc_hash, store_prove, store_root, IbcHash, IbcProof},
+    crate::{
+        data_model::{NoReplayToken, TX_FEE_MIN},
+        staking::DEFAULT_ENTRIES_DIR,
+    },
+    fbnc::{new_mapx, Mapx},
+    globutils::SignatureOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::{
+        serialization::ZeiFromToBytes,
+        xfr::sig::{XfrKeyPair, XfrPublicKey},
+    },
+};
+
+/// Max length of a key
+pub const MAX_KV_KEY_LEN: usize = 128;
+
+/// Max size of a value
+pub const MAX_KV_VALUE_SIZE: usize = 4 * 1024;
+
+/// Fee for each started KB of the key and the value of an operation
+pub const KV_FEE_PER_KB: u64 = TX_FEE_MIN;
+
+/// The fee of an operation on `key`, see `KV_FEE_PER_KB`
+pub fn kv_fee(key: &str, action: &KvAction) -> u64 {
+    let size = key.len()
+        + match action {
+            KvAction::Put(v) | KvAction::Update(v) => v.len(),
+            KvAction::Delete => 0,
+        };
+    ((size + 1023) / 1024) as u64 * KV_FEE_PER_KB
+}
+
+/// What to do with a key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum KvAction {
+    /// Create the key, it must not exist
+    Put(Vec<u8>),
+    /// Replace the value, the key must exist
+    Update(Vec<u8>),
+    /// Remove the key, it must exist
+    Delete,
+}
+
+/// Operation body of a key-value operation
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KvOperationBody {
+    #[allow(missing_docs)]
+    pub key: String,
+    #[allow(missing_docs)]
+    pub action: KvAction,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data of a key-value operation
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KvOperation {
+    #[allow(missing_docs)]
+    pub body: KvOperationBody,
+    /// The findora account publickey
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<KvOperationBody>,
+}
+
+impl KvOperation {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: KvOperationBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        KvOperation {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+
+    /// The signature, the replay token, and the sizes of the key and the value
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        if self.body.no_replay_token != no_replay_token {
+            return Err(eg!("replay token not match"));
+        }
+        self.signature.verify(&self.pubkey, &self.body).c(d!())?;
+        if self.body.key.is_empty() || self.body.key.len() > MAX_KV_KEY_LEN {
+            return Err(eg!("invalid key length"));
+        }
+        match &self.body.action {
+            KvAction::Put(v) | KvAction::Update(v) => {
+                if v.len() > MAX_KV_VALUE_SIZE {
+                    return Err(eg!("value too large"));
+                }
+            }
+            KvAction::Delete => {}
+        }
+        Ok(())
+    }
+
+    /// The fee to pay for the operation, besides the minimal fee of the transaction
+    #[inline(always)]
+    pub fn fee(&self) -> u64 {
+        kv_fee(&self.body.key, &self.body.action)
+    }
+
+    /// Operations on the same key can not be included in the same block
+    #[inline(always)]
+    pub fn conflict_key(&self) -> &str {
+        &self.body.key
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        self.body.no_replay_token = no_replay_token;
+    }
+}
+
+/// A value with its owner
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KvEntry {
+    #[allow(missing_docs)]
+    pub owner: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub value: Vec<u8>,
+    /// The height of the block of the last `Put` or `Update`
+    pub height: u64,
+}
+
+impl KvEntry {
+    /// The hash of the entry in the merkle tree of the store
+    pub fn digest(&self) -> IbcHash {
+        ibc_hash(&[
+            &self.owner.zei_to_bytes(),
+            &self.height.to_be_bytes(),
+            &self.value,
+        ])
+    }
+}
+
+/// The key-value store, maintained by the ledger.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct KvState {
+    entries: Mapx<String, KvEntry>,
+    // updated by `update_root` once the operations of a block are applied
+    root: Option<IbcHash>,
+}
+
+impl Default for KvState {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTRIES_DIR)
+    }
+}
+
+impl KvState {
+    #[allow(missing_docs)]
+    pub fn new(entries_dir: &str) -> Self {
+        KvState {
+            entries: new_mapx!(format!("{}/kv_entries", entries_dir)),
+            root: None,
+        }
+    }
+
+    /// The entry of a key
+    pub fn get(&self, key: &str) -> Option<KvEntry> {
+        self.entries.get(&key.to_owned())
+    }
+
+    /// Check an operation against the state
+    pub fn check(&self, op: &KvOperation) -> Result<()> {
+        let entry = self.entries.get(&op.body.key);
+        match op.body.action {
+            KvAction::Put(_) => {
+                if entry.is_some() {
+                    return Err(eg!("key exists"));
+                }
+            }
+            KvAction::Update(_) | KvAction::Delete => {
+                let entry = entry.c(d!("key not found"))?;
+                if entry.owner != op.pubkey {
+                    return Err(eg!("not the owner of the key"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply an operation checked by `check`
+    pub fn apply(&mut self, op: KvOperation, height: u64) {
+        let KvOperationBody { key, action, .. } = op.body;
+        match action {
+            KvAction::Put(value) | KvAction::Update(value) => {
+                self.entries.insert(
+                    key,
+                    KvEntry {
+                        owner: op.pubkey,
+                        value,
+                        height,
+                    },
+                );
+            }
+            KvAction::Delete => {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    /// Recompute the root after applying operations
+    pub fn update_root(&mut self) {
+        self.root = store_root(&self.digests());
+    }
+
+    /// Root of the store, None if it is empty
+    pub fn root(&self) -> Option<IbcHash> {
+        self.root
+    }
+
+    /// Merkle proof of a key, verified with `KvEntry::digest`
+    pub fn prove(&self, key: &str) -> Option<IbcProof> {
+        store_prove(&self.digests(), key)
+    }
+
+    fn digests(&self) -> BTreeMap<String, IbcHash> {
+        self.entries.iter().map(|(k, e)| (k, e.digest())).collect()
+    }
+}
@@ -1,4 +1,23 @@
 //! Multi Signer operation for transaction.
+//!
+//! The operations on the state of the ledger beyond the UTXOs, eg. key-value entries
+//! or payment streams, follow the same conventions:
+//!
+//! - the `check` of an operation is independent of the ledger state, its signatures,
+//!   its replay token and its form, it is called in `TxnEffect::compute_effect`
+//! - the `check` of a state checks an operation against it, its `apply` applies
+//!   the checked ones, a `height` is always the one of the block including them
+
+pub mod designated_transfer;
+pub mod ibc;
+pub mod kv;
+pub mod notary;
+pub mod payment_stream;
+pub mod prism;
+pub mod pull_payment;
+pub mod session_key;
+pub mod spending_policy;
+pub mod vesting;
 
 use crate::data_model::{
     NoReplayToken, Operation, Transaction, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING,
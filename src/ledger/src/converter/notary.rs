@@ -0,0 +1,225 @@
+//!
+//! # Hash notarization
+//!
+//! `AnchorHash` records a 32-byte hash, usually the digest of a document,
+//! under the key of the submitter, with the height and the timestamp of the block.
+//!
+//! A hash is anchored at most once by each key, so the record proves
+//! the existence of the document no later than its timestamp.
+//! The root of the records is committed in `StateCommitmentData`.
+//! The records are kept on disk, only the root is in the snapshots of the ledger.
+//!
+
+use {
+    super::ibc::{ibc_hash, store_prove, store_root, IbcHash, IbcProof},
+    crate::{data_model::NoReplayToken, staking::DEFAULT_ENTRIES_DIR},
+    fbnc::{new_mapx, Mapx},
+    globutils::{wallet, SignatureOf},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::{
+        serialization::ZeiFromToBytes,
+        xfr::sig::{XfrKeyPair, XfrPublicKey},
+    },
+};
+
+/// Max length of the tag of an anchored hash
+pub const MAX_ANCHOR_TAG_LEN: usize = 64;
+
+/// Key of an anchored hash in the notary store,
+/// the records of a hash are adjacent.
+#[inline(always)]
+pub fn anchor_key(hash: &[u8; 32], owner: &XfrPublicKey) -> String {
+    format!(
+        "{}/{}",
+        hex::encode(hash),
+        wallet::public_key_to_bech32(owner)
+    )
+}
+
+/// The hash of a document to anchor
+#[inline(always)]
+pub fn document_hash(data: &[u8]) -> [u8; 32] {
+    ibc_hash(&[data])
+}
+
+/// Operation body of anchoring a hash
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AnchorHashBody {
+    #[allow(missing_docs)]
+    pub hash: [u8; 32],
+    /// An optional label, e.g. the type of the document
+    pub tag: Option<String>,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data of anchoring a hash under the key of the submitter
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AnchorHash {
+    #[allow(missing_docs)]
+    pub body: AnchorHashBody,
+    /// The findora account publickey
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<AnchorHashBody>,
+}
+
+impl AnchorHash {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: AnchorHashBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        AnchorHash {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+
+    /// The signature, the replay token, and the length of the tag
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        if self.body.no_replay_token != no_replay_token {
+            return Err(eg!("replay token not match"));
+        }
+        self.signature.verify(&self.pubkey, &self.body).c(d!())?;
+        if let Some(tag) = self.body.tag.as_ref() {
+            if tag.is_empty() || tag.len() > MAX_ANCHOR_TAG_LEN {
+                return Err(eg!("invalid tag length"));
+            }
+        }
+        Ok(())
+    }
+
+    /// A hash can be anchored once by each key
+    #[inline(always)]
+    pub fn conflict_key(&self) -> String {
+        anchor_key(&self.body.hash, &self.pubkey)
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        self.body.no_replay_token = no_replay_token;
+    }
+}
+
+/// A hash anchored on chain
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NotarizedHash {
+    #[allow(missing_docs)]
+    pub hash: [u8; 32],
+    #[allow(missing_docs)]
+    pub owner: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub tag: Option<String>,
+    /// The height of the block anchoring it
+    pub height: u64,
+    /// The timestamp of the block anchoring it, in seconds
+    pub timestamp: u64,
+}
+
+impl NotarizedHash {
+    /// The hash of the record in the merkle tree of the store
+    pub fn digest(&self) -> IbcHash {
+        ibc_hash(&[
+            &self.hash,
+            &self.owner.zei_to_bytes(),
+            self.tag.as_deref().unwrap_or_default().as_bytes(),
+            &self.height.to_be_bytes(),
+            &self.timestamp.to_be_bytes(),
+        ])
+    }
+}
+
+/// The anchored hashes, maintained by the ledger.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NotaryState {
+    // records by the hex of their hashes, then by `anchor_key`
+    records: Mapx<String, BTreeMap<String, NotarizedHash>>,
+    // updated by `update_root` once the operations of a block are applied
+    root: Option<IbcHash>,
+}
+
+impl Default for NotaryState {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENTRIES_DIR)
+    }
+}
+
+impl NotaryState {
+    #[allow(missing_docs)]
+    pub fn new(entries_dir: &str) -> Self {
+        NotaryState {
+            records: new_mapx!(format!("{}/notary_records", entries_dir)),
+            root: None,
+        }
+    }
+
+    /// The record of a hash anchored by `owner`
+    pub fn get_record(
+        &self,
+        hash: &[u8; 32],
+        owner: &XfrPublicKey,
+    ) -> Option<NotarizedHash> {
+        self.records
+            .get(&hex::encode(hash))?
+            .remove(&anchor_key(hash, owner))
+    }
+
+    /// Check an operation against the state
+    pub fn check(&self, op: &AnchorHash) -> Result<()> {
+        if self.get_record(&op.body.hash, &op.pubkey).is_some() {
+            return Err(eg!("hash already anchored"));
+        }
+        Ok(())
+    }
+
+    /// Apply a checked operation, `height` and `timestamp` are
+    /// of the block including it.
+    pub fn apply(&mut self, op: AnchorHash, height: u64, timestamp: u64) {
+        let record = NotarizedHash {
+            hash: op.body.hash,
+            owner: op.pubkey,
+            tag: op.body.tag,
+            height,
+            timestamp,
+        };
+        let hash = hex::encode(&record.hash);
+        let mut records = self.records.get(&hash).unwrap_or_default();
+        records.insert(anchor_key(&record.hash, &record.owner), record);
+        self.records.insert(hash, records);
+    }
+
+    /// All records of a hash, by the keys anchoring it
+    pub fn get_records(&self, hash: &[u8; 32]) -> Vec<NotarizedHash> {
+        self.records
+            .get(&hex::encode(hash))
+            .map(|r| r.into_values().collect())
+            .unwrap_or_default()
+    }
+
+    /// Recompute the root after applying operations
+    pub fn update_root(&mut self) {
+        self.root = store_root(&self.digests());
+    }
+
+    /// Root of the store, None if it is empty
+    pub fn root(&self) -> Option<IbcHash> {
+        self.root
+    }
+
+    /// Merkle proof of a record, verified with `NotarizedHash::digest`
+    pub fn prove(&self, key: &str) -> Option<IbcProof> {
+        store_prove(&self.digests(), key)
+    }
+
+    fn digests(&self) -> BTreeMap<String, IbcHash> {
+        self.records
+            .iter()
+            .flat_map(|(_, r)| r.into_iter())
+            .map(|(k, r)| (k, r.digest()))
+            .collect()
+    }
+}
@@ -0,0 +1,408 @@
+//!
+//! # Payment streams
+//!
+//! Continuous payments vesting linearly over a range of blocks:
+//!
+//! - `CreateStream` locks non-confidential UTXOs of the sender in
+//!   `BLACK_HOLE_PUBKEY_STAKING`, by the transfers of the same transaction,
+//!   the amount vests linearly from `start_height` to `end_height`
+//! - `ClaimStream` releases some of the vested assets to a new UTXO of the recipient
+//! - `CancelStream` stops the vesting, at most the unvested amount is released back
+//!   to the sender, and the rest can be claimed by the recipient at once
+//!
+//! The ledger tracks the claimed amount of each stream,
+//! at most one operation per stream is allowed in a block.
+//!
+
+use {
+    crate::{
+        converter::pull_payment::released_output,
+        data_model::{AssetTypeCode, NoReplayToken, TxOutput},
+    },
+    globutils::{HashOf, SignatureOf},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+};
+
+/// The identifier of a stream, the hash of its `CreateStreamBody` in hex.
+pub type StreamId = String;
+
+/// A stream maintained by the ledger
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PaymentStream {
+    #[allow(missing_docs)]
+    pub sender: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub recipient: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    /// Total amount of the stream, minus the refund if cancelled
+    pub amount: u64,
+    /// Nothing is vested before this height
+    pub start_height: u64,
+    /// Everything is vested from this height
+    pub end_height: u64,
+    /// Released to the recipient
+    pub claimed: u64,
+    /// Height of the block cancelling the stream, all of `amount` is vested since then
+    pub cancelled_at: Option<u64>,
+}
+
+impl PaymentStream {
+    /// Amount vested at `height`
+    pub fn vested(&self, height: u64) -> u64 {
+        if self.cancelled_at.is_some() || height >= self.end_height {
+            self.amount
+        } else if height <= self.start_height {
+            0
+        } else {
+            let elapsed = (height - self.start_height) as u128;
+            let duration = (self.end_height - self.start_height) as u128;
+            (self.amount as u128 * elapsed / duration) as u64
+        }
+    }
+
+    /// Amount that can be claimed by the recipient at `height`
+    #[inline(always)]
+    pub fn claimable(&self, height: u64) -> u64 {
+        self.vested(height).saturating_sub(self.claimed)
+    }
+
+    /// Amount that can be refunded to the sender at `height`
+    #[inline(always)]
+    pub fn unvested(&self, height: u64) -> u64 {
+        self.amount - self.vested(height)
+    }
+}
+
+/// Operation body for creating a stream
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CreateStreamBody {
+    #[allow(missing_docs)]
+    pub recipient: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    /// Must be equal to the non-confidential amount of the asset
+    /// sent to `BLACK_HOLE_PUBKEY_STAKING` in the same transaction
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    #[allow(missing_docs)]
+    pub start_height: u64,
+    #[allow(missing_docs)]
+    pub end_height: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+impl CreateStreamBody {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn id(&self) -> StreamId {
+        HashOf::new(self).hex()
+    }
+}
+
+/// Operation data for creating a stream, signed by the sender
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CreateStream {
+    #[allow(missing_docs)]
+    pub body: CreateStreamBody,
+    /// The sender
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<CreateStreamBody>,
+}
+
+impl CreateStream {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: CreateStreamBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        CreateStream {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for claiming the vested assets
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ClaimStreamBody {
+    #[allow(missing_docs)]
+    pub id: StreamId,
+    /// The asset, must be the streamed one
+    pub asset: AssetTypeCode,
+    #[allow(missing_docs)]
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for claiming the vested assets, signed by the recipient
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ClaimStream {
+    #[allow(missing_docs)]
+    pub body: ClaimStreamBody,
+    /// The recipient, owner of the new UTXO
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<ClaimStreamBody>,
+}
+
+impl ClaimStream {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: ClaimStreamBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        ClaimStream {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for cancelling a stream
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CancelStreamBody {
+    #[allow(missing_docs)]
+    pub id: StreamId,
+    /// The asset, must be the streamed one
+    pub asset: AssetTypeCode,
+    /// At most the unvested amount in the block including the cancellation,
+    /// which only decreases, so a refund estimated at a later height is safe
+    #[serde(with = "serde_strz")]
+    pub refund: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for cancelling a stream, signed by the sender
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CancelStream {
+    #[allow(missing_docs)]
+    pub body: CancelStreamBody,
+    /// The sender, owner of the refund
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<CancelStreamBody>,
+}
+
+impl CancelStream {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: CancelStreamBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        CancelStream {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operations of payment streams
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PaymentStreamOperation {
+    #[allow(missing_docs)]
+    Create(CreateStream),
+    #[allow(missing_docs)]
+    Claim(ClaimStream),
+    #[allow(missing_docs)]
+    Cancel(CancelStream),
+}
+
+impl PaymentStreamOperation {
+    /// The signatures, the replay tokens, and the amounts, the block ranges
+    /// and the recipients of the new streams
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        match self {
+            PaymentStreamOperation::Create(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                if 0 == i.body.amount {
+                    return Err(eg!("zero amount"));
+                }
+                if i.body.end_height <= i.body.start_height {
+                    return Err(eg!("the stream must end after its start"));
+                }
+                if i.body.recipient == i.pubkey {
+                    return Err(eg!("the sender can not be the recipient"));
+                }
+            }
+            PaymentStreamOperation::Claim(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                if 0 == i.body.amount {
+                    return Err(eg!("zero claim"));
+                }
+            }
+            PaymentStreamOperation::Cancel(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Operations on the same stream can not be included in the same block
+    pub fn conflict_key(&self) -> StreamId {
+        match self {
+            PaymentStreamOperation::Create(i) => i.body.id(),
+            PaymentStreamOperation::Claim(i) => i.body.id.clone(),
+            PaymentStreamOperation::Cancel(i) => i.body.id.clone(),
+        }
+    }
+
+    /// The UTXO released by the operation
+    pub fn output(&self) -> Option<TxOutput> {
+        match self {
+            PaymentStreamOperation::Create(_) => None,
+            PaymentStreamOperation::Claim(i) => {
+                Some(released_output(i.body.asset, i.body.amount, i.pubkey))
+            }
+            PaymentStreamOperation::Cancel(i) if 0 < i.body.refund => {
+                Some(released_output(i.body.asset, i.body.refund, i.pubkey))
+            }
+            PaymentStreamOperation::Cancel(_) => None,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get_signer(&self) -> &XfrPublicKey {
+        match self {
+            PaymentStreamOperation::Create(i) => &i.pubkey,
+            PaymentStreamOperation::Claim(i) => &i.pubkey,
+            PaymentStreamOperation::Cancel(i) => &i.pubkey,
+        }
+    }
+
+    /// The recipient of a new stream, to index it for the recipient
+    pub fn get_recipient(&self) -> Option<&XfrPublicKey> {
+        match self {
+            PaymentStreamOperation::Create(i) => Some(&i.body.recipient),
+            _ => None,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        match self {
+            PaymentStreamOperation::Create(i) => {
+                i.body.no_replay_token = no_replay_token
+            }
+            PaymentStreamOperation::Claim(i) => i.body.no_replay_token = no_replay_token,
+            PaymentStreamOperation::Cancel(i) => {
+                i.body.no_replay_token = no_replay_token
+            }
+        }
+    }
+}
+
+/// The state of payment streams, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PaymentStreamState {
+    /// Active streams, fully claimed ones are removed
+    pub streams: BTreeMap<StreamId, PaymentStream>,
+}
+
+impl PaymentStreamState {
+    /// Check an operation against the state
+    pub fn check(&self, op: &PaymentStreamOperation, height: u64) -> Result<()> {
+        match op {
+            PaymentStreamOperation::Create(i) => {
+                if self.streams.contains_key(&i.body.id()) {
+                    return Err(eg!("stream exists"));
+                }
+            }
+            PaymentStreamOperation::Claim(i) => {
+                let stream = self.get(&i.body.id).c(d!())?;
+                if stream.recipient != i.pubkey || stream.asset != i.body.asset {
+                    return Err(eg!("not the recipient or the streamed asset"));
+                }
+                if i.body.amount > stream.claimable(height) {
+                    return Err(eg!(format!(
+                        "at most {} can be claimed now",
+                        stream.claimable(height)
+                    )));
+                }
+            }
+            PaymentStreamOperation::Cancel(i) => {
+                let stream = self.get(&i.body.id).c(d!())?;
+                if stream.sender != i.pubkey || stream.asset != i.body.asset {
+                    return Err(eg!("not the sender or the streamed asset"));
+                }
+                if stream.cancelled_at.is_some() {
+                    return Err(eg!("stream cancelled"));
+                }
+                if i.body.refund > stream.unvested(height) {
+                    return Err(eg!(format!(
+                        "at most {} can be refunded now",
+                        stream.unvested(height)
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply an operation checked by `check`
+    pub fn apply(&mut self, op: PaymentStreamOperation, height: u64) {
+        let id = op.conflict_key();
+        match op {
+            PaymentStreamOperation::Create(i) => {
+                let b = i.body;
+                self.streams.insert(
+                    id.clone(),
+                    PaymentStream {
+                        sender: i.pubkey,
+                        recipient: b.recipient,
+                        asset: b.asset,
+                        amount: b.amount,
+                        start_height: b.start_height,
+                        end_height: b.end_height,
+                        claimed: 0,
+                        cancelled_at: None,
+                    },
+                );
+            }
+            PaymentStreamOperation::Claim(i) => {
+                if let Some(stream) = self.streams.get_mut(&id) {
+                    stream.claimed += i.body.amount;
+                }
+            }
+            PaymentStreamOperation::Cancel(i) => {
+                if let Some(stream) = self.streams.get_mut(&id) {
+                    stream.amount -= i.body.refund;
+                    stream.cancelled_at = Some(height);
+                }
+            }
+        }
+
+        if self
+            .streams
+            .get(&id)
+            .map(|s| s.claimed == s.amount)
+            .unwrap_or(false)
+        {
+            self.streams.remove(&id);
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get(&self, id: &str) -> Result<&PaymentStream> {
+        self.streams
+            .get(id)
+            .c(d!(format!("unknown stream: {}", id)))
+    }
+}
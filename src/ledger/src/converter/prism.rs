@@ -0,0 +1,223 @@
+//!
+//! # Prism bridge
+//!
+//! Moves FRA and custom assets between the UTXO ledger and an EVM-compatible account side:
+//!
+//! - `PrismDeposit` locks non-confidential UTXOs in `BLACK_HOLE_PUBKEY_STAKING`,
+//!   the bridge authorities credit the receiver on the account side once it is committed,
+//!   the authenticated transaction(with its merkle proof) is the proof of the deposit
+//! - `PrismWithdraw` releases the locked assets to a new UTXO after they have been burned
+//!   on the account side, attested by the signatures of a threshold of the authorities
+//! - `UpdatePrismAuthorities` replaces the authorities, signed by a threshold of the
+//!   current ones, the first set is signed by the issuer of FRA
+//!
+//! The ledger tracks the locked amount of each asset,
+//! so withdrawals can never release more than what has been deposited.
+//!
+
+use {
+    crate::data_model::{AssetTypeCode, NoReplayToken, TxOutput},
+    fp_types::crypto::MultiSigner,
+    globutils::SignatureOf,
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeMap, BTreeSet, HashSet},
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{build_blind_asset_record, AssetRecordType},
+            sig::{XfrKeyPair, XfrPublicKey},
+            structs::AssetRecordTemplate,
+        },
+    },
+};
+
+/// A signature of a bridge authority.
+pub type PrismSignature<T> = (XfrPublicKey, SignatureOf<T>);
+
+/// The unique identifier of a withdrawal: (chain ID, hash of the burning transaction).
+pub type PrismWithdrawId = (u64, [u8; 32]);
+
+/// The bridge authorities.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PrismAuthorities {
+    /// Public keys of the authorities
+    pub keys: Vec<XfrPublicKey>,
+    /// How many of them must sign a withdrawal or an update
+    pub threshold: usize,
+}
+
+impl PrismAuthorities {
+    /// Keys must be unique, and the threshold must be in `[1, keys.len()]`.
+    pub fn check(&self) -> Result<()> {
+        let keys = self.keys.iter().collect::<HashSet<_>>();
+        if keys.len() != self.keys.len() {
+            return Err(eg!("duplicate authorities"));
+        }
+        if 0 == self.threshold || self.threshold > self.keys.len() {
+            return Err(eg!("invalid threshold"));
+        }
+        Ok(())
+    }
+
+    /// Check that enough distinct authorities have signed the message,
+    /// the signatures themselves should have been verified.
+    pub fn check_signers<T>(&self, sigs: &[PrismSignature<T>]) -> Result<()> {
+        let signers = sigs
+            .iter()
+            .map(|(pk, _)| pk)
+            .filter(|pk| self.keys.contains(pk))
+            .collect::<HashSet<_>>();
+        if signers.len() < self.threshold {
+            return Err(eg!(format!(
+                "{} authorities signed, {} required",
+                signers.len(),
+                self.threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The state of the prism bridge, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PrismState {
+    /// None before the first `UpdatePrismAuthorities`
+    pub authorities: Option<PrismAuthorities>,
+    /// Increased by one on each update of the authorities
+    pub version: u64,
+    /// Amounts deposited and not withdrawn yet
+    #[serde(with = "globutils::serde_pairs")]
+    pub locked: BTreeMap<AssetTypeCode, u64>,
+    /// Processed withdrawals
+    pub withdrawals: BTreeSet<PrismWithdrawId>,
+}
+
+/// Operation body for locking assets on the ledger
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PrismDepositBody {
+    /// The asset to deposit
+    pub asset: AssetTypeCode,
+    /// The receiver on the account side
+    pub receiver: MultiSigner,
+    /// Must be equal to the non-confidential amount of the asset
+    /// sent to `BLACK_HOLE_PUBKEY_STAKING` in the same transaction
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for locking assets on the ledger, signed by the owner of the assets
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PrismDeposit {
+    #[allow(missing_docs)]
+    pub body: PrismDepositBody,
+    /// The findora account publickey
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<PrismDepositBody>,
+}
+
+impl PrismDeposit {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: PrismDepositBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        PrismDeposit {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for releasing locked assets
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PrismWithdrawBody {
+    /// The chain ID of the account side
+    pub chain_id: u64,
+    /// Hash of the transaction burning the assets on the account side
+    pub burn_tx: [u8; 32],
+    /// The asset to release
+    pub asset: AssetTypeCode,
+    /// The owner of the new UTXO
+    pub receiver: XfrPublicKey,
+    #[allow(missing_docs)]
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+}
+
+impl PrismWithdrawBody {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn id(&self) -> PrismWithdrawId {
+        (self.chain_id, self.burn_tx)
+    }
+
+    /// The released UTXO, a non-confidential record built without randomness,
+    /// so that all nodes get the same output
+    pub fn output(&self) -> TxOutput {
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            self.amount,
+            self.asset.val,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            self.receiver,
+        );
+        let (record, _, _) = build_blind_asset_record(
+            &mut ChaChaRng::seed_from_u64(0),
+            &PublicParams::default().pc_gens,
+            &template,
+            vec![],
+        );
+        TxOutput {
+            id: None,
+            record,
+            lien: None,
+            memo: None,
+        }
+    }
+}
+
+/// Operation data for releasing locked assets,
+/// the authorities sign the body independently, no replay token is included,
+/// a withdrawal is processed at most once according to its ID
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PrismWithdraw {
+    #[allow(missing_docs)]
+    pub body: PrismWithdrawBody,
+    /// Signatures of the bridge authorities
+    pub signatures: Vec<PrismSignature<PrismWithdrawBody>>,
+}
+
+/// Operation body for replacing the bridge authorities
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdatePrismAuthoritiesBody {
+    /// The new authorities
+    pub authorities: PrismAuthorities,
+    /// Must be the current version plus one
+    pub version: u64,
+}
+
+/// Operation data for replacing the bridge authorities,
+/// the version prevents it from being replayed
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdatePrismAuthorities {
+    #[allow(missing_docs)]
+    pub body: UpdatePrismAuthoritiesBody,
+    /// Signatures of the current authorities, or the issuer of FRA
+    pub signatures: Vec<PrismSignature<UpdatePrismAuthoritiesBody>>,
+}
+
+/// Verify all signatures of a body signed by multiple authorities.
+pub fn verify_signatures<T>(body: &T, sigs: &[PrismSignature<T>]) -> Result<()>
+where
+    T: Serialize + serde::de::DeserializeOwned,
+{
+    for (pk, sig) in sigs.iter() {
+        sig.verify(pk, body).c(d!())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,416 @@
+//!
+//! # Pull payments
+//!
+//! Recurring payments pulled by the payee within the limits authorized by the payer:
+//!
+//! - `AuthorizePull` locks the designated non-confidential UTXOs of the payer in
+//!   `BLACK_HOLE_PUBKEY_STAKING`, by the transfers of the same transaction,
+//!   and allows the payee to pull up to `limit` units per `period` blocks from them
+//! - `Pull` releases some of the locked assets to a new UTXO of the payee
+//! - `RevokePull` ends the authorization, the rest is released back to the payer
+//!
+//! The ledger tracks the remaining amount and the amount pulled in the current period
+//! of each authorization, at most one operation per authorization is allowed in a block.
+//!
+
+use {
+    crate::data_model::{AssetTypeCode, NoReplayToken, TxOutput},
+    globutils::{HashOf, SignatureOf},
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{build_blind_asset_record, AssetRecordType},
+            sig::{XfrKeyPair, XfrPublicKey},
+            structs::AssetRecordTemplate,
+        },
+    },
+};
+
+/// The identifier of an authorization, the hash of its `AuthorizePullBody` in hex.
+pub type PullAuthorizationId = String;
+
+/// A non-confidential record released from the locked assets, built without randomness,
+/// so that all nodes get the same output
+pub fn released_output(
+    asset: AssetTypeCode,
+    amount: u64,
+    receiver: XfrPublicKey,
+) -> TxOutput {
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        amount,
+        asset.val,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        receiver,
+    );
+    let (record, _, _) = build_blind_asset_record(
+        &mut ChaChaRng::seed_from_u64(0),
+        &PublicParams::default().pc_gens,
+        &template,
+        vec![],
+    );
+    TxOutput {
+        id: None,
+        record,
+        lien: None,
+        memo: None,
+    }
+}
+
+/// An authorization maintained by the ledger
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PullAuthorization {
+    #[allow(missing_docs)]
+    pub payer: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub payee: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    /// Max amount pulled in a period
+    pub limit: u64,
+    /// Length of a period, in blocks
+    pub period: u64,
+    /// Height of the block including the authorization, periods start from it
+    pub start_height: u64,
+    /// Locked and not pulled yet
+    pub remaining: u64,
+    /// Index of the period of the last pull
+    pub last_period: u64,
+    /// Amount pulled in `last_period`
+    pub pulled_in_period: u64,
+}
+
+impl PullAuthorization {
+    /// Amount that can still be pulled in the block at `height`
+    pub fn available(&self, height: u64) -> u64 {
+        let pulled = alt!(
+            self.period_of(height) == self.last_period,
+            self.pulled_in_period,
+            0
+        );
+        self.limit.saturating_sub(pulled).min(self.remaining)
+    }
+
+    #[inline(always)]
+    fn period_of(&self, height: u64) -> u64 {
+        height.saturating_sub(self.start_height) / self.period
+    }
+}
+
+/// Operation body for authorizing a payee
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuthorizePullBody {
+    #[allow(missing_docs)]
+    pub payee: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    /// Max amount pulled in a period
+    #[serde(with = "serde_strz")]
+    pub limit: u64,
+    /// Length of a period, in blocks
+    pub period: u64,
+    /// Must be equal to the non-confidential amount of the asset
+    /// sent to `BLACK_HOLE_PUBKEY_STAKING` in the same transaction
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+impl AuthorizePullBody {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn id(&self) -> PullAuthorizationId {
+        HashOf::new(self).hex()
+    }
+}
+
+/// Operation data for authorizing a payee, signed by the payer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuthorizePull {
+    #[allow(missing_docs)]
+    pub body: AuthorizePullBody,
+    /// The payer
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<AuthorizePullBody>,
+}
+
+impl AuthorizePull {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: AuthorizePullBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        AuthorizePull {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for pulling a payment
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PullBody {
+    #[allow(missing_docs)]
+    pub id: PullAuthorizationId,
+    /// The asset, must be the authorized one
+    pub asset: AssetTypeCode,
+    #[allow(missing_docs)]
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for pulling a payment, signed by the payee
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Pull {
+    #[allow(missing_docs)]
+    pub body: PullBody,
+    /// The payee, owner of the new UTXO
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<PullBody>,
+}
+
+impl Pull {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: PullBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        Pull {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for revoking an authorization
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RevokePullBody {
+    #[allow(missing_docs)]
+    pub id: PullAuthorizationId,
+    /// The asset, must be the authorized one
+    pub asset: AssetTypeCode,
+    /// Must be equal to the remaining amount of the authorization
+    #[serde(with = "serde_strz")]
+    pub refund: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for revoking an authorization, signed by the payer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RevokePull {
+    #[allow(missing_docs)]
+    pub body: RevokePullBody,
+    /// The payer, owner of the refund
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<RevokePullBody>,
+}
+
+impl RevokePull {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: RevokePullBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        RevokePull {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operations of pull payments
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PullPaymentOperation {
+    #[allow(missing_docs)]
+    Authorize(AuthorizePull),
+    #[allow(missing_docs)]
+    Pull(Pull),
+    #[allow(missing_docs)]
+    Revoke(RevokePull),
+}
+
+impl PullPaymentOperation {
+    /// The signatures, the replay tokens, and the limits, the periods and the amounts
+    /// of the authorizations and the pulls
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        match self {
+            PullPaymentOperation::Authorize(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                if 0 == i.body.limit || 0 == i.body.period || 0 == i.body.amount {
+                    return Err(eg!("zero limit, period or amount"));
+                }
+                if i.body.payee == i.pubkey {
+                    return Err(eg!("the payer can not be the payee"));
+                }
+            }
+            PullPaymentOperation::Pull(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                if 0 == i.body.amount {
+                    return Err(eg!("zero pull"));
+                }
+            }
+            PullPaymentOperation::Revoke(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Operations on the same authorization can not be included in the same block
+    pub fn conflict_key(&self) -> PullAuthorizationId {
+        match self {
+            PullPaymentOperation::Authorize(i) => i.body.id(),
+            PullPaymentOperation::Pull(i) => i.body.id.clone(),
+            PullPaymentOperation::Revoke(i) => i.body.id.clone(),
+        }
+    }
+
+    /// The UTXO released by the operation
+    pub fn output(&self) -> Option<TxOutput> {
+        match self {
+            PullPaymentOperation::Authorize(_) => None,
+            PullPaymentOperation::Pull(i) => {
+                Some(released_output(i.body.asset, i.body.amount, i.pubkey))
+            }
+            PullPaymentOperation::Revoke(i) if 0 < i.body.refund => {
+                Some(released_output(i.body.asset, i.body.refund, i.pubkey))
+            }
+            PullPaymentOperation::Revoke(_) => None,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get_signer(&self) -> &XfrPublicKey {
+        match self {
+            PullPaymentOperation::Authorize(i) => &i.pubkey,
+            PullPaymentOperation::Pull(i) => &i.pubkey,
+            PullPaymentOperation::Revoke(i) => &i.pubkey,
+        }
+    }
+
+    /// The payee of an authorization, to index it for the payee
+    pub fn get_payee(&self) -> Option<&XfrPublicKey> {
+        match self {
+            PullPaymentOperation::Authorize(i) => Some(&i.body.payee),
+            _ => None,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        match self {
+            PullPaymentOperation::Authorize(i) => {
+                i.body.no_replay_token = no_replay_token
+            }
+            PullPaymentOperation::Pull(i) => i.body.no_replay_token = no_replay_token,
+            PullPaymentOperation::Revoke(i) => i.body.no_replay_token = no_replay_token,
+        }
+    }
+}
+
+/// The state of pull payments, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PullPaymentState {
+    /// Active authorizations, revoked ones are removed
+    pub authorizations: BTreeMap<PullAuthorizationId, PullAuthorization>,
+}
+
+impl PullPaymentState {
+    /// Check an operation against the state
+    pub fn check(&self, op: &PullPaymentOperation, height: u64) -> Result<()> {
+        match op {
+            PullPaymentOperation::Authorize(i) => {
+                if self.authorizations.contains_key(&i.body.id()) {
+                    return Err(eg!("authorization exists"));
+                }
+            }
+            PullPaymentOperation::Pull(i) => {
+                let auth = self.get(&i.body.id).c(d!())?;
+                if auth.payee != i.pubkey || auth.asset != i.body.asset {
+                    return Err(eg!("not the authorized payee or asset"));
+                }
+                if i.body.amount > auth.available(height) {
+                    return Err(eg!(format!(
+                        "at most {} can be pulled now",
+                        auth.available(height)
+                    )));
+                }
+            }
+            PullPaymentOperation::Revoke(i) => {
+                let auth = self.get(&i.body.id).c(d!())?;
+                if auth.payer != i.pubkey || auth.asset != i.body.asset {
+                    return Err(eg!("not the payer or the authorized asset"));
+                }
+                if auth.remaining != i.body.refund {
+                    return Err(eg!(format!("the refund must be {}", auth.remaining)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply an operation checked by `check`
+    pub fn apply(&mut self, op: PullPaymentOperation, height: u64) {
+        match op {
+            PullPaymentOperation::Authorize(i) => {
+                let id = i.body.id();
+                let b = i.body;
+                self.authorizations.insert(
+                    id,
+                    PullAuthorization {
+                        payer: i.pubkey,
+                        payee: b.payee,
+                        asset: b.asset,
+                        limit: b.limit,
+                        period: b.period,
+                        start_height: height,
+                        remaining: b.amount,
+                        last_period: 0,
+                        pulled_in_period: 0,
+                    },
+                );
+            }
+            PullPaymentOperation::Pull(i) => {
+                if let Some(auth) = self.authorizations.get_mut(&i.body.id) {
+                    let period = auth.period_of(height);
+                    if period != auth.last_period {
+                        auth.last_period = period;
+                        auth.pulled_in_period = 0;
+                    }
+                    auth.pulled_in_period += i.body.amount;
+                    auth.remaining -= i.body.amount;
+                }
+            }
+            PullPaymentOperation::Revoke(i) => {
+                self.authorizations.remove(&i.body.id);
+            }
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get(&self, id: &str) -> Result<&PullAuthorization> {
+        self.authorizations
+            .get(id)
+            .c(d!(format!("unknown authorization: {}", id)))
+    }
+}
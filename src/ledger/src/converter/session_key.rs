@@ -0,0 +1,436 @@
+//!
+//! # Session keys
+//!
+//! Delegated signing authority, so that hot services can operate
+//! without holding the main key:
+//!
+//! - `Authorize` allows a session key to sign the transfers of the signer,
+//!   the primary key, up to an allowance of one asset and until an expiry height
+//! - `Revoke` drops the authorization before it expires
+//!
+//! A transfer whose inputs are owned by a key which has not signed it is a delegated
+//! spend, it must be signed by an active session key of the owner instead, and
+//! everything it sends to others, in the asset of the session only, is charged to
+//! the allowance. Its outputs to others must be non-confidential.
+//!
+//! Authorizing a session key again replaces the authorization, resetting its charges.
+//! Sessions are swept at the end of the block of their expiry height.
+//!
+
+use {
+    crate::data_model::{AssetTypeCode, NoReplayToken, TransferAsset},
+    globutils::SignatureOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeMap, BTreeSet},
+    zei::xfr::{
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::{XfrAmount, XfrAssetType},
+    },
+};
+
+/// An authorization maintained by the ledger
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SessionKey {
+    /// The only asset the session key can send
+    pub asset: AssetTypeCode,
+    /// The maximum amount sent by the session key
+    pub allowance: u64,
+    /// The amount sent so far
+    pub spent: u64,
+    /// The last height at which the session key can sign
+    pub expires_at: u64,
+}
+
+impl SessionKey {
+    /// Whether the session key can no longer sign at `height`
+    #[inline(always)]
+    pub fn is_expired(&self, height: u64) -> bool {
+        height > self.expires_at
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn remaining(&self) -> u64 {
+        self.allowance.saturating_sub(self.spent)
+    }
+}
+
+/// Operation body for authorizing a session key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuthorizeSessionBody {
+    #[allow(missing_docs)]
+    pub session: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    #[allow(missing_docs)]
+    #[serde(with = "serde_strz")]
+    pub allowance: u64,
+    /// The last height at which the session key can sign
+    pub expires_at: u64,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for authorizing a session key, signed by the primary key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuthorizeSession {
+    #[allow(missing_docs)]
+    pub body: AuthorizeSessionBody,
+    /// The primary key
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<AuthorizeSessionBody>,
+}
+
+impl AuthorizeSession {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: AuthorizeSessionBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        AuthorizeSession {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for revoking a session key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RevokeSessionBody {
+    #[allow(missing_docs)]
+    pub session: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for revoking a session key, signed by the primary key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RevokeSession {
+    #[allow(missing_docs)]
+    pub body: RevokeSessionBody,
+    /// The primary key
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<RevokeSessionBody>,
+}
+
+impl RevokeSession {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: RevokeSessionBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        RevokeSession {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operations of session keys
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SessionKeyOperation {
+    /// Authorize a session key, or replace its authorization
+    Authorize(AuthorizeSession),
+    #[allow(missing_docs)]
+    Revoke(RevokeSession),
+}
+
+impl SessionKeyOperation {
+    /// The signatures, the replay tokens, and the allowances and the session keys
+    /// of the authorizations
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        match self {
+            SessionKeyOperation::Authorize(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                if 0 == i.body.allowance {
+                    return Err(eg!("zero allowance"));
+                }
+                if i.body.session == i.pubkey {
+                    return Err(eg!("the key can not be its own session key"));
+                }
+            }
+            SessionKeyOperation::Revoke(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Operations on the same session key can not be included in the same block
+    pub fn conflict_key(&self) -> (XfrPublicKey, XfrPublicKey) {
+        (*self.get_signer(), *self.get_session())
+    }
+
+    /// The primary key
+    pub fn get_signer(&self) -> &XfrPublicKey {
+        match self {
+            SessionKeyOperation::Authorize(i) => &i.pubkey,
+            SessionKeyOperation::Revoke(i) => &i.pubkey,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get_session(&self) -> &XfrPublicKey {
+        match self {
+            SessionKeyOperation::Authorize(i) => &i.body.session,
+            SessionKeyOperation::Revoke(i) => &i.body.session,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        match self {
+            SessionKeyOperation::Authorize(i) => {
+                i.body.no_replay_token = no_replay_token
+            }
+            SessionKeyOperation::Revoke(i) => i.body.no_replay_token = no_replay_token,
+        }
+    }
+}
+
+/// The inputs of a transfer owned by a key which has not signed it
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DelegatedSpend {
+    /// The owner of the inputs
+    pub primary: XfrPublicKey,
+    /// The keys which have signed the transfer without owning any of its inputs
+    pub signers: BTreeSet<XfrPublicKey>,
+    /// What the transfer sends to others than the owner
+    #[serde(with = "globutils::serde_pairs")]
+    pub amounts: BTreeMap<AssetTypeCode, u64>,
+}
+
+/// The delegated spends of a transfer, for the owners in `unsigned`,
+/// everything but the outputs back to the owners is sent to others.
+pub fn delegated_spends(
+    trn: &TransferAsset,
+    unsigned: &BTreeSet<XfrPublicKey>,
+) -> Result<Vec<DelegatedSpend>> {
+    let owners = trn
+        .get_owner_addresses()
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    let signers = trn
+        .body_signatures
+        .iter()
+        .map(|s| s.address.key)
+        .filter(|pk| !owners.contains(pk))
+        .collect::<BTreeSet<_>>();
+    if signers.is_empty() {
+        return Err(eg!("an input owner has not signed"));
+    }
+
+    let mut res = vec![];
+    for primary in unsigned.iter() {
+        let mut amounts: BTreeMap<AssetTypeCode, u64> = BTreeMap::new();
+        for o in trn
+            .body
+            .outputs
+            .iter()
+            .filter(|o| o.record.public_key != *primary)
+        {
+            if let (XfrAssetType::NonConfidential(ty), XfrAmount::NonConfidential(am)) =
+                (&o.record.asset_type, &o.record.amount)
+            {
+                let total = amounts.entry(AssetTypeCode { val: *ty }).or_insert(0);
+                *total = total.checked_add(*am).c(d!())?;
+            } else {
+                return Err(eg!("delegated spends must be non-confidential"));
+            }
+        }
+        res.push(DelegatedSpend {
+            primary: *primary,
+            signers: signers.clone(),
+            amounts,
+        });
+    }
+    Ok(res)
+}
+
+/// An amount charged to the allowance of a session key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SessionCharge {
+    #[allow(missing_docs)]
+    pub primary: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub session: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub amount: u64,
+}
+
+/// The state of session keys, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SessionKeyState {
+    /// Session keys by their primary keys
+    pub sessions: BTreeMap<XfrPublicKey, BTreeMap<XfrPublicKey, SessionKey>>,
+    /// The authorizations by their expiry heights,
+    /// stale ones are skipped by the sweep
+    expiries: BTreeMap<u64, Vec<(XfrPublicKey, XfrPublicKey)>>,
+}
+
+impl SessionKeyState {
+    /// Check an operation against the state
+    pub fn check(&self, op: &SessionKeyOperation, height: u64) -> Result<()> {
+        match op {
+            SessionKeyOperation::Authorize(i) => {
+                if i.body.expires_at <= height {
+                    return Err(eg!(format!(
+                        "the expiry height must be above {}",
+                        height
+                    )));
+                }
+            }
+            SessionKeyOperation::Revoke(i) => {
+                self.get(&i.pubkey, &i.body.session).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the delegated spends of a transaction, the amounts are charged
+    /// to the first active session key of the owner among their signers,
+    /// along with `pending`, the charges of the transactions before it in the block.
+    pub fn check_spends(
+        &self,
+        spends: &[DelegatedSpend],
+        pending: &[SessionCharge],
+        height: u64,
+    ) -> Result<Vec<SessionCharge>> {
+        let mut charges: Vec<SessionCharge> = vec![];
+        for s in spends.iter() {
+            let (session, key) = self
+                .sessions
+                .get(&s.primary)
+                .and_then(|sessions| {
+                    s.signers.iter().find_map(|pk| {
+                        sessions
+                            .get(pk)
+                            .filter(|k| !k.is_expired(height))
+                            .map(|k| (*pk, k))
+                    })
+                })
+                .c(d!("not signed by the owner or one of its session keys"))?;
+
+            if s.amounts.keys().any(|code| *code != key.asset) {
+                return Err(eg!("assets not authorized to the session key"));
+            }
+            let amount = s.amounts.get(&key.asset).copied().unwrap_or(0);
+            let spent = pending
+                .iter()
+                .chain(charges.iter())
+                .filter(|c| c.primary == s.primary && c.session == session)
+                .fold(key.spent, |acc, c| acc.saturating_add(c.amount))
+                .saturating_add(amount);
+            if spent > key.allowance {
+                return Err(eg!(format!(
+                    "the allowance is exceeded: {} > {}",
+                    spent, key.allowance
+                )));
+            }
+
+            charges.push(SessionCharge {
+                primary: s.primary,
+                session,
+                amount,
+            });
+        }
+        Ok(charges)
+    }
+
+    /// Charge the delegated spends of a block to the allowances
+    pub fn charge(&mut self, charges: Vec<SessionCharge>) {
+        for c in charges {
+            if let Some(key) = self
+                .sessions
+                .get_mut(&c.primary)
+                .and_then(|s| s.get_mut(&c.session))
+            {
+                key.spent = key.spent.saturating_add(c.amount);
+            }
+        }
+    }
+
+    /// Apply an operation checked by `check`.
+    pub fn apply(&mut self, op: SessionKeyOperation) {
+        match op {
+            SessionKeyOperation::Authorize(i) => {
+                let b = i.body;
+                self.expiries
+                    .entry(b.expires_at)
+                    .or_default()
+                    .push((i.pubkey, b.session));
+                self.sessions.entry(i.pubkey).or_default().insert(
+                    b.session,
+                    SessionKey {
+                        asset: b.asset,
+                        allowance: b.allowance,
+                        spent: 0,
+                        expires_at: b.expires_at,
+                    },
+                );
+            }
+            SessionKeyOperation::Revoke(i) => {
+                self.remove(&i.pubkey, &i.body.session, None);
+            }
+        }
+    }
+
+    /// Remove the sessions whose expiry height is at or before `height`,
+    /// at the end of the block of `height`.
+    pub fn sweep(&mut self, height: u64) {
+        let pending = self.expiries.split_off(&height.saturating_add(1));
+        for (h, keys) in std::mem::replace(&mut self.expiries, pending) {
+            for (primary, session) in keys {
+                self.remove(&primary, &session, Some(h));
+            }
+        }
+    }
+
+    // remove a session, if it expires at `expires_at` when specified
+    fn remove(
+        &mut self,
+        primary: &XfrPublicKey,
+        session: &XfrPublicKey,
+        expires_at: Option<u64>,
+    ) {
+        if let Some(sessions) = self.sessions.get_mut(primary) {
+            if let Some(key) = sessions.get(session) {
+                if expires_at.map_or(true, |h| h == key.expires_at) {
+                    sessions.remove(session);
+                }
+            }
+            if sessions.is_empty() {
+                self.sessions.remove(primary);
+            }
+        }
+    }
+
+    /// The session keys of `primary`
+    pub fn get_sessions(
+        &self,
+        primary: &XfrPublicKey,
+    ) -> Option<&BTreeMap<XfrPublicKey, SessionKey>> {
+        self.sessions.get(primary)
+    }
+
+    #[allow(missing_docs)]
+    pub fn get(
+        &self,
+        primary: &XfrPublicKey,
+        session: &XfrPublicKey,
+    ) -> Result<&SessionKey> {
+        self.sessions
+            .get(primary)
+            .and_then(|s| s.get(session))
+            .c(d!("unknown session key"))
+    }
+}
@@ -0,0 +1,424 @@
+//!
+//! # Spending policies
+//!
+//! Rules registered by the owner of a key, enforced by the ledger
+//! on the transfers spending the UTXOs of the key:
+//!
+//! - `limits`, the maximum amounts of each asset sent to others per `period` blocks
+//! - `cosigner`, whose signature is required for the transactions sending more than
+//!   its thresholds, and for the changes of the policy
+//! - `allowed_destinations`, if not empty, the only keys the transfers can send to
+//!
+//! `Set` registers or updates the policy of the signer, `Remove` drops it.
+//! Changes take effect from the next block, at most one of them per key in a block.
+//!
+//! The outputs of a transfer spending inputs of a key are all charged to that key,
+//! except those back to the key itself and the fee, and must be non-confidential
+//! if the policy has limits or thresholds to enforce.
+//!
+
+use {
+    crate::data_model::{
+        AssetTypeCode, NoReplayToken, Operation, Transaction, BLACK_HOLE_PUBKEY,
+    },
+    globutils::SignatureOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeMap, BTreeSet},
+    zei::xfr::{
+        sig::{XfrKeyPair, XfrPublicKey},
+        structs::{XfrAmount, XfrAssetType},
+    },
+};
+
+/// The co-signer of a policy
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CoSigner {
+    #[allow(missing_docs)]
+    pub pubkey: XfrPublicKey,
+    /// Transactions sending more than these amounts must be co-signed
+    #[serde(with = "globutils::serde_pairs")]
+    pub thresholds: BTreeMap<AssetTypeCode, u64>,
+}
+
+/// The rules enforced on the transfers of a key
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SpendingPolicy {
+    /// The maximum amounts sent to others within a period
+    #[serde(with = "globutils::serde_pairs")]
+    pub limits: BTreeMap<AssetTypeCode, u64>,
+    /// The number of blocks of a period of the limits
+    pub period: u64,
+    #[allow(missing_docs)]
+    pub cosigner: Option<CoSigner>,
+    /// If not empty, the only receivers of the transfers
+    pub allowed_destinations: BTreeSet<XfrPublicKey>,
+}
+
+impl SpendingPolicy {
+    // whether the amounts sent to others must be known
+    fn has_amount_rules(&self) -> bool {
+        !self.limits.is_empty()
+            || self
+                .cosigner
+                .as_ref()
+                .map(|c| !c.thresholds.is_empty())
+                .unwrap_or(false)
+    }
+}
+
+/// Operation body for setting the policy of the signer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SetSpendingPolicyBody {
+    #[allow(missing_docs)]
+    pub policy: SpendingPolicy,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for setting a policy, signed by the owner of the key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SetSpendingPolicy {
+    #[allow(missing_docs)]
+    pub body: SetSpendingPolicyBody,
+    /// The key bound to the policy
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<SetSpendingPolicyBody>,
+}
+
+impl SetSpendingPolicy {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: SetSpendingPolicyBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        SetSpendingPolicy {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operation body for removing the policy of the signer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RemoveSpendingPolicyBody {
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for removing a policy, signed by the owner of the key
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RemoveSpendingPolicy {
+    #[allow(missing_docs)]
+    pub body: RemoveSpendingPolicyBody,
+    /// The key bound to the policy
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<RemoveSpendingPolicyBody>,
+}
+
+impl RemoveSpendingPolicy {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: RemoveSpendingPolicyBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        RemoveSpendingPolicy {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Operations of spending policies
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SpendingPolicyOperation {
+    /// Register or update a policy
+    Set(SetSpendingPolicy),
+    #[allow(missing_docs)]
+    Remove(RemoveSpendingPolicy),
+}
+
+impl SpendingPolicyOperation {
+    /// The signatures, the replay tokens, and the periods and the co-signers
+    /// of the new policies
+    pub fn check(&self, no_replay_token: NoReplayToken) -> Result<()> {
+        match self {
+            SpendingPolicyOperation::Set(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+                let p = &i.body.policy;
+                if !p.limits.is_empty() && 0 == p.period {
+                    return Err(eg!("zero period"));
+                }
+                if p.cosigner.as_ref().map(|c| c.pubkey) == Some(i.pubkey) {
+                    return Err(eg!("the key can not be its own co-signer"));
+                }
+            }
+            SpendingPolicyOperation::Remove(i) => {
+                if i.body.no_replay_token != no_replay_token {
+                    return Err(eg!("replay token not match"));
+                }
+                i.signature.verify(&i.pubkey, &i.body).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The key bound to the policy
+    pub fn get_signer(&self) -> &XfrPublicKey {
+        match self {
+            SpendingPolicyOperation::Set(i) => &i.pubkey,
+            SpendingPolicyOperation::Remove(i) => &i.pubkey,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_no_replay_token(&mut self, no_replay_token: NoReplayToken) {
+        match self {
+            SpendingPolicyOperation::Set(i) => i.body.no_replay_token = no_replay_token,
+            SpendingPolicyOperation::Remove(i) => {
+                i.body.no_replay_token = no_replay_token
+            }
+        }
+    }
+}
+
+/// What the transfers of a transaction, or a block, send from a key to others
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Outgoing {
+    /// The non-confidential amounts
+    #[serde(with = "globutils::serde_pairs")]
+    pub amounts: BTreeMap<AssetTypeCode, u64>,
+    #[allow(missing_docs)]
+    pub receivers: BTreeSet<XfrPublicKey>,
+    /// Whether some of the amounts or asset types are confidential
+    pub confidential: bool,
+}
+
+impl Outgoing {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn amount(&self, code: &AssetTypeCode) -> u64 {
+        self.amounts.get(code).copied().unwrap_or(0)
+    }
+
+    /// Add up the outgoing of another transaction
+    pub fn merge(&mut self, other: &Outgoing) {
+        for (code, am) in other.amounts.iter() {
+            let total = self.amounts.entry(*code).or_insert(0);
+            *total = total.saturating_add(*am);
+        }
+        self.receivers.extend(other.receivers.iter().copied());
+        self.confidential |= other.confidential;
+    }
+}
+
+/// The outgoing of the transfers of `txn`, by the owners of their inputs
+pub fn outgoing_of(txn: &Transaction) -> BTreeMap<XfrPublicKey, Outgoing> {
+    let mut res: BTreeMap<XfrPublicKey, Outgoing> = BTreeMap::new();
+    for x in txn.body.operations.iter().filter_map(|op| match op {
+        Operation::TransferAsset(x) => Some(x),
+        _ => None,
+    }) {
+        let owners = x.get_owner_addresses().into_iter().collect::<BTreeSet<_>>();
+        for owner in owners {
+            let out = res.entry(owner).or_default();
+            for o in x.body.outputs.iter().filter(|o| {
+                o.record.public_key != owner && o.record.public_key != *BLACK_HOLE_PUBKEY
+            }) {
+                out.receivers.insert(o.record.public_key);
+                if let (
+                    XfrAssetType::NonConfidential(ty),
+                    XfrAmount::NonConfidential(am),
+                ) = (&o.record.asset_type, &o.record.amount)
+                {
+                    let total =
+                        out.amounts.entry(AssetTypeCode { val: *ty }).or_insert(0);
+                    *total = total.saturating_add(*am);
+                } else {
+                    out.confidential = true;
+                }
+            }
+        }
+    }
+    res
+}
+
+/// A policy registered on chain
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KeyPolicy {
+    #[allow(missing_docs)]
+    pub policy: SpendingPolicy,
+    /// The first height of the current period
+    pub period_start: u64,
+    /// The amounts charged to the limits in the current period
+    #[serde(with = "globutils::serde_pairs")]
+    pub spent: BTreeMap<AssetTypeCode, u64>,
+}
+
+impl KeyPolicy {
+    /// The amount charged to the limit of `code` in the period of `height`
+    pub fn spent_at(&self, code: &AssetTypeCode, height: u64) -> u64 {
+        if height >= self.period_start.saturating_add(self.policy.period) {
+            0
+        } else {
+            self.spent.get(code).copied().unwrap_or(0)
+        }
+    }
+
+    /// The amount that can still be sent at `height` within the limit of `code`
+    pub fn remaining(&self, code: &AssetTypeCode, height: u64) -> Option<u64> {
+        self.policy
+            .limits
+            .get(code)
+            .map(|limit| limit.saturating_sub(self.spent_at(code, height)))
+    }
+}
+
+/// The state of spending policies, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SpendingPolicyState {
+    #[allow(missing_docs)]
+    pub policies: BTreeMap<XfrPublicKey, KeyPolicy>,
+}
+
+impl SpendingPolicyState {
+    /// Check an operation against the state,
+    /// the co-signer of an existing policy must sign its changes.
+    pub fn check(&self, op: &SpendingPolicyOperation, txn: &Transaction) -> Result<()> {
+        if let Some(c) = self
+            .policies
+            .get(op.get_signer())
+            .and_then(|p| p.policy.cosigner.as_ref())
+        {
+            signed_by(txn, &c.pubkey).c(d!("not co-signed"))?;
+        }
+        Ok(())
+    }
+
+    /// Check the transfers of `txn` against the policies of the owners of their inputs,
+    /// `pending` is the outgoing of the transactions before it in the same block.
+    pub fn check_transfers(
+        &self,
+        txn: &Transaction,
+        outgoing: &BTreeMap<XfrPublicKey, Outgoing>,
+        pending: &BTreeMap<XfrPublicKey, Outgoing>,
+        height: u64,
+    ) -> Result<()> {
+        for (owner, out) in outgoing.iter() {
+            let kp = if let Some(kp) = self.policies.get(owner) {
+                kp
+            } else {
+                continue;
+            };
+            let p = &kp.policy;
+
+            if !p.allowed_destinations.is_empty() {
+                if let Some(r) = out
+                    .receivers
+                    .iter()
+                    .find(|r| !p.allowed_destinations.contains(r))
+                {
+                    return Err(eg!(format!(
+                        "{} is not an allowed destination",
+                        globutils::wallet::public_key_to_bech32(r)
+                    )));
+                }
+            }
+
+            if !p.has_amount_rules() {
+                continue;
+            }
+            if out.confidential {
+                return Err(eg!("confidential transfers are not allowed by the policy"));
+            }
+
+            if let Some(c) = p.cosigner.as_ref() {
+                if c.thresholds
+                    .iter()
+                    .any(|(code, threshold)| out.amount(code) > *threshold)
+                {
+                    signed_by(txn, &c.pubkey)
+                        .c(d!("transfers above the threshold must be co-signed"))?;
+                }
+            }
+
+            for (code, limit) in p.limits.iter() {
+                let spent = kp
+                    .spent_at(code, height)
+                    .saturating_add(
+                        pending.get(owner).map(|o| o.amount(code)).unwrap_or(0),
+                    )
+                    .saturating_add(out.amount(code));
+                if spent > *limit {
+                    return Err(eg!(format!(
+                        "the limit of {} is exceeded: {} > {}",
+                        code.to_base64(),
+                        spent,
+                        limit
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Charge the outgoing of a block to the limits,
+    /// `height` is the height of the block.
+    pub fn charge(&mut self, outgoing: &BTreeMap<XfrPublicKey, Outgoing>, height: u64) {
+        for (owner, out) in outgoing.iter() {
+            if let Some(kp) = self.policies.get_mut(owner) {
+                if height >= kp.period_start.saturating_add(kp.policy.period) {
+                    kp.period_start = height;
+                    kp.spent.clear();
+                }
+                for code in kp.policy.limits.keys() {
+                    let am = out.amount(code);
+                    if 0 < am {
+                        let spent = kp.spent.entry(*code).or_insert(0);
+                        *spent = spent.saturating_add(am);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply an operation checked by `check`
+    pub fn apply(&mut self, op: SpendingPolicyOperation, height: u64) {
+        match op {
+            SpendingPolicyOperation::Set(i) => {
+                // the charges of the current period are kept
+                if let Some(kp) = self.policies.get_mut(&i.pubkey) {
+                    kp.policy = i.body.policy;
+                } else {
+                    self.policies.insert(
+                        i.pubkey,
+                        KeyPolicy {
+                            policy: i.body.policy,
+                            period_start: height,
+                            spent: BTreeMap::new(),
+                        },
+                    );
+                }
+            }
+            SpendingPolicyOperation::Remove(i) => {
+                self.policies.remove(&i.pubkey);
+            }
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn get(&self, pubkey: &XfrPublicKey) -> Result<&KeyPolicy> {
+        self.policies.get(pubkey).c(d!("no spending policy"))
+    }
+}
+
+fn signed_by(txn: &Transaction, pubkey: &XfrPublicKey) -> Result<()> {
+    txn.check_has_signature(pubkey)
+        .or_else(|_| txn.check_has_signature_from_map(pubkey))
+        .c(d!())
+}
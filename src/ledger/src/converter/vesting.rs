@@ -0,0 +1,230 @@
+//!
+//! # Vesting schedules
+//!
+//! Outputs of an issuance can carry a vesting schedule, made of tranches each
+//! vesting linearly from its start height to its end height, nothing of it before
+//! its cliff height. The amount of an output which has not vested yet is locked.
+//!
+//! The ledger keeps the schedules of the unspent outputs. A transfer spending
+//! outputs with locked amounts must carry them over: per asset, the first
+//! non-confidential output of the transfer covering the amounts locked in its inputs,
+//! not burnt, inherits their schedules. It can be owned by anyone, the locked
+//! amounts follow the assets.
+//!
+//! Outputs with schedules can not be spent within the transaction creating them.
+//!
+
+use {
+    crate::data_model::{
+        AssetTypeCode, Operation, TxnEffect, TxoRef, TxoSID, BLACK_HOLE_PUBKEY,
+        BLACK_HOLE_PUBKEY_STAKING,
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeMap, BTreeSet},
+    zei::xfr::structs::{XfrAmount, XfrAssetType},
+};
+
+/// A part of a schedule, all heights are tendermint heights
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Tranche {
+    #[allow(missing_docs)]
+    #[serde(with = "serde_strz")]
+    pub amount: u64,
+    /// The height from which the tranche vests linearly
+    pub start: u64,
+    /// Nothing vests before this height
+    pub cliff: u64,
+    /// The height at which the whole tranche has vested
+    pub end: u64,
+}
+
+impl Tranche {
+    #[allow(missing_docs)]
+    pub fn check(&self) -> Result<()> {
+        if 0 == self.amount {
+            return Err(eg!("empty tranche"));
+        }
+        if self.start > self.cliff || self.cliff > self.end || self.start == self.end {
+            return Err(eg!(
+                "the heights of a tranche must be: start <= cliff <= end, start < end"
+            ));
+        }
+        Ok(())
+    }
+
+    /// The amount vested at `height`
+    pub fn vested(&self, height: u64) -> u64 {
+        if height < self.cliff {
+            0
+        } else if height >= self.end {
+            self.amount
+        } else {
+            (self.amount as u128 * (height - self.start) as u128
+                / (self.end - self.start) as u128) as u64
+        }
+    }
+
+    /// The amount locked at `height`
+    #[inline(always)]
+    pub fn locked(&self, height: u64) -> u64 {
+        self.amount - self.vested(height)
+    }
+}
+
+/// The vesting schedule of an output
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VestingSchedule {
+    #[allow(missing_docs)]
+    pub tranches: Vec<Tranche>,
+}
+
+impl VestingSchedule {
+    /// Check the schedule of an issued output of `amount`.
+    pub fn check(&self, amount: u64) -> Result<()> {
+        if self.tranches.is_empty() {
+            return Err(eg!("empty schedule"));
+        }
+        let mut total: u64 = 0;
+        for t in self.tranches.iter() {
+            t.check().c(d!())?;
+            total = total.checked_add(t.amount).c(d!("overflow"))?;
+        }
+        if total > amount {
+            return Err(eg!("the tranches exceed the amount of the output"));
+        }
+        Ok(())
+    }
+
+    /// The amount locked at `height`
+    pub fn locked(&self, height: u64) -> u64 {
+        self.tranches.iter().map(|t| t.locked(height)).sum()
+    }
+}
+
+/// The state of vesting schedules, maintained by the ledger.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VestingState {
+    /// Schedules of the unspent outputs
+    #[serde(with = "globutils::serde_pairs")]
+    pub schedules: BTreeMap<TxoSID, VestingSchedule>,
+}
+
+impl VestingState {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get(&self, sid: &TxoSID) -> Option<&VestingSchedule> {
+        self.schedules.get(sid)
+    }
+
+    /// Check the transfers of a transaction against the schedules of their inputs.
+    ///
+    /// Returns the schedules of the new outputs, by their indices in `txe.txos`,
+    /// the issued ones first.
+    pub fn check_transfers(
+        &self,
+        txe: &TxnEffect,
+        height: u64,
+    ) -> Result<Vec<(usize, VestingSchedule)>> {
+        let mut res = txe.vesting_outputs.clone();
+        let mut scheduled = res.iter().map(|(i, _)| *i).collect::<BTreeSet<_>>();
+
+        let transfers = txe.txn.body.operations.iter().filter_map(|op| match op {
+            Operation::TransferAsset(i) => Some(i),
+            _ => None,
+        });
+        for (trn, offset) in transfers.zip(txe.transfer_txo_offsets.iter()) {
+            let mut locked: BTreeMap<AssetTypeCode, (u64, Vec<Tranche>)> =
+                BTreeMap::new();
+            for (inp, record) in
+                trn.body.inputs.iter().zip(trn.body.transfer.inputs.iter())
+            {
+                let schedule = match inp {
+                    TxoRef::Relative(offs) => {
+                        let ix = offset.checked_sub(1 + *offs as usize).c(d!())?;
+                        if scheduled.contains(&ix) {
+                            return Err(eg!(
+                                "outputs with schedules can not be spent in the same transaction"
+                            ));
+                        }
+                        continue;
+                    }
+                    TxoRef::Absolute(sid) => match self.schedules.get(sid) {
+                        Some(s) => s,
+                        None => continue,
+                    },
+                    TxoRef::Pending(r) => {
+                        let sid = txe
+                            .resolved_inputs
+                            .iter()
+                            .find(|(i, _)| i == r)
+                            .map(|(_, sid)| sid)
+                            .c(d!("unresolved input"))?;
+                        match self.schedules.get(sid) {
+                            Some(s) => s,
+                            None => continue,
+                        }
+                    }
+                };
+                let tranches = schedule
+                    .tranches
+                    .iter()
+                    .filter(|t| 0 < t.locked(height))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if tranches.is_empty() {
+                    continue;
+                }
+                let code = match record.asset_type {
+                    XfrAssetType::NonConfidential(val) => AssetTypeCode { val },
+                    XfrAssetType::Confidential(_) => {
+                        return Err(eg!("confidential outputs can not vest"));
+                    }
+                };
+                let l = locked.entry(code).or_insert_with(|| (0, vec![]));
+                l.0 = l.0.saturating_add(schedule.locked(height));
+                l.1.extend(tranches);
+            }
+
+            for (code, (amount, tranches)) in locked {
+                let idx = trn
+                    .body
+                    .outputs
+                    .iter()
+                    .position(|o| {
+                        let r = &o.record;
+                        let covers = match (&r.asset_type, &r.amount) {
+                            (
+                                XfrAssetType::NonConfidential(val),
+                                XfrAmount::NonConfidential(am),
+                            ) => *val == code.val && *am >= amount,
+                            _ => false,
+                        };
+                        covers
+                            && r.public_key != *BLACK_HOLE_PUBKEY
+                            && r.public_key != *BLACK_HOLE_PUBKEY_STAKING
+                    })
+                    .c(d!(format!(
+                        "{} locked by vesting schedules must be carried over",
+                        amount
+                    )))?;
+                scheduled.insert(offset + idx);
+                res.push((offset + idx, VestingSchedule { tranches }));
+            }
+        }
+
+        Ok(res)
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn insert(&mut self, sid: TxoSID, schedule: VestingSchedule) {
+        self.schedules.insert(sid, schedule);
+    }
+
+    /// Drop the schedule of a spent output.
+    #[inline(always)]
+    pub fn remove(&mut self, sid: &TxoSID) {
+        self.schedules.remove(sid);
+    }
+}
@@ -1,9 +1,28 @@
 use {
     crate::{
+        converter::{
+            designated_transfer::DesignatedTransferOperation,
+            ibc::IbcOperation,
+            kv::KvOperation,
+            notary::AnchorHash,
+            payment_stream::PaymentStreamOperation,
+            prism::{
+                verify_signatures, PrismDeposit, PrismWithdraw, PrismWithdrawBody,
+                UpdatePrismAuthorities, UpdatePrismAuthoritiesBody,
+            },
+            pull_payment::PullPaymentOperation,
+            session_key::{
+                delegated_spends, DelegatedSpend, SessionCharge, SessionKeyOperation,
+            },
+            spending_policy::{outgoing_of, Outgoing, SpendingPolicyOperation},
+            vesting::VestingSchedule,
+        },
         data_model::{
-            AssetType, AssetTypeCode, DefineAsset, IssueAsset, IssuerPublicKey, Memo,
-            NoReplayToken, Operation, Transaction, TransferAsset, TransferType,
-            TxOutput, TxnTempSID, TxoRef, TxoSID, UpdateMemo,
+            cred_issuer_id, AssetMetadata, AssetType, AssetTypeCode, DefineAsset,
+            IssueAsset, IssuerPublicKey, Memo, NoReplayToken, Operation, PendingTxoRef,
+            Transaction, TransferAsset, TransferType, TxOutput, TxnTempSID, TxoRef,
+            TxoSID, UpdateAssetMirror, UpdateAssetMirrorBody, UpdateMemo,
+            UpdateRevocation, UpdateTracerKey, BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
         },
         staking::{
             self,
@@ -11,19 +30,22 @@ use {
                 claim::ClaimOps, delegation::DelegationOps,
                 fra_distribution::FraDistributionOps, governance::GovernanceOps,
                 replace_staker::ReplaceStakerOps, undelegation::UnDelegationOps,
-                update_staker::UpdateStakerOps, update_validator::UpdateValidatorOps,
+                unjail::UnjailOps, update_staker::UpdateStakerOps,
+                update_validator::UpdateValidatorOps,
             },
         },
     },
+    fp_types::crypto::MultiSigner,
     globutils::HashOf,
     lazy_static::lazy_static,
     parking_lot::Mutex,
     rand_chacha::{ChaCha20Rng, ChaChaRng},
     rand_core::SeedableRng,
     ruc::*,
-    serde::Serialize,
+    serde::{Deserialize, Serialize},
     std::{
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+        fmt,
         sync::Arc,
     },
     zei::{
@@ -32,7 +54,7 @@ use {
         xfr::{
             lib::verify_xfr_body,
             sig::XfrPublicKey,
-            structs::{XfrAmount, XfrAssetType},
+            structs::{TracingPolicies, XfrAmount, XfrAssetType},
         },
     },
 };
@@ -44,6 +66,76 @@ lazy_static! {
         Arc::new(Mutex::new(PublicParams::default()));
 }
 
+/// Classes of the reasons rejecting a transaction, the ones found by
+/// `TxnEffect::check_stateless` are the `code` of a rejected CheckTx.
+///
+/// `1` and `2` are taken by the generic and the EVM rejections of CheckTx.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TxnCheckCode {
+    /// A signature is invalid, or a required one is missing
+    BadSignature = 3,
+    /// Inputs and outputs of a transfer do not balance, or its proofs are invalid
+    Unbalanced = 4,
+    /// The replay token of an operation differs from the one of the transaction
+    ReplayTokenMismatch = 5,
+    /// Any other invalid operation
+    InvalidOperation = 6,
+    /// Invalid against the ledger state, e.g. spending outputs already spent
+    InvalidState = 7,
+    /// A transaction whose outputs it spends is rejected
+    ParentRejected = 8,
+    /// The transactions whose outputs it spends are not committed in time
+    ParentTimeout = 9,
+    /// Refused by the mempool of the node
+    NotForwarded = 10,
+    /// Past its expiry height, see `TransactionBody::expires_at`
+    Expired = 11,
+    /// Spends an output already spent by a transaction in the mempool
+    DoubleSpend = 12,
+}
+
+impl TxnCheckCode {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+}
+
+/// The reason rejecting a transaction, machine-readable
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TxnCheckError {
+    #[allow(missing_docs)]
+    pub code: TxnCheckCode,
+    /// The index of the offending operation, if it is known
+    #[serde(default)]
+    pub op_index: Option<usize>,
+    /// Details of the error
+    pub msg: String,
+}
+
+impl TxnCheckError {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(code: TxnCheckCode, op_index: Option<usize>, msg: String) -> Self {
+        TxnCheckError {
+            code,
+            op_index,
+            msg,
+        }
+    }
+}
+
+impl fmt::Display for TxnCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:?}({})] ", self.code, self.code.code())?;
+        if let Some(idx) = self.op_index {
+            write!(f, "operation {}: ", idx)?;
+        }
+        write!(f, "{}", self.msg)
+    }
+}
+
 /// Check operations in the context of a tx, partially.
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct TxnEffect {
@@ -53,6 +145,11 @@ pub struct TxnEffect {
     pub txos: Vec<Option<TxOutput>>,
     /// Which TXOs this consumes
     pub input_txos: HashMap<TxoSID, TxOutput>,
+    /// Which outputs of pending transactions this consumes,
+    /// moved to `input_txos` when this is applied
+    pub pending_inputs: Vec<(PendingTxoRef, TxOutput)>,
+    /// The addresses the `pending_inputs` have been resolved to
+    pub resolved_inputs: Vec<(PendingTxoRef, TxoSID)>,
     /// List of internally-spent TXOs. This does not include input txos;
     pub internally_spent_txos: Vec<TxOutput>,
     /// Which new asset types this defines
@@ -70,11 +167,23 @@ pub struct TxnEffect {
     pub confidential_issuance_types: HashSet<AssetTypeCode>,
     /// Non-confidential asset types involved in confidential transfers
     pub confidential_transfer_inputs: HashSet<AssetTypeCode>,
+    /// Tracing policies attached to the non-confidential-type records of transfers,
+    /// in the form of `(asset type, non-confidential amount, policies)`, the amount
+    /// is `None` if confidential, the record is traced whatever the tracing threshold
+    pub transfer_tracing_policies: Vec<(AssetTypeCode, Option<u64>, TracingPolicies)>,
 
     /// Asset types involved in this tx
     pub asset_types_involved: HashSet<AssetTypeCode>,
     /// Memo updates
     pub memo_updates: Vec<(AssetTypeCode, XfrPublicKey, Memo)>,
+    /// Revocation list updates, keyed by `cred_issuer_id`
+    pub revocation_updates: HashMap<String, UpdateRevocation>,
+    /// Tracer key rotations
+    pub tracer_key_updates: Vec<UpdateTracerKey>,
+    /// Updates of the mirrors of assets on external chains
+    pub mirror_updates: Vec<UpdateAssetMirror>,
+    /// Revealed credential attributes, in the form of `(cred_issuer_id, attributes)`
+    pub identity_disclosures: Vec<(String, Vec<(String, Vec<u8>)>)>,
 
     /// Staking operations
     pub delegations: Vec<DelegationOps>,
@@ -92,6 +201,42 @@ pub struct TxnEffect {
     pub update_stakers: Vec<UpdateStakerOps>,
     /// replace staker operations
     pub replace_stakers: Vec<ReplaceStakerOps>,
+    /// Staking operations
+    pub unjails: Vec<UnjailOps>,
+
+    /// Prism deposits
+    pub prism_deposits: Vec<PrismDeposit>,
+    /// Prism withdrawals
+    pub prism_withdrawals: Vec<PrismWithdraw>,
+    /// Update of the prism bridge authorities
+    pub prism_authorities_update: Option<UpdatePrismAuthorities>,
+
+    /// Cross-chain operations
+    pub ibc_ops: Vec<IbcOperation>,
+    /// Operations of pull payments
+    pub pull_payment_ops: Vec<PullPaymentOperation>,
+    /// Operations of payment streams
+    pub payment_stream_ops: Vec<PaymentStreamOperation>,
+    /// Operations of designated transfers
+    pub designated_transfer_ops: Vec<DesignatedTransferOperation>,
+    /// Operations of spending policies
+    pub spending_policy_ops: Vec<SpendingPolicyOperation>,
+    /// What the transfers send from each owner of their inputs to others,
+    /// checked against the spending policies
+    pub outgoing: BTreeMap<XfrPublicKey, Outgoing>,
+    /// Operations of session keys
+    pub session_key_ops: Vec<SessionKeyOperation>,
+    /// Transfers signed by session keys for the owners of their inputs,
+    /// checked against the allowances
+    pub delegated_spends: Vec<DelegatedSpend>,
+    /// Key-value operations
+    pub kv_ops: Vec<KvOperation>,
+    /// Hashes to anchor
+    pub anchor_hashes: Vec<AnchorHash>,
+    /// Vesting schedules of the issued outputs, by their indices in `txos`
+    pub vesting_outputs: Vec<(usize, VestingSchedule)>,
+    /// The index in `txos` of the first output of each transfer
+    pub transfer_txo_offsets: Vec<usize>,
 }
 
 impl TxnEffect {
@@ -118,14 +263,40 @@ impl TxnEffect {
     /// exist unspent in the ledger and correspond to the correct
     /// TxOutput).
     pub fn compute_effect(txn: Transaction) -> Result<TxnEffect> {
+        Self::check_stateless(txn).map_err(|e| eg!(e))
+    }
+
+    /// Same as `compute_effect`, with the error classified by `TxnCheckCode`.
+    ///
+    /// Nothing here depends on the ledger state, so the submission server
+    /// and CheckTx call it to reject malformed transactions before admission.
+    pub fn check_stateless(
+        txn: Transaction,
+    ) -> std::result::Result<TxnEffect, TxnCheckError> {
         let mut te = TxnEffect::default();
         let mut txo_count: usize = 0;
+        // the operation being checked, `None` for the checks on the whole transaction
+        let mut op_index = None;
+
+        macro_rules! check {
+            ($code: ident, $res: expr) => {
+                $res.c(d!()).map_err(|e| {
+                    TxnCheckError::new(TxnCheckCode::$code, op_index, e.to_string())
+                })?
+            };
+        }
+
+        for (idx, op) in txn.body.operations.iter().enumerate() {
+            op_index = Some(idx);
 
-        for op in txn.body.operations.iter() {
             macro_rules! check_nonce {
                 ($i: expr) => {
                     if $i.get_nonce() != txn.body.no_replay_token {
-                        return Err(eg!(("nonce does not match")));
+                        return Err(TxnCheckError::new(
+                            TxnCheckCode::ReplayTokenMismatch,
+                            op_index,
+                            "nonce does not match".to_owned(),
+                        ));
                     }
                 };
             }
@@ -138,33 +309,46 @@ impl TxnEffect {
                     });
                 }
                 Operation::TransferAsset(trn) => {
-                    te.add_transfer_asset(trn, &mut txo_count).c(d!())?;
+                    let unsigned = check!(BadSignature, check_transfer_signatures(trn));
+                    check!(Unbalanced, check_transfer_balance(trn));
+                    te.transfer_txo_offsets.push(txo_count);
+                    check!(InvalidOperation, te.add_transfer_asset(trn, &mut txo_count));
+                    if !unsigned.is_empty() {
+                        let spends =
+                            check!(BadSignature, delegated_spends(trn, &unsigned));
+                        te.delegated_spends.extend(spends);
+                    }
                 }
                 Operation::Claim(i) => {
                     check_nonce!(i);
-                    i.verify().c(d!())?;
+                    check!(BadSignature, i.verify());
                     te.claims.push(i.clone());
                 }
                 Operation::Delegation(i) => {
                     check_nonce!(i);
-                    i.verify().c(d!())?;
+                    check!(BadSignature, i.verify());
                     te.delegations.push(i.clone());
                 }
                 Operation::UnDelegation(i) => {
                     check_nonce!(i);
-                    i.verify().c(d!())?;
+                    check!(BadSignature, i.verify());
                     te.undelegations.push(i.as_ref().clone());
                 }
                 Operation::UpdateStaker(i) => {
                     check_nonce!(i);
-                    i.verify().c(d!())?;
+                    check!(BadSignature, i.verify());
                     te.update_stakers.push(i.clone());
                 }
                 Operation::ReplaceStaker(i) => {
                     check_nonce!(i);
-                    i.verify().c(d!())?;
+                    check!(BadSignature, i.verify());
                     te.replace_stakers.push(i.clone());
                 }
+                Operation::Unjail(i) => {
+                    check_nonce!(i);
+                    check!(BadSignature, i.verify());
+                    te.unjails.push(i.clone());
+                }
                 Operation::UpdateValidator(i) => {
                     check_nonce!(i);
                     // Only one update is allowed at the same height.
@@ -173,17 +357,30 @@ impl TxnEffect {
                         .insert(i.data.height, i.clone())
                         .is_some()
                     {
-                        return Err(eg!("dup entries"));
+                        return Err(TxnCheckError::new(
+                            TxnCheckCode::InvalidOperation,
+                            op_index,
+                            "dup entries".to_owned(),
+                        ));
                     }
                 }
                 Operation::DefineAsset(def) => {
-                    te.add_define_asset(def).c(d!())?;
+                    check!(InvalidOperation, te.add_define_asset(def));
                 }
                 Operation::IssueAsset(iss) => {
-                    te.add_issue_asset(iss, &mut txo_count).c(d!())?;
+                    check!(InvalidOperation, te.add_issue_asset(iss, &mut txo_count));
                 }
                 Operation::UpdateMemo(update_memo) => {
-                    te.add_update_memo(&txn, update_memo).c(d!())?;
+                    check!(InvalidOperation, te.add_update_memo(&txn, update_memo));
+                }
+                Operation::UpdateRevocation(i) => {
+                    check!(InvalidOperation, te.add_update_revocation(&txn, i));
+                }
+                Operation::UpdateTracerKey(i) => {
+                    check!(InvalidOperation, te.add_update_tracer_key(&txn, i));
+                }
+                Operation::UpdateAssetMirror(i) => {
+                    check!(InvalidOperation, te.add_update_asset_mirror(&txn, i));
                 }
                 Operation::Governance(i) => {
                     check_nonce!(i);
@@ -196,8 +393,77 @@ impl TxnEffect {
                 Operation::ConvertAccount(i) => {
                     check_nonce!(i)
                 }
+                Operation::PrismDeposit(i) => {
+                    check!(InvalidOperation, te.add_prism_deposit(&txn, i));
+                }
+                Operation::PrismWithdraw(i) => {
+                    check!(InvalidOperation, te.add_prism_withdraw(i, &mut txo_count));
+                }
+                Operation::UpdatePrismAuthorities(i) => {
+                    check!(InvalidOperation, te.add_update_prism_authorities(i));
+                }
+                Operation::Ibc(i) => {
+                    check!(InvalidOperation, te.add_ibc_operation(&txn, i));
+                }
+                Operation::PullPayment(i) => {
+                    check!(
+                        InvalidOperation,
+                        te.add_pull_payment(&txn, i, &mut txo_count)
+                    );
+                }
+                Operation::PaymentStream(i) => {
+                    check!(
+                        InvalidOperation,
+                        te.add_payment_stream(&txn, i, &mut txo_count)
+                    );
+                }
+                Operation::DesignatedTransfer(i) => {
+                    check!(
+                        InvalidOperation,
+                        te.add_designated_transfer(&txn, i, &mut txo_count)
+                    );
+                }
+                Operation::SpendingPolicy(i) => {
+                    check!(InvalidOperation, te.add_spending_policy(&txn, i));
+                }
+                Operation::SessionKey(i) => {
+                    check!(InvalidOperation, te.add_session_key(&txn, i));
+                }
+                Operation::Kv(i) => {
+                    check!(InvalidOperation, te.add_kv_operation(&txn, i));
+                }
+                Operation::AnchorHash(i) => {
+                    check!(InvalidOperation, te.add_anchor_hash(&txn, i));
+                }
             }
         }
+        op_index = None;
+
+        if !te.prism_deposits.is_empty() {
+            check!(InvalidOperation, te.check_prism_deposits(&txn));
+        }
+        if te
+            .pull_payment_ops
+            .iter()
+            .any(|i| matches!(i, PullPaymentOperation::Authorize(_)))
+        {
+            check!(InvalidOperation, te.check_pull_authorizations(&txn));
+        }
+        if te
+            .payment_stream_ops
+            .iter()
+            .any(|i| matches!(i, PaymentStreamOperation::Create(_)))
+        {
+            check!(InvalidOperation, te.check_stream_deposits(&txn));
+        }
+        if te
+            .designated_transfer_ops
+            .iter()
+            .any(|i| matches!(i, DesignatedTransferOperation::Send(_)))
+        {
+            check!(InvalidOperation, te.check_designated_deposits(&txn));
+        }
+        te.outgoing = outgoing_of(&txn);
 
         te.txn = txn;
         Ok(te)
@@ -208,9 +474,13 @@ impl TxnEffect {
     //         - Fully checked here
     //     2) The token id is available.
     //         - Partially checked here
+    //     3) The metadata in the memo, if any, is valid.
+    //         - Fully checked here
     fn add_define_asset(&mut self, def: &DefineAsset) -> Result<()> {
         // (1)
         def.signature.verify(&def.pubkey.key, &def.body).c(d!())?;
+        // (3)
+        AssetMetadata::check_memo(&def.body.asset.memo).c(d!())?;
 
         let code = def.body.asset.code;
         let token = AssetType {
@@ -244,6 +514,8 @@ impl TxnEffect {
     //      5) The assets in the TxOutputs have a non-confidential
     //         asset type which agrees with the stated asset type.
     //          - Fully checked here
+    //      6) The vesting schedules are valid, on distinct non-confidential outputs.
+    //          - Fully checked here
     fn add_issue_asset(
         &mut self,
         iss: &IssueAsset,
@@ -280,6 +552,20 @@ impl TxnEffect {
         } else {
             self.issuance_keys.insert(code, iss.pubkey);
         }
+        // (6)
+        let mut vesting = BTreeSet::new();
+        for (i, schedule) in iss.body.vesting.iter() {
+            if !vesting.insert(*i) {
+                return Err(eg!("duplicate vesting schedules"));
+            }
+            match iss.body.records.get(*i).map(|(o, _)| o.record.amount) {
+                Some(XfrAmount::NonConfidential(amt)) => schedule.check(amt).c(d!())?,
+                _ => return Err(eg!("vesting outputs must be non-confidential")),
+            }
+            self.vesting_outputs
+                .push((*txo_count + *i, schedule.clone()));
+        }
+
         // Increment amounts
         self.txos.reserve(iss.body.records.len());
         for (output, _) in iss.body.records.iter() {
@@ -294,6 +580,7 @@ impl TxnEffect {
                     id: None,
                     record: output.record.clone(),
                     lien: None,
+                    memo: None,
                 })
             {
                 return Err(eg!());
@@ -320,25 +607,27 @@ impl TxnEffect {
     // An asset transfer is valid iff:
     //     1) The signatures on the body (a) all are valid and (b)
     //        there is a signature for each input key
-    //          - Fully checked here
+    //          - Fully checked in `check_transfer_signatures`
     //     2) The UTXOs (a) exist on the ledger and (b) match the zei transaction.
     //          - Partially checked here -- anything which hasn't
     //            been checked will appear in `input_txos`
     //     3) The zei transaction is valid.
-    //          - Checked here and in check_txn_effects
+    //          - Checked in `check_transfer_balance` and in check_txn_effects
     //     4) Lien assignments match up
     //          - Checked within a transaction here, recorded for
     //            external checks later
     //          - For simplicity, only Standard transfers are allowed
     //            to have lien assignments
+    //     5) Revealed credential attributes are valid
+    //          - Fully checked here
+    //     6) Each record carries the tracer memos of all tracing policies of its asset
+    //          - Memos are checked against the attached policies here,
+    //            attached policies are recorded for checks against the asset later
     fn add_transfer_asset(
         &mut self,
         trn: &TransferAsset,
         txo_count: &mut usize,
     ) -> Result<()> {
-        let params = &mut *PARAMS.lock();
-        let prng = &mut *PRNG.lock();
-
         if trn.body.inputs.len() != trn.body.transfer.inputs.len() {
             return Err(eg!());
         }
@@ -397,29 +686,57 @@ impl TxnEffect {
         match trn.body.transfer_type {
             TransferType::DebtSwap => {}
             TransferType::Standard => {
-                let mut input_keys = HashSet::new();
-                // (1a) all body signatures are valid
-                for sig in &trn.body_signatures {
-                    if !trn.body.verify_body_signature(sig) {
-                        return Err(eg!());
+                // (1) and (3) are checked by `check_transfer_signatures`
+                // and `check_transfer_balance` beforehand
+
+                // (6), records with a confidential asset type are not recorded,
+                // assets with tracing policies can not become confidential
+                let policies = &trn.body.policies;
+                if policies.inputs_tracing_policies.len()
+                    != trn.body.transfer.inputs.len()
+                    || policies.outputs_tracing_policies.len()
+                        != trn.body.transfer.outputs.len()
+                {
+                    return Err(eg!("tracing policies mismatch"));
+                }
+                for (record, p) in trn
+                    .body
+                    .transfer
+                    .inputs
+                    .iter()
+                    .zip(policies.inputs_tracing_policies.iter())
+                    .chain(
+                        trn.body
+                            .transfer
+                            .outputs
+                            .iter()
+                            .zip(policies.outputs_tracing_policies.iter()),
+                    )
+                {
+                    if let Some(code) = record.asset_type.get_asset_type() {
+                        let amount = match record.amount {
+                            XfrAmount::NonConfidential(am) => Some(am),
+                            XfrAmount::Confidential(_) => None,
+                        };
+                        self.transfer_tracing_policies.push((
+                            AssetTypeCode { val: code },
+                            amount,
+                            p.clone(),
+                        ));
                     }
-                    input_keys.insert(sig.address.key.zei_to_bytes());
                 }
 
-                // (1b) all input record owners have signed
-                for record in trn.body.transfer.inputs.iter() {
-                    if !input_keys.contains(&record.public_key.zei_to_bytes()) {
-                        return Err(eg!());
+                // (1c) revealed credential attributes are bound to the owners of outputs
+                let mut disclosed = HashSet::new();
+                for d in trn.body.identity_disclosures.iter() {
+                    if !disclosed.insert(d.output_idx) {
+                        return Err(eg!("duplicate identity disclosures"));
                     }
+                    let output = trn.body.transfer.outputs.get(d.output_idx).c(d!())?;
+                    d.verify(&output.public_key).c(d!())?;
+                    self.identity_disclosures
+                        .push((cred_issuer_id(&d.issuer_pub_key), d.attrs.clone()));
                 }
-
-                verify_xfr_body(
-                    prng,
-                    params,
-                    &trn.body.transfer,
-                    &trn.body.policies.to_ref(),
-                )
-                .c(d!())?;
             }
         }
         // (3)
@@ -473,15 +790,42 @@ impl TxnEffect {
                             id: None,
                             record: record.clone(),
                             lien: lien.cloned(),
+                            memo: None,
                         },
                     );
                 }
+                TxoRef::Pending(r) => {
+                    // (2).(a), partially, the rest is checked once resolved
+                    if self.pending_inputs.iter().any(|(i, _)| *i == r) {
+                        return Err(eg!());
+                    }
+
+                    self.pending_inputs.push((
+                        r,
+                        TxOutput {
+                            id: None,
+                            record: record.clone(),
+                            lien: lien.cloned(),
+                            memo: None,
+                        },
+                    ));
+                }
             }
         }
 
         self.txos.reserve(trn.body.transfer.outputs.len());
         let mut conf_transfer = false;
-        for (out, lien) in trn.body.transfer.outputs.iter().zip(lien_outputs) {
+        for ((out, lien), output) in trn
+            .body
+            .transfer
+            .outputs
+            .iter()
+            .zip(lien_outputs)
+            .zip(trn.body.outputs.iter())
+        {
+            if let Some(memo) = output.memo.as_ref() {
+                memo.check().c(d!())?;
+            }
             if let XfrAssetType::Confidential(_) = out.asset_type {
                 conf_transfer = true;
             }
@@ -493,6 +837,7 @@ impl TxnEffect {
                 id: None,
                 record: out.clone(),
                 lien: lien.cloned(),
+                memo: output.memo.clone(),
             }));
             *txo_count += 1;
         }
@@ -511,6 +856,7 @@ impl TxnEffect {
     // 1) The signature is valid.
     // 2) The asset type is updatable (checked later).
     // 3) The signing key is the asset issuer key (checked later).
+    // 4) The metadata in the new memo, if any, is valid.
     fn add_update_memo(
         &mut self,
         txn: &Transaction,
@@ -525,6 +871,8 @@ impl TxnEffect {
             .signature
             .verify(&pk, &update_memo.body)
             .c(d!())?;
+        // 4)
+        AssetMetadata::check_memo(&update_memo.body.new_memo).c(d!())?;
         self.memo_updates.push((
             update_memo.body.asset_type,
             pk,
@@ -533,6 +881,602 @@ impl TxnEffect {
 
         Ok(())
     }
+
+    // A tracer key rotation is valid iff:
+    // 1) The signature is valid.
+    // 2) The signing key is the asset issuer key or the tracing admin key (checked later).
+    // 3) The policy exists, and the rotation takes effect after the current height
+    //    and the last rotation of the policy (checked later).
+    fn add_update_tracer_key(
+        &mut self,
+        txn: &Transaction,
+        update: &UpdateTracerKey,
+    ) -> Result<()> {
+        if txn.body.no_replay_token != update.body.no_replay_token {
+            return Err(eg!("replay token not match"));
+        }
+        // 1)
+        update
+            .signature
+            .verify(&update.pubkey, &update.body)
+            .c(d!())?;
+        self.tracer_key_updates.push(update.clone());
+
+        Ok(())
+    }
+
+    // An asset mirror update is valid iff:
+    // 1) The signature is valid.
+    // 2) The mirror of an asset on each chain is updated at most once,
+    //    and each contract is claimed by at most one asset.
+    // 3) The signing key is the asset issuer key, the contract does not mirror
+    //    other assets, and a removed mirror exists (checked later).
+    fn add_update_asset_mirror(
+        &mut self,
+        txn: &Transaction,
+        update: &UpdateAssetMirror,
+    ) -> Result<()> {
+        if txn.body.no_replay_token != update.body.no_replay_token {
+            return Err(eg!("replay token not match"));
+        }
+        // 1)
+        update
+            .signature
+            .verify(&update.pubkey, &update.body)
+            .c(d!())?;
+        // 2)
+        let u = &update.body;
+        if self.mirror_updates.iter().any(|i| {
+            i.body.chain_id == u.chain_id
+                && (i.body.asset_type == u.asset_type
+                    || (u.contract.is_some() && i.body.contract == u.contract))
+        }) {
+            return Err(eg!("duplicate asset mirror updates"));
+        }
+        self.mirror_updates.push(update.clone());
+
+        Ok(())
+    }
+
+    // A revocation list update is valid iff:
+    // 1) The signature is valid.
+    // 2) Each issuer is updated at most once, no identifier is both revoked and restored,
+    //    and each credential has at most one new expiry.
+    // 3) The signing key is the owner of the list, or the list is new
+    //    and `id_attr` is defined by the issuer (checked later).
+    fn add_update_revocation(
+        &mut self,
+        txn: &Transaction,
+        update: &UpdateRevocation,
+    ) -> Result<()> {
+        if txn.body.no_replay_token != update.body.no_replay_token {
+            return Err(eg!("replay token not match"));
+        }
+        // 1)
+        update
+            .signature
+            .verify(&update.pubkey, &update.body)
+            .c(d!())?;
+        // 2)
+        if update
+            .body
+            .revoke
+            .iter()
+            .any(|id| update.body.restore.contains(id))
+        {
+            return Err(eg!("identifiers revoked and restored at the same time"));
+        }
+        let mut expiries = HashSet::new();
+        if !update
+            .body
+            .expiries
+            .iter()
+            .all(|(id, _)| expiries.insert(id))
+        {
+            return Err(eg!("duplicate credential expiries"));
+        }
+        let id = cred_issuer_id(&update.body.issuer);
+        if self.revocation_updates.insert(id, update.clone()).is_some() {
+            return Err(eg!("duplicate revocation list updates"));
+        }
+
+        Ok(())
+    }
+
+    // A prism deposit is valid iff:
+    // 1) The signature is valid, and the receiver is an account address.
+    // 2) The amount is not zero.
+    // 3) The assets are locked by the transfers of the same transaction,
+    //    which spend inputs owned by the depositor (checked in `check_prism_deposits`).
+    // 4) The asset exists and has no tracing policies (checked later).
+    fn add_prism_deposit(
+        &mut self,
+        txn: &Transaction,
+        deposit: &PrismDeposit,
+    ) -> Result<()> {
+        if txn.body.no_replay_token != deposit.body.no_replay_token {
+            return Err(eg!("replay token not match"));
+        }
+        // 1)
+        deposit
+            .signature
+            .verify(&deposit.pubkey, &deposit.body)
+            .c(d!())?;
+        if let MultiSigner::Xfr(_) = deposit.body.receiver {
+            return Err(eg!("invalid receiver address"));
+        }
+        // 2)
+        if 0 == deposit.body.amount {
+            return Err(eg!("zero deposit"));
+        }
+        self.prism_deposits.push(deposit.clone());
+
+        Ok(())
+    }
+
+    // The prism deposits lock their assets, see `check_locked_deposits`.
+    fn check_prism_deposits(&self, txn: &Transaction) -> Result<()> {
+        check_locked_deposits(
+            txn,
+            |op| matches!(op, Operation::PrismDeposit(_)),
+            self.prism_deposits
+                .iter()
+                .map(|d| (&d.pubkey, d.body.asset, d.body.amount)),
+            "prism deposits",
+        )
+        .c(d!())
+    }
+
+    // A prism withdrawal is valid iff:
+    // 1) All signatures are valid.
+    // 2) The amount is not zero, and the withdrawal appears once in the transaction.
+    // 3) Enough authorities have signed it, it has not been processed,
+    //    and enough assets are locked (checked later).
+    fn add_prism_withdraw(
+        &mut self,
+        withdraw: &PrismWithdraw,
+        txo_count: &mut usize,
+    ) -> Result<()> {
+        // 1)
+        verify_signatures(&withdraw.body, &withdraw.signatures).c(d!())?;
+        // 2)
+        if 0 == withdraw.body.amount {
+            return Err(eg!("zero withdrawal"));
+        }
+        let id = withdraw.body.id();
+        if self.prism_withdrawals.iter().any(|w| w.body.id() == id) {
+            return Err(eg!("duplicate withdrawals"));
+        }
+
+        self.txos.push(Some(withdraw.body.output()));
+        *txo_count += 1;
+        self.prism_withdrawals.push(withdraw.clone());
+
+        Ok(())
+    }
+
+    // An update of the prism authorities is valid iff:
+    // 1) All signatures are valid, and the new authorities are well-formed.
+    // 2) There is at most one update in the transaction.
+    // 3) Enough current authorities have signed it, or the issuer of FRA for the first one,
+    //    and the version is the current one plus one (checked later).
+    fn add_update_prism_authorities(
+        &mut self,
+        update: &UpdatePrismAuthorities,
+    ) -> Result<()> {
+        // 1)
+        verify_signatures(&update.body, &update.signatures).c(d!())?;
+        update.body.authorities.check().c(d!())?;
+        // 2)
+        if self.prism_authorities_update.is_some() {
+            return Err(eg!("duplicate updates of the prism authorities"));
+        }
+        self.prism_authorities_update = Some(update.clone());
+
+        Ok(())
+    }
+
+    // A cross-chain operation is valid iff:
+    // 1) It passes `IbcOperation::check`.
+    // 2) It does not conflict with the other ones of the transaction.
+    // 3) It is valid against the light clients and the IBC store (checked later).
+    fn add_ibc_operation(&mut self, txn: &Transaction, op: &IbcOperation) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        if let Some(key) = op.conflict_key() {
+            if self
+                .ibc_ops
+                .iter()
+                .any(|i| i.conflict_key().as_ref() == Some(&key))
+            {
+                return Err(eg!(format!("conflicting cross-chain operations: {}", key)));
+            }
+        }
+        self.ibc_ops.push(op.clone());
+
+        Ok(())
+    }
+
+    // An operation of pull payments is valid iff:
+    // 1) It passes `PullPaymentOperation::check`.
+    // 2) No other operations of the transaction are on the same authorization.
+    // 3) The assets of an authorization are locked by the transfers of the same
+    //    transaction, which spend inputs owned by the payer
+    //    (checked in `check_pull_authorizations`).
+    // 4) It is valid against the authorizations of the ledger,
+    //    and the asset has no tracing policies (checked later).
+    fn add_pull_payment(
+        &mut self,
+        txn: &Transaction,
+        op: &PullPaymentOperation,
+        txo_count: &mut usize,
+    ) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        let key = op.conflict_key();
+        if self
+            .pull_payment_ops
+            .iter()
+            .any(|i| i.conflict_key() == key)
+        {
+            return Err(eg!(format!(
+                "duplicate operations on authorization {}",
+                key
+            )));
+        }
+
+        if let Some(o) = op.output() {
+            self.txos.push(Some(o));
+            *txo_count += 1;
+        }
+        self.pull_payment_ops.push(op.clone());
+
+        Ok(())
+    }
+
+    // The pull authorizations lock their assets, see `check_locked_deposits`.
+    fn check_pull_authorizations(&self, txn: &Transaction) -> Result<()> {
+        check_locked_deposits(
+            txn,
+            |op| {
+                matches!(
+                    op,
+                    Operation::PullPayment(PullPaymentOperation::Authorize(_))
+                )
+            },
+            self.pull_payment_ops.iter().filter_map(|op| match op {
+                PullPaymentOperation::Authorize(i) => {
+                    Some((&i.pubkey, i.body.asset, i.body.amount))
+                }
+                _ => None,
+            }),
+            "pull authorizations",
+        )
+        .c(d!())
+    }
+
+    // An operation of payment streams is valid iff:
+    // 1) It passes `PaymentStreamOperation::check`.
+    // 2) No other operations of the transaction are on the same stream.
+    // 3) The assets of a new stream are locked by the transfers of the same
+    //    transaction, which spend inputs owned by the sender
+    //    (checked in `check_stream_deposits`).
+    // 4) It is valid against the streams of the ledger,
+    //    and the asset has no tracing policies (checked later).
+    fn add_payment_stream(
+        &mut self,
+        txn: &Transaction,
+        op: &PaymentStreamOperation,
+        txo_count: &mut usize,
+    ) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        let key = op.conflict_key();
+        if self
+            .payment_stream_ops
+            .iter()
+            .any(|i| i.conflict_key() == key)
+        {
+            return Err(eg!(format!("duplicate operations on stream {}", key)));
+        }
+
+        if let Some(o) = op.output() {
+            self.txos.push(Some(o));
+            *txo_count += 1;
+        }
+        self.payment_stream_ops.push(op.clone());
+
+        Ok(())
+    }
+
+    // The new payment streams lock their assets, see `check_locked_deposits`.
+    fn check_stream_deposits(&self, txn: &Transaction) -> Result<()> {
+        check_locked_deposits(
+            txn,
+            |op| {
+                matches!(
+                    op,
+                    Operation::PaymentStream(PaymentStreamOperation::Create(_))
+                )
+            },
+            self.payment_stream_ops.iter().filter_map(|op| match op {
+                PaymentStreamOperation::Create(i) => {
+                    Some((&i.pubkey, i.body.asset, i.body.amount))
+                }
+                _ => None,
+            }),
+            "new payment streams",
+        )
+        .c(d!())
+    }
+
+    // An operation of designated transfers is valid iff:
+    // 1) It passes `DesignatedTransferOperation::check`.
+    // 2) No other operations of the transaction are on the same transfer.
+    // 3) The assets of a new transfer are locked by the transfers of the same
+    //    transaction, which spend inputs owned by the sender
+    //    (checked in `check_designated_deposits`).
+    // 4) It is valid against the designated transfers of the ledger,
+    //    and the asset has no tracing policies (checked later).
+    fn add_designated_transfer(
+        &mut self,
+        txn: &Transaction,
+        op: &DesignatedTransferOperation,
+        txo_count: &mut usize,
+    ) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        let key = op.conflict_key();
+        if self
+            .designated_transfer_ops
+            .iter()
+            .any(|i| i.conflict_key() == key)
+        {
+            return Err(eg!(format!(
+                "duplicate operations on designated transfer {}",
+                key
+            )));
+        }
+
+        if let Some(o) = op.output() {
+            self.txos.push(Some(o));
+            *txo_count += 1;
+        }
+        self.designated_transfer_ops.push(op.clone());
+
+        Ok(())
+    }
+
+    // The new designated transfers lock their assets, see `check_locked_deposits`.
+    fn check_designated_deposits(&self, txn: &Transaction) -> Result<()> {
+        check_locked_deposits(
+            txn,
+            |op| {
+                matches!(
+                    op,
+                    Operation::DesignatedTransfer(DesignatedTransferOperation::Send(_))
+                )
+            },
+            self.designated_transfer_ops
+                .iter()
+                .filter_map(|op| match op {
+                    DesignatedTransferOperation::Send(i) => {
+                        Some((&i.pubkey, i.body.asset, i.body.amount))
+                    }
+                    _ => None,
+                }),
+            "new designated transfers",
+        )
+        .c(d!())
+    }
+
+    // An operation of spending policies is valid iff:
+    // 1) It passes `SpendingPolicyOperation::check`.
+    // 2) No other operations of the transaction are on the same key.
+    // 3) It is co-signed if the current policy has a co-signer (checked later).
+    fn add_spending_policy(
+        &mut self,
+        txn: &Transaction,
+        op: &SpendingPolicyOperation,
+    ) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        if self
+            .spending_policy_ops
+            .iter()
+            .any(|i| i.get_signer() == op.get_signer())
+        {
+            return Err(eg!("duplicate operations on a spending policy"));
+        }
+
+        self.spending_policy_ops.push(op.clone());
+
+        Ok(())
+    }
+
+    // An operation of session keys is valid iff:
+    // 1) It passes `SessionKeyOperation::check`.
+    // 2) No other operations of the transaction are on the same session key.
+    fn add_session_key(
+        &mut self,
+        txn: &Transaction,
+        op: &SessionKeyOperation,
+    ) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        let key = op.conflict_key();
+        if self.session_key_ops.iter().any(|i| i.conflict_key() == key) {
+            return Err(eg!("duplicate operations on a session key"));
+        }
+
+        self.session_key_ops.push(op.clone());
+
+        Ok(())
+    }
+
+    // A key-value operation is valid iff:
+    // 1) It passes `KvOperation::check`.
+    // 2) No other operations of the transaction are on the same key.
+    // 3) The fee of the transaction covers the minimal fee
+    //    and the fees of all its key-value operations.
+    // 4) It is valid against the key-value store (checked later).
+    fn add_kv_operation(&mut self, txn: &Transaction, op: &KvOperation) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        let key = op.conflict_key();
+        if self.kv_ops.iter().any(|i| i.conflict_key() == key) {
+            return Err(eg!(format!("duplicate operations on key {}", key)));
+        }
+        // 3)
+        let fee = self
+            .kv_ops
+            .iter()
+            .fold(TX_FEE_MIN + op.fee(), |acc, i| acc + i.fee());
+        if txn.fee() < fee {
+            return Err(eg!(format!("insufficient fee, {} needed", fee)));
+        }
+
+        self.kv_ops.push(op.clone());
+
+        Ok(())
+    }
+
+    // An anchored hash is valid iff:
+    // 1) It passes `AnchorHash::check`.
+    // 2) The same hash is not anchored by the same key twice in the transaction.
+    // 3) It has not been anchored by the key yet (checked later).
+    fn add_anchor_hash(&mut self, txn: &Transaction, op: &AnchorHash) -> Result<()> {
+        // 1)
+        op.check(txn.body.no_replay_token).c(d!())?;
+        // 2)
+        let key = op.conflict_key();
+        if self.anchor_hashes.iter().any(|i| i.conflict_key() == key) {
+            return Err(eg!(format!("duplicate anchors of {}", key)));
+        }
+
+        self.anchor_hashes.push(op.clone());
+
+        Ok(())
+    }
+}
+
+// The operations locking assets, accepted by `allowed`, can only be mixed with
+// transfers, the non-confidential amounts these send to `BLACK_HOLE_PUBKEY_STAKING`
+// must be exactly the ones of the `deposits`, in the form of `(owner, asset, amount)`,
+// and the owner of each deposit must own inputs of the transfers.
+// `what` names the operations in the errors.
+fn check_locked_deposits<'a>(
+    txn: &Transaction,
+    allowed: impl Fn(&Operation) -> bool,
+    deposits: impl Iterator<Item = (&'a XfrPublicKey, AssetTypeCode, u64)>,
+    what: &str,
+) -> Result<()> {
+    let mut owners = HashSet::new();
+    let locked = locked_amounts(txn, &mut owners, allowed)
+        .c(d!(format!("only transfers can be mixed with {}", what)))?;
+
+    let mut deposited: HashMap<AssetTypeCode, u64> = HashMap::new();
+    for (owner, asset, amount) in deposits {
+        if !owners.contains(owner) {
+            return Err(eg!(format!("the owner of {} owns no inputs", what)));
+        }
+        let total = deposited.entry(asset).or_insert(0);
+        *total = total.checked_add(amount).c(d!())?;
+    }
+    if locked != deposited {
+        return Err(eg!(format!(
+            "the amounts of {} mismatch the locked ones",
+            what
+        )));
+    }
+
+    Ok(())
+}
+
+// The non-confidential amounts sent to `BLACK_HOLE_PUBKEY_STAKING` by the transfers
+// of `txn`, the owners of their inputs are collected into `owners`;
+// operations other than transfers must be accepted by `allowed`.
+fn locked_amounts(
+    txn: &Transaction,
+    owners: &mut HashSet<XfrPublicKey>,
+    allowed: impl Fn(&Operation) -> bool,
+) -> Result<HashMap<AssetTypeCode, u64>> {
+    let mut locked: HashMap<AssetTypeCode, u64> = HashMap::new();
+    for op in txn.body.operations.iter() {
+        match op {
+            Operation::TransferAsset(x) => {
+                owners.extend(x.get_owner_addresses());
+                for o in x
+                    .body
+                    .outputs
+                    .iter()
+                    .filter(|o| o.record.public_key == *BLACK_HOLE_PUBKEY_STAKING)
+                {
+                    match (&o.record.asset_type, &o.record.amount) {
+                        (
+                            XfrAssetType::NonConfidential(val),
+                            XfrAmount::NonConfidential(am),
+                        ) => {
+                            let total =
+                                locked.entry(AssetTypeCode { val: *val }).or_insert(0);
+                            *total = total.checked_add(*am).c(d!())?;
+                        }
+                        _ => {
+                            return Err(eg!("confidential records can not be locked"));
+                        }
+                    }
+                }
+            }
+            op if allowed(op) => {}
+            _ => {
+                return Err(eg!("unexpected operation"));
+            }
+        }
+    }
+    Ok(locked)
+}
+
+// (1a) all body signatures are valid,
+// (1b) all input record owners have signed, or session keys have signed for them,
+//      the owners who have not signed are returned as delegated spenders
+fn check_transfer_signatures(trn: &TransferAsset) -> Result<BTreeSet<XfrPublicKey>> {
+    let mut input_keys = HashSet::new();
+    for sig in &trn.body_signatures {
+        if !trn.body.verify_body_signature(sig) {
+            return Err(eg!("invalid body signature"));
+        }
+        input_keys.insert(sig.address.key.zei_to_bytes());
+    }
+
+    Ok(trn
+        .body
+        .transfer
+        .inputs
+        .iter()
+        .filter(|record| !input_keys.contains(&record.public_key.zei_to_bytes()))
+        .map(|record| record.public_key)
+        .collect())
+}
+
+// (3) the zei transaction is valid, amounts of each asset type balance
+fn check_transfer_balance(trn: &TransferAsset) -> Result<()> {
+    let params = &mut *PARAMS.lock();
+    let prng = &mut *PRNG.lock();
+
+    verify_xfr_body(
+        prng,
+        params,
+        &trn.body.transfer,
+        &trn.body.policies.to_ref(),
+    )
+    .c(d!())
 }
 
 /// Check tx in the context of a block, partially.
@@ -562,10 +1506,49 @@ pub struct BlockEffect {
     pub issuance_keys: HashMap<AssetTypeCode, IssuerPublicKey>,
     /// Memo updates
     pub memo_updates: HashMap<AssetTypeCode, Memo>,
+    /// Revocation list updates, keyed by `cred_issuer_id`
+    pub revocation_updates: HashMap<String, UpdateRevocation>,
+    /// Tracer key rotations
+    pub tracer_key_updates: HashMap<AssetTypeCode, UpdateTracerKey>,
+    /// Updates of the mirrors of assets on external chains
+    pub mirror_updates: Vec<UpdateAssetMirrorBody>,
     /// counter for consensus integration; will add to a running count when applied.
     pub pulse_count: u64,
     /// simulator for safety
     pub staking_simulator: staking::Staking,
+    /// Amounts locked by prism deposits
+    pub prism_deposits: HashMap<AssetTypeCode, u64>,
+    /// Prism withdrawals
+    pub prism_withdrawals: Vec<PrismWithdrawBody>,
+    /// Update of the prism bridge authorities
+    pub prism_authorities_update: Option<UpdatePrismAuthoritiesBody>,
+    /// Amounts available for prism withdrawals,
+    /// initialized from the ledger at the beginning of each block
+    pub prism_locked: BTreeMap<AssetTypeCode, u64>,
+    /// Cross-chain operations, in the order they were included
+    pub ibc_ops: Vec<IbcOperation>,
+    /// Operations of pull payments, in the order they were included
+    pub pull_payment_ops: Vec<PullPaymentOperation>,
+    /// Operations of payment streams, in the order they were included
+    pub payment_stream_ops: Vec<PaymentStreamOperation>,
+    /// Operations of designated transfers, in the order they were included
+    pub designated_transfer_ops: Vec<DesignatedTransferOperation>,
+    /// Operations of spending policies, in the order they were included
+    pub spending_policy_ops: Vec<SpendingPolicyOperation>,
+    /// What the transfers of the block send from each owner of their inputs to others
+    pub outgoing: BTreeMap<XfrPublicKey, Outgoing>,
+    /// Operations of session keys, in the order they were included
+    pub session_key_ops: Vec<SessionKeyOperation>,
+    /// Delegated spends charged to the session keys,
+    /// resolved by `LedgerState::apply_transaction`
+    pub session_charges: Vec<SessionCharge>,
+    /// Key-value operations, in the order they were included
+    pub kv_ops: Vec<KvOperation>,
+    /// Hashes to anchor, in the order they were included
+    pub anchor_hashes: Vec<AnchorHash>,
+    /// Vesting schedules of the new outputs by their transactions,
+    /// resolved by `LedgerState::apply_transaction`
+    pub vesting_outputs: Vec<(TxnTempSID, Vec<(usize, VestingSchedule)>)>,
 }
 
 impl BlockEffect {
@@ -612,6 +1595,51 @@ impl BlockEffect {
             self.memo_updates.insert(code, memo);
         }
 
+        for (id, update) in txn_effect.revocation_updates {
+            self.revocation_updates.insert(id, update);
+        }
+
+        for update in txn_effect.tracer_key_updates {
+            self.tracer_key_updates
+                .insert(update.body.asset_type, update);
+        }
+
+        for update in txn_effect.mirror_updates {
+            self.mirror_updates.push(update.body);
+        }
+
+        for d in txn_effect.prism_deposits {
+            let locked = self.prism_deposits.entry(d.body.asset).or_insert(0);
+            *locked = locked.saturating_add(d.body.amount);
+        }
+
+        // the locked amounts have been checked in `check_txn_effect`
+        for w in txn_effect.prism_withdrawals {
+            if let Some(locked) = self.prism_locked.get_mut(&w.body.asset) {
+                *locked -= w.body.amount;
+            }
+            self.prism_withdrawals.push(w.body);
+        }
+
+        if let Some(update) = txn_effect.prism_authorities_update {
+            self.prism_authorities_update = Some(update.body);
+        }
+
+        self.ibc_ops.extend(txn_effect.ibc_ops);
+        self.pull_payment_ops.extend(txn_effect.pull_payment_ops);
+        self.payment_stream_ops
+            .extend(txn_effect.payment_stream_ops);
+        self.designated_transfer_ops
+            .extend(txn_effect.designated_transfer_ops);
+        self.spending_policy_ops
+            .extend(txn_effect.spending_policy_ops);
+        for (owner, out) in txn_effect.outgoing.iter() {
+            self.outgoing.entry(*owner).or_default().merge(out);
+        }
+        self.session_key_ops.extend(txn_effect.session_key_ops);
+        self.kv_ops.extend(txn_effect.kv_ops);
+        self.anchor_hashes.extend(txn_effect.anchor_hashes);
+
         Ok(temp_sid)
     }
 
@@ -653,8 +1681,113 @@ impl BlockEffect {
                     return Err(eg!());
                 }
             }
+            // Ensure that each revocation list can only be updated once per block
+            for id in txn_effect.revocation_updates.keys() {
+                if self.revocation_updates.contains_key(id) {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that the mirror of each asset on a chain can only be updated once per block,
+            // and that each contract is claimed by at most one asset
+            for update in txn_effect.mirror_updates.iter() {
+                let u = &update.body;
+                if self.mirror_updates.iter().any(|i| {
+                    i.chain_id == u.chain_id
+                        && (i.asset_type == u.asset_type
+                            || (u.contract.is_some() && i.contract == u.contract))
+                }) {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that conflicting cross-chain operations are not in the same block,
+            // e.g. receiving a packet twice
+            for op in txn_effect.ibc_ops.iter() {
+                if let Some(key) = op.conflict_key() {
+                    if self
+                        .ibc_ops
+                        .iter()
+                        .any(|i| i.conflict_key().as_ref() == Some(&key))
+                    {
+                        return Err(eg!());
+                    }
+                }
+            }
+            // Ensure that each authorization of pull payments is used at most once per block
+            for op in txn_effect.pull_payment_ops.iter() {
+                let key = op.conflict_key();
+                if self
+                    .pull_payment_ops
+                    .iter()
+                    .any(|i| i.conflict_key() == key)
+                {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that each payment stream is operated at most once per block
+            for op in txn_effect.payment_stream_ops.iter() {
+                let key = op.conflict_key();
+                if self
+                    .payment_stream_ops
+                    .iter()
+                    .any(|i| i.conflict_key() == key)
+                {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that each designated transfer is operated at most once per block
+            for op in txn_effect.designated_transfer_ops.iter() {
+                let key = op.conflict_key();
+                if self
+                    .designated_transfer_ops
+                    .iter()
+                    .any(|i| i.conflict_key() == key)
+                {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that each spending policy is changed at most once per block
+            for op in txn_effect.spending_policy_ops.iter() {
+                if self
+                    .spending_policy_ops
+                    .iter()
+                    .any(|i| i.get_signer() == op.get_signer())
+                {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that each session key is changed at most once per block
+            for op in txn_effect.session_key_ops.iter() {
+                let key = op.conflict_key();
+                if self.session_key_ops.iter().any(|i| i.conflict_key() == key) {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that each key of the key-value store is changed at most once per block
+            for op in txn_effect.kv_ops.iter() {
+                let key = op.conflict_key();
+                if self.kv_ops.iter().any(|i| i.conflict_key() == key) {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that each hash is anchored by a key at most once per block
+            for op in txn_effect.anchor_hashes.iter() {
+                let key = op.conflict_key();
+                if self.anchor_hashes.iter().any(|i| i.conflict_key() == key) {
+                    return Err(eg!());
+                }
+            }
+            // Ensure that tracer keys of each asset can only be rotated once per block
+            let mut rotated = HashSet::new();
+            for update in txn_effect.tracer_key_updates.iter() {
+                let code = update.body.asset_type;
+                if self.tracer_key_updates.contains_key(&code) || !rotated.insert(code) {
+                    return Err(eg!());
+                }
+            }
         }
 
+        self.check_prism(txn_effect).c(d!())?;
+
         // Check that no operations are duplicated as in a replay attack
         // Note that we need to check here as well as in LedgerStatus::check_txn_effect
         for txn in self.txns.iter() {
@@ -669,6 +1802,39 @@ impl BlockEffect {
         Ok(())
     }
 
+    // Withdrawals can only be processed once, and can not release more than
+    // the amounts locked before this block, the authorities are updated at most once.
+    fn check_prism(&self, txn_effect: &TxnEffect) -> Result<()> {
+        let mut released: HashMap<AssetTypeCode, u64> = HashMap::new();
+        for w in txn_effect.prism_withdrawals.iter() {
+            let id = w.body.id();
+            if self.prism_withdrawals.iter().any(|i| i.id() == id) {
+                return Err(eg!("withdrawal processed in this block"));
+            }
+            let total = released.entry(w.body.asset).or_insert(0);
+            *total = total.checked_add(w.body.amount).c(d!())?;
+        }
+        for (code, am) in released {
+            let locked = self.prism_locked.get(&code).copied().unwrap_or(0);
+            if am > locked {
+                return Err(eg!(format!(
+                    "{}: {} locked, {} to withdraw",
+                    code.to_base64(),
+                    locked,
+                    am
+                )));
+            }
+        }
+
+        if txn_effect.prism_authorities_update.is_some()
+            && self.prism_authorities_update.is_some()
+        {
+            return Err(eg!("prism authorities updated in this block"));
+        }
+
+        Ok(())
+    }
+
     fn check_staking(&mut self, txn_effect: &TxnEffect) -> Result<()> {
         for i in txn_effect.update_stakers.iter() {
             i.check_run(&mut self.staking_simulator, &txn_effect.txn)
@@ -680,10 +1846,12 @@ impl BlockEffect {
                 .c(d!())?;
         }
 
-        for i in txn_effect.delegations.iter() {
-            i.check_run(&mut self.staking_simulator, &txn_effect.txn)
-                .c(d!())?;
-        }
+        DelegationOps::check_run_all(
+            &txn_effect.delegations,
+            &mut self.staking_simulator,
+            &txn_effect.txn,
+        )
+        .c(d!())?;
 
         for i in txn_effect.undelegations.iter() {
             i.check_run(&mut self.staking_simulator, &txn_effect.txn)
@@ -707,6 +1875,10 @@ impl BlockEffect {
                 .c(d!())?;
         }
 
+        for i in txn_effect.unjails.iter() {
+            i.check_run(&mut self.staking_simulator).c(d!())?;
+        }
+
         Ok(())
     }
 
@@ -9,25 +9,49 @@ mod __trash__;
 mod effects;
 mod test;
 
-pub use effects::{BlockEffect, TxnEffect};
+pub use effects::{BlockEffect, TxnCheckCode, TxnCheckError, TxnEffect};
 
 use {
-    crate::converter::ConvertAccount,
+    crate::converter::{
+        designated_transfer::DesignatedTransferOperation,
+        ibc::{IbcHash, IbcOperation, IbcProof},
+        kv::{KvEntry, KvOperation},
+        notary::{AnchorHash, NotarizedHash},
+        payment_stream::PaymentStreamOperation,
+        prism::{PrismDeposit, PrismWithdraw, UpdatePrismAuthorities},
+        pull_payment::PullPaymentOperation,
+        session_key::SessionKeyOperation,
+        spending_policy::SpendingPolicyOperation,
+        vesting::VestingSchedule,
+        ConvertAccount,
+    },
     crate::staking::{
         ops::{
             claim::ClaimOps, delegation::DelegationOps,
             fra_distribution::FraDistributionOps, governance::GovernanceOps,
             mint_fra::MintFraOps, replace_staker::ReplaceStakerOps,
-            undelegation::UnDelegationOps, update_staker::UpdateStakerOps,
-            update_validator::UpdateValidatorOps,
+            undelegation::UnDelegationOps, unjail::UnjailOps,
+            update_staker::UpdateStakerOps, update_validator::UpdateValidatorOps,
         },
         Staking,
     },
     __trash__::{Policy, PolicyGlobals, TxnPolicyData},
+    aes_gcm::{
+        aead::{generic_array::GenericArray, Aead, NewAead},
+        Aes256Gcm,
+    },
     bitmap::SparseMap,
+    credentials::{
+        credential_commit_with_key, credential_open_commitment, credential_verify,
+        credential_verify_commitment, CredCommitment, CredCommitmentKey,
+        CredIssuerPublicKey, CredPoK, CredUserSecretKey, Credential,
+    },
     cryptohash::{sha256::Digest as BitDigest, HashValue},
+    curve25519_dalek::{
+        constants::ED25519_BASEPOINT_TABLE, edwards::CompressedEdwardsY, scalar::Scalar,
+    },
     fbnc::NumKey,
-    globutils::wallet::public_key_to_base64,
+    globutils::wallet::{public_key_to_base64, public_key_to_bech32},
     globutils::{HashOf, ProofOf, Serialized, SignatureOf},
     lazy_static::lazy_static,
     rand::Rng,
@@ -35,8 +59,9 @@ use {
     rand_core::{CryptoRng, RngCore, SeedableRng},
     ruc::*,
     serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer},
+    sha2::{Digest, Sha512},
     std::{
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
         convert::TryFrom,
         fmt,
         hash::{Hash, Hasher},
@@ -51,9 +76,9 @@ use {
             lib::{gen_xfr_body, XfrNotePolicies},
             sig::{XfrKeyPair, XfrPublicKey},
             structs::{
-                AssetRecord, AssetType as ZeiAssetType, BlindAssetRecord, OwnerMemo,
-                TracingPolicies, TracingPolicy, XfrAmount, XfrAssetType, XfrBody,
-                ASSET_TYPE_LENGTH,
+                AssetRecord, AssetTracerEncKeys, AssetType as ZeiAssetType,
+                BlindAssetRecord, OwnerMemo, TracingPolicies, TracingPolicy, XfrAmount,
+                XfrAssetType, XfrBody, ASSET_TYPE_LENGTH,
             },
         },
     },
@@ -81,7 +106,7 @@ pub struct Code {
 }
 
 #[inline(always)]
-fn is_default<T: Default + PartialEq>(x: &T) -> bool {
+pub(crate) fn is_default<T: Default + PartialEq>(x: &T) -> bool {
     x == &T::default()
 }
 
@@ -394,16 +419,28 @@ pub struct XfrAddress {
 }
 
 impl XfrAddress {
-    #[cfg(not(target_arch = "wasm32"))]
     pub(crate) fn to_base64(self) -> String {
         b64enc(&self.key.as_bytes())
     }
 
+    /// The bech32 address with the prefix of the current network
+    #[inline(always)]
+    pub fn to_bech32(self) -> String {
+        public_key_to_bech32(&self.key)
+    }
+
     // pub(crate) fn to_bytes(self) -> Vec<u8> {
     //     self.key.as_bytes().to_vec()
     // }
 }
 
+impl fmt::Display for XfrAddress {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_bech32())
+    }
+}
+
 #[allow(clippy::derive_hash_xor_eq)]
 impl Hash for XfrAddress {
     #[inline(always)]
@@ -421,7 +458,6 @@ pub struct IssuerPublicKey {
 }
 
 impl IssuerPublicKey {
-    #[cfg(not(target_arch = "wasm32"))]
     pub(crate) fn to_base64(self) -> String {
         b64enc(self.key.as_bytes())
     }
@@ -534,12 +570,24 @@ pub struct AssetRules {
     pub max_units: Option<u64>,
     /// Decimals: default to FRA_DECIMALS
     pub decimals: u8,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    /// Tracing threshold: Records with a non-confidential amount not exceeding it
+    ///  need no tracer memos. The records with confidential amounts need them
+    ///  whatever their amounts, which can not be compared to the threshold.
+    pub tracing_threshold: Option<u64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    /// Tracing admin: A regulator key allowed to rotate tracer keys besides the issuer.
+    pub tracing_admin: Option<XfrPublicKey>,
 }
 impl Default for AssetRules {
     #[inline(always)]
     fn default() -> Self {
         AssetRules {
             tracing_policies: TracingPolicies::new(),
+            tracing_threshold: None,
+            tracing_admin: None,
             transferable: true,
             updatable: false,
             max_units: None,
@@ -557,6 +605,20 @@ impl AssetRules {
         self
     }
 
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_tracing_admin(&mut self, admin: Option<XfrPublicKey>) -> &mut Self {
+        self.tracing_admin = admin;
+        self
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_tracing_threshold(&mut self, threshold: Option<u64>) -> &mut Self {
+        self.tracing_threshold = threshold;
+        self
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn set_max_units(&mut self, max_units: Option<u64>) -> &mut Self {
@@ -597,6 +659,36 @@ impl AssetRules {
         self.decimals = decimals;
         Ok(self)
     }
+
+    /// Sanity checks of user supplied rules, before defining an asset with them,
+    /// e.g. the threshold of the multisig rules must be reachable.
+    pub fn check(&self) -> Result<()> {
+        if self.decimals > MAX_DECIMALS_LENGTH {
+            return Err(eg!("asset decimals should be less than 20"));
+        }
+        if Some(0) == self.max_units {
+            return Err(eg!("max units should be positive"));
+        }
+        if let Some(rules) = self.transfer_multisig_rules.as_ref() {
+            let mut keys = HashSet::new();
+            let mut sum: u64 = 0;
+            for (key, weight) in rules.weights.iter() {
+                if !keys.insert(key) {
+                    return Err(eg!("duplicate keys in the multisig rules"));
+                }
+                sum = sum.checked_add(*weight).c(d!("overflow of the weights"))?;
+            }
+            if 0 == rules.threshold || sum < rules.threshold {
+                return Err(eg!("unreachable threshold of the multisig rules"));
+            }
+        }
+        if self.tracing_policies.get_policies().is_empty()
+            && (self.tracing_threshold.is_some() || self.tracing_admin.is_some())
+        {
+            return Err(eg!("tracing threshold or admin without tracing policies"));
+        }
+        Ok(())
+    }
 }
 
 #[allow(missing_docs)]
@@ -619,6 +711,89 @@ pub struct Asset {
     pub policy: Option<(Box<Policy>, PolicyGlobals)>,
 }
 
+/// Max length of the name in `AssetMetadata`
+pub const MAX_ASSET_NAME_LEN: usize = 64;
+
+/// Max length of the symbol in `AssetMetadata`
+pub const MAX_ASSET_SYMBOL_LEN: usize = 16;
+
+/// Max length of the URI in `AssetMetadata`
+pub const MAX_ASSET_URI_LEN: usize = 256;
+
+/// Structured metadata of an asset, carried in JSON by the memo of its definition,
+/// the document at `uri`, e.g. an icon or a white paper, is pinned by `content_hash`.
+///
+/// The decimals are the ones of `AssetRules`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetMetadata {
+    #[allow(missing_docs)]
+    pub name: String,
+    /// Letters and digits, e.g. `USDT`
+    pub symbol: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    #[allow(missing_docs)]
+    pub uri: Option<String>,
+    /// The SHA-256 hash of the document at `uri`, in hex
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub content_hash: Option<String>,
+}
+
+impl AssetMetadata {
+    #[allow(missing_docs)]
+    pub fn check(&self) -> Result<()> {
+        if self.name.trim().is_empty() || self.name.len() > MAX_ASSET_NAME_LEN {
+            return Err(eg!("invalid length of the name"));
+        }
+        if self.symbol.is_empty()
+            || self.symbol.len() > MAX_ASSET_SYMBOL_LEN
+            || !self.symbol.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(eg!("invalid symbol"));
+        }
+        if let Some(uri) = self.uri.as_ref() {
+            if uri.is_empty()
+                || uri.len() > MAX_ASSET_URI_LEN
+                || uri.chars().any(|c| c.is_whitespace())
+            {
+                return Err(eg!("invalid uri"));
+            }
+        }
+        if let Some(h) = self.content_hash.as_ref() {
+            if self.uri.is_none() {
+                return Err(eg!("content hash without uri"));
+            }
+            if 64 != h.len() || !h.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(eg!("invalid content hash"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The metadata in an asset memo, `None` if it is a free text or invalid
+    pub fn from_memo(memo: &Memo) -> Option<AssetMetadata> {
+        serde_json::from_str::<AssetMetadata>(&memo.0)
+            .ok()
+            .filter(|m| m.check().is_ok())
+    }
+
+    /// A memo carrying metadata must be valid, free texts are not checked
+    pub fn check_memo(memo: &Memo) -> Result<()> {
+        match serde_json::from_str::<AssetMetadata>(&memo.0) {
+            Ok(m) => m.check().c(d!("invalid asset metadata")),
+            Err(_) => Ok(()),
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn to_memo(&self) -> Result<Memo> {
+        self.check().c(d!())?;
+        serde_json::to_string(self).c(d!()).map(Memo)
+    }
+}
+
 /// Note:
 /// if the properties field of this struct is changed,
 /// update the comment for AssetType::from_json in wasm_data_model.rs as well.
@@ -681,6 +856,96 @@ pub struct CredentialProof {
     pub key: CredentialProofKey,
 }
 
+/// Attributes of a credential publicly revealed for a transfer output,
+/// the other attributes of the credential stay hidden.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct IdentityDisclosure {
+    /// Index of the output in the transfer
+    pub output_idx: usize,
+    /// Issuer of the credential
+    pub issuer_pub_key: CredIssuerPublicKey,
+    /// Revealed attributes, in the form of `(name, value)`
+    pub attrs: Vec<(String, Vec<u8>)>,
+    /// Commitment to the credential
+    pub commitment: CredCommitment,
+    /// Proof that the commitment is bound to the owner of the output
+    pub commitment_pok: CredPoK,
+    /// Proof that the commitment opens to the revealed attributes
+    pub reveal_pok: CredPoK,
+}
+
+impl IdentityDisclosure {
+    /// Reveal `reveal_fields` of a credential for the output owned by `owner`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        output_idx: usize,
+        owner: &XfrPublicKey,
+        user_sk: &CredUserSecretKey,
+        credential: &Credential,
+        commitment_key: &CredCommitmentKey,
+        reveal_fields: &[String],
+    ) -> Result<Self> {
+        let (commitment, commitment_pok) = credential_commit_with_key(
+            prng,
+            user_sk,
+            credential,
+            commitment_key,
+            owner.as_bytes(),
+        )
+        .c(d!())?;
+        let reveal_pok = credential_open_commitment(
+            prng,
+            user_sk,
+            credential,
+            commitment_key,
+            reveal_fields,
+        )
+        .c(d!())?;
+        let attrs = credential
+            .attributes
+            .iter()
+            .filter(|(k, _)| reveal_fields.contains(k))
+            .cloned()
+            .collect();
+
+        Ok(IdentityDisclosure {
+            output_idx,
+            issuer_pub_key: credential.issuer_pub_key.clone(),
+            attrs,
+            commitment,
+            commitment_pok,
+            reveal_pok,
+        })
+    }
+
+    /// Verify the disclosure against the owner of the output:
+    /// 1) The commitment is signed by the issuer, and bound to `owner`
+    /// 2) The commitment opens to the revealed attributes
+    pub fn verify(&self, owner: &XfrPublicKey) -> Result<()> {
+        credential_verify_commitment(
+            &self.issuer_pub_key,
+            &self.commitment,
+            &self.commitment_pok,
+            owner.as_bytes(),
+        )
+        .c(d!())?;
+
+        let attrs = self
+            .attrs
+            .iter()
+            .map(|(k, v)| (k.clone(), v.as_slice()))
+            .collect::<Vec<_>>();
+        credential_verify(
+            &self.issuer_pub_key,
+            &attrs,
+            &self.commitment,
+            &self.reveal_pok,
+        )
+        .c(d!())
+    }
+}
+
 #[derive(
     Clone,
     Copy,
@@ -771,6 +1036,107 @@ pub struct TxOutput {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_default")]
     pub lien: Option<HashOf<Vec<TxOutput>>>,
+    /// An optional memo for the recipient,
+    /// eg. payment references or travel-rule data
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub memo: Option<EncryptedMemo>,
+}
+
+/// Max length in bytes of the plaintext of an `EncryptedMemo`
+pub const MAX_ENCRYPTED_MEMO_LEN: usize = 512;
+
+// length of the authentication tag of AES-GCM
+const AES_GCM_TAG_LEN: usize = 16;
+
+/// A memo of a transfer output, encrypted to the public key of the recipient,
+/// only the holder of the corresponding secret key can decrypt it.
+///
+/// The key of AES-256-GCM is derived from a ECDH on the ed25519 curve
+/// between a random ephemeral key and the key of the recipient.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EncryptedMemo {
+    /// The compressed ephemeral public key
+    pub ephemeral: CompressedEdwardsY,
+    #[allow(missing_docs)]
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedMemo {
+    /// Encrypt `msg` to `recipient`
+    pub fn encrypt<R: CryptoRng + RngCore>(
+        prng: &mut R,
+        recipient: &XfrPublicKey,
+        msg: &[u8],
+    ) -> Result<Self> {
+        if msg.len() > MAX_ENCRYPTED_MEMO_LEN {
+            return Err(eg!("memo too long"));
+        }
+        let point = CompressedEdwardsY::from_slice(recipient.as_bytes())
+            .decompress()
+            .c(d!("invalid public key"))?;
+
+        let r = Scalar::random(prng);
+        let ephemeral = (&r * &ED25519_BASEPOINT_TABLE).compress();
+        let cipher = memo_cipher(&ephemeral, &(r * point).compress());
+
+        cipher
+            .encrypt(GenericArray::from_slice(&[0; 12]), msg)
+            .map_err(|_| eg!("encryption failed"))
+            .map(|ciphertext| EncryptedMemo {
+                ephemeral,
+                ciphertext,
+            })
+    }
+
+    /// Decrypt the memo with the key pair of the recipient
+    pub fn decrypt(&self, keypair: &XfrKeyPair) -> Result<Vec<u8>> {
+        self.check().c(d!())?;
+        let point = self.ephemeral.decompress().c(d!("invalid ephemeral key"))?;
+
+        // the same scalar as the one of ed25519 signatures
+        let hash = Sha512::digest(&keypair.get_sk_ref().zei_to_bytes());
+        let mut bits = [0u8; 32];
+        bits.copy_from_slice(&hash[..32]);
+        bits[0] &= 248;
+        bits[31] &= 127;
+        bits[31] |= 64;
+        let cipher = memo_cipher(
+            &self.ephemeral,
+            &(Scalar::from_bits(bits) * point).compress(),
+        );
+
+        cipher
+            .decrypt(
+                GenericArray::from_slice(&[0; 12]),
+                self.ciphertext.as_slice(),
+            )
+            .map_err(|_| eg!("decryption failed, not the recipient?"))
+    }
+
+    /// Check the size of the memo
+    #[inline(always)]
+    pub fn check(&self) -> Result<()> {
+        alt!(
+            self.ciphertext.len() > MAX_ENCRYPTED_MEMO_LEN + AES_GCM_TAG_LEN,
+            Err(eg!("memo too long")),
+            Ok(())
+        )
+    }
+}
+
+// every memo has a fresh ephemeral key, thus a unique cipher key,
+// so a constant nonce is safe
+fn memo_cipher(
+    ephemeral: &CompressedEdwardsY,
+    shared: &CompressedEdwardsY,
+) -> Aes256Gcm {
+    let hash = Sha512::new()
+        .chain(b"Findora Encrypted Memo")
+        .chain(ephemeral.as_bytes())
+        .chain(shared.as_bytes())
+        .finalize();
+    Aes256Gcm::new(GenericArray::from_slice(&hash[..32]))
 }
 
 #[allow(missing_docs)]
@@ -786,7 +1152,6 @@ pub enum UtxoStatus {
 pub struct Utxo(pub TxOutput);
 
 impl Utxo {
-    #[cfg(not(target_arch = "wasm32"))]
     #[inline(always)]
     pub(crate) fn get_nonconfidential_balance(&self) -> u64 {
         if let XfrAmount::NonConfidential(n) = self.0.record.amount {
@@ -805,6 +1170,41 @@ pub enum TxoRef {
     Relative(u64),
     /// Absolute Txo address to a location outside this txn
     Absolute(TxoSID),
+    /// An output of a transaction still pending when this one is signed,
+    /// resolved to its absolute address when this one is applied
+    Pending(PendingTxoRef),
+}
+
+/// An output of a transaction referenced by its handle.
+///
+/// Only the transactions committed in the last `PENDING_REF_WINDOW` blocks
+/// can be referenced this way, see `LedgerStatus::resolve_pending_inputs`.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct PendingTxoRef {
+    /// the decoded handle of the parent transaction, see `Transaction::handle_digest`
+    pub txn: [u8; 32],
+    /// the index of the output among the ones of the parent recorded on the ledger,
+    /// ie. `FinalizedTransaction::txo_ids`
+    pub idx: u64,
+}
+
+impl PendingTxoRef {
+    /// The `idx`-th output of the transaction of the handle `handle`.
+    pub fn from_handle(handle: &str, idx: u64) -> Result<Self> {
+        let bytes = hex::decode(handle).c(d!("invalid handle"))?;
+        if 32 != bytes.len() {
+            return Err(eg!("invalid handle"));
+        }
+        let mut txn = [0; 32];
+        txn.copy_from_slice(&bytes);
+        Ok(PendingTxoRef { txn, idx })
+    }
+
+    /// The handle of the parent transaction.
+    #[inline(always)]
+    pub fn handle(&self) -> String {
+        hex::encode(self.txn)
+    }
 }
 
 #[allow(missing_docs)]
@@ -860,6 +1260,11 @@ pub struct TransferAssetBody {
 
     /// Only Standard type supported
     pub transfer_type: TransferType,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    /// Credential attributes revealed for some outputs, at most one per output
+    pub identity_disclosures: Vec<IdentityDisclosure>,
 }
 
 impl TransferAssetBody {
@@ -910,6 +1315,7 @@ impl TransferAssetBody {
                 id: None,
                 record: rec.clone(),
                 lien: None,
+                memo: None,
             })
             .collect();
         Ok(TransferAssetBody {
@@ -919,6 +1325,7 @@ impl TransferAssetBody {
             lien_assignments,
             transfer,
             transfer_type,
+            identity_disclosures: vec![],
         })
     }
 
@@ -957,6 +1364,10 @@ pub struct IssueAssetBody {
     pub seq_num: u64,
     pub num_outputs: usize,
     pub records: Vec<(TxOutput, Option<OwnerMemo>)>,
+    /// Vesting schedules of the records, by their indices
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vesting: Vec<(usize, VestingSchedule)>,
 }
 
 impl IssueAssetBody {
@@ -972,6 +1383,7 @@ impl IssueAssetBody {
             seq_num,
             num_outputs: records.len(),
             records: records.to_vec(),
+            vesting: vec![],
         })
     }
 }
@@ -1220,6 +1632,216 @@ impl UpdateMemo {
     }
 }
 
+/// A tracer key of an asset tracing policy taking effect from a block height,
+/// it decrypts the tracer memos until the next rotation of the same policy.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TracerKeyRotation {
+    /// Index of the policy in `AssetRules::tracing_policies`
+    pub policy_idx: usize,
+    /// The tendermint height from which the new keys take effect
+    pub effective_height: u64,
+    /// The new encryption keys of the tracer
+    pub enc_keys: AssetTracerEncKeys,
+}
+
+/// Operation body for rotating the tracer key of an asset tracing policy
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdateTracerKeyBody {
+    /// The asset whose tracing policy is updated
+    pub asset_type: AssetTypeCode,
+    #[allow(missing_docs)]
+    pub rotation: TracerKeyRotation,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for rotating the tracer key of an asset tracing policy,
+/// signed by the asset issuer or the tracing admin
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdateTracerKey {
+    /// Inner data to update
+    pub body: UpdateTracerKeyBody,
+    /// The findora account publickey
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<UpdateTracerKeyBody>,
+}
+
+impl UpdateTracerKey {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: UpdateTracerKeyBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        UpdateTracerKey {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// A token mirroring an asset on an external chain, eg. an ERC-20 contract
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct AssetMirror {
+    /// The chain ID of the external chain
+    pub chain_id: u64,
+    /// Address of the token contract
+    pub contract: [u8; 20],
+}
+
+/// Operation body for registering the mirror of an asset on an external chain
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdateAssetMirrorBody {
+    /// The mirrored asset
+    pub asset_type: AssetTypeCode,
+    /// The chain ID of the external chain
+    pub chain_id: u64,
+    /// Replaces the current mirror on the chain, `None` to remove it
+    pub contract: Option<[u8; 20]>,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for registering the mirror of an asset, signed by the asset issuer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdateAssetMirror {
+    /// Inner data to update
+    pub body: UpdateAssetMirrorBody,
+    /// The findora account publickey
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<UpdateAssetMirrorBody>,
+}
+
+impl UpdateAssetMirror {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: UpdateAssetMirrorBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        UpdateAssetMirror {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// Tracing policies of an asset at a block height, with tracer keys rotated
+/// by all the rotations that have taken effect
+pub fn tracing_policies_at(
+    policies: &TracingPolicies,
+    rotations: &[TracerKeyRotation],
+    height: u64,
+) -> TracingPolicies {
+    let mut ret = TracingPolicies::new();
+    for (idx, policy) in policies.get_policies().iter().enumerate() {
+        let mut policy = policy.clone();
+        if let Some(r) = rotations
+            .iter()
+            .filter(|r| r.policy_idx == idx && r.effective_height <= height)
+            .max_by_key(|r| r.effective_height)
+        {
+            policy.enc_keys = r.enc_keys.clone();
+        }
+        ret.add(policy);
+    }
+    ret
+}
+
+/// Identifier of a credential issuer, used as the key of its revocation list
+#[inline(always)]
+pub fn cred_issuer_id(issuer: &CredIssuerPublicKey) -> String {
+    HashOf::new(issuer).hex()
+}
+
+/// Operation body for updating the revocation list of a credential issuer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdateRevocationBody {
+    /// The credential issuer
+    pub issuer: CredIssuerPublicKey,
+    /// Name of the attribute identifying a credential,
+    /// only takes effect when the revocation list is created
+    pub id_attr: String,
+    /// Identifiers of the credentials to be revoked
+    pub revoke: Vec<Vec<u8>>,
+    /// Identifiers of the credentials to be restored
+    pub restore: Vec<Vec<u8>>,
+    /// Expiry heights of credentials, set when they are issued or renewed,
+    /// in the form of `(identifier, tendermint height)`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub expiries: Vec<(Vec<u8>, u64)>,
+    #[allow(missing_docs)]
+    pub no_replay_token: NoReplayToken,
+}
+
+/// Operation data for updating the revocation list of a credential issuer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UpdateRevocation {
+    /// Inner data to update
+    pub body: UpdateRevocationBody,
+    /// The findora account publickey,
+    /// the first one updating the list becomes its owner
+    pub pubkey: XfrPublicKey,
+    /// the signature
+    pub signature: SignatureOf<UpdateRevocationBody>,
+}
+
+impl UpdateRevocation {
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(body: UpdateRevocationBody, signing_key: &XfrKeyPair) -> Self {
+        let signature = SignatureOf::new(signing_key, &body);
+        UpdateRevocation {
+            body,
+            pubkey: *signing_key.get_pk_ref(),
+            signature,
+        }
+    }
+}
+
+/// The on-chain revocation list of a credential issuer
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RevocationRegistry {
+    /// The only findora account allowed to update the list
+    pub owner: XfrPublicKey,
+    /// Name of the attribute identifying a credential
+    pub id_attr: String,
+    /// Identifiers of revoked credentials
+    pub revoked: BTreeSet<Vec<u8>>,
+    /// Expiry heights of credentials, a credential can not be used
+    /// since the block of its expiry height; credentials not listed never expire
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub expiries: BTreeMap<Vec<u8>, u64>,
+}
+
+/// Digests of the revocation lists committed in `StateCommitmentData`,
+/// keyed by `cred_issuer_id`
+pub type RevocationDigests = BTreeMap<String, HashOf<RevocationRegistry>>;
+
+impl RevocationRegistry {
+    /// Check the attributes revealed by an `IdentityDisclosure` at block `height`,
+    /// the identifier must be revealed, and the credential must be neither revoked nor expired.
+    pub fn check(&self, attrs: &[(String, Vec<u8>)], height: u64) -> Result<()> {
+        let id = attrs
+            .iter()
+            .find(|(k, _)| *k == self.id_attr)
+            .map(|(_, v)| v)
+            .c(d!("credential identifier is not revealed"))?;
+        if self.revoked.contains(id) {
+            return Err(eg!("credential has been revoked"));
+        }
+        if let Some(expiry) = self.expiries.get(id) {
+            if height >= *expiry {
+                return Err(eg!("credential has expired at height {}", expiry));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Operation list supported in findora network
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Operation {
@@ -1251,6 +1873,36 @@ pub enum Operation {
     ConvertAccount(ConvertAccount),
     ///replace staker.
     ReplaceStaker(ReplaceStakerOps),
+    /// Update the revocation list of a credential issuer
+    UpdateRevocation(UpdateRevocation),
+    /// Rotate the tracer key of an asset tracing policy
+    UpdateTracerKey(UpdateTracerKey),
+    /// Lock assets on the ledger for the account side of the prism bridge
+    PrismDeposit(PrismDeposit),
+    /// Release assets locked by the prism bridge
+    PrismWithdraw(PrismWithdraw),
+    /// Replace the authorities of the prism bridge
+    UpdatePrismAuthorities(UpdatePrismAuthorities),
+    /// Register the mirror of an asset on an external chain
+    UpdateAssetMirror(UpdateAssetMirror),
+    /// Cross-chain light clients and packets
+    Ibc(IbcOperation),
+    /// Recurring payments pulled by the payee
+    PullPayment(PullPaymentOperation),
+    /// Payments vesting linearly over a range of blocks
+    PaymentStream(PaymentStreamOperation),
+    /// Transfers to be claimed by the recipient within a number of blocks
+    DesignatedTransfer(DesignatedTransferOperation),
+    /// Rules enforced by the ledger on the transfers of a key
+    SpendingPolicy(SpendingPolicyOperation),
+    /// Authorizations of session keys to sign the transfers of a key
+    SessionKey(SessionKeyOperation),
+    /// Bring a jailed validator back to tendermint
+    Unjail(UnjailOps),
+    /// Small data blobs anchored under keys owned by the signer
+    Kv(KvOperation),
+    /// Notarize a hash under the signer, with the timestamp of the block
+    AnchorHash(AnchorHash),
 }
 
 fn set_no_replay_token(op: &mut Operation, no_replay_token: NoReplayToken) {
@@ -1267,6 +1919,9 @@ fn set_no_replay_token(op: &mut Operation, no_replay_token: NoReplayToken) {
         Operation::Claim(i) => {
             i.set_nonce(no_replay_token);
         }
+        Operation::Unjail(i) => {
+            i.set_nonce(no_replay_token);
+        }
         Operation::FraDistribution(i) => {
             i.set_nonce(no_replay_token);
         }
@@ -1277,6 +1932,18 @@ fn set_no_replay_token(op: &mut Operation, no_replay_token: NoReplayToken) {
             i.set_nonce(no_replay_token);
         }
         Operation::UpdateMemo(i) => i.body.no_replay_token = no_replay_token,
+        Operation::UpdateRevocation(i) => i.body.no_replay_token = no_replay_token,
+        Operation::UpdateTracerKey(i) => i.body.no_replay_token = no_replay_token,
+        Operation::PrismDeposit(i) => i.body.no_replay_token = no_replay_token,
+        Operation::UpdateAssetMirror(i) => i.body.no_replay_token = no_replay_token,
+        Operation::Ibc(i) => i.set_no_replay_token(no_replay_token),
+        Operation::PullPayment(i) => i.set_no_replay_token(no_replay_token),
+        Operation::PaymentStream(i) => i.set_no_replay_token(no_replay_token),
+        Operation::DesignatedTransfer(i) => i.set_no_replay_token(no_replay_token),
+        Operation::SpendingPolicy(i) => i.set_no_replay_token(no_replay_token),
+        Operation::SessionKey(i) => i.set_no_replay_token(no_replay_token),
+        Operation::Kv(i) => i.set_no_replay_token(no_replay_token),
+        Operation::AnchorHash(i) => i.set_no_replay_token(no_replay_token),
         Operation::ConvertAccount(i) => i.set_nonce(no_replay_token),
         _ => {}
     }
@@ -1296,6 +1963,11 @@ pub struct TransactionBody {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_default")]
     pub memos: Vec<Memo>,
+    /// The last tendermint height the transaction can be included at,
+    /// it never expires if `None`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub expires_at: Option<u64>,
 }
 
 impl TransactionBody {
@@ -1493,6 +2165,163 @@ impl AuthenticatedBlock {
     }
 }
 
+/// The revocation list of a credential issuer, along with a proof of
+/// its inclusion(or absence) in the state commitment.
+///
+/// The proof carries the digests of all revocation lists,
+/// its size grows with the number of issuers rather than the size of the lists.
+#[allow(missing_docs)]
+#[derive(Serialize, Clone, Deserialize)]
+pub struct AuthenticatedRevocationRegistry {
+    pub issuer_id: String,
+    pub registry: Option<RevocationRegistry>,
+    pub digests: RevocationDigests,
+    pub state_commitment_data: StateCommitmentData,
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+}
+
+impl AuthenticatedRevocationRegistry {
+    /// An authenticated revocation list is valid if
+    /// 1) The state commitment of the proof matches the state commitment passed in,
+    ///    and the state commitment data hashes to it
+    /// 2) The digests hash to the revocation commitment of the state commitment data
+    /// 3) The list(or its absence) matches the digest of the issuer
+    pub fn is_valid(
+        &self,
+        state_commitment: HashOf<Option<StateCommitmentData>>,
+    ) -> bool {
+        //1)
+        if self.state_commitment != state_commitment
+            || self.state_commitment != self.state_commitment_data.compute_commitment()
+        {
+            return false;
+        }
+
+        //2)
+        let commitment = alt!(
+            self.digests.is_empty(),
+            None,
+            Some(HashOf::new(&self.digests))
+        );
+        if self.state_commitment_data.revocation != commitment {
+            return false;
+        }
+
+        //3)
+        self.digests.get(&self.issuer_id)
+            == self.registry.as_ref().map(HashOf::new).as_ref()
+    }
+}
+
+/// A value of the IBC store, along with its merkle proof,
+/// relayed to counterparty chains as the proof of a packet or an acknowledgement.
+#[allow(missing_docs)]
+#[derive(Serialize, Clone, Deserialize)]
+pub struct AuthenticatedIbcValue {
+    pub key: String,
+    pub value: IbcHash,
+    pub proof: IbcProof,
+    pub state_commitment_data: StateCommitmentData,
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+}
+
+impl AuthenticatedIbcValue {
+    /// An authenticated IBC value is valid if
+    /// 1) The state commitment of the proof matches the state commitment passed in,
+    ///    and the state commitment data hashes to it
+    /// 2) The merkle proof leads to the IBC root of the state commitment data
+    pub fn is_valid(
+        &self,
+        state_commitment: HashOf<Option<StateCommitmentData>>,
+    ) -> bool {
+        //1)
+        if self.state_commitment != state_commitment
+            || self.state_commitment != self.state_commitment_data.compute_commitment()
+        {
+            return false;
+        }
+
+        //2)
+        self.state_commitment_data
+            .ibc
+            .as_ref()
+            .map(|root| self.proof.verify(&self.key, &self.value, root))
+            .unwrap_or(false)
+    }
+}
+
+/// An entry of the key-value store, along with its merkle proof
+#[allow(missing_docs)]
+#[derive(Serialize, Clone, Deserialize)]
+pub struct AuthenticatedKvValue {
+    pub key: String,
+    pub entry: KvEntry,
+    pub proof: IbcProof,
+    pub state_commitment_data: StateCommitmentData,
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+}
+
+impl AuthenticatedKvValue {
+    /// An authenticated key-value entry is valid if
+    /// 1) The state commitment of the proof matches the state commitment passed in,
+    ///    and the state commitment data hashes to it
+    /// 2) The merkle proof leads to the key-value root of the state commitment data
+    pub fn is_valid(
+        &self,
+        state_commitment: HashOf<Option<StateCommitmentData>>,
+    ) -> bool {
+        //1)
+        if self.state_commitment != state_commitment
+            || self.state_commitment != self.state_commitment_data.compute_commitment()
+        {
+            return false;
+        }
+
+        //2)
+        self.state_commitment_data
+            .kv
+            .as_ref()
+            .map(|root| self.proof.verify(&self.key, &self.entry.digest(), root))
+            .unwrap_or(false)
+    }
+}
+
+/// An anchored hash, along with its merkle proof
+#[allow(missing_docs)]
+#[derive(Serialize, Clone, Deserialize)]
+pub struct AuthenticatedNotarizedHash {
+    pub key: String,
+    pub record: NotarizedHash,
+    pub proof: IbcProof,
+    pub state_commitment_data: StateCommitmentData,
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+}
+
+impl AuthenticatedNotarizedHash {
+    /// An authenticated anchored hash is valid if
+    /// 1) The state commitment of the proof matches the state commitment passed in,
+    ///    and the state commitment data hashes to it
+    /// 2) The merkle proof leads to the notary root of the state commitment data
+    pub fn is_valid(
+        &self,
+        state_commitment: HashOf<Option<StateCommitmentData>>,
+    ) -> bool {
+        //1)
+        if self.state_commitment != state_commitment
+            || self.state_commitment != self.state_commitment_data.compute_commitment()
+        {
+            return false;
+        }
+
+        //2)
+        self.state_commitment_data
+            .notary
+            .as_ref()
+            .map(|root| self.proof.verify(&self.key, &self.record.digest(), root))
+            .unwrap_or(false)
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Serialize, Clone, Deserialize)]
 pub struct AuthenticatedUtxoStatus {
@@ -1620,6 +2449,12 @@ impl Transaction {
             .any(|o| matches!(o, Operation::MintFra(_)))
     }
 
+    /// Whether the transaction can not be included in the block at `height`
+    #[inline(always)]
+    pub fn is_expired(&self, height: u64) -> bool {
+        self.body.expires_at.map(|h| h < height).unwrap_or(false)
+    }
+
     /// All-in-one checker
     #[inline(always)]
     pub fn valid_in_abci(&self) -> bool {
@@ -1672,6 +2507,64 @@ impl Transaction {
             })
     }
 
+    /// The amount of FRA paid as fees, see `check_fee`
+    pub fn fee(&self) -> u64 {
+        self.body
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::TransferAsset(x) => Some(x.body.outputs.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter(|o| *BLACK_HOLE_PUBKEY == o.record.public_key)
+            .filter_map(|o| match (o.record.asset_type, o.record.amount) {
+                (XfrAssetType::NonConfidential(ty), XfrAmount::NonConfidential(am))
+                    if ty == ASSET_TYPE_FRA =>
+                {
+                    Some(am)
+                }
+                _ => None,
+            })
+            .fold(0u64, |acc, am| acc.saturating_add(am))
+    }
+
+    /// The non-confidential outputs of the transfers must not be below
+    /// the dust limits of their assets, confidential outputs can not be checked.
+    ///
+    /// Fees and the assets locked by the ledger itself are not limited.
+    pub fn check_dust(&self, dust_limits: &BTreeMap<AssetTypeCode, u64>) -> Result<()> {
+        if dust_limits.is_empty() {
+            return Ok(());
+        }
+
+        for o in self.body.operations.iter().flat_map(|op| match op {
+            Operation::TransferAsset(x) => x.body.outputs.iter(),
+            _ => [].iter(),
+        }) {
+            if *BLACK_HOLE_PUBKEY == o.record.public_key
+                || *BLACK_HOLE_PUBKEY_STAKING == o.record.public_key
+            {
+                continue;
+            }
+            if let (XfrAssetType::NonConfidential(ty), XfrAmount::NonConfidential(am)) =
+                (&o.record.asset_type, &o.record.amount)
+            {
+                let code = AssetTypeCode { val: *ty };
+                if let Some(limit) = dust_limits.get(&code).filter(|l| am < *l) {
+                    return Err(eg!(format!(
+                        "output below the dust limit of {}: {} < {}",
+                        code.to_base64(),
+                        am,
+                        limit
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// findora hash
     #[inline(always)]
     pub fn hash(&self, id: TxnSID) -> HashOf<(TxnSID, Transaction)> {
@@ -1697,6 +2590,32 @@ impl Transaction {
         hex::encode(digest)
     }
 
+    /// The decoded handle, referenced by `PendingTxoRef`
+    #[inline(always)]
+    pub fn handle_digest(&self) -> [u8; 32] {
+        let mut digest = [0; 32];
+        digest.copy_from_slice(self.hash(TxnSID(0)).as_ref());
+        digest
+    }
+
+    /// The decoded handles of the transactions whose outputs are spent
+    /// by `TxoRef::Pending`, without duplicates
+    pub fn pending_parents(&self) -> Vec<[u8; 32]> {
+        let mut parents = vec![];
+        for op in self.body.operations.iter() {
+            if let Operation::TransferAsset(trn) = op {
+                for i in trn.body.inputs.iter() {
+                    if let TxoRef::Pending(r) = i {
+                        if !parents.contains(&r.txn) {
+                            parents.push(r.txn);
+                        }
+                    }
+                }
+            }
+        }
+        parents
+    }
+
     /// Create a transaction from seq id
     #[inline(always)]
     pub fn from_seq_id(seq_id: u64) -> Self {
@@ -1830,7 +2749,8 @@ impl Transaction {
             sign.0.verify(public_key, &Serialized::new(&self.body))
         } else {
             Err(eg!(
-                "the pubkey not match: {}",
+                "the pubkey not match: {} ({})",
+                public_key_to_bech32(public_key),
                 public_key_to_base64(public_key)
             ))
         }
@@ -1852,8 +2772,9 @@ impl Transaction {
         for operation in self.body.operations.iter() {
             match operation {
                 Operation::TransferAsset(o) => {
-                    for pk in o.get_owner_addresses().iter() {
-                        select_check(self, pk).c(d!())?;
+                    // the owners of the inputs, or the session keys signing for them
+                    for sig in o.body_signatures.iter() {
+                        select_check(self, &sig.address.key).c(d!())?;
                     }
                 }
                 Operation::IssueAsset(o) => {
@@ -1877,6 +2798,9 @@ impl Transaction {
                 Operation::Claim(o) => {
                     select_check(self, &o.pubkey).c(d!())?;
                 }
+                Operation::Unjail(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
                 Operation::UpdateValidator(_) => {}
                 Operation::Governance(_) => {}
                 Operation::FraDistribution(_) => {}
@@ -1891,6 +2815,48 @@ impl Transaction {
                         }
                     }
                 }
+                Operation::UpdateRevocation(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
+                Operation::UpdateTracerKey(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
+                Operation::PrismDeposit(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
+                Operation::UpdateAssetMirror(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
+                // signed by the bridge authorities inside the operations
+                Operation::PrismWithdraw(_) => {}
+                Operation::UpdatePrismAuthorities(_) => {}
+                Operation::Ibc(o) => {
+                    // relayed operations are proven by the counterparty
+                    if let Some(pk) = o.get_signer() {
+                        select_check(self, pk).c(d!())?;
+                    }
+                }
+                Operation::PullPayment(o) => {
+                    select_check(self, o.get_signer()).c(d!())?;
+                }
+                Operation::PaymentStream(o) => {
+                    select_check(self, o.get_signer()).c(d!())?;
+                }
+                Operation::DesignatedTransfer(o) => {
+                    select_check(self, o.get_signer()).c(d!())?;
+                }
+                Operation::SpendingPolicy(o) => {
+                    select_check(self, o.get_signer()).c(d!())?;
+                }
+                Operation::SessionKey(o) => {
+                    select_check(self, o.get_signer()).c(d!())?;
+                }
+                Operation::Kv(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
+                Operation::AnchorHash(o) => {
+                    select_check(self, &o.pubkey).c(d!())?;
+                }
             }
         }
 
@@ -1898,6 +2864,47 @@ impl Transaction {
     }
 }
 
+/// The cancellation of a transaction held by the submission server,
+/// or its replacement by another one
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CancelTxnBody {
+    /// the handle of the transaction, see `Transaction::handle`
+    pub handle: String,
+    /// the transaction replacing it, cancelled only if `None`
+    pub replacement: Option<Transaction>,
+}
+
+/// `CancelTxnBody` signed by a signer of the transaction
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CancelTxn {
+    #[allow(missing_docs)]
+    pub body: CancelTxnBody,
+    #[allow(missing_docs)]
+    pub pubkey: XfrPublicKey,
+    #[allow(missing_docs)]
+    pub signature: SignatureOf<CancelTxnBody>,
+}
+
+impl CancelTxn {
+    #[allow(missing_docs)]
+    pub fn new(keypair: &XfrKeyPair, body: CancelTxnBody) -> Self {
+        CancelTxn {
+            signature: SignatureOf::new(keypair, &body),
+            pubkey: keypair.pub_key,
+            body,
+        }
+    }
+
+    /// Check the signature, and that its signer signed `txn` too,
+    /// so only the submitters of a transaction can cancel it.
+    pub fn verify(&self, txn: &Transaction) -> Result<()> {
+        self.signature.verify(&self.pubkey, &self.body).c(d!())?;
+        txn.check_has_signature(&self.pubkey)
+            .or_else(|_| txn.check_has_signature_from_map(&self.pubkey))
+            .c(d!("not signed by the submitter of the transaction"))
+    }
+}
+
 /// Current ledger state commitment data
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct StateCommitmentData {
@@ -1923,6 +2930,22 @@ pub struct StateCommitmentData {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_default")]
     pub staking: Option<HashOf<Staking>>,
+    /// hash(non-empty digests of the revocation lists of credential issuers)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub revocation: Option<HashOf<RevocationDigests>>,
+    /// root of the non-empty IBC store, proves packets to counterparty chains
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub ibc: Option<IbcHash>,
+    /// root of the non-empty key-value store, proves the anchored records
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub kv: Option<IbcHash>,
+    /// root of the non-empty notary store, proves the anchored hashes
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub notary: Option<IbcHash>,
 }
 
 impl StateCommitmentData {
@@ -2,12 +2,18 @@
 #![allow(missing_docs)]
 use {
     super::*,
+    credentials::{
+        credential_issuer_key_gen, credential_keygen_commitment, credential_sign,
+        credential_user_key_gen,
+    },
     curve25519_dalek::ristretto::CompressedRistretto,
     rand_core::SeedableRng,
     std::cmp::min,
     zei::{
         ristretto,
-        xfr::structs::{AssetTypeAndAmountProof, XfrBody, XfrProofs},
+        xfr::structs::{
+            AssetTracerKeyPair, AssetTypeAndAmountProof, XfrBody, XfrProofs,
+        },
     },
     zeiutils::msg_eq,
 };
@@ -193,6 +199,7 @@ fn gen_sample_tx() -> Transaction {
         transfer: Box::new(xfr_note),
         lien_assignments: Vec::new(),
         transfer_type: TransferType::Standard,
+        identity_disclosures: Vec::new(),
     };
 
     let asset_transfer = {
@@ -209,6 +216,7 @@ fn gen_sample_tx() -> Transaction {
         seq_num: 0,
         num_outputs: 0,
         records: Vec::new(),
+        vesting: Vec::new(),
     };
 
     let asset_issuance =
@@ -284,6 +292,7 @@ fn gen_fee_operation(
                     public_key: dest_pubkey,
                 },
                 lien: None,
+                memo: None,
             }],
             lien_assignments: Vec::new(),
             transfer: Box::new(XfrBody {
@@ -297,6 +306,7 @@ fn gen_fee_operation(
                 owners_memos: Vec::new(),
             }),
             transfer_type: TransferType::Standard,
+            identity_disclosures: Vec::new(),
         },
         body_signatures: Vec::new(),
     })
@@ -377,3 +387,215 @@ fn test_check_fee() {
     tx.add_operation(invalid_destination_not_black_hole);
     assert!(tx.check_fee());
 }
+
+#[test]
+fn test_identity_disclosure() {
+    let mut prng = ChaChaRng::from_entropy();
+    let owner = XfrKeyPair::generate(&mut prng).get_pk();
+
+    let (issuer_pk, issuer_sk) = credential_issuer_key_gen(
+        &mut prng,
+        &[("jurisdiction".to_owned(), 2), ("dob".to_owned(), 8)],
+    );
+    let (user_pk, user_sk) = credential_user_key_gen(&mut prng, &issuer_pk);
+    let attrs = [
+        ("jurisdiction".to_owned(), &b"US"[..]),
+        ("dob".to_owned(), &b"19900101"[..]),
+    ];
+    let credential = Credential {
+        attributes: attrs.iter().map(|(k, v)| (k.clone(), v.to_vec())).collect(),
+        issuer_pub_key: issuer_pk,
+        signature: pnk!(credential_sign(&mut prng, &issuer_sk, &user_pk, &attrs)),
+    };
+    let key = credential_keygen_commitment(&mut prng);
+
+    let disclosure = pnk!(IdentityDisclosure::new(
+        &mut prng,
+        0,
+        &owner,
+        &user_sk,
+        &credential,
+        &key,
+        &["jurisdiction".to_owned()],
+    ));
+    assert_eq!(
+        disclosure.attrs,
+        vec![("jurisdiction".to_owned(), b"US".to_vec())]
+    );
+    assert!(disclosure.verify(&owner).is_ok());
+
+    // bound to another owner
+    let other = XfrKeyPair::generate(&mut prng).get_pk();
+    assert!(disclosure.verify(&other).is_err());
+
+    // tampered attributes
+    let mut tampered = disclosure.clone();
+    tampered.attrs[0].1 = b"CN".to_vec();
+    assert!(tampered.verify(&owner).is_err());
+
+    // hidden attributes can not be claimed
+    let mut tampered = disclosure;
+    tampered
+        .attrs
+        .push(("dob".to_owned(), b"20000101".to_vec()));
+    assert!(tampered.verify(&owner).is_err());
+}
+
+#[test]
+fn test_encrypted_memo() {
+    let mut prng = ChaChaRng::from_entropy();
+    let recipient = XfrKeyPair::generate(&mut prng);
+    let other = XfrKeyPair::generate(&mut prng);
+
+    let msg = b"invoice #42";
+    let memo = EncryptedMemo::encrypt(&mut prng, recipient.get_pk_ref(), msg).unwrap();
+    assert_eq!(memo.decrypt(&recipient).unwrap(), msg.to_vec());
+    assert!(memo.decrypt(&other).is_err());
+
+    let mut tampered = memo.clone();
+    tampered.ciphertext[0] ^= 1;
+    assert!(tampered.decrypt(&recipient).is_err());
+
+    assert!(EncryptedMemo::encrypt(
+        &mut prng,
+        recipient.get_pk_ref(),
+        &[0; MAX_ENCRYPTED_MEMO_LEN + 1]
+    )
+    .is_err());
+}
+
+#[test]
+fn test_tracing_policies_at() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut enc_keys = || AssetTracerKeyPair::generate(&mut prng).enc_key;
+
+    let mut policies = TracingPolicies::new();
+    for _ in 0..2 {
+        policies.add(TracingPolicy {
+            enc_keys: enc_keys(),
+            asset_tracing: true,
+            identity_tracing: None,
+        });
+    }
+
+    let rotations = vec![
+        TracerKeyRotation {
+            policy_idx: 0,
+            effective_height: 10,
+            enc_keys: enc_keys(),
+        },
+        TracerKeyRotation {
+            policy_idx: 0,
+            effective_height: 20,
+            enc_keys: enc_keys(),
+        },
+    ];
+
+    // not rotated yet
+    assert_eq!(tracing_policies_at(&policies, &rotations, 9), policies);
+
+    let p = tracing_policies_at(&policies, &rotations, 10);
+    assert_eq!(p.get_policy(0).unwrap().enc_keys, rotations[0].enc_keys);
+    assert_eq!(p.get_policy(1), policies.get_policy(1));
+
+    let p = tracing_policies_at(&policies, &rotations, 25);
+    assert_eq!(p.get_policy(0).unwrap().enc_keys, rotations[1].enc_keys);
+    assert_eq!(p.get_policy(1), policies.get_policy(1));
+}
+
+#[test]
+fn test_revocation_registry() {
+    let mut prng = ChaChaRng::from_entropy();
+    let owner = XfrKeyPair::generate(&mut prng).get_pk();
+
+    let mut registry = RevocationRegistry {
+        owner,
+        id_attr: "id".to_owned(),
+        revoked: BTreeSet::new(),
+        expiries: BTreeMap::new(),
+    };
+    let attrs = vec![
+        ("jurisdiction".to_owned(), b"US".to_vec()),
+        ("id".to_owned(), b"0001".to_vec()),
+    ];
+    assert!(registry.check(&attrs, 10).is_ok());
+
+    // the identifier must be revealed
+    assert!(registry.check(&attrs[..1], 10).is_err());
+
+    registry.revoked.insert(b"0001".to_vec());
+    assert!(registry.check(&attrs, 10).is_err());
+
+    registry.revoked.insert(b"0002".to_vec());
+    registry.revoked.remove(&b"0001".to_vec());
+    assert!(registry.check(&attrs, 10).is_ok());
+
+    // expired at height 10, and renewed
+    registry.expiries.insert(b"0001".to_vec(), 10);
+    assert!(registry.check(&attrs, 9).is_ok());
+    assert!(registry.check(&attrs, 10).is_err());
+    registry.expiries.insert(b"0001".to_vec(), 20);
+    assert!(registry.check(&attrs, 10).is_ok());
+}
+
+#[test]
+fn test_asset_rules_check() {
+    let mut prng = ChaChaRng::from_entropy();
+    let alice = XfrKeyPair::generate(&mut prng).get_pk();
+    let bob = XfrKeyPair::generate(&mut prng).get_pk();
+
+    let mut rules = AssetRules::default();
+    assert!(rules.check().is_ok());
+
+    rules.set_max_units(Some(0));
+    assert!(rules.check().is_err());
+    rules.set_max_units(Some(100));
+
+    // the threshold must be reachable by distinct keys
+    let mut multisig = SignatureRules {
+        threshold: 3,
+        weights: vec![(alice, 1), (bob, 1)],
+    };
+    rules.set_transfer_multisig_rules(Some(multisig.clone()));
+    assert!(rules.check().is_err());
+    multisig.weights.push((alice, 1));
+    rules.set_transfer_multisig_rules(Some(multisig.clone()));
+    assert!(rules.check().is_err());
+    multisig.weights.pop();
+    multisig.threshold = 2;
+    rules.set_transfer_multisig_rules(Some(multisig));
+    assert!(rules.check().is_ok());
+
+    rules.set_tracing_admin(Some(alice));
+    assert!(rules.check().is_err());
+}
+
+#[test]
+fn test_asset_metadata() {
+    let mut metadata = AssetMetadata {
+        name: "Findora Gold".to_owned(),
+        symbol: "FGLD".to_owned(),
+        uri: Some("ipfs://QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG".to_owned()),
+        content_hash: Some("ab".repeat(32)),
+    };
+    let memo = pnk!(metadata.to_memo());
+    assert_eq!(AssetMetadata::from_memo(&memo), Some(metadata.clone()));
+    assert!(AssetMetadata::check_memo(&memo).is_ok());
+
+    // free texts are not metadata
+    let text = Memo("some asset".to_owned());
+    assert!(AssetMetadata::from_memo(&text).is_none());
+    assert!(AssetMetadata::check_memo(&text).is_ok());
+
+    metadata.symbol = "F GLD".to_owned();
+    assert!(metadata.to_memo().is_err());
+    let memo = Memo(pnk!(serde_json::to_string(&metadata)));
+    assert!(AssetMetadata::from_memo(&memo).is_none());
+    assert!(AssetMetadata::check_memo(&memo).is_err());
+
+    metadata.symbol = "FGLD".to_owned();
+    metadata.uri = None;
+    assert!(metadata.check().is_err());
+    metadata.content_hash = None;
+    assert!(metadata.check().is_ok());
+}
@@ -0,0 +1,238 @@
+//!
+//! # Epoch Rewards
+//!
+//! From `CFG.checkpoint.epoch_rewards_height`, delegation rewards are not credited
+//! to every delegation block by block anymore:
+//!
+//! - each block adds the delegation rewards of its proposer to the pool of the proposer
+//! - at the close of an epoch, every `EPOCH_BLOCK_CNT` blocks, each pool is distributed,
+//!   the commission to the validator, the rest pro-rata to the amounts bonded to
+//!   the validator at that time; the remainders of the divisions go to the validator
+//! - every distribution is recorded, see `EpochRecord`
+//!
+//! The shares are not weighted by the time bonded within the epoch: the amounts
+//! bonded at the close share the whole pool, those undelegated before the close
+//! get nothing of it.
+//!
+//! The pools of the validators which have left the validator set, which have no
+//! amount bonded at the close or an invalid commission rate are dropped, recorded
+//! in `EpochRecord::dropped`.
+//!
+
+use {
+    super::{
+        calculate_delegation_rewards, Amount, BlockHeight, DelegationRwdDetail,
+        DelegationState, Staking, CHAN_D_RWD_HIST, CHAN_EPOCH_HIST, KEEP_HIST,
+    },
+    config::abci::global_cfg::CFG,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// The number of blocks of an epoch.
+pub const EPOCH_BLOCK_CNT: BlockHeight = 600;
+
+/// The rewards accumulated in the current epoch.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EpochState {
+    /// the first height of the current epoch
+    pub start_height: BlockHeight,
+    /// validator => rewards to distribute at the close
+    pub pools: BTreeMap<XfrPublicKey, Amount>,
+}
+
+/// The distribution of the pool of a validator.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ValidatorEpochRecord {
+    /// the rewards accumulated in the epoch
+    pub pool: Amount,
+    #[allow(missing_docs)]
+    pub commission_rate: [u64; 2],
+    /// the part of the pool paid to the validator
+    pub commission: Amount,
+    /// the total amount bonded to the validator at the close
+    pub bonded: Amount,
+    /// the number of delegations sharing the pool
+    pub delegation_cnt: u64,
+}
+
+/// The distributions at the close of an epoch.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EpochRecord {
+    /// `end_height / EPOCH_BLOCK_CNT`
+    pub epoch: u64,
+    #[allow(missing_docs)]
+    pub start_height: BlockHeight,
+    #[allow(missing_docs)]
+    pub end_height: BlockHeight,
+    #[allow(missing_docs)]
+    pub validators: BTreeMap<XfrPublicKey, ValidatorEpochRecord>,
+    /// the pools not distributed, see the module documentation
+    #[serde(default)]
+    pub dropped: BTreeMap<XfrPublicKey, Amount>,
+}
+
+impl Staking {
+    /// Add the delegation rewards of the current block to the pool of its proposer.
+    pub(crate) fn epoch_accumulate(
+        &mut self,
+        proposer: &XfrPublicKey,
+        return_rate: [u128; 2],
+        total_delegation_amount_of_validator: Amount,
+        global_delegation_amount: Amount,
+    ) -> Result<()> {
+        if 0 == total_delegation_amount_of_validator {
+            return Ok(());
+        }
+
+        let n = calculate_delegation_rewards(
+            return_rate,
+            total_delegation_amount_of_validator,
+            total_delegation_amount_of_validator,
+            global_delegation_amount,
+            true,
+            self.cur_height,
+        )
+        .c(d!())?;
+
+        // the first epoch starts from the first block after the checkpoint
+        if 0 == self.epoch.start_height {
+            self.epoch.start_height = self.cur_height;
+        }
+        let pool = self.epoch.pools.entry(*proposer).or_insert(0);
+        *pool = pool.saturating_add(n);
+
+        Ok(())
+    }
+
+    /// Distribute the pools if the current height closes an epoch.
+    ///
+    /// All the distributions are computed before anything is changed,
+    /// the pools which can not be distributed are dropped and recorded.
+    pub fn epoch_try_close(&mut self) -> Result<()> {
+        let h = self.cur_height;
+        if CFG.checkpoint.epoch_rewards_height >= h || 0 != h % EPOCH_BLOCK_CNT {
+            return Ok(());
+        }
+
+        let mut record = EpochRecord {
+            epoch: h / EPOCH_BLOCK_CNT,
+            start_height: self.epoch.start_height,
+            end_height: h,
+            validators: BTreeMap::new(),
+            dropped: BTreeMap::new(),
+        };
+
+        // validator => (commission rate, delegator => rewards, commission)
+        let mut payments = vec![];
+        let mut coinbase_bl = self.coinbase_balance();
+        for (pk, pool) in self.epoch.pools.iter() {
+            let commission_rate = match self.validator_get_current_one_by_id(pk) {
+                Some(v)
+                    if 0 < v.commission_rate[1]
+                        && v.commission_rate[0] <= v.commission_rate[1] =>
+                {
+                    v.commission_rate
+                }
+                // left the validator set, or an invalid commission rate
+                _ => {
+                    record.dropped.insert(*pk, *pool);
+                    continue;
+                }
+            };
+
+            let shares = self
+                .delegation_info
+                .global_delegation_records_map
+                .iter()
+                .filter(|(_, d)| DelegationState::Bond == d.state && h <= d.end_height)
+                .filter_map(|(id, d)| {
+                    d.delegations
+                        .get(pk)
+                        .filter(|am| 0 < **am)
+                        .map(|am| (*id, *am))
+                })
+                .collect::<Vec<_>>();
+            let bonded = shares.iter().map(|(_, am)| *am).sum::<Amount>();
+            if 0 == bonded {
+                record.dropped.insert(*pk, *pool);
+                continue;
+            }
+
+            let pool = (*pool).min(coinbase_bl);
+            let commission = (pool as u128 * commission_rate[0] as u128
+                / commission_rate[1] as u128) as Amount;
+            let distributable = pool - commission;
+
+            let rewards = shares
+                .iter()
+                .map(|(id, am)| {
+                    // NOTE: the `div` calculation is safe here
+                    let n =
+                        (distributable as u128 * *am as u128 / bonded as u128) as Amount;
+                    (*id, n)
+                })
+                .collect::<Vec<_>>();
+            let distributed = rewards.iter().map(|(_, n)| *n).sum::<Amount>();
+
+            // the remainders of the divisions go to the validator
+            let commission = pool - distributed;
+            coinbase_bl = coinbase_bl.saturating_sub(pool);
+
+            record.validators.insert(
+                *pk,
+                ValidatorEpochRecord {
+                    pool,
+                    commission_rate,
+                    commission,
+                    bonded,
+                    delegation_cnt: shares.len() as u64,
+                },
+            );
+            payments.push((*pk, commission_rate, rewards, commission));
+        }
+
+        self.epoch = EpochState {
+            start_height: 1 + h,
+            pools: BTreeMap::new(),
+        };
+
+        for (pk, commission_rate, rewards, commission) in payments {
+            for (id, n) in rewards {
+                if let Some(d) = self.delegation_get_mut(&id) {
+                    d.rwd_amount = d.rwd_amount.saturating_add(n);
+                    d.delegation_rwd_cnt += 1;
+                    if *KEEP_HIST {
+                        let r = DelegationRwdDetail {
+                            bond: d.amount(),
+                            amount: n,
+                            penalty_amount: 0,
+                            return_rate: None,
+                            commission_rate: Some(commission_rate),
+                            global_delegation_percent: None,
+                            block_height: h,
+                        };
+                        CHAN_D_RWD_HIST.0.lock().send((id, h, r)).unwrap();
+                    }
+                }
+            }
+            if let Some(d) = self.delegation_get_mut(&pk) {
+                d.rwd_amount = d.rwd_amount.saturating_add(commission);
+            }
+        }
+
+        if *KEEP_HIST {
+            CHAN_EPOCH_HIST.0.lock().send(record).unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// The rewards accumulated in the current epoch.
+    #[inline(always)]
+    pub fn epoch_state(&self) -> &EpochState {
+        &self.epoch
+    }
+}
@@ -54,6 +54,7 @@ impl TryFrom<ValidatorStr> for Validator {
             kind: v.kind.unwrap_or(ValidatorKind::Initiator),
             signed_last_block: false,
             signed_cnt: 0,
+            jailed: false,
             delegators: IndexMap::new(),
         })
     }
@@ -12,23 +12,22 @@
 #![deny(missing_docs)]
 #![allow(clippy::upper_case_acronyms)]
 
-#[cfg(not(target_arch = "wasm32"))]
 use {num_bigint::BigUint, std::convert::TryFrom};
 
 pub mod cosig;
+pub mod epoch;
 pub mod init;
 pub mod ops;
 
 use {
-    crate::{
-        data_model::{
-            ConsensusRng, Operation, Transaction, TransferAsset, TxoRef, FRA_DECIMALS,
-        },
-        SNAPSHOT_ENTRIES_DIR,
+    crate::data_model::{
+        is_default, ConsensusRng, Operation, Transaction, TransferAsset, TxoRef,
+        FRA_DECIMALS,
     },
     config::abci::global_cfg::CFG,
     cosig::CoSigRule,
     cryptohash::sha256::{self, Digest},
+    epoch::{EpochRecord, EpochState},
     fbnc::{new_mapx, Mapx},
     globutils::wallet,
     indexmap::IndexMap,
@@ -65,6 +64,12 @@ type DAHCP = (Arc<Mutex<Sender<DAH>>>, Arc<Mutex<Receiver<DAH>>>);
 // pk, height, <struct DelegationRwdDetail>
 type DRH = (XfrPublicKey, BlockHeight, DelegationRwdDetail);
 type DRHCP = (Arc<Mutex<Sender<DRH>>>, Arc<Mutex<Receiver<DRH>>>);
+// effective height, <struct ValidatorSetChange>
+type VSH = (BlockHeight, ValidatorSetChange);
+type VSHCP = (Arc<Mutex<Sender<VSH>>>, Arc<Mutex<Receiver<VSH>>>);
+// <struct EpochRecord>
+type EPH = EpochRecord;
+type EPHCP = (Arc<Mutex<Sender<EPH>>>, Arc<Mutex<Receiver<EPH>>>);
 
 macro_rules! chan {
     () => {{
@@ -96,6 +101,10 @@ lazy_static! {
     pub static ref CHAN_D_AMOUNT_HIST: DAHCP = chan!();
     #[allow(missing_docs)]
     pub static ref CHAN_D_RWD_HIST: DRHCP = chan!();
+    #[allow(missing_docs)]
+    pub static ref CHAN_V_SET_HIST: VSHCP = chan!();
+    #[allow(missing_docs)]
+    pub static ref CHAN_EPOCH_HIST: EPHCP = chan!();
 }
 
 // Reserved accounts of Findora Foundation.
@@ -232,11 +241,20 @@ pub struct Staking {
     // FRA CoinBase.
     coinbase: CoinBase,
     cr: ConsensusRng,
+    // rewards accumulated in the current epoch,
+    // default until `CFG.checkpoint.epoch_rewards_height`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    epoch: EpochState,
 }
 
+/// The directory of the entries of the ledger without prefix,
+/// used by the instances not created for a ledger, e.g. the simulators.
+pub const DEFAULT_ENTRIES_DIR: &str = "ledger_status_subdata";
+
 impl Default for Staking {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_ENTRIES_DIR)
     }
 }
 
@@ -253,17 +271,19 @@ impl Staking {
         XfrKeyPair::generate(cr).get_pk()
     }
 
+    /// The entries on disk, e.g. the history of the CoinBase,
+    /// are kept under `entries_dir`, apart for each ledger.
     #[inline(always)]
-    #[allow(missing_docs)]
-    pub fn new() -> Self {
+    pub fn new(entries_dir: &str) -> Self {
         Staking {
             // use '0' instead of '1' to
             // avoid conflicts with initial operations
             validator_info: map! {B 0 => ValidatorData::default()},
             delegation_info: DelegationInfo::new(),
             cur_height: 0,
-            coinbase: CoinBase::gen(),
+            coinbase: CoinBase::gen(entries_dir),
             cr: ConsensusRng::default(),
+            epoch: EpochState::default(),
         }
     }
 
@@ -285,6 +305,18 @@ impl Staking {
         }
     }
 
+    /// record a change of the validator set of tendermint
+    #[inline(always)]
+    pub fn record_validator_set_change(&self, change: ValidatorSetChange) {
+        if *KEEP_HIST {
+            CHAN_V_SET_HIST
+                .0
+                .lock()
+                .send((change.effective_height, change))
+                .unwrap();
+        }
+    }
+
     ///get the delegationInfo
     pub fn delegation_info_global_amount(&self) -> Amount {
         self.delegation_info.global_amount
@@ -422,6 +454,7 @@ impl Staking {
                 vs.body.iter_mut().for_each(|(k, v)| {
                     if let Some(pv) = prev.body.remove(k) {
                         v.td_power = pv.td_power;
+                        v.jailed = pv.jailed;
                     }
                 });
                 // out-dated validators should be removed from tendermint,
@@ -582,6 +615,93 @@ impl Staking {
         }
     }
 
+    /// The amount a validator self-delegates, zero once undelegated.
+    pub fn validator_self_delegation(&self, id: &XfrPublicKey) -> Amount {
+        self.delegation_get(id)
+            .filter(|d| {
+                DelegationState::Bond == d.state && BLOCK_HEIGHT_MAX == d.end_height
+            })
+            .and_then(|d| d.delegations.get(id).copied())
+            .unwrap_or(0)
+    }
+
+    /// Jail the current validators self-delegating less than
+    /// `CFG.checkpoint.min_self_delegation`, return the newly jailed ones.
+    ///
+    /// Jailed validators are removed from tendermint, see `Validator::jailed`;
+    /// at least `VALIDATORS_MIN` validators are left unjailed,
+    /// the lowest self-delegations are jailed first.
+    pub fn validator_jail_under_min_self_delegation(&mut self) -> Vec<XfrPublicKey> {
+        let min = CFG.checkpoint.min_self_delegation;
+        if 0 == min || CFG.checkpoint.min_self_delegation_height >= self.cur_height {
+            return vec![];
+        }
+        self.validator_jail_under(min)
+    }
+
+    // The checkpoints apart, see `validator_jail_under_min_self_delegation`.
+    pub(crate) fn validator_jail_under(&mut self, min: Amount) -> Vec<XfrPublicKey> {
+        let jailed = self
+            .validator_get_current()
+            .map(|vd| {
+                let unjailed = vd.body.values().filter(|v| !v.jailed).count();
+                let mut under = vd
+                    .body
+                    .values()
+                    .filter(|v| !v.jailed)
+                    .map(|v| (self.validator_self_delegation(&v.id), v.id))
+                    .filter(|(am, _)| min > *am)
+                    .collect::<Vec<_>>();
+                under.sort_unstable();
+                under
+                    .into_iter()
+                    .take(unjailed.saturating_sub(VALIDATORS_MIN))
+                    .map(|(_, id)| id)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        if let Some(vd) = self.validator_get_current_mut() {
+            jailed.iter().for_each(|id| {
+                if let Some(v) = vd.body.get_mut(id) {
+                    v.jailed = true;
+                }
+            });
+        }
+
+        jailed
+    }
+
+    /// Bring a jailed validator back to tendermint,
+    /// its self-delegation must have been topped up to the minimum.
+    #[inline(always)]
+    pub fn validator_unjail(&mut self, id: &XfrPublicKey) -> Result<()> {
+        self.validator_unjail_with_min(id, CFG.checkpoint.min_self_delegation)
+            .c(d!())
+    }
+
+    pub(crate) fn validator_unjail_with_min(
+        &mut self,
+        id: &XfrPublicKey,
+        min: Amount,
+    ) -> Result<()> {
+        let self_delegation = self.validator_self_delegation(id);
+        let v = self
+            .validator_get_current_mut_one_by_id(id)
+            .c(d!("not a validator"))?;
+        if !v.jailed {
+            return Err(eg!("not jailed"));
+        }
+        if min > self_delegation {
+            return Err(eg!(format!(
+                "self-delegation too low: {}, min: {}",
+                self_delegation, min
+            )));
+        }
+        v.jailed = false;
+        Ok(())
+    }
+
     ///replace_staker
     pub fn check_and_replace_staker(
         &mut self,
@@ -828,6 +948,80 @@ impl Staking {
         Ok(())
     }
 
+    /// Start the delegations of a transaction, all of them or none:
+    /// when one of them fails, the ones already started are reverted.
+    pub fn delegate_all(
+        &mut self,
+        delegations: &[(XfrPublicKey, TendermintAddrRef, Amount)],
+    ) -> Result<()> {
+        // the states changed by `delegate`, saved before the first change of each
+        let mut records = BTreeMap::new();
+        let mut validators = BTreeMap::new();
+        let amounts = (
+            self.delegation_info.global_amount,
+            self.coinbase.principal_balance,
+        );
+
+        for (owner, validator, am) in delegations.iter() {
+            if !records.contains_key(owner) {
+                let d = self.delegation_get(owner).cloned();
+                let indexed = d.as_ref().map_or(false, |d| {
+                    self.delegation_info
+                        .end_height_map
+                        .get(&d.end_height)
+                        .map_or(false, |set| set.contains(owner))
+                });
+                records.insert(*owner, (d, indexed));
+            }
+            if let Some(v) = self
+                .validator_td_addr_to_app_pk(validator)
+                .ok()
+                .and_then(|id| self.validator_get_current_one_by_id(&id))
+            {
+                validators.entry(v.id).or_insert_with(|| v.clone());
+            }
+
+            if let Err(e) = self.delegate(*owner, validator, *am) {
+                self.delegate_revert(records, validators, amounts);
+                return Err(e).c(d!());
+            }
+        }
+
+        Ok(())
+    }
+
+    // Restore the states saved by `delegate_all`.
+    fn delegate_revert(
+        &mut self,
+        records: BTreeMap<XfrPublicKey, (Option<Delegation>, bool)>,
+        validators: BTreeMap<XfrPublicKey, Validator>,
+        (global_amount, principal_balance): (Amount, Amount),
+    ) {
+        let info = &mut self.delegation_info;
+        for (owner, (d, indexed)) in records.into_iter() {
+            if let Some(set) = info.end_height_map.get_mut(&BLOCK_HEIGHT_MAX) {
+                set.remove(&owner);
+            }
+            if let Some(d) = d {
+                if indexed {
+                    info.end_height_map
+                        .entry(d.end_height)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(owner);
+                }
+                info.global_delegation_records_map.insert(owner, d);
+            } else {
+                info.global_delegation_records_map.remove(&owner);
+            }
+        }
+        info.global_amount = global_amount;
+        self.coinbase.principal_balance = principal_balance;
+
+        if let Some(vd) = self.validator_get_current_mut() {
+            vd.body.extend(validators);
+        }
+    }
+
     /// When un-delegation happens,
     /// - decrease the vote power of the co-responding validator
     pub fn undelegate(
@@ -1608,7 +1802,6 @@ impl Staking {
         &self.coinbase.distribution_plan
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
     /// set_proposer_rewards sets the rewards for the block proposer
     /// All rewards are allocated to the proposer only
     pub(crate) fn set_proposer_rewards(
@@ -1650,7 +1843,6 @@ impl Staking {
             .map(|_| ())
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
     fn get_proposer_rewards_rate(vote_percent: [u64; 2]) -> Result<[u128; 2]> {
         let p = [vote_percent[0] as u128, vote_percent[1] as u128];
         // p[0] = Validator power which voted for this block
@@ -1949,6 +2141,11 @@ pub struct Validator {
     pub signed_last_block: bool,
     /// how many blocks has the validator signed
     pub signed_cnt: u64,
+    /// removed from tendermint for a self-delegation below
+    /// `CFG.checkpoint.min_self_delegation`, until unjailed
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub jailed: bool,
 
     /// delegator pubkey => amount
     ///   - delegator entries on current block height
@@ -1979,6 +2176,7 @@ impl Validator {
             kind,
             signed_last_block: false,
             signed_cnt: 0,
+            jailed: false,
             delegators: IndexMap::new(),
         })
     }
@@ -2103,7 +2301,6 @@ impl Delegation {
     }
 
     #[inline(always)]
-    #[cfg(not(target_arch = "wasm32"))]
     pub(crate) fn validator_entry_exists(&self, validator: &XfrPublicKey) -> bool {
         self.delegations.contains_key(validator)
     }
@@ -2123,7 +2320,6 @@ impl Delegation {
     // > **NOTE:**
     // > use 'AssignAdd' instead of 'Assign'
     // > to keep compatible with the logic of governance penalty.
-    #[cfg(not(target_arch = "wasm32"))]
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn set_delegation_rewards(
         &mut self,
@@ -2212,7 +2408,6 @@ impl Delegation {
 
 // Calculate the amount(in FRA units) that
 // should be paid to the owner of this delegation.
-#[cfg(not(target_arch = "wasm32"))]
 fn calculate_delegation_rewards(
     return_rate: [u128; 2],
     amount: Amount,
@@ -2386,16 +2581,16 @@ struct CoinBase {
 
 impl Default for CoinBase {
     fn default() -> Self {
-        Self::gen()
+        Self::gen(DEFAULT_ENTRIES_DIR)
     }
 }
 
 impl CoinBase {
-    fn gen() -> Self {
+    fn gen(entries_dir: &str) -> Self {
         CoinBase {
             distribution_hist: new_mapx!(&format!(
                 "{}/staking/coinbase/distribution_hist",
-                SNAPSHOT_ENTRIES_DIR.as_str()
+                entries_dir
             )),
             distribution_plan: BTreeMap::new(),
             balance: ops::mint_fra::MINT_AMOUNT_LIMIT,
@@ -2410,6 +2605,20 @@ pub fn td_pubkey_to_td_addr(pubkey: &[u8]) -> String {
     hex::encode_upper(&sha2::Sha256::digest(pubkey)[..20])
 }
 
+/// A change of the validator set of tendermint,
+/// returned in the `EndBlock` of `height`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ValidatorSetChange {
+    #[allow(missing_docs)]
+    pub height: BlockHeight,
+    /// tendermint applies the updates two blocks later
+    pub effective_height: BlockHeight,
+    /// the new vote powers, a zero power removes the validator
+    pub updates: BTreeMap<TendermintAddr, Amount>,
+    /// the vote powers of the whole set after the updates
+    pub validators: BTreeMap<TendermintAddr, Amount>,
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub fn td_pubkey_to_td_addr_bytes(pubkey: &[u8]) -> Vec<u8> {
@@ -3,6 +3,11 @@
 //!
 //! Data representation required when users propose a delegation.
 //!
+//! A transaction can carry several delegations of a same delegator to different
+//! validators, paid by a single principal, the sum of their amounts. They take
+//! effect all together or not at all, and none of them can stake a new validator,
+//! which needs a transaction of its own.
+//!
 
 use {
     crate::{
@@ -46,6 +51,30 @@ impl DelegationOps {
         self.apply(staking_simulator, tx).c(d!())
     }
 
+    /// Check the validity of the delegations of a transaction
+    /// by running them in a staking simulator, all of them or none.
+    pub fn check_run_all(
+        ops: &[DelegationOps],
+        staking_simulator: &mut Staking,
+        tx: &Transaction,
+    ) -> Result<()> {
+        if let [op] = ops {
+            return op.check_run(staking_simulator, tx).c(d!());
+        }
+
+        let mut delegations = Vec::with_capacity(ops.len());
+        for op in ops.iter() {
+            if op.body.new_validator.is_some() {
+                return Err(eg!("a new validator must stake in a transaction of its own"));
+            }
+            op.verify().c(d!())?;
+            check_delegation_context(tx, op.pubkey).c(d!())?;
+            delegations.push((op.pubkey, op.body.validator.as_str(), op.body.amount));
+        }
+
+        staking_simulator.delegate_all(&delegations).c(d!())
+    }
+
     /// Apply new delegation to the target `Staking` instance.
     pub fn apply(&self, staking: &mut Staking, tx: &Transaction) -> Result<()> {
         self.verify()
@@ -86,7 +115,8 @@ impl DelegationOps {
         staking: &mut Staking,
         tx: &Transaction,
     ) -> Result<Amount> {
-        let am = check_delegation_context(tx).c(d!())?;
+        check_delegation_context(tx, self.pubkey).c(d!())?;
+        let am = self.body.amount;
 
         // Self Staking - New Validator
         if let Some(v) = self.body.new_validator.as_ref() {
@@ -193,26 +223,24 @@ impl Data {
     }
 }
 
-fn check_delegation_context(tx: &Transaction) -> Result<Amount> {
-    let owner = tx
-        .body
-        .operations
-        .iter()
-        .flat_map(|op| {
-            if let Operation::Delegation(ref x) = op {
-                Some((x.pubkey, x.body.amount))
-            } else {
-                None
+// The principal of `owner` pays for all its delegations in the transaction,
+// at most one per validator.
+fn check_delegation_context(tx: &Transaction, owner: XfrPublicKey) -> Result<Amount> {
+    let mut validators = HashSet::new();
+    let mut am: Amount = 0;
+    for op in tx.body.operations.iter() {
+        if let Operation::Delegation(ref x) = op {
+            if x.pubkey != owner {
+                continue;
             }
-        })
-        .collect::<Vec<_>>();
-
-    // only one delegation operation is allowed per transaction
-    if 1 != owner.len() {
-        return Err(eg!());
+            if !validators.insert(&x.body.validator) {
+                return Err(eg!("duplicate delegations to a validator"));
+            }
+            am = am.checked_add(x.body.amount).c(d!("overflow"))?;
+        }
     }
 
-    check_delegation_context_principal(tx, owner[0])
+    check_delegation_context_principal(tx, (owner, am))
         .c(d!("delegation amount is not paid correctly"))
 }
 
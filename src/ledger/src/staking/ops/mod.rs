@@ -14,5 +14,6 @@ pub mod governance;
 pub mod mint_fra;
 pub mod replace_staker;
 pub mod undelegation;
+pub mod unjail;
 pub mod update_staker;
 pub mod update_validator;
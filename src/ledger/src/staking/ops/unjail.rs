@@ -0,0 +1,102 @@
+//!
+//! # Unjail
+//!
+//! Bring a validator jailed for a low self-delegation back to tendermint,
+//! after topping up its self-delegation.
+//!
+
+use {
+    crate::{data_model::NoReplayToken, staking::Staking},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    zei::xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSignature},
+};
+
+/// Used as the inner object of an `Unjail Operation`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UnjailOps {
+    pub(crate) body: Data,
+    pub(crate) pubkey: XfrPublicKey,
+    signature: XfrSignature,
+}
+
+impl UnjailOps {
+    /// Check the validity of an operation by running it in a staking simulator.
+    #[inline(always)]
+    pub fn check_run(&self, staking_simulator: &mut Staking) -> Result<()> {
+        self.apply(staking_simulator).c(d!())
+    }
+
+    /// Unjail the validator in the target `Staking` instance.
+    pub fn apply(&self, staking: &mut Staking) -> Result<()> {
+        self.verify()
+            .c(d!())
+            .and_then(|_| staking.validator_unjail(&self.pubkey).c(d!()))
+    }
+
+    /// Verify signature.
+    #[inline(always)]
+    pub fn verify(&self) -> Result<()> {
+        self.pubkey
+            .verify(&self.body.to_bytes(), &self.signature)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_related_pubkeys(&self) -> Vec<XfrPublicKey> {
+        vec![self.pubkey]
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn new(keypair: &XfrKeyPair, nonce: NoReplayToken) -> Self {
+        let body = Data::new(nonce);
+        let signature = keypair.sign(&body.to_bytes());
+        UnjailOps {
+            body,
+            pubkey: keypair.get_pk(),
+            signature,
+        }
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.body.set_nonce(nonce);
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_nonce(&self) -> NoReplayToken {
+        self.body.get_nonce()
+    }
+}
+
+/// The body of an unjail operation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Data {
+    nonce: NoReplayToken,
+}
+
+impl Data {
+    #[inline(always)]
+    fn new(nonce: NoReplayToken) -> Self {
+        Data { nonce }
+    }
+
+    #[inline(always)]
+    fn to_bytes(&self) -> Vec<u8> {
+        pnk!(bincode::serialize(self))
+    }
+
+    #[inline(always)]
+    fn set_nonce(&mut self, nonce: NoReplayToken) {
+        self.nonce = nonce;
+    }
+
+    #[inline(always)]
+    fn get_nonce(&self) -> NoReplayToken {
+        self.nonce
+    }
+}
@@ -9,8 +9,10 @@ use {
             Transaction, TxOutput, TxnIDHash, TxnSID, TxoSID, XfrAddress,
         },
         staking::{
-            ops::mint_fra::MintEntry, Amount, BlockHeight, DelegationRwdDetail,
-            CHAN_D_AMOUNT_HIST, CHAN_GLOB_RATE_HIST, CHAN_V_SELF_D_HIST, KEEP_HIST,
+            epoch::EpochRecord, ops::mint_fra::MintEntry, Amount, BlockHeight,
+            DelegationRwdDetail, ValidatorSetChange, CHAN_D_AMOUNT_HIST,
+            CHAN_EPOCH_HIST, CHAN_GLOB_RATE_HIST, CHAN_V_SELF_D_HIST, CHAN_V_SET_HIST,
+            KEEP_HIST,
         },
         store::LedgerState,
     },
@@ -18,12 +20,31 @@ use {
     globutils::wallet,
     ruc::*,
     serde::{Deserialize, Serialize},
-    std::collections::HashSet,
-    zei::xfr::{sig::XfrPublicKey, structs::OwnerMemo},
+    std::collections::{BTreeMap, BTreeSet, HashSet},
+    zei::xfr::{
+        sig::XfrPublicKey,
+        structs::{OwnerMemo, XfrAmount, XfrAssetType},
+    },
 };
 
 type Issuances = Vec<(TxOutput, Option<OwnerMemo>)>;
 
+/// The number of characters of the addresses indexed for `SearchQuery::key_prefix`,
+/// after the network prefix, shorter prefixes can not be searched for
+pub const SEARCH_KEY_PREFIX_LEN: usize = 4;
+
+/// The default number of results of each kind of a search
+pub const SEARCH_LIMIT: usize = 100;
+
+const SEARCH_LIMIT_MAX: usize = 1000;
+
+/// The default number of blocks of `ApiCache::fee_history`
+pub const FEE_HISTORY_BLOCKS: u64 = 20;
+
+/// The maximal number of blocks of `ApiCache::fee_history`
+pub const FEE_HISTORY_BLOCKS_MAX: u64 = 1000;
+const MEMO_WORD_LEN: (usize, usize) = (2, 32);
+
 /// Used in APIs
 #[derive(Clone, Deserialize, Serialize)]
 pub struct ApiCache {
@@ -64,8 +85,29 @@ pub struct ApiCache {
     /// rewards history, used on some pulic nodes, such as fullnode
     pub staking_delegation_rwd_hist:
         Mapx<XfrPublicKey, Mapxnk<BlockHeight, DelegationRwdDetail>>,
+    /// changes of the validator set, by effective height
+    pub staking_validator_set_hist: Mapxnk<BlockHeight, ValidatorSetChange>,
+    /// distributions of the rewards at the close of every epoch
+    pub staking_epoch_hist: Mapxnk<u64, EpochRecord>,
     /// there are no transactions lost before last_sid
     pub last_sid: Mapx<String, u64>,
+    /// words of the memos of transactions and assets, see `memo_words`
+    pub memo_words: Mapx<String, Mapxnk<TxnSID, bool>>,
+    /// non-confidential outputs, `(asset, amount)`, bucketed by the bit length of their amounts
+    pub amount_buckets: Mapxnk<u64, Mapxnk<TxoSID, (AssetTypeCode, u64)>>,
+    /// related addresses, by the first `SEARCH_KEY_PREFIX_LEN` characters of their
+    /// bech32 forms after the network prefix
+    pub address_prefixes: Mapx<String, BTreeSet<XfrAddress>>,
+    /// defined assets, by their base64 codes
+    pub asset_codes: Mapx<String, AssetTypeCode>,
+    /// the statistics of assets by tendermint heights,
+    /// only the blocks touching them are recorded
+    pub asset_stats: Mapx<AssetTypeCode, Mapxnk<BlockHeight, AssetBlockStats>>,
+    /// the number of unspent outputs of assets owned by each address
+    pub asset_holders: Mapx<AssetTypeCode, Mapx<XfrAddress, u64>>,
+    /// the fees paid in the blocks, by tendermint heights,
+    /// only the blocks with transactions are recorded
+    pub fee_hist: Mapxnk<BlockHeight, BlockFees>,
 }
 
 impl ApiCache {
@@ -115,10 +157,411 @@ impl ApiCache {
                 "api_cache/{}staking_delegation_rwd_hist",
                 prefix
             )),
+            staking_validator_set_hist: new_mapxnk!(format!(
+                "api_cache/{}staking_validator_set_hist",
+                prefix
+            )),
+            staking_epoch_hist: new_mapxnk!(format!(
+                "api_cache/{}staking_epoch_hist",
+                prefix
+            )),
             last_sid: new_mapx!(format!("api_cache/{}last_sid", prefix)),
+            memo_words: new_mapx!(format!("api_cache/{}memo_words", prefix)),
+            amount_buckets: new_mapxnk!(format!("api_cache/{}amount_buckets", prefix)),
+            address_prefixes: new_mapx!(format!("api_cache/{}address_prefixes", prefix)),
+            asset_codes: new_mapx!(format!("api_cache/{}asset_codes", prefix)),
+            asset_stats: new_mapx!(format!("api_cache/{}asset_stats", prefix)),
+            asset_holders: new_mapx!(format!("api_cache/{}asset_holders", prefix)),
+            fee_hist: new_mapxnk!(format!("api_cache/{}fee_hist", prefix)),
         }
     }
 
+    /// Index a transaction and its outputs for `search`.
+    pub fn index_for_search(
+        &mut self,
+        txn_sid: TxnSID,
+        txn: &Transaction,
+        outputs: &[(TxoSID, TxOutput)],
+        related_addresses: &HashSet<XfrAddress>,
+    ) {
+        let prefix = self.prefix.clone();
+
+        let mut texts = txn
+            .body
+            .memos
+            .iter()
+            .map(|m| m.0.as_str())
+            .collect::<Vec<_>>();
+        for op in txn.body.operations.iter() {
+            match op {
+                Operation::DefineAsset(i) => {
+                    let code = i.body.asset.code;
+                    self.asset_codes.insert(code.to_base64(), code);
+                    texts.push(i.body.asset.memo.0.as_str());
+                }
+                Operation::UpdateMemo(i) => texts.push(i.body.new_memo.0.as_str()),
+                _ => {}
+            }
+        }
+        for word in texts
+            .into_iter()
+            .flat_map(memo_words)
+            .collect::<BTreeSet<_>>()
+        {
+            self.memo_words
+                .entry(word.clone())
+                .or_insert_with(|| {
+                    new_mapxnk!(format!("api_cache/{}memo_words/{}", prefix, word))
+                })
+                .insert(txn_sid, Default::default());
+        }
+
+        for (sid, o) in outputs.iter() {
+            if let (XfrAssetType::NonConfidential(val), XfrAmount::NonConfidential(am)) =
+                (o.record.asset_type, o.record.amount)
+            {
+                let bucket = amount_bucket(am);
+                self.amount_buckets
+                    .entry(bucket)
+                    .or_insert_with(|| {
+                        new_mapxnk!(format!(
+                            "api_cache/{}amount_buckets/{}",
+                            prefix, bucket
+                        ))
+                    })
+                    .insert(*sid, (AssetTypeCode { val }, am));
+            }
+        }
+
+        for addr in related_addresses.iter() {
+            let key = address_data(&wallet::public_key_to_bech32(&addr.key))
+                .chars()
+                .take(SEARCH_KEY_PREFIX_LEN)
+                .collect::<String>();
+            #[allow(unused_mut)]
+            let mut addrs = self
+                .address_prefixes
+                .entry(key)
+                .or_insert_with(BTreeSet::new);
+            addrs.insert(*addr);
+        }
+    }
+
+    /// Update the statistics of the assets touched by a transaction
+    /// of the block at `height`, see `AssetBlockStats`.
+    pub fn cache_asset_stats(
+        &mut self,
+        height: BlockHeight,
+        txn: &Transaction,
+        outputs: &[(TxoSID, TxOutput)],
+    ) {
+        let prefix = self.prefix.clone();
+        let mut deltas: BTreeMap<
+            AssetTypeCode,
+            (AssetBlockStats, BTreeMap<XfrAddress, i64>),
+        > = BTreeMap::new();
+
+        for op in txn.body.operations.iter() {
+            match op {
+                Operation::IssueAsset(i) => {
+                    let d = &mut deltas.entry(i.body.code).or_default().0;
+                    d.issuances += 1;
+                    for (o, _) in i.body.records.iter() {
+                        if let XfrAmount::NonConfidential(am) = o.record.amount {
+                            d.issued = d.issued.saturating_add(am);
+                        }
+                    }
+                }
+                Operation::TransferAsset(t) => {
+                    let mut touched = BTreeSet::new();
+                    let mut senders = HashSet::new();
+                    for i in t.body.transfer.inputs.iter() {
+                        senders.insert(i.public_key);
+                        if let XfrAssetType::NonConfidential(val) = i.asset_type {
+                            let code = AssetTypeCode { val };
+                            touched.insert(code);
+                            *deltas
+                                .entry(code)
+                                .or_default()
+                                .1
+                                .entry(XfrAddress { key: i.public_key })
+                                .or_insert(0) -= 1;
+                        }
+                    }
+                    for o in t.body.outputs.iter() {
+                        if let XfrAssetType::NonConfidential(val) = o.record.asset_type {
+                            let code = AssetTypeCode { val };
+                            touched.insert(code);
+                            if let XfrAmount::NonConfidential(am) = o.record.amount {
+                                if !senders.contains(&o.record.public_key) {
+                                    let d = &mut deltas.entry(code).or_default().0;
+                                    d.volume = d.volume.saturating_add(am);
+                                }
+                            }
+                        }
+                    }
+                    for code in touched {
+                        deltas.entry(code).or_default().0.transfers += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (_, o) in outputs.iter() {
+            if let XfrAssetType::NonConfidential(val) = o.record.asset_type {
+                *deltas
+                    .entry(AssetTypeCode { val })
+                    .or_default()
+                    .1
+                    .entry(XfrAddress {
+                        key: o.record.public_key,
+                    })
+                    .or_insert(0) += 1;
+            }
+        }
+
+        for (code, (d, holder_deltas)) in deltas.into_iter() {
+            let mut holders_delta = 0i64;
+            #[allow(unused_mut)]
+            let mut holders = self.asset_holders.entry(code).or_insert_with(|| {
+                new_mapx!(format!(
+                    "api_cache/{}asset_holders/{}",
+                    prefix,
+                    code.to_base64()
+                ))
+            });
+            for (addr, delta) in holder_deltas.into_iter() {
+                let n = holders.get(&addr).unwrap_or(0);
+                let m = (n as i64 + delta).max(0) as u64;
+                match (0 == n, 0 == m) {
+                    (true, false) => holders_delta += 1,
+                    (false, true) => holders_delta -= 1,
+                    _ => {}
+                }
+                holders.insert(addr, m);
+            }
+
+            #[allow(unused_mut)]
+            let mut hist = self.asset_stats.entry(code).or_insert_with(|| {
+                new_mapxnk!(format!(
+                    "api_cache/{}asset_stats/{}",
+                    prefix,
+                    code.to_base64()
+                ))
+            });
+            let mut s = hist.get(&height).unwrap_or_else(|| {
+                hist.get_closest_smaller(&height)
+                    .map(|(_, s)| AssetBlockStats {
+                        holders: s.holders,
+                        supply: s.supply,
+                        ..Default::default()
+                    })
+                    .unwrap_or_default()
+            });
+            s.holders = (s.holders as i64 + holders_delta).max(0) as u64;
+            s.transfers += d.transfers;
+            s.volume = s.volume.saturating_add(d.volume);
+            s.issuances += d.issuances;
+            s.issued = s.issued.saturating_add(d.issued);
+            s.supply = s.supply.saturating_add(d.issued);
+            hist.insert(height, s);
+        }
+    }
+
+    /// The statistics of an asset over the blocks `[from, to]`,
+    /// `None` if it has never been issued nor transferred.
+    pub fn asset_stats(
+        &self,
+        code: &AssetTypeCode,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<Option<AssetStats>> {
+        if from > to {
+            return Err(eg!("empty block range"));
+        }
+        let hist = match self.asset_stats.get(code) {
+            Some(hist) => hist,
+            None => return Ok(None),
+        };
+
+        let last = hist
+            .get_closest_smaller(&to)
+            .map(|(_, s)| s)
+            .unwrap_or_default();
+        let mut res = AssetStats {
+            code: *code,
+            from,
+            to,
+            holders: last.holders,
+            supply: last.supply,
+            ..Default::default()
+        };
+        for (h, s) in hist.iter().filter(|(h, _)| (from..=to).contains(h)) {
+            res.transfers += s.transfers;
+            res.volume = res.volume.saturating_add(s.volume);
+            res.issuances += s.issuances;
+            res.issued = res.issued.saturating_add(s.issued);
+            res.history.push((h, s));
+        }
+        if 0 < res.supply {
+            res.velocity = res.volume as f64 / res.supply as f64;
+        }
+
+        Ok(Some(res))
+    }
+
+    /// The fees of the last `blocks` recorded blocks up to the tendermint height `to`,
+    /// `FEE_HISTORY_BLOCKS` by default, at most `FEE_HISTORY_BLOCKS_MAX`.
+    pub fn fee_history(&self, to: BlockHeight, blocks: Option<u64>) -> FeeHistory {
+        let n = blocks
+            .unwrap_or(FEE_HISTORY_BLOCKS)
+            .min(FEE_HISTORY_BLOCKS_MAX) as usize;
+
+        let mut hist = vec![];
+        let mut h = to;
+        while hist.len() < n {
+            match self.fee_hist.get_closest_smaller(&h) {
+                Some((k, fees)) => {
+                    hist.push((k, fees));
+                    if 0 == k {
+                        break;
+                    }
+                    h = k - 1;
+                }
+                None => break,
+            }
+        }
+        hist.reverse();
+
+        let total_fee = hist.iter().map(|(_, f)| f.total_fee).sum::<u64>();
+        let operations = hist.iter().map(|(_, f)| f.operations).sum::<u64>();
+        let mut per_op = hist
+            .iter()
+            .map(|(_, f)| f.avg_fee_per_op)
+            .collect::<Vec<_>>();
+        per_op.sort_unstable();
+
+        FeeHistory {
+            total_fee,
+            operations,
+            avg_fee_per_op: total_fee.checked_div(operations).unwrap_or(0),
+            median_fee_per_op: per_op.get(per_op.len() / 2).copied().unwrap_or(0),
+            blocks: hist,
+        }
+    }
+
+    /// Search the indexed data, the criteria of `q` are applied to
+    /// the kinds of results they are relevant to, see `SearchResult`.
+    pub fn search(&self, q: &SearchQuery) -> Result<SearchResult> {
+        let limit = q.limit.unwrap_or(SEARCH_LIMIT).min(SEARCH_LIMIT_MAX);
+        let code = q.code.as_ref().map(|c| c.to_lowercase());
+        let code_matches = |c: &str| {
+            code.as_ref()
+                .map(|code| c.to_lowercase().contains(code))
+                .unwrap_or(true)
+        };
+        let key_prefix = q
+            .key_prefix
+            .as_ref()
+            .map(|p| {
+                let p = address_data(p).to_lowercase();
+                if p.chars().count() < SEARCH_KEY_PREFIX_LEN {
+                    Err(eg!(format!(
+                        "at least {} characters after the network prefix",
+                        SEARCH_KEY_PREFIX_LEN
+                    )))
+                } else {
+                    Ok(p)
+                }
+            })
+            .transpose()?;
+        let key_matches = |addr: &XfrAddress| {
+            key_prefix
+                .as_ref()
+                .map(|p| {
+                    address_data(&wallet::public_key_to_bech32(&addr.key)).starts_with(p)
+                })
+                .unwrap_or(true)
+        };
+
+        let mut res = SearchResult::default();
+
+        if code.is_some() {
+            res.assets = self
+                .asset_codes
+                .iter()
+                .filter(|(c, _)| code_matches(c))
+                .map(|(_, c)| c)
+                .take(limit)
+                .collect();
+        }
+
+        if let Some(memo) = q.memo.as_ref() {
+            let words = memo_words(memo).collect::<BTreeSet<_>>();
+            if words.is_empty() {
+                return Err(eg!("no word to search for"));
+            }
+            let sets = words
+                .iter()
+                .map(|w| self.memo_words.get(w))
+                .collect::<Option<Vec<_>>>();
+            if let Some((first, others)) = sets.as_ref().and_then(|s| s.split_first()) {
+                res.txns = first
+                    .iter()
+                    .map(|(sid, _)| sid)
+                    .filter(|sid| others.iter().all(|s| s.contains_key(sid)))
+                    .take(limit)
+                    .collect();
+            }
+        }
+
+        if q.min_amount.is_some() || q.max_amount.is_some() {
+            let (lo, hi) = (q.min_amount.unwrap_or(0), q.max_amount.unwrap_or(u64::MAX));
+            if lo > hi {
+                return Err(eg!("empty amount range"));
+            }
+            'buckets: for bucket in amount_bucket(lo)..=amount_bucket(hi) {
+                let outputs = match self.amount_buckets.get(&bucket) {
+                    Some(o) => o,
+                    None => continue,
+                };
+                for (sid, (asset, am)) in outputs.iter() {
+                    if am < lo || am > hi || !code_matches(&asset.to_base64()) {
+                        continue;
+                    }
+                    let owner = match self.utxos_to_map_index.get(&sid) {
+                        Some(owner) if key_matches(&owner) => owner,
+                        _ => continue,
+                    };
+                    res.outputs.push(SearchOutput {
+                        sid,
+                        asset,
+                        amount: am,
+                        owner: wallet::public_key_to_bech32(&owner.key),
+                    });
+                    if res.outputs.len() >= limit {
+                        break 'buckets;
+                    }
+                }
+            }
+        }
+
+        if let Some(p) = key_prefix.as_ref() {
+            let key = p.chars().take(SEARCH_KEY_PREFIX_LEN).collect::<String>();
+            if let Some(addrs) = self.address_prefixes.get(&key) {
+                res.addresses = addrs
+                    .iter()
+                    .filter(|a| key_matches(a))
+                    .map(|a| wallet::public_key_to_bech32(&a.key))
+                    .take(limit)
+                    .collect();
+            }
+        }
+
+        Ok(res)
+    }
+
     /// Add created asset
     #[inline(always)]
     pub fn add_created_asset(&mut self, creation: &DefineAsset) {
@@ -193,6 +636,14 @@ impl ApiCache {
                     .insert(h, r);
             });
 
+        CHAN_V_SET_HIST.1.lock().try_iter().for_each(|(h, c)| {
+            self.staking_validator_set_hist.insert(h, c);
+        });
+
+        CHAN_EPOCH_HIST.1.lock().try_iter().for_each(|r| {
+            self.staking_epoch_hist.insert(r.epoch, r);
+        });
+
         //         CHAN_D_RWD_HIST.1.lock().try_iter().for_each(|(pk, h, r)| {
         // #[allow(unused_mut)]
         // let mut dd =
@@ -222,6 +673,159 @@ impl ApiCache {
     }
 }
 
+/// A search over the secondary indexes of `ApiCache`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SearchQuery {
+    /// a part of the base64 code of assets, case-insensitive
+    pub code: Option<String>,
+    /// words of the memos of transactions, or of the definitions and memo updates
+    /// of assets, all of them must be found, case-insensitive
+    pub memo: Option<String>,
+    /// the minimal amount of non-confidential outputs, inclusive
+    pub min_amount: Option<u64>,
+    /// the maximal amount of non-confidential outputs, inclusive
+    pub max_amount: Option<u64>,
+    /// a prefix of bech32 addresses, with or without the network prefix,
+    /// at least `SEARCH_KEY_PREFIX_LEN` characters after it
+    pub key_prefix: Option<String>,
+    /// the maximal number of results of each kind, `SEARCH_LIMIT` by default
+    pub limit: Option<usize>,
+}
+
+/// The results of a `SearchQuery`, each kind is only searched for
+/// if its main criterion is given, in the order of the indexes.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SearchResult {
+    /// the assets matching `code`
+    pub assets: Vec<AssetTypeCode>,
+    /// the transactions matching `memo`
+    pub txns: Vec<TxnSID>,
+    /// the outputs in the amount range, of the assets matching `code`,
+    /// owned by the addresses matching `key_prefix`, spent or not
+    pub outputs: Vec<SearchOutput>,
+    /// the addresses matching `key_prefix`, related to at least one transaction
+    pub addresses: Vec<String>,
+}
+
+/// A non-confidential output found by a search.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchOutput {
+    #[allow(missing_docs)]
+    pub sid: TxoSID,
+    #[allow(missing_docs)]
+    pub asset: AssetTypeCode,
+    #[allow(missing_docs)]
+    pub amount: u64,
+    /// in bech32
+    pub owner: String,
+}
+
+/// The statistics of an asset in a block.
+///
+/// Only the outputs of non-confidential asset types are counted,
+/// and only the non-confidential amounts are summed up.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AssetBlockStats {
+    /// the number of addresses owning unspent outputs after the block
+    pub holders: u64,
+    /// the number of transfer operations
+    pub transfers: u64,
+    /// the amount transferred, the change sent back to the senders excluded
+    pub volume: u64,
+    /// the number of issuances
+    pub issuances: u64,
+    /// the amount issued
+    pub issued: u64,
+    /// the total amount issued after the block
+    pub supply: u64,
+}
+
+/// The statistics of an asset over a range of blocks, see `AssetBlockStats`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AssetStats {
+    #[allow(missing_docs)]
+    pub code: AssetTypeCode,
+    /// the first tendermint height of the range
+    pub from: BlockHeight,
+    /// the last tendermint height of the range
+    pub to: BlockHeight,
+    /// at the end of the range
+    pub holders: u64,
+    /// at the end of the range
+    pub supply: u64,
+    #[allow(missing_docs)]
+    pub transfers: u64,
+    #[allow(missing_docs)]
+    pub volume: u64,
+    #[allow(missing_docs)]
+    pub issuances: u64,
+    #[allow(missing_docs)]
+    pub issued: u64,
+    /// `volume / supply`, how many times the supply changed hands in the range
+    pub velocity: f64,
+    /// the blocks of the range touching the asset
+    pub history: Vec<(BlockHeight, AssetBlockStats)>,
+}
+
+/// The fees paid in a block.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BlockFees {
+    /// the index of the block in the ledger
+    pub block_sid: u64,
+    #[allow(missing_docs)]
+    pub txns: u64,
+    #[allow(missing_docs)]
+    pub operations: u64,
+    /// the sum of the fees of the transactions
+    pub total_fee: u64,
+    /// the lowest fee of a transaction, the ones exempted from fees excluded
+    pub min_fee: u64,
+    /// the highest fee of a transaction
+    pub max_fee: u64,
+    /// `total_fee / operations`
+    pub avg_fee_per_op: u64,
+    /// the number of transactions left in the mempool after the block, if known
+    pub mempool_depth: Option<u64>,
+}
+
+/// The fees paid in a range of blocks, see `BlockFees`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FeeHistory {
+    #[allow(missing_docs)]
+    pub total_fee: u64,
+    #[allow(missing_docs)]
+    pub operations: u64,
+    /// `total_fee / operations`
+    pub avg_fee_per_op: u64,
+    /// the median of the average fees per operation of the blocks,
+    /// less sensitive to a few generous transactions
+    pub median_fee_per_op: u64,
+    /// the oldest first
+    pub blocks: Vec<(BlockHeight, BlockFees)>,
+}
+
+/// The lowercased alphanumeric words of a memo, too short or too long words are ignored.
+pub fn memo_words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| (MEMO_WORD_LEN.0..=MEMO_WORD_LEN.1).contains(&w.chars().count()))
+        .map(|w| w.to_lowercase())
+}
+
+// the bit length of the amount, so one bucket covers `[2^(n-1), 2^n)`
+#[inline(always)]
+fn amount_bucket(am: u64) -> u64 {
+    (64 - am.leading_zeros()) as u64
+}
+
+// strip the network prefix of a bech32 address
+#[inline(always)]
+fn address_data(addr: &str) -> &str {
+    let hrp = wallet::address_hrp();
+    addr.strip_prefix(hrp)
+        .and_then(|a| a.strip_prefix('1'))
+        .unwrap_or(addr)
+}
+
 /// An xfr address is related to a transaction if it is one of the following:
 /// 1. Owner of a transfer output
 /// 2. Transfer signer (owner of input or co-signer)
@@ -253,6 +857,7 @@ where
             Operation::Delegation(i) => staking_gen!(i),
             Operation::UnDelegation(i) => staking_gen!(i),
             Operation::Claim(i) => staking_gen!(i),
+            Operation::Unjail(i) => staking_gen!(i),
             Operation::UpdateValidator(i) => staking_gen!(i),
             Operation::Governance(i) => staking_gen!(i),
             Operation::FraDistribution(i) => staking_gen!(i),
@@ -291,6 +896,78 @@ where
                     key: update_memo.pubkey,
                 });
             }
+            Operation::UpdateRevocation(update) => {
+                related_addresses.insert(XfrAddress { key: update.pubkey });
+            }
+            Operation::UpdateTracerKey(update) => {
+                related_addresses.insert(XfrAddress { key: update.pubkey });
+            }
+            Operation::UpdateAssetMirror(update) => {
+                related_addresses.insert(XfrAddress { key: update.pubkey });
+            }
+            Operation::PrismDeposit(deposit) => {
+                related_addresses.insert(XfrAddress {
+                    key: deposit.pubkey,
+                });
+            }
+            Operation::PrismWithdraw(withdraw) => {
+                related_addresses.insert(XfrAddress {
+                    key: withdraw.body.receiver,
+                });
+            }
+            Operation::UpdatePrismAuthorities(update) => {
+                for (pk, _) in update.signatures.iter() {
+                    related_addresses.insert(XfrAddress { key: *pk });
+                }
+            }
+            Operation::Ibc(op) => {
+                if let Some(pk) = op.get_signer() {
+                    related_addresses.insert(XfrAddress { key: *pk });
+                }
+            }
+            Operation::PaymentStream(op) => {
+                related_addresses.insert(XfrAddress {
+                    key: *op.get_signer(),
+                });
+                if let Some(pk) = op.get_recipient() {
+                    related_addresses.insert(XfrAddress { key: *pk });
+                }
+            }
+            Operation::DesignatedTransfer(op) => {
+                related_addresses.insert(XfrAddress {
+                    key: *op.get_signer(),
+                });
+                if let Some(pk) = op.get_recipient() {
+                    related_addresses.insert(XfrAddress { key: *pk });
+                }
+            }
+            Operation::SpendingPolicy(op) => {
+                related_addresses.insert(XfrAddress {
+                    key: *op.get_signer(),
+                });
+            }
+            Operation::SessionKey(op) => {
+                related_addresses.insert(XfrAddress {
+                    key: *op.get_signer(),
+                });
+                related_addresses.insert(XfrAddress {
+                    key: *op.get_session(),
+                });
+            }
+            Operation::PullPayment(op) => {
+                related_addresses.insert(XfrAddress {
+                    key: *op.get_signer(),
+                });
+                if let Some(pk) = op.get_payee() {
+                    related_addresses.insert(XfrAddress { key: *pk });
+                }
+            }
+            Operation::Kv(op) => {
+                related_addresses.insert(XfrAddress { key: op.pubkey });
+            }
+            Operation::AnchorHash(op) => {
+                related_addresses.insert(XfrAddress { key: op.pubkey });
+            }
         }
     }
     related_addresses
@@ -313,6 +990,50 @@ pub fn get_transferred_nonconfidential_assets(
     transferred_assets
 }
 
+/// Record the fees of the last block of `ledger` at its tendermint height,
+/// if not recorded yet, `mempool_depth` is the number of transactions
+/// left in the mempool after it.
+pub fn update_fee_hist(ledger: &mut LedgerState, mempool_depth: Option<u64>) {
+    let block_sid = match ledger.blocks.len().checked_sub(1) {
+        Some(sid) => sid as u64,
+        None => return,
+    };
+    let height = ledger.get_tendermint_height();
+    let (cache, block) = match (ledger.api_cache.as_mut(), ledger.blocks.last()) {
+        (Some(cache), Some(block)) if *KEEP_HIST => (cache, block),
+        _ => return,
+    };
+
+    // the empty blocks do not add a block to the ledger
+    if cache
+        .fee_hist
+        .get_closest_smaller(&height)
+        .map(|(_, f)| f.block_sid == block_sid)
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    let mut fees = BlockFees {
+        block_sid,
+        mempool_depth,
+        ..Default::default()
+    };
+    for ftx in block.txns.iter() {
+        let fee = ftx.txn.fee();
+        fees.txns += 1;
+        fees.operations += ftx.txn.body.operations.len() as u64;
+        fees.total_fee = fees.total_fee.saturating_add(fee);
+        if 0 < fee && (0 == fees.min_fee || fee < fees.min_fee) {
+            fees.min_fee = fee;
+        }
+        fees.max_fee = fees.max_fee.max(fee);
+    }
+    fees.avg_fee_per_op = fees.total_fee.checked_div(fees.operations).unwrap_or(0);
+
+    cache.fee_hist.insert(height, fees);
+}
+
 /// check the lost data
 pub fn check_lost_data(ledger: &mut LedgerState) -> Result<()> {
     // check the lost txn sids
@@ -566,6 +1287,31 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
                 .insert(txn_sid, Default::default());
         }
 
+        // Update the indexes of the search
+        let outputs = txo_sids
+            .iter()
+            .filter_map(|sid| {
+                ledger
+                    .get_utxo_light(*sid)
+                    .or_else(|| ledger.get_spent_utxo_light(*sid))
+                    .map(|u| (*sid, u.utxo.0))
+            })
+            .collect::<Vec<_>>();
+        ledger.api_cache.as_mut().unwrap().index_for_search(
+            txn_sid,
+            &curr_txn,
+            &outputs,
+            &related_addresses,
+        );
+
+        // Update the statistics of assets
+        let height = ledger.get_tendermint_height();
+        ledger
+            .api_cache
+            .as_mut()
+            .unwrap()
+            .cache_asset_stats(height, &curr_txn, &outputs);
+
         // Update transferred nonconfidential assets
         let transferred_assets = get_transferred_nonconfidential_assets(&curr_txn);
         for asset in &transferred_assets {
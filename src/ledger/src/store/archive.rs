@@ -0,0 +1,485 @@
+//!
+//! # Block archive
+//!
+//! The committed blocks of a node, along with the staking state each of them
+//! started from, one JSON object per line, recorded by `abcid --block-archive`.
+//!
+//! `replay` applies the archived blocks to an empty ledger one by one, and compares
+//! the state commitment after each block with the recorded one. The staking state is
+//! restored from the archive before each block, the staking logics of the consensus
+//! hooks are not replayed, so a divergence comes from the ledger itself.
+//!
+//! `catch_up` follows an archive still being recorded, eg. to serve the ledger of
+//! another network in the query server.
+//!
+//! `export_chunks` cuts a range of heights of an archive into self-contained chunks,
+//! for offline distribution and archival storage, and `import_chunk` appends a
+//! verified chunk to another archive, see `ArchiveChunk::verify`.
+//!
+
+use {
+    super::{api_cache, LedgerState},
+    crate::{
+        data_model::{FinalizedBlock, StateCommitmentData, Transaction, TxnEffect},
+        staking::Staking,
+    },
+    globutils::{
+        persist::{self, OpenMode, PersistFile},
+        HashOf,
+    },
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        io::{BufRead, BufReader, Seek, SeekFrom, Write},
+        mem,
+    },
+};
+
+/// A committed block of the archive.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchivedBlock {
+    /// the tendermint height of the block
+    pub height: u64,
+    /// the staking state before the transactions of the block
+    pub staking: Staking,
+    #[allow(missing_docs)]
+    pub block: FinalizedBlock,
+}
+
+/// The first divergence found by `replay`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Divergence {
+    /// the tendermint height of the block
+    pub height: u64,
+    /// the fields of the state commitment which differ,
+    /// empty if a transaction has been rejected
+    pub fields: Vec<String>,
+    #[allow(missing_docs)]
+    pub recorded: StateCommitmentData,
+    #[allow(missing_docs)]
+    pub replayed: Option<StateCommitmentData>,
+    /// the index of the offending transaction in the block
+    pub txn_idx: usize,
+    #[allow(missing_docs)]
+    pub txn: Transaction,
+    /// the decoded effects of the offending transaction
+    pub effect: Option<String>,
+    /// why the transaction has been rejected, if so
+    pub error: Option<String>,
+}
+
+/// The result of `replay`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReplayReport {
+    /// the number of blocks replayed without divergence
+    pub blocks: u64,
+    /// the tendermint height of the last one
+    pub last_height: u64,
+    #[allow(missing_docs)]
+    pub divergence: Option<Divergence>,
+}
+
+/// The version of the layout of `ArchiveChunk`
+pub const CHUNK_VERSION: u64 = 1;
+
+/// The header of a chunk of archived blocks.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChunkHeader {
+    #[allow(missing_docs)]
+    pub version: u64,
+    /// the tendermint height of the first block
+    pub from: u64,
+    /// the tendermint height of the last block
+    pub to: u64,
+    /// the number of blocks
+    pub count: u64,
+    /// the state commitment before the first block
+    pub prev_commitment: HashOf<Option<StateCommitmentData>>,
+    /// the state commitment after the last block
+    pub commitment: HashOf<Option<StateCommitmentData>>,
+    /// the hash of the blocks
+    pub blocks_hash: HashOf<Vec<ArchivedBlock>>,
+}
+
+/// Consecutive blocks of an archive, with the commitments they chain from and to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchiveChunk {
+    #[allow(missing_docs)]
+    pub header: ChunkHeader,
+    #[allow(missing_docs)]
+    pub blocks: Vec<ArchivedBlock>,
+}
+
+impl ArchiveChunk {
+    /// A chunk of `blocks`, which must be consecutive blocks of an archive.
+    pub fn new(blocks: Vec<ArchivedBlock>) -> Result<Self> {
+        let (first, last) = match (blocks.first(), blocks.last()) {
+            (Some(f), Some(l)) => (f, l),
+            _ => return Err(eg!("no blocks")),
+        };
+        let header = ChunkHeader {
+            version: CHUNK_VERSION,
+            from: first.height,
+            to: last.height,
+            count: blocks.len() as u64,
+            prev_commitment: first.block.state.previous_state_commitment.clone(),
+            commitment: last.block.state.compute_commitment(),
+            blocks_hash: HashOf::new(&blocks),
+        };
+        let chunk = ArchiveChunk { header, blocks };
+        chunk.verify().c(d!())?;
+        Ok(chunk)
+    }
+
+    /// Check the header against the blocks, and the chain of the blocks:
+    /// each one commits to the state after the previous one, and to its own
+    /// transactions.
+    ///
+    /// The transactions are not applied, replay the archive for that.
+    pub fn verify(&self) -> Result<()> {
+        let h = &self.header;
+        if CHUNK_VERSION != h.version {
+            return Err(eg!(format!("unsupported chunk version {}", h.version)));
+        }
+        if h.count != self.blocks.len() as u64 || self.blocks.is_empty() {
+            return Err(eg!("wrong number of blocks"));
+        }
+        // compared by their digests, the blocks are not comparable
+        if h.blocks_hash.0 != HashOf::new(&self.blocks).0 {
+            return Err(eg!("the blocks do not match the header"));
+        }
+
+        let mut prev_commitment = h.prev_commitment.clone();
+        let mut prev_height = None;
+        for b in self.blocks.iter() {
+            if prev_height.map(|p| b.height <= p).unwrap_or(false) {
+                return Err(eg!(format!("block {}: out of order", b.height)));
+            }
+            let state = &b.block.state;
+            if state.previous_state_commitment != prev_commitment {
+                return Err(eg!(format!(
+                    "block {}: not chained to the previous state",
+                    b.height
+                )));
+            }
+            let txns = b
+                .block
+                .txns
+                .iter()
+                .map(|ftx| ftx.txn.clone())
+                .collect::<Vec<_>>();
+            if state.txns_in_block_hash != HashOf::new(&txns) {
+                return Err(eg!(format!(
+                    "block {}: the transactions do not match the commitment",
+                    b.height
+                )));
+            }
+            prev_commitment = state.compute_commitment();
+            prev_height = Some(b.height);
+        }
+
+        if self.blocks[0].height != h.from || prev_height != Some(h.to) {
+            return Err(eg!("the heights do not match the header"));
+        }
+        if prev_commitment != h.commitment {
+            return Err(eg!("the last state does not match the header"));
+        }
+        Ok(())
+    }
+}
+
+/// Cut the blocks of the archive in `path` from the height `from` to the height `to`
+/// into chunks of `chunk_size` blocks at most, which are passed to `f` in order.
+/// Returns the number of chunks.
+pub fn export_chunks<F>(
+    path: &str,
+    from: u64,
+    to: u64,
+    chunk_size: usize,
+    mut f: F,
+) -> Result<u64>
+where
+    F: FnMut(ArchiveChunk) -> Result<()>,
+{
+    if from > to || 0 == chunk_size {
+        return Err(eg!("invalid range or chunk size"));
+    }
+
+    let file = open(path).c(d!())?;
+    let mut chunks = 0;
+    let mut blocks = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line.c(d!())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let b = serde_json::from_str::<ArchivedBlock>(&line).c(d!())?;
+        if b.height < from {
+            continue;
+        }
+        if b.height > to {
+            break;
+        }
+        blocks.push(b);
+        if chunk_size == blocks.len() {
+            f(ArchiveChunk::new(mem::take(&mut blocks)).c(d!())?).c(d!())?;
+            chunks += 1;
+        }
+    }
+    if !blocks.is_empty() {
+        f(ArchiveChunk::new(blocks).c(d!())?).c(d!())?;
+        chunks += 1;
+    }
+
+    Ok(chunks)
+}
+
+/// Verify `chunk` and append its blocks to the archive in `path`, created if missing.
+///
+/// The chunk must follow the last block of the archive, if any, a chunk already
+/// in the archive is skipped. Returns the number of blocks appended.
+pub fn import_chunk(path: &str, chunk: &ArchiveChunk) -> Result<u64> {
+    chunk.verify().c(d!())?;
+
+    if let Some(last) = last_block(path).c(d!())? {
+        if chunk.header.to <= last.height {
+            return Ok(0);
+        }
+        if chunk.header.from <= last.height
+            || chunk.header.prev_commitment != last.block.state.compute_commitment()
+        {
+            return Err(eg!(format!(
+                "the chunk of heights {}..={} does not follow the height {}",
+                chunk.header.from, chunk.header.to, last.height
+            )));
+        }
+    }
+
+    for b in chunk.blocks.iter() {
+        append(path, b).c(d!())?;
+    }
+    Ok(chunk.header.count)
+}
+
+// `None` if the archive is missing or empty
+fn last_block(path: &str) -> Result<Option<ArchivedBlock>> {
+    let file = match open(path) {
+        Ok(f) => f,
+        Err(_) => return Ok(None),
+    };
+    let mut last = None;
+    for line in BufReader::new(file).lines() {
+        let line = line.c(d!())?;
+        if !line.trim().is_empty() {
+            last = Some(line);
+        }
+    }
+    last.map(|l| serde_json::from_str(&l).c(d!())).transpose()
+}
+
+/// Append a committed block to the archive in `path`.
+pub fn append(path: &str, b: &ArchivedBlock) -> Result<()> {
+    let mut line = serde_json::to_vec(b).c(d!())?;
+    line.push(b'\n');
+    let mut f = persist::backend()
+        .open(path, OpenMode::Create)
+        .c(d!(path.to_owned()))?;
+    f.seek(SeekFrom::End(0)).c(d!())?;
+    f.write_all(&line).c(d!())
+}
+
+// the archives are accessed through the backend of the ledger store
+#[inline(always)]
+fn open(path: &str) -> Result<Box<dyn PersistFile>> {
+    persist::backend()
+        .open(path, OpenMode::Existing)
+        .c(d!(path.to_owned()))
+}
+
+/// Replay the archive in `path` through `ledger`, which should be empty,
+/// stop at the first divergence, or after the block at the height `until`.
+pub fn replay(
+    ledger: &mut LedgerState,
+    path: &str,
+    until: Option<u64>,
+) -> Result<ReplayReport> {
+    let f = open(path).c(d!())?;
+    let mut report = ReplayReport {
+        blocks: 0,
+        last_height: 0,
+        divergence: None,
+    };
+
+    for line in BufReader::new(f).lines() {
+        let line = line.c(d!())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let b = serde_json::from_str::<ArchivedBlock>(&line).c(d!())?;
+        if until.map(|h| b.height > h).unwrap_or(false) {
+            break;
+        }
+        if let Some(d) = replay_block(ledger, b).c(d!())? {
+            report.divergence = Some(d);
+            break;
+        }
+        report.blocks += 1;
+        report.last_height = ledger.get_tendermint_height();
+    }
+
+    Ok(report)
+}
+
+/// Replay the blocks of the archive in `path` above the tendermint height of `ledger`,
+/// reading from the byte `offset` of the file, stop at the first divergence.
+///
+/// The query cache of `ledger` is updated after each block, if the history is kept.
+/// Returns the offset to resume from, after the last complete line,
+/// a line still being written is left to the next call.
+pub fn catch_up(
+    ledger: &mut LedgerState,
+    path: &str,
+    offset: u64,
+) -> Result<(ReplayReport, u64)> {
+    let mut f = open(path).c(d!())?;
+    f.seek(SeekFrom::Start(offset)).c(d!())?;
+    let mut report = ReplayReport {
+        blocks: 0,
+        last_height: ledger.get_tendermint_height(),
+        divergence: None,
+    };
+
+    let mut reader = BufReader::new(f);
+    let mut offset = offset;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let n = reader.read_line(&mut line).c(d!())?;
+        if 0 == n || !line.ends_with('\n') {
+            break;
+        }
+        offset += n as u64;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let b = serde_json::from_str::<ArchivedBlock>(&line).c(d!())?;
+        if b.height <= ledger.get_tendermint_height() {
+            continue;
+        }
+        if let Some(d) = replay_block(ledger, b).c(d!())? {
+            report.divergence = Some(d);
+            break;
+        }
+        api_cache::update_api_cache(ledger).c(d!())?;
+        api_cache::update_fee_hist(ledger, None);
+        report.blocks += 1;
+        report.last_height = ledger.get_tendermint_height();
+    }
+
+    Ok((report, offset))
+}
+
+fn replay_block(
+    ledger: &mut LedgerState,
+    b: ArchivedBlock,
+) -> Result<Option<Divergence>> {
+    let ArchivedBlock {
+        height,
+        staking,
+        block: recorded,
+    } = b;
+
+    *ledger.get_staking_mut() = staking;
+    ledger.set_tendermint_height(height.saturating_sub(1));
+
+    let divergence = |idx: usize, replayed, fields, effect, error| Divergence {
+        height,
+        fields,
+        recorded: recorded.state.clone(),
+        replayed,
+        txn_idx: idx,
+        txn: recorded.txns[idx].txn.clone(),
+        effect,
+        error,
+    };
+
+    let mut block = ledger.start_block().c(d!())?;
+    let mut temp_sids = vec![];
+    for (idx, ftx) in recorded.txns.iter().enumerate() {
+        let txe = match TxnEffect::compute_effect(ftx.txn.clone()) {
+            Ok(txe) => txe,
+            Err(e) => {
+                return Ok(Some(divergence(
+                    idx,
+                    None,
+                    vec![],
+                    None,
+                    Some(e.to_string()),
+                )));
+            }
+        };
+        let effect = format!("{:#?}", txe);
+        match ledger.apply_transaction(&mut block, txe) {
+            Ok(tmp_sid) => temp_sids.push(tmp_sid),
+            Err(e) => {
+                return Ok(Some(divergence(
+                    idx,
+                    None,
+                    vec![],
+                    Some(effect),
+                    Some(e.to_string()),
+                )));
+            }
+        }
+    }
+    let tsm = ledger.finish_block(block).c(d!())?;
+    ledger.set_tendermint_height(height);
+
+    let replayed = ledger.blocks.last().c(d!())?.state;
+    let fields = diff_fields(&recorded.state, &replayed).c(d!())?;
+    if fields.is_empty() {
+        return Ok(None);
+    }
+
+    // the first transaction with different sids is the offending one,
+    // or the last one of the block if they are all the same
+    let idx = temp_sids
+        .iter()
+        .zip(recorded.txns.iter())
+        .position(|(tmp_sid, ftx)| {
+            tsm.get(tmp_sid)
+                .map(|(txn_sid, txo_sids)| {
+                    *txn_sid != ftx.tx_id || *txo_sids != ftx.txo_ids
+                })
+                .unwrap_or(true)
+        })
+        .unwrap_or_else(|| recorded.txns.len().saturating_sub(1));
+    let effect = TxnEffect::compute_effect(recorded.txns[idx].txn.clone())
+        .map(|txe| format!("{:#?}", txe))
+        .ok();
+
+    Ok(Some(divergence(idx, Some(replayed), fields, effect, None)))
+}
+
+fn diff_fields(
+    recorded: &StateCommitmentData,
+    replayed: &StateCommitmentData,
+) -> Result<Vec<String>> {
+    let recorded = serde_json::to_value(recorded).c(d!())?;
+    let replayed = serde_json::to_value(replayed).c(d!())?;
+    let (recorded, replayed) = match (recorded.as_object(), replayed.as_object()) {
+        (Some(l), Some(r)) => (l.clone(), r.clone()),
+        _ => return Err(eg!()),
+    };
+
+    let mut fields = recorded
+        .keys()
+        .chain(replayed.keys())
+        .filter(|k| recorded.get(*k) != replayed.get(*k))
+        .cloned()
+        .collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+
+    Ok(fields)
+}
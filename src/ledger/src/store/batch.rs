@@ -0,0 +1,369 @@
+//!
+//! # Write batching of the ledger store
+//!
+//! Finishing a block writes the utxo bitmap and each level of the transaction
+//! and block Merkle trees, each of them synced to disk, many small synced writes
+//! for a single block. The `SyncMode` of a ledger chooses how they are grouped:
+//!
+//! - `always`: the structures are written and synced at each block, the default
+//! - `batched:<blocks>`: the mutations of each block, the bits of the bitmap and
+//!   the leaves of the trees, are appended to a write-ahead log as one record,
+//!   with a single sync; the structures are written and synced together every
+//!   `<blocks>` blocks, a group commit, after which the log is emptied
+//! - `off`: nothing is written at each block, the structures are written by
+//!   `LedgerState::sync` and when they are dropped, for the ledgers which can be
+//!   rebuilt, e.g. by the replays of block archives
+//!
+//! When a ledger is opened, the records of the log missing in the structures,
+//! after a crash between two group commits, are applied to them again.
+//!
+
+use {
+    bitmap::BitMap,
+    cryptohash::HashValue,
+    globutils::persist::{self, OpenMode, PersistFile},
+    merkle_tree::AppendOnlyMerkle,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt,
+        io::{ErrorKind, Seek, SeekFrom, Write},
+        mem,
+        str::FromStr,
+    },
+};
+
+/// The blocks between two group commits of `batched` by default
+pub const DEFAULT_BATCHED_BLOCKS: u64 = 100;
+
+/// When the structures of the ledger are written and synced to disk
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SyncMode {
+    /// at each block
+    Always,
+    /// every `blocks` blocks, the blocks between are in the write-ahead log
+    Batched {
+        #[allow(missing_docs)]
+        blocks: u64,
+    },
+    /// only by `LedgerState::sync` and when dropped
+    Off,
+}
+
+impl Default for SyncMode {
+    fn default() -> Self {
+        SyncMode::Always
+    }
+}
+
+impl fmt::Display for SyncMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncMode::Always => write!(f, "always"),
+            SyncMode::Batched { blocks } => write!(f, "batched:{}", blocks),
+            SyncMode::Off => write!(f, "off"),
+        }
+    }
+}
+
+impl FromStr for SyncMode {
+    type Err = Box<dyn RucError>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().splitn(2, ':');
+        match (parts.next().unwrap_or_default(), parts.next()) {
+            ("always", None) => Ok(SyncMode::Always),
+            ("off", None) => Ok(SyncMode::Off),
+            ("batched", None) => Ok(SyncMode::Batched {
+                blocks: DEFAULT_BATCHED_BLOCKS,
+            }),
+            ("batched", Some(n)) => n
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|n| 0 < *n)
+                .map(|blocks| SyncMode::Batched { blocks })
+                .c(d!(format!("invalid number of blocks: {}", n))),
+            _ => Err(eg!(format!(
+                "unknown sync mode: {}, expected always, batched[:<blocks>] or off",
+                s
+            ))),
+        }
+    }
+}
+
+// The mutations of the structures by a block, a line of JSON in the log.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct WalRecord {
+    // the outputs spent, cleared in the bitmap before the new ones are appended
+    spent: Vec<u64>,
+    // the new outputs, appended to the bitmap from `utxo_base`, unspent or not
+    utxo_base: u64,
+    utxos: Vec<bool>,
+    txn_base: u64,
+    txn_leaves: Vec<HashValue>,
+    block_base: u64,
+    block_leaves: Vec<HashValue>,
+}
+
+impl WalRecord {
+    // Idempotent, the parts already in the structures are skipped.
+    fn redo(
+        &self,
+        utxo_map: &mut BitMap,
+        txn_merkle: &mut AppendOnlyMerkle,
+        block_merkle: &mut AppendOnlyMerkle,
+    ) -> Result<()> {
+        redo_leaves(txn_merkle, self.txn_base, &self.txn_leaves)
+            .c(d!("transaction tree"))?;
+        redo_leaves(block_merkle, self.block_base, &self.block_leaves)
+            .c(d!("block tree"))?;
+
+        let size = utxo_map.size() as u64;
+        if size < self.utxo_base {
+            return Err(eg!(format!(
+                "the utxo map has {} bits, the record starts at {}",
+                size, self.utxo_base
+            )));
+        }
+        for sid in self.spent.iter() {
+            utxo_map.clear(*sid as usize).c(d!())?;
+        }
+        for (i, unspent) in self.utxos.iter().enumerate() {
+            let sid = self.utxo_base as usize + i;
+            if sid < utxo_map.size() {
+                continue;
+            }
+            // only `set` extends the bitmap
+            utxo_map.set(sid).c(d!())?;
+            if !unspent {
+                utxo_map.clear(sid).c(d!())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn redo_leaves(
+    tree: &mut AppendOnlyMerkle,
+    base: u64,
+    leaves: &[HashValue],
+) -> Result<()> {
+    let n = tree.state();
+    if n < base {
+        return Err(eg!(format!(
+            "the tree has {} leaves, the record starts at {}",
+            n, base
+        )));
+    }
+    for leaf in leaves.iter().skip((n - base) as usize) {
+        tree.append_hash(leaf).c(d!())?;
+    }
+    Ok(())
+}
+
+// write and sync the structures
+fn write_all(
+    utxo_map: &mut BitMap,
+    txn_merkle: &mut AppendOnlyMerkle,
+    block_merkle: &mut AppendOnlyMerkle,
+) -> Result<()> {
+    utxo_map.write().c(d!())?;
+    txn_merkle.write().c(d!())?;
+    block_merkle.write().c(d!())
+}
+
+/// The writes of the structures of a ledger, see the module documentation.
+pub struct WriteBatch {
+    mode: SyncMode,
+    path: String,
+    // opened in the `batched` mode
+    log: Option<Box<dyn PersistFile>>,
+    // the mutations of the block being finished
+    pending: WalRecord,
+    // the size of the bitmap at the end of the last block
+    utxo_size: u64,
+    // the blocks not written to the structures since the last group commit
+    unsynced: u64,
+}
+
+impl WriteBatch {
+    /// Apply the records of the log at `path` missing in the structures,
+    /// the mode is `always` until changed by `set_mode`.
+    pub fn open(
+        path: &str,
+        utxo_map: &mut BitMap,
+        txn_merkle: &mut AppendOnlyMerkle,
+        block_merkle: &mut AppendOnlyMerkle,
+    ) -> Result<WriteBatch> {
+        let n = recover(path, utxo_map, txn_merkle, block_merkle).c(d!())?;
+        if 0 < n {
+            log::info!("{} blocks recovered from the write-ahead log {}", n, path);
+        }
+
+        Ok(WriteBatch {
+            mode: SyncMode::Always,
+            path: path.to_owned(),
+            log: None,
+            pending: WalRecord::default(),
+            utxo_size: utxo_map.size() as u64,
+            unsynced: 0,
+        })
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn mode(&self) -> SyncMode {
+        self.mode
+    }
+
+    /// Change the mode, after a group commit of the blocks not written yet.
+    pub fn set_mode(
+        &mut self,
+        mode: SyncMode,
+        utxo_map: &mut BitMap,
+        txn_merkle: &mut AppendOnlyMerkle,
+        block_merkle: &mut AppendOnlyMerkle,
+    ) -> Result<()> {
+        self.commit(utxo_map, txn_merkle, block_merkle).c(d!())?;
+
+        self.log = if let SyncMode::Batched { .. } = mode {
+            let mut log = persist::backend()
+                .open(&self.path, OpenMode::Create)
+                .c(d!(self.path.clone()))?;
+            log.seek(SeekFrom::End(0)).c(d!())?;
+            Some(log)
+        } else {
+            None
+        };
+        self.mode = mode;
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(super) fn spend(&mut self, sid: u64) {
+        self.pending.spent.push(sid);
+    }
+
+    #[inline(always)]
+    pub(super) fn append_txn(&mut self, leaf: HashValue) {
+        self.pending.txn_leaves.push(leaf);
+    }
+
+    #[inline(always)]
+    pub(super) fn append_block(&mut self, leaf: HashValue) {
+        self.pending.block_leaves.push(leaf);
+    }
+
+    /// The end of a block, its mutations are written as the mode says.
+    pub(super) fn finish_block(
+        &mut self,
+        utxo_map: &mut BitMap,
+        txn_merkle: &mut AppendOnlyMerkle,
+        block_merkle: &mut AppendOnlyMerkle,
+    ) -> Result<()> {
+        let mut record = mem::take(&mut self.pending);
+        let utxo_size = utxo_map.size() as u64;
+
+        match self.mode {
+            SyncMode::Always => {
+                write_all(utxo_map, txn_merkle, block_merkle).c(d!())?;
+            }
+            SyncMode::Batched { blocks } => {
+                record.utxo_base = self.utxo_size;
+                record.utxos = (self.utxo_size..utxo_size)
+                    .map(|sid| utxo_map.query(sid as usize))
+                    .collect::<Result<Vec<_>>>()
+                    .c(d!())?;
+                record.txn_base = txn_merkle.state() - record.txn_leaves.len() as u64;
+                record.block_base =
+                    block_merkle.state() - record.block_leaves.len() as u64;
+
+                let mut line = serde_json::to_vec(&record).c(d!())?;
+                line.push(b'\n');
+                let log = self.log.as_mut().c(d!("the log is not opened"))?;
+                log.write_all(&line).c(d!())?;
+                log.sync_all().c(d!())?;
+
+                self.unsynced += 1;
+                if blocks <= self.unsynced {
+                    self.commit(utxo_map, txn_merkle, block_merkle).c(d!())?;
+                }
+            }
+            SyncMode::Off => {
+                self.unsynced += 1;
+            }
+        }
+        self.utxo_size = utxo_size;
+
+        Ok(())
+    }
+
+    /// Write and sync the structures, then empty the log, a group commit.
+    pub fn commit(
+        &mut self,
+        utxo_map: &mut BitMap,
+        txn_merkle: &mut AppendOnlyMerkle,
+        block_merkle: &mut AppendOnlyMerkle,
+    ) -> Result<()> {
+        if 0 == self.unsynced {
+            return Ok(());
+        }
+
+        write_all(utxo_map, txn_merkle, block_merkle).c(d!())?;
+        if let Some(log) = self.log.as_mut() {
+            log.set_len(0).c(d!())?;
+            log.seek(SeekFrom::Start(0)).c(d!())?;
+            log.sync_all().c(d!())?;
+        }
+        self.unsynced = 0;
+
+        Ok(())
+    }
+}
+
+// Apply the records of the log at `path`, then empty it,
+// return the number of records.
+fn recover(
+    path: &str,
+    utxo_map: &mut BitMap,
+    txn_merkle: &mut AppendOnlyMerkle,
+    block_merkle: &mut AppendOnlyMerkle,
+) -> Result<u64> {
+    let contents = match persist::backend().read(path) {
+        Ok(c) => c,
+        Err(e) if ErrorKind::NotFound == e.kind() => return Ok(0),
+        Err(e) => return Err(eg!(e)),
+    };
+    if contents.is_empty() {
+        return Ok(0);
+    }
+
+    let lines = contents
+        .split_inclusive(|b| b'\n' == *b)
+        .collect::<Vec<_>>();
+    let mut n = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let record = if line.ends_with(b"\n") {
+            serde_json::from_slice::<WalRecord>(line).c(d!())
+        } else {
+            Err(eg!("unterminated record"))
+        };
+        match record {
+            Ok(r) => r
+                .redo(utxo_map, txn_merkle, block_merkle)
+                .c(d!(format!("record {}", i)))?,
+            // torn by a crash in the middle of an append, the block was not finished
+            Err(_) if i + 1 == lines.len() => break,
+            Err(e) => return Err(e).c(d!(format!("record {}", i))),
+        }
+        n += 1;
+    }
+
+    write_all(utxo_map, txn_merkle, block_merkle).c(d!())?;
+    persist::backend().write(path, &[]).c(d!())?;
+
+    Ok(n)
+}
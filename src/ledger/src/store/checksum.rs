@@ -0,0 +1,171 @@
+//!
+//! # Checksums of the subsystems of a ledger
+//!
+//! The state commitment covers the whole ledger, so it can not tell which part of
+//! two diverged nodes differs, and a full `export` is heavy to move around.
+//! `LedgerState::checksums` digests each subsystem apart, in a canonical order:
+//!
+//! - `utxo`: the unspent outputs, by their sids
+//! - `asset`: the asset registry, with the issuance numbers and amounts
+//! - `air`: the revocation lists of the credential issuers
+//! - `staking`: the staking state
+//!
+//! Two nodes at a same height can then be compared subsystem by subsystem,
+//! see `LedgerChecksums::mismatches`.
+//!
+
+use {
+    super::{export::ExportedAsset, LedgerState},
+    crate::data_model::StateCommitmentData,
+    globutils::HashOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha256},
+    std::{collections::BTreeMap, fmt, str::FromStr},
+};
+
+/// The subsystems with a checksum
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(missing_docs)]
+pub enum Subsystem {
+    Utxo,
+    Asset,
+    Air,
+    Staking,
+}
+
+impl Subsystem {
+    /// All of them
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::Utxo,
+        Subsystem::Asset,
+        Subsystem::Air,
+        Subsystem::Staking,
+    ];
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Subsystem::Utxo => "utxo",
+            Subsystem::Asset => "asset",
+            Subsystem::Air => "air",
+            Subsystem::Staking => "staking",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Subsystem {
+    type Err = Box<dyn RucError>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Subsystem::ALL
+            .iter()
+            .find(|sub| sub.to_string().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .c(d!(format!(
+                "unknown subsystem: {}, expected one of utxo, asset, air, staking",
+                s
+            )))
+    }
+}
+
+/// The checksum of a subsystem.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SubsystemChecksum {
+    /// sha256 in hex
+    pub checksum: String,
+    /// the number of entries digested
+    pub entries: u64,
+}
+
+/// The checksums of some subsystems of a ledger at a height.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LedgerChecksums {
+    /// the tendermint height
+    pub height: u64,
+    /// the number of non-empty blocks
+    pub block_count: u64,
+    #[allow(missing_docs)]
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+    #[allow(missing_docs)]
+    pub subsystems: BTreeMap<Subsystem, SubsystemChecksum>,
+}
+
+impl LedgerChecksums {
+    /// The subsystems present on both sides with different checksums,
+    /// the two should be of a same height.
+    pub fn mismatches(&self, other: &LedgerChecksums) -> Vec<Subsystem> {
+        self.subsystems
+            .iter()
+            .filter(|(sub, c)| other.subsystems.get(*sub).map_or(false, |o| o != *c))
+            .map(|(sub, _)| *sub)
+            .collect()
+    }
+}
+
+impl LedgerState {
+    /// The checksums of `subsystems`, of all of them if empty.
+    pub fn checksums(&self, subsystems: &[Subsystem]) -> Result<LedgerChecksums> {
+        let subsystems = alt!(subsystems.is_empty(), &Subsystem::ALL[..], subsystems);
+        let status = &self.status;
+        let (state_commitment, block_count) = self.get_state_commitment();
+
+        let mut res = LedgerChecksums {
+            height: self.get_tendermint_height(),
+            block_count,
+            state_commitment,
+            subsystems: BTreeMap::new(),
+        };
+        for sub in subsystems.iter() {
+            let c = match sub {
+                Subsystem::Utxo => {
+                    digest(status.utxos.iter().collect::<BTreeMap<_, _>>())
+                }
+                Subsystem::Asset => digest(
+                    status
+                        .asset_types
+                        .iter()
+                        .map(|(code, asset)| {
+                            let e = ExportedAsset {
+                                asset,
+                                issuance_num: status.issuance_num.get(&code),
+                                issued: status.issuance_amounts.get(&code),
+                            };
+                            (code, e)
+                        })
+                        .collect::<BTreeMap<_, _>>(),
+                ),
+                Subsystem::Air => digest(status.revocation_registries.iter()),
+                Subsystem::Staking => digest([((), &status.staking)]),
+            }
+            .c(d!(sub.to_string()))?;
+            res.subsystems.insert(*sub, c);
+        }
+
+        Ok(res)
+    }
+}
+
+// each entry is prefixed by its length, so the boundaries can not be shifted
+fn digest<I, K, V>(entries: I) -> Result<SubsystemChecksum>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Serialize,
+    V: Serialize,
+{
+    let mut hasher = Sha256::new();
+    let mut n = 0;
+    for (k, v) in entries {
+        let entry = serde_json::to_vec(&(k, v)).c(d!())?;
+        hasher.update((entry.len() as u64).to_le_bytes());
+        hasher.update(&entry);
+        n += 1;
+    }
+    Ok(SubsystemChecksum {
+        checksum: hex::encode(hasher.finalize()),
+        entries: n,
+    })
+}
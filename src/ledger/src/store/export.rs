@@ -0,0 +1,221 @@
+//!
+//! # State exports
+//!
+//! `LedgerState::export` dumps the unspent outputs, the asset registry and the staking
+//! state of a ledger, `abcid --export-state`, and `diff` compares two exports,
+//! eg. of two nodes, or of a same chain replayed to two heights by `--replay-archive`.
+//!
+//! The differences are structured, for post-incident analysis and upgrade checks:
+//!
+//! - outputs are compared by their sids
+//! - assets and staking states are compared field by field, see `ValueDiff`
+//!
+
+use {
+    super::LedgerState,
+    crate::{
+        data_model::{AssetType, AssetTypeCode, StateCommitmentData, TxoSID, Utxo},
+        staking::Staking,
+    },
+    globutils::HashOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    serde_json::{Map, Value},
+    std::collections::BTreeMap,
+};
+
+/// The state of a ledger at a height.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LedgerExport {
+    /// the tendermint height
+    pub height: u64,
+    /// the number of non-empty blocks
+    pub block_count: u64,
+    #[allow(missing_docs)]
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+    /// all unspent outputs
+    #[serde(with = "globutils::serde_pairs")]
+    pub utxos: BTreeMap<TxoSID, Utxo>,
+    /// all defined assets
+    #[serde(with = "globutils::serde_pairs")]
+    pub assets: BTreeMap<AssetTypeCode, ExportedAsset>,
+    #[allow(missing_docs)]
+    pub staking: Staking,
+}
+
+/// An asset of the registry.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExportedAsset {
+    #[allow(missing_docs)]
+    pub asset: AssetType,
+    /// the last sequence number of its issuances
+    pub issuance_num: Option<u64>,
+    /// the total amount issued, only tracked for the assets with limits
+    pub issued: Option<u64>,
+}
+
+/// A difference between two values, `None` if absent on one side.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ValueDiff {
+    /// the path of the field, eg. `delegation_info.global_amount`
+    pub path: String,
+    #[allow(missing_docs)]
+    pub left: Option<Value>,
+    #[allow(missing_docs)]
+    pub right: Option<Value>,
+}
+
+/// The differences of the unspent outputs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UtxoDiff {
+    /// only in the right one
+    pub added: Vec<(TxoSID, Utxo)>,
+    /// only in the left one
+    pub removed: Vec<(TxoSID, Utxo)>,
+    /// in both, with different contents
+    pub changed: Vec<TxoSID>,
+}
+
+/// The differences between two exports.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LedgerDiff {
+    #[allow(missing_docs)]
+    pub left_height: u64,
+    #[allow(missing_docs)]
+    pub right_height: u64,
+    #[allow(missing_docs)]
+    pub same_state_commitment: bool,
+    #[allow(missing_docs)]
+    pub utxos: UtxoDiff,
+    /// paths start with the base64 code of the asset
+    pub assets: Vec<ValueDiff>,
+    #[allow(missing_docs)]
+    pub staking: Vec<ValueDiff>,
+}
+
+impl LedgerDiff {
+    /// No difference found.
+    pub fn is_empty(&self) -> bool {
+        self.utxos.added.is_empty()
+            && self.utxos.removed.is_empty()
+            && self.utxos.changed.is_empty()
+            && self.assets.is_empty()
+            && self.staking.is_empty()
+    }
+}
+
+impl LedgerState {
+    /// Export the state of the ledger, see `LedgerExport`.
+    pub fn export(&self) -> LedgerExport {
+        let status = &self.status;
+        let (state_commitment, block_count) = self.get_state_commitment();
+        LedgerExport {
+            height: self.get_tendermint_height(),
+            block_count,
+            state_commitment,
+            utxos: status.utxos.iter().collect(),
+            assets: status
+                .asset_types
+                .iter()
+                .map(|(code, asset)| {
+                    let e = ExportedAsset {
+                        asset,
+                        issuance_num: status.issuance_num.get(&code),
+                        issued: status.issuance_amounts.get(&code),
+                    };
+                    (code, e)
+                })
+                .collect(),
+            staking: status.staking.clone(),
+        }
+    }
+}
+
+/// Compare two exports.
+pub fn diff(left: &LedgerExport, right: &LedgerExport) -> Result<LedgerDiff> {
+    let mut utxos = UtxoDiff::default();
+    for (sid, l) in left.utxos.iter() {
+        match right.utxos.get(sid) {
+            None => utxos.removed.push((*sid, l.clone())),
+            Some(r) if r != l => utxos.changed.push(*sid),
+            _ => {}
+        }
+    }
+    utxos.added = right
+        .utxos
+        .iter()
+        .filter(|(sid, _)| !left.utxos.contains_key(sid))
+        .map(|(sid, u)| (*sid, u.clone()))
+        .collect();
+
+    let assets_value = |e: &LedgerExport| -> Result<Value> {
+        let mut m = Map::new();
+        for (code, a) in e.assets.iter() {
+            m.insert(code.to_base64(), serde_json::to_value(a).c(d!())?);
+        }
+        Ok(Value::Object(m))
+    };
+    let mut assets = vec![];
+    diff_values(
+        "",
+        Some(&assets_value(left).c(d!())?),
+        Some(&assets_value(right).c(d!())?),
+        &mut assets,
+    );
+
+    let mut staking = vec![];
+    diff_values(
+        "",
+        Some(&serde_json::to_value(&left.staking).c(d!())?),
+        Some(&serde_json::to_value(&right.staking).c(d!())?),
+        &mut staking,
+    );
+
+    Ok(LedgerDiff {
+        left_height: left.height,
+        right_height: right.height,
+        same_state_commitment: left.state_commitment == right.state_commitment,
+        utxos,
+        assets,
+        staking,
+    })
+}
+
+// objects are compared field by field, arrays of a same length item by item,
+// other values as a whole
+fn diff_values(
+    path: &str,
+    left: Option<&Value>,
+    right: Option<&Value>,
+    res: &mut Vec<ValueDiff>,
+) {
+    let sub = |k: &str| {
+        if path.is_empty() {
+            k.to_owned()
+        } else {
+            format!("{}.{}", path, k)
+        }
+    };
+
+    match (left, right) {
+        (Some(Value::Object(l)), Some(Value::Object(r))) => {
+            for (k, lv) in l.iter() {
+                diff_values(&sub(k), Some(lv), r.get(k), res);
+            }
+            for (k, rv) in r.iter().filter(|(k, _)| !l.contains_key(*k)) {
+                diff_values(&sub(k), None, Some(rv), res);
+            }
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) if l.len() == r.len() => {
+            for (i, (lv, rv)) in l.iter().zip(r.iter()).enumerate() {
+                diff_values(&sub(&i.to_string()), Some(lv), Some(rv), res);
+            }
+        }
+        (l, r) if l != r => res.push(ValueDiff {
+            path: path.to_owned(),
+            left: l.cloned(),
+            right: r.cloned(),
+        }),
+        _ => {}
+    }
+}
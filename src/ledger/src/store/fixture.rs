@@ -0,0 +1,154 @@
+//!
+//! # Ledger fixtures
+//!
+//! The ledger state after a scripted scenario, saved in the format of the block
+//! archive, so tests of edge cases can reload it instead of re-running
+//! a long setup sequence:
+//!
+//! ```ignore
+//! let ledger = LedgerFixture::load_or_capture("issued_assets.snap", |f| {
+//!     f.commit(vec![define_tx]).c(d!())?;
+//!     f.commit(vec![issue_tx]).c(d!())
+//! })?;
+//! ```
+//!
+//! Loading replays the recorded blocks through a temporary ledger and checks
+//! the state commitment after each of them, so a fixture made stale by a change
+//! of the ledger fails loudly, remove the file to capture it again.
+//!
+//! The staking state is recorded at the start of each block, changes made to it
+//! after the last committed block are not a part of the fixture.
+//!
+
+use {
+    super::{
+        archive::{self, ArchivedBlock},
+        LedgerState,
+    },
+    crate::data_model::{Transaction, TxnEffect},
+    ruc::*,
+    std::{fs, path::PathBuf},
+};
+
+/// The directory of relative fixture names.
+pub const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+
+/// A scenario being captured, block by block.
+pub struct LedgerFixture {
+    ledger: LedgerState,
+    blocks: Vec<ArchivedBlock>,
+}
+
+impl LedgerFixture {
+    /// Start a scenario from an empty temporary ledger.
+    pub fn new() -> Self {
+        LedgerFixture {
+            ledger: LedgerState::tmp_ledger(),
+            blocks: vec![],
+        }
+    }
+
+    /// Load the fixture `name` into an empty temporary ledger.
+    pub fn load(name: &str) -> Result<LedgerState> {
+        let path = fixture_path(name);
+        let path = path.to_str().c(d!())?;
+        let mut ledger = LedgerState::tmp_ledger();
+        let report = archive::replay(&mut ledger, path, None).c(d!())?;
+        if let Some(d) = report.divergence {
+            return Err(eg!(format!(
+                "stale fixture {}, diverged at height {}: {:?} {}, remove it to capture it again",
+                name,
+                d.height,
+                d.fields,
+                d.error.unwrap_or_default()
+            )));
+        }
+        Ok(ledger)
+    }
+
+    /// Load the fixture `name`, or capture it by running `scenario`
+    /// if it does not exist yet.
+    pub fn load_or_capture<F>(name: &str, scenario: F) -> Result<LedgerState>
+    where
+        F: FnOnce(&mut LedgerFixture) -> Result<()>,
+    {
+        if fixture_path(name).exists() {
+            return LedgerFixture::load(name).c(d!());
+        }
+        let mut fixture = LedgerFixture::new();
+        scenario(&mut fixture).c(d!())?;
+        fixture.save(name).c(d!())?;
+        Ok(fixture.into_ledger())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn ledger(&self) -> &LedgerState {
+        &self.ledger
+    }
+
+    /// Changes made through it out of `commit` are not recorded,
+    /// except the ones to the staking state before the next block.
+    #[inline(always)]
+    pub fn ledger_mut(&mut self) -> &mut LedgerState {
+        &mut self.ledger
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn into_ledger(self) -> LedgerState {
+        self.ledger
+    }
+
+    /// Commit `txns` in a block at the next tendermint height,
+    /// all of them must be accepted.
+    pub fn commit(&mut self, txns: Vec<Transaction>) -> Result<()> {
+        let staking = self.ledger.get_staking().clone();
+        let height = 1 + self.ledger.get_tendermint_height();
+
+        let mut block = self.ledger.start_block().c(d!())?;
+        for (idx, tx) in txns.into_iter().enumerate() {
+            let applied = TxnEffect::compute_effect(tx)
+                .c(d!())
+                .and_then(|txe| self.ledger.apply_transaction(&mut block, txe).c(d!()));
+            if let Err(e) = applied {
+                // the rejected transaction has not been added to the block
+                self.ledger.block_ctx = Some(block);
+                return Err(e).c(d!(format!("transaction {} rejected", idx)));
+            }
+        }
+        self.ledger.finish_block(block).c(d!())?;
+        self.ledger.set_tendermint_height(height);
+
+        self.blocks.push(ArchivedBlock {
+            height,
+            staking,
+            block: self.ledger.blocks.last().c(d!())?,
+        });
+        Ok(())
+    }
+
+    /// Save the committed blocks as the fixture `name`, replacing any previous one.
+    pub fn save(&self, name: &str) -> Result<()> {
+        let path = fixture_path(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).c(d!())?;
+        }
+        omit!(fs::remove_file(&path));
+        let path = path.to_str().c(d!())?;
+        self.blocks
+            .iter()
+            .try_for_each(|b| archive::append(path, b).c(d!()))
+    }
+}
+
+impl Default for LedgerFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// absolute paths are taken as is
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(FIXTURE_DIR).join(name)
+}
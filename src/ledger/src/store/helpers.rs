@@ -158,6 +158,7 @@ pub fn create_issue_and_transfer_txn(
                 id: None,
                 record: ba.clone(),
                 lien: None,
+                memo: None,
             },
             None,
         )],
@@ -216,7 +217,33 @@ pub fn create_issue_and_transfer_txn_with_asset_tracing(
     seq_num: u64,
     tracing_policy: TracingPolicy,
 ) -> (Transaction, AssetRecord) {
-    let tracing_policies = TracingPolicies::from_policy(tracing_policy);
+    create_issue_and_transfer_txn_with_tracing_policies(
+        ledger,
+        params,
+        code,
+        amount,
+        issuer_keys,
+        recipient_pk,
+        seq_num,
+        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        TracingPolicies::from_policy(tracing_policy),
+    )
+}
+
+/// Like `create_issue_and_transfer_txn_with_asset_tracing`,
+/// the records carry the tracer memos of all `tracing_policies`
+#[allow(clippy::too_many_arguments)]
+pub fn create_issue_and_transfer_txn_with_tracing_policies(
+    ledger: &mut LedgerState,
+    params: &PublicParams,
+    code: &AssetTypeCode,
+    amount: u64,
+    issuer_keys: &XfrKeyPair,
+    recipient_pk: &XfrPublicKey,
+    seq_num: u64,
+    record_type: AssetRecordType,
+    tracing_policies: TracingPolicies,
+) -> (Transaction, AssetRecord) {
     let xfr_note_policies = XfrNotePolicies::new(
         vec![tracing_policies.clone()],
         vec![None],
@@ -227,7 +254,7 @@ pub fn create_issue_and_transfer_txn_with_asset_tracing(
     let ar_template = AssetRecordTemplate::with_asset_tracing(
         amount,
         code.val,
-        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        record_type,
         issuer_keys.get_pk(),
         tracing_policies.clone(),
     );
@@ -235,7 +262,7 @@ pub fn create_issue_and_transfer_txn_with_asset_tracing(
         &mut ledger.get_prng(),
         &params.pc_gens,
         &ar_template,
-        vec![vec![]],
+        vec![vec![]; tracing_policies.get_policies().len().max(1)],
     );
 
     let asset_issuance_body = IssueAssetBody::new(
@@ -246,6 +273,7 @@ pub fn create_issue_and_transfer_txn_with_asset_tracing(
                 id: None,
                 record: ba.clone(),
                 lien: None,
+                memo: None,
             },
             None,
         )],
@@ -265,7 +293,7 @@ pub fn create_issue_and_transfer_txn_with_asset_tracing(
     let ar_template = AssetRecordTemplate::with_asset_tracing(
         amount,
         code.val,
-        AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+        record_type,
         *recipient_pk,
         tracing_policies.clone(),
     );
@@ -334,6 +362,7 @@ pub fn create_issuance_txn(
                 id: None,
                 record: ba,
                 lien: None,
+                memo: None,
             },
             None,
         )],
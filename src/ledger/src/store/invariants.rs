@@ -0,0 +1,216 @@
+//!
+//! # Ledger invariants
+//!
+//! Checks that must hold for any sequence of transactions accepted by the ledger:
+//!
+//! - no TXO is spent twice
+//! - the issued supply of an asset never exceeds its cap
+//! - the tracing policies of an asset are always attached to its transferred records
+//!
+//! They are derived from the transactions themselves instead of the bookkeeping
+//! of `TxnEffect`, so forks adding their own operations or validation rules
+//! can run the same checks against what their ledger accepts.
+//!
+
+use {
+    super::LedgerState,
+    crate::data_model::{
+        tracing_policies_at, AssetType, AssetTypeCode, Operation, Transaction,
+        TxnEffect, TxoRef, TxoSID,
+    },
+    ruc::*,
+    std::collections::{HashMap, HashSet},
+    zei::xfr::structs::XfrAmount,
+};
+
+/// Tracks the transactions accepted by a ledger and checks the invariants on them.
+///
+/// Every transaction accepted after the creation of an instance must be
+/// passed to `check_effect` in order, including the ones of unfinished blocks.
+#[derive(Debug, Default)]
+pub struct Invariants {
+    // TXOs spent by the checked transactions
+    spent: HashSet<TxoSID>,
+    // assets defined by the checked transactions, maybe not committed yet
+    defined: HashMap<AssetTypeCode, AssetType>,
+    // issued amounts, including the ones not committed yet
+    issued: HashMap<AssetTypeCode, u64>,
+}
+
+impl Invariants {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check an effect accepted by `LedgerState::apply_transaction`, then record it.
+    pub fn check_effect(
+        &mut self,
+        ledger: &LedgerState,
+        effect: &TxnEffect,
+    ) -> Result<()> {
+        self.defined.extend(
+            effect
+                .new_asset_codes
+                .iter()
+                .map(|(code, asset)| (*code, asset.clone())),
+        );
+
+        self.check_spending(ledger, &effect.txn)
+            .c(d!("TXO spent twice"))
+            .and_then(|_| {
+                self.check_issuance(ledger, &effect.txn)
+                    .c(d!("issuance cap exceeded"))
+            })
+            .and_then(|_| {
+                self.check_tracing(ledger, &effect.txn)
+                    .c(d!("tracing policies bypassed"))
+            })
+    }
+
+    // Absolute inputs must be unspent in the ledger and in the checked transactions,
+    // relative ones are outputs of the same transaction.
+    fn check_spending(&mut self, ledger: &LedgerState, tx: &Transaction) -> Result<()> {
+        let mut inputs = HashSet::new();
+        for op in tx.body.operations.iter() {
+            if let Operation::TransferAsset(x) = op {
+                for i in x.body.inputs.iter() {
+                    if let TxoRef::Absolute(sid) = i {
+                        if !inputs.insert(*sid)
+                            || self.spent.contains(sid)
+                            || ledger.status.get_utxo(*sid).is_none()
+                        {
+                            return Err(eg!(format!("{:?}", sid)));
+                        }
+                    }
+                }
+            }
+        }
+
+        self.spent.extend(inputs);
+        Ok(())
+    }
+
+    fn check_issuance(&mut self, ledger: &LedgerState, tx: &Transaction) -> Result<()> {
+        let mut amounts: HashMap<AssetTypeCode, u64> = HashMap::new();
+        for op in tx.body.operations.iter() {
+            if let Operation::IssueAsset(x) = op {
+                let code = x.body.code;
+                let asset = self.asset_type(ledger, &code).c(d!())?;
+                for (output, _) in x.body.records.iter() {
+                    match output.record.amount {
+                        XfrAmount::NonConfidential(am) => {
+                            let total = amounts.entry(code).or_insert(0);
+                            *total = total.checked_add(am).c(d!())?;
+                        }
+                        // the amount of a capped asset must be known
+                        XfrAmount::Confidential(_) => {
+                            if asset.has_issuance_restrictions() {
+                                return Err(eg!(format!(
+                                    "confidential issuance of {}",
+                                    code.to_base64()
+                                )));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for (code, am) in amounts {
+            let cap = self
+                .asset_type(ledger, &code)
+                .c(d!())?
+                .properties
+                .asset_rules
+                .max_units;
+            let issued = self.issued.entry(code).or_insert_with(|| {
+                ledger.status.issuance_amounts.get(&code).unwrap_or(0)
+            });
+            let total = issued.checked_add(am).c(d!())?;
+            if let Some(cap) = cap {
+                if total > cap {
+                    return Err(eg!(format!(
+                        "{}: {} issued, cap {}",
+                        code.to_base64(),
+                        total,
+                        cap
+                    )));
+                }
+            }
+            *issued = total;
+        }
+
+        Ok(())
+    }
+
+    // Records with a confidential asset type are skipped,
+    // assets with tracing policies can not become confidential.
+    fn check_tracing(&self, ledger: &LedgerState, tx: &Transaction) -> Result<()> {
+        // tracer keys are rotated at the height of the next tendermint block
+        let height = ledger.status.td_commit_height + 1;
+
+        for op in tx.body.operations.iter() {
+            if let Operation::TransferAsset(x) = op {
+                let policies = &x.body.policies;
+                let transfer = &x.body.transfer;
+                if policies.inputs_tracing_policies.len() != transfer.inputs.len()
+                    || policies.outputs_tracing_policies.len() != transfer.outputs.len()
+                {
+                    return Err(eg!("tracing policies mismatch"));
+                }
+
+                let records = transfer
+                    .inputs
+                    .iter()
+                    .zip(policies.inputs_tracing_policies.iter())
+                    .chain(
+                        transfer
+                            .outputs
+                            .iter()
+                            .zip(policies.outputs_tracing_policies.iter()),
+                    );
+                for (record, attached) in records {
+                    let code = match record.asset_type.get_asset_type() {
+                        Some(val) => AssetTypeCode { val },
+                        None => continue,
+                    };
+                    let asset = self.asset_type(ledger, &code).c(d!())?;
+                    let threshold = asset.properties.asset_rules.tracing_threshold;
+                    if let (Some(am), Some(th)) = (record.amount.get_amount(), threshold)
+                    {
+                        if am <= th {
+                            continue;
+                        }
+                    }
+
+                    let required = tracing_policies_at(
+                        asset.get_tracing_policies_ref(),
+                        ledger.status.get_tracer_key_rotations(&code),
+                        height,
+                    );
+                    if required
+                        .get_policies()
+                        .iter()
+                        .any(|p| !attached.get_policies().contains(p))
+                    {
+                        return Err(eg!(format!("asset {}", code.to_base64())));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn asset_type(
+        &self,
+        ledger: &LedgerState,
+        code: &AssetTypeCode,
+    ) -> Option<AssetType> {
+        self.defined
+            .get(code)
+            .cloned()
+            .or_else(|| ledger.get_asset_type(code))
+    }
+}
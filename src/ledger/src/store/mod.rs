@@ -3,7 +3,14 @@
 //!
 
 pub mod api_cache;
+pub mod archive;
+pub mod batch;
+pub mod checksum;
+pub mod export;
+pub mod fixture;
 pub mod helpers;
+pub mod invariants;
+pub mod sim;
 mod test;
 pub mod utils;
 
@@ -11,26 +18,46 @@ pub use fbnc;
 
 use {
     crate::{
+        converter::{
+            designated_transfer::{
+                DesignatedTransferOperation, DesignatedTransferState,
+            },
+            ibc::IbcState,
+            kv::KvState,
+            notary::{anchor_key, NotaryState},
+            payment_stream::{PaymentStreamOperation, PaymentStreamState},
+            prism::PrismState,
+            pull_payment::{PullPaymentOperation, PullPaymentState},
+            session_key::SessionKeyState,
+            spending_policy::SpendingPolicyState,
+            vesting::VestingState,
+        },
         data_model::{
-            AssetType, AssetTypeCode, AuthenticatedBlock, AuthenticatedTransaction,
-            AuthenticatedUtxo, AuthenticatedUtxoStatus, BlockEffect, BlockSID,
-            FinalizedBlock, FinalizedTransaction, IssuerKeyPair, IssuerPublicKey,
-            OutputPosition, StateCommitmentData, Transaction, TransferType, TxnEffect,
-            TxnSID, TxnTempSID, TxoSID, UnAuthenticatedUtxo, Utxo, UtxoStatus,
-            BLACK_HOLE_PUBKEY,
+            tracing_policies_at, AssetMirror, AssetType, AssetTypeCode,
+            AuthenticatedBlock, AuthenticatedIbcValue, AuthenticatedKvValue,
+            AuthenticatedNotarizedHash, AuthenticatedRevocationRegistry,
+            AuthenticatedTransaction, AuthenticatedUtxo, AuthenticatedUtxoStatus,
+            BlockEffect, BlockSID, FinalizedBlock, FinalizedTransaction, IssuerKeyPair,
+            IssuerPublicKey, OutputPosition, RevocationDigests, RevocationRegistry,
+            StateCommitmentData, TracerKeyRotation, Transaction, TransferType,
+            TxnEffect, TxnSID, TxnTempSID, TxoSID, UnAuthenticatedUtxo, Utxo,
+            UtxoStatus, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
         },
         staking::{
-            Amount, Power, Staking, TendermintAddrRef, FF_PK_EXTRA_120_0000, FF_PK_LIST,
-            FRA_TOTAL_AMOUNT, KEEP_HIST,
+            Amount, BlockHeight, Power, Staking, TendermintAddrRef,
+            FF_PK_EXTRA_120_0000, FF_PK_LIST, FRA_TOTAL_AMOUNT, KEEP_HIST,
         },
-        LSSED_VAR, SNAPSHOT_ENTRIES_DIR,
     },
     api_cache::ApiCache,
+    batch::{SyncMode, WriteBatch},
     bitmap::{BitMap, SparseMap},
     config::abci::global_cfg::CFG,
     cryptohash::sha256::Digest as BitDigest,
     fbnc::{new_mapx, new_mapxnk, new_vecx, Mapx, Mapxnk, Vecx},
-    globutils::{HashOf, ProofOf},
+    globutils::{
+        persist::{self, OpenMode},
+        HashOf, ProofOf,
+    },
     merkle_tree::AppendOnlyMerkle,
     parking_lot::RwLock,
     rand_chacha::ChaChaRng,
@@ -39,9 +66,7 @@ use {
     serde::{Deserialize, Serialize},
     sliding_set::SlidingSet,
     std::{
-        collections::{BTreeMap, HashMap, HashSet},
-        env,
-        fs::{self, OpenOptions},
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
         io::ErrorKind,
         mem,
         ops::{Deref, DerefMut},
@@ -56,6 +81,9 @@ use {
 
 const TRANSACTION_WINDOW_WIDTH: u64 = 128;
 
+/// How many blocks the transactions stay referenceable by `PendingTxoRef`
+pub const PENDING_REF_WINDOW: u64 = 128;
+
 type TmpSidMap = HashMap<TxnTempSID, (TxnSID, Vec<TxoSID>)>;
 
 /// findora ledger
@@ -83,6 +111,8 @@ pub struct LedgerState {
     txn_merkle: Arc<RwLock<AppendOnlyMerkle>>,
     // Bitmap tracing all the live TXOs
     utxo_map: Arc<RwLock<BitMap>>,
+    // When the three above are written to disk
+    batch: Arc<RwLock<WriteBatch>>,
 }
 
 impl LedgerState {
@@ -104,6 +134,7 @@ impl LedgerState {
             .take()
             .map(|mut b| {
                 *b.get_staking_simulator_mut() = self.get_staking().clone();
+                b.prism_locked = self.status.prism.locked.clone();
                 b
             })
             .c(d!())
@@ -113,13 +144,52 @@ impl LedgerState {
     pub fn apply_transaction(
         &self,
         block: &mut BlockEffect,
-        txe: TxnEffect,
+        mut txe: TxnEffect,
     ) -> Result<TxnTempSID> {
         let tx = txe.txn.clone();
         self.status
-            .check_txn_effects(&txe)
+            .resolve_pending_inputs(&mut txe)
             .c(d!())
-            .and_then(|_| block.add_txn_effect(txe).c(d!()))
+            .and_then(|_| self.status.check_txn_effects(&txe).c(d!()))
+            .and_then(|_| {
+                // along with the transfers before it in the same block
+                self.status
+                    .spending_policies
+                    .check_transfers(
+                        &txe.txn,
+                        &txe.outgoing,
+                        &block.outgoing,
+                        self.status.td_commit_height + 1,
+                    )
+                    .c(d!())
+            })
+            .and_then(|_| {
+                // along with the delegated spends before it in the same block
+                self.status
+                    .session_keys
+                    .check_spends(
+                        &txe.delegated_spends,
+                        &block.session_charges,
+                        self.status.td_commit_height + 1,
+                    )
+                    .c(d!())
+            })
+            .and_then(|charges| {
+                // the locked amounts of the inputs must be carried over
+                self.status
+                    .vesting
+                    .check_transfers(&txe, self.status.td_commit_height + 1)
+                    .c(d!())
+                    .map(|vesting| (charges, vesting))
+            })
+            .and_then(|(charges, vesting)| {
+                let tmpid = block.add_txn_effect(txe).c(d!())?;
+                block.session_charges.extend(charges);
+                if !vesting.is_empty() {
+                    block.vesting_outputs.push((tmpid, vesting));
+                }
+                Ok(tmpid)
+            })
             .map(|tmpid| {
                 // NOTE: set at the last position
                 block.staking_simulator.coinbase_check_and_pay(&tx);
@@ -188,15 +258,16 @@ impl LedgerState {
         // Update the transaction Merkle tree
         // Store the location of each utxo so we can create authenticated utxo proofs
         let mut txn_merkle = self.txn_merkle.write();
+        let mut batch = self.batch.write();
         for (tmp_sid, txn) in block.temp_sids.iter().zip(block.txns.iter()) {
             let txn = txn.clone();
             let txo_sid_map = tsm.get(&tmp_sid).c(d!())?;
             let txn_sid = txo_sid_map.0;
             let txo_sids = &txo_sid_map.1;
 
-            let merkle_id = txn_merkle
-                .append_hash(&txn.hash(txn_sid).0.hash.into())
-                .c(d!())?;
+            let leaf = txn.hash(txn_sid).0.hash.into();
+            let merkle_id = txn_merkle.append_hash(&leaf).c(d!())?;
+            batch.append_txn(leaf);
 
             tx_block.push(FinalizedTransaction {
                 txn: txn.clone(),
@@ -210,9 +281,20 @@ impl LedgerState {
                     .txo_to_txn_location
                     .insert(*sid, (txn_sid, OutputPosition(position)));
             }
+
+            self.status.recent_txns.insert(
+                txn.handle_digest(),
+                (self.status.block_commit_count, txo_sids.clone()),
+            );
         }
+        drop(batch);
         drop(txn_merkle);
 
+        let h = self.status.block_commit_count;
+        self.status
+            .recent_txns
+            .retain(|_, (n, _)| *n + PENDING_REF_WINDOW > h);
+
         // Checkpoint
         let block_merkle_id = self.checkpoint(&block).c(d!())?;
         block.temp_sids.clear();
@@ -247,8 +329,10 @@ impl LedgerState {
     pub fn finish_block(&mut self, mut block: BlockEffect) -> Result<TmpSidMap> {
         {
             let mut utxo_map = self.utxo_map.write();
+            let mut batch = self.batch.write();
             for (inp_sid, _) in block.input_txos.iter() {
                 utxo_map.clear(inp_sid.0 as usize).c(d!())?;
+                batch.spend(inp_sid.0);
             }
         }
 
@@ -278,6 +362,13 @@ impl LedgerState {
         self.status.td_commit_height
     }
 
+    /// Set the timestamp of the block being built, in seconds,
+    /// recorded by the operations depending on time.
+    #[inline(always)]
+    pub fn set_block_time(&mut self, timestamp: u64) {
+        self.status.block_time = timestamp;
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_next_txn(&self) -> TxnSID {
@@ -299,7 +390,11 @@ impl LedgerState {
     /// create a tmp ledger for testing purpose
     pub fn tmp_ledger() -> LedgerState {
         fbnc::clear();
+        #[cfg(not(target_arch = "wasm32"))]
         let tmp_dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+        // directories are implicit in the in-memory backend
+        #[cfg(target_arch = "wasm32")]
+        let tmp_dir = format!("/tmp/findora_ledger_{}", rand::random::<u64>());
         LedgerState::new(&tmp_dir, Some("test")).unwrap()
     }
 
@@ -314,11 +409,9 @@ impl LedgerState {
 
         // 2. Append txns_in_block_hash to block_merkle
         //  2.1 Update the block Merkle tree
-        let ret = self
-            .block_merkle
-            .write()
-            .append_hash(&txns_in_block_hash.0.hash.into())
-            .unwrap();
+        let leaf = txns_in_block_hash.0.hash.into();
+        let ret = self.block_merkle.write().append_hash(&leaf).unwrap();
+        self.batch.write().append_block(leaf);
 
         ret
     }
@@ -343,6 +436,14 @@ impl LedgerState {
                 Some(HashOf::new(self.get_staking())),
                 None
             ),
+            revocation: alt!(
+                self.status.revocation_registries.is_empty(),
+                None,
+                Some(HashOf::new(&self.status.get_revocation_digests()))
+            ),
+            ibc: self.status.ibc.root(),
+            kv: self.status.kv.root(),
+            notary: self.status.notary.root(),
         };
 
         self.status
@@ -364,12 +465,12 @@ impl LedgerState {
     // Initialize a bitmap to track the unspent utxos.
     #[inline(always)]
     fn init_utxo_map(path: &str) -> Result<BitMap> {
-        let mut file = OpenOptions::new();
-        let f = file.read(true).write(true);
+        let backend = persist::backend();
 
-        f.open(path)
+        backend
+            .open(path, OpenMode::Existing)
             .c(d!())
-            .or_else(|e| f.create(true).truncate(true).open(path).c(d!(e)))
+            .or_else(|e| backend.open(path, OpenMode::Truncate).c(d!(e)))
             .and_then(|f| BitMap::open(f).c(d!()))
     }
 
@@ -386,28 +487,33 @@ impl LedgerState {
         let utxo_map_path = format!("{}/{}utxo_map", basedir, &prefix);
 
         // These iterms will be set under ${BNC_DATA_DIR}
-        fs::create_dir_all(&basedir).c(d!())?;
+        persist::backend().create_dir_all(basedir).c(d!())?;
         let snapshot_file = format!("{}ledger_status", &prefix);
 
-        let snapshot_entries_dir = prefix.clone() + "ledger_status_subdata";
-        env::set_var(LSSED_VAR, &snapshot_entries_dir);
-
         let blocks_path = prefix.clone() + "blocks";
         let tx_to_block_location_path = prefix.clone() + "tx_to_block_location";
 
+        let mut block_merkle =
+            LedgerState::init_merkle_log(&block_merkle_path).c(d!())?;
+        let mut txn_merkle = LedgerState::init_merkle_log(&txn_merkle_path).c(d!())?;
+        let mut utxo_map = LedgerState::init_utxo_map(&utxo_map_path).c(d!())?;
+        // the blocks finished after the last group commit, if any
+        let batch = WriteBatch::open(
+            &format!("{}/{}ledger_wal", basedir, &prefix),
+            &mut utxo_map,
+            &mut txn_merkle,
+            &mut block_merkle,
+        )
+        .c(d!())?;
+
         let mut ledger = LedgerState {
             status: LedgerStatus::new(&basedir, &snapshot_file).c(d!())?,
-            block_merkle: Arc::new(RwLock::new(
-                LedgerState::init_merkle_log(&block_merkle_path).c(d!())?,
-            )),
-            txn_merkle: Arc::new(RwLock::new(
-                LedgerState::init_merkle_log(&txn_merkle_path).c(d!())?,
-            )),
+            block_merkle: Arc::new(RwLock::new(block_merkle)),
+            txn_merkle: Arc::new(RwLock::new(txn_merkle)),
             blocks: new_vecx!(&blocks_path),
             tx_to_block_location: new_mapxnk!(&tx_to_block_location_path),
-            utxo_map: Arc::new(RwLock::new(
-                LedgerState::init_utxo_map(&utxo_map_path).c(d!())?,
-            )),
+            utxo_map: Arc::new(RwLock::new(utxo_map)),
+            batch: Arc::new(RwLock::new(batch)),
             block_ctx: Some(BlockEffect::default()),
             api_cache: alt!(*KEEP_HIST, Some(ApiCache::new(&prefix)), None),
         };
@@ -442,13 +548,50 @@ impl LedgerState {
             .cur_height()
             .saturating_sub(self.get_block_commit_count() + 1);
         self.compute_and_save_state_commitment_data(pulse_count);
-        self.utxo_map.write().write().c(d!())?;
-        self.txn_merkle.write().write().c(d!())?;
-        self.block_merkle.write().write().c(d!())?;
+        self.batch
+            .write()
+            .finish_block(
+                &mut self.utxo_map.write(),
+                &mut self.txn_merkle.write(),
+                &mut self.block_merkle.write(),
+            )
+            .c(d!())?;
 
         Ok(merkle_id)
     }
 
+    /// When the utxo bitmap and the Merkle trees are written to disk,
+    /// see `batch`, the blocks not written yet are written first.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) -> Result<()> {
+        self.batch
+            .write()
+            .set_mode(
+                mode,
+                &mut self.utxo_map.write(),
+                &mut self.txn_merkle.write(),
+                &mut self.block_merkle.write(),
+            )
+            .c(d!())
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn get_sync_mode(&self) -> SyncMode {
+        self.batch.read().mode()
+    }
+
+    /// Write and sync the blocks not written yet, by the `batched` and `off` modes.
+    pub fn sync(&mut self) -> Result<()> {
+        self.batch
+            .write()
+            .commit(
+                &mut self.utxo_map.write(),
+                &mut self.txn_merkle.write(),
+                &mut self.block_merkle.write(),
+            )
+            .c(d!())
+    }
+
     /// A helper for setting block rewards in ABCI.
     // This function is called from end_block
     pub fn staking_set_last_block_rewards(
@@ -497,6 +640,53 @@ impl LedgerState {
         // Get total delegation amount
         let gda = s.get_global_delegation_amount();
 
+        if CFG.checkpoint.epoch_rewards_height < h {
+            // distributed at the close of the epoch, see `staking::epoch`
+            self.get_staking_mut()
+                .epoch_accumulate(
+                    &pk,
+                    return_rate,
+                    total_delegation_amount_of_validator,
+                    gda,
+                )
+                .c(d!())?;
+        } else {
+            self.staking_set_delegation_rewards(
+                &pk,
+                h,
+                return_rate,
+                commission_rate,
+                gdp,
+                total_delegation_amount_of_validator,
+                gda,
+                cbl,
+            )
+            .c(d!())?;
+        }
+
+        if let Some(vote_percent) = block_vote_percent {
+            self.get_staking_mut()
+                .set_proposer_rewards(&pk, vote_percent)
+                .c(d!())?;
+        }
+
+        Ok(())
+    }
+
+    // Set the delegation rewards of the current block on every delegation
+    // to the proposer, the logic before `staking::epoch`.
+    #[allow(clippy::too_many_arguments)]
+    fn staking_set_delegation_rewards(
+        &mut self,
+        pk: &XfrPublicKey,
+        h: BlockHeight,
+        return_rate: [u128; 2],
+        commission_rate: [u64; 2],
+        gdp: [u64; 2],
+        total_delegation_amount_of_validator: Amount,
+        gda: Amount,
+        cbl: Amount,
+    ) -> Result<()> {
         // Iterate over every delegation , check if it has an entry for this validator .
         // Set commission rewards for the validator , if a valid delegation to this validator exists
         let commissions = self
@@ -504,10 +694,10 @@ impl LedgerState {
             .delegation_info
             .global_delegation_records_map
             .values_mut()
-            .filter(|d| d.validator_entry_exists(&pk))
+            .filter(|d| d.validator_entry_exists(pk))
             .map(|d| {
                 d.set_delegation_rewards(
-                    &pk,
+                    pk,
                     h,
                     return_rate,
                     commission_rate,
@@ -522,16 +712,10 @@ impl LedgerState {
             .c(d!())?;
 
         // Add total commission to the Validators own delegation
-        if let Some(v) = self.get_staking_mut().delegation_get_mut(&pk) {
+        if let Some(v) = self.get_staking_mut().delegation_get_mut(pk) {
             v.rwd_amount = v.rwd_amount.saturating_add(commissions.into_iter().sum());
         }
 
-        if let Some(vote_percent) = block_vote_percent {
-            self.get_staking_mut()
-                .set_proposer_rewards(&pk, vote_percent)
-                .c(d!())?;
-        }
-
         Ok(())
     }
 
@@ -834,6 +1018,63 @@ impl LedgerState {
         (commitment, block_count)
     }
 
+    /// Get the revocation list of a credential issuer and its proof data,
+    /// also proves the absence of the list if it does not exist
+    pub fn get_revocation_registry(
+        &self,
+        issuer_id: &str,
+    ) -> Option<AuthenticatedRevocationRegistry> {
+        let state_commitment_data = self.status.state_commitment_data.as_ref()?;
+        Some(AuthenticatedRevocationRegistry {
+            issuer_id: issuer_id.to_owned(),
+            registry: self.status.get_revocation_registry(issuer_id).cloned(),
+            digests: self.status.get_revocation_digests(),
+            state_commitment_data: state_commitment_data.clone(),
+            state_commitment: state_commitment_data.compute_commitment(),
+        })
+    }
+
+    /// A value of the IBC store with its proof, see `commitment_key` and `ack_key`
+    pub fn get_ibc_value(&self, key: &str) -> Option<AuthenticatedIbcValue> {
+        let state_commitment_data = self.status.state_commitment_data.as_ref()?;
+        Some(AuthenticatedIbcValue {
+            key: key.to_owned(),
+            value: *self.status.ibc.store.get(key)?,
+            proof: self.status.ibc.prove(key)?,
+            state_commitment_data: state_commitment_data.clone(),
+            state_commitment: state_commitment_data.compute_commitment(),
+        })
+    }
+
+    /// An entry of the key-value store with its proof
+    pub fn get_kv_value(&self, key: &str) -> Option<AuthenticatedKvValue> {
+        let state_commitment_data = self.status.state_commitment_data.as_ref()?;
+        Some(AuthenticatedKvValue {
+            key: key.to_owned(),
+            entry: self.status.kv.get(key)?,
+            proof: self.status.kv.prove(key)?,
+            state_commitment_data: state_commitment_data.clone(),
+            state_commitment: state_commitment_data.compute_commitment(),
+        })
+    }
+
+    /// A hash anchored by `owner` with its proof
+    pub fn get_notarized_hash(
+        &self,
+        hash: &[u8; 32],
+        owner: &XfrPublicKey,
+    ) -> Option<AuthenticatedNotarizedHash> {
+        let state_commitment_data = self.status.state_commitment_data.as_ref()?;
+        let key = anchor_key(hash, owner);
+        Some(AuthenticatedNotarizedHash {
+            record: self.status.notary.get_record(hash, owner)?,
+            proof: self.status.notary.prove(&key)?,
+            key,
+            state_commitment_data: state_commitment_data.clone(),
+            state_commitment: state_commitment_data.compute_commitment(),
+        })
+    }
+
     /// Get utxo status and its proof data
     pub fn get_utxo_status(&self, addr: TxoSID) -> AuthenticatedUtxoStatus {
         let state_commitment_data = self.status.state_commitment_data.as_ref().unwrap();
@@ -862,6 +1103,13 @@ impl LedgerState {
         }
     }
 
+    /// The staking state before the transactions of the last finished block,
+    /// the one its state commitment is computed from, until the next block starts.
+    #[inline(always)]
+    pub fn get_last_block_staking(&self) -> Option<&Staking> {
+        self.block_ctx.as_ref().map(|b| &b.staking_simulator)
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_staking(&self) -> &Staking {
@@ -983,6 +1231,52 @@ pub struct LedgerStatus {
     staking: Staking,
     // tendermint commit height
     td_commit_height: u64,
+    // Revocation lists of credential issuers, keyed by `cred_issuer_id`
+    #[serde(default)]
+    revocation_registries: BTreeMap<String, RevocationRegistry>,
+    // Tracer key rotations of asset tracing policies, in the order they were recorded
+    #[serde(default, with = "globutils::serde_pairs")]
+    tracer_key_rotations: BTreeMap<AssetTypeCode, Vec<TracerKeyRotation>>,
+    // Authorities and locked assets of the prism bridge
+    #[serde(default)]
+    prism: PrismState,
+    // Mirrors of assets on external chains, at most one per chain
+    #[serde(default, with = "globutils::serde_pairs")]
+    asset_mirrors: BTreeMap<AssetTypeCode, Vec<AssetMirror>>,
+    // Light clients of counterparty chains and the IBC store
+    #[serde(default)]
+    ibc: IbcState,
+    // Authorizations of pull payments
+    #[serde(default)]
+    pull_payments: PullPaymentState,
+    // Payment streams vesting linearly
+    #[serde(default)]
+    payment_streams: PaymentStreamState,
+    // Designated transfers waiting for their recipients
+    #[serde(default)]
+    designated_transfers: DesignatedTransferState,
+    // Spending policies bound to keys
+    #[serde(default)]
+    spending_policies: SpendingPolicyState,
+    // Session keys signing transfers for their primary keys
+    #[serde(default)]
+    session_keys: SessionKeyState,
+    // Vesting schedules of the unspent outputs
+    #[serde(default)]
+    vesting: VestingState,
+    // Small data blobs anchored by applications
+    #[serde(default)]
+    kv: KvState,
+    // Hashes notarized by their submitters
+    #[serde(default)]
+    notary: NotaryState,
+    // Timestamp of the current block in seconds, from its tendermint header
+    #[serde(default)]
+    block_time: u64,
+    // Outputs of the transactions of the last `PENDING_REF_WINDOW` blocks,
+    // by their handles, in the form of `(block_commit_count, outputs)`
+    #[serde(default, with = "globutils::serde_pairs")]
+    recent_txns: BTreeMap<[u8; 32], (u64, Vec<TxoSID>)>,
 
     // An obsolete feature, ignore it!
     tracing_policies: HashMap<AssetTypeCode, TracingPolicy>,
@@ -998,6 +1292,116 @@ impl LedgerStatus {
             .unwrap_or_default()
     }
 
+    /// The revocation list of a credential issuer, see `cred_issuer_id`
+    #[inline(always)]
+    pub fn get_revocation_registry(&self, id: &str) -> Option<&RevocationRegistry> {
+        self.revocation_registries.get(id)
+    }
+
+    /// Tracer key rotations of the tracing policies of an asset,
+    /// in the order they were recorded
+    #[inline(always)]
+    pub fn get_tracer_key_rotations(
+        &self,
+        code: &AssetTypeCode,
+    ) -> &[TracerKeyRotation] {
+        self.tracer_key_rotations
+            .get(code)
+            .map(|r| r.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Mirrors of an asset on external chains
+    #[inline(always)]
+    pub fn get_asset_mirrors(&self, code: &AssetTypeCode) -> &[AssetMirror] {
+        self.asset_mirrors
+            .get(code)
+            .map(|m| m.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The asset mirrored by a contract on an external chain
+    pub fn get_mirrored_asset(
+        &self,
+        chain_id: u64,
+        contract: &[u8; 20],
+    ) -> Option<AssetTypeCode> {
+        self.asset_mirrors.iter().find_map(|(code, mirrors)| {
+            mirrors
+                .iter()
+                .any(|m| m.chain_id == chain_id && &m.contract == contract)
+                .then(|| *code)
+        })
+    }
+
+    /// The state of the prism bridge
+    #[inline(always)]
+    pub fn get_prism_state(&self) -> &PrismState {
+        &self.prism
+    }
+
+    /// The cross-chain state
+    #[inline(always)]
+    pub fn get_ibc_state(&self) -> &IbcState {
+        &self.ibc
+    }
+
+    /// Authorizations of pull payments
+    #[inline(always)]
+    pub fn get_pull_payment_state(&self) -> &PullPaymentState {
+        &self.pull_payments
+    }
+
+    /// Payment streams
+    #[inline(always)]
+    pub fn get_payment_stream_state(&self) -> &PaymentStreamState {
+        &self.payment_streams
+    }
+
+    /// Designated transfers
+    #[inline(always)]
+    pub fn get_designated_transfer_state(&self) -> &DesignatedTransferState {
+        &self.designated_transfers
+    }
+
+    /// Spending policies
+    #[inline(always)]
+    pub fn get_spending_policy_state(&self) -> &SpendingPolicyState {
+        &self.spending_policies
+    }
+
+    /// Session keys
+    #[inline(always)]
+    pub fn get_session_key_state(&self) -> &SessionKeyState {
+        &self.session_keys
+    }
+
+    /// Vesting schedules
+    #[inline(always)]
+    pub fn get_vesting_state(&self) -> &VestingState {
+        &self.vesting
+    }
+
+    /// The key-value store
+    #[inline(always)]
+    pub fn get_kv_state(&self) -> &KvState {
+        &self.kv
+    }
+
+    /// The anchored hashes
+    #[inline(always)]
+    pub fn get_notary_state(&self) -> &NotaryState {
+        &self.notary
+    }
+
+    /// Digests of all revocation lists, committed in `StateCommitmentData`
+    pub fn get_revocation_digests(&self) -> RevocationDigests {
+        self.revocation_registries
+            .iter()
+            .map(|(id, r)| (id.clone(), HashOf::new(r)))
+            .collect()
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     fn get_utxo(&self, id: TxoSID) -> Option<Utxo> {
@@ -1046,8 +1450,8 @@ impl LedgerStatus {
     #[inline(always)]
     pub fn new(basedir: &str, snapshot_file: &str) -> Result<LedgerStatus> {
         let path = format!("{}/{}", basedir, snapshot_file);
-        match fs::read_to_string(path) {
-            Ok(s) => serde_json::from_str(&s).c(d!()),
+        match persist::backend().read(&path) {
+            Ok(s) => serde_json::from_slice(&s).c(d!()),
             Err(e) => {
                 if ErrorKind::NotFound != e.kind() {
                     Err(eg!(e))
@@ -1059,19 +1463,20 @@ impl LedgerStatus {
     }
 
     fn create(snapshot_file: &str) -> Result<LedgerStatus> {
-        let utxos_path = SNAPSHOT_ENTRIES_DIR.to_owned() + "/utxo";
+        // apart for each prefix, so several ledgers can live in one process
+        let entries_dir = format!("{}_subdata", snapshot_file);
+
+        let utxos_path = entries_dir.clone() + "/utxo";
         let nonconfidential_balances_path =
-            SNAPSHOT_ENTRIES_DIR.to_owned() + "/nonconfidential_balances";
-        let spent_utxos_path = SNAPSHOT_ENTRIES_DIR.to_owned() + "/spent_utxos";
-        let txo_to_txn_location_path =
-            SNAPSHOT_ENTRIES_DIR.to_owned() + "/txo_to_txn_location";
-        let issuance_amounts_path =
-            SNAPSHOT_ENTRIES_DIR.to_owned() + "/issuance_amounts";
+            entries_dir.clone() + "/nonconfidential_balances";
+        let spent_utxos_path = entries_dir.clone() + "/spent_utxos";
+        let txo_to_txn_location_path = entries_dir.clone() + "/txo_to_txn_location";
+        let issuance_amounts_path = entries_dir.clone() + "/issuance_amounts";
         let state_commitment_versions_path =
-            SNAPSHOT_ENTRIES_DIR.to_owned() + "/state_commitment_versions";
-        let asset_types_path = SNAPSHOT_ENTRIES_DIR.to_owned() + "/asset_types";
-        let issuance_num_path = SNAPSHOT_ENTRIES_DIR.to_owned() + "/issuance_num";
-        let owned_utxos_path = SNAPSHOT_ENTRIES_DIR.to_owned() + "/owned_utxos";
+            entries_dir.clone() + "/state_commitment_versions";
+        let asset_types_path = entries_dir.clone() + "/asset_types";
+        let issuance_num_path = entries_dir.clone() + "/issuance_num";
+        let owned_utxos_path = entries_dir.clone() + "/owned_utxos";
 
         let ledger = LedgerStatus {
             snapshot_file: snapshot_file.to_owned(),
@@ -1091,8 +1496,23 @@ impl LedgerStatus {
             txns_in_block_hash: None,
             state_commitment_data: None,
             block_commit_count: 0,
-            staking: Staking::new(),
+            staking: Staking::new(&entries_dir),
             td_commit_height: 0,
+            revocation_registries: BTreeMap::new(),
+            tracer_key_rotations: BTreeMap::new(),
+            prism: PrismState::default(),
+            asset_mirrors: BTreeMap::new(),
+            ibc: IbcState::default(),
+            pull_payments: PullPaymentState::default(),
+            payment_streams: PaymentStreamState::default(),
+            designated_transfers: DesignatedTransferState::default(),
+            spending_policies: SpendingPolicyState::default(),
+            session_keys: SessionKeyState::default(),
+            vesting: VestingState::default(),
+            kv: KvState::new(&entries_dir),
+            notary: NotaryState::new(&entries_dir),
+            block_time: 0,
+            recent_txns: BTreeMap::new(),
         };
 
         Ok(ledger)
@@ -1105,6 +1525,33 @@ impl LedgerStatus {
         self.sliding_set.incr_current();
     }
 
+    /// The outputs of a transaction committed in the last `PENDING_REF_WINDOW` blocks,
+    /// `handle` is the one of `Transaction::handle_digest`
+    #[inline(always)]
+    pub fn get_recent_txn_outputs(&self, handle: &[u8; 32]) -> Option<&[TxoSID]> {
+        self.recent_txns
+            .get(handle)
+            .map(|(_, sids)| sids.as_slice())
+    }
+
+    // Move the inputs referenced by `PendingTxoRef` to `input_txos`,
+    // their parents must have been committed in a previous block.
+    fn resolve_pending_inputs(&self, txe: &mut TxnEffect) -> Result<()> {
+        for (r, txo) in mem::take(&mut txe.pending_inputs) {
+            let sid = self
+                .get_recent_txn_outputs(&r.txn)
+                .c(d!(format!("transaction {} not committed", r.handle())))?
+                .get(r.idx as usize)
+                .copied()
+                .c(d!("output index out of range"))?;
+            if txe.input_txos.insert(sid, txo).is_some() {
+                return Err(eg!("input spent twice"));
+            }
+            txe.resolved_inputs.push((r, sid));
+        }
+        Ok(())
+    }
+
     // Check that `txn` can be safely applied to the current ledger.
     //
     // Returns the same TxnEffect (unchanged) if it is safe. Consumes `txn`
@@ -1133,13 +1580,18 @@ impl LedgerStatus {
             }
         }
 
+        if txn_effect.txn.is_expired(self.td_commit_height + 1) {
+            return Err(eg!("Transaction expired"));
+        }
+
         // 1. Each input must be unspent and correspond to the claimed record
         // 2. Inputs with transfer restrictions can only be owned by the asset issuer
         for (inp_sid, inp_record) in txn_effect.input_txos.iter() {
             // (1)
             let inp_utxo = self.utxos.get(inp_sid).c(d!("Input must be unspent"))?;
             let record = &(inp_utxo.0);
-            if record != inp_record {
+            // memos are not part of the claimed record
+            if record.record != inp_record.record || record.lien != inp_record.lien {
                 return Err(eg!((format!(
                     "Input must correspond to claimed record: {} != {}",
                     serde_json::to_string(&record).c(d!())?,
@@ -1285,6 +1737,279 @@ impl LedgerStatus {
             }
         }
 
+        // Tracer key rotations
+        // The signing key must be the asset issuer key or the tracing admin key,
+        // rotations of a policy must take effect in a strictly increasing order of heights.
+        for update in txn_effect.tracer_key_updates.iter() {
+            let code = &update.body.asset_type;
+            let r = &update.body.rotation;
+            let asset_type = self.asset_types.get(code).c(d!())?;
+            let rules = &asset_type.properties.asset_rules;
+            if asset_type.properties.issuer != (IssuerPublicKey { key: update.pubkey })
+                && rules.tracing_admin != Some(update.pubkey)
+            {
+                return Err(eg!("neither the asset issuer nor the tracing admin"));
+            }
+            if rules.tracing_policies.get_policy(r.policy_idx).is_none() {
+                return Err(eg!("tracing policy does not exist"));
+            }
+            let last_height = self
+                .get_tracer_key_rotations(code)
+                .iter()
+                .filter(|i| i.policy_idx == r.policy_idx)
+                .map(|i| i.effective_height)
+                .max()
+                .unwrap_or(0);
+            if r.effective_height <= self.td_commit_height.max(last_height) {
+                return Err(eg!("tracer key rotation can not take effect in the past"));
+            }
+        }
+
+        // Asset mirror updates
+        // Must be signed by the asset issuer, a contract can mirror only one asset,
+        // and only existing mirrors can be removed.
+        for update in txn_effect.mirror_updates.iter() {
+            let u = &update.body;
+            let asset_type = self
+                .asset_types
+                .get(&u.asset_type)
+                .or_else(|| txn_effect.new_asset_codes.get(&u.asset_type).cloned())
+                .c(d!())?;
+            if asset_type.properties.issuer != (IssuerPublicKey { key: update.pubkey }) {
+                return Err(eg!("not the asset issuer"));
+            }
+            if let Some(contract) = u.contract.as_ref() {
+                if let Some(code) = self.get_mirrored_asset(u.chain_id, contract) {
+                    if code != u.asset_type {
+                        return Err(eg!(format!(
+                            "the contract mirrors asset {}",
+                            code.to_base64()
+                        )));
+                    }
+                }
+            } else if !self
+                .get_asset_mirrors(&u.asset_type)
+                .iter()
+                .any(|m| m.chain_id == u.chain_id)
+            {
+                return Err(eg!("no mirror to remove"));
+            }
+        }
+
+        // Prism deposits
+        // Traced assets can not be deposited, there are no tracers on the account side.
+        for d in txn_effect.prism_deposits.iter() {
+            let asset_type = self.asset_types.get(&d.body.asset).c(d!())?;
+            if !asset_type
+                .get_tracing_policies_ref()
+                .get_policies()
+                .is_empty()
+            {
+                return Err(eg!("assets with tracing policies can not be deposited"));
+            }
+        }
+
+        // Prism withdrawals
+        // Must be signed by enough authorities, and processed at most once,
+        // the locked amounts are checked in the context of the block.
+        if !txn_effect.prism_withdrawals.is_empty() {
+            let authorities = self
+                .prism
+                .authorities
+                .as_ref()
+                .c(d!("no prism authorities"))?;
+            for w in txn_effect.prism_withdrawals.iter() {
+                authorities.check_signers(&w.signatures).c(d!())?;
+                if self.prism.withdrawals.contains(&w.body.id()) {
+                    return Err(eg!("withdrawal already processed"));
+                }
+            }
+        }
+
+        // Update of the prism authorities
+        // The first set is signed by the issuer of FRA,
+        // the next ones by enough of the current authorities.
+        if let Some(update) = txn_effect.prism_authorities_update.as_ref() {
+            if update.body.version != self.prism.version + 1 {
+                return Err(eg!("invalid version of the prism authorities"));
+            }
+            if let Some(authorities) = self.prism.authorities.as_ref() {
+                authorities.check_signers(&update.signatures).c(d!())?;
+            } else {
+                let fra = self
+                    .asset_types
+                    .get(&AssetTypeCode {
+                        val: ASSET_TYPE_FRA,
+                    })
+                    .c(d!("FRA not defined"))?;
+                if !update
+                    .signatures
+                    .iter()
+                    .any(|(pk, _)| *pk == fra.properties.issuer.key)
+                {
+                    return Err(eg!("not signed by the issuer of FRA"));
+                }
+            }
+        }
+
+        // Cross-chain operations
+        // Checked against the light clients and the IBC store,
+        // packets must be received before their timeout heights.
+        for op in txn_effect.ibc_ops.iter() {
+            self.ibc.check(op, self.td_commit_height + 1).c(d!())?;
+        }
+
+        // Pull payments
+        // Checked against the authorizations, traced assets can not be authorized,
+        // since the released records have no tracing memos.
+        for op in txn_effect.pull_payment_ops.iter() {
+            self.pull_payments
+                .check(op, self.td_commit_height + 1)
+                .c(d!())?;
+            if let PullPaymentOperation::Authorize(i) = op {
+                let asset_type = self.asset_types.get(&i.body.asset).c(d!())?;
+                if !asset_type
+                    .get_tracing_policies_ref()
+                    .get_policies()
+                    .is_empty()
+                {
+                    return Err(eg!(
+                        "assets with tracing policies can not be authorized"
+                    ));
+                }
+            }
+        }
+
+        // Payment streams
+        // Checked against the streams, traced assets can not be streamed either.
+        for op in txn_effect.payment_stream_ops.iter() {
+            self.payment_streams
+                .check(op, self.td_commit_height + 1)
+                .c(d!())?;
+            if let PaymentStreamOperation::Create(i) = op {
+                let asset_type = self.asset_types.get(&i.body.asset).c(d!())?;
+                if !asset_type
+                    .get_tracing_policies_ref()
+                    .get_policies()
+                    .is_empty()
+                {
+                    return Err(eg!("assets with tracing policies can not be streamed"));
+                }
+            }
+        }
+
+        // Designated transfers
+        // Checked against the pending transfers, traced assets can not be locked either.
+        for op in txn_effect.designated_transfer_ops.iter() {
+            self.designated_transfers
+                .check(op, self.td_commit_height + 1)
+                .c(d!())?;
+            if let DesignatedTransferOperation::Send(i) = op {
+                let asset_type = self.asset_types.get(&i.body.asset).c(d!())?;
+                if !asset_type
+                    .get_tracing_policies_ref()
+                    .get_policies()
+                    .is_empty()
+                {
+                    return Err(eg!(
+                        "assets with tracing policies can not be sent as designated"
+                    ));
+                }
+            }
+        }
+
+        // Spending policies
+        // Changes of a policy with a co-signer must be co-signed,
+        // the transfers are checked against the policies along with their block.
+        for op in txn_effect.spending_policy_ops.iter() {
+            self.spending_policies.check(op, &txn_effect.txn).c(d!())?;
+        }
+
+        // Session keys
+        // The delegated spends are checked against the allowances along with their block.
+        for op in txn_effect.session_key_ops.iter() {
+            self.session_keys
+                .check(op, self.td_commit_height + 1)
+                .c(d!())?;
+        }
+
+        // Key-value store
+        // Only the owner of a key can update or delete it.
+        for op in txn_effect.kv_ops.iter() {
+            self.kv.check(op).c(d!())?;
+        }
+
+        // Anchored hashes
+        // Each key anchors a hash once, the first record proves the existence.
+        for op in txn_effect.anchor_hashes.iter() {
+            self.notary.check(op).c(d!())?;
+        }
+
+        // Dust policy
+        // Transfers must not create outputs below the dust limits of their assets.
+        txn_effect
+            .txn
+            .check_dust(dust_limits(self.td_commit_height + 1))
+            .c(d!())?;
+
+        // Revocation list updates
+        // An existing list can only be updated by its owner,
+        // the identifier attribute of a new list must be defined by the issuer.
+        for (id, update) in txn_effect.revocation_updates.iter() {
+            if let Some(registry) = self.revocation_registries.get(id) {
+                if registry.owner != update.pubkey {
+                    return Err(eg!("not the owner of the revocation list"));
+                }
+            } else {
+                update
+                    .body
+                    .issuer
+                    .get_len(&update.body.id_attr)
+                    .c(d!("unknown identifier attribute"))?;
+            }
+        }
+
+        // Credentials revealed in transfers must be neither revoked nor expired,
+        // the transaction will be included in the next tendermint block
+        for (id, attrs) in txn_effect.identity_disclosures.iter() {
+            if let Some(registry) = self.revocation_registries.get(id) {
+                registry.check(attrs, self.td_commit_height + 1).c(d!())?;
+            }
+        }
+
+        // Every tracing policy of an asset must be attached to its records,
+        // so that all the tracers are able to decrypt their memos,
+        // except for small non-confidential amounts under the tracing threshold,
+        // the confidential ones, with no amount here, are always traced
+        for (code, amount, attached) in txn_effect.transfer_tracing_policies.iter() {
+            let asset_type = self
+                .asset_types
+                .get(code)
+                .or_else(|| txn_effect.new_asset_codes.get(code).cloned())
+                .c(d!())?;
+            let threshold = asset_type.properties.asset_rules.tracing_threshold;
+            if let (Some(am), Some(th)) = (amount, threshold) {
+                if *am <= th {
+                    continue;
+                }
+            }
+            // tracer keys are checked against the height of the next tendermint block
+            if tracing_policies_at(
+                asset_type.get_tracing_policies_ref(),
+                self.get_tracer_key_rotations(code),
+                self.td_commit_height + 1,
+            )
+            .get_policies()
+            .iter()
+            .any(|p| !attached.get_policies().contains(p))
+            {
+                return Err(eg!(
+                    "missing tracing policies of asset {}",
+                    code.to_base64()
+                ));
+            }
+        }
+
         // Until we can distinguish assets that have policies that invoke transfer restrictions
         // from those that don't, prevent any non-confidential assets with transfer restrictions
         // from becoming confidential
@@ -1339,6 +2064,7 @@ impl LedgerStatus {
                 }
                 self.spent_utxos.insert(inp_sid, v);
             }
+            self.vesting.remove(&inp_sid);
         }
 
         // Apply memo updates
@@ -1347,11 +2073,129 @@ impl LedgerStatus {
             (*asset).properties.memo = memo;
         }
 
+        // Record tracer key rotations
+        for (code, update) in block.tracer_key_updates.drain() {
+            self.tracer_key_rotations
+                .entry(code)
+                .or_insert_with(Vec::new)
+                .push(update.body.rotation);
+        }
+
+        // Apply revocation list updates
+        for (id, update) in block.revocation_updates.drain() {
+            let registry = self.revocation_registries.entry(id).or_insert_with(|| {
+                RevocationRegistry {
+                    owner: update.pubkey,
+                    id_attr: update.body.id_attr.clone(),
+                    revoked: BTreeSet::new(),
+                    expiries: BTreeMap::new(),
+                }
+            });
+            // renewals only touch the on-chain status,
+            // the credential and its binding to the owner stay unchanged
+            registry.expiries.extend(update.body.expiries);
+            registry.revoked.extend(update.body.revoke);
+            for cred_id in update.body.restore.iter() {
+                registry.revoked.remove(cred_id);
+            }
+        }
+
         for (code, amount) in block.issuance_amounts.drain() {
             let mut amt = self.issuance_amounts.entry(code).or_insert(0);
             *amt.deref_mut() += amount;
         }
 
+        // Apply asset mirror updates
+        for u in block.mirror_updates.drain(..) {
+            let mirrors = self
+                .asset_mirrors
+                .entry(u.asset_type)
+                .or_insert_with(Vec::new);
+            mirrors.retain(|m| m.chain_id != u.chain_id);
+            if let Some(contract) = u.contract {
+                mirrors.push(AssetMirror {
+                    chain_id: u.chain_id,
+                    contract,
+                });
+            }
+            if mirrors.is_empty() {
+                self.asset_mirrors.remove(&u.asset_type);
+            }
+        }
+
+        // Apply the prism bridge
+        for (code, amount) in block.prism_deposits.drain() {
+            let locked = self.prism.locked.entry(code).or_insert(0);
+            *locked = locked.saturating_add(amount);
+        }
+        for w in block.prism_withdrawals.drain(..) {
+            if let Some(locked) = self.prism.locked.get_mut(&w.asset) {
+                *locked -= w.amount;
+            }
+            self.prism.withdrawals.insert(w.id());
+        }
+        if let Some(update) = block.prism_authorities_update.take() {
+            self.prism.authorities = Some(update.authorities);
+            self.prism.version = update.version;
+        }
+
+        // Apply cross-chain operations, in the order they were included
+        for op in block.ibc_ops.drain(..) {
+            self.ibc.apply(op);
+        }
+
+        // Apply pull payments, in the order they were included
+        for op in block.pull_payment_ops.drain(..) {
+            self.pull_payments.apply(op, self.td_commit_height + 1);
+        }
+
+        // Apply payment streams, in the order they were included
+        for op in block.payment_stream_ops.drain(..) {
+            self.payment_streams.apply(op, self.td_commit_height + 1);
+        }
+
+        // Apply designated transfers, in the order they were included,
+        // then expire the ones whose claim period ends with this block
+        for op in block.designated_transfer_ops.drain(..) {
+            self.designated_transfers
+                .apply(op, self.td_commit_height + 1);
+        }
+        self.designated_transfers.sweep(self.td_commit_height + 1);
+
+        // Charge the transfers to the spending policies,
+        // then apply the changes of the policies for the next blocks
+        self.spending_policies
+            .charge(&mem::take(&mut block.outgoing), self.td_commit_height + 1);
+        for op in block.spending_policy_ops.drain(..) {
+            self.spending_policies.apply(op, self.td_commit_height + 1);
+        }
+
+        // Charge the delegated spends to the session keys, apply the changes of
+        // the session keys, then remove the ones expiring with this block
+        self.session_keys
+            .charge(mem::take(&mut block.session_charges));
+        for op in block.session_key_ops.drain(..) {
+            self.session_keys.apply(op);
+        }
+        self.session_keys.sweep(self.td_commit_height + 1);
+
+        // Apply key-value operations, in the order they were included
+        if !block.kv_ops.is_empty() {
+            for op in block.kv_ops.drain(..) {
+                self.kv.apply(op, self.td_commit_height + 1);
+            }
+            self.kv.update_root();
+        }
+
+        // Record anchored hashes with the height and the timestamp of this block
+        if !block.anchor_hashes.is_empty() {
+            for op in block.anchor_hashes.drain(..) {
+                self.notary
+                    .apply(op, self.td_commit_height + 1, self.block_time);
+            }
+            self.notary.update_root();
+        }
+
         // Add new UTXOs
         // Each transaction gets a TxnSID, and each of its unspent TXOs gets
         // a TxoSID. TxoSID assignments are based on the order TXOs appear in
@@ -1360,11 +2204,18 @@ impl LedgerStatus {
         {
             let mut next_txn = self.next_txn.0;
             let mut next_txo = self.next_txo.0;
+            let mut vesting_outputs =
+                block.vesting_outputs.drain(..).collect::<HashMap<_, _>>();
 
             for (ix, txos) in block.temp_sids.iter().zip(block.txos.drain(..)) {
                 let txn_sid = next_txn;
                 next_txn += 1;
 
+                // schedules are indexed by all TXOs, internally spent ones included
+                for (i, schedule) in vesting_outputs.remove(ix).unwrap_or_default() {
+                    self.vesting.insert(TxoSID(next_txo + i as u64), schedule);
+                }
+
                 let mut txn_utxo_sids: Vec<TxoSID> = vec![];
 
                 for txo in txos {
@@ -1439,6 +2290,26 @@ pub struct LoggedBlock {
     pub state: StateCommitmentData,
 }
 
+lazy_static::lazy_static! {
+    static ref DUST_LIMITS: BTreeMap<AssetTypeCode, u64> = CFG
+        .checkpoint
+        .dust_limits
+        .iter()
+        .map(|(code, limit)| (pnk!(AssetTypeCode::new_from_base64(code)), *limit))
+        .collect();
+    static ref NO_DUST_LIMITS: BTreeMap<AssetTypeCode, u64> = BTreeMap::new();
+}
+
+/// The dust limits in force at the tendermint height `h`,
+/// see `CFG.checkpoint.dust_limits`.
+pub fn dust_limits(h: u64) -> &'static BTreeMap<AssetTypeCode, u64> {
+    if CFG.checkpoint.dust_policy_height <= h {
+        &DUST_LIMITS
+    } else {
+        &NO_DUST_LIMITS
+    }
+}
+
 /// Flush data to disk
 pub fn flush_data() {
     fbnc::flush_data();
@@ -0,0 +1,695 @@
+//!
+//! # Deterministic simulation of block application
+//!
+//! Generates random but valid operation sequences(define, issue, transfer)
+//! from a seed, applies them to a temporary ledger block by block,
+//! and checks the invariants below after every block:
+//!
+//! - supply conservation: the unspent outputs of an asset sum up to its issued amount
+//! - UTXO consistency: the unspent(and spent) outputs of the ledger are exactly the simulated ones
+//! - commitment stability: the state commitment of a committed block never changes
+//!
+//! Every accepted transaction is also checked by `Invariants`,
+//! some of the generated assets have issuance caps or tracing policies for that.
+//! With `SimConfig.invalid_txns`, transactions breaking these invariants
+//! are generated too, and they must be rejected by the ledger.
+//!
+//! All randomness is drawn from the seed, so a failed run can be reproduced by its seed.
+//!
+//! NOTE: delegations are not generated, they need an initial validator set,
+//! which only comes from the genesis of a real chain.
+//!
+
+use {
+    super::{invariants::Invariants, LedgerState, StateCommitmentData},
+    crate::data_model::{
+        AssetRules, AssetTypeCode, DefineAsset, DefineAssetBody, IssueAsset,
+        IssueAssetBody, IssuerKeyPair, IssuerPublicKey, Memo, NoReplayToken, Operation,
+        Transaction, TransferAsset, TransferAssetBody, TransferType, TxOutput,
+        TxnEffect, TxoRef, TxoSID,
+    },
+    globutils::HashOf,
+    rand::Rng,
+    rand_chacha::ChaChaRng,
+    rand_core::SeedableRng,
+    ruc::*,
+    std::collections::{BTreeMap, HashMap, HashSet},
+    zei::{
+        setup::PublicParams,
+        xfr::{
+            asset_record::{
+                build_blind_asset_record, open_blind_asset_record, AssetRecordType,
+            },
+            lib::XfrNotePolicies,
+            sig::XfrKeyPair,
+            structs::{
+                AssetRecord, AssetRecordTemplate, AssetTracerKeyPair, BlindAssetRecord,
+                TracingPolicies, TracingPolicy,
+            },
+        },
+    },
+};
+
+const NON_CONFIDENTIAL: AssetRecordType =
+    AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType;
+
+/// Parameters of a simulation.
+#[derive(Clone, Debug)]
+pub struct SimConfig {
+    /// Seed of all randomness
+    pub seed: u64,
+    /// Number of accounts to transfer between
+    pub accounts: usize,
+    /// Number of blocks to apply
+    pub blocks: u64,
+    /// Number of transactions in each block
+    pub txns_per_block: usize,
+    /// Also try double spends, issuances over caps and
+    /// transfers without tracing policies, which must be rejected
+    pub invalid_txns: bool,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            seed: 0,
+            accounts: 5,
+            blocks: 20,
+            txns_per_block: 8,
+            invalid_txns: false,
+        }
+    }
+}
+
+/// Statistics of a finished simulation.
+#[derive(Clone, Debug)]
+pub struct SimReport {
+    #[allow(missing_docs)]
+    pub defines: usize,
+    #[allow(missing_docs)]
+    pub issues: usize,
+    #[allow(missing_docs)]
+    pub transfers: usize,
+    /// Number of invalid transactions rejected by the ledger
+    pub rejected: usize,
+    /// Number of unspent outputs at the end
+    pub utxos: usize,
+    /// The final state commitment
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+}
+
+#[derive(Clone, Debug)]
+struct SimAsset {
+    code: AssetTypeCode,
+    // index of the issuer in `Simulator.accounts`
+    issuer: usize,
+    next_seq_num: u64,
+    issued: u64,
+    cap: Option<u64>,
+    tracing: Option<TracingPolicies>,
+}
+
+impl SimAsset {
+    fn remaining(&self) -> u64 {
+        self.cap.map(|cap| cap - self.issued).unwrap_or(u64::MAX)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SimUtxo {
+    owner: usize,
+    code: AssetTypeCode,
+    amount: u64,
+}
+
+// The expected effect of a generated transaction,
+// applied to the simulated state once its block is committed.
+#[derive(Default)]
+struct SimTxn {
+    define: Option<SimAsset>,
+    issue: Option<(usize, u64)>,
+    spent: Vec<(TxoSID, SimUtxo)>,
+    outputs: Vec<SimUtxo>,
+}
+
+/// Applies random operations to a temporary ledger,
+/// and tracks what the ledger state is expected to be.
+pub struct Simulator {
+    cfg: SimConfig,
+    ledger: LedgerState,
+    invariants: Invariants,
+    prng: ChaChaRng,
+    params: PublicParams,
+    accounts: Vec<XfrKeyPair>,
+    assets: Vec<SimAsset>,
+    utxos: BTreeMap<TxoSID, SimUtxo>,
+    spent: BTreeMap<TxoSID, SimUtxo>,
+    // (block height, state commitment)
+    commitments: Vec<(u64, HashOf<Option<StateCommitmentData>>)>,
+    defines: usize,
+    issues: usize,
+    transfers: usize,
+    rejected: usize,
+}
+
+impl Simulator {
+    #[allow(missing_docs)]
+    pub fn new(cfg: SimConfig) -> Self {
+        let mut prng = ChaChaRng::seed_from_u64(cfg.seed);
+        let accounts = (0..cfg.accounts.max(1))
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect();
+
+        Simulator {
+            cfg,
+            ledger: LedgerState::tmp_ledger(),
+            invariants: Invariants::new(),
+            prng,
+            params: PublicParams::default(),
+            accounts,
+            assets: vec![],
+            utxos: BTreeMap::new(),
+            spent: BTreeMap::new(),
+            commitments: vec![],
+            defines: 0,
+            issues: 0,
+            transfers: 0,
+            rejected: 0,
+        }
+    }
+
+    /// Apply all blocks, stop at the first failure.
+    pub fn run(mut self) -> Result<SimReport> {
+        for _ in 0..self.cfg.blocks {
+            self.run_block()
+                .c(d!(format!("simulation failed, seed: {}", self.cfg.seed)))?;
+        }
+
+        Ok(SimReport {
+            defines: self.defines,
+            issues: self.issues,
+            transfers: self.transfers,
+            rejected: self.rejected,
+            utxos: self.utxos.len(),
+            state_commitment: self.ledger.get_state_commitment().0,
+        })
+    }
+
+    /// Check the ledger against the simulated state.
+    pub fn check_invariants(&self) -> Result<()> {
+        self.check_utxos()
+            .c(d!("UTXO consistency"))
+            .and_then(|_| self.check_supply().c(d!("supply conservation")))
+            .and_then(|_| self.check_commitments().c(d!("commitment stability")))
+    }
+
+    #[allow(missing_docs)]
+    pub fn ledger(&self) -> &LedgerState {
+        &self.ledger
+    }
+
+    fn run_block(&mut self) -> Result<()> {
+        let mut block = self.ledger.start_block().c(d!())?;
+        let mut issued = HashSet::new();
+        let mut pending = vec![];
+
+        for _ in 0..self.cfg.txns_per_block {
+            let (tx, stxn) = self.gen_txn(&mut issued).c(d!())?;
+            let effect = TxnEffect::compute_effect(tx).c(d!())?;
+            let tmp_sid = self
+                .ledger
+                .apply_transaction(&mut block, effect.clone())
+                .c(d!())?;
+            self.invariants
+                .check_effect(&self.ledger, &effect)
+                .c(d!())?;
+            pending.push((tmp_sid, stxn));
+
+            if self.cfg.invalid_txns && self.prng.gen_bool(0.3) {
+                if let Some((tx, restore)) = self.gen_invalid().c(d!())? {
+                    let accepted = TxnEffect::compute_effect(tx).and_then(|effect| {
+                        self.ledger
+                            .apply_transaction(&mut block, effect.clone())
+                            .map(|_| effect)
+                    });
+                    if let Ok(effect) = accepted {
+                        return self
+                            .invariants
+                            .check_effect(&self.ledger, &effect)
+                            .c(d!("invalid transaction accepted"))
+                            .and_then(|_| Err(eg!("invalid transaction accepted")));
+                    }
+                    self.rejected += 1;
+                    self.utxos.extend(restore);
+                }
+            }
+        }
+
+        let sids = self.ledger.finish_block(block).c(d!())?;
+        for (tmp_sid, stxn) in pending {
+            let (_, txo_sids) = sids.get(&tmp_sid).c(d!())?;
+            if txo_sids.len() != stxn.outputs.len() {
+                return Err(eg!("unexpected number of outputs"));
+            }
+            if let Some(asset) = stxn.define {
+                self.assets.push(asset);
+            }
+            if let Some((idx, amount)) = stxn.issue {
+                self.assets[idx].issued += amount;
+            }
+            self.spent.extend(stxn.spent);
+            self.utxos
+                .extend(txo_sids.iter().copied().zip(stxn.outputs));
+        }
+
+        let (commitment, height) = self.ledger.get_state_commitment();
+        self.commitments.push((height, commitment));
+
+        self.check_invariants().c(d!())
+    }
+
+    // Transfers are preferred, an asset can only be issued once in a block,
+    // and newly created assets and outputs can be used in the next block.
+    fn gen_txn(&mut self, issued: &mut HashSet<usize>) -> Result<(Transaction, SimTxn)> {
+        let issuable = (0..self.assets.len())
+            .filter(|i| !issued.contains(i) && 0 < self.assets[*i].remaining())
+            .collect::<Vec<_>>();
+
+        match self.prng.gen_range(0, 10) {
+            n if n < 5 && !self.utxos.is_empty() => self.gen_transfer().c(d!()),
+            n if n < 8 && !issuable.is_empty() => {
+                let idx = issuable[self.prng.gen_range(0, issuable.len())];
+                issued.insert(idx);
+                let amount = self
+                    .prng
+                    .gen_range(1, self.assets[idx].remaining().min(1_000_000) + 1);
+                self.issues += 1;
+                self.gen_issue(idx, amount).c(d!())
+            }
+            _ => self.gen_define().c(d!()),
+        }
+    }
+
+    // One of a double spend, an issuance over the cap and a transfer without
+    // the tracing policies, `None` if there is nothing to break.
+    // Returns the transaction along with the unspent outputs it takes.
+    #[allow(clippy::type_complexity)]
+    fn gen_invalid(&mut self) -> Result<Option<(Transaction, Vec<(TxoSID, SimUtxo)>)>> {
+        match self.prng.gen_range(0, 3) {
+            0 if !self.spent.is_empty() => {
+                let nth = self.prng.gen_range(0, self.spent.len());
+                let (sid, u) = self
+                    .spent
+                    .iter()
+                    .nth(nth)
+                    .map(|(sid, u)| (*sid, u.clone()))
+                    .c(d!())?;
+                let record = self.ledger.status.get_spent_utxo(sid).c(d!())?.0.record;
+                let (tx, _) = self
+                    .transfer_txn(u.owner, u.code, &[(sid, record)], true)
+                    .c(d!())?;
+                Ok(Some((tx, vec![])))
+            }
+            1 => {
+                let capped = (0..self.assets.len())
+                    .filter(|i| self.assets[*i].cap.is_some())
+                    .collect::<Vec<_>>();
+                if capped.is_empty() {
+                    return Ok(None);
+                }
+                let idx = capped[self.prng.gen_range(0, capped.len())];
+                let amount = self.assets[idx].remaining() + self.prng.gen_range(1, 1000);
+                let (tx, _) = self.gen_issue(idx, amount).c(d!())?;
+                Ok(Some((tx, vec![])))
+            }
+            2 => {
+                let traced = self
+                    .utxos
+                    .iter()
+                    .filter(|(_, u)| {
+                        self.assets
+                            .iter()
+                            .any(|a| a.code == u.code && a.tracing.is_some())
+                    })
+                    .map(|(sid, _)| *sid)
+                    .collect::<Vec<_>>();
+                if traced.is_empty() {
+                    return Ok(None);
+                }
+                let sid = traced[self.prng.gen_range(0, traced.len())];
+                let u = self.utxos.remove(&sid).c(d!())?;
+                let record = self.ledger.status.get_utxo(sid).c(d!())?.0.record;
+                let (tx, _) = self
+                    .transfer_txn(u.owner, u.code, &[(sid, record)], false)
+                    .c(d!())?;
+                Ok(Some((tx, vec![(sid, u)])))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Half of the assets are capped, a third of them have a tracing policy.
+    fn gen_define(&mut self) -> Result<(Transaction, SimTxn)> {
+        let issuer = self.prng.gen_range(0, self.accounts.len());
+        let code = AssetTypeCode::gen_random_with_rng(&mut self.prng);
+
+        let cap = alt!(
+            self.prng.gen_bool(0.5),
+            Some(self.prng.gen_range(1_000, 10_000_000)),
+            None
+        );
+        let tracing = alt!(
+            self.prng.gen_bool(0.3),
+            Some(TracingPolicies::from_policy(TracingPolicy {
+                enc_keys: AssetTracerKeyPair::generate(&mut self.prng).enc_key,
+                asset_tracing: true,
+                identity_tracing: None,
+            })),
+            None
+        );
+        let rules = AssetRules {
+            max_units: cap,
+            tracing_policies: tracing.clone().unwrap_or_default(),
+            ..AssetRules::default()
+        };
+
+        let kp = &self.accounts[issuer];
+        let body = DefineAssetBody::new(
+            &code,
+            &IssuerPublicKey { key: kp.get_pk() },
+            rules,
+            Some(Memo(format!("sim-{}", self.defines))),
+            None,
+        )
+        .c(d!())?;
+        let op = DefineAsset::new(body, &IssuerKeyPair { keypair: kp }).c(d!())?;
+
+        self.defines += 1;
+        let stxn = SimTxn {
+            define: Some(SimAsset {
+                code,
+                issuer,
+                next_seq_num: 0,
+                issued: 0,
+                cap,
+                tracing,
+            }),
+            ..Default::default()
+        };
+        Ok((self.new_txn(Operation::DefineAsset(op)), stxn))
+    }
+
+    fn gen_issue(&mut self, idx: usize, amount: u64) -> Result<(Transaction, SimTxn)> {
+        let asset = &mut self.assets[idx];
+        let seq_num = asset.next_seq_num;
+        asset.next_seq_num += 1;
+        let (code, issuer) = (asset.code, asset.issuer);
+        let kp = &self.accounts[issuer];
+
+        let (template, identity) = match asset.tracing.clone() {
+            Some(policies) => (
+                AssetRecordTemplate::with_asset_tracing(
+                    amount,
+                    code.val,
+                    NON_CONFIDENTIAL,
+                    kp.get_pk(),
+                    policies,
+                ),
+                vec![vec![]],
+            ),
+            None => (
+                AssetRecordTemplate::with_no_asset_tracing(
+                    amount,
+                    code.val,
+                    NON_CONFIDENTIAL,
+                    kp.get_pk(),
+                ),
+                vec![],
+            ),
+        };
+        let (ba, _, _) = build_blind_asset_record(
+            &mut self.prng,
+            &self.params.pc_gens,
+            &template,
+            identity,
+        );
+        let output = TxOutput {
+            id: None,
+            record: ba,
+            lien: None,
+            memo: None,
+        };
+        let body = IssueAssetBody::new(&code, seq_num, &[(output, None)]).c(d!())?;
+        let op = IssueAsset::new(body, &IssuerKeyPair { keypair: kp }).c(d!())?;
+
+        let stxn = SimTxn {
+            issue: Some((idx, amount)),
+            outputs: vec![SimUtxo {
+                owner: issuer,
+                code,
+                amount,
+            }],
+            ..Default::default()
+        };
+        Ok((self.new_txn(Operation::IssueAsset(op)), stxn))
+    }
+
+    // Spend up to 3 outputs of the same owner and asset.
+    fn gen_transfer(&mut self) -> Result<(Transaction, SimTxn)> {
+        let first = self.prng.gen_range(0, self.utxos.len());
+        let (owner, code) = self
+            .utxos
+            .values()
+            .nth(first)
+            .map(|u| (u.owner, u.code))
+            .c(d!())?;
+        let sids = self
+            .utxos
+            .iter()
+            .filter(|(_, u)| u.owner == owner && u.code == code)
+            .map(|(sid, _)| *sid)
+            .collect::<Vec<_>>();
+        let n_inputs = self.prng.gen_range(1, 4).min(sids.len());
+
+        let mut spent = vec![];
+        let mut inputs = vec![];
+        for sid in sids[..n_inputs].iter() {
+            spent.push((*sid, self.utxos.remove(sid).c(d!())?));
+            inputs.push((*sid, self.ledger.status.get_utxo(*sid).c(d!())?.0.record));
+        }
+        let (tx, outputs) = self.transfer_txn(owner, code, &inputs, true).c(d!())?;
+
+        self.transfers += 1;
+        let stxn = SimTxn {
+            spent,
+            outputs,
+            ..Default::default()
+        };
+        Ok((tx, stxn))
+    }
+
+    // Split the total amount of `inputs` into up to 3 outputs to random accounts,
+    // the tracing policies of the asset are attached if `traced`.
+    fn transfer_txn(
+        &mut self,
+        owner: usize,
+        code: AssetTypeCode,
+        inputs: &[(TxoSID, BlindAssetRecord)],
+        traced: bool,
+    ) -> Result<(Transaction, Vec<SimUtxo>)> {
+        let policies = self
+            .assets
+            .iter()
+            .find(|a| a.code == code)
+            .and_then(|a| a.tracing.clone())
+            .filter(|_| traced);
+
+        let kp = &self.accounts[owner];
+        let mut input_records = vec![];
+        let mut total = 0;
+        for (_, record) in inputs.iter() {
+            let oar = open_blind_asset_record(record, &None, kp).c(d!())?;
+            total += oar.amount;
+            input_records.push(match policies.clone() {
+                Some(p) => AssetRecord::from_open_asset_record_with_asset_tracing_but_no_identity(
+                    &mut self.prng,
+                    oar,
+                    p,
+                )
+                .c(d!())?,
+                None => AssetRecord::from_open_asset_record_no_asset_tracing(oar),
+            });
+        }
+
+        let n_outputs = self.prng.gen_range(1, 4).min(total as usize);
+        let mut remaining = total;
+        let mut outputs = vec![];
+        for i in 0..n_outputs {
+            let left = (n_outputs - i - 1) as u64;
+            let amount = alt!(
+                0 == left,
+                remaining,
+                self.prng.gen_range(1, remaining - left + 1)
+            );
+            remaining -= amount;
+            outputs.push(SimUtxo {
+                owner: self.prng.gen_range(0, self.accounts.len()),
+                code,
+                amount,
+            });
+        }
+
+        let output_records = outputs
+            .iter()
+            .map(|o| {
+                let pk = self.accounts[o.owner].get_pk();
+                let template = match policies.clone() {
+                    Some(p) => AssetRecordTemplate::with_asset_tracing(
+                        o.amount,
+                        o.code.val,
+                        NON_CONFIDENTIAL,
+                        pk,
+                        p,
+                    ),
+                    None => AssetRecordTemplate::with_no_asset_tracing(
+                        o.amount,
+                        o.code.val,
+                        NON_CONFIDENTIAL,
+                        pk,
+                    ),
+                };
+                AssetRecord::from_template_no_identity_tracing(&mut self.prng, &template)
+                    .c(d!())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let note_policies = policies.map(|p| {
+            XfrNotePolicies::new(
+                vec![p.clone(); input_records.len()],
+                vec![None; input_records.len()],
+                vec![p; output_records.len()],
+                vec![None; output_records.len()],
+            )
+        });
+        let body = TransferAssetBody::new(
+            &mut self.prng,
+            inputs
+                .iter()
+                .map(|(sid, _)| TxoRef::Absolute(*sid))
+                .collect(),
+            &input_records,
+            &output_records,
+            note_policies,
+            vec![],
+            TransferType::Standard,
+        )
+        .c(d!())?;
+        let mut op = TransferAsset::new(body).c(d!())?;
+        op.sign(&self.accounts[owner]);
+
+        Ok((self.new_txn(Operation::TransferAsset(op)), outputs))
+    }
+
+    // the no-replay token is drawn from the seed too
+    fn new_txn(&mut self, op: Operation) -> Transaction {
+        let seq_id = self.ledger.get_block_commit_count();
+        let mut tx = Transaction::from_seq_id(seq_id);
+        tx.body.no_replay_token = NoReplayToken::new(&mut self.prng, seq_id);
+        tx.add_operation(op);
+        tx
+    }
+
+    fn check_utxos(&self) -> Result<()> {
+        for (idx, kp) in self.accounts.iter().enumerate() {
+            let mut owned = self.ledger.status.get_owned_utxos(kp.get_pk_ref());
+            owned.sort();
+            let expected = self
+                .utxos
+                .iter()
+                .filter(|(_, u)| u.owner == idx)
+                .map(|(sid, _)| *sid)
+                .collect::<Vec<_>>();
+            if owned != expected {
+                return Err(eg!(format!(
+                    "account {}: owns {:?}, expected {:?}",
+                    idx, owned, expected
+                )));
+            }
+        }
+
+        for (sid, u) in self.utxos.iter() {
+            let record = self.ledger.status.get_utxo(*sid).c(d!())?.0.record;
+            if record.public_key != self.accounts[u.owner].get_pk()
+                || record.amount.get_amount() != Some(u.amount)
+                || record.asset_type.get_asset_type() != Some(u.code.val)
+            {
+                return Err(eg!(format!("{:?}: unexpected record", sid)));
+            }
+        }
+
+        if let Some(sid) = self
+            .spent
+            .keys()
+            .find(|sid| self.ledger.status.get_utxo(**sid).is_some())
+        {
+            return Err(eg!(format!("{:?}: spent but still unspent", sid)));
+        }
+
+        Ok(())
+    }
+
+    // the unspent amounts are read from the ledger, not from the simulated state
+    fn check_supply(&self) -> Result<()> {
+        let mut supply: HashMap<AssetTypeCode, u64> = HashMap::new();
+        for kp in self.accounts.iter() {
+            for sid in self.ledger.status.get_owned_utxos(kp.get_pk_ref()) {
+                let record = self.ledger.status.get_utxo(sid).c(d!())?.0.record;
+                let code = record.asset_type.get_asset_type().c(d!())?;
+                let amount = record.amount.get_amount().c(d!())?;
+                *supply.entry(AssetTypeCode { val: code }).or_default() += amount;
+            }
+        }
+
+        for asset in self.assets.iter() {
+            let unspent = supply.remove(&asset.code).unwrap_or(0);
+            if unspent != asset.issued {
+                return Err(eg!(format!(
+                    "{}: {} unspent, {} issued",
+                    asset.code.to_base64(),
+                    unspent,
+                    asset.issued
+                )));
+            }
+        }
+        if !supply.is_empty() {
+            return Err(eg!("outputs of unknown assets"));
+        }
+
+        Ok(())
+    }
+
+    fn check_commitments(&self) -> Result<()> {
+        for (height, commitment) in self.commitments.iter() {
+            if self
+                .ledger
+                .get_state_commitment_at_block_height(*height)
+                .as_ref()
+                != Some(commitment)
+            {
+                return Err(eg!(format!("commitment of block {} changed", height)));
+            }
+        }
+
+        let latest = self
+            .ledger
+            .status
+            .state_commitment_data
+            .as_ref()
+            .map(|data| data.compute_commitment());
+        if latest.as_ref() != self.commitments.last().map(|(_, c)| c) {
+            return Err(eg!("commitment data mismatch"));
+        }
+
+        Ok(())
+    }
+}
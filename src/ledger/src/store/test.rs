@@ -3,19 +3,73 @@
 
 use {
     super::{helpers::*, *},
-    crate::data_model::{
-        AssetRules, AssetTypeCode, IssueAsset, IssueAssetBody, Memo, Operation,
-        Transaction, TransferAsset, TransferAssetBody, TxOutput, TxnEffect, TxoRef,
-        TxoSID, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+    crate::{
+        converter::{
+            designated_transfer::{
+                DesignatedTransferOperation, ReleaseDesignated, ReleaseDesignatedBody,
+                SendDesignated, SendDesignatedBody,
+            },
+            ibc::{
+                ack_key, commitment_key, ibc_hash, AckIbcPacket, CreateIbcClient,
+                CreateIbcClientBody, IbcHeader, IbcOperation, IbcPacket, IbcState,
+                RecvIbcPacket, SendIbcPacket, SendIbcPacketBody, UpdateIbcClient,
+                ACK_SUCCESS,
+            },
+            kv::{kv_fee, KvAction, KvOperation, KvOperationBody, MAX_KV_VALUE_SIZE},
+            notary::{document_hash, AnchorHash, AnchorHashBody},
+            payment_stream::{
+                CancelStream, CancelStreamBody, ClaimStream, ClaimStreamBody,
+                CreateStream, CreateStreamBody, PaymentStreamOperation,
+            },
+            prism::{
+                PrismAuthorities, PrismDeposit, PrismDepositBody, PrismWithdraw,
+                PrismWithdrawBody, UpdatePrismAuthorities, UpdatePrismAuthoritiesBody,
+            },
+            pull_payment::{
+                AuthorizePull, AuthorizePullBody, Pull, PullBody, PullPaymentOperation,
+                RevokePull, RevokePullBody,
+            },
+            session_key::{
+                AuthorizeSession, AuthorizeSessionBody, RevokeSession,
+                RevokeSessionBody, SessionKeyOperation,
+            },
+            spending_policy::{
+                CoSigner, RemoveSpendingPolicy, RemoveSpendingPolicyBody,
+                SetSpendingPolicy, SetSpendingPolicyBody, SpendingPolicy,
+                SpendingPolicyOperation,
+            },
+            vesting::{Tranche, VestingSchedule},
+        },
+        data_model::{
+            cred_issuer_id, AssetRules, AssetTypeCode, IssueAsset, IssueAssetBody, Memo,
+            NoReplayToken, Operation, PendingTxoRef, Transaction, TransferAsset,
+            TransferAssetBody, TxOutput, TxnCheckCode, TxnEffect, TxoRef, TxoSID,
+            UpdateAssetMirror, UpdateAssetMirrorBody, UpdateRevocation,
+            UpdateRevocationBody, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
+            BLACK_HOLE_PUBKEY_STAKING, TX_FEE_MIN,
+        },
+        staking::{
+            epoch::EPOCH_BLOCK_CNT,
+            ops::{delegation::DelegationOps, fra_distribution::FraDistributionOps},
+            td_pubkey_to_td_addr, Validator, ValidatorData, ValidatorKind, FRA,
+        },
+    },
+    credentials::credential_issuer_key_gen,
+    fp_types::crypto::MultiSigner,
+    globutils::SignatureOf,
+    proptest::{
+        prelude::{any, ProptestConfig},
+        prop_assert_eq, proptest,
     },
     rand_core::SeedableRng,
+    std::{fs, io::Write},
     zei::{
         setup::PublicParams,
         xfr::{
             asset_record::{
                 build_blind_asset_record, open_blind_asset_record, AssetRecordType,
             },
-            sig::XfrKeyPair,
+            sig::{XfrKeyPair, XfrPublicKey},
             structs::{AssetRecord, AssetRecordTemplate},
         },
     },
@@ -50,6 +104,10 @@ fn test_compute_and_save_block_hash() {
         txo_count: 0,
         pulse_count: 0,
         staking: None,
+        revocation: None,
+        ibc: None,
+        kv: None,
+        notary: None,
     };
 
     let count_original = ledger_state.status.block_commit_count;
@@ -78,6 +136,102 @@ fn test_compute_and_save_block_hash() {
     assert_eq!(ledger_state.status.block_commit_count, count_original + 1);
 }
 
+#[test]
+fn test_revocation_registry_proof() {
+    let mut ledger_state = LedgerState::tmp_ledger();
+    let owner = XfrKeyPair::generate(&mut ledger_state.get_prng()).get_pk();
+
+    let registry = RevocationRegistry {
+        owner,
+        id_attr: "id".to_owned(),
+        revoked: BTreeSet::new(),
+        expiries: BTreeMap::new(),
+    };
+    ledger_state
+        .status
+        .revocation_registries
+        .insert("issuer".to_owned(), registry);
+
+    let b = ledger_state.start_block().unwrap();
+    ledger_state.finish_block(b).unwrap();
+    let (commitment, _) = ledger_state.get_state_commitment();
+
+    let proof = ledger_state.get_revocation_registry("issuer").unwrap();
+    assert!(proof.registry.is_some());
+    assert!(proof.is_valid(commitment.clone()));
+
+    // absence of a list
+    let proof_absent = ledger_state.get_revocation_registry("nobody").unwrap();
+    assert!(proof_absent.registry.is_none());
+    assert!(proof_absent.is_valid(commitment.clone()));
+
+    // tampered list
+    let mut tampered = proof.clone();
+    tampered
+        .registry
+        .as_mut()
+        .unwrap()
+        .revoked
+        .insert(b"0001".to_vec());
+    assert!(!tampered.is_valid(commitment.clone()));
+
+    // hidden list
+    let mut tampered = proof;
+    tampered.registry = None;
+    assert!(!tampered.is_valid(commitment));
+}
+
+#[test]
+fn test_credential_renewal() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let owner_kp = XfrKeyPair::generate(&mut prng);
+    let other_kp = XfrKeyPair::generate(&mut prng);
+    let (issuer_pk, _) = credential_issuer_key_gen(&mut prng, &[("id".to_owned(), 4)]);
+    let id = cred_issuer_id(&issuer_pk);
+
+    let update = |ledger: &LedgerState, kp: &XfrKeyPair, expiries: &[(&str, u64)]| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = UpdateRevocationBody {
+            issuer: issuer_pk.clone(),
+            id_attr: "id".to_owned(),
+            revoke: vec![],
+            restore: vec![],
+            expiries: expiries
+                .iter()
+                .map(|(c, h)| (c.as_bytes().to_vec(), *h))
+                .collect(),
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::UpdateRevocation(UpdateRevocation::new(body, kp)));
+        tx
+    };
+    let attrs = vec![("id".to_owned(), b"0001".to_vec())];
+
+    // issued with an expiry
+    let tx = update(&ledger, &owner_kp, &[("0001", 10)]);
+    pnk!(try_apply(&mut ledger, tx));
+    let registry = &ledger.status.revocation_registries[&id];
+    assert_eq!(Some(&10), registry.expiries.get(&b"0001".to_vec()));
+    assert!(registry.check(&attrs, 10).is_err());
+
+    // at most one new expiry for a credential
+    let tx = update(&ledger, &owner_kp, &[("0001", 20), ("0001", 30)]);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // only renewed by the owner of the list
+    let tx = update(&ledger, &other_kp, &[("0001", 20)]);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    let tx = update(&ledger, &owner_kp, &[("0001", 20)]);
+    pnk!(try_apply(&mut ledger, tx));
+    let registry = &ledger.status.revocation_registries[&id];
+    assert_eq!(owner_kp.get_pk(), registry.owner);
+    assert!(registry.revoked.is_empty());
+    assert!(registry.check(&attrs, 10).is_ok());
+    assert!(registry.check(&attrs, 20).is_err());
+}
+
 #[test]
 fn test_asset_creation_valid() {
     let mut prng = ChaChaRng::from_entropy();
@@ -190,6 +344,7 @@ fn test_asset_transfer() {
                     id: None,
                     record: ba,
                     lien: None,
+                    memo: None,
                 },
                 None,
             ),
@@ -198,6 +353,7 @@ fn test_asset_transfer() {
                     id: None,
                     record: second_ba,
                     lien: None,
+                    memo: None,
                 },
                 None,
             ),
@@ -306,6 +462,35 @@ fn test_asset_transfer() {
     assert!(effect.is_err());
 }
 
+#[test]
+fn test_check_stateless_bad_signature() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let owner_kp = XfrKeyPair::generate(&mut prng);
+    let receiver = XfrKeyPair::generate(&mut prng).get_pk();
+
+    let (_, fra_sids) =
+        apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&owner_kp));
+    let op = gen_send_operation(&mut ledger, fra_sids[0], &owner_kp, &[(1, receiver)]);
+    let seq_id = ledger.get_block_commit_count();
+    assert!(
+        TxnEffect::check_stateless(Transaction::from_operation(op.clone(), seq_id))
+            .is_ok()
+    );
+
+    // the same transfer without its signatures
+    let mut transfer = match op {
+        Operation::TransferAsset(x) => x,
+        _ => unreachable!(),
+    };
+    transfer.body_signatures = Vec::new();
+    let tx = Transaction::from_operation(Operation::TransferAsset(transfer), seq_id);
+
+    let err = TxnEffect::check_stateless(tx).unwrap_err();
+    assert_eq!(TxnCheckCode::BadSignature, err.code);
+    assert_eq!(Some(0), err.op_index);
+}
+
 // Sign with the wrong key.
 #[test]
 fn test_asset_creation_invalid_signature() {
@@ -378,6 +563,7 @@ fn asset_issued() {
                 id: None,
                 record: ba,
                 lien: None,
+                memo: None,
             },
             None,
         )],
@@ -787,3 +973,2357 @@ fn test_check_fee_with_ledger() {
     let mut block = ledger.start_block().unwrap();
     assert!(ledger.apply_transaction(&mut block, effect).is_err());
 }
+
+// Apply a transaction in its own block, the ledger is unchanged if it is rejected.
+fn try_apply(ledger: &mut LedgerState, tx: Transaction) -> Result<()> {
+    let effect = TxnEffect::compute_effect(tx).c(d!())?;
+    let mut block = ledger.start_block().c(d!())?;
+    match ledger.apply_transaction(&mut block, effect) {
+        Ok(_) => ledger.finish_block(block).map(|_| ()).c(d!()),
+        Err(e) => {
+            // the rejected transaction has not been added to the block
+            ledger.block_ctx = Some(block);
+            Err(e)
+        }
+    }
+}
+
+// Lock `amount` of the FRA in `txo_sid`, the rest goes back to the owner.
+fn gen_lock_operation(
+    l: &mut LedgerState,
+    txo_sid: TxoSID,
+    fra_owner_kp: &XfrKeyPair,
+    amount: u64,
+) -> Operation {
+    gen_send_operation(
+        l,
+        txo_sid,
+        fra_owner_kp,
+        &[(amount, *BLACK_HOLE_PUBKEY_STAKING)],
+    )
+}
+
+// Send the asset in `txo_sid` to the receivers, the rest goes back to the owner.
+fn gen_send_operation(
+    l: &mut LedgerState,
+    txo_sid: TxoSID,
+    fra_owner_kp: &XfrKeyPair,
+    receivers: &[(u64, XfrPublicKey)],
+) -> Operation {
+    let input_bar = l.get_utxo_light(txo_sid).unwrap().utxo.0.record;
+    let input_oar = open_blind_asset_record(&input_bar, &None, &fra_owner_kp).unwrap();
+
+    let sent = receivers.iter().map(|(am, _)| am).sum::<u64>();
+    let outputs = [(input_oar.amount - sent, fra_owner_kp.get_pk())]
+        .iter()
+        .chain(receivers.iter())
+        .map(|(am, pk)| {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                *am,
+                input_oar.asset_type,
+                AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+                *pk,
+            );
+            AssetRecord::from_template_no_identity_tracing(&mut l.get_prng(), &template)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let input_ar = AssetRecord::from_open_asset_record_no_asset_tracing(input_oar);
+    let mut transfer = TransferAsset::new(
+        TransferAssetBody::new(
+            &mut l.get_prng(),
+            vec![TxoRef::Absolute(txo_sid)],
+            &[input_ar],
+            &outputs,
+            None,
+            vec![],
+            TransferType::Standard,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    transfer.sign(&fra_owner_kp);
+
+    Operation::TransferAsset(transfer)
+}
+
+#[test]
+fn test_prism_bridge() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let fra_owner_kp = XfrKeyPair::generate(&mut prng);
+    let receiver_kp = XfrKeyPair::generate(&mut prng);
+    let authorities = (0..3)
+        .map(|_| XfrKeyPair::generate(&mut prng))
+        .collect::<Vec<_>>();
+    let fra_code = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+
+    let (_, fra_sids) =
+        apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&fra_owner_kp));
+
+    let update = |seq_id: u64, version: u64, signers: &[&XfrKeyPair]| {
+        let body = UpdatePrismAuthoritiesBody {
+            authorities: PrismAuthorities {
+                keys: authorities.iter().map(|kp| kp.get_pk()).collect(),
+                threshold: 2,
+            },
+            version,
+        };
+        let signatures = signers
+            .iter()
+            .map(|kp| (kp.get_pk(), SignatureOf::new(kp, &body)))
+            .collect();
+        Transaction::from_operation(
+            Operation::UpdatePrismAuthorities(UpdatePrismAuthorities {
+                body,
+                signatures,
+            }),
+            seq_id,
+        )
+    };
+
+    let withdraw = |seq_id: u64, id: u8, amount: u64, signers: &[&XfrKeyPair]| {
+        let body = PrismWithdrawBody {
+            chain_id: 1,
+            burn_tx: [id; 32],
+            asset: fra_code,
+            receiver: receiver_kp.get_pk(),
+            amount,
+        };
+        let signatures = signers
+            .iter()
+            .map(|kp| (kp.get_pk(), SignatureOf::new(kp, &body)))
+            .collect();
+        Transaction::from_operation(
+            Operation::PrismWithdraw(PrismWithdraw { body, signatures }),
+            seq_id,
+        )
+    };
+
+    // the first set of authorities must be signed by the issuer of FRA
+    let seq_id = ledger.get_block_commit_count();
+    assert!(try_apply(&mut ledger, update(seq_id, 1, &[&authorities[0]])).is_err());
+    let seq_id = ledger.get_block_commit_count();
+    pnk!(try_apply(&mut ledger, update(seq_id, 1, &[&fra_owner_kp])));
+    assert_eq!(1, ledger.get_status().get_prism_state().version);
+
+    // the deposited amount must be exactly the locked one
+    let deposit = |ledger: &mut LedgerState, locked: u64, deposited: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let op = gen_lock_operation(ledger, fra_sids[0], &fra_owner_kp, locked);
+        let body = PrismDepositBody {
+            asset: fra_code,
+            receiver: MultiSigner::Ethereum(Default::default()),
+            amount: deposited,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(op);
+        tx.add_operation(Operation::PrismDeposit(PrismDeposit::new(
+            body,
+            &fra_owner_kp,
+        )));
+        tx
+    };
+    let tx = deposit(&mut ledger, 100, 200);
+    assert!(TxnEffect::compute_effect(tx).is_err());
+    let tx = deposit(&mut ledger, 100, 100);
+    pnk!(try_apply(&mut ledger, tx));
+    assert_eq!(
+        Some(&100),
+        ledger.get_status().get_prism_state().locked.get(&fra_code)
+    );
+
+    // withdrawals must be signed by enough authorities
+    let seq_id = ledger.get_block_commit_count();
+    assert!(
+        try_apply(&mut ledger, withdraw(seq_id, 1, 60, &[&authorities[0]])).is_err()
+    );
+    let signers = [&authorities[0], &authorities[2]];
+    let seq_id = ledger.get_block_commit_count();
+    pnk!(try_apply(&mut ledger, withdraw(seq_id, 1, 60, &signers)));
+    let sids = ledger
+        .get_status()
+        .get_owned_utxos(receiver_kp.get_pk_ref());
+    assert_eq!(1, sids.len());
+    let record = ledger.get_utxo_light(sids[0]).unwrap().utxo.0.record;
+    assert_eq!(
+        60,
+        open_blind_asset_record(&record, &None, &receiver_kp)
+            .unwrap()
+            .amount
+    );
+
+    // processed at most once, and never more than the locked amount
+    let seq_id = ledger.get_block_commit_count();
+    assert!(try_apply(&mut ledger, withdraw(seq_id, 1, 60, &signers)).is_err());
+    let seq_id = ledger.get_block_commit_count();
+    assert!(try_apply(&mut ledger, withdraw(seq_id, 2, 50, &signers)).is_err());
+    let seq_id = ledger.get_block_commit_count();
+    pnk!(try_apply(&mut ledger, withdraw(seq_id, 2, 40, &signers)));
+    assert_eq!(
+        Some(&0),
+        ledger.get_status().get_prism_state().locked.get(&fra_code)
+    );
+
+    // later updates must be signed by the current authorities
+    let seq_id = ledger.get_block_commit_count();
+    assert!(try_apply(&mut ledger, update(seq_id, 2, &[&fra_owner_kp])).is_err());
+    let seq_id = ledger.get_block_commit_count();
+    pnk!(try_apply(&mut ledger, update(seq_id, 2, &signers)));
+    assert_eq!(2, ledger.get_status().get_prism_state().version);
+}
+
+#[test]
+fn test_asset_mirrors() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let issuer_kp = XfrKeyPair::generate(&mut prng);
+    let other_kp = XfrKeyPair::generate(&mut prng);
+    let code = AssetTypeCode::gen_random();
+    let other_code = AssetTypeCode::gen_random();
+
+    for (code, kp) in [(&code, &issuer_kp), (&other_code, &other_kp)] {
+        let tx = pnk!(create_definition_transaction(
+            code,
+            kp,
+            AssetRules::default(),
+            None,
+            ledger.get_block_commit_count(),
+        ));
+        apply_transaction(&mut ledger, tx);
+    }
+
+    let update = |ledger: &LedgerState,
+                  code: AssetTypeCode,
+                  contract: Option<[u8; 20]>,
+                  kp: &XfrKeyPair| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = UpdateAssetMirrorBody {
+            asset_type: code,
+            chain_id: 1,
+            contract,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::UpdateAssetMirror(UpdateAssetMirror::new(
+            body, kp,
+        )));
+        tx
+    };
+
+    // only the issuer can register a mirror
+    let tx = update(&ledger, code, Some([1; 20]), &other_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = update(&ledger, code, Some([1; 20]), &issuer_kp);
+    pnk!(try_apply(&mut ledger, tx));
+    assert_eq!(
+        Some(code),
+        ledger.get_status().get_mirrored_asset(1, &[1; 20])
+    );
+
+    // a contract mirrors at most one asset
+    let tx = update(&ledger, other_code, Some([1; 20]), &other_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // replaced, then removed
+    let tx = update(&ledger, code, Some([2; 20]), &issuer_kp);
+    pnk!(try_apply(&mut ledger, tx));
+    assert_eq!(1, ledger.get_status().get_asset_mirrors(&code).len());
+    assert!(ledger
+        .get_status()
+        .get_mirrored_asset(1, &[1; 20])
+        .is_none());
+    let tx = update(&ledger, code, None, &issuer_kp);
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(ledger.get_status().get_asset_mirrors(&code).is_empty());
+    let tx = update(&ledger, code, None, &issuer_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+}
+
+#[test]
+fn test_simulation() {
+    let cfg = sim::SimConfig {
+        seed: 7,
+        blocks: 10,
+        ..Default::default()
+    };
+
+    let report = pnk!(sim::Simulator::new(cfg.clone()).run());
+    assert!(0 < report.defines);
+    assert!(0 < report.issues);
+    assert!(0 < report.transfers);
+    assert!(0 < report.utxos);
+
+    // same seed, same ledger
+    let replay = pnk!(sim::Simulator::new(cfg).run());
+    assert_eq!(report.state_commitment, replay.state_commitment);
+}
+
+#[test]
+fn test_invariants() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let params = PublicParams::default();
+    let kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let code = AssetTypeCode::gen_random();
+    let mut invariants = invariants::Invariants::new();
+
+    let rules = AssetRules {
+        max_units: Some(100),
+        ..AssetRules::default()
+    };
+    let tx = pnk!(create_definition_transaction(&code, &kp, rules, None, 0));
+    let effect = pnk!(TxnEffect::compute_effect(tx.clone()));
+    assert!(invariants.check_effect(&ledger, &effect).is_ok());
+    apply_transaction(&mut ledger, tx);
+
+    // issuances are summed up across transactions
+    let issue = |ledger: &mut LedgerState, seq_num: u64, amount: u64| {
+        let tx = create_issuance_txn(
+            ledger,
+            &params,
+            &code,
+            amount,
+            seq_num,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            &kp,
+        );
+        pnk!(TxnEffect::compute_effect(tx))
+    };
+    let effect = issue(&mut ledger, 0, 60);
+    assert!(invariants.check_effect(&ledger, &effect).is_ok());
+    let effect = issue(&mut ledger, 1, 60);
+    assert!(invariants.check_effect(&ledger, &effect).is_err());
+
+    // spend a TXO that does not exist
+    let (tx, _) = create_issue_and_transfer_txn(
+        &mut ledger,
+        &params,
+        &code,
+        10,
+        &kp,
+        kp.get_pk_ref(),
+        2,
+    );
+    let mut effect = pnk!(TxnEffect::compute_effect(tx));
+    if let Operation::TransferAsset(x) = &mut effect.txn.body.operations[1] {
+        x.body.inputs[0] = TxoRef::Absolute(TxoSID(999));
+    }
+    assert!(invariants.check_effect(&ledger, &effect).is_err());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    // invalid transactions must be rejected by the ledger,
+    // and every accepted one must keep the invariants
+    #[test]
+    fn prop_simulation_invariants(
+        seed in any::<u64>(),
+        accounts in 1usize..6,
+        txns_per_block in 1usize..10,
+    ) {
+        let cfg = sim::SimConfig {
+            seed,
+            accounts,
+            blocks: 6,
+            txns_per_block,
+            invalid_txns: true,
+        };
+        let res = sim::Simulator::new(cfg)
+            .run()
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        prop_assert_eq!(res, Ok(()));
+    }
+}
+
+#[test]
+fn test_ibc_packets() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let kp = XfrKeyPair::generate(&mut prng);
+    let validators = (0..3)
+        .map(|_| XfrKeyPair::generate(&mut prng))
+        .collect::<Vec<_>>();
+    let client_id = "client-0".to_owned();
+    let cp_client_id = "client-9".to_owned();
+
+    // the IBC store of the counterparty
+    let mut cp = IbcState::default();
+    let packet = IbcPacket {
+        sequence: 1,
+        data: b"hello".to_vec(),
+        timeout_height: 0,
+    };
+    cp.store
+        .insert(commitment_key(&cp_client_id, 1), packet.commitment());
+
+    let ibc_tx = |ledger: &LedgerState, op: IbcOperation| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        tx.add_operation(Operation::Ibc(op));
+        tx
+    };
+    let header = |height: u64, root, signers: &[XfrKeyPair]| {
+        let header = IbcHeader {
+            client_id: client_id.clone(),
+            height,
+            root,
+        };
+        let signatures = signers
+            .iter()
+            .map(|kp| (kp.get_pk(), SignatureOf::new(kp, &header)))
+            .collect();
+        IbcOperation::UpdateClient(UpdateIbcClient { header, signatures })
+    };
+
+    // client IDs are unique
+    let create = |ledger: &LedgerState| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = CreateIbcClientBody {
+            client_id: client_id.clone(),
+            chain_id: "cosmoshub-4".to_owned(),
+            counterparty_client_id: cp_client_id.clone(),
+            validators: validators.iter().map(|v| (v.get_pk(), 1)).collect(),
+            height: 10,
+            root: [0; 32],
+            no_replay_token: tx.body.no_replay_token,
+        };
+        let op = IbcOperation::CreateClient(CreateIbcClient::new(body, &kp));
+        tx.add_operation(Operation::Ibc(op));
+        tx
+    };
+    let tx = create(&ledger);
+    pnk!(try_apply(&mut ledger, tx));
+    let tx = create(&ledger);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // more than 2/3 of the voting power must sign
+    let root = cp.root().unwrap();
+    let tx = ibc_tx(&ledger, header(11, root, &validators[..2]));
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = ibc_tx(&ledger, header(11, root, &validators));
+    pnk!(try_apply(&mut ledger, tx));
+    let tx = ibc_tx(&ledger, header(11, root, &validators));
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // received once, with a valid proof
+    let recv = |packet: &IbcPacket| {
+        IbcOperation::RecvPacket(RecvIbcPacket {
+            client_id: client_id.clone(),
+            packet: packet.clone(),
+            proof_height: 11,
+            proof: cp.prove(&commitment_key(&cp_client_id, 1)).unwrap(),
+        })
+    };
+    let mut forged = packet.clone();
+    forged.data = b"bye".to_vec();
+    let tx = ibc_tx(&ledger, recv(&forged));
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let mut timed_out = packet.clone();
+    timed_out.timeout_height = 1;
+    let tx = ibc_tx(&ledger, recv(&timed_out));
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = ibc_tx(&ledger, recv(&packet));
+    pnk!(try_apply(&mut ledger, tx));
+    let tx = ibc_tx(&ledger, recv(&packet));
+    assert!(try_apply(&mut ledger, tx).is_err());
+    assert!(ledger
+        .get_ibc_value(&ack_key(&client_id, 1))
+        .unwrap()
+        .is_valid(ledger.get_state_commitment().0));
+
+    // sent packets are committed in the state commitment
+    let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+    let body = SendIbcPacketBody {
+        client_id: client_id.clone(),
+        data: b"world".to_vec(),
+        timeout_height: 0,
+        no_replay_token: tx.body.no_replay_token,
+    };
+    let op = IbcOperation::SendPacket(SendIbcPacket::new(body, &kp));
+    tx.add_operation(Operation::Ibc(op));
+    pnk!(try_apply(&mut ledger, tx));
+    let sent = IbcPacket {
+        sequence: 1,
+        data: b"world".to_vec(),
+        timeout_height: 0,
+    };
+    let value = ledger
+        .get_ibc_value(&commitment_key(&client_id, 1))
+        .unwrap();
+    assert_eq!(sent.commitment(), value.value);
+    assert!(value.is_valid(ledger.get_state_commitment().0));
+
+    // acknowledged by the counterparty, the commitment is deleted
+    cp.store
+        .insert(ack_key(&cp_client_id, 1), ibc_hash(&[ACK_SUCCESS]));
+    let tx = ibc_tx(&ledger, header(12, cp.root().unwrap(), &validators));
+    pnk!(try_apply(&mut ledger, tx));
+    let ack = IbcOperation::AckPacket(AckIbcPacket {
+        client_id: client_id.clone(),
+        packet: sent,
+        ack: ACK_SUCCESS.to_vec(),
+        proof_height: 12,
+        proof: cp.prove(&ack_key(&cp_client_id, 1)).unwrap(),
+    });
+    let tx = ibc_tx(&ledger, ack.clone());
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(ledger
+        .get_ibc_value(&commitment_key(&client_id, 1))
+        .is_none());
+    let tx = ibc_tx(&ledger, ack);
+    assert!(try_apply(&mut ledger, tx).is_err());
+}
+
+#[test]
+fn test_pull_payments() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let payer_kp = XfrKeyPair::generate(&mut prng);
+    let payee_kp = XfrKeyPair::generate(&mut prng);
+    let fra_code = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+
+    let (_, fra_sids) =
+        apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&payer_kp));
+
+    // the authorized amount must be exactly the locked one
+    let authorize = |ledger: &mut LedgerState, locked: u64, authorized: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let op = gen_lock_operation(ledger, fra_sids[0], &payer_kp, locked);
+        let body = AuthorizePullBody {
+            payee: payee_kp.get_pk(),
+            asset: fra_code,
+            limit: 30,
+            period: 10,
+            amount: authorized,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        let id = body.id();
+        tx.add_operation(op);
+        tx.add_operation(Operation::PullPayment(PullPaymentOperation::Authorize(
+            AuthorizePull::new(body, &payer_kp),
+        )));
+        (id, tx)
+    };
+    let (_, tx) = authorize(&mut ledger, 100, 200);
+    assert!(TxnEffect::compute_effect(tx).is_err());
+    let (id, tx) = authorize(&mut ledger, 100, 100);
+    pnk!(try_apply(&mut ledger, tx));
+
+    let pull = |ledger: &LedgerState, kp: &XfrKeyPair, amount: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = PullBody {
+            id: id.clone(),
+            asset: fra_code,
+            amount,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::PullPayment(PullPaymentOperation::Pull(
+            Pull::new(body, kp),
+        )));
+        tx
+    };
+    let revoke = |ledger: &LedgerState, kp: &XfrKeyPair, refund: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = RevokePullBody {
+            id: id.clone(),
+            asset: fra_code,
+            refund,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::PullPayment(PullPaymentOperation::Revoke(
+            RevokePull::new(body, kp),
+        )));
+        tx
+    };
+
+    // only by the payee, within the limit of the period
+    let tx = pull(&ledger, &payer_kp, 30);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = pull(&ledger, &payee_kp, 40);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = pull(&ledger, &payee_kp, 30);
+    pnk!(try_apply(&mut ledger, tx));
+    let tx = pull(&ledger, &payee_kp, 1);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let sids = ledger.get_status().get_owned_utxos(payee_kp.get_pk_ref());
+    assert_eq!(1, sids.len());
+    let record = ledger.get_utxo_light(sids[0]).unwrap().utxo.0.record;
+    assert_eq!(
+        30,
+        open_blind_asset_record(&record, &None, &payee_kp)
+            .unwrap()
+            .amount
+    );
+
+    // the limit is reset in the next period
+    ledger.set_tendermint_height(20);
+    let tx = pull(&ledger, &payee_kp, 30);
+    pnk!(try_apply(&mut ledger, tx));
+    let auth = pnk!(ledger.get_status().get_pull_payment_state().get(&id)).clone();
+    assert_eq!(40, auth.remaining);
+
+    // only by the payer, with the remaining amount refunded
+    let tx = revoke(&ledger, &payee_kp, 40);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = revoke(&ledger, &payer_kp, 30);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = revoke(&ledger, &payer_kp, 40);
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(ledger
+        .get_status()
+        .get_pull_payment_state()
+        .get(&id)
+        .is_err());
+    let tx = pull(&ledger, &payee_kp, 10);
+    assert!(try_apply(&mut ledger, tx).is_err());
+}
+
+#[test]
+fn test_payment_streams() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let sender_kp = XfrKeyPair::generate(&mut prng);
+    let recipient_kp = XfrKeyPair::generate(&mut prng);
+    let fra_code = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+
+    let (_, fra_sids) =
+        apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&sender_kp));
+
+    // the streamed amount must be exactly the locked one
+    let create = |ledger: &mut LedgerState, locked: u64, streamed: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let op = gen_lock_operation(ledger, fra_sids[0], &sender_kp, locked);
+        let body = CreateStreamBody {
+            recipient: recipient_kp.get_pk(),
+            asset: fra_code,
+            amount: streamed,
+            start_height: 10,
+            end_height: 20,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        let id = body.id();
+        tx.add_operation(op);
+        tx.add_operation(Operation::PaymentStream(PaymentStreamOperation::Create(
+            CreateStream::new(body, &sender_kp),
+        )));
+        (id, tx)
+    };
+    let (_, tx) = create(&mut ledger, 100, 200);
+    assert!(TxnEffect::compute_effect(tx).is_err());
+    let (id, tx) = create(&mut ledger, 100, 100);
+    pnk!(try_apply(&mut ledger, tx));
+
+    let claim = |ledger: &LedgerState, kp: &XfrKeyPair, amount: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = ClaimStreamBody {
+            id: id.clone(),
+            asset: fra_code,
+            amount,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::PaymentStream(PaymentStreamOperation::Claim(
+            ClaimStream::new(body, kp),
+        )));
+        tx
+    };
+    let cancel = |ledger: &LedgerState, kp: &XfrKeyPair, refund: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = CancelStreamBody {
+            id: id.clone(),
+            asset: fra_code,
+            refund,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::PaymentStream(PaymentStreamOperation::Cancel(
+            CancelStream::new(body, kp),
+        )));
+        tx
+    };
+
+    // nothing is vested before the start height
+    let tx = claim(&ledger, &recipient_kp, 1);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // only by the recipient, within the vested amount
+    ledger.set_tendermint_height(14);
+    let tx = claim(&ledger, &sender_kp, 50);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = claim(&ledger, &recipient_kp, 60);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = claim(&ledger, &recipient_kp, 50);
+    pnk!(try_apply(&mut ledger, tx));
+    let sids = ledger
+        .get_status()
+        .get_owned_utxos(recipient_kp.get_pk_ref());
+    assert_eq!(1, sids.len());
+    let record = ledger.get_utxo_light(sids[0]).unwrap().utxo.0.record;
+    assert_eq!(
+        50,
+        open_blind_asset_record(&record, &None, &recipient_kp)
+            .unwrap()
+            .amount
+    );
+
+    // only by the sender, refunding at most the unvested amount
+    ledger.set_tendermint_height(16);
+    let tx = cancel(&ledger, &recipient_kp, 20);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = cancel(&ledger, &sender_kp, 40);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = cancel(&ledger, &sender_kp, 20);
+    pnk!(try_apply(&mut ledger, tx));
+
+    // the rest is claimable at once, the stream is removed when fully claimed
+    let stream = pnk!(ledger.get_status().get_payment_stream_state().get(&id)).clone();
+    assert_eq!(30, stream.claimable(17));
+    let tx = claim(&ledger, &recipient_kp, 30);
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(ledger
+        .get_status()
+        .get_payment_stream_state()
+        .get(&id)
+        .is_err());
+}
+
+#[test]
+fn test_designated_transfers() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let sender_kp = XfrKeyPair::generate(&mut prng);
+    let recipient_kp = XfrKeyPair::generate(&mut prng);
+    let fra_code = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+
+    apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&sender_kp));
+
+    // the designated amount must be exactly the locked one
+    let send = |ledger: &mut LedgerState, locked: u64, designated: u64| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let sid = ledger
+            .get_status()
+            .get_owned_utxos(sender_kp.get_pk_ref())
+            .into_iter()
+            .max()
+            .unwrap();
+        let op = gen_lock_operation(ledger, sid, &sender_kp, locked);
+        let body = SendDesignatedBody {
+            recipient: recipient_kp.get_pk(),
+            asset: fra_code,
+            amount: designated,
+            claim_period: 5,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        let id = body.id();
+        tx.add_operation(op);
+        tx.add_operation(Operation::DesignatedTransfer(
+            DesignatedTransferOperation::Send(SendDesignated::new(body, &sender_kp)),
+        ));
+        (id, tx)
+    };
+    let release =
+        |ledger: &LedgerState, kp: &XfrKeyPair, id: &str, amount: u64, claim: bool| {
+            let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+            let body = ReleaseDesignatedBody {
+                id: id.to_owned(),
+                asset: fra_code,
+                amount,
+                no_replay_token: tx.body.no_replay_token,
+            };
+            let op = ReleaseDesignated::new(body, kp);
+            tx.add_operation(Operation::DesignatedTransfer(alt!(
+                claim,
+                DesignatedTransferOperation::Claim(op),
+                DesignatedTransferOperation::Reclaim(op)
+            )));
+            tx
+        };
+
+    ledger.set_tendermint_height(10);
+    let (_, tx) = send(&mut ledger, 100, 200);
+    assert!(TxnEffect::compute_effect(tx).is_err());
+    let (id, tx) = send(&mut ledger, 100, 100);
+    pnk!(try_apply(&mut ledger, tx));
+    let transfer = pnk!(ledger.get_status().get_designated_transfer_state().get(&id));
+    assert_eq!(16, transfer.expires_at);
+
+    // only by the recipient, of all the amount, within the claim period
+    ledger.set_tendermint_height(12);
+    let tx = release(&ledger, &sender_kp, &id, 100, true);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = release(&ledger, &sender_kp, &id, 100, false);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = release(&ledger, &recipient_kp, &id, 50, true);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = release(&ledger, &recipient_kp, &id, 100, true);
+    pnk!(try_apply(&mut ledger, tx));
+    let sids = ledger
+        .get_status()
+        .get_owned_utxos(recipient_kp.get_pk_ref());
+    assert_eq!(1, sids.len());
+    let record = ledger.get_utxo_light(sids[0]).unwrap().utxo.0.record;
+    assert_eq!(
+        100,
+        open_blind_asset_record(&record, &None, &recipient_kp)
+            .unwrap()
+            .amount
+    );
+    assert!(ledger
+        .get_status()
+        .get_designated_transfer_state()
+        .get(&id)
+        .is_err());
+
+    // expired by the sweep at the end of its claim period
+    ledger.set_tendermint_height(20);
+    let (id, tx) = send(&mut ledger, 100, 100);
+    pnk!(try_apply(&mut ledger, tx));
+    ledger.set_tendermint_height(25);
+    let block = pnk!(ledger.start_block());
+    pnk!(ledger.finish_block(block));
+    assert!(pnk!(ledger.get_status().get_designated_transfer_state().get(&id)).expired);
+
+    // then only reclaimable by the sender
+    let tx = release(&ledger, &recipient_kp, &id, 100, true);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = release(&ledger, &recipient_kp, &id, 100, false);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = release(&ledger, &sender_kp, &id, 100, false);
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(ledger
+        .get_status()
+        .get_designated_transfer_state()
+        .get(&id)
+        .is_err());
+}
+
+#[test]
+fn test_spending_policies() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let owner_kp = XfrKeyPair::generate(&mut prng);
+    let cosigner_kp = XfrKeyPair::generate(&mut prng);
+    let friend = XfrKeyPair::generate(&mut prng).get_pk();
+    let stranger = XfrKeyPair::generate(&mut prng).get_pk();
+    let fra_code = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+
+    apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&owner_kp));
+    let owned = |ledger: &LedgerState| {
+        let mut sids = ledger.get_status().get_owned_utxos(owner_kp.get_pk_ref());
+        sids.sort();
+        sids
+    };
+    let richest = |ledger: &LedgerState| {
+        owned(ledger)
+            .into_iter()
+            .max_by_key(|sid| {
+                let record = ledger.get_utxo_light(*sid).unwrap().utxo.0.record;
+                open_blind_asset_record(&record, &None, &owner_kp)
+                    .unwrap()
+                    .amount
+            })
+            .unwrap()
+    };
+
+    // some UTXOs to spend in the same block
+    let sid = richest(&ledger);
+    let op = gen_send_operation(
+        &mut ledger,
+        sid,
+        &owner_kp,
+        &[(1000, owner_kp.get_pk()), (1000, owner_kp.get_pk())],
+    );
+    pnk!(try_apply(
+        &mut ledger,
+        Transaction::from_operation(op, ledger.get_block_commit_count())
+    ));
+
+    let send = |ledger: &mut LedgerState, sid, to, amount, cosigned: bool| {
+        let op = gen_send_operation(ledger, sid, &owner_kp, &[(amount, to)]);
+        let mut tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+        if cosigned {
+            tx.sign(&cosigner_kp);
+        }
+        tx
+    };
+    let change =
+        |ledger: &LedgerState, policy: Option<SpendingPolicy>, cosigned: bool| {
+            let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+            let nrt = tx.body.no_replay_token;
+            let op = if let Some(policy) = policy {
+                SpendingPolicyOperation::Set(SetSpendingPolicy::new(
+                    SetSpendingPolicyBody {
+                        policy,
+                        no_replay_token: nrt,
+                    },
+                    &owner_kp,
+                ))
+            } else {
+                SpendingPolicyOperation::Remove(RemoveSpendingPolicy::new(
+                    RemoveSpendingPolicyBody {
+                        no_replay_token: nrt,
+                    },
+                    &owner_kp,
+                ))
+            };
+            tx.add_operation(Operation::SpendingPolicy(op));
+            if cosigned {
+                tx.sign(&cosigner_kp);
+            }
+            tx
+        };
+
+    let mut policy = SpendingPolicy {
+        limits: [(fra_code, 1000)].into_iter().collect(),
+        period: 10,
+        cosigner: Some(CoSigner {
+            pubkey: cosigner_kp.get_pk(),
+            thresholds: [(fra_code, 500)].into_iter().collect(),
+        }),
+        allowed_destinations: [friend].into_iter().collect(),
+    };
+    ledger.set_tendermint_height(10);
+    let tx = change(&ledger, Some(policy.clone()), false);
+    pnk!(try_apply(&mut ledger, tx));
+
+    // only to the allowed destinations, co-signed above the threshold
+    let sid = richest(&ledger);
+    let tx = send(&mut ledger, sid, stranger, 100, true);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = send(&mut ledger, sid, friend, 600, false);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = send(&mut ledger, sid, friend, 600, true);
+    pnk!(try_apply(&mut ledger, tx));
+
+    // within the limit of the period
+    let sid = richest(&ledger);
+    let tx = send(&mut ledger, sid, friend, 500, false);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = send(&mut ledger, sid, friend, 400, false);
+    pnk!(try_apply(&mut ledger, tx));
+    let sid = richest(&ledger);
+    let tx = send(&mut ledger, sid, friend, 1, false);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // reset in the next period, and charged by the transactions of the same block
+    ledger.set_tendermint_height(21);
+    let sids = owned(&ledger);
+    let txs = sids[..3]
+        .iter()
+        .map(|sid| send(&mut ledger, *sid, friend, 400, false))
+        .collect::<Vec<_>>();
+    let mut block = pnk!(ledger.start_block());
+    let results = txs
+        .into_iter()
+        .map(|tx| {
+            let effect = pnk!(TxnEffect::compute_effect(tx));
+            ledger.apply_transaction(&mut block, effect).is_ok()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(vec![true, true, false], results);
+    pnk!(ledger.finish_block(block));
+    let kp = pnk!(ledger
+        .get_status()
+        .get_spending_policy_state()
+        .get(owner_kp.get_pk_ref()))
+    .clone();
+    assert_eq!(22, kp.period_start);
+    assert_eq!(Some(200), kp.remaining(&fra_code, 23));
+
+    // changed with the co-signer only, keeping the charges of the period
+    policy.cosigner = None;
+    policy.allowed_destinations.clear();
+    let tx = change(&ledger, Some(policy.clone()), false);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = change(&ledger, Some(policy), true);
+    pnk!(try_apply(&mut ledger, tx));
+    let sid = richest(&ledger);
+    let tx = send(&mut ledger, sid, stranger, 300, false);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = send(&mut ledger, sid, stranger, 200, false);
+    pnk!(try_apply(&mut ledger, tx));
+
+    // no rules once removed
+    let tx = change(&ledger, None, false);
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(ledger
+        .get_status()
+        .get_spending_policy_state()
+        .get(owner_kp.get_pk_ref())
+        .is_err());
+    let sid = richest(&ledger);
+    let tx = send(&mut ledger, sid, stranger, 3000, false);
+    pnk!(try_apply(&mut ledger, tx));
+}
+
+#[test]
+fn test_session_keys() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let primary_kp = XfrKeyPair::generate(&mut prng);
+    let session_kp = XfrKeyPair::generate(&mut prng);
+    let other_kp = XfrKeyPair::generate(&mut prng);
+    let receiver = XfrKeyPair::generate(&mut prng).get_pk();
+    let fra_code = AssetTypeCode {
+        val: ASSET_TYPE_FRA,
+    };
+
+    apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&primary_kp));
+    let owned = |ledger: &LedgerState| {
+        let mut sids = ledger.get_status().get_owned_utxos(primary_kp.get_pk_ref());
+        sids.sort();
+        sids
+    };
+
+    // a transfer of the UTXO of the primary key, signed by `signer` only
+    let delegated = |ledger: &mut LedgerState, sid, amount, signer: &XfrKeyPair| {
+        let mut op = gen_send_operation(ledger, sid, &primary_kp, &[(amount, receiver)]);
+        if let Operation::TransferAsset(t) = &mut op {
+            t.body_signatures.clear();
+            t.sign(signer);
+        }
+        let mut tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+        tx.sign(signer);
+        tx
+    };
+    let session_op = |ledger: &LedgerState, authorize: Option<(u64, u64)>| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let nrt = tx.body.no_replay_token;
+        let op = if let Some((allowance, expires_at)) = authorize {
+            SessionKeyOperation::Authorize(AuthorizeSession::new(
+                AuthorizeSessionBody {
+                    session: session_kp.get_pk(),
+                    asset: fra_code,
+                    allowance,
+                    expires_at,
+                    no_replay_token: nrt,
+                },
+                &primary_kp,
+            ))
+        } else {
+            SessionKeyOperation::Revoke(RevokeSession::new(
+                RevokeSessionBody {
+                    session: session_kp.get_pk(),
+                    no_replay_token: nrt,
+                },
+                &primary_kp,
+            ))
+        };
+        tx.add_operation(Operation::SessionKey(op));
+        tx
+    };
+
+    ledger.set_tendermint_height(10);
+    let sid = *owned(&ledger).last().unwrap();
+    let tx = delegated(&mut ledger, sid, 100, &session_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = session_op(&ledger, Some((1000, 11)));
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = session_op(&ledger, Some((1000, 20)));
+    pnk!(try_apply(&mut ledger, tx));
+
+    // only by the session key, within the allowance
+    let sid = *owned(&ledger).last().unwrap();
+    let tx = delegated(&mut ledger, sid, 600, &other_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = delegated(&mut ledger, sid, 600, &session_kp);
+    pnk!(try_apply(&mut ledger, tx));
+    let sids = ledger.get_status().get_owned_utxos(&receiver);
+    assert_eq!(1, sids.len());
+    let sid = *owned(&ledger).last().unwrap();
+    let tx = delegated(&mut ledger, sid, 500, &session_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = delegated(&mut ledger, sid, 400, &session_kp);
+    pnk!(try_apply(&mut ledger, tx));
+    let state = ledger.get_status().get_session_key_state();
+    let key = pnk!(state.get(primary_kp.get_pk_ref(), session_kp.get_pk_ref()));
+    assert_eq!(0, key.remaining());
+
+    // authorized again, charged by the transactions of the same block
+    let tx = session_op(&ledger, Some((500, 20)));
+    pnk!(try_apply(&mut ledger, tx));
+    let sids = owned(&ledger);
+    let txs = sids
+        .iter()
+        .rev()
+        .take(2)
+        .map(|sid| delegated(&mut ledger, *sid, 300, &session_kp))
+        .collect::<Vec<_>>();
+    let mut block = pnk!(ledger.start_block());
+    let results = txs
+        .into_iter()
+        .map(|tx| {
+            let effect = pnk!(TxnEffect::compute_effect(tx));
+            ledger.apply_transaction(&mut block, effect).is_ok()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(vec![true, false], results);
+    pnk!(ledger.finish_block(block));
+    let state = ledger.get_status().get_session_key_state();
+    let key = pnk!(state.get(primary_kp.get_pk_ref(), session_kp.get_pk_ref()));
+    assert_eq!(200, key.remaining());
+
+    // no longer valid once revoked
+    let tx = session_op(&ledger, None);
+    pnk!(try_apply(&mut ledger, tx));
+    let sid = *owned(&ledger).last().unwrap();
+    let tx = delegated(&mut ledger, sid, 100, &session_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // nor after the expiry height, and removed by the sweep
+    let tx = session_op(&ledger, Some((500, 20)));
+    pnk!(try_apply(&mut ledger, tx));
+    ledger.set_tendermint_height(20);
+    let tx = delegated(&mut ledger, sid, 100, &session_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let block = pnk!(ledger.start_block());
+    pnk!(ledger.finish_block(block));
+    assert!(ledger
+        .get_status()
+        .get_session_key_state()
+        .get_sessions(primary_kp.get_pk_ref())
+        .is_none());
+}
+
+#[test]
+fn test_vesting_schedules() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let params = PublicParams::default();
+    let code = AssetTypeCode::gen_random();
+    let issuer_kp = build_keys(&mut ledger.get_prng());
+    let receiver_kp = build_keys(&mut ledger.get_prng());
+    let other = build_keys(&mut ledger.get_prng()).get_pk();
+
+    let seq_id = ledger.get_block_commit_count();
+    let tx = pnk!(create_definition_transaction(
+        &code,
+        &issuer_kp,
+        AssetRules::default(),
+        None,
+        seq_id,
+    ));
+    pnk!(try_apply(&mut ledger, tx));
+
+    // 1000 issued, 600 of them vesting linearly from 10 to 30 after a cliff at 20
+    let issue = |ledger: &mut LedgerState, tranche_amount| {
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            1000,
+            code.val,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            issuer_kp.get_pk(),
+        );
+        let (ba, _, _) = build_blind_asset_record(
+            &mut ledger.get_prng(),
+            &params.pc_gens,
+            &template,
+            vec![],
+        );
+        let output = TxOutput {
+            id: None,
+            record: ba,
+            lien: None,
+            memo: None,
+        };
+        let mut body = pnk!(IssueAssetBody::new(&code, 0, &[(output, None)]));
+        body.vesting = vec![(
+            0,
+            VestingSchedule {
+                tranches: vec![Tranche {
+                    amount: tranche_amount,
+                    start: 10,
+                    cliff: 20,
+                    end: 30,
+                }],
+            },
+        )];
+        let op = pnk!(IssueAsset::new(
+            body,
+            &IssuerKeyPair {
+                keypair: &issuer_kp
+            }
+        ));
+        Transaction::from_operation(
+            Operation::IssueAsset(op),
+            ledger.get_block_commit_count(),
+        )
+    };
+    let scheduled = |ledger: &LedgerState| {
+        ledger
+            .get_status()
+            .get_vesting_state()
+            .schedules
+            .iter()
+            .map(|(sid, s)| (*sid, s.clone()))
+            .collect::<Vec<_>>()
+    };
+    let owned = |ledger: &LedgerState, pk: &XfrPublicKey| {
+        let mut sids = ledger.get_status().get_owned_utxos(pk);
+        sids.sort();
+        sids
+    };
+
+    ledger.set_tendermint_height(10);
+    let tx = issue(&mut ledger, 1200);
+    assert!(TxnEffect::compute_effect(tx).is_err());
+    let tx = issue(&mut ledger, 600);
+    pnk!(try_apply(&mut ledger, tx));
+    let vesting = scheduled(&ledger);
+    assert_eq!(1, vesting.len());
+    let (sid, schedule) = vesting[0].clone();
+    assert_eq!(600, schedule.locked(11));
+    assert_eq!(180, schedule.locked(24));
+    assert_eq!(0, schedule.locked(30));
+
+    // the locked amount must be carried over, by an output of anyone
+    let op = gen_send_operation(&mut ledger, sid, &issuer_kp, &[(500, other)]);
+    let tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let op =
+        gen_send_operation(&mut ledger, sid, &issuer_kp, &[(650, receiver_kp.get_pk())]);
+    let tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+    pnk!(try_apply(&mut ledger, tx));
+    let sid = owned(&ledger, receiver_kp.get_pk_ref())[0];
+    assert_eq!(vec![(sid, schedule)], scheduled(&ledger));
+
+    // burnt outputs do not carry the locked amounts
+    ledger.set_tendermint_height(25);
+    let op =
+        gen_send_operation(&mut ledger, sid, &receiver_kp, &[(560, *BLACK_HOLE_PUBKEY)]);
+    let tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let op = gen_send_operation(&mut ledger, sid, &receiver_kp, &[(500, other)]);
+    let tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+    pnk!(try_apply(&mut ledger, tx));
+    let sid = owned(&ledger, receiver_kp.get_pk_ref())[0];
+    let vesting = scheduled(&ledger);
+    assert_eq!(1, vesting.len());
+    assert_eq!(sid, vesting[0].0);
+    assert_eq!(120, vesting[0].1.locked(26));
+
+    // free once fully vested
+    ledger.set_tendermint_height(30);
+    let op = gen_send_operation(&mut ledger, sid, &receiver_kp, &[(100, other)]);
+    let tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(scheduled(&ledger).is_empty());
+}
+
+#[test]
+fn test_kv_store() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let kp = XfrKeyPair::generate(&mut prng);
+    let other_kp = XfrKeyPair::generate(&mut prng);
+    let key = "app/records/1".to_owned();
+
+    apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&kp));
+
+    // the fee is paid by `kp`, the operation is signed by `signer`
+    let kv_tx =
+        |ledger: &mut LedgerState, action: KvAction, fee, signer: &XfrKeyPair| {
+            let mut sids = ledger.get_status().get_owned_utxos(kp.get_pk_ref());
+            sids.sort();
+            let op = gen_send_operation(
+                ledger,
+                *sids.last().unwrap(),
+                &kp,
+                &[(fee, *BLACK_HOLE_PUBKEY)],
+            );
+            let mut tx =
+                Transaction::from_operation(op, ledger.get_block_commit_count());
+            let body = KvOperationBody {
+                key: key.clone(),
+                action,
+                no_replay_token: tx.body.no_replay_token,
+            };
+            tx.add_operation(Operation::Kv(KvOperation::new(body, signer)));
+            tx.sign(&kp);
+            tx.sign(signer);
+            tx
+        };
+
+    // the fee covers each started KB of the key and the value
+    let put = KvAction::Put(vec![1; 1500]);
+    let fee = TX_FEE_MIN + kv_fee(&key, &put);
+    assert_eq!(TX_FEE_MIN * 3, fee);
+    let tx = kv_tx(&mut ledger, put.clone(), fee - 1, &kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = kv_tx(&mut ledger, put.clone(), fee, &kp);
+    pnk!(try_apply(&mut ledger, tx));
+    let value = pnk!(ledger.get_kv_value(&key));
+    assert_eq!(kp.get_pk(), value.entry.owner);
+    assert!(value.is_valid(ledger.get_state_commitment().0));
+    // only the root is in the snapshots of the status
+    let snapshot = pnk!(serde_json::to_string(ledger.get_status()));
+    assert!(!snapshot.contains(&key));
+    let tx = kv_tx(&mut ledger, put, fee, &kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // updated by the owner only, within the size limit
+    let update = KvAction::Update(b"hello".to_vec());
+    let fee = TX_FEE_MIN + kv_fee(&key, &update);
+    let tx = kv_tx(&mut ledger, update.clone(), fee, &other_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let large = KvAction::Update(vec![0; MAX_KV_VALUE_SIZE + 1]);
+    let tx = kv_tx(&mut ledger, large, TX_FEE_MIN * 10, &kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = kv_tx(&mut ledger, update, fee, &kp);
+    pnk!(try_apply(&mut ledger, tx));
+    let value = pnk!(ledger.get_kv_value(&key));
+    assert_eq!(b"hello".to_vec(), value.entry.value);
+    assert!(value.is_valid(ledger.get_state_commitment().0));
+
+    // deleted by the owner
+    let fee = TX_FEE_MIN + kv_fee(&key, &KvAction::Delete);
+    let tx = kv_tx(&mut ledger, KvAction::Delete, fee, &other_kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = kv_tx(&mut ledger, KvAction::Delete, fee, &kp);
+    pnk!(try_apply(&mut ledger, tx));
+    assert!(ledger.get_kv_value(&key).is_none());
+    assert!(ledger.get_status().get_kv_state().get(&key).is_none());
+    assert!(ledger.get_status().get_kv_state().root().is_none());
+}
+
+#[test]
+fn test_anchor_hashes() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let kp = XfrKeyPair::generate(&mut prng);
+    let other_kp = XfrKeyPair::generate(&mut prng);
+    let hash = document_hash(b"contract v1");
+
+    let anchor = |ledger: &LedgerState, tag: Option<&str>, signer: &XfrKeyPair| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        let body = AnchorHashBody {
+            hash,
+            tag: tag.map(|t| t.to_owned()),
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::AnchorHash(AnchorHash::new(body, signer)));
+        tx.sign(signer);
+        tx
+    };
+
+    // recorded with the timestamp of the block
+    ledger.set_block_time(1_600_000_000);
+    let tx = anchor(&ledger, Some(&"x".repeat(65)), &kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = anchor(&ledger, Some("contract"), &kp);
+    pnk!(try_apply(&mut ledger, tx));
+    let record = pnk!(ledger.get_notarized_hash(&hash, kp.get_pk_ref()));
+    assert_eq!(1_600_000_000, record.record.timestamp);
+    assert_eq!(Some("contract".to_owned()), record.record.tag);
+    assert!(record.is_valid(ledger.get_state_commitment().0));
+
+    // once by each key
+    ledger.set_block_time(1_600_000_100);
+    let tx = anchor(&ledger, None, &kp);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = anchor(&ledger, None, &other_kp);
+    pnk!(try_apply(&mut ledger, tx));
+    let records = ledger.get_status().get_notary_state().get_records(&hash);
+    assert_eq!(2, records.len());
+    assert!(records
+        .iter()
+        .any(|r| r.owner == other_kp.get_pk() && 1_600_000_100 == r.timestamp));
+    assert!(ledger
+        .get_status()
+        .get_notary_state()
+        .get_records(&document_hash(b"contract v2"))
+        .is_empty());
+    assert!(pnk!(ledger.get_notarized_hash(&hash, kp.get_pk_ref()))
+        .is_valid(ledger.get_state_commitment().0));
+}
+
+#[test]
+fn test_txn_expiry() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let kp = XfrKeyPair::generate(&mut prng);
+
+    let anchor = |ledger: &LedgerState, data: &[u8], expires_at: Option<u64>| {
+        let mut tx = Transaction::from_seq_id(ledger.get_block_commit_count());
+        tx.body.expires_at = expires_at;
+        let body = AnchorHashBody {
+            hash: document_hash(data),
+            tag: None,
+            no_replay_token: tx.body.no_replay_token,
+        };
+        tx.add_operation(Operation::AnchorHash(AnchorHash::new(body, &kp)));
+        tx.sign(&kp);
+        tx
+    };
+
+    // the next block is at height 11
+    ledger.set_tendermint_height(10);
+    let tx = anchor(&ledger, b"1", Some(10));
+    assert!(tx.is_expired(11));
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = anchor(&ledger, b"2", Some(11));
+    pnk!(try_apply(&mut ledger, tx));
+    let tx = anchor(&ledger, b"3", None);
+    pnk!(try_apply(&mut ledger, tx));
+}
+
+#[test]
+fn test_state_export_diff() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let fra_owner_kp = XfrKeyPair::generate(&mut prng);
+    let bob_kp = XfrKeyPair::generate(&mut prng);
+
+    pnk!(try_apply(
+        &mut ledger,
+        utils::fra_gen_initial_tx(&fra_owner_kp)
+    ));
+    let before = ledger.export();
+    let d = pnk!(export::diff(&before, &before));
+    assert!(d.is_empty() && d.same_state_commitment);
+
+    let txo_sid = ledger
+        .get_status()
+        .get_owned_utxos(fra_owner_kp.get_pk_ref())
+        .into_iter()
+        .min()
+        .unwrap();
+    let op = gen_send_operation(
+        &mut ledger,
+        txo_sid,
+        &fra_owner_kp,
+        &[(100, bob_kp.get_pk())],
+    );
+    let tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+    pnk!(try_apply(&mut ledger, tx));
+    let after = ledger.export();
+
+    let d = pnk!(export::diff(&before, &after));
+    assert!(!d.is_empty() && !d.same_state_commitment);
+    assert_eq!(
+        vec![txo_sid],
+        d.utxos
+            .removed
+            .iter()
+            .map(|(sid, _)| *sid)
+            .collect::<Vec<_>>()
+    );
+    let mut owners = d
+        .utxos
+        .added
+        .iter()
+        .map(|(_, u)| u.0.record.public_key)
+        .collect::<Vec<_>>();
+    owners.sort();
+    let mut expected = vec![fra_owner_kp.get_pk(), bob_kp.get_pk()];
+    expected.sort();
+    assert_eq!(expected, owners);
+    assert!(d.utxos.changed.is_empty());
+    assert!(d.assets.is_empty());
+
+    // exports are compared after a round trip through JSON
+    let e = pnk!(serde_json::to_vec(&after));
+    let e = pnk!(serde_json::from_slice::<export::LedgerExport>(&e));
+    assert!(pnk!(export::diff(&after, &e)).is_empty());
+}
+
+#[test]
+fn test_pending_txo_ref() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let fra_owner_kp = XfrKeyPair::generate(&mut prng);
+    let bob_kp = XfrKeyPair::generate(&mut prng);
+    let carol_kp = XfrKeyPair::generate(&mut prng);
+
+    pnk!(try_apply(
+        &mut ledger,
+        utils::fra_gen_initial_tx(&fra_owner_kp)
+    ));
+    let txo_sid = ledger
+        .get_status()
+        .get_owned_utxos(fra_owner_kp.get_pk_ref())
+        .into_iter()
+        .min()
+        .unwrap();
+
+    // the change goes first, `100` to bob is the second output
+    let op = gen_send_operation(
+        &mut ledger,
+        txo_sid,
+        &fra_owner_kp,
+        &[(100, bob_kp.get_pk())],
+    );
+    let parent = Transaction::from_operation(op, ledger.get_block_commit_count());
+    let parent_handle = parent.handle();
+
+    let input_bar = match &parent.body.operations[0] {
+        Operation::TransferAsset(trn) => trn.body.outputs[1].record.clone(),
+        _ => unreachable!(),
+    };
+    let input_oar = pnk!(open_blind_asset_record(&input_bar, &None, &bob_kp));
+    let output = AssetRecord::from_template_no_identity_tracing(
+        &mut prng,
+        &AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            input_oar.asset_type,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            carol_kp.get_pk(),
+        ),
+    )
+    .unwrap();
+    let mut transfer = TransferAsset::new(
+        TransferAssetBody::new(
+            &mut prng,
+            vec![TxoRef::Pending(pnk!(PendingTxoRef::from_handle(
+                &parent_handle,
+                1
+            )))],
+            &[AssetRecord::from_open_asset_record_no_asset_tracing(
+                input_oar,
+            )],
+            &[output],
+            None,
+            vec![],
+            TransferType::Standard,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    transfer.sign(&bob_kp);
+    let child = Transaction::from_operation(
+        Operation::TransferAsset(transfer),
+        ledger.get_block_commit_count(),
+    );
+    assert_eq!(vec![parent.handle_digest()], child.pending_parents());
+
+    // the parent is not committed yet
+    assert!(try_apply(&mut ledger, child.clone()).is_err());
+
+    pnk!(try_apply(&mut ledger, parent.clone()));
+    let parent_outputs = ledger
+        .get_status()
+        .get_recent_txn_outputs(&parent.handle_digest())
+        .unwrap()
+        .to_vec();
+    assert_eq!(2, parent_outputs.len());
+
+    pnk!(try_apply(&mut ledger, child));
+    assert!(ledger.get_utxo_light(parent_outputs[1]).is_none());
+    assert_eq!(
+        1,
+        ledger
+            .get_status()
+            .get_owned_utxos(carol_kp.get_pk_ref())
+            .len()
+    );
+
+    // out of the window, the parent can no longer be referenced
+    for _ in 0..PENDING_REF_WINDOW {
+        let block = pnk!(ledger.start_block());
+        pnk!(ledger.finish_block(block));
+    }
+    assert!(ledger
+        .get_status()
+        .get_recent_txn_outputs(&parent.handle_digest())
+        .is_none());
+}
+
+#[test]
+fn test_ledger_fixture() {
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let bob_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let mut fixture = fixture::LedgerFixture::new();
+    pnk!(fixture.commit(vec![utils::fra_gen_initial_tx(&fra_owner_kp)]));
+    let txo_sid = fixture
+        .ledger()
+        .get_status()
+        .get_owned_utxos(fra_owner_kp.get_pk_ref())[0];
+    let op = gen_send_operation(
+        fixture.ledger_mut(),
+        txo_sid,
+        &fra_owner_kp,
+        &[(100, bob_kp.get_pk())],
+    );
+    let tx = Transaction::from_operation(op, fixture.ledger().get_block_commit_count());
+    pnk!(fixture.commit(vec![tx.clone()]));
+
+    // a rejected transaction fails the capture
+    assert!(fixture.commit(vec![tx]).is_err());
+
+    let path = globutils::fresh_tmp_dir().join("transfer.snap");
+    let path = path.to_str().unwrap();
+    pnk!(fixture.save(path));
+    let ledger = fixture.into_ledger();
+    let commitment = ledger.get_state_commitment();
+    let height = ledger.get_tendermint_height();
+    let bob_utxos = ledger.get_status().get_owned_utxos(bob_kp.get_pk_ref());
+
+    // an existing fixture is loaded, the scenario is not run again
+    let loaded = pnk!(fixture::LedgerFixture::load_or_capture(path, |_| Err(
+        eg!()
+    )));
+    assert_eq!(commitment, loaded.get_state_commitment());
+    assert_eq!(height, loaded.get_tendermint_height());
+    assert_eq!(
+        bob_utxos,
+        loaded.get_status().get_owned_utxos(bob_kp.get_pk_ref())
+    );
+}
+
+#[test]
+fn test_archive_catch_up() {
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let bob_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let path = globutils::fresh_tmp_dir().join("follow.archive");
+    let path = path.to_str().unwrap();
+
+    let mut fixture = fixture::LedgerFixture::new();
+    pnk!(fixture.commit(vec![utils::fra_gen_initial_tx(&fra_owner_kp)]));
+    pnk!(fixture.save(path));
+
+    let mut follower = LedgerState::tmp_ledger();
+    let (report, offset) = pnk!(archive::catch_up(&mut follower, path, 0));
+    assert_eq!(1, report.blocks);
+    assert!(report.divergence.is_none());
+    assert_eq!(fs::metadata(path).unwrap().len(), offset);
+
+    let txo_sid = fixture
+        .ledger()
+        .get_status()
+        .get_owned_utxos(fra_owner_kp.get_pk_ref())[0];
+    let op = gen_send_operation(
+        fixture.ledger_mut(),
+        txo_sid,
+        &fra_owner_kp,
+        &[(100, bob_kp.get_pk())],
+    );
+    let tx = Transaction::from_operation(op, fixture.ledger().get_block_commit_count());
+    pnk!(fixture.commit(vec![tx]));
+    pnk!(fixture.save(path));
+
+    // a line still being written is left to the next call
+    let mut f = fs::OpenOptions::new().append(true).open(path).unwrap();
+    f.write_all(b"{\"height\":").unwrap();
+
+    let (report, next) = pnk!(archive::catch_up(&mut follower, path, offset));
+    assert_eq!(1, report.blocks);
+    assert!(next < fs::metadata(path).unwrap().len());
+    assert_eq!(
+        fixture.ledger().get_state_commitment(),
+        follower.get_state_commitment()
+    );
+    assert_eq!(fixture.ledger().get_tendermint_height(), report.last_height);
+
+    let (report, offset) = pnk!(archive::catch_up(&mut follower, path, next));
+    assert_eq!(0, report.blocks);
+    assert_eq!(next, offset);
+}
+
+#[test]
+fn test_last_block_staking() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    ledger.get_staking_mut().set_custom_block_height(5);
+
+    // the staking state is changed by the block
+    let mut block = pnk!(ledger.start_block());
+    block.staking_simulator.set_custom_block_height(6);
+    let effect = pnk!(TxnEffect::compute_effect(utils::fra_gen_initial_tx(&kp)));
+    pnk!(ledger.apply_transaction(&mut block, effect));
+    pnk!(ledger.finish_block(block));
+
+    assert_eq!(6, ledger.get_staking().cur_height);
+    assert_eq!(5, pnk!(ledger.get_last_block_staking()).cur_height);
+
+    // until the next block starts
+    let block = pnk!(ledger.start_block());
+    assert_eq!(6, block.staking_simulator.cur_height);
+}
+
+#[test]
+fn test_archive_chunks() {
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let bob_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let dir = globutils::fresh_tmp_dir();
+    let src = dir.join("src.archive");
+    let src = src.to_str().unwrap();
+    let dst = dir.join("dst.archive");
+    let dst = dst.to_str().unwrap();
+
+    let mut fixture = fixture::LedgerFixture::new();
+    pnk!(fixture.commit(vec![utils::fra_gen_initial_tx(&fra_owner_kp)]));
+    for _ in 0..2 {
+        let txo_sid = fixture
+            .ledger()
+            .get_status()
+            .get_owned_utxos(fra_owner_kp.get_pk_ref())[0];
+        let op = gen_send_operation(
+            fixture.ledger_mut(),
+            txo_sid,
+            &fra_owner_kp,
+            &[(100, bob_kp.get_pk())],
+        );
+        let tx =
+            Transaction::from_operation(op, fixture.ledger().get_block_commit_count());
+        pnk!(fixture.commit(vec![tx]));
+    }
+    pnk!(fixture.save(src));
+
+    let mut chunks = vec![];
+    let n = pnk!(archive::export_chunks(src, 0, u64::MAX, 2, |c| {
+        chunks.push(c);
+        Ok(())
+    }));
+    assert_eq!(2, n);
+    assert_eq!(2, chunks[0].header.count);
+    assert_eq!(1, chunks[1].header.count);
+    assert_eq!(
+        fixture.ledger().get_state_commitment().0,
+        chunks[1].header.commitment
+    );
+
+    // the chunks must be imported in order
+    assert!(archive::import_chunk(dst, &chunks[0]).is_ok());
+    assert_eq!(0, pnk!(archive::import_chunk(dst, &chunks[0])));
+
+    // a tampered chunk is refused
+    let mut tampered = chunks[1].clone();
+    tampered.blocks[0].block.txns[0]
+        .txn
+        .body
+        .memos
+        .push(Memo("x".to_owned()));
+    assert!(tampered.verify().is_err());
+    assert!(archive::import_chunk(dst, &tampered).is_err());
+
+    assert_eq!(1, pnk!(archive::import_chunk(dst, &chunks[1])));
+
+    let mut replayed = LedgerState::tmp_ledger();
+    let report = pnk!(archive::replay(&mut replayed, dst, None));
+    assert_eq!(3, report.blocks);
+    assert!(report.divergence.is_none());
+}
+
+#[test]
+fn test_ledger_checksums() {
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let bob_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let path = globutils::fresh_tmp_dir().join("checksums.archive");
+    let path = path.to_str().unwrap();
+
+    let mut fixture = fixture::LedgerFixture::new();
+    pnk!(fixture.commit(vec![utils::fra_gen_initial_tx(&fra_owner_kp)]));
+    pnk!(fixture.save(path));
+
+    let mut follower = LedgerState::tmp_ledger();
+    pnk!(archive::catch_up(&mut follower, path, 0));
+
+    let left = pnk!(fixture.ledger().checksums(&[]));
+    let right = pnk!(follower.checksums(&[]));
+    assert_eq!(checksum::Subsystem::ALL.len(), left.subsystems.len());
+    assert_eq!(left.height, right.height);
+    assert!(left.mismatches(&right).is_empty());
+
+    let txo_sid = fixture
+        .ledger()
+        .get_status()
+        .get_owned_utxos(fra_owner_kp.get_pk_ref())[0];
+    let op = gen_send_operation(
+        fixture.ledger_mut(),
+        txo_sid,
+        &fra_owner_kp,
+        &[(100, bob_kp.get_pk())],
+    );
+    let tx = Transaction::from_operation(op, fixture.ledger().get_block_commit_count());
+    pnk!(fixture.commit(vec![tx]));
+
+    // only the outputs differ
+    let subsystems = [checksum::Subsystem::Utxo, checksum::Subsystem::Asset];
+    let left = pnk!(fixture.ledger().checksums(&subsystems));
+    let right = pnk!(follower.checksums(&subsystems));
+    assert_eq!(2, left.subsystems.len());
+    assert_eq!(vec![checksum::Subsystem::Utxo], left.mismatches(&right));
+
+    assert_eq!(
+        checksum::Subsystem::Air,
+        pnk!("AIR".parse::<checksum::Subsystem>())
+    );
+    assert!("bitmap".parse::<checksum::Subsystem>().is_err());
+}
+
+#[test]
+fn test_write_batching() {
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let bob_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+    let wal = format!("{}/test_ledger_wal", dir);
+    let records = || {
+        pnk!(persist::backend().read(&wal))
+            .split(|b| b'\n' == *b)
+            .filter(|l| !l.is_empty())
+            .count()
+    };
+
+    let mut ledger = pnk!(LedgerState::new(&dir, Some("test")));
+    assert_eq!(SyncMode::Always, ledger.get_sync_mode());
+    pnk!(ledger.set_sync_mode(pnk!("batched:10".parse())));
+    assert_eq!(SyncMode::Batched { blocks: 10 }, ledger.get_sync_mode());
+
+    apply_transaction(&mut ledger, utils::fra_gen_initial_tx(&fra_owner_kp));
+    let txo_sid = ledger
+        .get_status()
+        .get_owned_utxos(fra_owner_kp.get_pk_ref())[0];
+    let op = gen_send_operation(
+        &mut ledger,
+        txo_sid,
+        &fra_owner_kp,
+        &[(100, bob_kp.get_pk())],
+    );
+    let tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+    apply_transaction(&mut ledger, tx);
+    assert_eq!(2, records());
+
+    let state = ledger.status.state_commitment_data.clone().unwrap();
+    // a crash, nothing has been written but the log
+    mem::forget(ledger);
+
+    let mut ledger = pnk!(LedgerState::new(&dir, Some("test")));
+    assert_eq!(0, records());
+    assert_eq!(
+        state.block_merkle,
+        ledger.block_merkle.read().get_root_hash()
+    );
+    assert_eq!(
+        state.transaction_merkle_commitment,
+        ledger.txn_merkle.read().get_root_hash()
+    );
+    assert_eq!(state.bitmap, ledger.utxo_map.write().compute_checksum());
+
+    // the log is emptied by the group commits
+    pnk!(ledger.set_sync_mode(SyncMode::Batched { blocks: 1 }));
+    let code = AssetTypeCode::gen_random();
+    let tx = pnk!(create_definition_transaction(
+        &code,
+        &bob_kp,
+        AssetRules::default(),
+        None,
+        ledger.get_block_commit_count(),
+    ));
+    apply_transaction(&mut ledger, tx);
+    assert_eq!(0, records());
+
+    assert_eq!(SyncMode::Off, pnk!("off".parse()));
+    assert!("batched:0".parse::<SyncMode>().is_err());
+    assert!("sometimes".parse::<SyncMode>().is_err());
+}
+
+#[test]
+fn test_ledger_status_serde() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut ledger = LedgerState::tmp_ledger();
+    let code = AssetTypeCode::gen_random();
+
+    // JSON only takes strings as keys, these maps are keyed by asset codes
+    let status = &mut ledger.status;
+    status.tracer_key_rotations.insert(
+        code,
+        vec![TracerKeyRotation {
+            policy_idx: 0,
+            effective_height: 10,
+            enc_keys: zei::xfr::structs::AssetTracerKeyPair::generate(&mut prng).enc_key,
+        }],
+    );
+    status.prism.locked.insert(code, 100);
+    status.asset_mirrors.insert(
+        code,
+        vec![AssetMirror {
+            chain_id: 1,
+            contract: [7; 20],
+        }],
+    );
+
+    let json = pnk!(serde_json::to_vec(&ledger.status));
+    let back = pnk!(serde_json::from_slice::<LedgerStatus>(&json));
+    assert_eq!(
+        ledger.status.tracer_key_rotations,
+        back.tracer_key_rotations
+    );
+    assert_eq!(ledger.status.prism.locked, back.prism.locked);
+    assert_eq!(ledger.status.asset_mirrors, back.asset_mirrors);
+}
+
+// The staking is hashed in JSON into the state commitment,
+// the fields added since are not serialized while they are default.
+#[test]
+fn test_staking_serde_compat() {
+    let kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+    let v = pnk!(Validator::new(
+        vec![1; 32],
+        1,
+        kp.get_pk(),
+        [1, 10],
+        Default::default(),
+        ValidatorKind::Initiator,
+    ));
+    let json = pnk!(serde_json::to_value(&v));
+    assert!(json.get("jailed").is_none());
+    assert_eq!(v, pnk!(serde_json::from_value::<Validator>(json)));
+
+    let mut jailed = v;
+    jailed.jailed = true;
+    let json = pnk!(serde_json::to_value(&jailed));
+    assert_eq!(Some(&serde_json::Value::Bool(true)), json.get("jailed"));
+    assert_eq!(jailed, pnk!(serde_json::from_value::<Validator>(json)));
+
+    // the fields of the baseline only, before the epoch rewards
+    let staking = LedgerState::tmp_ledger().get_staking().clone();
+    let json = pnk!(serde_json::to_value(&staking));
+    let mut keys = json
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(vec!["coinbase", "cr", "cur_height", "di", "vi"], keys);
+}
+
+#[test]
+fn test_prefixed_ledgers_staking() {
+    let mut prng = ChaChaRng::from_entropy();
+    let kp = XfrKeyPair::generate(&mut prng);
+
+    let dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+    let mut a = pnk!(LedgerState::new(&dir, Some("a")));
+    let mut b = pnk!(LedgerState::new(&dir, Some("b")));
+
+    let ops = pnk!(FraDistributionOps::new(
+        &[&kp],
+        map! {B kp.get_pk() => 100},
+        NoReplayToken::new(&mut prng, 0),
+    ));
+
+    // the history of the CoinBase of each ledger is its own
+    pnk!(a
+        .get_staking_mut()
+        .coinbase_config_fra_distribution(ops.clone()));
+    assert!(a
+        .get_staking_mut()
+        .coinbase_config_fra_distribution(ops.clone())
+        .is_err());
+    pnk!(b.get_staking_mut().coinbase_config_fra_distribution(ops));
+}
+
+#[test]
+fn test_epoch_rewards() {
+    let mut prng = ChaChaRng::from_entropy();
+    let kps = (0..6)
+        .map(|_| XfrKeyPair::generate(&mut prng))
+        .collect::<Vec<_>>();
+    // `v` and `w` are validators, `d1` and `d2` delegate to `v`,
+    // `gone` has left the validator set,
+    // the power of `big` keeps the others under the limit of each validator
+    let (v, w, d1, d2, gone, big) = (
+        kps[0].get_pk(),
+        kps[1].get_pk(),
+        kps[2].get_pk(),
+        kps[3].get_pk(),
+        kps[4].get_pk(),
+        kps[5].get_pk(),
+    );
+
+    let mut ledger = LedgerState::tmp_ledger();
+    let staking = ledger.get_staking_mut();
+    let vs = [
+        (v, [1, 10], 1),
+        (w, [1, 5], 1),
+        (big, [1, 5], 100_000_000 * FRA),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, (id, rate, power))| {
+        Validator::new(
+            vec![i as u8 + 1; 32],
+            *power,
+            *id,
+            *rate,
+            Default::default(),
+            ValidatorKind::Initiator,
+        )
+    })
+    .collect::<Result<Vec<_>>>();
+    staking.validator_set_at_height_force(1, pnk!(ValidatorData::new(1, pnk!(vs))));
+    staking.set_custom_block_height(1);
+
+    let v_addr = td_pubkey_to_td_addr(&[1; 32]);
+    let w_addr = td_pubkey_to_td_addr(&[2; 32]);
+    pnk!(staking.delegate(v, &v_addr, 1_000_003 * FRA));
+    pnk!(staking.delegate(d1, &v_addr, 2_000_007 * FRA));
+    pnk!(staking.delegate(d2, &v_addr, 333_331 * FRA));
+    pnk!(staking.delegate(w, &w_addr, 1_000_000 * FRA));
+
+    // the first close of an epoch after the checkpoint
+    let h =
+        (CFG.checkpoint.epoch_rewards_height / EPOCH_BLOCK_CNT + 1) * EPOCH_BLOCK_CNT;
+    staking.set_custom_block_height(h);
+    let bonded = (1_000_003 + 2_000_007 + 333_331) * FRA;
+    for pk in [v, v, w, gone].iter() {
+        pnk!(staking.epoch_accumulate(pk, [1, 10], bonded, 2 * bonded));
+    }
+    let pool = staking.epoch_state().pools[&v];
+    assert!(0 < pool);
+
+    // an invalid commission rate skips its validator only
+    staking
+        .validator_get_current_mut()
+        .unwrap()
+        .body
+        .get_mut(&w)
+        .unwrap()
+        .commission_rate = [2, 1];
+
+    let rwd = |s: &Staking, pk: &XfrPublicKey| s.delegation_get(pk).unwrap().rwd_amount;
+    assert_eq!(0, rwd(staking, &v));
+    pnk!(staking.epoch_try_close());
+    assert!(staking.epoch_state().pools.is_empty());
+    assert_eq!(1 + h, staking.epoch_state().start_height);
+
+    let commission = pool / 10;
+    let distributable = pool - commission;
+    let share = |am: Amount| {
+        (distributable as u128 * (am * FRA) as u128 / bonded as u128) as Amount
+    };
+    let (sv, s1, s2) = (share(1_000_003), share(2_000_007), share(333_331));
+    assert_eq!(s1, rwd(staking, &d1));
+    assert_eq!(s2, rwd(staking, &d2));
+    // the commission, its own share, and the remainders of the divisions
+    let remainder = distributable - sv - s1 - s2;
+    assert_eq!(commission + sv + remainder, rwd(staking, &v));
+    assert_eq!(pool, rwd(staking, &v) + s1 + s2);
+
+    assert_eq!(0, rwd(staking, &w));
+    assert!(staking.delegation_get(&gone).is_none());
+
+    // nothing more until the next close
+    staking.set_custom_block_height(1 + h);
+    pnk!(staking.epoch_accumulate(&v, [1, 10], bonded, 2 * bonded));
+    pnk!(staking.epoch_try_close());
+    assert_eq!(pool, rwd(staking, &v) + s1 + s2);
+}
+
+#[test]
+fn test_jail_validators() {
+    let mut prng = ChaChaRng::from_entropy();
+    let kps = (0..7)
+        .map(|_| XfrKeyPair::generate(&mut prng))
+        .collect::<Vec<_>>();
+    let ids = kps.iter().map(|kp| kp.get_pk()).collect::<Vec<_>>();
+
+    let mut ledger = LedgerState::tmp_ledger();
+    let staking = ledger.get_staking_mut();
+    // the last one does not self-delegate,
+    // its power keeps the others under the limit of each validator
+    let vs = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            Validator::new(
+                vec![i as u8 + 1; 32],
+                alt!(6 == i, 100_000_000 * FRA, 1),
+                *id,
+                [1, 10],
+                Default::default(),
+                ValidatorKind::Initiator,
+            )
+        })
+        .collect::<Result<Vec<_>>>();
+    staking.validator_set_at_height_force(1, pnk!(ValidatorData::new(1, pnk!(vs))));
+    staking.set_custom_block_height(1);
+
+    let addr = |i: usize| td_pubkey_to_td_addr(&[i as u8 + 1; 32]);
+    for (i, am) in [3, 1, 2, 10, 10, 10].iter().enumerate() {
+        pnk!(staking.delegate(ids[i], &addr(i), am * 1000 * FRA));
+    }
+
+    // three are under the minimum, two can be jailed,
+    // the lowest self-delegations first
+    let min = 5 * 1000 * FRA;
+    let mut jailed = staking.validator_jail_under(min);
+    jailed.sort();
+    let mut expected = vec![ids[1], ids[2]];
+    expected.sort();
+    assert_eq!(expected, jailed);
+    let is_jailed = |s: &Staking, id: &XfrPublicKey| {
+        s.validator_get_current_one_by_id(id).unwrap().jailed
+    };
+    assert!(!is_jailed(staking, &ids[0]));
+
+    // `VALIDATORS_MIN` validators are left
+    assert!(staking.validator_jail_under(min).is_empty());
+    assert!(!is_jailed(staking, &ids[0]));
+
+    assert!(staking.validator_unjail_with_min(&ids[0], min).is_err());
+    assert!(staking.validator_unjail_with_min(&ids[1], min).is_err());
+    assert!(is_jailed(staking, &ids[1]));
+
+    // topped up to the minimum
+    pnk!(staking.delegate(ids[1], &addr(1), 4 * 1000 * FRA));
+    pnk!(staking.validator_unjail_with_min(&ids[1], min));
+    assert!(!is_jailed(staking, &ids[1]));
+    assert!(staking.validator_unjail_with_min(&ids[1], min).is_err());
+
+    // the one unjailed is above the minimum, the next lowest is jailed
+    assert_eq!(vec![ids[0]], staking.validator_jail_under(min));
+}
+
+#[test]
+fn test_multi_delegation() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let mut prng = ChaChaRng::from_entropy();
+    let kps = (0..4)
+        .map(|_| XfrKeyPair::generate(&mut prng))
+        .collect::<Vec<_>>();
+    // `v1` and `v2` are validators, `d` delegates to both of them,
+    // the power of `big` keeps the others under the limit of each validator
+    let (v1, v2, big, d_kp) =
+        (kps[0].get_pk(), kps[1].get_pk(), kps[2].get_pk(), &kps[3]);
+    let d = d_kp.get_pk();
+
+    let (_, fra_sids) = apply_transaction(&mut ledger, utils::fra_gen_initial_tx(d_kp));
+
+    let staking = ledger.get_staking_mut();
+    let vs = [(v1, 1), (v2, 1), (big, 100_000_000 * FRA)]
+        .iter()
+        .enumerate()
+        .map(|(i, (id, power))| {
+            Validator::new(
+                vec![i as u8 + 1; 32],
+                *power,
+                *id,
+                [1, 10],
+                Default::default(),
+                ValidatorKind::Initiator,
+            )
+        })
+        .collect::<Result<Vec<_>>>();
+    staking.validator_set_at_height_force(1, pnk!(ValidatorData::new(1, pnk!(vs))));
+    staking.set_custom_block_height(1);
+
+    let addr = |i: u8| td_pubkey_to_td_addr(&[i + 1; 32]);
+    pnk!(staking.delegate(v1, &addr(0), 1000 * FRA));
+    pnk!(staking.delegate(v2, &addr(1), 1000 * FRA));
+
+    // a single principal of `d` split across the targets
+    let gen_tx = |ledger: &mut LedgerState, targets: &[(String, Amount)]| {
+        let principal = targets.iter().map(|(_, am)| am).sum();
+        let op = gen_lock_operation(ledger, fra_sids[0], d_kp, principal);
+        let mut tx = Transaction::from_operation(op, ledger.get_block_commit_count());
+        let nonce = tx.body.no_replay_token;
+        for (validator, am) in targets.iter() {
+            tx.add_operation(Operation::Delegation(DelegationOps::new(
+                d_kp,
+                None,
+                *am,
+                validator.clone(),
+                None,
+                nonce,
+            )));
+        }
+        tx.sign(d_kp);
+        tx
+    };
+    let powers = |s: &Staking| {
+        [
+            pnk!(s.validator_get_power(&v1)),
+            pnk!(s.validator_get_power(&v2)),
+        ]
+    };
+    let before = powers(ledger.get_staking());
+
+    // at most one delegation to each validator
+    let tx = gen_tx(&mut ledger, &[(addr(0), 10 * FRA), (addr(0), 20 * FRA)]);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // the last target is not a validator, the first two are reverted
+    let mut block = pnk!(ledger.start_block());
+    let tx = gen_tx(
+        &mut ledger,
+        &[
+            (addr(0), 10 * FRA),
+            (addr(1), 20 * FRA),
+            (addr(5), 30 * FRA),
+        ],
+    );
+    let effect = pnk!(TxnEffect::compute_effect(tx));
+    assert!(ledger.apply_transaction(&mut block, effect).is_err());
+    let simulator = &block.staking_simulator;
+    assert!(simulator.delegation_get(&d).is_none());
+    assert_eq!(before, powers(simulator));
+    assert_eq!(
+        ledger.get_staking().delegation_info.global_amount,
+        simulator.delegation_info.global_amount
+    );
+    assert!(pnk!(simulator.validator_get_current_one_by_id(&v1))
+        .delegators
+        .is_empty());
+
+    // all together, in the same block
+    let tx = gen_tx(&mut ledger, &[(addr(0), 10 * FRA), (addr(1), 20 * FRA)]);
+    let effect = pnk!(TxnEffect::compute_effect(tx));
+    pnk!(ledger.apply_transaction(&mut block, effect));
+    pnk!(ledger.finish_block(block));
+
+    let staking = ledger.get_staking();
+    assert_eq!(
+        map! {B v1 => 10 * FRA, v2 => 20 * FRA},
+        pnk!(staking.delegation_get(&d)).delegations
+    );
+    assert_eq!(
+        [before[0] + 10 * FRA, before[1] + 20 * FRA],
+        powers(staking)
+    );
+}
+
+#[test]
+fn test_multiple_tracing_policies() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut ledger = LedgerState::tmp_ledger();
+    let params = PublicParams::default();
+    let kp = XfrKeyPair::generate(&mut prng);
+    let code = AssetTypeCode::gen_random();
+
+    let policies = (0..2)
+        .map(|_| TracingPolicy {
+            enc_keys: zei::xfr::structs::AssetTracerKeyPair::generate(&mut prng).enc_key,
+            asset_tracing: true,
+            identity_tracing: None,
+        })
+        .collect::<Vec<_>>();
+    let mut rules = AssetRules::default();
+    for p in policies.iter() {
+        rules.add_tracing_policy(p.clone());
+    }
+    let tx = pnk!(create_definition_transaction(&code, &kp, rules, None, 0));
+    apply_transaction(&mut ledger, tx);
+
+    let issue_and_transfer = |ledger: &mut LedgerState, attached: &[TracingPolicy]| {
+        let mut tracing_policies = TracingPolicies::new();
+        for p in attached.iter() {
+            tracing_policies.add(p.clone());
+        }
+        create_issue_and_transfer_txn_with_tracing_policies(
+            ledger,
+            &params,
+            &code,
+            100,
+            &kp,
+            kp.get_pk_ref(),
+            0,
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            tracing_policies,
+        )
+        .0
+    };
+
+    // one of the tracers would not be able to decrypt its memos
+    let tx = issue_and_transfer(&mut ledger, &policies[..1]);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let tx = issue_and_transfer(&mut ledger, &policies[1..]);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // in another order
+    let tx =
+        issue_and_transfer(&mut ledger, &[policies[1].clone(), policies[0].clone()]);
+    pnk!(try_apply(&mut ledger, tx));
+    assert_eq!(
+        ledger
+            .get_asset_type(&code)
+            .unwrap()
+            .get_tracing_policies_ref()
+            .get_policies(),
+        &policies[..]
+    );
+}
+
+#[test]
+fn test_tracing_threshold() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut ledger = LedgerState::tmp_ledger();
+    let params = PublicParams::default();
+    let kp = XfrKeyPair::generate(&mut prng);
+    let code = AssetTypeCode::gen_random();
+
+    let mut policies = TracingPolicies::new();
+    policies.add(TracingPolicy {
+        enc_keys: zei::xfr::structs::AssetTracerKeyPair::generate(&mut prng).enc_key,
+        asset_tracing: true,
+        identity_tracing: None,
+    });
+    let mut rules = AssetRules::default();
+    rules
+        .add_tracing_policy(policies.get_policies()[0].clone())
+        .set_tracing_threshold(Some(50));
+    let tx = pnk!(create_definition_transaction(&code, &kp, rules, None, 0));
+    apply_transaction(&mut ledger, tx);
+
+    // no tracer memos at all
+    let issue_and_transfer =
+        |ledger: &mut LedgerState,
+         amount: u64,
+         seq_num: u64,
+         record_type: AssetRecordType| {
+            create_issue_and_transfer_txn_with_tracing_policies(
+                ledger,
+                &params,
+                &code,
+                amount,
+                &kp,
+                kp.get_pk_ref(),
+                seq_num,
+                record_type,
+                TracingPolicies::new(),
+            )
+            .0
+        };
+    let confidential = AssetRecordType::ConfidentialAmount_NonConfidentialAssetType;
+    let non_confidential =
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType;
+
+    // above the threshold
+    let tx = issue_and_transfer(&mut ledger, 51, 0, non_confidential);
+    assert!(try_apply(&mut ledger, tx).is_err());
+
+    // confidential amounts are always traced, whatever their values
+    let tx = issue_and_transfer(&mut ledger, 10, 0, confidential);
+    assert!(try_apply(&mut ledger, tx).is_err());
+    let (tx, _) = create_issue_and_transfer_txn_with_tracing_policies(
+        &mut ledger,
+        &params,
+        &code,
+        10,
+        &kp,
+        kp.get_pk_ref(),
+        0,
+        confidential,
+        policies,
+    );
+    pnk!(try_apply(&mut ledger, tx));
+
+    // not exceeding it
+    let tx = issue_and_transfer(&mut ledger, 50, 1, non_confidential);
+    pnk!(try_apply(&mut ledger, tx));
+    let tx = issue_and_transfer(&mut ledger, 10, 2, non_confidential);
+    pnk!(try_apply(&mut ledger, tx));
+}
@@ -0,0 +1,87 @@
+//!
+//! The ledger store on the in-memory backend,
+//! in a test binary of its own as the backend is global to the process.
+//!
+
+use {
+    globutils::persist::{self, MemPersistence, Persistence},
+    ledger::{
+        data_model::{AssetRules, AssetTypeCode, Memo},
+        store::{
+            archive::{self, ArchivedBlock},
+            helpers::{
+                apply_transaction, create_definition_transaction,
+                create_issue_and_transfer_txn,
+            },
+            LedgerState,
+        },
+    },
+    std::sync::Arc,
+    zei::{setup::PublicParams, xfr::sig::XfrKeyPair},
+};
+
+#[test]
+fn ledger_on_mem_persistence() {
+    let mem = Arc::new(MemPersistence::default());
+    persist::set_backend(mem.clone());
+
+    let mut ledger = LedgerState::tmp_ledger();
+    let params = PublicParams::default();
+    let issuer = XfrKeyPair::generate(&mut ledger.get_prng());
+    let alice = XfrKeyPair::generate(&mut ledger.get_prng());
+
+    let code = AssetTypeCode::gen_random();
+    let tx = create_definition_transaction(
+        &code,
+        &issuer,
+        AssetRules::default(),
+        Some(Memo("test".to_string())),
+        ledger.get_block_commit_count(),
+    )
+    .unwrap();
+    apply_transaction(&mut ledger, tx);
+
+    let (tx, _) = create_issue_and_transfer_txn(
+        &mut ledger,
+        &params,
+        &code,
+        100,
+        &issuer,
+        alice.get_pk_ref(),
+        0,
+    );
+    let (_, sids) = apply_transaction(&mut ledger, tx);
+    assert_eq!(2, ledger.get_block_commit_count());
+    assert!(ledger.get_utxo_light(sids[0]).is_some());
+
+    // the structures of the ledger are all in memory
+    let files = mem.export();
+    for name in ["block_merkle", "txn_merkle", "utxo_map"].iter() {
+        assert!(files.keys().any(|k| k.ends_with(name)), "{}", name);
+    }
+
+    // so are the block archives
+    let path = "block_archive";
+    for i in 0..2 {
+        let b = ArchivedBlock {
+            height: 1 + i as u64,
+            staking: ledger.get_staking().clone(),
+            block: ledger.blocks.get(i).unwrap(),
+        };
+        archive::append(path, &b).unwrap();
+    }
+    assert!(mem.exists(path));
+
+    let mut chunks = vec![];
+    let n = archive::export_chunks(path, 1, 2, 2, |c| {
+        chunks.push(c);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(1, n);
+    assert_eq!(
+        2,
+        archive::import_chunk("block_archive_copy", &chunks[0]).unwrap()
+    );
+    assert!(mem.exists("block_archive_copy"));
+}
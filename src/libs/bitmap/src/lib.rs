@@ -2,8 +2,9 @@
 //!
 //! This module implements a simple persistent bitmap. The
 //! bitmap currently is stored in a single file. The caller
-//! is responsible for file creation and open operations; thus
-//! this module does not manage or use file paths.
+//! is responsible for file creation and open operations(usually
+//! by `globutils::persist`); thus this module does not manage or
+//! use file paths.
 //!
 //! The bitmap is maintained in memory and on disk as a sequence
 //! of blocks. Each block is self-identifying and checksummed
@@ -30,12 +31,11 @@ mod test;
 
 use {
     cryptohash::sha256::{self, Digest, DIGESTBYTES},
-    globutils::Commas,
+    globutils::{persist::PersistFile, Commas},
     ruc::*,
     std::{
         cmp,
         collections::{HashMap, HashSet},
-        fs::File,
         io::{Read, Seek, SeekFrom, Write},
         mem, slice,
     },
@@ -502,7 +502,7 @@ const EMPTY_CHECKSUM: ChecksumData = [0_u8; CHECK_SIZE + DIGESTBYTES];
 ///   set_bits        the count of set bits in the block
 ///
 pub struct BitMap {
-    file: File,
+    file: Box<dyn PersistFile>,
     size: usize,
     checksum: Digest,
     first_invalid: usize,
@@ -602,9 +602,9 @@ fn encode(mut value: usize) -> [u8; INDEX_SIZE] {
 type StoredState = (usize, Vec<BitBlock>, Vec<i64>, Vec<bool>, Vec<u32>);
 
 impl BitMap {
-    /// Create a new bit map. The caller should pass a File
-    /// structure opened to an empty file.
-    pub fn create(mut data: File) -> Result<BitMap> {
+    /// Create a new bit map. The caller should pass a file
+    /// opened to an empty file.
+    pub fn create<F: PersistFile + 'static>(mut data: F) -> Result<BitMap> {
         let file_size = data.seek(SeekFrom::End(0)).c(d!())?;
 
         if file_size != 0 {
@@ -612,7 +612,7 @@ impl BitMap {
         }
 
         let result = BitMap {
-            file: data,
+            file: Box::new(data),
             size: 0,
             blocks: Vec::new(),
             checksum_data: Vec::new(),
@@ -629,12 +629,12 @@ impl BitMap {
 
     /// Open an existing bitmap. The caller is responsible
     /// for opening the file.
-    pub fn open(mut data: File) -> Result<BitMap> {
+    pub fn open<F: PersistFile + 'static>(mut data: F) -> Result<BitMap> {
         let (count, block_vector, state_vector, checksum_vector, set_vector) =
             BitMap::read_file(&mut data).c(d!())?;
 
         let mut result = BitMap {
-            file: data,
+            file: Box::new(data),
             size: count,
             blocks: block_vector,
             checksum_data: Vec::new(),
@@ -658,7 +658,7 @@ impl BitMap {
 
     // Read the contents of a file into memory, checking the
     // validity as we go.
-    fn read_file(file: &mut File) -> Result<StoredState> {
+    fn read_file<F: PersistFile>(file: &mut F) -> Result<StoredState> {
         let mut blocks = Vec::new();
         let mut dirty = Vec::new();
         let mut checksum_valid = Vec::new();
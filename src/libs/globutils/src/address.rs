@@ -0,0 +1,148 @@
+//!
+//! Parsing of the accepted representations of keys.
+//!
+//! Public keys are accepted as:
+//! - bech32 addresses with the prefix of the current network, e.g. `fra1...`
+//! - URL-safe base64 of the public key
+//! - hex of the public key, with or without `0x`
+//! - mnemonics, with the default derivation path
+//!
+//! Key pairs are accepted as base64 secret keys or mnemonics.
+//!
+//! All tools parse user input here, so that an address accepted by one of them
+//! is accepted by all of them.
+//!
+
+use {
+    crate::wallet,
+    ruc::*,
+    std::fmt,
+    zei::{
+        serialization::ZeiFromToBytes,
+        xfr::sig::{XfrKeyPair, XfrPublicKey},
+    },
+};
+
+/// The representations of a public key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyFormat {
+    #[allow(missing_docs)]
+    Bech32,
+    #[allow(missing_docs)]
+    Base64,
+    #[allow(missing_docs)]
+    Hex,
+    #[allow(missing_docs)]
+    Mnemonic,
+}
+
+impl KeyFormat {
+    /// Guess the format of `key`, the guess is not a validation.
+    pub fn detect(key: &str) -> Self {
+        let key = key.trim();
+        let hex = key.strip_prefix("0x").unwrap_or(key);
+        if key.split_whitespace().nth(1).is_some() {
+            KeyFormat::Mnemonic
+        } else if key
+            .to_lowercase()
+            .starts_with(&format!("{}1", wallet::address_hrp()))
+        {
+            KeyFormat::Bech32
+        } else if hex.len() == 2 * XFR_PUBLIC_KEY_LEN
+            && hex.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            KeyFormat::Hex
+        } else {
+            KeyFormat::Base64
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            KeyFormat::Bech32 => "bech32",
+            KeyFormat::Base64 => "base64",
+            KeyFormat::Hex => "hex",
+            KeyFormat::Mnemonic => "mnemonic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+const XFR_PUBLIC_KEY_LEN: usize = 32;
+
+/// Parse an address, in bech32, base64 or hex format.
+///
+/// Mnemonics are rejected, use `parse_public_key` where they are acceptable.
+pub fn parse_address(addr: &str) -> Result<XfrPublicKey> {
+    match KeyFormat::detect(addr) {
+        KeyFormat::Mnemonic => Err(eg!("a mnemonic is not an address")),
+        _ => parse_public_key(addr).c(d!()),
+    }
+}
+
+/// Parse a public key in any of the accepted formats.
+pub fn parse_public_key(key: &str) -> Result<XfrPublicKey> {
+    let key = key.trim();
+    let format = KeyFormat::detect(key);
+    match format {
+        KeyFormat::Bech32 => wallet::public_key_from_bech32(key),
+        KeyFormat::Hex => hex::decode(key.strip_prefix("0x").unwrap_or(key))
+            .c(d!())
+            .and_then(|bytes| XfrPublicKey::zei_from_bytes(&bytes).c(d!())),
+        KeyFormat::Mnemonic => {
+            wallet::restore_keypair_from_mnemonic_default(key).map(|kp| kp.get_pk())
+        }
+        // an address of another network is reported as such
+        KeyFormat::Base64 => wallet::public_key_from_base64(key)
+            .or_else(|_| wallet::public_key_from_bech32(key)),
+    }
+    .c(d!(format!("invalid {} key: {}", format, key)))
+}
+
+/// Parse a key pair, from a base64 secret key or a mnemonic.
+pub fn parse_keypair(key: &str) -> Result<XfrKeyPair> {
+    let key = key.trim();
+    match KeyFormat::detect(key) {
+        KeyFormat::Mnemonic => wallet::restore_keypair_from_mnemonic_default(key),
+        _ => wallet::restore_keypair_from_seckey_base64(key),
+    }
+    .c(d!("invalid secret key or mnemonic"))
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {super::*, rand_core::SeedableRng};
+
+    #[test]
+    fn t_parse_public_key() {
+        let kp = XfrKeyPair::generate(&mut rand_chacha::ChaChaRng::from_entropy());
+        let pk = kp.get_pk();
+        let bytes = pk.zei_to_bytes();
+
+        for (key, format) in [
+            (wallet::public_key_to_bech32(&pk), KeyFormat::Bech32),
+            (wallet::public_key_to_base64(&pk), KeyFormat::Base64),
+            (hex::encode(&bytes), KeyFormat::Hex),
+            (format!("0x{}", hex::encode(&bytes)), KeyFormat::Hex),
+        ] {
+            assert_eq!(format, KeyFormat::detect(&key));
+            assert_eq!(pk, pnk!(parse_address(&key)));
+            assert_eq!(pk, pnk!(parse_public_key(&format!(" {} ", key))));
+        }
+
+        let phrase = wallet::generate_mnemonic_default();
+        let kp = pnk!(wallet::restore_keypair_from_mnemonic_default(&phrase));
+        assert_eq!(KeyFormat::Mnemonic, KeyFormat::detect(&phrase));
+        assert_eq!(kp.get_pk(), pnk!(parse_public_key(&phrase)));
+        assert_eq!(kp.get_pk(), pnk!(parse_keypair(&phrase)).get_pk());
+        assert!(parse_address(&phrase).is_err());
+
+        assert!(parse_public_key("").is_err());
+        assert!(parse_public_key("0x1234").is_err());
+        let other = wallet::public_key_to_bech32_with_hrp(&pk, "tfra");
+        assert!(parse_public_key(&other).is_err());
+    }
+}
@@ -5,8 +5,13 @@
 #![deny(warnings)]
 #![deny(missing_docs)]
 
+pub mod address;
 pub mod logging;
+pub mod payment_request;
+pub mod persist;
+pub mod serde_pairs;
 pub mod wallet;
+pub mod wire;
 
 use {
     cryptohash::{
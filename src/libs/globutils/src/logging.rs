@@ -3,14 +3,23 @@
 //!
 
 use {
-    std::env, std::io, tracing_subscriber::filter::LevelFilter,
-    tracing_subscriber::EnvFilter,
+    lazy_static::lazy_static,
+    ruc::*,
+    std::{env, io, sync::Mutex},
+    tracing_subscriber::{filter::LevelFilter, EnvFilter},
 };
 
+const BASE_FILTER: &str = "actix_web=warn,actix_server=warn,actix_http=warn,rpc=warn";
+
+type FilterReloader = Box<dyn Fn(EnvFilter) -> Result<()> + Send>;
+
+lazy_static! {
+    static ref RELOADER: Mutex<Option<FilterReloader>> = Mutex::new(None);
+}
+
 #[allow(missing_docs)]
 pub fn init_logging(verbose: Option<&str>) {
-    let mut env_filter =
-        EnvFilter::new("actix_web=warn,actix_server=warn,actix_http=warn,rpc=warn");
+    let mut env_filter = EnvFilter::new(BASE_FILTER);
     if let Some(module) = verbose {
         if module.is_empty() {
             env_filter = env_filter.add_directive(LevelFilter::DEBUG.into());
@@ -36,9 +45,26 @@ pub fn init_logging(verbose: Option<&str>) {
         env_filter = env_filter.add_directive(LevelFilter::ERROR.into());
     }
 
-    tracing_subscriber::fmt::Subscriber::builder()
+    let builder = tracing_subscriber::fmt::Subscriber::builder()
         .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
         .with_env_filter(env_filter)
         .with_writer(io::stderr)
-        .init();
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    if let Ok(mut r) = RELOADER.lock() {
+        *r = Some(Box::new(move |f| handle.reload(f).c(d!())));
+    }
+    builder.init();
+}
+
+/// Replace the filter of the logs set by `init_logging`,
+/// `directives` are in the format of `RUST_LOG`.
+pub fn set_log_filter(directives: &str) -> Result<()> {
+    let filter =
+        EnvFilter::try_new(format!("{},{}", BASE_FILTER, directives)).c(d!())?;
+    let reloader = RELOADER.lock().map_err(|_| eg!("poisoned"))?;
+    match reloader.as_ref() {
+        Some(reload) => reload(filter).c(d!()),
+        None => Err(eg!("the logging is not initialized")),
+    }
 }
@@ -0,0 +1,201 @@
+//!
+//! # Payment requests
+//!
+//! A request to be paid, shared as a URI, eg. in a QR code:
+//!
+//! `findora:fra1...?asset=<code>&amount=100&memo=invoice%2042&expiry=1700000000`
+//!
+//! Only the address is required, the wallets fill the parameters of a transfer
+//! with the others, and refuse to pay an expired request.
+//!
+
+use {
+    crate::{address, wallet},
+    percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt,
+        str::FromStr,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    zei::xfr::sig::XfrPublicKey,
+};
+
+/// The scheme of the URIs of payment requests
+pub const PAYMENT_URI_SCHEME: &str = "findora";
+
+/// A request to be paid, see the module documentation.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PaymentRequest {
+    /// the receiver, in bech32
+    pub address: String,
+    /// the asset code in base64, FRA if not set
+    pub asset: Option<String>,
+    /// in the smallest units of the asset
+    pub amount: Option<u64>,
+    /// eg. an invoice number, to be sent along with the payment
+    pub memo: Option<String>,
+    /// the unix timestamp in seconds after which it must not be paid
+    pub expiry: Option<u64>,
+}
+
+impl PaymentRequest {
+    /// A request without any parameter, to be paid to `receiver`.
+    pub fn new(receiver: &XfrPublicKey) -> Self {
+        PaymentRequest {
+            address: wallet::public_key_to_bech32(receiver),
+            ..Default::default()
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn receiver(&self) -> Result<XfrPublicKey> {
+        address::parse_address(&self.address).c(d!())
+    }
+
+    /// Expired at the unix timestamp `now`, in seconds.
+    #[inline(always)]
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expiry.map(|e| e < now).unwrap_or(false)
+    }
+
+    /// An error if it has expired.
+    pub fn check_expiry(&self) -> Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .c(d!())?
+            .as_secs();
+        if self.is_expired_at(now) {
+            return Err(eg!(format!(
+                "the payment request has expired at {}",
+                self.expiry.unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+
+    #[allow(missing_docs)]
+    pub fn to_uri(&self) -> String {
+        let params = [
+            ("asset", self.asset.clone()),
+            ("amount", self.amount.map(|am| am.to_string())),
+            ("memo", self.memo.clone()),
+            ("expiry", self.expiry.map(|e| e.to_string())),
+        ]
+        .iter()
+        .filter_map(|(k, v)| {
+            v.as_ref()
+                .map(|v| format!("{}={}", k, utf8_percent_encode(v, NON_ALPHANUMERIC)))
+        })
+        .collect::<Vec<_>>();
+
+        let mut uri = format!("{}:{}", PAYMENT_URI_SCHEME, &self.address);
+        if !params.is_empty() {
+            uri.push('?');
+            uri.push_str(&params.join("&"));
+        }
+        uri
+    }
+
+    /// Parse a URI of a payment request, the unknown parameters are ignored.
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let uri = uri.trim();
+        let rest = uri
+            .split_once(':')
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(PAYMENT_URI_SCHEME))
+            .map(|(_, rest)| rest)
+            .c(d!(format!("not a `{}:` URI", PAYMENT_URI_SCHEME)))?;
+        let (addr, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let receiver = address::parse_address(addr).c(d!("invalid address"))?;
+        let mut req = PaymentRequest::new(&receiver);
+
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (k, v) = param.split_once('=').unwrap_or((param, ""));
+            let v = percent_decode_str(v)
+                .decode_utf8()
+                .c(d!(format!("invalid value of `{}`", k)))?
+                .into_owned();
+            let dup = match k {
+                "asset" => req.asset.replace(v).is_some(),
+                "amount" => req
+                    .amount
+                    .replace(v.parse::<u64>().c(d!("invalid amount"))?)
+                    .is_some(),
+                "memo" => req.memo.replace(v).is_some(),
+                "expiry" => req
+                    .expiry
+                    .replace(v.parse::<u64>().c(d!("invalid expiry"))?)
+                    .is_some(),
+                _ => false,
+            };
+            if dup {
+                return Err(eg!(format!("duplicate parameter `{}`", k)));
+            }
+        }
+
+        Ok(req)
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_uri())
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = Box<dyn RucError>;
+
+    fn from_str(uri: &str) -> Result<Self> {
+        PaymentRequest::from_uri(uri)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, rand_core::SeedableRng, zei::xfr::sig::XfrKeyPair};
+
+    #[test]
+    fn t_payment_request_uri() {
+        let kp = XfrKeyPair::generate(&mut rand_chacha::ChaChaRng::from_entropy());
+
+        let req = PaymentRequest::new(kp.get_pk_ref());
+        assert_eq!(
+            format!("findora:{}", wallet::public_key_to_bech32(kp.get_pk_ref())),
+            req.to_uri()
+        );
+        assert_eq!(req, pnk!(PaymentRequest::from_uri(&req.to_uri())));
+
+        let req = PaymentRequest {
+            asset: Some("AAAAAAAAAAAAAAAAAAAAAA==".to_owned()),
+            amount: Some(100),
+            memo: Some("invoice #42 & co=ok".to_owned()),
+            expiry: Some(1_700_000_000),
+            ..req
+        };
+        let uri = req.to_uri();
+        assert!(!uri.contains(' ') && !uri.contains('#'));
+        assert_eq!(req, pnk!(uri.parse::<PaymentRequest>()));
+        assert_eq!(kp.get_pk(), pnk!(req.receiver()));
+
+        assert!(!req.is_expired_at(1_700_000_000));
+        assert!(req.is_expired_at(1_700_000_001));
+        assert!(req.check_expiry().is_err());
+
+        // the unknown parameters are ignored
+        let with_unknown = format!("{}&label=shop", uri);
+        assert_eq!(req, pnk!(PaymentRequest::from_uri(&with_unknown)));
+
+        let addr = &req.address;
+        for bad in [
+            format!("bitcoin:{}", addr),
+            format!("findora:{}?amount=-1", addr),
+            format!("findora:{}?amount=1&amount=2", addr),
+            "findora:fra1invalid".to_owned(),
+        ] {
+            assert!(PaymentRequest::from_uri(&bad).is_err(), "{}", bad);
+        }
+    }
+}
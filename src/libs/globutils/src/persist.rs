@@ -0,0 +1,371 @@
+//!
+//! # Pluggable persistence
+//!
+//! All file-system accesses of the ledger store(merkle trees, bitmaps, snapshots)
+//! go through the `Persistence` backend set by `set_backend`:
+//!
+//! - `FsPersistence`, the default one on native targets
+//! - `MemPersistence`, the default one on wasm32,
+//!   its contents can be dumped by `export` and restored by `import`,
+//!   so a browser can save them to IndexedDB(or any other storage) asynchronously
+//!
+
+use {
+    lazy_static::lazy_static,
+    std::{
+        collections::BTreeMap,
+        fmt,
+        io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+        sync::{Arc, Mutex, RwLock},
+    },
+};
+
+lazy_static! {
+    static ref BACKEND: RwLock<Arc<dyn Persistence>> = RwLock::new(default_backend());
+}
+
+/// Replace the global backend,
+/// should be called before any ledger component is opened.
+pub fn set_backend(backend: Arc<dyn Persistence>) {
+    *BACKEND.write().unwrap() = backend;
+}
+
+/// The global backend.
+pub fn backend() -> Arc<dyn Persistence> {
+    Arc::clone(&BACKEND.read().unwrap())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_backend() -> Arc<dyn Persistence> {
+    Arc::new(FsPersistence)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_backend() -> Arc<dyn Persistence> {
+    Arc::new(MemPersistence::default())
+}
+
+/// How to open a file, all files are opened readable and writable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Fail if the file does not exist
+    Existing,
+    /// Create the file if it does not exist
+    Create,
+    /// Fail if the file exists
+    CreateNew,
+    /// Create the file if it does not exist, truncate it otherwise
+    Truncate,
+}
+
+/// A file opened by a `Persistence` backend.
+pub trait PersistFile: Read + Write + Seek + Send + Sync + fmt::Debug {
+    /// Truncate or extend the file to `size` bytes.
+    fn set_len(&self, size: u64) -> io::Result<()>;
+    /// Make sure all the contents have reached the underlying storage.
+    fn sync_all(&self) -> io::Result<()>;
+}
+
+impl PersistFile for Box<dyn PersistFile> {
+    fn set_len(&self, size: u64) -> io::Result<()> {
+        (**self).set_len(size)
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        (**self).sync_all()
+    }
+}
+
+/// The storage of the ledger store.
+pub trait Persistence: Send + Sync {
+    /// Open a file, see `OpenMode`.
+    fn open(&self, path: &str, mode: OpenMode) -> io::Result<Box<dyn PersistFile>>;
+    /// Read the whole contents of a file.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+    /// Create or overwrite a file with `contents`.
+    fn write(&self, path: &str, contents: &[u8]) -> io::Result<()>;
+    /// Remove a file.
+    fn remove(&self, path: &str) -> io::Result<()>;
+    /// Rename a file, replace `to` if it exists.
+    fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+    /// Whether a file exists.
+    fn exists(&self, path: &str) -> bool;
+    /// Create a directory and all of its parents.
+    fn create_dir_all(&self, path: &str) -> io::Result<()>;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use fs_impl::FsPersistence;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod fs_impl {
+    use {
+        super::{OpenMode, PersistFile, Persistence},
+        std::{
+            fs::{self, File, OpenOptions},
+            io,
+            path::Path,
+        },
+    };
+
+    impl PersistFile for File {
+        fn set_len(&self, size: u64) -> io::Result<()> {
+            File::set_len(self, size)
+        }
+
+        fn sync_all(&self) -> io::Result<()> {
+            File::sync_all(self)
+        }
+    }
+
+    /// The local file system.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct FsPersistence;
+
+    impl Persistence for FsPersistence {
+        fn open(&self, path: &str, mode: OpenMode) -> io::Result<Box<dyn PersistFile>> {
+            let mut opts = OpenOptions::new();
+            opts.read(true).write(true);
+            match mode {
+                OpenMode::Existing => &mut opts,
+                OpenMode::Create => opts.create(true),
+                OpenMode::CreateNew => opts.create_new(true),
+                OpenMode::Truncate => opts.create(true).truncate(true),
+            };
+            opts.open(path).map(|f| Box::new(f) as Box<dyn PersistFile>)
+        }
+
+        fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+            fs::read(path)
+        }
+
+        fn write(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+            fs::write(path, contents)
+        }
+
+        fn remove(&self, path: &str) -> io::Result<()> {
+            fs::remove_file(path)
+        }
+
+        fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+            fs::rename(from, to)
+        }
+
+        fn exists(&self, path: &str) -> bool {
+            Path::new(path).exists()
+        }
+
+        fn create_dir_all(&self, path: &str) -> io::Result<()> {
+            fs::create_dir_all(path)
+        }
+    }
+}
+
+type MemData = Arc<Mutex<Vec<u8>>>;
+
+/// An in-memory storage, directories are implicit.
+#[derive(Default)]
+pub struct MemPersistence {
+    files: RwLock<BTreeMap<String, MemData>>,
+}
+
+impl MemPersistence {
+    /// Dump all files.
+    pub fn export(&self) -> BTreeMap<String, Vec<u8>> {
+        self.files
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.lock().unwrap().clone()))
+            .collect()
+    }
+
+    /// Replace all files with a dump of `export`.
+    pub fn import(&self, files: BTreeMap<String, Vec<u8>>) {
+        *self.files.write().unwrap() = files
+            .into_iter()
+            .map(|(k, v)| (k, Arc::new(Mutex::new(v))))
+            .collect();
+    }
+
+    /// Remove all files.
+    pub fn clear(&self) {
+        self.files.write().unwrap().clear();
+    }
+}
+
+impl Persistence for MemPersistence {
+    fn open(&self, path: &str, mode: OpenMode) -> io::Result<Box<dyn PersistFile>> {
+        let mut files = self.files.write().unwrap();
+        let data = match (mode, files.get(path)) {
+            (OpenMode::Existing, None) => return Err(not_found(path)),
+            (OpenMode::CreateNew, Some(_)) => {
+                return Err(io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} already exists", path),
+                ));
+            }
+            (OpenMode::Truncate, Some(data)) => {
+                data.lock().unwrap().clear();
+                Arc::clone(data)
+            }
+            (_, Some(data)) => Arc::clone(data),
+            (_, None) => {
+                let data = MemData::default();
+                files.insert(path.to_owned(), Arc::clone(&data));
+                data
+            }
+        };
+
+        Ok(Box::new(MemFile { data, pos: 0 }))
+    }
+
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.files
+            .read()
+            .unwrap()
+            .get(path)
+            .map(|data| data.lock().unwrap().clone())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+        self.files
+            .write()
+            .unwrap()
+            .insert(path.to_owned(), Arc::new(Mutex::new(contents.to_vec())));
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> io::Result<()> {
+        self.files
+            .write()
+            .unwrap()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut files = self.files.write().unwrap();
+        let data = files.remove(from).ok_or_else(|| not_found(from))?;
+        files.insert(to.to_owned(), data);
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.read().unwrap().contains_key(path)
+    }
+
+    fn create_dir_all(&self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// A file of `MemPersistence`, handles of the same file share the contents,
+// but each one has its own cursor.
+#[derive(Debug)]
+struct MemFile {
+    data: MemData,
+    pos: u64,
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.data.lock().unwrap();
+        let start = (self.pos as usize).min(data.len());
+        let n = buf.len().min(data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let start = self.pos as usize;
+        let end = start + buf.len();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+        self.pos = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = self.data.lock().unwrap().len() as i64;
+        let pos = match pos {
+            SeekFrom::Start(n) => n as i64,
+            SeekFrom::End(n) => len + n,
+            SeekFrom::Current(n) => self.pos as i64 + n,
+        };
+        if pos < 0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "seek to a negative position",
+            ));
+        }
+        self.pos = pos as u64;
+        Ok(self.pos)
+    }
+}
+
+impl PersistFile for MemFile {
+    fn set_len(&self, size: u64) -> io::Result<()> {
+        self.data.lock().unwrap().resize(size as usize, 0);
+        Ok(())
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(ErrorKind::NotFound, format!("{} not found", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mem_persistence() {
+        let mem = MemPersistence::default();
+        assert!(mem.open("a", OpenMode::Existing).is_err());
+
+        let mut f = mem.open("a", OpenMode::CreateNew).unwrap();
+        f.write_all(b"hello").unwrap();
+        f.seek(SeekFrom::Start(1)).unwrap();
+        f.write_all(b"a").unwrap();
+        assert!(mem.open("a", OpenMode::CreateNew).is_err());
+
+        let mut g = mem.open("a", OpenMode::Existing).unwrap();
+        let mut buf = vec![];
+        g.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"hallo");
+        assert_eq!(f.seek(SeekFrom::End(0)).unwrap(), 5);
+
+        f.set_len(2).unwrap();
+        assert_eq!(mem.read("a").unwrap(), b"ha");
+
+        mem.rename("a", "b").unwrap();
+        assert!(!mem.exists("a"));
+        let dump = mem.export();
+        mem.clear();
+        assert!(!mem.exists("b"));
+        mem.import(dump);
+        assert_eq!(mem.read("b").unwrap(), b"ha");
+
+        mem.open("b", OpenMode::Truncate).unwrap();
+        assert!(mem.read("b").unwrap().is_empty());
+    }
+}
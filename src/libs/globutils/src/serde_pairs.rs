@@ -0,0 +1,116 @@
+//!
+//! # Maps with non-string keys in JSON
+//!
+//! JSON objects only take strings as keys, so a map keyed by e.g. `AssetTypeCode`
+//! can not be serialized by serde_json as is. With
+//! `#[serde(with = "globutils::serde_pairs")]`, the map is serialized
+//! as a sequence of `[key, value]` pairs instead.
+//!
+//! Maps in the form of JSON objects are still accepted, for the data saved before.
+//!
+
+use {
+    serde::{
+        de::{MapAccess, SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    },
+    std::{
+        collections::BTreeMap, fmt, marker::PhantomData, result::Result as StdResult,
+    },
+};
+
+#[allow(missing_docs)]
+pub fn serialize<K, V, S>(
+    map: &BTreeMap<K, V>,
+    serializer: S,
+) -> StdResult<S::Ok, S::Error>
+where
+    K: Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+#[allow(missing_docs)]
+pub fn deserialize<'de, K, V, D>(deserializer: D) -> StdResult<BTreeMap<K, V>, D::Error>
+where
+    K: Deserialize<'de> + Ord,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(PairsVisitor(PhantomData))
+}
+
+struct PairsVisitor<K, V>(PhantomData<(K, V)>);
+
+impl<'de, K, V> Visitor<'de> for PairsVisitor<K, V>
+where
+    K: Deserialize<'de> + Ord,
+    V: Deserialize<'de>,
+{
+    type Value = BTreeMap<K, V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of key-value pairs")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> StdResult<Self::Value, A::Error> {
+        let mut map = BTreeMap::new();
+        while let Some((k, v)) = seq.next_element::<(K, V)>()? {
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut access: A,
+    ) -> StdResult<Self::Value, A::Error> {
+        let mut map = BTreeMap::new();
+        while let Some((k, v)) = access.next_entry::<K, V>()? {
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+#[allow(missing_docs)]
+mod test {
+    use {super::*, serde::Deserialize};
+
+    #[derive(
+        Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize,
+    )]
+    struct Code {
+        val: [u8; 2],
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct State {
+        #[serde(with = "super")]
+        locked: BTreeMap<Code, u64>,
+    }
+
+    #[test]
+    fn t_serde_pairs() {
+        let state = State {
+            locked: [(Code { val: [1, 2] }, 3), (Code { val: [0, 1] }, 5)]
+                .into_iter()
+                .collect(),
+        };
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(
+            r#"{"locked":[[{"val":[0,1]},5],[{"val":[1,2]},3]]}"#,
+            json.as_str()
+        );
+        assert_eq!(state, serde_json::from_str::<State>(&json).unwrap());
+
+        let empty = serde_json::from_str::<State>(r#"{"locked":{}}"#).unwrap();
+        assert!(empty.locked.is_empty());
+    }
+}
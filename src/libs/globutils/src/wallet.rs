@@ -8,7 +8,9 @@ use {
     bech32::{self, FromBase32, ToBase32},
     bip0039::{Count, Language, Mnemonic},
     ed25519_dalek_bip32::{DerivationPath, ExtendedSecretKey},
+    lazy_static::lazy_static,
     ruc::*,
+    std::env,
     zei::{
         serialization::ZeiFromToBytes,
         xfr::sig::{XfrKeyPair, XfrPublicKey, XfrSecretKey},
@@ -170,6 +172,25 @@ fn check_lang(lang: &str) -> Result<Language> {
 
 /////////////////////////////////////////////////////////////////
 
+/// The human-readable prefix of bech32 addresses on the mainnet.
+pub const DEFAULT_ADDRESS_HRP: &str = "fra";
+
+lazy_static! {
+    // Other networks set their own prefix, e.g. `FRA_ADDRESS_HRP=tfra`,
+    // so that the addresses of a network can not be mistaken for another one's.
+    static ref ADDRESS_HRP: String = env::var("FRA_ADDRESS_HRP")
+        .ok()
+        .map(|hrp| hrp.to_lowercase())
+        .filter(|hrp| !hrp.is_empty())
+        .unwrap_or_else(|| DEFAULT_ADDRESS_HRP.to_owned());
+}
+
+/// The human-readable prefix of bech32 addresses on the current network.
+#[inline(always)]
+pub fn address_hrp() -> &'static str {
+    ADDRESS_HRP.as_str()
+}
+
 /// Convert a XfrPublicKey to base64 human-readable address
 #[inline(always)]
 pub fn public_key_to_base64(key: &XfrPublicKey) -> String {
@@ -184,30 +205,49 @@ pub fn public_key_from_base64(pk: &str) -> Result<XfrPublicKey> {
         .and_then(|bytes| XfrPublicKey::zei_from_bytes(&bytes).c(d!()))
 }
 
-/// Convert a XfrPublicKey to bech32 human-readable address
+/// Convert a XfrPublicKey to bech32 human-readable address,
+/// with the prefix of the current network
 #[inline(always)]
 pub fn public_key_to_bech32(key: &XfrPublicKey) -> String {
-    bech32enc(&XfrPublicKey::zei_to_bytes(key))
+    public_key_to_bech32_with_hrp(key, address_hrp())
 }
 
-/// Restore a XfrPublicKey to bech32 human-readable address
+/// Restore a XfrPublicKey from bech32 human-readable address,
+/// the checksum and the prefix of the current network are verified
 #[inline(always)]
 pub fn public_key_from_bech32(addr: &str) -> Result<XfrPublicKey> {
-    bech32dec(addr)
+    public_key_from_bech32_with_hrp(addr, address_hrp()).c(d!())
+}
+
+/// Convert a XfrPublicKey to bech32 human-readable address with a custom prefix
+#[inline(always)]
+pub fn public_key_to_bech32_with_hrp(key: &XfrPublicKey, hrp: &str) -> String {
+    bech32enc(hrp, &XfrPublicKey::zei_to_bytes(key))
+}
+
+/// Restore a XfrPublicKey from bech32 human-readable address with a custom prefix
+#[inline(always)]
+pub fn public_key_from_bech32_with_hrp(addr: &str, hrp: &str) -> Result<XfrPublicKey> {
+    bech32dec(hrp, addr)
         .c(d!())
         .and_then(|bytes| XfrPublicKey::zei_from_bytes(&bytes).c(d!()))
 }
 
 #[inline(always)]
-fn bech32enc<T: AsRef<[u8]> + ToBase32>(input: &T) -> String {
-    bech32::encode("fra", input.to_base32()).unwrap()
+fn bech32enc<T: AsRef<[u8]> + ToBase32>(hrp: &str, input: &T) -> String {
+    bech32::encode(hrp, input.to_base32()).unwrap()
 }
 
 #[inline(always)]
-fn bech32dec(input: &str) -> Result<Vec<u8>> {
-    bech32::decode(input)
-        .c(d!())
-        .and_then(|(_, data)| Vec::<u8>::from_base32(&data).c(d!()))
+fn bech32dec(hrp: &str, input: &str) -> Result<Vec<u8>> {
+    let (prefix, data) = bech32::decode(input).c(d!("invalid bech32 checksum"))?;
+    if prefix != hrp {
+        return Err(eg!(format!(
+            "the address of another network: expected prefix '{}', found '{}'",
+            hrp, prefix
+        )));
+    }
+    Vec::<u8>::from_base32(&data).c(d!())
 }
 
 /////////////////////////////////////////////////////////////////
@@ -260,4 +300,23 @@ mod test {
         assert_eq!(pk, pnk!(public_key_from_base64(&public_key_to_base64(&pk))));
         assert_eq!(pk, pnk!(public_key_from_bech32(&public_key_to_bech32(&pk))));
     }
+
+    #[test]
+    fn t_bech32_checks() {
+        let pk = new_keypair().get_pk();
+        let addr = public_key_to_bech32(&pk);
+        assert!(addr.starts_with("fra1"));
+
+        // a corrupted character is caught by the checksum
+        let mut corrupted = addr.clone().into_bytes();
+        let last = corrupted.len() - 1;
+        corrupted[last] = if corrupted[last] == b'q' { b'p' } else { b'q' };
+        let corrupted = String::from_utf8(corrupted).unwrap();
+        assert!(public_key_from_bech32(&corrupted).is_err());
+
+        // addresses of other networks are rejected
+        let other = public_key_to_bech32_with_hrp(&pk, "tfra");
+        assert!(public_key_from_bech32(&other).is_err());
+        assert_eq!(pk, pnk!(public_key_from_bech32_with_hrp(&other, "tfra")));
+    }
 }
@@ -0,0 +1,192 @@
+//!
+//! # Binary encoding on the wire
+//!
+//! The http APIs speak JSON by default, the transactions and the blocks can also
+//! be exchanged in CBOR (RFC 8949), smaller and faster to parse, negotiated by
+//! the `Content-Type` of the requests and the `Accept` header of the clients.
+//!
+//! CBOR is self-describing, so the fields skipped when empty and the maps of
+//! `serde_pairs` decode as they do in JSON, unlike bincode. The keys, the
+//! commitments and the proofs of zei are in their raw bytes, not in base64.
+//! The hashes and the signatures are over JSON whatever the encoding.
+//!
+
+use {
+    ruc::*,
+    serde::{de::DeserializeOwned, Serialize},
+};
+
+/// The media type of the binary encoding
+pub const BINARY_MIME: &str = "application/cbor";
+
+/// The media type of JSON
+pub const JSON_MIME: &str = "application/json";
+
+/// The encodings of the bodies
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    #[allow(missing_docs)]
+    Json,
+    #[allow(missing_docs)]
+    Binary,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Json
+    }
+}
+
+impl Encoding {
+    /// The encoding of a body of `Content-Type` `ct`, JSON if it is missing.
+    pub fn from_content_type(ct: Option<&str>) -> Result<Self> {
+        match ct.map(media_type) {
+            None | Some("") => Ok(Encoding::Json),
+            Some(mt) if mt.eq_ignore_ascii_case(JSON_MIME) => Ok(Encoding::Json),
+            Some(mt) if mt.eq_ignore_ascii_case(BINARY_MIME) => Ok(Encoding::Binary),
+            Some(mt) => Err(eg!(format!("unsupported content type: {}", mt))),
+        }
+    }
+
+    /// The encoding preferred by an `Accept` header, JSON unless CBOR is
+    /// accepted with a higher quality, or without JSON nor wildcards.
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        let accept = match accept {
+            Some(a) => a,
+            None => return Encoding::Json,
+        };
+
+        let (mut json_q, mut binary_q) = (None, None);
+        for item in accept.split(',') {
+            let mt = media_type(item);
+            let q = item
+                .split(';')
+                .skip(1)
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if mt.eq_ignore_ascii_case(BINARY_MIME) {
+                binary_q = Some(q);
+            } else if mt.eq_ignore_ascii_case(JSON_MIME)
+                || "*/*" == mt
+                || mt.eq_ignore_ascii_case("application/*")
+            {
+                json_q = Some(json_q.map_or(q, |j: f32| j.max(q)));
+            }
+        }
+
+        match binary_q {
+            Some(b) if 0.0 < b && json_q.map_or(true, |j| b > j) => Encoding::Binary,
+            _ => Encoding::Json,
+        }
+    }
+
+    /// The `Content-Type` of the bodies in this encoding
+    pub fn mime(self) -> &'static str {
+        match self {
+            Encoding::Json => JSON_MIME,
+            Encoding::Binary => BINARY_MIME,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Json => serde_json::to_vec(value).c(d!()),
+            Encoding::Binary => serde_cbor::to_vec(value).c(d!()),
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T> {
+        match self {
+            Encoding::Json => serde_json::from_slice(bytes).c(d!()),
+            Encoding::Binary => serde_cbor::from_slice(bytes).c(d!()),
+        }
+    }
+}
+
+// `application/json; charset=utf-8` => `application/json`
+#[inline(always)]
+fn media_type(v: &str) -> &str {
+    v.split(';').next().unwrap_or_default().trim()
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::{HashOf, SignatureOf},
+        rand_core::SeedableRng,
+        serde::Deserialize,
+        std::collections::BTreeMap,
+        zei::xfr::sig::{XfrKeyPair, XfrPublicKey},
+    };
+
+    #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+    struct Signed {
+        signer: XfrPublicKey,
+        signature: SignatureOf<String>,
+        hash: HashOf<String>,
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+        #[serde(with = "crate::serde_pairs")]
+        amounts: BTreeMap<u64, u64>,
+    }
+
+    #[test]
+    fn t_wire_encoding() {
+        let kp = XfrKeyPair::generate(&mut rand_chacha::ChaChaRng::from_entropy());
+        let msg = "findora".to_owned();
+        let signed = Signed {
+            signer: kp.get_pk(),
+            signature: SignatureOf::new(&kp, &msg),
+            hash: HashOf::new(&msg),
+            memo: None,
+            amounts: vec![(1, 10), (2, 20)].into_iter().collect(),
+        };
+
+        let json = pnk!(Encoding::Json.encode(&signed));
+        let binary = pnk!(Encoding::Binary.encode(&signed));
+        assert!(binary.len() < json.len());
+        assert_eq!(signed, pnk!(Encoding::Json.decode::<Signed>(&json)));
+        assert_eq!(signed, pnk!(Encoding::Binary.decode::<Signed>(&binary)));
+        assert!(Encoding::Binary.decode::<Signed>(&json).is_err());
+    }
+
+    #[test]
+    fn t_wire_negotiation() {
+        assert_eq!(
+            Encoding::Json,
+            pnk!(Encoding::from_content_type(Some(
+                "application/json; charset=utf-8"
+            )))
+        );
+        assert_eq!(Encoding::Json, pnk!(Encoding::from_content_type(None)));
+        assert_eq!(
+            Encoding::Binary,
+            pnk!(Encoding::from_content_type(Some("application/cbor")))
+        );
+        assert!(Encoding::from_content_type(Some("text/plain")).is_err());
+
+        assert_eq!(Encoding::Json, Encoding::from_accept(None));
+        assert_eq!(Encoding::Json, Encoding::from_accept(Some("*/*")));
+        assert_eq!(
+            Encoding::Binary,
+            Encoding::from_accept(Some("application/cbor"))
+        );
+        assert_eq!(
+            Encoding::Binary,
+            Encoding::from_accept(Some("application/json;q=0.5, application/cbor"))
+        );
+        assert_eq!(
+            Encoding::Json,
+            Encoding::from_accept(Some("application/json, application/cbor;q=0.9"))
+        );
+        assert_eq!(
+            Encoding::Json,
+            Encoding::from_accept(Some("application/cbor;q=0"))
+        );
+    }
+}
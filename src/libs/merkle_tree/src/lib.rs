@@ -16,16 +16,17 @@
 use {
     chrono::Utc,
     cryptohash::{hash_pair, hash_partial, sha256, HashValue, Proof, HASH_SIZE},
-    globutils::Commas,
+    globutils::{
+        persist::{self, OpenMode, PersistFile},
+        Commas,
+    },
     ruc::*,
     serde::{Deserialize, Deserializer, Serialize, Serializer},
     std::{
         collections::HashMap,
         fmt,
-        fs::{self, File, OpenOptions},
         io::{ErrorKind, Read, Seek, SeekFrom, Write},
         mem::{self, MaybeUninit},
-        path,
         ptr::copy_nonoverlapping,
         result::Result as StdResult,
         slice,
@@ -572,7 +573,7 @@ pub struct AppendOnlyMerkle {
     blocks: Vec<Vec<Block>>,
 
     #[serde(skip_serializing, skip_deserializing)]
-    files: Vec<File>,
+    files: Vec<Box<dyn PersistFile>>,
 
     #[serde(skip_serializing, skip_deserializing)]
     blocks_on_disk: Vec<u64>, // the number of entries on stable store
@@ -592,7 +593,7 @@ impl AppendOnlyMerkle {
     // This constructor is private. Use open or create to get a
     // Merkle tree.
     #[inline(always)]
-    fn new(path: &str, file: File) -> AppendOnlyMerkle {
+    fn new(path: &str, file: Box<dyn PersistFile>) -> AppendOnlyMerkle {
         AppendOnlyMerkle {
             entry_count: 0,
             entries_on_disk: 0,
@@ -611,7 +612,7 @@ impl AppendOnlyMerkle {
     /// * `path` - a string specifying the path to the base file
     #[inline(always)]
     pub fn open(path: &str) -> Result<AppendOnlyMerkle> {
-        let check_path = OpenOptions::new().read(true).write(true).open(path);
+        let check_path = persist::backend().open(path, OpenMode::Existing);
 
         match check_path {
             Ok(file) => {
@@ -628,11 +629,7 @@ impl AppendOnlyMerkle {
     /// Create a new Merkle tree at the given path. This routine returns
     /// an error if the tree exists.
     pub fn create(path: &str) -> Result<AppendOnlyMerkle> {
-        let check_path = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create_new(true)
-            .open(path);
+        let check_path = persist::backend().open(path, OpenMode::CreateNew);
 
         match check_path {
             Ok(file) => {
@@ -641,7 +638,7 @@ impl AppendOnlyMerkle {
                 // Remove any files left over from another tree with the same name.
                 for i in 1..MAX_BLOCK_LEVELS {
                     let path = result.file_path(i);
-                    let _ = fs::remove_file(&path);
+                    let _ = persist::backend().remove(&path);
                 }
 
                 Ok(result)
@@ -667,25 +664,19 @@ impl AppendOnlyMerkle {
     /// returned, and this tree is guaranteed to be fully synchronized
     /// to disk.
     pub fn rebuild(path: &str) -> Result<AppendOnlyMerkle> {
-        let input = OpenOptions::new().read(true).open(&path).c(d!())?;
+        let input = persist::backend().open(path, OpenMode::Existing).c(d!())?;
         let ext = AppendOnlyMerkle::rebuild_ext();
         let save = path.to_owned() + &ext;
 
-        if path::Path::new(&save).exists() {
+        if persist::backend().exists(&save) {
             return Err(eg!(format!("Rebuild path {} already exists.", save)));
         }
 
         // Rename the level zero file out of the way and then create
         // a new one.
-        fs::rename(&path, &save).c(d!())?;
+        persist::backend().rename(path, &save).c(d!())?;
 
-        let output = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&path)
-            .c(d!())?;
+        let output = persist::backend().open(path, OpenMode::Truncate).c(d!())?;
 
         let mut tree = AppendOnlyMerkle::new(&path, output);
         tree.rebuild_internal(input).c(d!())?;
@@ -704,7 +695,7 @@ impl AppendOnlyMerkle {
 
     // The rebuild method creates a skeleton tree that is empty. Now do
     // the work of recreating all the blocks for all the files.
-    fn rebuild_internal(&mut self, mut input: File) -> Result<()> {
+    fn rebuild_internal(&mut self, mut input: Box<dyn PersistFile>) -> Result<()> {
         // Compute the number of complete blocks there.
         let file_size = input.seek(SeekFrom::End(0)).c(d!())?;
         let block_count = file_size / BLOCK_SIZE as u64;
@@ -733,7 +724,7 @@ impl AppendOnlyMerkle {
         }
 
         if entries == 0 {
-            let _ = fs::remove_file(self.file_path(0));
+            let _ = persist::backend().remove(&self.file_path(0));
 
             // Level 0 file contails no valid leaves, then release level 0 initial resources:
             // self.files / self.blocks / self.blocks_on_disk
@@ -758,15 +749,9 @@ impl AppendOnlyMerkle {
         while leaves_at_this_level > 0 {
             let path = self.file_path(level);
             let ext = self.rebuild_extension();
-            let _ = fs::rename(&path, path.to_owned() + &ext);
+            let _ = persist::backend().rename(&path, &(path.to_owned() + &ext));
 
-            let file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(&path)
-                .c(d!())?;
+            let file = persist::backend().open(&path, OpenMode::Truncate).c(d!())?;
 
             self.push_file(file);
             let block_count = covered(leaves_at_this_level, LEAVES_IN_BLOCK as u64);
@@ -779,17 +764,13 @@ impl AppendOnlyMerkle {
         for i in level..MAX_BLOCK_LEVELS {
             let path = self.file_path(i);
             let ext = self.rebuild_extension();
-            let _ = fs::rename(&path, path.to_owned() + &ext);
+            let _ = persist::backend().rename(&path, &(path.to_owned() + &ext));
         }
 
         // Okay, we have recovered all the upper level files. Point the
         // level zero file at the correct path.
-        self.files[0] = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&self.path)
+        self.files[0] = persist::backend()
+            .open(&self.path, OpenMode::Truncate)
             .c(d!())?;
 
         self.check().c(d!())?;
@@ -843,11 +824,8 @@ impl AppendOnlyMerkle {
         self.files = Vec::new();
 
         for i in 0..self.blocks.len() {
-            let file_check = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create_new(true)
-                .open(&self.file_path(i));
+            let file_check =
+                persist::backend().open(&self.file_path(i), OpenMode::CreateNew);
 
             match file_check {
                 Ok(file) => {
@@ -873,7 +851,7 @@ impl AppendOnlyMerkle {
 
         loop {
             let path = self.file_path(i);
-            let result = OpenOptions::new().read(true).write(true).open(&path);
+            let result = persist::backend().open(&path, OpenMode::Existing);
 
             match result {
                 Err(x) => {
@@ -918,7 +896,7 @@ impl AppendOnlyMerkle {
     // Add a level to the tree's data structures. This function is called
     // to prepare for reading a file during open or when adding a new level.
     #[inline(always)]
-    fn push_file(&mut self, file: File) {
+    fn push_file(&mut self, file: Box<dyn PersistFile>) {
         self.files.push(file);
         self.blocks_on_disk.push(0);
         self.blocks.push(Vec::new());
@@ -944,7 +922,7 @@ impl AppendOnlyMerkle {
         // file, but there might be others there at higher levels.
         for i in self.files.len()..MAX_BLOCK_LEVELS {
             let path = self.file_path(i);
-            let _ = fs::remove_file(&path);
+            let _ = persist::backend().remove(&path);
         }
 
         Ok(())
@@ -1067,14 +1045,9 @@ impl AppendOnlyMerkle {
 
         let path = self.file_path(level);
 
-        let _ = fs::remove_file(&path);
+        let _ = persist::backend().remove(&path);
 
-        let result = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&path);
+        let result = persist::backend().open(&path, OpenMode::Truncate);
 
         let file = match result {
             Err(x) => {
@@ -1301,12 +1274,7 @@ impl AppendOnlyMerkle {
         let level = self.blocks.len();
         let path = self.file_path(level);
 
-        let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(path)
-            .c(d!())?;
+        let file = persist::backend().open(&path, OpenMode::Create).c(d!())?;
 
         self.push_file(file);
 
@@ -2055,14 +2023,9 @@ impl AppendOnlyMerkle {
             self.blocks_on_disk[i] = 0;
 
             let path = self.file_path(i);
-            let _ = fs::remove_file(&path);
+            let _ = persist::backend().remove(&path);
 
-            self.files[i] = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .open(path)
-                .c(d!())?;
+            self.files[i] = persist::backend().open(&path, OpenMode::Create).c(d!())?;
         }
 
         Ok(())
@@ -2083,6 +2046,7 @@ mod tests {
         byteorder::{LittleEndian, WriteBytesExt},
         cryptohash::sha256,
         rand::{prelude::thread_rng, Rng},
+        std::fs::{self, OpenOptions},
     };
 
     #[test]